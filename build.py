@@ -412,6 +412,7 @@ def build_flutter_dmg(version, features):
     os.chdir('flutter')
     system2('flutter build macos --release')
     system2('cp -rf ../target/release/service ./build/macos/Build/Products/Release/RustDesk.app/Contents/MacOS/')
+    system2('cp -rf ../target/release/libdylib_virtual_display.dylib ./build/macos/Build/Products/Release/RustDesk.app/Contents/MacOS/')
     '''
     system2(
         "create-dmg --volname \"RustDesk Installer\" --window-pos 200 120 --window-size 800 400 --icon-size 100 --app-drop-link 600 185 --icon RustDesk.app 200 190 --hide-extension RustDesk.app rustdesk.dmg ./build/macos/Build/Products/Release/RustDesk.app")
@@ -486,12 +487,12 @@ def main():
         return
     res_dir = 'resources'
     external_resources(flutter, args, res_dir)
-    if windows:
+    if windows or osx:
         # build virtual display dynamic library
         os.chdir('libs/virtual_display/dylib')
         system2('cargo build --release')
         os.chdir('../../..')
-
+    if windows:
         if flutter:
             build_flutter_windows(version, features, args.skip_portable_pack)
             return
@@ -563,6 +564,8 @@ def main():
                     'strip target/release/bundle/osx/RustDesk.app/Contents/MacOS/rustdesk')
                 system2(
                     'cp libsciter.dylib target/release/bundle/osx/RustDesk.app/Contents/MacOS/')
+                system2(
+                    'cp target/release/libdylib_virtual_display.dylib target/release/bundle/osx/RustDesk.app/Contents/MacOS/')
                 # https://github.com/sindresorhus/create-dmg
                 system2('/bin/rm -rf *.dmg')
                 pa = os.environ.get('P')
@@ -693,6 +693,9 @@ List<Locale> supportedLocales = const [
 ];
 
 String formatDurationToTime(Duration duration) {
+  if (!isWeb) {
+    return bind.mainFormatDuration(seconds: duration.inSeconds);
+  }
   var totalTime = duration.inSeconds;
   final secs = totalTime % 60;
   totalTime = (totalTime - secs) ~/ 60;
@@ -701,6 +704,14 @@ String formatDurationToTime(Duration duration) {
   return "${totalTime.toString().padLeft(2, "0")}:${mins.toString().padLeft(2, "0")}:${secs.toString().padLeft(2, "0")}";
 }
 
+/// The local date and time, in the format of the language of the UI.
+String formatDateTime(DateTime time) {
+  if (!isWeb) {
+    return bind.mainFormatDateTime(ms: time.millisecondsSinceEpoch);
+  }
+  return time.toString().replaceAll(".000", "");
+}
+
 closeConnection({String? id}) {
   if (isAndroid || isIOS) {
     () async {
@@ -1406,6 +1417,9 @@ const M = K * K;
 const G = M * K;
 
 String readableFileSize(double size) {
+  if (!isWeb) {
+    return bind.mainFormatBytes(size: size);
+  }
   if (size < K) {
     return "${size.toStringAsFixed(2)} B";
   } else if (size < M) {
@@ -1927,6 +1927,212 @@ trackpadSpeedDialog(SessionID sessionId, FFI ffi) async {
       [btnClose]);
 }
 
+mouseSpeedDialog(SessionID sessionId, FFI ffi) async {
+  var curve = {'scale': 1.0, 'accel': 0.0};
+  final saved = await bind.sessionGetOption(
+      sessionId: sessionId, arg: kOptionMouseCurve);
+  try {
+    if (saved != null && saved.isNotEmpty) {
+      final map = jsonDecode(saved) as Map<String, dynamic>;
+      curve = {
+        'scale': (map['scale'] as num).toDouble(),
+        'accel': ((map['accel'] ?? 0) as num).toDouble(),
+      };
+    }
+  } catch (e) {
+    debugPrint('Invalid mouse curve: $e');
+  }
+  ffi.dialogManager.show((setState, close, context) {
+    submit() async {
+      await bind.sessionSetMouseCurve(
+          sessionId: sessionId, curve: jsonEncode(curve));
+      close();
+    }
+
+    Widget slider(String label, String key, double min, double max) {
+      final value = curve[key]!;
+      return Column(
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          Text('${translate(label)}: ${(value * 100).round()}%'),
+          Slider(
+            value: value,
+            min: min,
+            max: max,
+            divisions: ((max - min) * 20).round(),
+            onChanged: (v) => setState(() => curve[key] = v),
+          ),
+        ],
+      );
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Mouse speed')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          slider('Speed', 'scale', 0.1, 5.0),
+          slider('Mouse acceleration', 'accel', 0.0, 2.0),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+scrollSettingsDialog(SessionID sessionId, FFI ffi) async {
+  Map<String, dynamic> transform = {
+    'invert': false,
+    'multiplier': 1.0,
+    'unit': 'auto',
+    'momentum': false,
+  };
+  final saved = await bind.sessionGetOption(
+      sessionId: sessionId, arg: kOptionScrollTransform);
+  try {
+    if (saved != null && saved.isNotEmpty) {
+      transform.addAll(jsonDecode(saved) as Map<String, dynamic>);
+      transform['multiplier'] = (transform['multiplier'] as num).toDouble();
+    }
+  } catch (e) {
+    debugPrint('Invalid scroll transform: $e');
+  }
+  ffi.dialogManager.show((setState, close, context) {
+    submit() async {
+      await bind.sessionSetScrollTransform(
+          sessionId: sessionId, transform: jsonEncode(transform));
+      close();
+    }
+
+    Widget check(String label, String key) {
+      return CheckboxListTile(
+        contentPadding: const EdgeInsets.all(0),
+        dense: true,
+        controlAffinity: ListTileControlAffinity.leading,
+        title: Text(translate(label)),
+        value: transform[key] as bool,
+        onChanged: (v) {
+          if (v == null) return;
+          setState(() => transform[key] = v);
+        },
+      );
+    }
+
+    Widget unit(String label, String value) {
+      return Row(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          Radio<String>(
+            visualDensity: VisualDensity(horizontal: -4, vertical: -4),
+            value: value,
+            groupValue: transform['unit'] as String,
+            onChanged: (v) => setState(() => transform['unit'] = v),
+          ),
+          Text(translate(label)).marginOnly(right: 10),
+        ],
+      );
+    }
+
+    final multiplier = transform['multiplier'] as double;
+    return CustomAlertDialog(
+      title: Text(translate('Scroll settings')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          check('Invert scrolling', 'invert'),
+          Text('${translate('Scroll speed')}: ${(multiplier * 100).round()}%'),
+          Slider(
+            value: multiplier.clamp(0.1, 5.0),
+            min: 0.1,
+            max: 5.0,
+            divisions: 98,
+            onChanged: (v) => setState(() => transform['multiplier'] = v),
+          ),
+          Row(children: [
+            unit('Auto', 'auto'),
+            unit('Pixels', 'pixel'),
+            unit('Lines', 'line'),
+          ]),
+          check('Scroll momentum', 'momentum'),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+transferThrottleDialog(SessionID sessionId, FFI ffi) async {
+  var settings = <String, dynamic>{'kbps': 0, 'idle_only': false};
+  final saved = await bind.sessionGetOption(
+      sessionId: sessionId, arg: kOptionTransferThrottle);
+  try {
+    if (saved != null && saved.isNotEmpty) {
+      settings.addAll(jsonDecode(saved) as Map<String, dynamic>);
+    }
+  } catch (e) {
+    debugPrint('Invalid transfer throttle: $e');
+  }
+  final controller = TextEditingController(text: '${settings['kbps']}');
+  ffi.dialogManager.show((setState, close, context) {
+    submit() async {
+      settings['kbps'] = int.tryParse(controller.text) ?? 0;
+      await bind.sessionSetTransferThrottle(
+          sessionId: sessionId, settings: jsonEncode(settings));
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Transfer speed limit')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          TextField(
+            controller: controller,
+            autofocus: true,
+            keyboardType: TextInputType.number,
+            inputFormatters: [
+              FilteringTextInputFormatter.allow(RegExp(r'[0-9]')),
+            ],
+            decoration: InputDecoration(
+              labelText: 'KB/s',
+              helperText: translate('0 for no limit'),
+            ),
+          ),
+          CheckboxListTile(
+            contentPadding: const EdgeInsets.all(0),
+            dense: true,
+            controlAffinity: ListTileControlAffinity.leading,
+            title: Text(translate('Only transfer when idle')),
+            value: settings['idle_only'] as bool,
+            onChanged: (v) {
+              if (v == null) return;
+              setState(() => settings['idle_only'] = v);
+            },
+          ),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
 void deleteConfirmDialog(Function onSubmit, String title) async {
   gFFI.dialogManager.show(
     (setState, close, context) {
@@ -2330,6 +2536,100 @@ void enter2FaDialog(
   });
 }
 
+/// The challenge of the authentication provider of the peer, instead of its password.
+void authProviderDialog(
+    SessionID sessionId, OverlayDialogManager dialogManager, String text) {
+  Map<String, dynamic> challenge = {};
+  try {
+    challenge = json.decode(text);
+  } catch (e) {
+    debugPrint('Invalid challenge of the authentication provider: $e');
+    return;
+  }
+  final Map<String, dynamic> prompt = challenge['prompt'] ?? {};
+  final isDeviceCode = prompt['type'] == 'device_code';
+  final String uri = prompt['verification_uri'] ?? '';
+  final userController = TextEditingController();
+  final passwordController = TextEditingController();
+
+  dialogManager.dismissAll();
+  dialogManager.show((setState, close, context) {
+    cancel() {
+      close();
+      closeConnection();
+    }
+
+    submit() {
+      final Map<String, dynamic> answer;
+      if (isDeviceCode) {
+        answer = {'type': 'device_code'};
+      } else {
+        final user = userController.text.trim();
+        if (user.isEmpty || passwordController.text.isEmpty) return;
+        answer = {
+          'type': 'credentials',
+          'user': user,
+          'password': passwordController.text,
+        };
+      }
+      bind.sessionSendAuthProviderAnswer(
+          sessionId: sessionId, answer: jsonEncode(answer));
+      close();
+      dialogManager.showLoading(translate('Logging in...'),
+          onCancel: closeConnection);
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Authentication')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          if (challenge['failed'] == true)
+            Text(
+              translate('auth-provider-failed-tip'),
+              style: TextStyle(color: Theme.of(context).colorScheme.error),
+            ).marginOnly(bottom: 12),
+          if (isDeviceCode) ...[
+            Text(translate('auth-provider-device-code-tip'))
+                .marginOnly(bottom: 12),
+            InkWell(
+              onTap: () => launchUrl(Uri.parse(uri)),
+              child: Text(
+                uri,
+                style: TextStyle(decoration: TextDecoration.underline),
+              ),
+            ).marginOnly(bottom: 12),
+            SelectableText(
+              prompt['user_code'] ?? '',
+              style: TextStyle(fontSize: 24, fontWeight: FontWeight.bold),
+            ),
+          ] else ...[
+            Text(translate('auth-provider-credentials-tip'))
+                .marginOnly(bottom: 12),
+            DialogTextField(
+              title: translate(DialogTextField.kUsernameTitle),
+              controller: userController,
+              prefixIcon: DialogTextField.kUsernameIcon,
+              errorText: null,
+            ),
+            PasswordWidget(
+              controller: passwordController,
+              autoFocus: false,
+            ),
+          ],
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: cancel, isOutline: true),
+        dialogButton(isDeviceCode ? 'Continue' : 'OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: cancel,
+    );
+  });
+}
+
 // This dialog should not be dismissed, otherwise it will be black screen, have not reproduced this.
 void showWindowsSessionsDialog(
     String type,
@@ -2382,6 +2682,407 @@ void showWindowsSessionsDialog(
   });
 }
 
+void showAudioDevicesDialog(Map<String, dynamic> evt, SessionID sessionId,
+    OverlayDialogManager dialogManager) {
+  final String error = evt['error'] ?? '';
+  if (error.isNotEmpty) {
+    msgBox(sessionId, 'custom-nook-nocancel-hasclose-error', 'Audio device',
+        error, '', dialogManager);
+    return;
+  }
+  final inputs = List<String>.from(evt['inputs'] ?? []);
+  final outputs = List<String>.from(evt['outputs'] ?? []);
+  final keys = ['', ...inputs, ...outputs];
+  final values = [
+    translate('Default'),
+    ...inputs,
+    ...outputs.map((e) => '$e (${translate('Loopback')})'),
+  ];
+  String selected = evt['selected'] ?? '';
+  if (!keys.contains(selected)) selected = '';
+  dialogManager.dismissAll();
+  dialogManager.show((setState, close, context) {
+    submit() {
+      bind.sessionSelectAudioDevice(sessionId: sessionId, device: selected);
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Audio device')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          Text(translate('remote-audio-device-tip')).marginOnly(bottom: 12),
+          ComboBox(
+              keys: keys,
+              values: values,
+              initialKey: selected,
+              onChanged: (value) {
+                selected = value;
+              }),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+void showKeyboardLayoutsDialog(Map<String, dynamic> evt, SessionID sessionId,
+    OverlayDialogManager dialogManager) {
+  final String error = evt['error'] ?? '';
+  if (error.isNotEmpty) {
+    msgBox(sessionId, 'custom-nook-nocancel-hasclose-error', 'Keyboard layout',
+        error, '', dialogManager);
+    return;
+  }
+  final layouts = List<Map<String, dynamic>>.from(evt['layouts'] ?? []);
+  final keys = [
+    'sync',
+    ...layouts.map((e) => e['id'] as String),
+  ];
+  final values = [
+    translate('Same as local'),
+    ...layouts.map((e) => e['name'] as String),
+  ];
+  String selected = evt['current'] ?? '';
+  if (!keys.contains(selected)) selected = 'sync';
+  dialogManager.dismissAll();
+  dialogManager.show((setState, close, context) {
+    submit() {
+      bind.sessionSwitchKeyboardLayout(sessionId: sessionId, layout: selected);
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Keyboard layout')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: [
+          Text(translate('remote-keyboard-layout-tip')).marginOnly(bottom: 12),
+          ComboBox(
+              keys: keys,
+              values: values,
+              initialKey: selected,
+              onChanged: (value) {
+                selected = value;
+              }),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+void showMonitorModesDialog(Map<String, dynamic> evt, SessionID sessionId,
+    OverlayDialogManager dialogManager) {
+  final String error = evt['error'] ?? '';
+  if (error.isNotEmpty) {
+    msgBox(sessionId, 'custom-nook-nocancel-hasclose-error',
+        'Virtual display modes', error, '', dialogManager);
+    return;
+  }
+  if (evt['set'] == true) {
+    showToast(translate('Successful'));
+    return;
+  }
+  final controller = TextEditingController(text: evt['modes'] ?? '');
+  dialogManager.dismissAll();
+  dialogManager.show((setState, close, context) {
+    submit() {
+      bind.sessionSetMonitorModes(
+          sessionId: sessionId, modes: controller.text.trim());
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Virtual display modes')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          Text(translate('virtual-display-modes-tip')).marginOnly(bottom: 12),
+          TextField(
+            controller: controller,
+            autofocus: true,
+            decoration: InputDecoration(hintText: '3440x1440@100,1920x1080'),
+          ),
+        ],
+      ),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('OK', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  });
+}
+
+void showCameraControlsDialog(Map<String, dynamic> evt, SessionID sessionId,
+    OverlayDialogManager dialogManager) {
+  final String error = evt['error'] ?? '';
+  if (error.isNotEmpty) {
+    msgBox(sessionId, 'custom-nook-nocancel-hasclose-error', 'Camera controls',
+        error, '', dialogManager);
+    return;
+  }
+  final int display = evt['display'] ?? 0;
+  final controls = List<Map<String, dynamic>>.from(evt['controls'] ?? []);
+  const labels = {
+    'zoom': 'Zoom',
+    'pan': 'Pan',
+    'tilt': 'Tilt',
+    'focus': 'Focus',
+    'exposure': 'Exposure',
+    'white_balance': 'White balance',
+  };
+  // The host answers each change with the controls, shown again.
+  setControl(String control, bool auto, double? value) =>
+      bind.sessionSetCameraControl(
+          sessionId: sessionId,
+          display: display,
+          control: control,
+          auto: auto,
+          value: value);
+  dialogManager.dismissAll();
+  dialogManager.show((setState, close, context) {
+    Widget item(Map<String, dynamic> c) {
+      final String control = c['control'];
+      final bool auto = c['auto'] == true;
+      final double min = ((c['min'] ?? 0) as num).toDouble();
+      final double max = ((c['max'] ?? 0) as num).toDouble();
+      final double? value = (c['value'] as num?)?.toDouble().clamp(min, max);
+      return Column(
+        crossAxisAlignment: CrossAxisAlignment.start,
+        children: [
+          Row(children: [
+            Expanded(child: Text(translate(labels[control] ?? control))),
+            Text(translate('Auto')),
+            Switch(
+                value: auto,
+                onChanged: (v) {
+                  setState(() => c['auto'] = v);
+                  setControl(control, v, null);
+                }),
+          ]),
+          if (value != null && max > min)
+            Slider(
+              value: value,
+              min: min,
+              max: max,
+              onChanged: (v) => setState(() => c['value'] = v),
+              // The exposure is biased in the automatic mode too.
+              onChangeEnd: (v) =>
+                  setControl(control, control == 'exposure' && auto, v),
+            ),
+        ],
+      );
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Camera controls')),
+      content: Column(
+        mainAxisSize: MainAxisSize.min,
+        children: controls.map(item).toList(),
+      ),
+      actions: [
+        dialogButton('Close', onPressed: close),
+      ],
+      onCancel: close,
+    );
+  });
+}
+
+void showConfigEditDialog(Map<String, dynamic> evt, SessionID sessionId,
+    OverlayDialogManager dialogManager) {
+  final String error = evt['error'] ?? '';
+  if (error.isNotEmpty) {
+    msgBox(sessionId, 'custom-nook-nocancel-hasclose-error', 'Edit config files',
+        error, '', dialogManager);
+    return;
+  }
+  request(Map<String, dynamic> req) =>
+      bind.sessionConfigEdit(sessionId: sessionId, request: jsonEncode(req));
+  final String path = evt['path'] ?? '';
+  final String content = evt['content'] ?? '';
+  final env = List<dynamic>.from(evt['env'] ?? []);
+  final monospace = TextStyle(fontFamily: 'monospace', fontSize: 12);
+  dialogManager.dismissAll();
+  if (evt['written'] == true) {
+    showToast('${translate('Saved')} ${evt['backup'] ?? ''}');
+    return;
+  }
+  if (path.isNotEmpty) {
+    final controller = TextEditingController(text: content);
+    dialogManager.show((setState, close, context) {
+      save() {
+        final diff = bind.mainConfigEditDiff(
+            oldContent: content, newContent: controller.text);
+        final lines = diff.split('\n');
+        if (!lines.any((l) => l.startsWith('+') || l.startsWith('-'))) {
+          close();
+          return;
+        }
+        close();
+        dialogManager.show((setState, close, context) {
+          submit() {
+            request({
+              'action': 'write',
+              'path': path,
+              'base': content,
+              'content': controller.text,
+            });
+            close();
+          }
+
+          return CustomAlertDialog(
+            title: Text(path),
+            content: SizedBox(
+              width: 600,
+              height: 400,
+              child: SingleChildScrollView(
+                child: Column(
+                  crossAxisAlignment: CrossAxisAlignment.start,
+                  children: lines
+                      .where((l) => !l.startsWith(' '))
+                      .map((l) => Text(l,
+                          style: monospace.copyWith(
+                              color: l.startsWith('+')
+                                  ? Colors.green
+                                  : Colors.red)))
+                      .toList(),
+                ),
+              ),
+            ),
+            actions: [
+              dialogButton('Cancel', onPressed: close, isOutline: true),
+              dialogButton('Save', onPressed: submit),
+            ],
+            onSubmit: submit,
+            onCancel: close,
+          );
+        });
+      }
+
+      return CustomAlertDialog(
+        title: Text(path),
+        content: SizedBox(
+          width: 600,
+          height: 400,
+          child: TextField(
+            controller: controller,
+            maxLines: null,
+            expands: true,
+            style: monospace,
+          ),
+        ),
+        actions: [
+          dialogButton('Cancel', onPressed: close, isOutline: true),
+          dialogButton('Save', onPressed: save),
+        ],
+        onCancel: close,
+      );
+    });
+    return;
+  }
+  if (env.isNotEmpty) {
+    dialogManager.show((setState, close, context) {
+      return CustomAlertDialog(
+        title: Text(translate('Environment variables')),
+        content: SizedBox(
+          width: 500,
+          child: SingleChildScrollView(
+            child: SelectableText(
+                env.map((e) => '${e[0]}=${e[1]}').join('\n'),
+                style: monospace),
+          ),
+        ),
+        actions: [
+          dialogButton('Close', onPressed: close, isOutline: true),
+        ],
+        onCancel: close,
+      );
+    });
+    return;
+  }
+  final files = List<Map<String, dynamic>>.from(evt['files'] ?? []);
+  dialogManager.show((setState, close, context) {
+    return CustomAlertDialog(
+      title: Text(translate('Edit config files')),
+      content: SizedBox(
+        width: 500,
+        child: Column(
+          mainAxisSize: MainAxisSize.min,
+          children: files
+              .map((f) => ListTile(
+                    dense: true,
+                    title: Text(f['path'] ?? ''),
+                    subtitle: Text(f['exists'] == true
+                        ? readableFileSize((f['size'] as int).toDouble())
+                        : translate('New file')),
+                    onTap: () {
+                      request({'action': 'read', 'path': f['path']});
+                      close();
+                    },
+                  ))
+              .toList(),
+        ),
+      ),
+      actions: [
+        dialogButton('Environment variables', onPressed: () {
+          request({'action': 'env'});
+          close();
+        }, isOutline: true),
+        dialogButton('Close', onPressed: close, isOutline: true),
+      ],
+      onCancel: close,
+    );
+  });
+}
+
+void showUnavailableFeaturesDialog(
+    List<Map<String, dynamic>> features, OverlayDialogManager dialogManager) {
+  dialogManager.show((setState, close, context) {
+    return CustomAlertDialog(
+      title: Text(translate('Unavailable features')),
+      content: SizedBox(
+        width: 500,
+        child: SingleChildScrollView(
+          child: Column(
+            mainAxisSize: MainAxisSize.min,
+            children: features
+                .map((f) => ListTile(
+                      dense: true,
+                      title: Text(translate(f['label'] ?? '')),
+                      subtitle: Text(f['reason'] == 'version'
+                          ? translate(
+                              'upgrade_remote_rustdesk_client_to_{${f['min_version']}}_tip')
+                          : translate(f['reason'] == 'platform'
+                              ? 'feature-unavailable-platform-tip'
+                              : 'feature-unavailable-peer-tip')),
+                    ))
+                .toList(),
+          ),
+        ),
+      ),
+      actions: [
+        dialogButton('Close', onPressed: close, isOutline: true),
+      ],
+      onCancel: close,
+    );
+  });
+}
+
 void addPeersToAbDialog(
   List<Peer> peers,
 ) async {
@@ -2865,3 +3566,112 @@ Widget trustedDevicesTable(
         )),
   );
 }
+
+showClipboardHistoryDialog(
+    SessionID sessionId, OverlayDialogManager dialogManager) {
+  List<dynamic> items = [];
+  try {
+    final history = bind.sessionGetClipboardHistory(sessionId: sessionId);
+    if (history.isNotEmpty) {
+      items = jsonDecode(history) as List<dynamic>;
+    }
+  } catch (e) {
+    debugPrint('Invalid clipboard history: $e');
+  }
+  dialogManager.show((setState, close, context) {
+    Widget item(Map<String, dynamic> e) {
+      final preview = (e['preview'] as String).trim();
+      return ListTile(
+        contentPadding: const EdgeInsets.all(0),
+        dense: true,
+        title: Text(
+          preview.isEmpty
+              ? (e['formats'] as List<dynamic>).join(', ')
+              : preview,
+          maxLines: 2,
+          overflow: TextOverflow.ellipsis,
+        ),
+        subtitle: Text(
+            '${formatDateTime(DateTime.fromMillisecondsSinceEpoch(e['time'] as int))}  ${readableFileSize((e['size'] as int).toDouble())}'),
+        onTap: () {
+          bind.sessionSendClipboardHistory(
+              sessionId: sessionId, id: e['id'] as int);
+          close();
+        },
+      );
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Clipboard history')),
+      content: SizedBox(
+        width: 400,
+        child: items.isEmpty
+            ? Text(translate('clipboard-history-empty-tip'))
+            : SingleChildScrollView(
+                child: Column(
+                  mainAxisSize: MainAxisSize.min,
+                  children: items
+                      .map((e) => item(e as Map<String, dynamic>))
+                      .toList(),
+                ),
+              ),
+      ),
+      actions: [
+        dialogButton('Close', onPressed: close, isOutline: true),
+      ],
+      onCancel: close,
+    );
+  });
+}
+
+final _filePasteProgress = <String, RxList<int>>{};
+
+/// The progress of the files of the peer pasted, updated in place, the paste cancelled by
+/// [onCancel]. The dialog is closed once the paste ends.
+void showFilePasteProgress(OverlayDialogManager dialogManager, String tag,
+    Map<String, dynamic> evt, VoidCallback onCancel) {
+  final transferred = int.tryParse('${evt['transferred']}') ?? 0;
+  final total = int.tryParse('${evt['total']}') ?? 0;
+  final canceled = '${evt['canceled']}' == 'true';
+  final failed = '${evt['failed']}' == 'true';
+  if (canceled || failed || transferred >= total) {
+    dialogManager.dismissByTag(tag);
+    _filePasteProgress.remove(tag);
+    if (failed) {
+      showToast(translate('Failed to paste the files'));
+    }
+    return;
+  }
+  final progress = _filePasteProgress[tag];
+  if (progress != null && dialogManager.existing(tag)) {
+    progress.value = [transferred, total];
+    return;
+  }
+  final value = [transferred, total].obs;
+  _filePasteProgress[tag] = value;
+  dialogManager.show(tag: tag, (setState, close, context) {
+    cancel() {
+      onCancel();
+      _filePasteProgress.remove(tag);
+      close();
+    }
+
+    return CustomAlertDialog(
+      title: Text(translate('Pasting files')),
+      content: Obx(() => Column(
+            mainAxisSize: MainAxisSize.min,
+            crossAxisAlignment: CrossAxisAlignment.start,
+            children: [
+              LinearProgressIndicator(
+                  value: value[1] > 0 ? value[0] / value[1] : null),
+              Text('${readableFileSize(value[0].toDouble())} / ${readableFileSize(value[1].toDouble())}')
+                  .marginOnly(top: 8),
+            ],
+          )),
+      actions: [
+        dialogButton('Cancel', onPressed: cancel, isOutline: true),
+      ],
+      onCancel: cancel,
+    );
+  });
+}
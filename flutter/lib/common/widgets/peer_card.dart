@@ -1,3 +1,5 @@
+import 'dart:convert';
+
 import 'package:bot_toast/bot_toast.dart';
 import 'package:flutter/material.dart';
 import 'package:flutter/services.dart';
@@ -25,6 +27,24 @@ final peerCardUiType = PeerUiType.grid.obs;
 
 bool? hideUsernameOnCard;
 
+String _peerTitle(Peer peer) {
+  if (peer.alias.isNotEmpty) return peer.alias;
+  if (peer.displayName.isNotEmpty) return peer.displayName;
+  return formatID(peer.id);
+}
+
+// The avatar published by the host, or the platform icon.
+Widget _peerImage(Peer peer, double size) {
+  if (peer.avatar.isNotEmpty) {
+    try {
+      return ClipOval(
+          child: Image.memory(base64Decode(peer.avatar),
+              width: size, height: size, fit: BoxFit.cover));
+    } catch (_) {}
+  }
+  return getPlatformImage(peer.platform, size: size);
+}
+
 class _PeerCard extends StatefulWidget {
   final Peer peer;
   final PeerTabIndex tab;
@@ -158,8 +178,7 @@ class _PeerCardState extends State<_PeerCard>
             height: isPortrait ? 50 : null,
             child: Stack(
               children: [
-                getPlatformImage(peer.platform, size: isPortrait ? 38 : 30)
-                    .paddingAll(6),
+                _peerImage(peer, isPortrait ? 38 : 30).paddingAll(6),
                 if (_shouldBuildPasswordIcon(peer))
                   Positioned(
                     top: 1,
@@ -183,10 +202,10 @@ class _PeerCardState extends State<_PeerCard>
                   child: Column(
                     children: [
                       Row(children: [
-                        getOnline(isPortrait ? 4 : 8, peer.online),
+                        getOnline(isPortrait ? 4 : 8, peer.online, peer: peer),
                         Expanded(
                             child: Text(
-                          peer.alias.isEmpty ? formatID(peer.id) : peer.alias,
+                          _peerTitle(peer),
                           overflow: TextOverflow.ellipsis,
                           style: Theme.of(context).textTheme.titleSmall,
                         )),
@@ -312,8 +331,7 @@ class _PeerCardState extends State<_PeerCard>
                             children: [
                               Container(
                                 padding: const EdgeInsets.all(6),
-                                child:
-                                    getPlatformImage(peer.platform, size: 60),
+                                child: _peerImage(peer, 60),
                               ),
                               Row(
                                 children: [
@@ -365,10 +383,10 @@ class _PeerCardState extends State<_PeerCard>
                     children: [
                       Expanded(
                           child: Row(children: [
-                        getOnline(8, peer.online),
+                        getOnline(8, peer.online, peer: peer),
                         Expanded(
                             child: Text(
-                          peer.alias.isEmpty ? formatID(peer.id) : peer.alias,
+                          _peerTitle(peer),
                           overflow: TextOverflow.ellipsis,
                           style: Theme.of(context).textTheme.titleSmall,
                         )),
@@ -1464,14 +1482,26 @@ void _rdpDialog(String id) async {
   });
 }
 
-Widget getOnline(double rightPadding, bool online) {
+Widget getOnline(double rightPadding, bool online, {Peer? peer}) {
+  var message = translate(online ? 'Online' : 'Offline');
+  var color = online ? Colors.green : kColorWarn;
+  // The availability is only meaningful while the host is online.
+  final availability = online ? peer?.availability ?? '' : '';
+  if (availability == 'busy' || availability == 'maintenance') {
+    color = availability == 'busy' ? Colors.orange : Colors.red;
+    message +=
+        ' - ${translate(availability == 'busy' ? 'Busy' : 'Maintenance')}';
+    final note = peer?.availabilityNote ?? '';
+    if (note.isNotEmpty) {
+      message += ': $note';
+    }
+  }
   return Tooltip(
-      message: translate(online ? 'Online' : 'Offline'),
+      message: message,
       waitDuration: const Duration(seconds: 1),
       child: Padding(
           padding: EdgeInsets.fromLTRB(0, 4, rightPadding, 4),
-          child: CircleAvatar(
-              radius: 3, backgroundColor: online ? Colors.green : kColorWarn)));
+          child: CircleAvatar(radius: 3, backgroundColor: color)));
 }
 
 Widget build_more(BuildContext context, {bool invert = false}) {
@@ -1,5 +1,6 @@
 import 'dart:async';
 import 'dart:convert';
+import 'dart:typed_data';
 
 import 'package:flutter/material.dart';
 import 'package:flutter/services.dart';
@@ -11,6 +12,7 @@ import 'package:flutter_hbb/consts.dart';
 import 'package:flutter_hbb/desktop/widgets/remote_toolbar.dart';
 import 'package:flutter_hbb/models/model.dart';
 import 'package:flutter_hbb/models/platform_model.dart';
+import 'package:flutter_hbb/models/server_model.dart';
 import 'package:flutter_hbb/utils/multi_window_manager.dart';
 import 'package:get/get.dart';
 
@@ -149,6 +151,16 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
           }
         }));
   }
+  // clipboard history
+  if (isDefaultConn &&
+      ffiModel.keyboard &&
+      perms['clipboard'] != false &&
+      !ffiModel.viewOnly) {
+    v.add(TTextMenu(
+        child: Text(translate('Clipboard history')),
+        onPressed: () =>
+            showClipboardHistoryDialog(sessionId, ffi.dialogManager)));
+  }
   // reset canvas
   if (isDefaultConn && isMobile) {
     v.add(TTextMenu(
@@ -156,6 +168,16 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
         onPressed: () => ffi.cursorModel.reset()));
   }
 
+  // controls of the camera viewed
+  if (ffi.connType == ConnType.viewCamera &&
+      pi.isSupportCameraControls &&
+      pi.currentDisplay >= 0) {
+    v.add(TTextMenu(
+        child: Text(translate('Camera controls')),
+        onPressed: () => bind.sessionGetCameraControls(
+            sessionId: sessionId, display: pi.currentDisplay)));
+  }
+
   // https://github.com/rustdesk/rustdesk/pull/9731
   // Does not work for connection established by "accept".
   connectWithToken(
@@ -193,6 +215,37 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
           child: Text(translate('TCP tunneling')),
           onPressed: () => connectWithToken(isTcpTunneling: true)),
     );
+    // workspace
+    String workspaceId = '';
+    try {
+      for (final w in jsonDecode(bind.mainGetWorkspaces()) as List<dynamic>) {
+        if ((w['sessions'] as List<dynamic>)
+            .any((s) => s['session_id'] == sessionId.toString())) {
+          workspaceId = w['id'];
+        }
+      }
+    } catch (e) {
+      debugPrint('failed to load workspaces: $e');
+    }
+    if (workspaceId.isEmpty) {
+      v.add(
+        TTextMenu(
+            child: Text(translate('Open workspace')),
+            onPressed: () {
+              if (bind.sessionCreateWorkspace(sessionId: sessionId).isEmpty) {
+                return;
+              }
+              connectWithToken(isFileTransfer: true);
+              connectWithToken(isTerminal: true);
+            }),
+      );
+    } else {
+      v.add(
+        TTextMenu(
+            child: Text(translate('Close workspace')),
+            onPressed: () => bind.mainCloseWorkspace(id: workspaceId)),
+      );
+    }
   }
   // note
   if (isDefaultConn && !bind.isDisableAccount()) {
@@ -232,6 +285,91 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
           onPressed: () => bind.sessionCtrlAltDel(sessionId: sessionId)),
     );
   }
+  // talk-back
+  if (!isWeb && pi.isSupportTalkBack) {
+    final talking = bind.sessionGetCommonSync(
+            sessionId: sessionId, key: 'is_talking_back', param: '') ==
+        'true';
+    v.add(TTextMenu(
+        child: Text(translate(talking ? 'Stop talk-back' : 'Start talk-back')),
+        onPressed: () =>
+            bind.sessionTalkBack(sessionId: sessionId, on: !talking)));
+  }
+  // audio device of the peer
+  if (isDefaultConn && !isWeb && pi.isSupportAudioDevices) {
+    v.add(TTextMenu(
+        child: Text(translate('Audio device')),
+        onPressed: () => bind.sessionGetAudioDevices(sessionId: sessionId)));
+  }
+  // keyboard layout of the peer
+  if (isDefaultConn &&
+      !ffiModel.viewOnly &&
+      ffiModel.keyboard &&
+      pi.isSupportKeyboardLayout) {
+    v.add(TTextMenu(
+        child: Text(translate('Keyboard layout')),
+        onPressed: () =>
+            bind.sessionGetKeyboardLayouts(sessionId: sessionId)));
+  }
+  // config files of the peer
+  if (isDefaultConn &&
+      !isWeb &&
+      !ffiModel.viewOnly &&
+      ffiModel.permissions['file'] != false &&
+      pi.isSupportConfigEdit) {
+    v.add(TTextMenu(
+        child: Text(translate('Edit config files')),
+        onPressed: () => bind.sessionConfigEdit(
+            sessionId: sessionId, request: jsonEncode({'action': 'list'}))));
+  }
+  // modes of the virtual display of the peer
+  if (isDefaultConn &&
+      !ffiModel.viewOnly &&
+      ffiModel.keyboard &&
+      pi.isSupportMonitorModes) {
+    v.add(TTextMenu(
+        child: Text(translate('Virtual display modes')),
+        onPressed: () => bind.sessionGetMonitorModes(sessionId: sessionId)));
+  }
+  // annotations on the screen of the peer
+  if (isDefaultConn &&
+      !ffiModel.viewOnly &&
+      ffiModel.keyboard &&
+      pi.isSupportAnnotation) {
+    v.add(TTextMenu(
+        child: Text(translate('Undo annotation')),
+        onPressed: () => ffi.inputModel.sendAnnotation({'t': 'Undo'})));
+    v.add(TTextMenu(
+        child: Text(translate('Clear annotations')),
+        onPressed: () => ffi.inputModel.sendAnnotation({'t': 'Clear'})));
+  }
+  // features the peer lacks, and why
+  if (isDefaultConn) {
+    final features = List<Map<String, dynamic>>.from(jsonDecode(
+        bind.sessionGetUnavailableFeatures(sessionId: sessionId)));
+    if (features.isNotEmpty) {
+      v.add(TTextMenu(
+          child: Text(translate('Unavailable features')),
+          onPressed: () => showUnavailableFeaturesDialog(
+              features, ffi.dialogManager)));
+    }
+  }
+  // custom actions of the peer
+  if (isDefaultConn && !ffiModel.viewOnly && ffiModel.keyboard) {
+    try {
+      final actions = bind.sessionGetToolbarActions(sessionId: sessionId);
+      if (actions.isNotEmpty) {
+        for (final a in jsonDecode(actions) as List<dynamic>) {
+          v.add(TTextMenu(
+              child: Text(a['label'] ?? ''),
+              onPressed: () => bind.sessionRunToolbarAction(
+                  sessionId: sessionId, id: a['id'])));
+        }
+      }
+    } catch (e) {
+      debugPrint('failed to load toolbar actions: $e');
+    }
+  }
   // restart
   if (isDefaultConn &&
       perms['restart'] != false &&
@@ -257,7 +395,8 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
   if (isDefaultConn &&
       ffi.ffiModel.keyboard &&
       ffi.ffiModel.permissions['block_input'] != false &&
-      pi.platform == kPeerPlatformWindows) // privacy-mode != true ??
+      (pi.platform == kPeerPlatformWindows ||
+          pi.isSupportBlockInput)) // privacy-mode != true ??
   {
     v.add(TTextMenu(
         child: Obx(() => Text(translate(
@@ -283,6 +422,18 @@ List<TTextMenu> toolbarControls(BuildContext context, String id, FFI ffi) {
         onPressed: () =>
             showConfirmSwitchSidesDialog(sessionId, id, ffi.dialogManager)));
   }
+  // request the permissions which the peer disabled
+  if (isDefaultConn && pi.isSupportPermissionRequest) {
+    for (final name in ['keyboard', 'clipboard', 'file', 'audio', 'restart']) {
+      if (perms[name] == false) {
+        v.add(TTextMenu(
+            child: Text(
+                '${translate('Request permission')}: ${translate(permissionRequestNames[name]!)}'),
+            onPressed: () => bind.sessionRequestPermission(
+                sessionId: sessionId, permission: name)));
+      }
+    }
+  }
   // refresh
   if (pi.version.isNotEmpty) {
     v.add(TTextMenu(
@@ -423,6 +574,33 @@ Future<List<TRadioMenu<String>>> toolbarImageQuality(
   ];
 }
 
+Future<List<TRadioMenu<String>>> toolbarWakePolicy(
+    BuildContext context, String id, FFI ffi) async {
+  if (!ffi.ffiModel.pi.isSupportWakePolicy) return [];
+  final sessionId = ffi.sessionId;
+  var groupValue = await bind.sessionGetOption(
+          sessionId: sessionId, arg: kOptionWakePolicy) ??
+      '';
+  if (groupValue.isEmpty) groupValue = 'host';
+  onChanged(String? value) async {
+    if (value == null) return;
+    await bind.sessionSetWakePolicy(sessionId: sessionId, policy: value);
+  }
+
+  return [
+    ['host', 'wake-policy-host'],
+    ['system', 'wake-policy-system'],
+    ['display', 'wake-policy-display'],
+    ['none', 'wake-policy-none'],
+  ]
+      .map((e) => TRadioMenu<String>(
+          child: Text(translate(e[1])),
+          value: e[0],
+          groupValue: groupValue,
+          onChanged: onChanged))
+      .toList();
+}
+
 Future<List<TRadioMenu<String>>> toolbarCodec(
     BuildContext context, String id, FFI ffi) async {
   final sessionId = ffi.sessionId;
@@ -571,6 +749,27 @@ Future<List<TToggleMenu>> toolbarCursor(
               sessionId: sessionId, arg: option);
         }));
   }
+  // annotate the screen of the peer
+  if (ffi.connType == ConnType.defaultConn &&
+      !ffiModel.viewOnly &&
+      ffiModel.keyboard &&
+      pi.isSupportAnnotation) {
+    final inputModel = ffi.inputModel;
+    v.add(TToggleMenu(
+        child: Text(translate('Annotate')),
+        value: inputModel.annotating.value,
+        onChanged: (value) {
+          if (value == null) return;
+          inputModel.annotating.value = value;
+        }));
+    v.add(TToggleMenu(
+        child: Text(translate('Laser pointer')),
+        value: inputModel.laser.value,
+        onChanged: (value) {
+          if (value == null) return;
+          inputModel.setLaser(value);
+        }));
+  }
   // zoom cursor
   final viewStyle = await bind.sessionGetViewStyle(sessionId: sessionId) ?? '';
   if (!isMobile &&
@@ -668,6 +867,21 @@ Future<List<TToggleMenu>> toolbarDisplayToggle(
             : null,
         child: Text(translate('Disable clipboard'))));
   }
+  // plain text clipboard
+  if (isDefaultConn && ffiModel.keyboard && perms['clipboard'] != false) {
+    final option = kOptionClipboardPlainTextOnly;
+    final value =
+        bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option);
+    v.add(TToggleMenu(
+        value: value,
+        onChanged: ffiModel.viewOnly
+            ? null
+            : (value) {
+                if (value == null) return;
+                bind.sessionToggleOption(sessionId: sessionId, value: option);
+              },
+        child: Text(translate('Plain text clipboard only'))));
+  }
   // lock after session end
   if (isDefaultConn && ffiModel.keyboard && !ffiModel.isPeerAndroid) {
     final enabled = !ffiModel.viewOnly;
@@ -684,6 +898,30 @@ Future<List<TToggleMenu>> toolbarDisplayToggle(
             : null,
         child: Text(translate('Lock after session end'))));
   }
+  // wake the display of the peer when it sleeps
+  if (isDefaultConn && ffiModel.keyboard && pi.isSupportDisplayPower) {
+    final enabled = !ffiModel.viewOnly;
+    final value = await bind.sessionGetOption(
+            sessionId: sessionId, arg: kOptionWakeDisplay) ==
+        'Y';
+    v.add(TToggleMenu(
+        value: value,
+        onChanged: enabled
+            ? (value) async {
+                if (value == null) return;
+                await bind.sessionPeerOption(
+                    sessionId: sessionId,
+                    name: kOptionWakeDisplay,
+                    value: value ? 'Y' : 'N');
+                if (value && ffiModel.asleepDisplays.isNotEmpty) {
+                  bind.sessionWakeDisplay(
+                      sessionId: sessionId,
+                      display: ffiModel.asleepDisplays.first);
+                }
+              }
+            : null,
+        child: Text(translate('Wake the display when it sleeps'))));
+  }
 
   if (pi.isSupportMultiDisplay &&
       PrivacyModeState.find(id).isEmpty &&
@@ -827,6 +1065,36 @@ List<TToggleMenu> toolbarPrivacyMode(
   }
 }
 
+/// The displays the privacy mode blanks, none if the peer blanks all of them only.
+List<TToggleMenu> toolbarPrivacyDisplays(FFI ffi) {
+  final ffiModel = ffi.ffiModel;
+  final pi = ffiModel.pi;
+  if (!pi.isSupportPrivacyDisplays || pi.displays.length < 2) {
+    return [];
+  }
+  final all = List<int>.generate(pi.displays.length, (i) => i);
+  final chosen = ffiModel.privacyDisplays.isEmpty
+      ? all
+      : ffiModel.privacyDisplays.toList();
+  return all.map((i) {
+    return TToggleMenu(
+        child: Text('${translate('Display')} ${i + 1}'),
+        value: chosen.contains(i),
+        // One display at least, none is all of them.
+        onChanged: chosen.length == 1 && chosen.contains(i)
+            ? null
+            : (value) {
+                if (value == null) return;
+                final displays = all
+                    .where((d) => d == i ? value : chosen.contains(d))
+                    .toList();
+                bind.sessionSetPrivacyDisplays(
+                    sessionId: ffi.sessionId,
+                    displays: Int32List.fromList(displays));
+              });
+  }).toList();
+}
+
 List<TToggleMenu> toolbarKeyboardToggles(FFI ffi) {
   final ffiModel = ffi.ffiModel;
   final pi = ffiModel.pi;
@@ -881,6 +1149,24 @@ List<TToggleMenu> toolbarKeyboardToggles(FFI ffi) {
         child: Text(translate('Relative mouse mode'))));
   }
 
+  // game controllers of this device
+  if (isDesktop &&
+      isDefaultConn &&
+      ffiModel.keyboard &&
+      !ffiModel.viewOnly &&
+      pi.isSupportGamepad) {
+    final option = 'forward-gamepad';
+    final value =
+        bind.sessionGetToggleOptionSync(sessionId: sessionId, arg: option);
+    v.add(TToggleMenu(
+        value: value,
+        onChanged: (value) {
+          if (value == null) return;
+          bind.sessionToggleOption(sessionId: sessionId, value: option);
+        },
+        child: Text(translate('Forward game controllers'))));
+  }
+
   // reverse mouse wheel
   if (ffiModel.keyboard) {
     var optionValue =
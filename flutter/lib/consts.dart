@@ -28,6 +28,39 @@ const String kPlatformAdditionsAmyuniVirtualDisplays =
 const String kPlatformAdditionsHasFileClipboard = "has_file_clipboard";
 const String kPlatformAdditionsSupportedPrivacyModeImpl =
     "supported_privacy_mode_impl";
+const String kPlatformAdditionsDoNotRecord = "do_not_record";
+const String kPlatformAdditionsHostRecording = "host_recording";
+const String kPlatformAdditionsSupportTalkBack = "support_talk_back";
+const String kPlatformAdditionsSupportAudioDevices = "support_audio_devices";
+const String kPlatformAdditionsKeyboardLayout = "keyboard_layout";
+const String kPlatformAdditionsConfigEdit = "config_edit";
+const String kPlatformAdditionsSupportMonitorModes = "support_monitor_modes";
+const String kPlatformAdditionsSupportBlockInput = "support_block_input";
+const String kPlatformAdditionsSupportPreciseWheel =
+    "support_precise_wheel";
+const String kPlatformAdditionsSupportStylus = "support_stylus";
+const String kPlatformAdditionsSupportGamepad = "support_gamepad";
+const String kPlatformAdditionsSupportCameraControls =
+    "support_camera_controls";
+const String kPlatformAdditionsSupportAnnotation = "support_annotation";
+const String kPlatformAdditionsSupportTouchGesture = "support_touch_gesture";
+const String kPlatformAdditionsSupportDisplayPower = "support_display_power";
+const String kPlatformAdditionsSupportSpecialKeys = "support_special_keys";
+const String kPlatformAdditionsSupportMouseCurve = "support_mouse_curve";
+const String kPlatformAdditionsSupportScrollTransform =
+    "support_scroll_transform";
+const String kPlatformAdditionsSupportTransferThrottle =
+    "support_transfer_throttle";
+const String kPlatformAdditionsSupportWindowVisibility =
+    "support_window_visibility";
+const String kPlatformAdditionsSupportWakePolicy = "support_wake_policy";
+const String kPlatformAdditionsSupportPrivacyDisplays =
+    "support_privacy_displays";
+const String kPlatformAdditionsSupportFolderSync = "support_folder_sync";
+const String kPlatformAdditionsSupportFileDrop = "support_file_drop";
+const String kPlatformAdditionsSupportFileTrash = "support_file_trash";
+const String kPlatformAdditionsSupportPermissionRequest =
+    "support_permission_request";
 
 const String kPeerPlatformWindows = "Windows";
 const String kPeerPlatformLinux = "Linux";
@@ -79,12 +112,15 @@ const String kWindowEventGetCachedSessionData = "get_cached_session_data";
 const String kWindowEventOpenMonitorSession = "open_monitor_session";
 
 const String kOptionViewStyle = "view_style";
+const String kOptionWakeDisplay = "wake-display";
 const String kOptionScrollStyle = "scroll_style";
 const String kOptionEdgeScrollEdgeThickness = "edge-scroll-edge-thickness";
 const String kOptionImageQuality = "image_quality";
 const String kOptionOpenNewConnInTabs = "enable-open-new-connections-in-tabs";
 const String kOptionTextureRender = "use-texture-render";
 const String kOptionD3DRender = "allow-d3d-render";
+const String kOptionFramePacing = "allow-frame-pacing";
+const String kOptionVideoDatagram = "allow-video-datagram";
 const String kOptionOpenInTabs = "allow-open-in-tabs";
 const String kOptionOpenInWindows = "allow-open-in-windows";
 const String kOptionForceAlwaysRelay = "force-always-relay";
@@ -92,6 +128,10 @@ const String kOptionViewOnly = "view_only";
 const String kOptionEnableLanDiscovery = "enable-lan-discovery";
 const String kOptionWhitelist = "whitelist";
 const String kOptionEnableAbr = "enable-abr";
+const String kOptionAdaptiveResolution = "allow-adaptive-resolution";
+const String kOptionSimulcast = "allow-simulcast";
+const String kOptionCameraAudio = "allow-camera-audio";
+const String kOptionTalkBack = "allow-talk-back";
 const String kOptionEnableRecordSession = "enable-record-session";
 const String kOptionDirectServer = "direct-server";
 const String kOptionDirectAccessPort = "direct-access-port";
@@ -100,6 +140,7 @@ const String kOptionAutoDisconnectTimeout = "auto-disconnect-timeout";
 const String kOptionEnableHwcodec = "enable-hwcodec";
 const String kOptionAllowAutoRecordIncoming = "allow-auto-record-incoming";
 const String kOptionAllowAutoRecordOutgoing = "allow-auto-record-outgoing";
+const String kOptionDoNotRecord = "allow-do-not-record";
 const String kOptionVideoSaveDirectory = "video-save-directory";
 const String kOptionAccessMode = "access-mode";
 const String kOptionEnableKeyboard = "enable-keyboard";
@@ -129,6 +170,7 @@ const String kOptionZoomCursor = "zoom-cursor";
 const String kOptionShowQualityMonitor = "show_quality_monitor";
 const String kOptionDisableAudio = "disable_audio";
 const String kOptionEnableFileCopyPaste = "enable-file-copy-paste";
+const String kOptionClipboardPlainTextOnly = "clipboard-plain-text-only";
 // "Settings -> Display -> Other default options"
 const String kOptionDisableClipboard = "disable_clipboard";
 const String kOptionLockAfterSessionEnd = "lock_after_session_end";
@@ -199,6 +241,12 @@ const String kOptionKeepAwakeDuringOutgoingSessions = "keep-awake-during-outgoin
 
 const String kOptionShowMobileAction = "showMobileActions";
 
+const String kOptionMouseCurve = "mouse-curve";
+const String kOptionScrollTransform = "scroll-transform";
+const String kOptionTransferThrottle = "transfer-throttle";
+const String kOptionFolderSync = "folder-sync";
+const String kOptionWakePolicy = "wake-policy";
+
 const String kUrlActionClose = "close";
 
 const String kTabLabelHomePage = "Home";
@@ -282,6 +330,10 @@ const String kMinVersionForRelativeMouseMode = '1.4.5';
 /// `MAX_RELATIVE_MOUSE_DELTA` in `src/server/input_service.rs`.
 const int kMaxRelativeMouseDelta = 10000;
 
+/// The points of a pen stroke annotating the screen of the peer, `MAX_SHAPE_POINTS` in
+/// `src/whiteboard/mod.rs`.
+const int kMaxAnnotationPoints = 4096;
+
 /// Debounce duration (in milliseconds) for relative mouse mode toggle.
 /// This prevents double-toggle from race condition between Rust rdev grab loop
 /// and Flutter keyboard handling. Value should be small enough to allow
@@ -704,6 +704,12 @@ class _DesktopHomePageState extends State<DesktopHomePage>
         systemError = error;
         setState(() {});
       }
+      final hostFree = await bind.mainTakeHostFreePeers();
+      if (hostFree.isNotEmpty) {
+        for (final id in jsonDecode(hostFree) as List<dynamic>) {
+          showHostFreeDialog(id.toString());
+        }
+      }
       final v = await mainGetBoolOption(kOptionStopService);
       if (v != svcStopped.value) {
         svcStopped.value = v;
@@ -907,6 +913,27 @@ class _DesktopHomePageState extends State<DesktopHomePage>
   }
 }
 
+/// Shown when a host that rejected us because it was busy becomes free.
+void showHostFreeDialog(String id) {
+  gFFI.dialogManager.show((setState, close, context) {
+    submit() {
+      close();
+      connect(context, id);
+    }
+
+    return CustomAlertDialog(
+      title: null,
+      content: msgboxContent('info', 'Prompt', 'host-free-{$id}-tip'),
+      actions: [
+        dialogButton('Cancel', onPressed: close, isOutline: true),
+        dialogButton('Connect', onPressed: submit),
+      ],
+      onSubmit: submit,
+      onCancel: close,
+    );
+  }, tag: 'host-free-$id');
+}
+
 void setPasswordDialog({VoidCallback? notEmptyCallback}) async {
   final pw = await bind.mainGetPermanentPassword();
   final p0 = TextEditingController(text: pw);
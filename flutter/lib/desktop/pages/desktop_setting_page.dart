@@ -481,6 +481,10 @@ class _GeneralState extends State<_General> {
             kOptionEnableConfirmClosingTabs,
             isServer: false),
       _OptionCheckBox(context, 'Adaptive bitrate', kOptionEnableAbr),
+      _OptionCheckBox(
+          context, 'Lower resolution on slow networks', kOptionAdaptiveResolution),
+      _OptionCheckBox(
+          context, 'Separate stream for slow viewers', kOptionSimulcast),
       if (!isWeb) wallpaper(),
       if (!isWeb && !bind.isIncomingOnly()) ...[
         _OptionCheckBox(
@@ -511,6 +515,26 @@ class _GeneralState extends State<_General> {
                   await bind.mainSetLocalOption(key: k, value: v ? 'Y' : 'N'),
             ),
           ),
+        if (!isWeb)
+          Tooltip(
+            message: translate('frame_pacing_tip'),
+            child: _OptionCheckBox(
+              context,
+              "Smooth video playback",
+              kOptionFramePacing,
+              isServer: false,
+            ),
+          ),
+        if (!isWeb)
+          Tooltip(
+            message: translate('video_datagram_tip'),
+            child: _OptionCheckBox(
+              context,
+              "Skip lost video frames",
+              kOptionVideoDatagram,
+              isServer: false,
+            ),
+          ),
         if (isWindows)
           Tooltip(
             message: translate('d3d_render_tip'),
@@ -695,6 +719,10 @@ class _GeneralState extends State<_General> {
         if (!bind.isOutgoingOnly())
           _OptionCheckBox(context, 'Automatically record incoming sessions',
               kOptionAllowAutoRecordIncoming),
+        if (!bind.isOutgoingOnly())
+          _OptionCheckBox(context,
+              'Do not allow viewers to record or take screenshots',
+              kOptionDoNotRecord),
         if (!bind.isIncomingOnly())
           _OptionCheckBox(context, 'Automatically record outgoing sessions',
               kOptionAllowAutoRecordOutgoing,
@@ -1039,6 +1067,10 @@ class _SafetyState extends State<_Safety> with AutomaticKeepAliveClientMixin {
                 enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(context, 'Enable camera', kOptionEnableCamera,
                 enabled: enabled, fakeValue: fakeValue),
+            _OptionCheckBox(context, 'Send the microphone with the camera',
+                kOptionCameraAudio),
+            _OptionCheckBox(
+                context, 'Play the microphone of the viewers', kOptionTalkBack),
             _OptionCheckBox(context, 'Enable terminal', kOptionEnableTerminal,
                 enabled: enabled, fakeValue: fakeValue),
             _OptionCheckBox(
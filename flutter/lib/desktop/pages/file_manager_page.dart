@@ -292,6 +292,20 @@ class _FileManagerPageState extends State<FileManagerPage>
                         Row(
                           mainAxisAlignment: MainAxisAlignment.end,
                           children: [
+                            Offstage(
+                              offstage: item.trashed.isEmpty ||
+                                  item.state == JobState.inProgress,
+                              child: MenuButton(
+                                tooltip: translate("Undo"),
+                                onPressed: () {
+                                  jobController.restoreTrashed(item.id);
+                                },
+                                child: Icon(Icons.undo_rounded,
+                                    color: Colors.white, size: 16),
+                                color: MyTheme.accent,
+                                hoverColor: MyTheme.accent80,
+                              ),
+                            ),
                             Offstage(
                               offstage: item.state != JobState.paused,
                               child: MenuButton(
@@ -999,7 +1013,53 @@ class _FileManagerViewState extends State<FileManagerView> {
               Text(translate("Unselect All"), style: style),
           proc: () => selectedItems.clear(),
           padding: kDesktopMenuPadding,
-          dismissOnClicked: true)
+          dismissOnClicked: true),
+      MenuEntrySwitch<String>(
+        switchType: SwitchType.scheckbox,
+        text: translate("Only send the changed files"),
+        getter: () async {
+          return (await bind.sessionGetPeerOption(
+                  sessionId: controller.sessionId, name: kOptionFolderSync))
+              .isNotEmpty;
+        },
+        setter: (bool v) async {
+          await bind.sessionPeerOption(
+              sessionId: controller.sessionId,
+              name: kOptionFolderSync,
+              value: v ? 'meta' : '');
+        },
+        padding: kDesktopMenuPadding,
+        dismissOnClicked: true,
+      ),
+      if (controller.rootState.target?.ffiModel.pi.isSupportFolderSync == true)
+        MenuEntrySwitch<String>(
+          switchType: SwitchType.scheckbox,
+          text: translate("Compare the file contents"),
+          getter: () async {
+            return (await bind.sessionGetPeerOption(
+                    sessionId: controller.sessionId,
+                    name: kOptionFolderSync)) ==
+                'hash';
+          },
+          setter: (bool v) async {
+            await bind.sessionPeerOption(
+                sessionId: controller.sessionId,
+                name: kOptionFolderSync,
+                value: v ? 'hash' : 'meta');
+          },
+          padding: kDesktopMenuPadding,
+          dismissOnClicked: true,
+        ),
+      if (!isLocal &&
+          controller.rootState.target?.ffiModel.pi.isSupportTransferThrottle ==
+              true)
+        MenuEntryButton(
+            childBuilder: (style) =>
+                Text(translate("Transfer speed limit"), style: style),
+            proc: () => transferThrottleDialog(
+                controller.sessionId, controller.rootState.target!),
+            padding: kDesktopMenuPadding,
+            dismissOnClicked: true),
     ];
 
     return Listener(
@@ -1099,7 +1159,7 @@ class _FileManagerViewState extends State<FileManagerView> {
               entry.isFile ? readableFileSize(entry.size.toDouble()) : "";
           final lastModifiedStr = entry.isDrive
               ? " "
-              : "${entry.lastModified().toString().replaceAll(".000", "")}   ";
+              : "${formatDateTime(entry.lastModified())}   ";
           var secondaryPosition = RelativeRect.fromLTRB(0, 0, 0, 0);
           onTap() {
             final items = selectedItems;
@@ -1,6 +1,9 @@
 import 'dart:async';
+import 'dart:io';
 
+import 'package:desktop_drop/desktop_drop.dart';
 import 'package:desktop_multi_window/desktop_multi_window.dart';
+import 'package:flutter/gestures.dart';
 import 'package:flutter/material.dart';
 import 'package:flutter/services.dart';
 import 'package:flutter/scheduler.dart';
@@ -15,6 +18,7 @@ import '../../common.dart';
 import '../../common/widgets/dialog.dart';
 import '../../common/widgets/toolbar.dart';
 import '../../models/model.dart';
+import '../../models/file_model.dart';
 import '../../models/input_model.dart';
 import '../../models/platform_model.dart';
 import '../../common/shared_state.dart';
@@ -216,6 +220,9 @@ class _RemotePageState extends State<RemotePage>
       _isWindowBlur = false;
     }
     stateGlobal.isFocused.value = true;
+    // The frames decoded while in the background may be stale, recover at once.
+    bind.sessionRequestKeyFrame(
+        sessionId: sessionId, display: _ffi.ffiModel.pi.currentDisplay);
 
     // Restore relative mouse mode constraints when window regains focus.
     if (_ffi.inputModel.relativeMouseMode.value) {
@@ -232,6 +239,7 @@ class _RemotePageState extends State<RemotePage>
     if (isWindows) {
       _isWindowBlur = false;
     }
+    _ffi.ffiModel.setWindowVisible(true);
     WakelockManager.enable(_uniqueKey);
     // Update pointer lock center when window is restored
     _updatePointerLockCenterIfNeeded();
@@ -241,6 +249,7 @@ class _RemotePageState extends State<RemotePage>
   @override
   void onWindowMaximize() {
     super.onWindowMaximize();
+    _ffi.ffiModel.setWindowVisible(true);
     WakelockManager.enable(_uniqueKey);
     // Update pointer lock center when window is maximized
     _updatePointerLockCenterIfNeeded();
@@ -281,6 +290,7 @@ class _RemotePageState extends State<RemotePage>
   @override
   void onWindowMinimize() {
     super.onWindowMinimize();
+    _ffi.ffiModel.setWindowVisible(false);
     WakelockManager.disable(_uniqueKey);
     // Release cursor constraints when minimized
     if (_ffi.inputModel.relativeMouseMode.value) {
@@ -352,6 +362,29 @@ class _RemotePageState extends State<RemotePage>
         ),
       );
 
+  // Instead of the last frame, which stays on the canvas while the display sleeps.
+  Widget displayAsleepOverlay() => Container(
+        color: kColorCanvas,
+        alignment: Alignment.center,
+        child: Column(
+          mainAxisSize: MainAxisSize.min,
+          children: [
+            const Icon(Icons.bedtime_outlined,
+                size: 48, color: Colors.white70),
+            const SizedBox(height: 12),
+            Text(translate('The remote display is asleep'),
+                style: const TextStyle(color: Colors.white70)),
+            const SizedBox(height: 12),
+            ElevatedButton(
+              onPressed: () => bind.sessionWakeDisplay(
+                  sessionId: _ffi.sessionId,
+                  display: _ffi.ffiModel.asleepDisplays.first),
+              child: Text(translate('Wake up')),
+            ),
+          ],
+        ),
+      );
+
   Widget buildBody(BuildContext context) {
     remoteToolbar(BuildContext context) => RemoteToolbar(
           id: widget.id,
@@ -398,7 +431,7 @@ class _RemotePageState extends State<RemotePage>
                     }
                   },
                   inputModel: _ffi.inputModel,
-                  child: getBodyForDesktop(context))),
+                  child: _buildDropTarget(getBodyForDesktop(context)))),
           Stack(
             children: [
               _ffi.ffiModel.pi.isSet.isTrue &&
@@ -421,6 +454,10 @@ class _RemotePageState extends State<RemotePage>
                             ));
                       }
                     }(),
+              Obx(() => _ffi.ffiModel.asleepDisplays.isNotEmpty &&
+                      _ffi.ffiModel.isDisplayAsleep
+                  ? displayAsleepOverlay()
+                  : const Offstage()),
               // Use Overlay to enable rebuild every time on menu button click.
               // Hide toolbar when relative mouse mode is active to prevent
               // cursor from escaping to toolbar area.
@@ -531,6 +568,35 @@ class _RemotePageState extends State<RemotePage>
     }
   }
 
+  // The files dropped on the remote desktop are sent to the folder under the remote cursor.
+  Widget _buildDropTarget(Widget child) {
+    if (isWeb) return child;
+    return DropTarget(
+        onDragDone: (details) {
+          final ffiModel = _ffi.ffiModel;
+          if (details.files.isEmpty ||
+              ffiModel.viewOnly ||
+              ffiModel.permissions['file'] == false ||
+              !ffiModel.pi.isSupportFileDrop) {
+            return;
+          }
+          final entries = details.files.map((file) {
+            final isDir = FileSystemEntity.isDirectorySync(file.path);
+            return Entry()
+              ..path = file.path
+              ..name = file.name
+              ..entryType = isDir ? 0 : 4
+              ..size = isDir ? 0 : File(file.path).lengthSync();
+          }).toList();
+          // Move the remote cursor to the drop first, the peer looks for the folder there.
+          _ffi.inputModel.onPointHoverImage(PointerHoverEvent(
+              position: details.globalPosition, kind: PointerDeviceKind.mouse));
+          Future.delayed(const Duration(milliseconds: 200),
+              () => _ffi.fileModel.dropFiles(entries));
+        },
+        child: child);
+  }
+
   Widget _buildRawTouchAndPointerRegion(
     Widget child,
     PointerEnterEventListener? onEnter,
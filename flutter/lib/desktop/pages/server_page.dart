@@ -776,6 +776,18 @@ class _PrivilegeBoardState extends State<_PrivilegeBoard> {
                         },
                         translate('Enable recording session'),
                       ),
+                      buildPermissionIcon(
+                        client.viewOnly,
+                        Icons.visibility_rounded,
+                        (enabled) {
+                          bind.cmSetViewOnly(
+                              connId: client.id, viewOnly: enabled);
+                          setState(() {
+                            client.viewOnly = enabled;
+                          });
+                        },
+                        translate('View only now'),
+                      ),
                       // only windows support block input
                       if (isWindows)
                         buildPermissionIcon(
@@ -826,6 +838,42 @@ class _CmControlPanel extends StatelessWidget {
     return Column(
       mainAxisAlignment: MainAxisAlignment.end,
       children: [
+        Offstage(
+          offstage: !client.inRecording,
+          child: Padding(
+            padding: const EdgeInsets.only(bottom: buttonBottomMargin),
+            child: Row(
+              mainAxisAlignment: MainAxisAlignment.center,
+              children: [
+                Icon(Icons.fiber_manual_record, color: Colors.red, size: 14),
+                const SizedBox(width: 4),
+                Text(translate('peer-recording-tip'),
+                    style: TextStyle(color: Colors.red)),
+              ],
+            ),
+          ),
+        ),
+        Offstage(
+          offstage: client.tunnels.isEmpty,
+          child: Padding(
+            padding: const EdgeInsets.only(bottom: buttonBottomMargin),
+            child: Tooltip(
+              message: client.tunnels.join('\n'),
+              child: Row(
+                mainAxisAlignment: MainAxisAlignment.center,
+                children: [
+                  Icon(Icons.swap_horiz, size: 14),
+                  const SizedBox(width: 4),
+                  Flexible(
+                    child: Text(
+                        '${translate('TCP tunnels')}: ${client.tunnels.join(', ')}',
+                        overflow: TextOverflow.ellipsis),
+                  ),
+                ],
+              ),
+            ),
+          ),
+        ),
         Offstage(
           offstage: !client.inVoiceCall,
           child: Row(
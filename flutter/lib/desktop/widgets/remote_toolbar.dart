@@ -404,6 +404,9 @@ class _RemoteToolbarState extends State<RemoteToolbar> {
       toolbarItems.add(_VoiceCallMenu(id: widget.id, ffi: widget.ffi));
     }
     if (!isWeb) toolbarItems.add(_RecordMenu());
+    if (widget.ffi.ffiModel.pi.isHostRecording) {
+      toolbarItems.add(_HostRecordingIndicator());
+    }
     toolbarItems.add(_CloseMenu(id: widget.id, ffi: widget.ffi));
     final toolbarBorderRadius = BorderRadius.all(Radius.circular(4.0));
     return Column(
@@ -979,6 +982,7 @@ class _DisplayMenuState extends State<_DisplayMenu> {
         scrollStyle(state, colorScheme),
         imageQuality(),
         codec(),
+        if (ffi.connType == ConnType.defaultConn) wakePolicy(),
         if (ffi.connType == ConnType.defaultConn)
           _ResolutionsMenu(
             id: widget.id,
@@ -1023,6 +1027,19 @@ class _DisplayMenuState extends State<_DisplayMenu> {
                     .toList()),
           ]);
         }
+        final privacyDisplays = toolbarPrivacyDisplays(ffi);
+        if (privacyModeList.isNotEmpty && privacyDisplays.isNotEmpty) {
+          menuChildren.add(_SubmenuButton(
+              ffi: widget.ffi,
+              child: Text(translate('Privacy mode displays')),
+              menuChildren: privacyDisplays
+                  .map((e) => CkbMenuButton(
+                      value: e.value,
+                      onChanged: e.onChanged,
+                      child: e.child,
+                      ffi: ffi))
+                  .toList()));
+        }
       }
       if (ffi.connType == ConnType.defaultConn) {
         menuChildren.add(widget.pluginItem);
@@ -1224,6 +1241,26 @@ class _DisplayMenuState extends State<_DisplayMenu> {
         });
   }
 
+  wakePolicy() {
+    return futureBuilder(
+        future: toolbarWakePolicy(context, id, ffi),
+        hasData: (data) {
+          final v = data as List<TRadioMenu<String>>;
+          if (v.isEmpty) return Offstage();
+          return _SubmenuButton(
+              ffi: widget.ffi,
+              child: Text(translate('Keep the host awake')),
+              menuChildren: v
+                  .map((e) => RdoMenuButton<String>(
+                      value: e.value,
+                      groupValue: e.groupValue,
+                      onChanged: e.onChanged,
+                      child: e.child,
+                      ffi: ffi))
+                  .toList());
+        });
+  }
+
   cursorToggles() {
     return futureBuilder(
         future: toolbarCursor(context, id, ffi),
@@ -1798,6 +1835,7 @@ class _KeyboardMenu extends StatelessWidget {
               ...toolbarToggles(),
               ...mouseSpeed(),
               ...mobileActions(),
+              ...specialKeys(),
             ]);
   }
 
@@ -1813,6 +1851,21 @@ class _KeyboardMenu extends StatelessWidget {
           ffi: ffi,
         );
         speedWidgets.add(trackpad);
+        if (pi.isSupportMouseCurve) {
+          speedWidgets.add(MenuButton(
+            child: Text(translate('Mouse speed')).paddingOnly(left: 26.0),
+            onPressed: enabled ? () => mouseSpeedDialog(sessionId, ffi) : null,
+            ffi: ffi,
+          ));
+        }
+        if (pi.isSupportScrollTransform) {
+          speedWidgets.add(MenuButton(
+            child: Text(translate('Scroll settings')).paddingOnly(left: 26.0),
+            onPressed:
+                enabled ? () => scrollSettingsDialog(sessionId, ffi) : null,
+            ffi: ffi,
+          ));
+        }
       }
     }
     return speedWidgets;
@@ -2019,6 +2072,39 @@ class _KeyboardMenu extends StatelessWidget {
           ffi: ffi),
     ];
   }
+
+  // The media keys and the functions of the system, without a key code.
+  specialKeys() {
+    if (!pi.isSupportSpecialKeys) return [];
+    final enabled = !ffi.ffiModel.viewOnly;
+    const keys = [
+      ('volume_up', 'Volume up'),
+      ('volume_down', 'Volume down'),
+      ('mute', 'Mute'),
+      ('brightness_up', 'Brightness up'),
+      ('brightness_down', 'Brightness down'),
+      ('play_pause', 'Play/Pause'),
+      ('previous_track', 'Previous track'),
+      ('next_track', 'Next track'),
+      ('mission_control', 'Mission Control'),
+      ('launchpad', 'Launchpad'),
+    ];
+    return [
+      Divider(),
+      _SubmenuButton(
+          ffi: ffi,
+          child: Text(translate('Special keys')),
+          menuChildren: keys
+              .map((e) => MenuButton(
+                  child: Text(translate(e.$2)),
+                  onPressed: enabled
+                      ? () => bind.sessionSendSpecialKey(
+                          sessionId: ffi.sessionId, key: e.$1)
+                      : null,
+                  ffi: ffi))
+              .toList()),
+    ];
+  }
 }
 
 class _ChatMenu extends StatefulWidget {
@@ -2201,6 +2287,21 @@ class _RecordMenu extends StatelessWidget {
   }
 }
 
+class _HostRecordingIndicator extends StatelessWidget {
+  const _HostRecordingIndicator({Key? key}) : super(key: key);
+
+  @override
+  Widget build(BuildContext context) {
+    return _IconMenuButton(
+      assetName: 'assets/rec.svg',
+      tooltip: 'host-recording-tip',
+      onPressed: () {},
+      color: _ToolbarTheme.redColor,
+      hoverColor: _ToolbarTheme.redColor,
+    );
+  }
+}
+
 class _CloseMenu extends StatelessWidget {
   final String id;
   final FFI ffi;
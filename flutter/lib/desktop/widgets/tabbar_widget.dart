@@ -279,7 +279,10 @@ class DesktopTab extends StatefulWidget {
   }) : super(key: key);
 
   static RxString tablabelGetter(String peerId) {
-    final alias = bind.mainGetPeerOptionSync(id: peerId, key: 'alias');
+    var alias = bind.mainGetPeerOptionSync(id: peerId, key: 'alias');
+    if (alias.isEmpty) {
+      alias = bind.mainGetPeerOptionSync(id: peerId, key: 'peer-display-name');
+    }
     return RxString(getDesktopTabLabel(peerId, alias));
   }
 
@@ -328,7 +331,10 @@ class _DesktopTabState extends State<DesktopTab>
   _DesktopTabState() : super();
 
   static RxString tablabelGetter(String peerId) {
-    final alias = bind.mainGetPeerOptionSync(id: peerId, key: 'alias');
+    var alias = bind.mainGetPeerOptionSync(id: peerId, key: 'alias');
+    if (alias.isEmpty) {
+      alias = bind.mainGetPeerOptionSync(id: peerId, key: 'peer-display-name');
+    }
     return RxString(getDesktopTabLabel(peerId, alias));
   }
 
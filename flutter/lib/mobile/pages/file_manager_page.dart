@@ -494,7 +494,7 @@ class _FileManagerViewState extends State<FileManagerView> {
                 subtitle: entries[index].isDrive
                     ? null
                     : Text(
-                        "${entries[index].lastModified().toString().replaceAll(".000", "")}   $sizeStr",
+                        "${formatDateTime(entries[index].lastModified())}   $sizeStr",
                         style: TextStyle(fontSize: 12, color: MyTheme.darkGray),
                       ),
                 trailing: entries[index].isDrive
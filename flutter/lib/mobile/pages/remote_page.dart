@@ -158,6 +158,9 @@ class _RemotePageState extends State<RemotePage> with WidgetsBindingObserver {
   void didChangeAppLifecycleState(AppLifecycleState state) {
     if (state == AppLifecycleState.resumed) {
       trySyncClipboard();
+      gFFI.ffiModel.setWindowVisible(true);
+    } else if (state == AppLifecycleState.paused) {
+      gFFI.ffiModel.setWindowVisible(false);
     }
   }
 
@@ -60,6 +60,8 @@ class FileModel {
   late final GetDialogManager getDialogManager;
   SessionID get sessionId => getSessionID();
   late final FileDialogEventLoop evtLoop;
+  // The files dropped on the remote desktop, by their request, until the peer answers.
+  final Map<int, List<Entry>> _droppedFiles = {};
 
   FileModel(this.parent) {
     getSessionID = () => parent.target!.sessionId;
@@ -109,6 +111,73 @@ class FileModel {
     fileFetcher.tryCompleteTask(evt['value'], evt['is_local']);
   }
 
+  /// Send the files dropped on the remote desktop to the folder under the remote cursor,
+  /// the peer tells which one with a `file_drop` event.
+  void dropFiles(List<Entry> entries) {
+    final id = JobController.jobID.next();
+    _droppedFiles[id] = entries;
+    bind.sessionRequestFileDrop(sessionId: sessionId, id: id);
+  }
+
+  void onFileDrop(Map<String, dynamic> evt) {
+    final entries = _droppedFiles.remove(int.tryParse(evt['id'].toString()));
+    if (entries == null) {
+      return;
+    }
+    final String dir = evt['path'] ?? '';
+    if (dir.isEmpty) {
+      final String error = evt['error'] ?? '';
+      showToast(translate(error.isEmpty ? 'Failed' : error));
+      return;
+    }
+    final isWindows =
+        parent.target?.ffiModel.pi.platform == kPeerPlatformWindows;
+    for (var from in entries) {
+      final jobID = jobController.addTransferJob(from, false);
+      bind.sessionSendFiles(
+          sessionId: sessionId,
+          actId: jobID,
+          path: from.path,
+          to: PathUtil.join(dir, from.name, isWindows),
+          fileNum: 0,
+          includeHidden: false,
+          isRemote: false,
+          isDir: from.isDirectory);
+    }
+    showToast('${translate('Sending the files to')} $dir');
+  }
+
+  /// The files a removal job moved to the trash of the peer, and their restore.
+  void onFileTrash(Map<String, dynamic> evt) {
+    final id = int.tryParse(evt['id'].toString()) ?? 0;
+    if (evt['action'] == 'trashed') {
+      jobController.addTrashed(
+          id, {'path': evt['path'], 'trash_path': evt['trash_path']});
+    } else if (evt['action'] == 'restored') {
+      final String error = evt['error'] ?? '';
+      if (error.isEmpty) {
+        jobController.clearTrashed(id);
+        showToast(translate('Restored'));
+      } else {
+        final dm = parent.target?.dialogManager;
+        if (dm != null) {
+          msgBox(sessionId, 'custom-nocancel', 'Error', error, '', dm);
+        }
+      }
+      remoteController.refresh();
+    }
+  }
+
+  /// The jobs the peer had when it restarted, resumed or discarded there.
+  void onTransferJournal(Map<String, dynamic> evt) {
+    final resume = evt['resume'] == true;
+    final List<dynamic> jobs = evt['jobs'] ?? [];
+    for (final job in jobs) {
+      jobController.onInterruptedJob(
+          job['path'], job['remote'], job['is_write'] != true, resume);
+    }
+  }
+
   void receiveEmptyDirs(Map<String, dynamic> evt) {
     fileFetcher.tryCompleteEmptyDirsTask(evt['value'], evt['is_local']);
   }
@@ -545,17 +614,33 @@ class FileController {
     final toPath = otherSideData.directory.path;
     final isWindows = otherSideData.options.isWindows;
     final showHidden = otherSideData.options.showHidden;
+    // Only the files changed are sent in the folder sync mode.
+    final syncMode = isWeb
+        ? ''
+        : await bind.sessionGetPeerOption(
+            sessionId: sessionId, name: kOptionFolderSync);
     for (var from in items.items) {
       final jobID = jobController.addTransferJob(from, isRemoteToLocal);
-      bind.sessionSendFiles(
-          sessionId: sessionId,
-          actId: jobID,
-          path: from.path,
-          to: PathUtil.join(toPath, from.name, isWindows),
-          fileNum: 0,
-          includeHidden: showHidden,
-          isRemote: isRemoteToLocal,
-          isDir: from.isDirectory);
+      if (syncMode.isNotEmpty) {
+        bind.sessionSyncFiles(
+            sessionId: sessionId,
+            actId: jobID,
+            path: from.path,
+            to: PathUtil.join(toPath, from.name, isWindows),
+            includeHidden: showHidden,
+            isRemote: isRemoteToLocal,
+            mode: syncMode);
+      } else {
+        bind.sessionSendFiles(
+            sessionId: sessionId,
+            actId: jobID,
+            path: from.path,
+            to: PathUtil.join(toPath, from.name, isWindows),
+            fileNum: 0,
+            includeHidden: showHidden,
+            isRemote: isRemoteToLocal,
+            isDir: from.isDirectory);
+      }
       debugPrint(
           "path: ${from.path}, toPath: $toPath, to: ${PathUtil.join(toPath, from.name, isWindows)}");
     }
@@ -599,9 +684,15 @@ class FileController {
   }
 
   bool _removeCheckboxRemember = false;
+  bool _removePermanently = false;
+
+  // The removed files go to the trash of the peer, unless [_removePermanently].
+  bool get _canTrash =>
+      !isLocal && (rootState.target?.ffiModel.pi.isSupportFileTrash ?? false);
 
   Future<void> removeAction(SelectedItems items) async {
     _removeCheckboxRemember = false;
+    _removePermanently = false;
     if (items.isLocal != isLocal) {
       debugPrint("Failed to removeFile, wrong files");
       return;
@@ -744,13 +835,28 @@ class FileController {
           crossAxisAlignment: CrossAxisAlignment.start,
           children: [
             Text(content),
-            Text(
-              translate("This is irreversible!"),
-              style: const TextStyle(
-                fontWeight: FontWeight.bold,
-                color: Colors.red,
-              ),
-            ).paddingOnly(top: 20),
+            _canTrash && !_removePermanently
+                ? Text(translate("file-trash-tip")).paddingOnly(top: 20)
+                : Text(
+                    translate("This is irreversible!"),
+                    style: const TextStyle(
+                      fontWeight: FontWeight.bold,
+                      color: Colors.red,
+                    ),
+                  ).paddingOnly(top: 20),
+            _canTrash
+                ? CheckboxListTile(
+                    contentPadding: const EdgeInsets.all(0),
+                    dense: true,
+                    controlAffinity: ListTileControlAffinity.leading,
+                    title: Text(translate("Delete permanently")),
+                    value: _removePermanently,
+                    onChanged: (v) {
+                      if (v == null) return;
+                      setState(() => _removePermanently = v);
+                    },
+                  )
+                : const SizedBox.shrink(),
             showCheckbox
                 ? CheckboxListTile(
                     contentPadding: const EdgeInsets.all(0),
@@ -788,6 +894,7 @@ class FileController {
   }
 
   void sendRemoveFile(String path, int fileNum, int actId) {
+    _sendTrashMode();
     bind.sessionRemoveFile(
         sessionId: sessionId,
         actId: actId,
@@ -798,10 +905,18 @@ class FileController {
 
   Future<void> sendRemoveEmptyDir(String path, int fileNum, int actId) async {
     history.removeWhere((element) => element.contains(path));
+    _sendTrashMode();
     await bind.sessionRemoveAllEmptyDirs(
         sessionId: sessionId, actId: actId, path: path, isRemote: !isLocal);
   }
 
+  void _sendTrashMode() {
+    if (_canTrash) {
+      bind.sessionSetFileTrashPermanent(
+          sessionId: sessionId, permanent: _removePermanently);
+    }
+  }
+
   Future<void> createDirWithRemote(String path, bool isRemote) async {
     bind.sessionCreateDir(
         sessionId: sessionId,
@@ -887,6 +1002,7 @@ class FileController {
 }
 
 const _kOneWayFileTransferError = 'one-way-file-transfer-tip';
+const _kReadOnlyFileTransferError = 'read-only-file-transfer-tip';
 
 class JobController {
   static final JobID jobID = JobID();
@@ -1036,7 +1152,8 @@ class JobController {
       }
       jobTable.refresh();
     }
-    if (err == _kOneWayFileTransferError) {
+    if (err == _kOneWayFileTransferError ||
+        err == _kReadOnlyFileTransferError) {
       if (DateTime.now().millisecondsSinceEpoch - _lastTimeShowMsgbox > 3000) {
         final dm = alogManager;
         if (dm != null) {
@@ -1074,6 +1191,30 @@ class JobController {
     await bind.sessionCancelJob(sessionId: sessionId, actId: id);
   }
 
+  void addTrashed(int id, Map<String, dynamic> item) {
+    final jobIndex = getJob(id);
+    if (jobIndex < 0) return;
+    jobTable[jobIndex].trashed.add(item);
+    jobTable.refresh();
+  }
+
+  void clearTrashed(int id) {
+    final jobIndex = getJob(id);
+    if (jobIndex < 0) return;
+    jobTable[jobIndex].trashed.clear();
+    jobTable.refresh();
+  }
+
+  /// Move back the files the removal job moved to the trash of the peer.
+  Future<void> restoreTrashed(int id) async {
+    final jobIndex = getJob(id);
+    if (jobIndex < 0) return;
+    await bind.sessionRestoreTrashed(
+        sessionId: sessionId,
+        id: id,
+        items: jsonEncode(jobTable[jobIndex].trashed));
+  }
+
   Future<void> loadLastJob(Map<String, dynamic> evt) async {
     debugPrint("load last job: $evt");
     Map<String, dynamic> jobDetail = json.decode(evt['value']);
@@ -1150,6 +1291,24 @@ class JobController {
     }
   }
 
+  /// Resume the paused job with the paths of one the peer had when it restarted, or drop it.
+  void onInterruptedJob(
+      String remote, String to, bool isRemoteToLocal, bool resume) {
+    final jobIndex = jobTable.indexWhere((e) =>
+        e.remote == remote &&
+        e.to == to &&
+        e.isRemoteToLocal == isRemoteToLocal &&
+        e.state == JobState.paused);
+    if (jobIndex < 0) return;
+    final id = jobTable[jobIndex].id;
+    if (resume) {
+      resumeJob(id);
+    } else {
+      jobTable.removeAt(jobIndex);
+      cancelJob(id);
+    }
+  }
+
   void updateFolderFiles(Map<String, dynamic> evt) {
     // ret: "{\"id\":1,\"num_entries\":12,\"total_size\":1264822.0}"
     Map<String, dynamic> info = json.decode(evt['info']);
@@ -1487,6 +1646,8 @@ class JobProgress {
   var showHidden = false;
   var err = "";
   int lastTransferredSize = 0;
+  // The files of a removal job in the trash of the peer, to undo it.
+  var trashed = <Map<String, dynamic>>[];
 
   double get percent =>
       totalSize > 0 ? (finishedSize.toDouble() / totalSize) : 0.0;
@@ -1506,6 +1667,7 @@ class JobProgress {
     remote = "";
     to = "";
     err = "";
+    trashed = [];
   }
 
   String display() {
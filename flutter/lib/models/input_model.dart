@@ -351,12 +351,15 @@ class InputModel {
   int _trackpadSpeed = kDefaultTrackpadSpeed;
   double _trackpadSpeedInner = kDefaultTrackpadSpeed / 100.0;
   var _trackpadScrollUnsent = Offset.zero;
+  // The fractions of pixels of the precise wheel not sent yet.
+  var _wheelScrollUnsent = Offset.zero;
 
   // Mobile relative mouse delta accumulators (for slow/fine movements).
   double _mobileDeltaRemainderX = 0.0;
   double _mobileDeltaRemainderY = 0.0;
 
   var _lastScale = 1.0;
+  var _lastRotation = 0.0;
 
   bool _pointerMovedAfterEnter = false;
   bool _pointerInsideImage = false;
@@ -374,6 +377,14 @@ class InputModel {
   // Disposer for the relativeMouseMode observer (to prevent memory leaks).
   Worker? _relativeMouseModeDisposer;
 
+  // Annotate the screen of the peer, the strokes of the pointer are drawn on its whiteboard
+  // instead of sent as mouse events. See `src/whiteboard`.
+  final annotating = false.obs;
+  // The trail of the pointer drawn on the screen of the peer.
+  final laser = false.obs;
+  int _annotationId = 0;
+  List<List<double>>? _annotationPoints;
+
   bool _queryOtherWindowCoords = false;
   Rect? _windowRect;
   List<RemoteWindowCoords> _remoteWindowCoords = [];
@@ -1099,9 +1110,88 @@ class InputModel {
     _relativeMouse.onWindowFocus();
   }
 
+  // Pen events with the pressure and the tilt, if the peer supports them. See `src/stylus.rs`.
+  bool _handleStylus(PointerEvent e, String phase) {
+    if (e.kind != ui.PointerDeviceKind.stylus &&
+        e.kind != ui.PointerDeviceKind.invertedStylus) {
+      return false;
+    }
+    if (isWeb ||
+        isViewOnly ||
+        !(parent.target?.ffiModel.pi.isSupportStylus ?? false)) {
+      return false;
+    }
+    final pos = handlePointerDevicePos(
+        kPointerEventKindMouse, e.position.dx, e.position.dy, true, '');
+    if (pos == null) return true;
+    final range = e.pressureMax - e.pressureMin;
+    final pressure = range > 0 ? (e.pressure - e.pressureMin) / range : 0.0;
+    // `tilt` is the angle from the normal of the screen, `orientation` the direction,
+    // 0 when pointing up.
+    final tilt = sin(e.tilt);
+    bind.sessionSendStylus(
+        sessionId: sessionId,
+        evt: json.encode({
+          'phase': phase,
+          'x': pos.x.toInt(),
+          'y': pos.y.toInt(),
+          'pressure': pressure,
+          'tilt_x': tilt * sin(e.orientation),
+          'tilt_y': -tilt * cos(e.orientation),
+          'eraser': e.kind == ui.PointerDeviceKind.invertedStylus,
+        }));
+    return true;
+  }
+
+  void sendAnnotation(Map<String, dynamic> annotation) {
+    bind.sessionSendAnnotation(
+        sessionId: sessionId, annotation: json.encode(annotation));
+  }
+
+  void setLaser(bool on) {
+    laser.value = on;
+    sendAnnotation({'t': 'Laser', 'c': on});
+  }
+
+  // A stroke of the pen from the press to the release, sent as a whole each time it grows, the
+  // peer replaces the shape with the same id.
+  bool _handleAnnotation(PointerEvent e, String phase) {
+    if (!annotating.value || isViewOnly) return false;
+    if (phase == 'down') {
+      _annotationId++;
+      _annotationPoints = [];
+    }
+    final points = _annotationPoints;
+    if (points == null) return true;
+    final pos = handlePointerDevicePos(
+        kPointerEventKindMouse, e.position.dx, e.position.dy, false, '');
+    if (pos != null) {
+      if (points.length >= kMaxAnnotationPoints) {
+        // The peer takes a limited number of points in a shape, go on with a new one.
+        _annotationId++;
+        points.removeRange(0, points.length - 1);
+      }
+      points.add([pos.x.toDouble(), pos.y.toDouble()]);
+    }
+    if (points.isNotEmpty) {
+      sendAnnotation({
+        't': 'Shape',
+        'c': {
+          'id': _annotationId,
+          'argb': 0,
+          'width': 3.0,
+          'kind': {'t': 'Pen', 'c': points},
+        },
+      });
+    }
+    if (phase == 'up') _annotationPoints = null;
+    return true;
+  }
+
   void onPointHoverImage(PointerHoverEvent e) {
     _stopFling = true;
     if (isViewOnly && !showMyCursor) return;
+    if (_handleStylus(e, 'hover')) return;
     if (e.kind != ui.PointerDeviceKind.mouse) return;
 
     // May fix https://github.com/rustdesk/rustdesk/issues/13009
@@ -1129,12 +1219,17 @@ class InputModel {
     }
   }
 
+  // The host injects the pinch, the rotation and the scroll as native gestures.
+  bool get _isSupportTouchGesture =>
+      parent.target?.ffiModel.pi.isSupportTouchGesture ?? false;
+
   void onPointerPanZoomStart(PointerPanZoomStartEvent e) {
     _lastScale = 1.0;
+    _lastRotation = 0.0;
     _stopFling = true;
     if (isViewOnly) return;
     if (isViewCamera) return;
-    if (peerPlatform == kPeerPlatformAndroid) {
+    if (peerPlatform == kPeerPlatformAndroid || _isSupportTouchGesture) {
       handlePointerEvent('touch', kMouseEventTypePanStart, e.position);
     }
   }
@@ -1156,6 +1251,20 @@ class InputModel {
         return;
       }
     }
+    if (_isSupportTouchGesture) {
+      // Thousandths of a degree, clockwise.
+      final rotation =
+          ((e.rotation - _lastRotation) * 180 / pi * 1000).toInt();
+      if (rotation != 0) {
+        _lastRotation = e.rotation;
+        bind.sessionSendPointer(
+            sessionId: sessionId,
+            msg: json.encode(
+                PointerEventToRust(kPointerEventKindTouch, 'rotate', rotation)
+                    .toJson()));
+        return;
+      }
+    }
 
     var delta = e.panDelta * _trackpadSpeedInner;
     if (isMacOS && peerPlatform == kPeerPlatformWindows) {
@@ -1181,7 +1290,7 @@ class InputModel {
       }
     }
     if (x != 0 || y != 0) {
-      if (peerPlatform == kPeerPlatformAndroid) {
+      if (peerPlatform == kPeerPlatformAndroid || _isSupportTouchGesture) {
         handlePointerEvent('touch', kMouseEventTypePanUpdate,
             Offset(x.toDouble(), y.toDouble()));
       } else {
@@ -1256,6 +1365,15 @@ class InputModel {
         sessionId: sessionId,
         msg: json.encode(
             PointerEventToRust(kPointerEventKindTouch, 'scale', 0).toJson()));
+    if (_isSupportTouchGesture) {
+      bind.sessionSendPointer(
+          sessionId: sessionId,
+          msg: json.encode(PointerEventToRust(kPointerEventKindTouch, 'rotate', 0)
+              .toJson()));
+      // The host scrolls natively, without the fling of the client.
+      handlePointerEvent('touch', kMouseEventTypePanEnd, e.position);
+      return;
+    }
 
     waitLastFlingDone();
     _stopFling = false;
@@ -1304,6 +1422,8 @@ class InputModel {
     _windowRect = null;
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (_handleStylus(e, 'down')) return;
+    if (_handleAnnotation(e, 'down')) return;
 
     // Track mouse down events for duplicate detection on iOS.
     final nowMs = DateTime.now().millisecondsSinceEpoch;
@@ -1337,6 +1457,8 @@ class InputModel {
     if (isDesktop) _queryOtherWindowCoords = false;
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (_handleStylus(e, 'up')) return;
+    if (_handleAnnotation(e, 'up')) return;
 
     if (_relativeMouse.enabled.value) {
       _relativeMouse.updatePointerRegionTopLeftGlobal(e);
@@ -1358,6 +1480,8 @@ class InputModel {
   void onPointMoveImage(PointerMoveEvent e) {
     if (isViewOnly && !showMyCursor) return;
     if (isViewCamera) return;
+    if (_handleStylus(e, 'move')) return;
+    if (_handleAnnotation(e, 'move')) return;
     if (e.kind != ui.PointerDeviceKind.mouse) return;
 
     if (_relativeMouse.enabled.value) {
@@ -1371,6 +1495,12 @@ class InputModel {
       _queryOtherWindowCoords = false;
     }
     if (isPhysicalMouse.value) {
+      // A button pressed or released while another one is held, e.g. back or
+      // forward, comes as a move. Send it as a button in relative mouse mode.
+      if (_relativeMouse.enabled.value && e.buttons != _lastButtons) {
+        _relativeMouse
+            .sendRelativeMouseButton(_getMouseEvent(e, _kMouseEventMove));
+      }
       if (!_relativeMouse.handleRelativeMouseMove(e.localPosition)) {
         handleMouse(_getMouseEvent(e, _kMouseEventMove), e.position,
             edgeScroll: useEdgeScroll);
@@ -1403,10 +1533,17 @@ class InputModel {
   /// This is because scroll events don't need relative positioning - they represent
   /// scroll deltas that are independent of cursor position. Games and 3D applications
   /// handle scroll events the same way regardless of mouse mode.
+  /// In relative mouse mode the deltas are sent in pixels if the peer supports it,
+  /// instead of one notch per event.
   void onPointerSignalImage(PointerSignalEvent e) {
     if (isViewOnly) return;
     if (isViewCamera) return;
     if (e is PointerScrollEvent) {
+      if (_relativeMouse.enabled.value &&
+          (parent.target?.ffiModel.pi.isSupportPreciseWheel ?? false)) {
+        _sendPreciseWheel(e.scrollDelta);
+        return;
+      }
       var dx = e.scrollDelta.dx.toInt();
       var dy = e.scrollDelta.dy.toInt();
       if (dx > 0) {
@@ -1425,6 +1562,17 @@ class InputModel {
     }
   }
 
+  void _sendPreciseWheel(Offset delta) {
+    _wheelScrollUnsent -= delta;
+    final x = _wheelScrollUnsent.dx.truncate();
+    final y = _wheelScrollUnsent.dy.truncate();
+    if (x == 0 && y == 0) return;
+    _wheelScrollUnsent -= Offset(x.toDouble(), y.toDouble());
+    bind.sessionSendMouse(
+        sessionId: sessionId,
+        msg: '{"type": "wheel_precise", "x": "$x", "y": "$y"}');
+  }
+
   void refreshMousePos() => handleMouse({
         'buttons': 0,
         'type': _kMouseEventMove,
@@ -110,6 +110,7 @@ class CachedPeerData {
 class FfiModel with ChangeNotifier {
   CachedPeerData cachedPeerData = CachedPeerData();
   PeerInfo _pi = PeerInfo();
+  bool _windowVisible = true;
   Rect? _rect;
 
   var _inputBlocked = false;
@@ -129,6 +130,10 @@ class FfiModel with ChangeNotifier {
   RxBool waitForImageDialogShow = true.obs;
   Timer? waitForImageTimer;
   RxBool waitForFirstImage = true.obs;
+  // The displays of the peer which sleep, they send no frames.
+  final RxSet<int> asleepDisplays = <int>{}.obs;
+  // The displays the privacy mode blanks, all of them if empty.
+  final RxList<int> privacyDisplays = <int>[].obs;
   bool isRefreshing = false;
 
   Timer? timerScreenshot;
@@ -425,6 +430,21 @@ class FfiModel with ChangeNotifier {
         parent.target?.chatModel.onVoiceCallIncoming();
       } else if (name == 'update_voice_call_state') {
         parent.target?.serverModel.updateVoiceCallState(evt);
+      } else if (name == 'update_client_record_status') {
+        parent.target?.serverModel.updateClientRecordStatus(evt);
+      } else if (name == 'update_client_tunnels') {
+        parent.target?.serverModel.updateClientTunnels(evt);
+      } else if (name == 'cm_permission_request') {
+        parent.target?.serverModel.showPermissionRequestDialog(evt);
+      } else if (name == 'cm_interrupted_transfer_jobs') {
+        parent.target?.serverModel.showInterruptedTransferJobsDialog(evt);
+      } else if (name == 'cm_file_paste_progress') {
+        final connId = int.tryParse(evt['id'] ?? '') ?? 0;
+        showFilePasteProgress(
+            parent.target!.dialogManager,
+            'file-paste-$connId',
+            evt,
+            () => bind.cmCancelFilePaste(connId: connId));
       } else if (name == 'fingerprint') {
         FingerprintState.find(peerId).value = evt['fingerprint'] ?? '';
       } else if (name == 'plugin_manager') {
@@ -473,6 +493,36 @@ class FfiModel with ChangeNotifier {
         _handlePrinterRequest(evt, sessionId, peerId);
       } else if (name == 'screenshot') {
         _handleScreenshot(evt, sessionId, peerId);
+      } else if (name == 'close_workspace') {
+        closeConnection(id: peerId);
+      } else if (name == 'audio_devices') {
+        showAudioDevicesDialog(evt, sessionId, parent.target!.dialogManager);
+      } else if (name == 'privacy_displays') {
+        _handlePrivacyDisplays(evt);
+      } else if (name == 'file_drop') {
+        parent.target?.fileModel.onFileDrop(evt);
+      } else if (name == 'file_trash') {
+        parent.target?.fileModel.onFileTrash(evt);
+      } else if (name == 'transfer_journal') {
+        parent.target?.fileModel.onTransferJournal(evt);
+      } else if (name == 'config_edit') {
+        showConfigEditDialog(evt, sessionId, parent.target!.dialogManager);
+      } else if (name == 'monitor_modes') {
+        showMonitorModesDialog(evt, sessionId, parent.target!.dialogManager);
+      } else if (name == 'camera_controls') {
+        showCameraControlsDialog(evt, sessionId, parent.target!.dialogManager);
+      } else if (name == 'keyboard_layouts') {
+        showKeyboardLayoutsDialog(evt, sessionId, parent.target!.dialogManager);
+      } else if (name == 'display_power') {
+        _handleDisplayPower(evt);
+      } else if (name == 'clipboard_progress') {
+        showToast('${translate('Receiving clipboard')} ${evt['percent']}%');
+      } else if (name == 'file_paste_progress') {
+        showFilePasteProgress(
+            parent.target!.dialogManager,
+            '$sessionId-file-paste',
+            evt,
+            () => bind.sessionCancelFilePaste(sessionId: sessionId));
       } else if (name == 'exit_relative_mouse_mode') {
         // Handle exit shortcut from rdev grab loop (Ctrl+Alt on Win/Linux, Cmd+G on macOS)
         parent.target?.inputModel.exitRelativeMouseModeWithKeyRelease();
@@ -731,6 +781,32 @@ class FfiModel with ChangeNotifier {
         'success', 'Successful', kMsgboxTextWaitingForImage);
   }
 
+  _handlePrivacyDisplays(Map<String, dynamic> evt) {
+    privacyDisplays.value = List<int>.from(evt['displays'] ?? []);
+    final String error = evt['error'] ?? '';
+    if (error.isNotEmpty) {
+      showToast(translate(error));
+    }
+  }
+
+  _handleDisplayPower(Map<String, dynamic> evt) {
+    final display = evt['display'] as int;
+    if (evt['asleep'] == true) {
+      asleepDisplays.add(display);
+    } else {
+      asleepDisplays.remove(display);
+    }
+  }
+
+  /// Whether the displays shown are all asleep.
+  bool get isDisplayAsleep {
+    if (asleepDisplays.isEmpty) return false;
+    if (pi.currentDisplay == kAllDisplayValue) {
+      return asleepDisplays.length >= pi.displays.length;
+    }
+    return asleepDisplays.contains(pi.currentDisplay);
+  }
+
   _handleSyncPeerOption(Map<String, dynamic> evt, String peer) {
     final k = evt['k'];
     final v = evt['v'];
@@ -908,6 +984,8 @@ class FfiModel with ChangeNotifier {
       wrongPasswordDialog(sessionId, dialogManager, type, title, text);
     } else if (type == 'input-2fa') {
       enter2FaDialog(sessionId, dialogManager);
+    } else if (type == 'auth-provider') {
+      authProviderDialog(sessionId, dialogManager, text);
     } else if (type == 'input-password') {
       enterPasswordDialog(sessionId, dialogManager);
     } else if (type == 'session-login' || type == 'session-re-login') {
@@ -934,6 +1012,8 @@ class FfiModel with ChangeNotifier {
       showElevationError(sessionId, type, title, text, dialogManager);
     } else if (type == 'relay-hint' || type == 'relay-hint2') {
       showRelayHintDialog(sessionId, type, title, text, dialogManager, peerId);
+    } else if (type == 'host-busy') {
+      showHostBusyDialog(sessionId, type, title, text, dialogManager, peerId);
     } else if (text == kMsgboxTextWaitingForImage) {
       showConnectedWaitingForImage(dialogManager, sessionId, type, title, text);
     } else if (title == 'Privacy mode') {
@@ -1108,6 +1188,39 @@ class FfiModel with ChangeNotifier {
     });
   }
 
+  void showHostBusyDialog(
+      SessionID sessionId,
+      String type,
+      String title,
+      String text,
+      OverlayDialogManager dialogManager,
+      String peerId) {
+    dialogManager.show(tag: '$sessionId-$type', (setState, close, context) {
+      onClose() {
+        closeConnection();
+        close();
+      }
+
+      onNotify() async {
+        final err = await bind.mainAddHostWait(id: peerId);
+        if (err.isNotEmpty) {
+          showToast(translate(err));
+        }
+        onClose();
+      }
+
+      return CustomAlertDialog(
+        title: null,
+        content: msgboxContent(type, title, text),
+        actions: [
+          dialogButton('Close', onPressed: onClose, isOutline: true),
+          dialogButton('Notify me when available', onPressed: onNotify),
+        ],
+        onCancel: onClose,
+      );
+    });
+  }
+
   void showConnectedWaitingForImage(OverlayDialogManager dialogManager,
       SessionID sessionId, String type, String title, String text) {
     onClose() {
@@ -1268,6 +1381,8 @@ class FfiModel with ChangeNotifier {
   /// Handle the peer info event based on [evt].
   handlePeerInfo(Map<String, dynamic> evt, String peerId, bool isCache) async {
     parent.target?.chatModel.voiceCallStatus.value = VoiceCallStatus.notStarted;
+    // Sent again by the peer for the displays still asleep.
+    asleepDisplays.clear();
 
     _queryAuditGuid(peerId);
 
@@ -1380,7 +1495,10 @@ class FfiModel with ChangeNotifier {
       setShowMyCursor(bind.sessionGetToggleOptionSync(
           sessionId: sessionId, arg: kOptionToggleShowMyCursor));
     }
-    if (connType == ConnType.defaultConn || connType == ConnType.viewCamera) {
+    // The file transfers read the support of the extensions from them too.
+    if (connType == ConnType.defaultConn ||
+        connType == ConnType.viewCamera ||
+        connType == ConnType.fileTransfer) {
       final platformAdditions = evt['platform_additions'];
       if (platformAdditions != null && platformAdditions != '') {
         try {
@@ -1389,6 +1507,10 @@ class FfiModel with ChangeNotifier {
           debugPrint('Failed to decode platformAdditions $e');
         }
       }
+      // The peer of a reconnection does not know the window is hidden.
+      if (!_windowVisible) {
+        _sendWindowVisible();
+      }
     }
 
     _pi.isSet.value = true;
@@ -1722,6 +1844,21 @@ class FfiModel with ChangeNotifier {
     }
   }
 
+  /// Tell the peer whether the window of the session is seen, it lowers the
+  /// frame rate for a hidden one.
+  void setWindowVisible(bool visible) {
+    if (_windowVisible == visible) return;
+    _windowVisible = visible;
+    _sendWindowVisible();
+  }
+
+  void _sendWindowVisible() {
+    if (_pi.isSupportWindowVisibility) {
+      bind.sessionSetWindowVisible(
+          sessionId: sessionId, visible: _windowVisible);
+    }
+  }
+
   void setViewOnly(String id, bool value) {
     if (versionCmp(_pi.version, '1.2.0') < 0) return;
     // tmp fix for https://github.com/rustdesk/rustdesk/pull/3706#issuecomment-1481242389
@@ -2283,6 +2420,7 @@ class CanvasModel with ChangeNotifier {
       }
     }
     _resetCanvasOffset(displayWidth, displayHeight);
+    _updateFrameTransform();
     final overflow = _x < 0 || y < 0;
     if (_imageOverflow.value != overflow) {
       _imageOverflow.value = overflow;
@@ -2304,6 +2442,26 @@ class CanvasModel with ChangeNotifier {
     }
   }
 
+  // Scale the frames of the textures to the size shown in the core, so smaller frames are copied
+  // to them. Only downscaled, the UI stretches the textures anyway.
+  void _updateFrameTransform() {
+    final ffi = parent.target;
+    if (ffi == null || isWeb) return;
+    final pi = ffi.ffiModel.pi;
+    if (!ffi.imageModel.useTextureRender && !pi.forceTextureRender) return;
+    final scale = min(_scale * _devicePixelRatio, 1.0);
+    final displays = pi.currentDisplay == kAllDisplayValue
+        ? List.generate(pi.displays.length, (i) => i)
+        : [pi.currentDisplay];
+    for (final display in displays) {
+      final err = bind.sessionSetFrameTransform(
+          sessionId: sessionId, display: display, scale: scale, rotation: 0);
+      if (err.isNotEmpty) {
+        debugPrint('Failed to set the frame transform of $display: $err');
+      }
+    }
+  }
+
   tryUpdateScrollStyle(Duration duration, String? style) async {
     if (_scrollStyle == ScrollStyle.scrollauto) return;
     style ??= await bind.sessionGetViewStyle(sessionId: sessionId);
@@ -2596,6 +2754,7 @@ class CanvasModel with ChangeNotifier {
     if (isMobile) {
       isMobileCanvasChanged = true;
     }
+    _updateFrameTransform();
     notifyListeners();
   }
 
@@ -2607,6 +2766,7 @@ class CanvasModel with ChangeNotifier {
       _scale = 1.0 / _devicePixelRatio;
     }
     _resetCanvasOffset(getDisplayWidth(), getDisplayHeight());
+    _updateFrameTransform();
     bind.sessionSetViewStyle(sessionId: sessionId, value: _lastViewStyle.style);
     notifyListeners();
   }
@@ -3407,6 +3567,7 @@ class QualityMonitorData {
   String? targetBitrate;
   String? codecFormat;
   String? chroma;
+  String? decodeDropped;
 }
 
 class QualityMonitorModel with ChangeNotifier {
@@ -3469,6 +3630,10 @@ class QualityMonitorModel with ChangeNotifier {
       if (evt.containsKey('chroma') && (evt['chroma'] as String).isNotEmpty) {
         _data.chroma = evt['chroma'];
       }
+      if (evt.containsKey('decode_dropped') &&
+          (evt['decode_dropped'] as String).isNotEmpty) {
+        _data.decodeDropped = evt['decode_dropped'];
+      }
       notifyListeners();
     } catch (e) {
       //
@@ -3982,6 +4147,58 @@ class PeerInfo with ChangeNotifier {
 
   bool get isWayland => platformAdditions[kPlatformAdditionsIsWayland] == true;
   bool get isHeadless => platformAdditions[kPlatformAdditionsHeadless] == true;
+  bool get isDoNotRecord =>
+      platformAdditions[kPlatformAdditionsDoNotRecord] == true;
+  bool get isHostRecording =>
+      platformAdditions[kPlatformAdditionsHostRecording] == true;
+  bool get isSupportTalkBack =>
+      platformAdditions[kPlatformAdditionsSupportTalkBack] == true;
+  bool get isSupportAudioDevices =>
+      platformAdditions[kPlatformAdditionsSupportAudioDevices] == true;
+  bool get isSupportPreciseWheel =>
+      platformAdditions[kPlatformAdditionsSupportPreciseWheel] == true;
+  bool get isSupportBlockInput =>
+      platformAdditions[kPlatformAdditionsSupportBlockInput] == true;
+  bool get isSupportKeyboardLayout =>
+      platformAdditions.containsKey(kPlatformAdditionsKeyboardLayout);
+  bool get isSupportConfigEdit =>
+      platformAdditions[kPlatformAdditionsConfigEdit] == true;
+  bool get isSupportMonitorModes =>
+      platformAdditions[kPlatformAdditionsSupportMonitorModes] == true;
+  bool get isSupportStylus =>
+      platformAdditions[kPlatformAdditionsSupportStylus] == true;
+  bool get isSupportCameraControls =>
+      platformAdditions[kPlatformAdditionsSupportCameraControls] == true;
+  bool get isSupportGamepad =>
+      platformAdditions[kPlatformAdditionsSupportGamepad] == true;
+  bool get isSupportAnnotation =>
+      platformAdditions[kPlatformAdditionsSupportAnnotation] == true;
+  bool get isSupportTouchGesture =>
+      platformAdditions[kPlatformAdditionsSupportTouchGesture] == true;
+  bool get isSupportDisplayPower =>
+      platformAdditions[kPlatformAdditionsSupportDisplayPower] == true;
+  bool get isSupportSpecialKeys =>
+      platformAdditions[kPlatformAdditionsSupportSpecialKeys] == true;
+  bool get isSupportMouseCurve =>
+      platformAdditions[kPlatformAdditionsSupportMouseCurve] == true;
+  bool get isSupportScrollTransform =>
+      platformAdditions[kPlatformAdditionsSupportScrollTransform] == true;
+  bool get isSupportTransferThrottle =>
+      platformAdditions[kPlatformAdditionsSupportTransferThrottle] == true;
+  bool get isSupportPrivacyDisplays =>
+      platformAdditions[kPlatformAdditionsSupportPrivacyDisplays] == true;
+  bool get isSupportWakePolicy =>
+      platformAdditions[kPlatformAdditionsSupportWakePolicy] == true;
+  bool get isSupportWindowVisibility =>
+      platformAdditions[kPlatformAdditionsSupportWindowVisibility] == true;
+  bool get isSupportFolderSync =>
+      platformAdditions[kPlatformAdditionsSupportFolderSync] == true;
+  bool get isSupportFileDrop =>
+      platformAdditions[kPlatformAdditionsSupportFileDrop] == true;
+  bool get isSupportFileTrash =>
+      platformAdditions[kPlatformAdditionsSupportFileTrash] == true;
+  bool get isSupportPermissionRequest =>
+      platformAdditions[kPlatformAdditionsSupportPermissionRequest] == true;
   bool get isInstalled =>
       platform != kPeerPlatformWindows ||
       platformAdditions[kPlatformAdditionsIsInstalled] == true;
@@ -1,11 +1,13 @@
 import 'dart:convert';
 import 'dart:ffi';
 import 'dart:io';
+import 'dart:ui' as ui;
 
 import 'package:device_info_plus/device_info_plus.dart';
 import 'package:external_path/external_path.dart';
 import 'package:ffi/ffi.dart';
 import 'package:flutter/foundation.dart';
+import 'package:flutter/semantics.dart';
 import 'package:flutter/services.dart';
 import 'package:flutter_hbb/consts.dart';
 import 'package:flutter_hbb/main.dart';
@@ -151,6 +153,9 @@ class PlatformFFI {
         _ffiBind.mainStartIpcUrlServer();
       }
       _startListenEvent(_ffiBind); // global event
+      if (_appType == kAppTypeMain) {
+        _startListenA11yEvent(_ffiBind);
+      }
       try {
         if (isAndroid) {
           // only support for android
@@ -260,6 +265,25 @@ class PlatformFFI {
     });
   }
 
+  /// Announce semantic session events to screen readers.
+  ///
+  /// Only the main window listens, to avoid announcing an event once per window.
+  void _startListenA11yEvent(RustdeskImpl rustdeskImpl) {
+    rustdeskImpl
+        .startAccessibilityEventStream(appType: _appType)
+        .listen((message) {
+      try {
+        final event = json.decode(message);
+        final text = event['text'];
+        if (text is String && text.isNotEmpty) {
+          SemanticsService.announce(text, ui.TextDirection.ltr);
+        }
+      } catch (e) {
+        debugPrint('a11y event json.decode fail(): $e');
+      }
+    });
+  }
+
   void setEventCallback(StreamEventHandler fun) async {
     _eventCallback = fun;
   }
@@ -22,6 +22,12 @@ class Peer {
   String device_group_name;
   String note;
   bool? sameServer;
+  // Published by the host: available, busy or maintenance.
+  String availability = '';
+  String availabilityNote = '';
+  // Published by the host, the avatar is a PNG in base64.
+  String displayName = '';
+  String avatar = '';
 
   String getId() {
     if (alias != '') {
@@ -45,7 +51,15 @@ class Peer {
         loginName = json['loginName'] ?? '',
         device_group_name = json['device_group_name'] ?? '',
         note = json['note'] is String ? json['note'] : '',
-        sameServer = json['same_server'];
+        sameServer = json['same_server'],
+        availability = json['availability'] is Map
+            ? (json['availability']['status'] ?? '')
+            : '',
+        availabilityNote = json['availability'] is Map
+            ? (json['availability']['note'] ?? '')
+            : '',
+        displayName = json['display_name'] ?? '',
+        avatar = json['avatar'] ?? '';
 
   Map<String, dynamic> toJson() {
     return <String, dynamic>{
@@ -161,7 +175,12 @@ class Peer {
             loginName: other.loginName,
             device_group_name: other.device_group_name,
             note: other.note,
-            sameServer: other.sameServer);
+            sameServer: other.sameServer) {
+    availability = other.availability;
+    availabilityNote = other.availabilityNote;
+    displayName = other.displayName;
+    avatar = other.avatar;
+  }
 }
 
 enum UpdateEvent { online, load }
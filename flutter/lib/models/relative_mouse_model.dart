@@ -397,8 +397,11 @@ class RelativeMouseModel {
       }
 
       if (!isSupported) {
-        // Check server version support before enabling.
-        showToast(translate('rel-mouse-not-supported-peer-tip'));
+        // Check server version support before enabling, tell why if the peer is the cause.
+        if (!bind.sessionNotifyUnavailableFeature(
+            sessionId: sessionId, name: 'relative_mouse')) {
+          showToast(translate('rel-mouse-not-supported-peer-tip'));
+        }
         return false;
       }
     }
@@ -23,6 +23,17 @@ const kUseTemporaryPassword = "use-temporary-password";
 const kUsePermanentPassword = "use-permanent-password";
 const kUseBothPasswords = "use-both-passwords";
 
+// The permissions which the peer can request, with their labels.
+const permissionRequestNames = {
+  'keyboard': 'Enable keyboard/mouse',
+  'clipboard': 'Enable clipboard',
+  'audio': 'Enable audio',
+  'file': 'Enable file copy and paste',
+  'restart': 'Enable remote restart',
+  'recording': 'Enable recording session',
+  'block_input': 'Enable blocking user input',
+};
+
 class ServerModel with ChangeNotifier {
   bool _isStart = false; // Android MainService status
   bool _mediaOk = false;
@@ -784,6 +795,143 @@ class ServerModel with ChangeNotifier {
     }
   }
 
+  void updateClientRecordStatus(Map<String, dynamic> evt) {
+    try {
+      final client = Client.fromJson(jsonDecode(evt["client"]));
+      final index = _clients.indexWhere((element) => element.id == client.id);
+      if (index != -1) {
+        _clients[index].inRecording = client.inRecording;
+        notifyListeners();
+      }
+    } catch (e) {
+      debugPrint("updateClientRecordStatus failed: $e");
+    }
+  }
+
+  void updateClientTunnels(Map<String, dynamic> evt) {
+    try {
+      final client = Client.fromJson(jsonDecode(evt["client"]));
+      final index = _clients.indexWhere((element) => element.id == client.id);
+      if (index != -1) {
+        _clients[index].tunnels = client.tunnels;
+        notifyListeners();
+      }
+    } catch (e) {
+      debugPrint("updateClientTunnels failed: $e");
+    }
+  }
+
+  // The peer asks for a permission of this session, e.g. "file".
+  void showPermissionRequestDialog(Map<String, dynamic> evt) {
+    final id = int.tryParse(evt['id'] ?? '');
+    final String name = evt['name'] ?? '';
+    final index = _clients.indexWhere((element) => element.id == id);
+    if (index == -1) return;
+    final client = _clients[index];
+    Future.delayed(Duration.zero, () {
+      windowOnTop(null);
+    });
+    answer(bool granted) {
+      bind.cmAnswerPermissionRequest(
+          connId: client.id, name: name, granted: granted);
+      if (granted) {
+        switch (name) {
+          case 'keyboard':
+            client.keyboard = true;
+            break;
+          case 'clipboard':
+            client.clipboard = true;
+            break;
+          case 'audio':
+            client.audio = true;
+            break;
+          case 'file':
+            client.file = true;
+            break;
+          case 'restart':
+            client.restart = true;
+            break;
+          case 'recording':
+            client.recording = true;
+            break;
+          case 'block_input':
+            client.blockInput = true;
+            break;
+        }
+        notifyListeners();
+      }
+    }
+
+    parent.target?.dialogManager.show((setState, close, context) {
+      cancel() {
+        answer(false);
+        close();
+      }
+
+      submit() {
+        answer(true);
+        close();
+      }
+
+      return CustomAlertDialog(
+        title: Text(translate('Permission request')),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            ClientInfo(client),
+            Text(
+                '${translate('permission-request-tip')}: ${translate(permissionRequestNames[name] ?? name)}'),
+          ],
+        ),
+        actions: [
+          dialogButton("Deny", onPressed: cancel, isOutline: true),
+          dialogButton("Allow", onPressed: submit),
+        ],
+        onSubmit: submit,
+        onCancel: cancel,
+      );
+    }, tag: 'permission-request-${client.id}-$name');
+  }
+
+  /// The file jobs of the peer the restart of the service interrupted, to resume or discard.
+  void showInterruptedTransferJobsDialog(Map<String, dynamic> evt) {
+    final id = int.tryParse(evt['id'] ?? '');
+    final List<dynamic> jobs = jsonDecode(evt['jobs'] ?? '[]');
+    final index = _clients.indexWhere((element) => element.id == id);
+    if (index == -1 || jobs.isEmpty) return;
+    final client = _clients[index];
+    parent.target?.dialogManager.show((setState, close, context) {
+      answer(bool resume) {
+        bind.cmResumeTransferJobs(connId: client.id, resume: resume);
+        close();
+      }
+
+      return CustomAlertDialog(
+        title: Text(translate('Interrupted file transfers')),
+        content: Column(
+          mainAxisSize: MainAxisSize.min,
+          crossAxisAlignment: CrossAxisAlignment.start,
+          children: [
+            ClientInfo(client),
+            Text(translate('interrupted-file-transfers-tip')),
+            ...jobs.map((job) => Text(
+                '${job['is_write'] == true ? '↓' : '↑'} ${job['path']} '
+                '(${readableFileSize((job['transferred'] ?? 0).toDouble())} / '
+                '${readableFileSize((job['total_size'] ?? 0).toDouble())})')),
+          ],
+        ),
+        actions: [
+          dialogButton("Discard",
+              onPressed: () => answer(false), isOutline: true),
+          dialogButton("Resume", onPressed: () => answer(true)),
+        ],
+        onSubmit: () => answer(true),
+        onCancel: () => answer(false),
+      );
+    }, tag: 'interrupted-transfer-jobs-${client.id}');
+  }
+
   void androidUpdatekeepScreenOn() async {
     if (!isAndroid) return;
     var floatingWindowDisabled =
@@ -832,6 +980,10 @@ class Client {
   bool fromSwitch = false;
   bool inVoiceCall = false;
   bool incomingVoiceCall = false;
+  bool inRecording = false;
+  bool viewOnly = false;
+  // The targets of the tunnels open in the session.
+  List<String> tunnels = [];
 
   RxInt unreadChatMessageCount = 0.obs;
 
@@ -859,6 +1011,11 @@ class Client {
     fromSwitch = json['from_switch'];
     inVoiceCall = json['in_voice_call'];
     incomingVoiceCall = json['incoming_voice_call'];
+    inRecording = json['in_recording'] ?? false;
+    viewOnly = json['view_only'] ?? false;
+    tunnels = ((json['tunnels'] ?? []) as List)
+        .map((t) => (t as List)[1].toString())
+        .toList();
   }
 
   Map<String, dynamic> toJson() {
@@ -882,6 +1039,8 @@ class Client {
     data['from_switch'] = fromSwitch;
     data['in_voice_call'] = inVoiceCall;
     data['incoming_voice_call'] = incomingVoiceCall;
+    data['in_recording'] = inRecording;
+    data['view_only'] = viewOnly;
     return data;
   }
 
@@ -179,6 +179,11 @@ class RustdeskImpl {
     return Future(() => js.context.callMethod('setByName', ['refresh']));
   }
 
+  Future<void> sessionRequestKeyFrame(
+      {required UuidValue sessionId, required int display, dynamic hint}) {
+    return Future.value();
+  }
+
   Future<void> sessionRecordScreen(
       {required UuidValue sessionId, required bool start, dynamic hint}) {
     throw UnimplementedError("sessionRecordScreen");
@@ -481,6 +486,11 @@ class RustdeskImpl {
         () => js.context.callMethod('setByName', ['input_string', value]));
   }
 
+  Future<void> sessionCancelFilePaste(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionCancelFilePaste");
+  }
+
   Future<void> sessionSendChat(
       {required UuidValue sessionId, required String text, dynamic hint}) {
     return Future(
@@ -545,6 +555,18 @@ class RustdeskImpl {
         ]));
   }
 
+  Future<void> sessionSyncFiles(
+      {required UuidValue sessionId,
+      required int actId,
+      required String path,
+      required String to,
+      required bool includeHidden,
+      required bool isRemote,
+      required String mode,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSyncFiles");
+  }
+
   Future<void> sessionSetConfirmOverrideFile(
       {required UuidValue sessionId,
       required int actId,
@@ -1406,6 +1428,28 @@ class RustdeskImpl {
     throw UnimplementedError("cmSwitchPermission");
   }
 
+  Future<void> cmAnswerPermissionRequest(
+      {required int connId,
+      required String name,
+      required bool granted,
+      dynamic hint}) {
+    throw UnimplementedError("cmAnswerPermissionRequest");
+  }
+
+  Future<void> cmSetViewOnly(
+      {required int connId, required bool viewOnly, dynamic hint}) {
+    throw UnimplementedError("cmSetViewOnly");
+  }
+
+  Future<void> cmResumeTransferJobs(
+      {required int connId, required bool resume, dynamic hint}) {
+    throw UnimplementedError("cmResumeTransferJobs");
+  }
+
+  Future<void> cmCancelFilePaste({required int connId, dynamic hint}) {
+    throw UnimplementedError("cmCancelFilePaste");
+  }
+
   bool cmCanElevate({dynamic hint}) {
     throw UnimplementedError("cmCanElevate");
   }
@@ -1426,6 +1470,18 @@ class RustdeskImpl {
     return Future(() => js.context.callMethod('getByName', ['build_date']));
   }
 
+  String mainFormatBytes({required double size, dynamic hint}) {
+    throw UnimplementedError("mainFormatBytes");
+  }
+
+  String mainFormatDuration({required int seconds, dynamic hint}) {
+    throw UnimplementedError("mainFormatDuration");
+  }
+
+  String mainFormatDateTime({required int ms, dynamic hint}) {
+    throw UnimplementedError("mainFormatDateTime");
+  }
+
   String translate(
       {required String name, required String locale, dynamic hint}) {
     return js.context.callMethod('getByName', [
@@ -1448,6 +1504,15 @@ class RustdeskImpl {
       required int ptr,
       dynamic hint}) {}
 
+  String sessionSetFrameTransform(
+      {required UuidValue sessionId,
+      required int display,
+      required double scale,
+      required int rotation,
+      dynamic hint}) {
+    return '';
+  }
+
   void sessionRegisterGpuTexture(
       {required UuidValue sessionId,
       required int display,
@@ -1928,6 +1993,221 @@ class RustdeskImpl {
     throw UnimplementedError("sessionTakeScreenshot");
   }
 
+  Future<void> sessionTalkBack(
+      {required UuidValue sessionId, required bool on, dynamic hint}) {
+    throw UnimplementedError("sessionTalkBack");
+  }
+
+  String sessionCreateWorkspace({required UuidValue sessionId, dynamic hint}) {
+    return '';
+  }
+
+  String mainGetWorkspaces({dynamic hint}) {
+    return '[]';
+  }
+
+  String mainGetSessionTiles({dynamic hint}) {
+    return '[]';
+  }
+
+  String mainGetQosSessions({required String id, dynamic hint}) {
+    return '[]';
+  }
+
+  Future<String> mainGetQosHistory(
+      {required String id, required int start, dynamic hint}) {
+    return Future.value('[]');
+  }
+
+  Future<void> sessionSetTile(
+      {required UuidValue sessionId,
+      required int width,
+      required int height,
+      required bool visible,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetTile");
+  }
+
+  Future<void> mainCloseWorkspace({required String id, dynamic hint}) {
+    throw UnimplementedError("mainCloseWorkspace");
+  }
+
+  Future<void> sessionSendStylus(
+      {required UuidValue sessionId, required String evt, dynamic hint}) {
+    throw UnimplementedError("sessionSendStylus");
+  }
+
+  Future<void> sessionGetAudioDevices(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionGetAudioDevices");
+  }
+
+  Future<void> sessionSelectAudioDevice(
+      {required UuidValue sessionId, required String device, dynamic hint}) {
+    throw UnimplementedError("sessionSelectAudioDevice");
+  }
+
+  Future<void> sessionGetKeyboardLayouts(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionGetKeyboardLayouts");
+  }
+
+  Future<void> sessionSwitchKeyboardLayout(
+      {required UuidValue sessionId, required String layout, dynamic hint}) {
+    throw UnimplementedError("sessionSwitchKeyboardLayout");
+  }
+
+  Future<void> sessionGetMonitorModes(
+      {required UuidValue sessionId, dynamic hint}) {
+    throw UnimplementedError("sessionGetMonitorModes");
+  }
+
+  Future<void> sessionSetMonitorModes(
+      {required UuidValue sessionId, required String modes, dynamic hint}) {
+    throw UnimplementedError("sessionSetMonitorModes");
+  }
+
+  Future<void> sessionConfigEdit(
+      {required UuidValue sessionId, required String request, dynamic hint}) {
+    throw UnimplementedError("sessionConfigEdit");
+  }
+
+  String mainConfigEditDiff(
+      {required String oldContent,
+      required String newContent,
+      dynamic hint}) {
+    throw UnimplementedError("mainConfigEditDiff");
+  }
+
+  Future<void> sessionWakeDisplay(
+      {required UuidValue sessionId, required int display, dynamic hint}) {
+    throw UnimplementedError("sessionWakeDisplay");
+  }
+
+  Future<void> sessionSendAnnotation(
+      {required UuidValue sessionId,
+      required String annotation,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSendAnnotation");
+  }
+
+  Future<void> sessionSendSpecialKey(
+      {required UuidValue sessionId, required String key, dynamic hint}) {
+    throw UnimplementedError("sessionSendSpecialKey");
+  }
+
+  Future<void> sessionSetMouseCurve(
+      {required UuidValue sessionId, required String curve, dynamic hint}) {
+    throw UnimplementedError("sessionSetMouseCurve");
+  }
+
+  Future<void> sessionSetScrollTransform(
+      {required UuidValue sessionId, required String transform, dynamic hint}) {
+    throw UnimplementedError("sessionSetScrollTransform");
+  }
+
+  Future<void> sessionSetWakePolicy(
+      {required UuidValue sessionId, required String policy, dynamic hint}) {
+    throw UnimplementedError("sessionSetWakePolicy");
+  }
+
+  Future<void> sessionSetWindowVisible(
+      {required UuidValue sessionId, required bool visible, dynamic hint}) {
+    throw UnimplementedError("sessionSetWindowVisible");
+  }
+
+  String sessionGetUnavailableFeatures(
+      {required UuidValue sessionId, dynamic hint}) {
+    return '[]';
+  }
+
+  bool sessionNotifyUnavailableFeature(
+      {required UuidValue sessionId, required String name, dynamic hint}) {
+    return false;
+  }
+
+  Future<void> sessionSetPrivacyDisplays(
+      {required UuidValue sessionId,
+      required Int32List displays,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetPrivacyDisplays");
+  }
+
+  Future<void> sessionRequestFileDrop(
+      {required UuidValue sessionId, required int id, dynamic hint}) {
+    throw UnimplementedError("sessionRequestFileDrop");
+  }
+
+  Future<void> sessionSetFileTrashPermanent(
+      {required UuidValue sessionId, required bool permanent, dynamic hint}) {
+    throw UnimplementedError("sessionSetFileTrashPermanent");
+  }
+
+  Future<void> sessionRestoreTrashed(
+      {required UuidValue sessionId,
+      required int id,
+      required String items,
+      dynamic hint}) {
+    throw UnimplementedError("sessionRestoreTrashed");
+  }
+
+  Future<void> sessionSetTransferThrottle(
+      {required UuidValue sessionId, required String settings, dynamic hint}) {
+    throw UnimplementedError("sessionSetTransferThrottle");
+  }
+
+  String sessionGetClipboardHistory(
+      {required UuidValue sessionId, dynamic hint}) {
+    return '';
+  }
+
+  Future<void> sessionSendClipboardHistory(
+      {required UuidValue sessionId, required int id, dynamic hint}) {
+    throw UnimplementedError("sessionSendClipboardHistory");
+  }
+
+  Future<void> sessionRequestPermission(
+      {required UuidValue sessionId,
+      required String permission,
+      dynamic hint}) {
+    throw UnimplementedError("sessionRequestPermission");
+  }
+
+  Future<void> sessionSendAuthProviderAnswer(
+      {required UuidValue sessionId, required String answer, dynamic hint}) {
+    throw UnimplementedError("sessionSendAuthProviderAnswer");
+  }
+
+  String sessionGetToolbarActions(
+      {required UuidValue sessionId, dynamic hint}) {
+    return '';
+  }
+
+  Future<String> sessionSetToolbarActions(
+      {required UuidValue sessionId, required String actions, dynamic hint}) {
+    throw UnimplementedError("sessionSetToolbarActions");
+  }
+
+  Future<void> sessionRunToolbarAction(
+      {required UuidValue sessionId, required String id, dynamic hint}) {
+    throw UnimplementedError("sessionRunToolbarAction");
+  }
+
+  Future<void> sessionGetCameraControls(
+      {required UuidValue sessionId, required int display, dynamic hint}) {
+    throw UnimplementedError("sessionGetCameraControls");
+  }
+
+  Future<void> sessionSetCameraControl(
+      {required UuidValue sessionId,
+      required int display,
+      required String control,
+      required bool auto,
+      double? value,
+      dynamic hint}) {
+    throw UnimplementedError("sessionSetCameraControl");
+  }
+
   Future<void> sessionOpenTerminal(
       {required UuidValue sessionId,
       required int terminalId,
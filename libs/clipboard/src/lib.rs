@@ -27,6 +27,11 @@ pub struct ProgressPercent {
     pub percent: f64,
     pub is_canceled: bool,
     pub is_failed: bool,
+    /// the connection the pasted files come from.
+    pub conn_id: i32,
+    /// the bytes written and to write.
+    pub transferred: u64,
+    pub total: u64,
 }
 
 // to-do: This trait may be removed, because unix file copy paste does not need it.
@@ -452,6 +452,9 @@ impl PasteTaskHandle {
             percent,
             is_canceled: self.progress.is_canceled,
             is_failed: self.progress.error.is_some(),
+            conn_id: self.files.first().map(|f| f.conn_id).unwrap_or(0),
+            transferred: self.progress.current_size,
+            total: self.progress.total_size,
         }
     }
 
@@ -755,6 +755,24 @@ impl Enigo {
         }
     }
 
+    /// Scroll by pixels on both axes in one event, positive is up and left.
+    pub fn mouse_scroll_pixels(&mut self, dx: i32, dy: i32) {
+        if let Some(src) = self.event_source.as_ref() {
+            unsafe {
+                let mouse_ev = CGEventCreateScrollWheelEvent(
+                    &src,
+                    ScrollUnit::Pixel,
+                    2, // CGWheelCount 1 = y 2 = xy 3 = xyz
+                    dy,
+                    dx,
+                );
+
+                CGEventPost(CGEventTapLocation::HID, mouse_ev);
+                CFRelease(mouse_ev as *const std::ffi::c_void);
+            }
+        }
+    }
+
     /// handle scroll vertically
     pub fn mouse_scroll_y(&mut self, length: i32, is_track_pad: bool) {
         self.mouse_scroll_impl(length, is_track_pad, false)
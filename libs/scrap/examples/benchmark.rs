@@ -95,6 +95,7 @@ fn test_vpx(
         quality,
         codec: codec_id,
         keyframe_interval: None,
+        tuning: Default::default(),
     });
     let mut encoder = VpxEncoder::new(config, i444).unwrap();
     let mut vpxs = vec![];
@@ -3,4 +3,6 @@
 #include <libyuv/convert_from.h>
 #include <libyuv/convert_from_argb.h>
 #include <libyuv/rotate.h>
-#include <libyuv/rotate_argb.h>
\ No newline at end of file
+#include <libyuv/rotate_argb.h>
+#include <libyuv/scale.h>
+#include <libyuv/scale_argb.h>
\ No newline at end of file
@@ -47,6 +47,20 @@ pub struct AomEncoderConfig {
     pub height: u32,
     pub quality: f32,
     pub keyframe_interval: Option<usize>,
+    pub tuning: AomTuning,
+}
+
+/// Host overrides of the realtime defaults, `None` keeps the default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct AomTuning {
+    /// Screen content coding tools, palette and intra block copy. `Some(false)` tunes for camera content.
+    pub screen_content: Option<bool>,
+    /// AOME_SET_CPUUSED, lower is slower with better quality. The realtime range is 6 - 10.
+    pub cpu_used: Option<u32>,
+    /// log2 of the tile columns.
+    pub tile_columns: Option<u32>,
+    /// log2 of the tile rows.
+    pub tile_rows: Option<u32>,
 }
 
 pub struct AomEncoder {
@@ -55,6 +69,7 @@ pub struct AomEncoder {
     height: usize,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    force_key_frame: bool,
 }
 
 // https://webrtc.googlesource.com/src/+/refs/heads/main/modules/video_coding/codecs/av1/libaom_av1_encoder.cc
@@ -65,6 +80,9 @@ mod webrtc {
     const kBitDepth: u32 = 8;
     const kLagInFrames: u32 = 0; // No look ahead.
     pub(super) const kTimeBaseDen: i64 = 1000;
+    const MIN_CPU_USED: u32 = 6;
+    const MAX_CPU_USED: u32 = 10;
+    const MAX_TILE_LOG2: u32 = 6;
 
     // Only positive speeds, range for real-time coding currently is: 6 - 8.
     // Lower means slower/better quality, higher means fastest/lower quality.
@@ -132,7 +150,11 @@ mod webrtc {
         Ok(c)
     }
 
-    pub fn set_controls(ctx: *mut aom_codec_ctx_t, cfg: &aom_codec_enc_cfg) -> ResultType<()> {
+    pub fn set_controls(
+        ctx: *mut aom_codec_ctx_t,
+        cfg: &aom_codec_enc_cfg,
+        tuning: AomTuning,
+    ) -> ResultType<()> {
         use aom_tune_content::*;
         use aome_enc_control_id::*;
         macro_rules! call_ctl {
@@ -141,7 +163,11 @@ mod webrtc {
             }};
         }
 
-        call_ctl!(ctx, AOME_SET_CPUUSED, get_cpu_speed(cfg.g_w, cfg.g_h));
+        let cpu_used = tuning
+            .cpu_used
+            .map(|v| v.clamp(MIN_CPU_USED, MAX_CPU_USED))
+            .unwrap_or(get_cpu_speed(cfg.g_w, cfg.g_h));
+        call_ctl!(ctx, AOME_SET_CPUUSED, cpu_used);
         call_ctl!(ctx, AV1E_SET_ENABLE_CDEF, 1);
         call_ctl!(ctx, AV1E_SET_ENABLE_TPL_MODEL, 0);
         call_ctl!(ctx, AV1E_SET_DELTAQ_MODE, 0);
@@ -152,16 +178,27 @@ mod webrtc {
         call_ctl!(ctx, AV1E_SET_MODE_COST_UPD_FREQ, 3);
         call_ctl!(ctx, AV1E_SET_MV_COST_UPD_FREQ, 3);
         // kScreensharing
-        call_ctl!(ctx, AV1E_SET_TUNE_CONTENT, AOM_CONTENT_SCREEN);
-        call_ctl!(ctx, AV1E_SET_ENABLE_PALETTE, 1);
-        let tile_set = if cfg.g_threads == 4 && cfg.g_w == 640 && (cfg.g_h == 360 || cfg.g_h == 480)
-        {
-            AV1E_SET_TILE_ROWS
+        if tuning.screen_content == Some(false) {
+            call_ctl!(ctx, AV1E_SET_TUNE_CONTENT, AOM_CONTENT_DEFAULT);
+            call_ctl!(ctx, AV1E_SET_ENABLE_PALETTE, 0);
         } else {
-            AV1E_SET_TILE_COLUMNS
-        };
-        // Failed on android
-        call_ctl!(ctx, tile_set, (cfg.g_threads as f64 * 1.0f64).log2().ceil());
+            call_ctl!(ctx, AV1E_SET_TUNE_CONTENT, AOM_CONTENT_SCREEN);
+            call_ctl!(ctx, AV1E_SET_ENABLE_PALETTE, 1);
+        }
+        if tuning.tile_columns.is_some() || tuning.tile_rows.is_some() {
+            let log2 = |v: Option<u32>| v.unwrap_or(0).min(MAX_TILE_LOG2);
+            call_ctl!(ctx, AV1E_SET_TILE_COLUMNS, log2(tuning.tile_columns));
+            call_ctl!(ctx, AV1E_SET_TILE_ROWS, log2(tuning.tile_rows));
+        } else {
+            let tile_set =
+                if cfg.g_threads == 4 && cfg.g_w == 640 && (cfg.g_h == 360 || cfg.g_h == 480) {
+                    AV1E_SET_TILE_ROWS
+                } else {
+                    AV1E_SET_TILE_COLUMNS
+                };
+            // Failed on android
+            call_ctl!(ctx, tile_set, (cfg.g_threads as f64 * 1.0f64).log2().ceil());
+        }
         call_ctl!(ctx, AV1E_SET_ROW_MT, 1);
         call_ctl!(ctx, AV1E_SET_ENABLE_OBMC, 0);
         call_ctl!(ctx, AV1E_SET_NOISE_SENSITIVITY, 0);
@@ -185,7 +222,9 @@ mod webrtc {
         call_ctl!(ctx, AV1E_SET_ENABLE_INTERINTRA_COMP, 0);
         call_ctl!(ctx, AV1E_SET_ENABLE_INTERINTRA_WEDGE, 0);
         call_ctl!(ctx, AV1E_SET_ENABLE_INTRA_EDGE_FILTER, 0);
-        call_ctl!(ctx, AV1E_SET_ENABLE_INTRABC, 0);
+        // Intra block copy helps with repeated text, but costs encoding time.
+        let intrabc = (tuning.screen_content == Some(true)) as i32;
+        call_ctl!(ctx, AV1E_SET_ENABLE_INTRABC, intrabc);
         call_ctl!(ctx, AV1E_SET_ENABLE_MASKED_COMP, 0);
         call_ctl!(ctx, AV1E_SET_ENABLE_PAETH_INTRA, 0);
         call_ctl!(ctx, AV1E_SET_ENABLE_QM, 0);
@@ -219,13 +258,14 @@ impl EncoderApi for AomEncoder {
                     flags,
                     AOM_ENCODER_ABI_VERSION as _
                 ));
-                webrtc::set_controls(&mut ctx, &c)?;
+                webrtc::set_controls(&mut ctx, &c, config.tuning)?;
                 Ok(Self {
                     ctx,
                     width: config.width as _,
                     height: config.height as _,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    force_key_frame: false,
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -284,6 +324,11 @@ impl EncoderApi for AomEncoder {
     }
 
     fn disable(&self) {}
+
+    fn request_key_frame(&mut self) -> bool {
+        self.force_key_frame = true;
+        true
+    }
 }
 
 impl AomEncoder {
@@ -309,12 +354,17 @@ impl AomEncoder {
         ));
         let pts = webrtc::kTimeBaseDen / 1000 * ms;
         let duration = webrtc::kTimeBaseDen / 1000;
+        let flags = if std::mem::take(&mut self.force_key_frame) {
+            AOM_EFLAG_FORCE_KF
+        } else {
+            0
+        };
         call_aom!(aom_codec_encode(
             &mut self.ctx,
             &image,
             pts as _,
             duration as _, // Duration
-            0,             // Flags
+            flags as _,
         ));
 
         Ok(EncodeFrames {
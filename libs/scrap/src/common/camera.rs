@@ -1,19 +1,30 @@
 use std::{
+    collections::HashMap,
     io,
     sync::{Arc, Mutex},
 };
 
-use hbb_common::message_proto::{DisplayInfo, Resolution};
+use hbb_common::{
+    message_proto::{DisplayInfo, Resolution},
+    serde_derive::{Deserialize, Serialize},
+};
 
 use crate::common::{bail, ResultType};
-use crate::{Frame, TraitCapturer};
+#[cfg(quartz)]
+use crate::quartz::camera;
+use crate::TraitCapturer;
+#[cfg(quartz)]
+use crate::{Frame, PixelBuffer};
+#[cfg(not(quartz))]
+use unsupported as camera;
 
 pub const PRIMARY_CAMERA_IDX: usize = 0;
 lazy_static::lazy_static! {
     static ref SYNC_CAMERA_DISPLAYS: Arc<Mutex<Vec<DisplayInfo>>> = Arc::new(Mutex::new(Vec::new()));
+    // The digital zoom, pan and tilt of the cameras, by their unique id.
+    static ref VIEWS: Mutex<HashMap<String, View>> = Default::default();
 }
-
-const CAMERA_NOT_SUPPORTED: &str = "This platform doesn't support camera yet";
+const MAX_ZOOM: f32 = 4.;
 
 pub struct Cameras;
 
@@ -24,40 +35,405 @@ pub fn primary_camera_exists() -> bool {
 
 impl Cameras {
     pub fn all_info() -> ResultType<Vec<DisplayInfo>> {
-        return Ok(Vec::new());
+        let mut x = 0;
+        let camera_displays: Vec<DisplayInfo> = camera::devices()
+            .into_iter()
+            .map(|device| {
+                let (width, height) = (device.width as i32, device.height as i32);
+                // Side by side, the same as the monitors.
+                let info = DisplayInfo {
+                    x,
+                    y: 0,
+                    name: device.name,
+                    width,
+                    height,
+                    online: true,
+                    cursor_embedded: false,
+                    scale: 1.0,
+                    original_resolution: Some(Resolution {
+                        width,
+                        height,
+                        ..Default::default()
+                    })
+                    .into(),
+                    ..Default::default()
+                };
+                x += width;
+                info
+            })
+            .collect();
+        *SYNC_CAMERA_DISPLAYS.lock().unwrap() = camera_displays.clone();
+        Ok(camera_displays)
+    }
+
+    pub fn exists(index: usize) -> bool {
+        camera::devices().len() > index
     }
 
-    pub fn exists(_index: usize) -> bool {
-        false
+    pub fn get_camera_resolution(index: usize) -> ResultType<Resolution> {
+        match camera::devices().get(index) {
+            Some(device) => Ok(Resolution {
+                width: device.width as _,
+                height: device.height as _,
+                ..Default::default()
+            }),
+            None => bail!("Camera of index {} doesn't exist", index),
+        }
     }
 
-    pub fn get_camera_resolution(_index: usize) -> ResultType<Resolution> {
-        bail!(CAMERA_NOT_SUPPORTED);
+    /// The resolutions the camera supports, for the controller to choose from.
+    pub fn get_camera_resolutions(index: usize) -> ResultType<Vec<Resolution>> {
+        let Some(device) = camera::devices().into_iter().nth(index) else {
+            bail!("Camera of index {} doesn't exist", index);
+        };
+        Ok(camera::resolutions(&device.unique_id)?
+            .into_iter()
+            .map(|(width, height)| Resolution {
+                width: width as _,
+                height: height as _,
+                ..Default::default()
+            })
+            .collect())
+    }
+
+    /// Change the resolution of the camera, its capturer must be recreated.
+    pub fn set_camera_resolution(index: usize, r: &Resolution) -> ResultType<()> {
+        let Some(device) = camera::devices().into_iter().nth(index) else {
+            bail!("Camera of index {} doesn't exist", index);
+        };
+        camera::set_resolution(&device.unique_id, r.width as _, r.height as _)?;
+        // The cameras are side by side, the ones after it are moved.
+        Self::all_info()?;
+        Ok(())
     }
 
     pub fn get_sync_cameras() -> Vec<DisplayInfo> {
-        vec![]
+        SYNC_CAMERA_DISPLAYS.lock().unwrap().clone()
+    }
+
+    pub fn get_capturer(current: usize) -> ResultType<Box<dyn TraitCapturer>> {
+        #[cfg(quartz)]
+        return Ok(Box::new(CameraCapturer::new(current)?));
+        #[cfg(not(quartz))]
+        bail!(unsupported::CAMERA_NOT_SUPPORTED);
+    }
+}
+
+/// An adjustable property of a camera.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Control {
+    Zoom,
+    Pan,
+    Tilt,
+    Focus,
+    Exposure,
+    WhiteBalance,
+}
+
+impl Control {
+    fn mode_control(self) -> Option<camera::ModeControl> {
+        match self {
+            Control::Focus => Some(camera::ModeControl::Focus),
+            Control::Exposure => Some(camera::ModeControl::Exposure),
+            Control::WhiteBalance => Some(camera::ModeControl::WhiteBalance),
+            Control::Zoom | Control::Pan | Control::Tilt => None,
+        }
+    }
+
+    // The range of the digital controls, automatic at their default.
+    fn view_range(self) -> Option<(f32, f32, f32)> {
+        match self {
+            Control::Zoom => Some((1., MAX_ZOOM, 1.)),
+            Control::Pan | Control::Tilt => Some((-1., 1., 0.)),
+            _ => None,
+        }
+    }
+}
+
+/// The state of a supported control. `value` is within `min..=max` for the controls that
+/// can be set manually, `None` if the control only switches between automatic and locked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ControlState {
+    pub control: Control,
+    pub auto: bool,
+    #[serde(default)]
+    pub value: Option<f32>,
+    #[serde(default)]
+    pub min: f32,
+    #[serde(default)]
+    pub max: f32,
+}
+
+/// The part of the frames of a camera which is captured, the zoom, pan and tilt are digital as
+/// the cameras of macOS have none. Pan and tilt move the zoomed part, from -1 to 1, left and down
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct View {
+    zoom: f32,
+    pan: f32,
+    tilt: f32,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self {
+            zoom: 1.,
+            pan: 0.,
+            tilt: 0.,
+        }
+    }
+}
+
+impl View {
+    fn get(unique_id: &str) -> Self {
+        VIEWS
+            .lock()
+            .unwrap()
+            .get(unique_id)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    fn value(&self, control: Control) -> f32 {
+        match control {
+            Control::Zoom => self.zoom,
+            Control::Pan => self.pan,
+            _ => self.tilt,
+        }
+    }
+
+    fn value_mut(&mut self, control: Control) -> &mut f32 {
+        match control {
+            Control::Zoom => &mut self.zoom,
+            Control::Pan => &mut self.pan,
+            _ => &mut self.tilt,
+        }
+    }
+}
+
+#[cfg(quartz)]
+impl View {
+    fn is_full(&self) -> bool {
+        *self == View::default()
+    }
+
+    // Copy the part of the BGRA frame in `src` to `dst`, scaled up to the size of the frame.
+    fn apply(&self, src: &[u8], width: usize, height: usize, stride: usize, dst: &mut Vec<u8>) {
+        let (w, h) = (width as f32 / self.zoom, height as f32 / self.zoom);
+        let left = (width as f32 - w) * (self.pan + 1.) / 2.;
+        let top = (height as f32 - h) * (1. - self.tilt) / 2.;
+        dst.resize(width * 4 * height, 0);
+        let xs: Vec<usize> = (0..width)
+            .map(|x| ((left + x as f32 * w / width as f32) as usize).min(width - 1) * 4)
+            .collect();
+        for (y, row) in dst.chunks_exact_mut(width * 4).enumerate() {
+            let sy = ((top + y as f32 * h / height as f32) as usize).min(height - 1);
+            let src_row = &src[sy * stride..];
+            for (pixel, sx) in row.chunks_exact_mut(4).zip(xs.iter()) {
+                pixel.copy_from_slice(&src_row[*sx..*sx + 4]);
+            }
+        }
+    }
+}
+
+pub struct Controls;
+
+impl Controls {
+    /// The supported controls of the camera.
+    pub fn get(index: usize) -> ResultType<Vec<ControlState>> {
+        let Some(device) = camera::devices().into_iter().nth(index) else {
+            bail!("Camera of index {} doesn't exist", index);
+        };
+        let view = View::get(&device.unique_id);
+        let mut controls: Vec<ControlState> = [Control::Zoom, Control::Pan, Control::Tilt]
+            .into_iter()
+            .filter_map(|control| {
+                let (min, max, default) = control.view_range()?;
+                let value = view.value(control);
+                Some(ControlState {
+                    control,
+                    auto: value == default,
+                    value: Some(value),
+                    min,
+                    max,
+                })
+            })
+            .collect();
+        controls.extend(
+            camera::mode_controls(&device.unique_id)?
+                .into_iter()
+                .map(|state| ControlState {
+                    control: match state.control {
+                        camera::ModeControl::Focus => Control::Focus,
+                        camera::ModeControl::Exposure => Control::Exposure,
+                        camera::ModeControl::WhiteBalance => Control::WhiteBalance,
+                    },
+                    auto: state.auto,
+                    value: state.manual.map(|m| m.value),
+                    min: state.manual.map(|m| m.min).unwrap_or_default(),
+                    max: state.manual.map(|m| m.max).unwrap_or_default(),
+                }),
+        );
+        Ok(controls)
     }
 
-    pub fn get_capturer(_current: usize) -> ResultType<Box<dyn TraitCapturer>> {
-        bail!(CAMERA_NOT_SUPPORTED);
+    /// Set a control of the camera, `auto` lets the camera adjust it, otherwise the `value`
+    /// is used, or the current one is kept if it is `None`. The zoom, pan and tilt are back to
+    /// the full frame when automatic.
+    pub fn set(index: usize, control: Control, auto: bool, value: Option<f32>) -> ResultType<()> {
+        let Some(device) = camera::devices().into_iter().nth(index) else {
+            bail!("Camera of index {} doesn't exist", index);
+        };
+        if let Some((min, max, default)) = control.view_range() {
+            let value = match value {
+                _ if auto => default,
+                Some(value) if (min..=max).contains(&value) => value,
+                Some(value) => bail!(
+                    "Camera control {:?} value {} is out of range",
+                    control,
+                    value
+                ),
+                None => return Ok(()),
+            };
+            let mut views = VIEWS.lock().unwrap();
+            let view = views.entry(device.unique_id).or_default();
+            *view.value_mut(control) = value;
+            return Ok(());
+        }
+        let Some(mode_control) = control.mode_control() else {
+            bail!("Camera control {:?} is not supported", control);
+        };
+        camera::set_mode_control(&device.unique_id, mode_control, auto, value)?;
+        Ok(())
     }
 }
 
-pub struct CameraCapturer;
+#[cfg(quartz)]
+pub struct CameraCapturer {
+    session: camera::Session,
+    unique_id: String,
+    width: usize,
+    height: usize,
+    seq: u64,
+    data: Vec<u8>,
+    // The frame zoomed in, see `View`.
+    viewed: Vec<u8>,
+}
 
+#[cfg(quartz)]
 impl CameraCapturer {
-    #[allow(dead_code)]
-    fn new(_current: usize) -> ResultType<Self> {
-        bail!(CAMERA_NOT_SUPPORTED);
+    fn new(current: usize) -> ResultType<Self> {
+        let Some(device) = camera::devices().into_iter().nth(current) else {
+            bail!("Camera of index {} doesn't exist", current);
+        };
+        let session = camera::Session::new(&device.unique_id)?;
+        Ok(CameraCapturer {
+            session,
+            unique_id: device.unique_id,
+            width: device.width,
+            height: device.height,
+            seq: 0,
+            data: Vec::new(),
+            viewed: Vec::new(),
+        })
     }
 }
 
+#[cfg(quartz)]
 impl TraitCapturer for CameraCapturer {
     fn frame<'a>(&'a mut self, _timeout: std::time::Duration) -> std::io::Result<Frame<'a>> {
+        let Some((width, height, stride)) = self.session.frame(&mut self.seq, &mut self.data)
+        else {
+            return Err(io::ErrorKind::WouldBlock.into());
+        };
+        if width != self.width || height != self.height {
+            // The encoder is created for the size of the active format.
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "camera resolution changed",
+            ));
+        }
+        let view = View::get(&self.unique_id);
+        if view.is_full() {
+            return Ok(Frame::PixelBuffer(PixelBuffer::bgra(
+                &self.data, width, height, stride,
+            )));
+        }
+        view.apply(&self.data, width, height, stride, &mut self.viewed);
+        Ok(Frame::PixelBuffer(PixelBuffer::bgra(
+            &self.viewed,
+            width,
+            height,
+            width * 4,
+        )))
+    }
+}
+
+// The platforms without a capture of the cameras, none is found.
+#[cfg(not(quartz))]
+mod unsupported {
+    use std::io;
+
+    pub const CAMERA_NOT_SUPPORTED: &str = "This platform doesn't support camera yet";
+
+    fn not_supported<T>() -> io::Result<T> {
         Err(io::Error::new(
-            io::ErrorKind::Other,
-            CAMERA_NOT_SUPPORTED.to_string(),
+            io::ErrorKind::Unsupported,
+            CAMERA_NOT_SUPPORTED,
         ))
     }
+
+    pub struct Device {
+        pub unique_id: String,
+        pub name: String,
+        pub width: usize,
+        pub height: usize,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ModeControl {
+        Focus,
+        Exposure,
+        WhiteBalance,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ManualValue {
+        pub value: f32,
+        pub min: f32,
+        pub max: f32,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    pub struct ModeControlState {
+        pub control: ModeControl,
+        pub auto: bool,
+        pub manual: Option<ManualValue>,
+    }
+
+    pub fn devices() -> Vec<Device> {
+        vec![]
+    }
+
+    pub fn resolutions(_unique_id: &str) -> io::Result<Vec<(usize, usize)>> {
+        not_supported()
+    }
+
+    pub fn set_resolution(_unique_id: &str, _width: usize, _height: usize) -> io::Result<()> {
+        not_supported()
+    }
+
+    pub fn mode_controls(_unique_id: &str) -> io::Result<Vec<ModeControlState>> {
+        not_supported()
+    }
+
+    pub fn set_mode_control(
+        _unique_id: &str,
+        _control: ModeControl,
+        _auto: bool,
+        _value: Option<f32>,
+    ) -> io::Result<()> {
+        not_supported()
+    }
 }
@@ -66,6 +66,9 @@ pub trait EncoderApi {
     fn is_hardware(&self) -> bool;
 
     fn disable(&self);
+
+    /// Encode the next frame as a key frame, returns false if not supported.
+    fn request_key_frame(&mut self) -> bool;
 }
 
 pub struct Encoder {
@@ -847,6 +850,7 @@ pub fn test_av1() {
                     height,
                     quality,
                     keyframe_interval,
+                    tuning: Default::default(),
                 }),
                 i444,
             ) else {
@@ -198,6 +198,120 @@ pub fn convert_to_yuv(
     Ok(())
 }
 
+/// The layout of a `w` x `h` frame in the pixel format of `fmt`, with the planes packed in one buffer.
+pub fn yuvfmt_with_size(fmt: &EncodeYuvFormat, w: usize, h: usize) -> EncodeYuvFormat {
+    let align = |x: usize| (x + 63) / 64 * 64;
+    let stride_y = align(w);
+    let (stride, u, v) = match fmt.pixfmt {
+        crate::Pixfmt::NV12 => {
+            let stride_uv = align((w + 1) / 2 * 2);
+            (vec![stride_y, stride_uv], stride_y * h, 0)
+        }
+        crate::Pixfmt::I444 => (
+            vec![stride_y, stride_y, stride_y],
+            stride_y * h,
+            stride_y * h * 2,
+        ),
+        _ => {
+            let stride_uv = align((w + 1) / 2);
+            let u = stride_y * h;
+            (
+                vec![stride_y, stride_uv, stride_uv],
+                u,
+                u + stride_uv * ((h + 1) / 2),
+            )
+        }
+    };
+    EncodeYuvFormat {
+        pixfmt: fmt.pixfmt,
+        w,
+        h,
+        stride,
+        u,
+        v,
+    }
+}
+
+// Length of the buffer holding a frame of `fmt`.
+fn yuv_len(fmt: &EncodeYuvFormat) -> ResultType<usize> {
+    let stride = |i: usize| fmt.stride.get(i).copied().unwrap_or_default();
+    let chroma_h = (fmt.h + 1) / 2;
+    Ok(match fmt.pixfmt {
+        crate::Pixfmt::I420 => fmt.v + stride(2) * chroma_h,
+        crate::Pixfmt::NV12 => fmt.u + stride(1) * chroma_h,
+        crate::Pixfmt::I444 => fmt.v + stride(2) * fmt.h,
+        _ => bail!("unsupported pixfmt: {:?}", fmt.pixfmt),
+    })
+}
+
+/// Scale the yuv frame `src` of `src_fmt` to the size of `dst_fmt`, the pixel formats must match.
+pub fn scale_yuv(
+    src: &[u8],
+    src_fmt: &EncodeYuvFormat,
+    dst: &mut Vec<u8>,
+    dst_fmt: &EncodeYuvFormat,
+) -> ResultType<()> {
+    if src_fmt.pixfmt != dst_fmt.pixfmt {
+        bail!(
+            "pixfmt mismatch: {:?} != {:?}",
+            src_fmt.pixfmt,
+            dst_fmt.pixfmt
+        );
+    }
+    let src_len = yuv_len(src_fmt)?;
+    if src.len() < src_len {
+        bail!("wrong src len, {} < {}", src.len(), src_len);
+    }
+    dst.resize(yuv_len(dst_fmt)?, 0);
+    let (ss, ds) = (&src_fmt.stride, &dst_fmt.stride);
+    match dst_fmt.pixfmt {
+        crate::Pixfmt::NV12 => {
+            call_yuv!(NV12Scale(
+                src.as_ptr(),
+                ss[0] as _,
+                src[src_fmt.u..].as_ptr(),
+                ss[1] as _,
+                src_fmt.w as _,
+                src_fmt.h as _,
+                dst.as_mut_ptr(),
+                ds[0] as _,
+                dst[dst_fmt.u..].as_mut_ptr(),
+                ds[1] as _,
+                dst_fmt.w as _,
+                dst_fmt.h as _,
+                FilterMode::kFilterBilinear,
+            ));
+        }
+        pixfmt => {
+            let f = if pixfmt == crate::Pixfmt::I444 {
+                I444Scale
+            } else {
+                I420Scale
+            };
+            call_yuv!(f(
+                src.as_ptr(),
+                ss[0] as _,
+                src[src_fmt.u..].as_ptr(),
+                ss[1] as _,
+                src[src_fmt.v..].as_ptr(),
+                ss[2] as _,
+                src_fmt.w as _,
+                src_fmt.h as _,
+                dst.as_mut_ptr(),
+                ds[0] as _,
+                dst[dst_fmt.u..].as_mut_ptr(),
+                ds[1] as _,
+                dst[dst_fmt.v..].as_mut_ptr(),
+                ds[2] as _,
+                dst_fmt.w as _,
+                dst_fmt.h as _,
+                FilterMode::kFilterBilinear,
+            ));
+        }
+    }
+    Ok(())
+}
+
 #[cfg(not(target_os = "ios"))]
 pub fn convert(captured: &PixelBuffer, pixfmt: crate::Pixfmt, dst: &mut Vec<u8>) -> ResultType<()> {
     if captured.pixfmt() == pixfmt {
@@ -234,3 +348,92 @@ pub fn convert(captured: &PixelBuffer, pixfmt: crate::Pixfmt, dst: &mut Vec<u8>)
     }
     Ok(())
 }
+
+/// Scale the 32-bit `src` to `w` x `h`, then rotate it clockwise by `rotation` degrees.
+///
+/// `dst` keeps its align and gets the format of `src`. `mid` holds the scaled image when rotating.
+pub fn scale_rotate_rgb(
+    src: &crate::ImageRgb,
+    dst: &mut crate::ImageRgb,
+    w: usize,
+    h: usize,
+    rotation: u32,
+    mid: &mut Vec<u8>,
+) -> ResultType<()> {
+    if !matches!(src.fmt, crate::ImageFormat::ARGB | crate::ImageFormat::ABGR) {
+        bail!("unsupported format: {:?}", src.fmt);
+    }
+    if w == 0 || h == 0 || src.w == 0 || src.h == 0 {
+        bail!("empty image");
+    }
+    let stride = |w: usize, align: usize| {
+        if align > 1 {
+            (w * 4 + align - 1) & !(align - 1)
+        } else {
+            w * 4
+        }
+    };
+    let src_stride = stride(src.w, src.align);
+    if src.raw.len() < src_stride * src.h {
+        bail!(
+            "wrong src len, {} < {} * {}",
+            src.raw.len(),
+            src_stride,
+            src.h
+        );
+    }
+    let mode = match rotation {
+        0 => None,
+        90 => Some(RotationMode::kRotate90),
+        180 => Some(RotationMode::kRotate180),
+        270 => Some(RotationMode::kRotate270),
+        _ => bail!("unsupported rotation: {}", rotation),
+    };
+    let (dst_w, dst_h) = if rotation == 90 || rotation == 270 {
+        (h, w)
+    } else {
+        (w, h)
+    };
+    dst.fmt = src.fmt;
+    dst.w = dst_w;
+    dst.h = dst_h;
+    let dst_stride = stride(dst_w, dst.align);
+    dst.raw.resize(dst_stride * dst_h, 0);
+    let Some(mode) = mode else {
+        call_yuv!(ARGBScale(
+            src.raw.as_ptr(),
+            src_stride as _,
+            src.w as _,
+            src.h as _,
+            dst.raw.as_mut_ptr(),
+            dst_stride as _,
+            w as _,
+            h as _,
+            FilterMode::kFilterBilinear,
+        ));
+        return Ok(());
+    };
+    let mid_stride = w * 4;
+    mid.resize(mid_stride * h, 0);
+    call_yuv!(ARGBScale(
+        src.raw.as_ptr(),
+        src_stride as _,
+        src.w as _,
+        src.h as _,
+        mid.as_mut_ptr(),
+        mid_stride as _,
+        w as _,
+        h as _,
+        FilterMode::kFilterBilinear,
+    ));
+    call_yuv!(ARGBRotate(
+        mid.as_ptr(),
+        mid_stride as _,
+        dst.raw.as_mut_ptr(),
+        dst_stride as _,
+        w as _,
+        h as _,
+        mode,
+    ));
+    Ok(())
+}
@@ -201,6 +201,11 @@ impl EncoderApi for HwRamEncoder {
     fn disable(&self) {
         HwCodecConfig::clear(false, true);
     }
+
+    fn request_key_frame(&mut self) -> bool {
+        // The ffmpeg encoders of hwcodec have no api to force an IDR frame.
+        false
+    }
 }
 
 impl HwRamEncoder {
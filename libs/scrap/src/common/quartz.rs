@@ -1,5 +1,4 @@
 use crate::{quartz, Frame, Pixfmt};
-use std::marker::PhantomData;
 use std::sync::{Arc, Mutex, TryLockError};
 use std::{io, mem};
 
@@ -56,8 +55,7 @@ impl crate::TraitCapturer for Capturer {
                         crate::would_block_if_equal(&mut self.saved_raw_data, frame.inner())?;
                         frame.surface_to_bgra(self.height());
                         Ok(Frame::PixelBuffer(PixelBuffer {
-                            frame,
-                            data: PhantomData,
+                            data: PixelData::Surface(frame),
                             width: self.width(),
                             height: self.height(),
                         }))
@@ -74,16 +72,83 @@ impl crate::TraitCapturer for Capturer {
     }
 }
 
+/// Captures a single application window, see `quartz::window`.
+pub struct WindowCapturer {
+    id: u32,
+    width: usize,
+    height: usize,
+    data: Vec<u8>,
+    saved_raw_data: Vec<u8>,
+}
+
+impl WindowCapturer {
+    pub fn new(id: u32) -> io::Result<WindowCapturer> {
+        let mut data = Vec::new();
+        let (width, height, _) = quartz::window::capture(id, &mut data)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "window not found"))?;
+        Ok(WindowCapturer {
+            id,
+            width,
+            height,
+            data,
+            saved_raw_data: Vec::new(),
+        })
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+}
+
+impl crate::TraitCapturer for WindowCapturer {
+    fn frame<'a>(&'a mut self, _timeout_ms: std::time::Duration) -> io::Result<Frame<'a>> {
+        let Some((width, height, stride)) = quartz::window::capture(self.id, &mut self.data) else {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "window closed"));
+        };
+        if width != self.width || height != self.height {
+            // The encoder is created for the old size, let the caller recreate the capturer.
+            return Err(io::Error::new(io::ErrorKind::Other, "window resized"));
+        }
+        crate::would_block_if_equal(&mut self.saved_raw_data, &self.data)?;
+        Ok(Frame::PixelBuffer(PixelBuffer::bgra(
+            &self.data, width, height, stride,
+        )))
+    }
+}
+
+enum PixelData<'a> {
+    Surface(quartz::Frame),
+    // BGRA with the stride.
+    Buffer(&'a [u8], usize),
+}
+
 pub struct PixelBuffer<'a> {
-    frame: quartz::Frame,
-    data: PhantomData<&'a [u8]>,
+    data: PixelData<'a>,
     width: usize,
     height: usize,
 }
 
+impl<'a> PixelBuffer<'a> {
+    /// A BGRA buffer with the stride in bytes.
+    pub(crate) fn bgra(data: &'a [u8], width: usize, height: usize, stride: usize) -> Self {
+        Self {
+            data: PixelData::Buffer(data, stride),
+            width,
+            height,
+        }
+    }
+}
+
 impl<'a> crate::TraitPixelBuffer for PixelBuffer<'a> {
     fn data(&self) -> &[u8] {
-        &*self.frame
+        match &self.data {
+            PixelData::Surface(frame) => &**frame,
+            PixelData::Buffer(data, _) => data,
+        }
     }
 
     fn width(&self) -> usize {
@@ -96,7 +161,10 @@ impl<'a> crate::TraitPixelBuffer for PixelBuffer<'a> {
 
     fn stride(&self) -> Vec<usize> {
         let mut v = Vec::new();
-        v.push(self.frame.stride());
+        v.push(match &self.data {
+            PixelData::Surface(frame) => frame.stride(),
+            PixelData::Buffer(_, stride) => *stride,
+        });
         v
     }
 
@@ -14,12 +14,54 @@ use std::{
     ops::{Deref, DerefMut},
     path::PathBuf,
     sync::mpsc::Sender,
-    time::Instant,
+    time::{Duration, Instant},
 };
 use webm::mux::{self, Segment, Track, VideoTrack, Writer};
 
 const MIN_SECS: u64 = 1;
 
+/// The container of the recording file.
+///
+/// The frames are written in it as they come, by the muxers of the recorder: webm is a Matroska,
+/// so the webm muxer of VP8/VP9/AV1 writes mkv as it is, and the muxer of H264/H265 picks the
+/// container of the extension of the file. The mp4 muxer takes no VP8/VP9/AV1, they are in mkv.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RecordContainer {
+    #[default]
+    Native,
+    Mp4,
+    Mkv,
+}
+
+impl From<&str> for RecordContainer {
+    fn from(s: &str) -> Self {
+        match s.trim().to_lowercase().as_str() {
+            "mp4" => RecordContainer::Mp4,
+            "mkv" => RecordContainer::Mkv,
+            _ => RecordContainer::Native,
+        }
+    }
+}
+
+impl RecordContainer {
+    fn extension(&self, format: CodecFormat) -> &'static str {
+        match self {
+            RecordContainer::Mp4 if native_extension(format) == "mp4" => "mp4",
+            RecordContainer::Mp4 | RecordContainer::Mkv => "mkv",
+            RecordContainer::Native => native_extension(format),
+        }
+    }
+}
+
+#[inline]
+fn native_extension(format: CodecFormat) -> &'static str {
+    if format == CodecFormat::VP9 || format == CodecFormat::VP8 || format == CodecFormat::AV1 {
+        "webm"
+    } else {
+        "mp4"
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct RecorderContext {
     pub server: bool,
@@ -27,6 +69,9 @@ pub struct RecorderContext {
     pub dir: String,
     pub display_idx: usize,
     pub camera: bool,
+    pub container: RecordContainer,
+    /// Start a new file at the first key frame after this duration.
+    pub rotate: Option<Duration>,
     pub tx: Option<Sender<RecordState>>,
 }
 
@@ -53,14 +98,8 @@ impl RecorderContext2 {
                 ctx.display_idx
             )
             + &self.format.to_string().to_lowercase()
-            + if self.format == CodecFormat::VP9
-                || self.format == CodecFormat::VP8
-                || self.format == CodecFormat::AV1
-            {
-                ".webm"
-            } else {
-                ".mp4"
-            };
+            + "."
+            + ctx.container.extension(self.format);
         self.filename = PathBuf::from(&ctx.dir)
             .join(file)
             .to_string_lossy()
@@ -93,6 +132,7 @@ pub struct Recorder {
     ctx2: Option<RecorderContext2>,
     pts: Option<i64>,
     check_failed: bool,
+    file_start: Instant,
 }
 
 impl Deref for Recorder {
@@ -117,6 +157,7 @@ impl Recorder {
             ctx2: None,
             pts: None,
             check_failed: false,
+            file_start: Instant::now(),
         })
     }
 
@@ -165,6 +206,7 @@ impl Recorder {
             };
             // pts is None when new inner is created
             self.pts = None;
+            self.file_start = Instant::now();
             self.send_state(RecordState::NewFile(ctx2.filename.clone()));
         }
         Ok(())
@@ -261,6 +303,23 @@ impl Recorder {
                 res?;
             }
             self.pts = Some(pts);
+        } else if key
+            && old_pts.is_some()
+            && self
+                .ctx
+                .rotate
+                .map_or(false, |rotate| self.file_start.elapsed() >= rotate)
+        {
+            log::info!("rotate record file");
+            self.inner = None;
+            self.ctx2 = None;
+            let res = self.check(w, h, format);
+            if res.is_err() {
+                self.check_failed = true;
+                log::error!("check failed: {:?}", res);
+                res?;
+            }
+            self.pts = Some(pts);
         }
         Ok(())
     }
@@ -39,6 +39,7 @@ pub struct VpxEncoder {
     id: VpxVideoCodecId,
     i444: bool,
     yuvfmt: EncodeYuvFormat,
+    force_key_frame: bool,
 }
 
 pub struct VpxDecoder {
@@ -165,6 +166,7 @@ impl EncoderApi for VpxEncoder {
                     id: config.codec,
                     i444,
                     yuvfmt: Self::get_yuvfmt(config.width, config.height, i444),
+                    force_key_frame: false,
                 })
             }
             _ => Err(anyhow!("encoder type mismatch")),
@@ -228,6 +230,11 @@ impl EncoderApi for VpxEncoder {
     }
 
     fn disable(&self) {}
+
+    fn request_key_frame(&mut self) -> bool {
+        self.force_key_frame = true;
+        true
+    }
 }
 
 impl VpxEncoder {
@@ -252,12 +259,17 @@ impl VpxEncoder {
             data.as_ptr() as _,
         ));
 
+        let flags = if std::mem::take(&mut self.force_key_frame) {
+            VPX_EFLAG_FORCE_KF
+        } else {
+            0
+        };
         call_vpx!(vpx_codec_encode(
             &mut self.ctx,
             &image,
             pts as _,
             1, // Duration
-            0, // Flags
+            flags as _,
             VPX_DL_REALTIME as _,
         ));
 
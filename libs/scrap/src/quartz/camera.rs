@@ -0,0 +1,613 @@
+use std::{
+    ffi::{CStr, CString},
+    io,
+    os::raw::c_void,
+    ptr,
+    sync::{Arc, Mutex, Once},
+};
+
+use block::ConcreteBlock;
+use objc::{
+    class,
+    declare::ClassDecl,
+    msg_send,
+    rc::autoreleasepool,
+    runtime::{Class, Object, Sel, BOOL, NO, YES},
+    sel, sel_impl,
+};
+
+use super::ffi::{dispatch_queue_create, dispatch_release, DispatchQueue};
+
+type Id = *mut Object;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct CMVideoDimensions {
+    width: i32,
+    height: i32,
+}
+
+// 'BGRA'
+const CV_PIXEL_FORMAT_TYPE_32_BGRA: u32 = 0x42475241;
+const CV_PIXEL_BUFFER_LOCK_READ_ONLY: u64 = 1;
+const AV_AUTHORIZATION_STATUS_NOT_DETERMINED: isize = 0;
+const AV_AUTHORIZATION_STATUS_AUTHORIZED: isize = 3;
+const DELEGATE_CLASS: &str = "RustDeskCameraDelegate";
+const SHARED_IVAR: &str = "_shared";
+
+#[link(name = "Foundation", kind = "framework")]
+#[link(name = "AVFoundation", kind = "framework")]
+extern "C" {
+    static AVMediaTypeVideo: Id;
+    static AVCaptureSessionPresetInputPriority: Id;
+}
+
+#[link(name = "CoreMedia", kind = "framework")]
+extern "C" {
+    fn CMSampleBufferGetImageBuffer(sbuf: *mut c_void) -> *mut c_void;
+    fn CMVideoFormatDescriptionGetDimensions(desc: *mut c_void) -> CMVideoDimensions;
+}
+
+#[link(name = "CoreVideo", kind = "framework")]
+extern "C" {
+    static kCVPixelBufferPixelFormatTypeKey: Id;
+    fn CVPixelBufferLockBaseAddress(buf: *mut c_void, flags: u64) -> i32;
+    fn CVPixelBufferUnlockBaseAddress(buf: *mut c_void, flags: u64) -> i32;
+    fn CVPixelBufferGetBaseAddress(buf: *mut c_void) -> *mut c_void;
+    fn CVPixelBufferGetBytesPerRow(buf: *mut c_void) -> usize;
+    fn CVPixelBufferGetWidth(buf: *mut c_void) -> usize;
+    fn CVPixelBufferGetHeight(buf: *mut c_void) -> usize;
+}
+
+/// A video capture device, the size is of its active format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Device {
+    pub unique_id: String,
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+}
+
+unsafe fn to_string(s: Id) -> String {
+    if s.is_null() {
+        return String::new();
+    }
+    let p: *const i8 = msg_send![s, UTF8String];
+    if p.is_null() {
+        return String::new();
+    }
+    CStr::from_ptr(p).to_string_lossy().into_owned()
+}
+
+unsafe fn nsstring(s: &str) -> io::Result<Id> {
+    let s = CString::new(s).map_err(|e| other_error(e.to_string()))?;
+    Ok(msg_send![class!(NSString), stringWithUTF8String: s.as_ptr()])
+}
+
+unsafe fn device_by_id(unique_id: &str) -> io::Result<Id> {
+    let id = nsstring(unique_id)?;
+    let device: Id = msg_send![class!(AVCaptureDevice), deviceWithUniqueID: id];
+    if device.is_null() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "camera not found"));
+    }
+    Ok(device)
+}
+
+unsafe fn format_dimensions(format: Id) -> (usize, usize) {
+    if format.is_null() {
+        return (0, 0);
+    }
+    let desc: *mut c_void = msg_send![format, formatDescription];
+    let d = CMVideoFormatDescriptionGetDimensions(desc);
+    (d.width.max(0) as usize, d.height.max(0) as usize)
+}
+
+unsafe fn device_info(device: Id) -> Device {
+    let unique_id: Id = msg_send![device, uniqueID];
+    let name: Id = msg_send![device, localizedName];
+    let format: Id = msg_send![device, activeFormat];
+    let (width, height) = format_dimensions(format);
+    Device {
+        unique_id: to_string(unique_id),
+        name: to_string(name),
+        width,
+        height,
+    }
+}
+
+/// The connected cameras, the order is stable while no camera is plugged or unplugged.
+pub fn devices() -> Vec<Device> {
+    autoreleasepool(|| unsafe {
+        let devices: Id =
+            msg_send![class!(AVCaptureDevice), devicesWithMediaType: AVMediaTypeVideo];
+        if devices.is_null() {
+            return vec![];
+        }
+        let count: usize = msg_send![devices, count];
+        (0..count)
+            .map(|i| {
+                let device: Id = msg_send![devices, objectAtIndex: i];
+                device_info(device)
+            })
+            .filter(|d| d.width > 0 && d.height > 0)
+            .collect()
+    })
+}
+
+/// The sizes of the formats of the camera, largest first.
+pub fn resolutions(unique_id: &str) -> io::Result<Vec<(usize, usize)>> {
+    autoreleasepool(|| unsafe {
+        let device = device_by_id(unique_id)?;
+        let formats: Id = msg_send![device, formats];
+        let count: usize = if formats.is_null() {
+            0
+        } else {
+            msg_send![formats, count]
+        };
+        let mut res: Vec<(usize, usize)> = (0..count)
+            .map(|i| {
+                let format: Id = msg_send![formats, objectAtIndex: i];
+                format_dimensions(format)
+            })
+            .filter(|(w, h)| *w > 0 && *h > 0)
+            .collect();
+        res.sort_by(|a, b| (b.0 * b.1).cmp(&(a.0 * a.1)).then(b.0.cmp(&a.0)));
+        res.dedup();
+        Ok(res)
+    })
+}
+
+/// Make the first format of the size the active one, kept by the capture sessions.
+pub fn set_resolution(unique_id: &str, width: usize, height: usize) -> io::Result<()> {
+    autoreleasepool(|| unsafe {
+        let device = device_by_id(unique_id)?;
+        let formats: Id = msg_send![device, formats];
+        let count: usize = if formats.is_null() {
+            0
+        } else {
+            msg_send![formats, count]
+        };
+        let format = (0..count)
+            .map(|i| -> Id { msg_send![formats, objectAtIndex: i] })
+            .find(|f| format_dimensions(*f) == (width, height))
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unsupported camera resolution {}x{}", width, height),
+                )
+            })?;
+        let mut error: Id = ptr::null_mut();
+        let locked: BOOL = msg_send![device, lockForConfiguration: &mut error];
+        if locked == NO {
+            return Err(other_error("failed to configure camera".to_owned()));
+        }
+        let _: () = msg_send![device, setActiveFormat: format];
+        let _: () = msg_send![device, unlockForConfiguration];
+        Ok(())
+    })
+}
+
+// AVCaptureFocusMode, AVCaptureExposureMode and AVCaptureWhiteBalanceMode share the values.
+const AV_CAPTURE_MODE_LOCKED: isize = 0;
+const AV_CAPTURE_MODE_CONTINUOUS_AUTO: isize = 2;
+// The white balance is set by its temperature, in kelvin.
+const MIN_TEMPERATURE: f32 = 2000.;
+const MAX_TEMPERATURE: f32 = 10000.;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct WhiteBalanceGains {
+    red: f32,
+    green: f32,
+    blue: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TemperatureAndTint {
+    temperature: f32,
+    tint: f32,
+}
+
+/// The controls of a camera with an automatic and a locked mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModeControl {
+    Focus,
+    Exposure,
+    WhiteBalance,
+}
+
+/// A value to set manually, within `min..=max`: the lens position of the focus, the bias of
+/// the exposure in EV and the temperature of the white balance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ManualValue {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModeControlState {
+    pub control: ModeControl,
+    pub auto: bool,
+    /// None if the camera only switches the control between automatic and locked.
+    pub manual: Option<ManualValue>,
+}
+
+unsafe fn responds_to(device: Id, sel: Sel) -> bool {
+    let responds: BOOL = msg_send![device, respondsToSelector: sel];
+    responds != NO
+}
+
+impl ModeControl {
+    pub const ALL: [ModeControl; 3] = [
+        ModeControl::Focus,
+        ModeControl::Exposure,
+        ModeControl::WhiteBalance,
+    ];
+
+    unsafe fn mode(self, device: Id) -> isize {
+        match self {
+            ModeControl::Focus => msg_send![device, focusMode],
+            ModeControl::Exposure => msg_send![device, exposureMode],
+            ModeControl::WhiteBalance => msg_send![device, whiteBalanceMode],
+        }
+    }
+
+    unsafe fn is_supported(self, device: Id, mode: isize) -> bool {
+        let supported: BOOL = match self {
+            ModeControl::Focus => msg_send![device, isFocusModeSupported: mode],
+            ModeControl::Exposure => msg_send![device, isExposureModeSupported: mode],
+            ModeControl::WhiteBalance => msg_send![device, isWhiteBalanceModeSupported: mode],
+        };
+        supported != NO
+    }
+
+    unsafe fn set_mode(self, device: Id, mode: isize) {
+        match self {
+            ModeControl::Focus => msg_send![device, setFocusMode: mode],
+            ModeControl::Exposure => msg_send![device, setExposureMode: mode],
+            ModeControl::WhiteBalance => msg_send![device, setWhiteBalanceMode: mode],
+        }
+    }
+
+    // Most of the manual settings of AVFoundation are missing on macOS, they are used if the
+    // device has them.
+    unsafe fn manual(self, device: Id) -> Option<ManualValue> {
+        match self {
+            ModeControl::Focus => {
+                if !responds_to(
+                    device,
+                    sel!(setFocusModeLockedWithLensPosition:completionHandler:),
+                ) {
+                    return None;
+                }
+                let value: f32 = msg_send![device, lensPosition];
+                Some(ManualValue {
+                    value,
+                    min: 0.,
+                    max: 1.,
+                })
+            }
+            ModeControl::Exposure => {
+                if !responds_to(device, sel!(setExposureTargetBias:completionHandler:)) {
+                    return None;
+                }
+                let value: f32 = msg_send![device, exposureTargetBias];
+                let min: f32 = msg_send![device, minExposureTargetBias];
+                let max: f32 = msg_send![device, maxExposureTargetBias];
+                Some(ManualValue { value, min, max })
+            }
+            ModeControl::WhiteBalance => {
+                if !responds_to(
+                    device,
+                    sel!(setWhiteBalanceModeLockedWithDeviceWhiteBalanceGains:completionHandler:),
+                ) || !responds_to(
+                    device,
+                    sel!(temperatureAndTintValuesForDeviceWhiteBalanceGains:),
+                ) {
+                    return None;
+                }
+                let gains: WhiteBalanceGains = msg_send![device, deviceWhiteBalanceGains];
+                let values: TemperatureAndTint =
+                    msg_send![device, temperatureAndTintValuesForDeviceWhiteBalanceGains: gains];
+                Some(ManualValue {
+                    value: values.temperature.clamp(MIN_TEMPERATURE, MAX_TEMPERATURE),
+                    min: MIN_TEMPERATURE,
+                    max: MAX_TEMPERATURE,
+                })
+            }
+        }
+    }
+
+    // The device is locked for configuration. The focus and the white balance are locked to the
+    // value, the bias of the exposure applies to the automatic mode too.
+    unsafe fn set_manual(self, device: Id, value: f32) {
+        let handler: Id = ptr::null_mut();
+        match self {
+            ModeControl::Focus => {
+                let _: () = msg_send![device, setFocusModeLockedWithLensPosition: value completionHandler: handler];
+            }
+            ModeControl::Exposure => {
+                let _: () =
+                    msg_send![device, setExposureTargetBias: value completionHandler: handler];
+            }
+            ModeControl::WhiteBalance => {
+                let values = TemperatureAndTint {
+                    temperature: value,
+                    tint: 0.,
+                };
+                let gains: WhiteBalanceGains =
+                    msg_send![device, deviceWhiteBalanceGainsForTemperatureAndTintValues: values];
+                let max: f32 = msg_send![device, maxWhiteBalanceGain];
+                let gains = WhiteBalanceGains {
+                    red: gains.red.clamp(1., max),
+                    green: gains.green.clamp(1., max),
+                    blue: gains.blue.clamp(1., max),
+                };
+                let _: () = msg_send![device, setWhiteBalanceModeLockedWithDeviceWhiteBalanceGains: gains completionHandler: handler];
+            }
+        }
+    }
+}
+
+/// The controls the camera can switch between automatic and locked, with whether they
+/// are automatic now and their manual value.
+pub fn mode_controls(unique_id: &str) -> io::Result<Vec<ModeControlState>> {
+    autoreleasepool(|| unsafe {
+        let device = device_by_id(unique_id)?;
+        Ok(ModeControl::ALL
+            .iter()
+            .filter(|c| {
+                c.is_supported(device, AV_CAPTURE_MODE_LOCKED)
+                    && c.is_supported(device, AV_CAPTURE_MODE_CONTINUOUS_AUTO)
+            })
+            .map(|c| ModeControlState {
+                control: *c,
+                auto: c.mode(device) != AV_CAPTURE_MODE_LOCKED,
+                manual: c.manual(device),
+            })
+            .collect())
+    })
+}
+
+/// Let the camera adjust the control continuously, or lock it, to the `value` if there is one
+/// or to its current value.
+pub fn set_mode_control(
+    unique_id: &str,
+    control: ModeControl,
+    auto: bool,
+    value: Option<f32>,
+) -> io::Result<()> {
+    let mode = if auto {
+        AV_CAPTURE_MODE_CONTINUOUS_AUTO
+    } else {
+        AV_CAPTURE_MODE_LOCKED
+    };
+    autoreleasepool(|| unsafe {
+        let device = device_by_id(unique_id)?;
+        if !control.is_supported(device, mode) {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{:?} mode {} is not supported", control, mode),
+            ));
+        }
+        if let Some(value) = value {
+            if auto && control != ModeControl::Exposure {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} has no value while automatic", control),
+                ));
+            }
+            let Some(manual) = control.manual(device) else {
+                return Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    format!("{:?} has no manual value", control),
+                ));
+            };
+            if !(manual.min..=manual.max).contains(&value) {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} value {} is out of range", control, value),
+                ));
+            }
+        }
+        let mut error: Id = ptr::null_mut();
+        let locked: BOOL = msg_send![device, lockForConfiguration: &mut error];
+        if locked == NO {
+            return Err(other_error("failed to configure camera".to_owned()));
+        }
+        match value {
+            Some(value) if control != ModeControl::Exposure => control.set_manual(device, value),
+            Some(value) => {
+                control.set_mode(device, mode);
+                control.set_manual(device, value);
+            }
+            None => control.set_mode(device, mode),
+        }
+        let _: () = msg_send![device, unlockForConfiguration];
+        Ok(())
+    })
+}
+
+/// Whether the process may use the cameras. The first call asks the user, the capture
+/// fails until it is granted.
+pub fn is_authorized() -> bool {
+    unsafe {
+        let status: isize =
+            msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: AVMediaTypeVideo];
+        if status == AV_AUTHORIZATION_STATUS_NOT_DETERMINED {
+            let handler = ConcreteBlock::new(|_granted: BOOL| {}).copy();
+            let _: () = msg_send![class!(AVCaptureDevice), requestAccessForMediaType: AVMediaTypeVideo completionHandler: &*handler];
+        }
+        status == AV_AUTHORIZATION_STATUS_AUTHORIZED
+    }
+}
+
+// The latest frame, written on the capture queue.
+#[derive(Default)]
+struct Shared {
+    data: Vec<u8>,
+    width: usize,
+    height: usize,
+    stride: usize,
+    seq: u64,
+}
+
+extern "C" fn did_output_sample_buffer(
+    this: &Object,
+    _: Sel,
+    _output: Id,
+    sample: *mut c_void,
+    _connection: Id,
+) {
+    unsafe {
+        let shared = *this.get_ivar::<*mut c_void>(SHARED_IVAR) as *const Mutex<Shared>;
+        if shared.is_null() {
+            return;
+        }
+        let buf = CMSampleBufferGetImageBuffer(sample);
+        if buf.is_null() || CVPixelBufferLockBaseAddress(buf, CV_PIXEL_BUFFER_LOCK_READ_ONLY) != 0 {
+            return;
+        }
+        let base = CVPixelBufferGetBaseAddress(buf) as *const u8;
+        let stride = CVPixelBufferGetBytesPerRow(buf);
+        let height = CVPixelBufferGetHeight(buf);
+        if !base.is_null() {
+            let mut s = (*shared).lock().unwrap();
+            s.data.resize(stride * height, 0);
+            ptr::copy_nonoverlapping(base, s.data.as_mut_ptr(), stride * height);
+            s.width = CVPixelBufferGetWidth(buf);
+            s.height = height;
+            s.stride = stride;
+            s.seq = s.seq.wrapping_add(1);
+        }
+        CVPixelBufferUnlockBaseAddress(buf, CV_PIXEL_BUFFER_LOCK_READ_ONLY);
+    }
+}
+
+fn delegate_class() -> &'static Class {
+    static REGISTER: Once = Once::new();
+    REGISTER.call_once(|| {
+        let mut decl = ClassDecl::new(DELEGATE_CLASS, class!(NSObject)).unwrap();
+        decl.add_ivar::<*mut c_void>(SHARED_IVAR);
+        unsafe {
+            decl.add_method(
+                sel!(captureOutput:didOutputSampleBuffer:fromConnection:),
+                did_output_sample_buffer as extern "C" fn(&Object, Sel, Id, *mut c_void, Id),
+            );
+        }
+        decl.register();
+    });
+    Class::get(DELEGATE_CLASS).unwrap()
+}
+
+fn other_error(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, msg)
+}
+
+/// A running capture session of one camera, the frames are BGRA.
+pub struct Session {
+    session: Id,
+    delegate: Id,
+    queue: DispatchQueue,
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Session {
+    pub fn new(unique_id: &str) -> io::Result<Session> {
+        if !is_authorized() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "camera access is not granted",
+            ));
+        }
+        autoreleasepool(|| unsafe {
+            let device = device_by_id(unique_id)?;
+            let mut error: Id = ptr::null_mut();
+            let input: Id = msg_send![class!(AVCaptureDeviceInput), deviceInputWithDevice: device error: &mut error];
+            if input.is_null() {
+                let desc: Id = if error.is_null() {
+                    ptr::null_mut()
+                } else {
+                    msg_send![error, localizedDescription]
+                };
+                return Err(other_error(format!(
+                    "failed to open camera: {}",
+                    to_string(desc)
+                )));
+            }
+
+            let shared = Arc::new(Mutex::new(Shared::default()));
+            let delegate: Id = msg_send![delegate_class(), new];
+            (*delegate).set_ivar(SHARED_IVAR, Arc::into_raw(shared.clone()) as *mut c_void);
+            let session = Session {
+                session: msg_send![class!(AVCaptureSession), new],
+                delegate,
+                queue: dispatch_queue_create(
+                    b"rustdesk.camera\0".as_ptr() as *const i8,
+                    ptr::null_mut(),
+                ),
+                shared,
+            };
+
+            let can_add: BOOL = msg_send![session.session, canAddInput: input];
+            if can_add == NO {
+                return Err(other_error("failed to add camera input".to_owned()));
+            }
+            let _: () = msg_send![session.session, addInput: input];
+            // Keep the active format of the device, the frames have the size of `devices()`.
+            let _: () =
+                msg_send![session.session, setSessionPreset: AVCaptureSessionPresetInputPriority];
+
+            let output: Id = msg_send![class!(AVCaptureVideoDataOutput), new];
+            let format: Id =
+                msg_send![class!(NSNumber), numberWithUnsignedInt: CV_PIXEL_FORMAT_TYPE_32_BGRA];
+            let settings: Id = msg_send![class!(NSDictionary), dictionaryWithObject: format forKey: kCVPixelBufferPixelFormatTypeKey];
+            let _: () = msg_send![output, setVideoSettings: settings];
+            let _: () = msg_send![output, setAlwaysDiscardsLateVideoFrames: YES];
+            let _: () =
+                msg_send![output, setSampleBufferDelegate: session.delegate queue: session.queue];
+            let can_add: BOOL = msg_send![session.session, canAddOutput: output];
+            if can_add == NO {
+                let _: () = msg_send![output, release];
+                return Err(other_error("failed to add camera output".to_owned()));
+            }
+            // Retained by the session.
+            let _: () = msg_send![session.session, addOutput: output];
+            let _: () = msg_send![output, release];
+            let _: () = msg_send![session.session, startRunning];
+            Ok(session)
+        })
+    }
+
+    /// Copy the latest frame into `buf` if it is newer than `seq`, returns
+    /// `(width, height, stride)` in pixels and bytes.
+    pub fn frame(&self, seq: &mut u64, buf: &mut Vec<u8>) -> Option<(usize, usize, usize)> {
+        let s = self.shared.lock().unwrap();
+        if s.seq == *seq || s.data.is_empty() {
+            return None;
+        }
+        *seq = s.seq;
+        buf.resize(s.data.len(), 0);
+        buf.copy_from_slice(&s.data);
+        Some((s.width, s.height, s.stride))
+    }
+}
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        unsafe {
+            // No frame is delivered after the session is stopped.
+            let _: () = msg_send![self.session, stopRunning];
+            let _: () = msg_send![self.session, release];
+            let shared = *(*self.delegate).get_ivar::<*mut c_void>(SHARED_IVAR);
+            (*self.delegate).set_ivar(SHARED_IVAR, ptr::null_mut::<c_void>());
+            let _: () = msg_send![self.delegate, release];
+            dispatch_release(self.queue);
+            if !shared.is_null() {
+                drop(Arc::from_raw(shared as *const Mutex<Shared>));
+            }
+        }
+    }
+}
@@ -13,6 +13,16 @@ pub type IOSurfaceRef = *mut c_void;
 pub type DispatchQueue = *mut c_void;
 pub type DispatchQueueAttr = *mut c_void;
 pub type CFAllocatorRef = *mut c_void;
+pub type CFArrayRef = *mut c_void;
+pub type CFDataRef = *mut c_void;
+pub type CGImageRef = *mut c_void;
+pub type CGDataProviderRef = *mut c_void;
+
+pub const CG_WINDOW_LIST_OPTION_ON_SCREEN_ONLY: u32 = 1 << 0;
+pub const CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW: u32 = 1 << 3;
+pub const CG_WINDOW_LIST_EXCLUDE_DESKTOP_ELEMENTS: u32 = 1 << 4;
+pub const CG_WINDOW_IMAGE_BOUNDS_IGNORE_FRAMING: u32 = 1 << 0;
+pub const CF_STRING_ENCODING_UTF8: u32 = 0x0800_0100;
 
 #[repr(C)]
 pub struct CFDictionaryKeyCallBacks {
@@ -195,6 +205,30 @@ extern "C" {
     pub fn CGDisplayBounds(display: u32) -> CGRect;
     pub fn BackingScaleFactor(display: u32) -> f32;
 
+    pub static kCGWindowNumber: CFStringRef;
+    pub static kCGWindowOwnerPID: CFStringRef;
+    pub static kCGWindowOwnerName: CFStringRef;
+    pub static kCGWindowName: CFStringRef;
+    pub static kCGWindowLayer: CFStringRef;
+    pub static kCGWindowBounds: CFStringRef;
+
+    pub fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> CFArrayRef;
+    pub fn CGWindowListCreateImage(
+        screen_bounds: CGRect,
+        list_option: u32,
+        window_id: u32,
+        image_option: u32,
+    ) -> CGImageRef;
+    pub fn CGRectMakeWithDictionaryRepresentation(dict: CFDictionaryRef, rect: *mut CGRect)
+        -> bool;
+    pub fn CGImageGetWidth(image: CGImageRef) -> usize;
+    pub fn CGImageGetHeight(image: CGImageRef) -> usize;
+    pub fn CGImageGetBytesPerRow(image: CGImageRef) -> usize;
+    pub fn CGImageGetBitsPerPixel(image: CGImageRef) -> usize;
+    pub fn CGImageGetDataProvider(image: CGImageRef) -> CGDataProviderRef;
+    pub fn CGImageRelease(image: CGImageRef);
+    pub fn CGDataProviderCopyData(provider: CGDataProviderRef) -> CFDataRef;
+
     // IOSurface
 
     pub fn IOSurfaceGetAllocSize(buffer: IOSurfaceRef) -> usize;
@@ -238,4 +272,21 @@ extern "C" {
 
     pub fn CFRetain(cf: *const c_void);
     pub fn CFRelease(cf: *const c_void);
+
+    pub fn CFArrayGetCount(array: CFArrayRef) -> i64;
+    pub fn CFArrayGetValueAtIndex(array: CFArrayRef, index: i64) -> *const c_void;
+    pub fn CFDictionaryGetValue(dict: CFDictionaryRef, key: *const c_void) -> *const c_void;
+    pub fn CFNumberGetValue(
+        number: CFNumberRef,
+        the_type: CFNumberType,
+        value: *mut c_void,
+    ) -> bool;
+    pub fn CFStringGetCString(
+        string: CFStringRef,
+        buffer: *mut i8,
+        buffer_size: i64,
+        encoding: u32,
+    ) -> bool;
+    pub fn CFDataGetBytePtr(data: CFDataRef) -> *const u8;
+    pub fn CFDataGetLength(data: CFDataRef) -> i64;
 }
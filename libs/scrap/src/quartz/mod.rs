@@ -3,12 +3,15 @@ pub use self::config::Config;
 pub use self::display::Display;
 pub use self::ffi::{CGError, PixelFormat};
 pub use self::frame::Frame;
+pub use self::window::Window;
 
+pub mod camera;
 mod capturer;
 mod config;
 mod display;
 pub mod ffi;
 mod frame;
+pub mod window;
 
 use std::sync::{Arc, Mutex};
 
@@ -0,0 +1,152 @@
+use std::{ffi::CStr, ptr};
+
+use super::ffi::*;
+
+/// An on-screen application window, the bounds are in points of the global display space.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Window {
+    pub id: u32,
+    pub pid: i32,
+    pub owner: String,
+    pub title: String,
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+unsafe fn get_value(dict: CFDictionaryRef, key: CFStringRef) -> *const std::ffi::c_void {
+    CFDictionaryGetValue(dict, key as _)
+}
+
+unsafe fn get_i64(dict: CFDictionaryRef, key: CFStringRef) -> Option<i64> {
+    let number = get_value(dict, key);
+    if number.is_null() {
+        return None;
+    }
+    let mut value: i64 = 0;
+    if CFNumberGetValue(
+        number as _,
+        CFNumberType::SInt64,
+        &mut value as *mut i64 as _,
+    ) {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+unsafe fn get_string(dict: CFDictionaryRef, key: CFStringRef) -> String {
+    let string = get_value(dict, key);
+    if string.is_null() {
+        return String::new();
+    }
+    let mut buf = [0i8; 512];
+    if CFStringGetCString(
+        string as _,
+        buf.as_mut_ptr(),
+        buf.len() as _,
+        CF_STRING_ENCODING_UTF8,
+    ) {
+        CStr::from_ptr(buf.as_ptr()).to_string_lossy().into_owned()
+    } else {
+        String::new()
+    }
+}
+
+/// The normal windows on screen, front to back. Menus, the dock and other
+/// windows above the normal layer are skipped.
+pub fn windows() -> Vec<Window> {
+    let mut res = Vec::new();
+    unsafe {
+        let list = CGWindowListCopyWindowInfo(
+            CG_WINDOW_LIST_OPTION_ON_SCREEN_ONLY | CG_WINDOW_LIST_EXCLUDE_DESKTOP_ELEMENTS,
+            0,
+        );
+        if list.is_null() {
+            return res;
+        }
+        for i in 0..CFArrayGetCount(list) {
+            let dict = CFArrayGetValueAtIndex(list, i) as CFDictionaryRef;
+            if dict.is_null() || get_i64(dict, kCGWindowLayer) != Some(0) {
+                continue;
+            }
+            let Some(id) = get_i64(dict, kCGWindowNumber) else {
+                continue;
+            };
+            let bounds = get_value(dict, kCGWindowBounds);
+            if bounds.is_null() {
+                continue;
+            }
+            let mut rect: CGRect = std::mem::zeroed();
+            if !CGRectMakeWithDictionaryRepresentation(bounds as _, &mut rect) {
+                continue;
+            }
+            if rect.size.width < 1. || rect.size.height < 1. {
+                continue;
+            }
+            res.push(Window {
+                id: id as _,
+                pid: get_i64(dict, kCGWindowOwnerPID).unwrap_or_default() as _,
+                owner: get_string(dict, kCGWindowOwnerName),
+                title: get_string(dict, kCGWindowName),
+                x: rect.origin.x as _,
+                y: rect.origin.y as _,
+                width: rect.size.width as _,
+                height: rect.size.height as _,
+            });
+        }
+        CFRelease(list as _);
+    }
+    res
+}
+
+pub fn window(id: u32) -> Option<Window> {
+    windows().into_iter().find(|w| w.id == id)
+}
+
+/// Capture the content of the window into `buf` as BGRA, returns `(width, height, stride)` in pixels
+/// of the backing store, `None` if the window is gone.
+pub fn capture(id: u32, buf: &mut Vec<u8>) -> Option<(usize, usize, usize)> {
+    unsafe {
+        // CGRectNull, the bounds of the window.
+        let null = CGRect {
+            origin: CGPoint {
+                x: CGFloat::INFINITY,
+                y: CGFloat::INFINITY,
+            },
+            size: CGSize {
+                width: 0.,
+                height: 0.,
+            },
+        };
+        let image = CGWindowListCreateImage(
+            null,
+            CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW,
+            id,
+            CG_WINDOW_IMAGE_BOUNDS_IGNORE_FRAMING,
+        );
+        if image.is_null() {
+            return None;
+        }
+        let width = CGImageGetWidth(image);
+        let height = CGImageGetHeight(image);
+        let stride = CGImageGetBytesPerRow(image);
+        let mut res = None;
+        if width > 0 && height > 0 && CGImageGetBitsPerPixel(image) == 32 {
+            let data = CGDataProviderCopyData(CGImageGetDataProvider(image));
+            if !data.is_null() {
+                let len = CFDataGetLength(data) as usize;
+                let src = CFDataGetBytePtr(data);
+                if !src.is_null() && len >= stride * height {
+                    buf.resize(stride * height, 0);
+                    ptr::copy_nonoverlapping(src, buf.as_mut_ptr(), stride * height);
+                    res = Some((width, height, stride));
+                }
+                CFRelease(data as _);
+            }
+        }
+        CGImageRelease(image);
+        res
+    }
+}
@@ -1 +1,16 @@
-fn main() {}
+#[cfg(target_os = "macos")]
+fn build_mac() {
+    let file = "src/macos/virtual_display.m";
+    cc::Build::new()
+        .flag("-fno-objc-arc")
+        .file(file)
+        .compile("virtual_display");
+    println!("cargo:rustc-link-lib=framework=Foundation");
+    println!("cargo:rustc-link-lib=framework=CoreGraphics");
+    println!("cargo:rerun-if-changed={}", file);
+}
+
+fn main() {
+    #[cfg(target_os = "macos")]
+    build_mac();
+}
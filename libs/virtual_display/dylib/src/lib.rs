@@ -1,5 +1,14 @@
 use hbb_common::ResultType;
 
+#[cfg(target_os = "macos")]
+mod macos;
+
+#[no_mangle]
+pub fn get_driver_install_path() -> &'static str {
+    // No driver, `CGVirtualDisplay` is part of macOS.
+    ""
+}
+
 #[no_mangle]
 pub fn download_driver() -> ResultType<()> {
     // process download and report progress
@@ -19,7 +28,10 @@ pub fn uninstall_driver(_reboot_required: &mut bool) -> ResultType<()> {
 
 #[no_mangle]
 pub fn is_device_created() -> bool {
-    false
+    #[cfg(target_os = "macos")]
+    return macos::is_available();
+    #[cfg(not(target_os = "macos"))]
+    return false;
 }
 
 #[no_mangle]
@@ -27,30 +39,66 @@ pub fn create_device() -> ResultType<()> {
     if is_device_created() {
         return Ok(());
     }
-    Ok(())
+    #[cfg(target_os = "macos")]
+    hbb_common::bail!("CGVirtualDisplay is not available, macOS 11 or later is required");
+    #[cfg(not(target_os = "macos"))]
+    return Ok(());
 }
 
 #[no_mangle]
 pub fn close_device() {
+    #[cfg(target_os = "macos")]
+    macos::remove_all_monitors();
 }
 
 type PMonitorMode = *mut std::ffi::c_void;
 
+/// Plug in the monitor with the modes last set for its index, or the default ones, 1920x1080
+/// first. `_edid` and `_retries` are for the Windows driver.
 #[no_mangle]
 pub fn plug_in_monitor(_monitor_index: u32, _edid: u32, _retries: u32) -> ResultType<()> {
+    #[cfg(target_os = "macos")]
+    if macos::display_id(_monitor_index).is_none() {
+        let id = macos::set_monitor(_monitor_index, &macos::modes(_monitor_index))?;
+        hbb_common::log::info!(
+            "Virtual monitor {} plugged in, display {}",
+            _monitor_index,
+            id
+        );
+    }
     Ok(())
 }
 
 #[no_mangle]
 pub fn plug_out_monitor(_monitor_index: u32) -> ResultType<()> {
+    #[cfg(target_os = "macos")]
+    if macos::remove_monitor(_monitor_index) {
+        hbb_common::log::info!(
+            "Virtual monitor {} plugged out, {} left",
+            _monitor_index,
+            macos::monitor_count()
+        );
+    }
     Ok(())
 }
 
+/// Set the modes of the monitor, any resolution, portrait or ultrawide, the first is the current
+/// one. They are kept for the next time the monitor is plugged in.
 #[no_mangle]
 pub fn update_monitor_modes(
     _monitor_index: u32,
     _mode_count: u32,
     _modes: PMonitorMode,
 ) -> ResultType<()> {
+    #[cfg(target_os = "macos")]
+    {
+        if _modes.is_null() || _mode_count == 0 {
+            hbb_common::bail!("No mode for the virtual monitor {}", _monitor_index);
+        }
+        let modes = unsafe {
+            std::slice::from_raw_parts(_modes as *const macos::MonitorMode, _mode_count as _)
+        };
+        macos::update_modes(_monitor_index, modes)?;
+    }
     Ok(())
 }
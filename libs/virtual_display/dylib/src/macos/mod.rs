@@ -0,0 +1,212 @@
+//! Virtual monitors with `CGVirtualDisplay` of macOS 11+, see `virtual_display.m`.
+//!
+//! There is no driver to install, the monitors exist while the process holding them runs, so
+//! `video_service` captures them as any other display. The modes set for a monitor index are kept
+//! when it is plugged out, and used again the next time it is plugged in.
+
+use hbb_common::{bail, ResultType};
+use std::{collections::HashMap, os::raw::c_ulong, sync::Mutex};
+
+// The sides of the modes, in pixels, wide enough for the portrait and the ultrawide monitors.
+const MIN_SIDE: u32 = 480;
+const MAX_SIDE: u32 = 8192;
+const MAX_MODES: usize = 32;
+const DEFAULT_REFRESH_RATE: u32 = 60;
+
+lazy_static::lazy_static! {
+    static ref MODES: Mutex<HashMap<u32, Vec<MonitorMode>>> = Default::default();
+}
+
+/// The modes a monitor is plugged in with, until others are set with `update_monitor_modes`.
+pub const DEFAULT_MODES: [MonitorMode; 3] = [
+    MonitorMode {
+        width: 1920,
+        height: 1080,
+        sync: 60,
+    },
+    MonitorMode {
+        width: 2560,
+        height: 1440,
+        sync: 60,
+    },
+    MonitorMode {
+        width: 1280,
+        height: 720,
+        sync: 60,
+    },
+];
+
+/// As `MonitorMode` of the `virtual_display` crate, `sync` is the refresh rate.
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct MonitorMode {
+    pub width: c_ulong,
+    pub height: c_ulong,
+    pub sync: c_ulong,
+}
+
+extern "C" {
+    fn VdIsAvailable() -> bool;
+    fn VdSetMonitor(
+        index: u32,
+        widths: *const u32,
+        heights: *const u32,
+        rates: *const u32,
+        count: u32,
+    ) -> u32;
+    fn VdMonitorDisplayId(index: u32) -> u32;
+    fn VdRemoveMonitor(index: u32) -> bool;
+    fn VdMonitorCount() -> u32;
+    fn VdRemoveAllMonitors();
+}
+
+pub fn is_available() -> bool {
+    unsafe { VdIsAvailable() }
+}
+
+/// The modes macOS can show, in their order without the duplicates, the refresh rate 60 if it is
+/// not set.
+pub fn valid_modes(modes: &[MonitorMode]) -> Vec<MonitorMode> {
+    let side = |v: c_ulong| (MIN_SIDE as c_ulong..=MAX_SIDE as c_ulong).contains(&v);
+    let mut valid: Vec<MonitorMode> = vec![];
+    for m in modes.iter().filter(|m| side(m.width) && side(m.height)) {
+        let m = MonitorMode {
+            sync: if m.sync == 0 {
+                DEFAULT_REFRESH_RATE as c_ulong
+            } else {
+                m.sync
+            },
+            ..*m
+        };
+        if !valid.contains(&m) && valid.len() < MAX_MODES {
+            valid.push(m);
+        }
+    }
+    valid
+}
+
+/// The modes of the monitor, the last ones set or the default ones.
+pub fn modes(index: u32) -> Vec<MonitorMode> {
+    MODES
+        .lock()
+        .unwrap()
+        .get(&index)
+        .cloned()
+        .unwrap_or_else(|| DEFAULT_MODES.to_vec())
+}
+
+/// Keep the modes of the monitor, and apply them if it is plugged in. The first is the current
+/// mode.
+pub fn update_modes(index: u32, modes: &[MonitorMode]) -> ResultType<()> {
+    let modes = valid_modes(modes);
+    if modes.is_empty() {
+        bail!("No valid mode for the virtual monitor {}", index);
+    }
+    MODES.lock().unwrap().insert(index, modes.clone());
+    if display_id(index).is_some() {
+        set_monitor(index, &modes)?;
+    }
+    Ok(())
+}
+
+/// The modes as the columns `VdSetMonitor` takes, without the empty ones, the first is the
+/// current mode.
+fn split_modes(modes: &[MonitorMode]) -> (Vec<u32>, Vec<u32>, Vec<u32>) {
+    let modes = modes.iter().filter(|m| m.width > 0 && m.height > 0);
+    (
+        modes.clone().map(|m| m.width as u32).collect(),
+        modes.clone().map(|m| m.height as u32).collect(),
+        modes.map(|m| m.sync as u32).collect(),
+    )
+}
+
+/// Add the monitor or change its modes, the id of its display.
+pub fn set_monitor(index: u32, modes: &[MonitorMode]) -> ResultType<u32> {
+    if !is_available() {
+        bail!("CGVirtualDisplay is not available, macOS 11 or later is required");
+    }
+    let (widths, heights, rates) = split_modes(modes);
+    if widths.is_empty() {
+        bail!("No valid mode for the virtual monitor {}", index);
+    }
+    let id = unsafe {
+        VdSetMonitor(
+            index,
+            widths.as_ptr(),
+            heights.as_ptr(),
+            rates.as_ptr(),
+            widths.len() as _,
+        )
+    };
+    if id == 0 {
+        bail!("Failed to set the virtual monitor {}", index);
+    }
+    Ok(id)
+}
+
+pub fn display_id(index: u32) -> Option<u32> {
+    match unsafe { VdMonitorDisplayId(index) } {
+        0 => None,
+        id => Some(id),
+    }
+}
+
+pub fn remove_monitor(index: u32) -> bool {
+    unsafe { VdRemoveMonitor(index) }
+}
+
+pub fn monitor_count() -> u32 {
+    unsafe { VdMonitorCount() }
+}
+
+pub fn remove_all_monitors() {
+    unsafe { VdRemoveAllMonitors() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtual_display_valid_modes() {
+        let mode = |width, height, sync| MonitorMode {
+            width,
+            height,
+            sync,
+        };
+        let modes = [
+            mode(3440, 1440, 0),
+            mode(1080, 1920, 60),
+            mode(3440, 1440, 60),
+            mode(320, 240, 60),
+            mode(10000, 1440, 60),
+            mode(5120, 1440, 120),
+        ];
+        assert_eq!(
+            valid_modes(&modes),
+            vec![
+                mode(3440, 1440, 60),
+                mode(1080, 1920, 60),
+                mode(5120, 1440, 120)
+            ]
+        );
+        assert!(valid_modes(&modes[3..5]).is_empty());
+    }
+
+    #[test]
+    fn virtual_display_modes() {
+        let mut modes = DEFAULT_MODES.to_vec();
+        modes.insert(
+            1,
+            MonitorMode {
+                width: 0,
+                height: 900,
+                sync: 60,
+            },
+        );
+        let (widths, heights, rates) = split_modes(&modes);
+        assert_eq!(widths, vec![1920, 2560, 1280]);
+        assert_eq!(heights, vec![1080, 1440, 720]);
+        assert_eq!(rates, vec![60; 3]);
+    }
+}
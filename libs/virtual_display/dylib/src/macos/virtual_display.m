@@ -0,0 +1,193 @@
+// Virtual monitors on macOS 11+ with the private CGVirtualDisplay API, as used by the virtual
+// display apps. A monitor is a CGVirtualDisplay object, the system adds the display when it is
+// created and removes it when it is released, or when the process exits.
+//
+// Built without ARC, the objects are retained and released by hand.
+
+#import <CoreGraphics/CoreGraphics.h>
+#import <Foundation/Foundation.h>
+
+@interface CGVirtualDisplayDescriptor : NSObject
+@property(retain, nonatomic) dispatch_queue_t queue;
+@property(retain, nonatomic) NSString *name;
+@property(nonatomic) unsigned int maxPixelsHigh;
+@property(nonatomic) unsigned int maxPixelsWide;
+@property(nonatomic) CGSize sizeInMillimeters;
+@property(nonatomic) unsigned int serialNum;
+@property(nonatomic) unsigned int productID;
+@property(nonatomic) unsigned int vendorID;
+@end
+
+@interface CGVirtualDisplayMode : NSObject
+- (instancetype)initWithWidth:(unsigned int)width height:(unsigned int)height refreshRate:(double)refreshRate;
+@end
+
+@interface CGVirtualDisplaySettings : NSObject
+@property(retain, nonatomic) NSArray *modes;
+@property(nonatomic) unsigned int hiDPI;
+@end
+
+@interface CGVirtualDisplay : NSObject
+@property(readonly, nonatomic) unsigned int displayID;
+- (instancetype)initWithDescriptor:(CGVirtualDisplayDescriptor *)descriptor;
+- (BOOL)applySettings:(CGVirtualDisplaySettings *)settings;
+@end
+
+// The monitors by their index, guarded by @synchronized on the dictionary, and the size each one
+// was created for.
+static NSMutableDictionary *g_monitors = nil;
+static NSMutableDictionary *g_maxSizes = nil;
+
+static NSMutableDictionary *Monitors(void) {
+    static dispatch_once_t once;
+    dispatch_once(&once, ^{
+        g_monitors = [[NSMutableDictionary alloc] init];
+        g_maxSizes = [[NSMutableDictionary alloc] init];
+    });
+    return g_monitors;
+}
+
+static NSMutableDictionary *MaxSizes(void) {
+    Monitors();
+    return g_maxSizes;
+}
+
+bool VdIsAvailable(void) {
+    return NSClassFromString(@"CGVirtualDisplay") != nil &&
+           NSClassFromString(@"CGVirtualDisplayDescriptor") != nil &&
+           NSClassFromString(@"CGVirtualDisplaySettings") != nil &&
+           NSClassFromString(@"CGVirtualDisplayMode") != nil;
+}
+
+static NSArray *MakeModes(const uint32_t *widths, const uint32_t *heights, const uint32_t *rates,
+                          uint32_t count, uint32_t *maxWidth, uint32_t *maxHeight) {
+    Class modeClass = NSClassFromString(@"CGVirtualDisplayMode");
+    NSMutableArray *modes = [NSMutableArray arrayWithCapacity:count];
+    *maxWidth = 0;
+    *maxHeight = 0;
+    for (uint32_t i = 0; i < count; i++) {
+        if (widths[i] == 0 || heights[i] == 0) {
+            continue;
+        }
+        double rate = rates[i] > 0 ? rates[i] : 60;
+        CGVirtualDisplayMode *mode =
+            [[[modeClass alloc] initWithWidth:widths[i] height:heights[i] refreshRate:rate] autorelease];
+        [modes addObject:mode];
+        *maxWidth = MAX(*maxWidth, widths[i]);
+        *maxHeight = MAX(*maxHeight, heights[i]);
+    }
+    return modes;
+}
+
+// A new display which can show the modes up to maxWidth x maxHeight, retained, nil on failure.
+static CGVirtualDisplay *CreateDisplay(uint32_t index, uint32_t maxWidth, uint32_t maxHeight) {
+    Class displayClass = NSClassFromString(@"CGVirtualDisplay");
+    Class descriptorClass = NSClassFromString(@"CGVirtualDisplayDescriptor");
+    CGVirtualDisplayDescriptor *descriptor = [[[descriptorClass alloc] init] autorelease];
+    descriptor.queue = dispatch_get_global_queue(DISPATCH_QUEUE_PRIORITY_DEFAULT, 0);
+    descriptor.name = [NSString stringWithFormat:@"RustDesk Virtual Display %u", index + 1];
+    descriptor.maxPixelsWide = maxWidth;
+    descriptor.maxPixelsHigh = maxHeight;
+    // 96 DPI at the largest mode, in the shape of the monitor, the size only decides the DPI
+    // reported.
+    descriptor.sizeInMillimeters = CGSizeMake(maxWidth * 25.4 / 96, maxHeight * 25.4 / 96);
+    descriptor.vendorID = 0x5253;
+    descriptor.productID = 0x4456;
+    // Distinct serials, so macOS keeps the arrangement of each monitor.
+    descriptor.serialNum = index + 1;
+    return [[displayClass alloc] initWithDescriptor:descriptor];
+}
+
+static bool ApplyModes(CGVirtualDisplay *display, NSArray *modes) {
+    Class settingsClass = NSClassFromString(@"CGVirtualDisplaySettings");
+    CGVirtualDisplaySettings *settings = [[[settingsClass alloc] init] autorelease];
+    settings.hiDPI = 0;
+    settings.modes = modes;
+    return [display applySettings:settings];
+}
+
+// Set the modes of the monitor, the first is the current one, and add it if it does not exist.
+// A monitor which can not show the larger modes is replaced. Returns the display id, 0 on failure.
+uint32_t VdSetMonitor(uint32_t index, const uint32_t *widths, const uint32_t *heights,
+                      const uint32_t *rates, uint32_t count) {
+    if (!VdIsAvailable()) {
+        NSLog(@"VdSetMonitor: CGVirtualDisplay is not available");
+        return 0;
+    }
+    @autoreleasepool {
+        uint32_t maxWidth, maxHeight;
+        NSArray *modes = MakeModes(widths, heights, rates, count, &maxWidth, &maxHeight);
+        if (modes.count == 0) {
+            NSLog(@"VdSetMonitor: No valid mode for the monitor %u", index);
+            return 0;
+        }
+        NSMutableDictionary *monitors = Monitors();
+        @synchronized(monitors) {
+            NSNumber *key = @(index);
+            NSMutableDictionary *maxSizes = MaxSizes();
+            CGVirtualDisplay *display = monitors[key];
+            if (display != nil) {
+                // The modes of a display can not be larger than the size it was created for.
+                CGSize max = [maxSizes[key] sizeValue];
+                bool fits = max.width >= maxWidth && max.height >= maxHeight;
+                if (fits && ApplyModes(display, modes)) {
+                    return display.displayID;
+                }
+                [monitors removeObjectForKey:key];
+                [maxSizes removeObjectForKey:key];
+            }
+            display = CreateDisplay(index, maxWidth, maxHeight);
+            if (display == nil) {
+                NSLog(@"VdSetMonitor: Failed to create the monitor %u", index);
+                return 0;
+            }
+            if (!ApplyModes(display, modes)) {
+                NSLog(@"VdSetMonitor: Failed to apply the modes of the monitor %u", index);
+                [display release];
+                return 0;
+            }
+            uint32_t displayId = display.displayID;
+            monitors[key] = display;
+            maxSizes[key] = [NSValue valueWithSize:NSMakeSize(maxWidth, maxHeight)];
+            [display release];
+            return displayId;
+        }
+    }
+}
+
+// The display id of the monitor, 0 if it does not exist.
+uint32_t VdMonitorDisplayId(uint32_t index) {
+    NSMutableDictionary *monitors = Monitors();
+    @synchronized(monitors) {
+        CGVirtualDisplay *display = monitors[@(index)];
+        return display != nil ? display.displayID : 0;
+    }
+}
+
+bool VdRemoveMonitor(uint32_t index) {
+    NSMutableDictionary *monitors = Monitors();
+    @synchronized(monitors) {
+        NSNumber *key = @(index);
+        if (monitors[key] == nil) {
+            return false;
+        }
+        [monitors removeObjectForKey:key];
+        [MaxSizes() removeObjectForKey:key];
+        return true;
+    }
+}
+
+uint32_t VdMonitorCount(void) {
+    NSMutableDictionary *monitors = Monitors();
+    @synchronized(monitors) {
+        return (uint32_t)monitors.count;
+    }
+}
+
+void VdRemoveAllMonitors(void) {
+    NSMutableDictionary *monitors = Monitors();
+    @synchronized(monitors) {
+        [monitors removeAllObjects];
+        [MaxSizes() removeAllObjects];
+    }
+}
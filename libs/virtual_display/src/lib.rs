@@ -8,7 +8,7 @@ const LIB_NAME_VIRTUAL_DISPLAY: &str = "dylib_virtual_display";
 
 pub type DWORD = ::std::os::raw::c_ulong;
 #[repr(C)]
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct _MonitorMode {
     pub width: DWORD,
     pub height: DWORD,
@@ -92,8 +92,13 @@ lazy_static::lazy_static! {
     static ref MONITOR_INDICES: Mutex<HashSet<u32>> = Mutex::new(HashSet::new());
 }
 
+// Next to the executable, in the bundle.
 fn get_lib_name() -> String {
-    format!("lib{}.dylib", LIB_NAME_VIRTUAL_DISPLAY)
+    let name = format!("lib{}.dylib", LIB_NAME_VIRTUAL_DISPLAY);
+    match std::env::current_exe() {
+        Ok(exe) => exe.with_file_name(&name).to_string_lossy().to_string(),
+        Err(_) => name,
+    }
 }
 
 pub fn is_device_created() -> bool {
@@ -140,3 +145,83 @@ pub fn uninstall_driver(reboot_required: &mut bool) -> ResultType<()> {
         .uninstall_driver
         .ok_or(anyhow::Error::msg("uninstall_driver method not found"))?(reboot_required)
 }
+
+pub fn plug_in_monitor(monitor_index: u32, edid: u32, retries: u32) -> ResultType<()> {
+    LIB_WRAPPER
+        .lock()
+        .unwrap()
+        .plug_in_monitor
+        .ok_or(anyhow::Error::msg("plug_in_monitor method not found"))?(
+        monitor_index,
+        edid,
+        retries,
+    )
+}
+
+pub fn plug_out_monitor(monitor_index: u32) -> ResultType<()> {
+    LIB_WRAPPER
+        .lock()
+        .unwrap()
+        .plug_out_monitor
+        .ok_or(anyhow::Error::msg("plug_out_monitor method not found"))?(monitor_index)
+}
+
+/// The modes of a list as the client sends it, e.g. "3440x1440@100,1080x1920", the refresh rate
+/// is 60 if it is not set. The first is the current mode.
+pub fn parse_modes(list: &str) -> ResultType<Vec<MonitorMode>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|m| !m.is_empty())
+        .map(|m| {
+            let (size, sync) = m.split_once('@').unwrap_or((m, "60"));
+            let (width, height) = size
+                .split_once('x')
+                .ok_or_else(|| anyhow::anyhow!("Invalid mode {}", m))?;
+            let parse = |v: &str| {
+                v.trim()
+                    .parse::<DWORD>()
+                    .map_err(|_| anyhow::anyhow!("Invalid mode {}", m))
+            };
+            Ok(MonitorMode {
+                width: parse(width)?,
+                height: parse(height)?,
+                sync: parse(sync)?,
+            })
+        })
+        .collect()
+}
+
+/// Set the modes of the monitor, the first is the current one. The library keeps them for the
+/// next time the monitor is plugged in.
+pub fn update_monitor_modes(monitor_index: u32, modes: &[MonitorMode]) -> ResultType<()> {
+    LIB_WRAPPER
+        .lock()
+        .unwrap()
+        .update_monitor_modes
+        .ok_or(anyhow::Error::msg("update_monitor_modes method not found"))?(
+        monitor_index,
+        modes.len() as _,
+        modes.as_ptr() as _,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtual_display_parse_modes() {
+        let mode = |width, height, sync| MonitorMode {
+            width,
+            height,
+            sync,
+        };
+        assert_eq!(
+            parse_modes("3440x1440@100, 1080x1920,").unwrap(),
+            vec![mode(3440, 1440, 100), mode(1080, 1920, 60)]
+        );
+        assert!(parse_modes("").unwrap().is_empty());
+        assert!(parse_modes("1920*1080").is_err());
+        assert!(parse_modes("1920x1080@fast").is_err());
+    }
+}
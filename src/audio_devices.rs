@@ -0,0 +1,101 @@
+//! Selection of the captured audio device of the host by the viewers.
+//!
+//! The client sends a `Request` as a plugin request, with a device to select or without one to
+//! query. The host answers with its input and output devices and the selected one. The pick of a
+//! viewer is dropped when it disconnects, and the client saves it in the `PeerConfig` options under
+//! `PEER_OPTION` to select it again on the next connection.
+
+use crate::plugin_request;
+use hbb_common::message_proto::{Message, Misc, PluginRequest};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "audio-devices";
+pub const PEER_OPTION: &str = "audio-device";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    /// The name of the device, empty for the device of the host settings.
+    #[serde(default)]
+    pub select: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub inputs: Vec<String>,
+    pub outputs: Vec<String>,
+    /// The device picked by the viewers, empty if none.
+    pub selected: String,
+    #[serde(default)]
+    pub error: String,
+}
+
+pub fn make_request(select: Option<String>) -> Message {
+    plugin_request::make(ID, &Request { select })
+}
+
+/// Apply the request of the connection on the host, the answer has the devices and the error.
+pub fn handle_request(conn_id: i32, content: &[u8]) -> Message {
+    let req: Request = match serde_json::from_slice(content) {
+        Ok(req) => req,
+        Err(e) => {
+            return plugin_request::make(
+                ID,
+                &Response {
+                    error: e.to_string(),
+                    ..Default::default()
+                },
+            )
+        }
+    };
+    let (inputs, outputs) = crate::audio_service::get_devices();
+    let mut res = Response {
+        inputs,
+        outputs,
+        ..Default::default()
+    };
+    match req.select {
+        Some(device) if device.is_empty() => {
+            crate::audio_service::set_session_input_device(conn_id, None)
+        }
+        Some(device) => {
+            if res.inputs.contains(&device) || res.outputs.contains(&device) {
+                crate::audio_service::set_session_input_device(conn_id, Some(device));
+            } else {
+                res.error = format!("unknown audio device: {device}");
+            }
+        }
+        None => {}
+    }
+    res.selected = crate::audio_service::get_session_input_device().unwrap_or_default();
+    plugin_request::make(ID, &res)
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{message, misc};
+
+    #[test]
+    fn audio_devices_request() {
+        let msg = make_request(Some("BlackHole 2ch".to_owned()));
+        let Some(message::Union::Misc(m)) = msg.union else {
+            panic!("not a misc message");
+        };
+        let Some(misc::Union::PluginRequest(p)) = m.union else {
+            panic!("not a plugin request");
+        };
+        assert_eq!(p.id, ID);
+        let req: Request = serde_json::from_slice(&p.content).unwrap();
+        assert_eq!(req.select.as_deref(), Some("BlackHole 2ch"));
+        let req: Request = serde_json::from_slice(b"{}").unwrap();
+        assert_eq!(req.select, None);
+        let res =
+            parse_response(br#"{"inputs":["Mic"],"outputs":["Speakers"],"selected":""}"#).unwrap();
+        assert_eq!(res.outputs, vec!["Speakers".to_owned()]);
+        assert!(res.error.is_empty());
+    }
+}
@@ -0,0 +1,175 @@
+//! Bandwidth and round trip time to the host, measured when the session starts.
+//!
+//! The video starts at the default quality and the QoS of the host needs several seconds of delay
+//! samples to find the right one, so the first seconds are either blurry on a fast link or stall
+//! on a slow one. A host with "support_bandwidth_probe" in its platform additions answers a
+//! `Frame::Request` with a burst of `Frame::Chunk`s, `CHUNKS` of `CHUNK` bytes, once per
+//! connection. The client measures the rate they arrive at and the time to the first one, and
+//! sends them back in a `Frame::Result`, which seeds the quality and the fps of `VideoQoS`.
+//!
+//! The content of the frames is binary: the kind of the frame, then its fields in big endian.
+//! `OPTION_BANDWIDTH_PROBE` on "N" turns it off on the client.
+
+use crate::plugin_request;
+use hbb_common::{
+    bytes::{BufMut, Bytes, BytesMut},
+    message_proto::Message,
+};
+use std::time::Instant;
+
+pub const ID: &str = "bandwidth-probe";
+pub const PLATFORM_ADDITION_KEY: &str = "support_bandwidth_probe";
+pub const OPTION_BANDWIDTH_PROBE: &str = "enable-bandwidth-probe";
+pub const CHUNKS: u16 = 16;
+const MAX_CHUNKS: u16 = 64;
+const CHUNK: usize = 16 * 1024;
+
+const FRAME_REQUEST: u8 = 0;
+const FRAME_CHUNK: u8 = 1;
+const FRAME_RESULT: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    Request { chunks: u16 },
+    Chunk { seq: u16, total: u16 },
+    Result { kbps: u32, rtt_ms: u32 },
+}
+
+impl Frame {
+    fn encode(&self) -> Bytes {
+        let mut buf = BytesMut::new();
+        match self {
+            Self::Request { chunks } => {
+                buf.put_u8(FRAME_REQUEST);
+                buf.put_u16(*chunks);
+            }
+            Self::Chunk { seq, total } => {
+                buf.reserve(CHUNK);
+                buf.put_u8(FRAME_CHUNK);
+                buf.put_u16(*seq);
+                buf.put_u16(*total);
+                buf.resize(CHUNK, 0);
+            }
+            Self::Result { kbps, rtt_ms } => {
+                buf.put_u8(FRAME_RESULT);
+                buf.put_u32(*kbps);
+                buf.put_u32(*rtt_ms);
+            }
+        }
+        buf.freeze()
+    }
+
+    pub fn decode(content: &[u8]) -> Option<Self> {
+        let u16_at = |i: usize| Some(u16::from_be_bytes(content.get(i..i + 2)?.try_into().ok()?));
+        let u32_at = |i: usize| Some(u32::from_be_bytes(content.get(i..i + 4)?.try_into().ok()?));
+        match *content.first()? {
+            FRAME_REQUEST => Some(Self::Request { chunks: u16_at(1)? }),
+            FRAME_CHUNK => Some(Self::Chunk {
+                seq: u16_at(1)?,
+                total: u16_at(3)?,
+            }),
+            FRAME_RESULT => Some(Self::Result {
+                kbps: u32_at(1)?,
+                rtt_ms: u32_at(5)?,
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make_raw(ID, self.encode())
+    }
+}
+
+/// The chunks to answer a request with.
+pub fn burst(chunks: u16) -> impl Iterator<Item = Message> {
+    let total = chunks.clamp(1, MAX_CHUNKS);
+    (0..total).map(move |seq| Frame::Chunk { seq, total }.to_message())
+}
+
+/// The measure on the client.
+pub struct Probe {
+    requested: Instant,
+    first: Option<Instant>,
+    rtt_ms: u32,
+    bytes: usize,
+}
+
+impl Probe {
+    pub fn start() -> (Self, Message) {
+        let probe = Self {
+            requested: Instant::now(),
+            first: None,
+            rtt_ms: 0,
+            bytes: 0,
+        };
+        (probe, Frame::Request { chunks: CHUNKS }.to_message())
+    }
+
+    /// The result to send to the host, after the last chunk.
+    pub fn on_chunk(&mut self, seq: u16, total: u16, bytes: usize) -> Option<Frame> {
+        self.on_chunk_at(Instant::now(), seq, total, bytes)
+    }
+
+    fn on_chunk_at(&mut self, now: Instant, seq: u16, total: u16, bytes: usize) -> Option<Frame> {
+        match self.first {
+            // The rate is measured from the end of the first chunk.
+            None => {
+                self.first = Some(now);
+                self.rtt_ms = now.duration_since(self.requested).as_millis() as u32;
+            }
+            Some(_) => self.bytes += bytes,
+        }
+        if seq + 1 < total {
+            return None;
+        }
+        let elapsed_ms = now.duration_since(self.first?).as_millis();
+        // Too fast to be measured, a kbps of 0 keeps the quality of the host.
+        let kbps = if elapsed_ms > 0 {
+            (self.bytes as u128 * 8 / elapsed_ms).min(u32::MAX as u128) as u32
+        } else {
+            0
+        };
+        Some(Frame::Result {
+            kbps,
+            rtt_ms: self.rtt_ms,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn bandwidth_probe_measure() {
+        for frame in [
+            Frame::Request { chunks: 16 },
+            Frame::Chunk { seq: 3, total: 16 },
+            Frame::Result {
+                kbps: 12000,
+                rtt_ms: 40,
+            },
+        ] {
+            assert_eq!(Frame::decode(&frame.encode()), Some(frame));
+        }
+        assert_eq!(Frame::Chunk { seq: 0, total: 1 }.encode().len(), CHUNK);
+        assert_eq!(Frame::decode(&[FRAME_RESULT, 0]), None);
+        assert_eq!(burst(1000).count(), MAX_CHUNKS as usize);
+
+        let (mut probe, _) = Probe::start();
+        let start = probe.requested;
+        let at = |ms| start + Duration::from_millis(ms);
+        assert_eq!(probe.on_chunk_at(at(50), 0, 3, CHUNK), None);
+        assert_eq!(probe.on_chunk_at(at(60), 1, 3, CHUNK), None);
+        // 2 chunks of 128 kbits in 20 ms.
+        assert_eq!(
+            probe.on_chunk_at(at(70), 2, 3, CHUNK),
+            Some(Frame::Result {
+                kbps: 2 * CHUNK as u32 * 8 / 20,
+                rtt_ms: 50,
+            })
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! The same operation on several peers from the command line, for the small fleets without an RMM:
+//! `rustdesk --bulk --peers id1,id2,... (--exec "cmd" | --file-push <path> <remote-dir>)`.
+//!
+//! Each peer gets its own unattended session, logged in with `--password` or the password saved
+//! for it, so nobody has to answer a prompt. `--exec` runs the command in a terminal session of
+//! the peer and exits the shell, the exit code of the command is the result. `--file-push` sends
+//! the file or the folder into the remote folder with a file transfer session, replacing the
+//! files there. The peers are done one by one, or `--parallel` at a time, each within
+//! `--timeout` seconds, and the report has the result of each.
+
+use crate::client::{self, Client, Data, Interface, LoginConfigHandler};
+use async_trait::async_trait;
+use hbb_common::{
+    bail,
+    config::{LocalConfig, READ_TIMEOUT},
+    fs,
+    message_proto::*,
+    protobuf::Message as _,
+    rendezvous_proto::ConnType,
+    timeout,
+    tokio::{
+        self,
+        sync::Semaphore,
+        time::{self, Duration, Instant},
+    },
+    ResultType, Stream,
+};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+};
+
+const USAGE: &str = "Usage: --bulk --peers id1,id2,... (--exec \"cmd\" | --file-push <path> <remote-dir>) [--password <password>] [--parallel N] [--timeout seconds]";
+const DEFAULT_TIMEOUT: u64 = 300;
+const MAX_PARALLEL: usize = 16;
+const TERMINAL_ID: i32 = 1;
+// The lines of the output of a command in the report.
+const MAX_OUTPUT_LINES: usize = 20;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Operation {
+    Exec(String),
+    FilePush { path: String, remote_dir: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Options {
+    pub peers: Vec<String>,
+    pub operation: Operation,
+    pub password: String,
+    pub parallel: usize,
+    pub timeout: u64,
+}
+
+impl Options {
+    /// Parse the arguments after `--bulk`.
+    pub fn parse(args: &[String]) -> ResultType<Self> {
+        let value = |name: &str, n: usize| -> Option<&String> {
+            let pos = args.iter().position(|a| a == name)?;
+            args.get(pos + n)
+        };
+        let peers: Vec<String> = value("--peers", 1)
+            .map(|v| {
+                v.split(',')
+                    .map(|id| id.trim().replace(' ', ""))
+                    .filter(|id| !id.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if peers.is_empty() {
+            bail!(USAGE);
+        }
+        let operation = match (value("--exec", 1), value("--file-push", 1)) {
+            (Some(cmd), None) => Operation::Exec(cmd.clone()),
+            (None, Some(path)) => match value("--file-push", 2) {
+                Some(remote_dir) => {
+                    if !Path::new(path).exists() {
+                        bail!("{} does not exist", path);
+                    }
+                    Operation::FilePush {
+                        path: path.clone(),
+                        remote_dir: remote_dir.clone(),
+                    }
+                }
+                None => bail!(USAGE),
+            },
+            _ => bail!(USAGE),
+        };
+        let parallel = match value("--parallel", 1) {
+            Some(n) => n.parse::<usize>()?.clamp(1, MAX_PARALLEL),
+            None => 1,
+        };
+        let timeout = match value("--timeout", 1) {
+            Some(n) => n.parse::<u64>()?.max(1),
+            None => DEFAULT_TIMEOUT,
+        };
+        Ok(Self {
+            peers,
+            operation,
+            password: value("--password", 1).cloned().unwrap_or_default(),
+            parallel,
+            timeout,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PeerResult {
+    pub id: String,
+    pub error: String,
+    pub exit_code: Option<i32>,
+    pub output: String,
+    pub seconds: f32,
+}
+
+impl PeerResult {
+    pub fn ok(&self) -> bool {
+        self.error.is_empty() && self.exit_code.unwrap_or(0) == 0
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub results: Vec<PeerResult>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for r in self.results.iter() {
+            let status = match (&r.exit_code, r.error.is_empty()) {
+                (_, false) => format!("FAIL  {}", r.error),
+                (Some(code), true) if *code != 0 => format!("FAIL  exit {}", code),
+                (Some(code), true) => format!("ok    exit {}", code),
+                (None, true) => "ok".to_owned(),
+            };
+            writeln!(f, "{}\t{:.1}s\t{}", r.id, r.seconds, status)?;
+            let lines: Vec<&str> = r.output.lines().collect();
+            for line in lines[lines.len().saturating_sub(MAX_OUTPUT_LINES)..].iter() {
+                writeln!(f, "    {}", line)?;
+            }
+        }
+        let ok = self.results.iter().filter(|r| r.ok()).count();
+        write!(f, "{}/{} peers succeeded", ok, self.results.len())
+    }
+}
+
+#[derive(Clone)]
+struct Handler {
+    lc: Arc<RwLock<LoginConfigHandler>>,
+    password: String,
+    error: Arc<Mutex<Option<String>>>,
+}
+
+impl Handler {
+    fn new(id: &str, conn_type: ConnType, password: &str) -> Self {
+        let lc: Arc<RwLock<LoginConfigHandler>> = Default::default();
+        lc.write()
+            .unwrap()
+            .initialize(id.to_owned(), conn_type, None, false, None, None, None);
+        Self {
+            lc,
+            password: password.to_owned(),
+            error: Default::default(),
+        }
+    }
+
+    fn set_error(&self, err: &str) {
+        self.error
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| err.to_owned());
+    }
+
+    fn take_error(&self) -> Option<String> {
+        self.error.lock().unwrap().take()
+    }
+}
+
+#[async_trait]
+impl Interface for Handler {
+    fn send(&self, _data: Data) {}
+
+    // Nobody answers a prompt, so a prompt fails the peer.
+    fn msgbox(&self, msgtype: &str, title: &str, text: &str, _link: &str) {
+        match msgtype {
+            "input-password" => self.set_error("No password, give it with --password"),
+            "re-input-password" => self.set_error(text),
+            msg if msg.contains("error") || msg.contains("input") => {
+                self.set_error(if text.is_empty() { title } else { text })
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_login_error(&self, err: &str) -> bool {
+        self.set_error(err);
+        false
+    }
+
+    fn handle_peer_info(&self, pi: PeerInfo) {
+        self.lc.write().unwrap().handle_peer_info(&pi);
+    }
+
+    fn set_multiple_windows_session(&self, _sessions: Vec<WindowsSession>) {}
+
+    async fn handle_hash(&self, pass: &str, hash: Hash, peer: &mut Stream) {
+        client::handle_hash(self.lc.clone(), pass, hash, self, peer).await;
+    }
+
+    async fn handle_login_from_ui(
+        &self,
+        os_username: String,
+        os_password: String,
+        password: String,
+        remember: bool,
+        peer: &mut Stream,
+    ) {
+        client::handle_login_from_ui(
+            self.lc.clone(),
+            os_username,
+            os_password,
+            password,
+            remember,
+            peer,
+        )
+        .await;
+    }
+
+    async fn handle_test_delay(&self, t: TestDelay, peer: &mut Stream) {
+        client::handle_test_delay(t, peer).await;
+    }
+
+    fn get_lch(&self) -> Arc<RwLock<LoginConfigHandler>> {
+        self.lc.clone()
+    }
+}
+
+async fn next_message(peer: &mut Stream) -> ResultType<Message> {
+    match timeout(READ_TIMEOUT, peer.next()).await? {
+        Some(Ok(bytes)) => Ok(Message::parse_from_bytes(&bytes)?),
+        Some(Err(err)) => bail!("Connection closed: {}", err),
+        None => bail!("Reset by the peer"),
+    }
+}
+
+async fn login(handler: &Handler, peer: &mut Stream) -> ResultType<PeerInfo> {
+    loop {
+        match next_message(peer).await?.union {
+            Some(message::Union::Hash(hash)) => {
+                handler
+                    .handle_hash(&handler.password.clone(), hash, peer)
+                    .await;
+            }
+            Some(message::Union::LoginResponse(lr)) => match lr.union {
+                Some(login_response::Union::Error(err)) => {
+                    handler.handle_login_error(&err);
+                }
+                Some(login_response::Union::PeerInfo(pi)) => {
+                    handler.handle_peer_info(pi.clone());
+                    return Ok(pi);
+                }
+                _ => {}
+            },
+            Some(message::Union::TestDelay(t)) => {
+                handler.handle_test_delay(t, peer).await;
+            }
+            _ => {}
+        }
+        if let Some(err) = handler.take_error() {
+            bail!(err);
+        }
+    }
+}
+
+// Without the escape sequences and the carriage returns of the terminal.
+fn plain_text(output: &[u8]) -> String {
+    lazy_static::lazy_static! {
+        static ref ESCAPE: regex::Regex =
+            regex::Regex::new(r"\x1b\[[0-9;?]*[ -/]*[@-~]|\x1b\][^\x07]*\x07|\r").unwrap();
+    }
+    ESCAPE
+        .replace_all(&String::from_utf8_lossy(output), "")
+        .trim()
+        .to_owned()
+}
+
+async fn exec(peer: &mut Stream, cmd: &str, result: &mut PeerResult) -> ResultType<()> {
+    let send_action = |action: TerminalAction| {
+        let mut msg = Message::new();
+        msg.set_terminal_action(action);
+        msg
+    };
+    let mut action = TerminalAction::new();
+    action.set_open(OpenTerminal {
+        terminal_id: TERMINAL_ID,
+        rows: 24,
+        cols: 200,
+        ..Default::default()
+    });
+    peer.send(&send_action(action)).await?;
+    let mut output = Vec::new();
+    loop {
+        let res = match next_message(peer).await?.union {
+            Some(message::Union::TerminalResponse(res)) => res,
+            Some(message::Union::TestDelay(t)) => {
+                client::handle_test_delay(t, peer).await;
+                continue;
+            }
+            _ => continue,
+        };
+        match res.union {
+            Some(terminal_response::Union::Opened(opened)) => {
+                if !opened.success {
+                    bail!("Failed to open the terminal: {}", opened.message);
+                }
+                // `exit` keeps the exit code of the command.
+                let mut action = TerminalAction::new();
+                action.set_data(TerminalData {
+                    terminal_id: TERMINAL_ID,
+                    data: format!("{}\nexit\n", cmd).into_bytes().into(),
+                    ..Default::default()
+                });
+                peer.send(&send_action(action)).await?;
+            }
+            Some(terminal_response::Union::Data(data)) => {
+                if data.compressed {
+                    output.extend(hbb_common::compress::decompress(&data.data));
+                } else {
+                    output.extend_from_slice(&data.data);
+                }
+            }
+            Some(terminal_response::Union::Closed(closed)) => {
+                result.exit_code = Some(closed.exit_code);
+                break;
+            }
+            Some(terminal_response::Union::Error(err)) => bail!(err.message),
+            _ => {}
+        }
+    }
+    result.output = plain_text(&output);
+    Ok(())
+}
+
+fn remote_join(pi: &PeerInfo, dir: &str, name: &str) -> String {
+    let sep = if pi.platform == "Windows" { '\\' } else { '/' };
+    format!("{}{}{}", dir.trim_end_matches(sep), sep, name)
+}
+
+async fn file_push(
+    peer: &mut Stream,
+    pi: &PeerInfo,
+    path: &str,
+    remote_dir: &str,
+) -> ResultType<()> {
+    let id = 1;
+    let name = Path::new(path)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let to = remote_join(pi, remote_dir, &name);
+    // Without the overwrite detection, the files there are replaced.
+    let job = fs::TransferJob::new_read(
+        id,
+        fs::JobType::Generic,
+        to.clone(),
+        fs::DataSource::FilePath(PathBuf::from(path)),
+        0,
+        false,
+        true,
+        false,
+    )?;
+    let files = job.files().clone();
+    let total_size = job.total_size();
+    let mut read_jobs = vec![job];
+    peer.send(&fs::new_receive(id, to, 0, files, total_size))
+        .await?;
+    let mut timer = time::interval(Duration::from_millis(1));
+    let mut last_recv = Instant::now();
+    loop {
+        tokio::select! {
+            res = peer.next() => {
+                let msg = match res {
+                    Some(Ok(bytes)) => Message::parse_from_bytes(&bytes)?,
+                    Some(Err(err)) => bail!("Connection closed: {}", err),
+                    None => bail!("Reset by the peer"),
+                };
+                last_recv = Instant::now();
+                match msg.union {
+                    Some(message::Union::FileResponse(res)) => match res.union {
+                        Some(file_response::Union::Done(d)) if d.id == id => return Ok(()),
+                        Some(file_response::Union::Error(e)) if e.id == id => bail!(e.error),
+                        _ => {}
+                    },
+                    Some(message::Union::TestDelay(t)) => {
+                        client::handle_test_delay(t, peer).await;
+                    }
+                    _ => {}
+                }
+            }
+            _ = timer.tick() => {
+                if !read_jobs.is_empty() {
+                    fs::handle_read_jobs(&mut read_jobs, peer).await?;
+                } else if last_recv.elapsed() >= READ_TIMEOUT {
+                    bail!("Timeout");
+                }
+            }
+        }
+    }
+}
+
+async fn run_peer(id: &str, options: &Options, result: &mut PeerResult) -> ResultType<()> {
+    let conn_type = match options.operation {
+        Operation::Exec(_) => ConnType::TERMINAL,
+        Operation::FilePush { .. } => ConnType::FILE_TRANSFER,
+    };
+    let handler = Handler::new(id, conn_type, &options.password);
+    let key = crate::get_key(false).await;
+    let token = LocalConfig::get_option("access_token");
+    let ((mut peer, direct, _pk, _kcp, _stream_type), (feedback, rendezvous_server)) =
+        Client::start(id, &key, &token, conn_type, handler.clone()).await?;
+    handler.update_direct(Some(direct));
+    let _keep_it = client::hc_connection(feedback, rendezvous_server, &token).await;
+    let pi = login(&handler, &mut peer).await?;
+    match &options.operation {
+        Operation::Exec(cmd) => exec(&mut peer, cmd, result).await,
+        Operation::FilePush { path, remote_dir } => {
+            file_push(&mut peer, &pi, path, remote_dir).await
+        }
+    }
+}
+
+#[tokio::main(flavor = "multi_thread")]
+pub async fn run(options: Options) -> Report {
+    let options = Arc::new(options);
+    let semaphore = Arc::new(Semaphore::new(options.parallel));
+    let mut tasks = vec![];
+    for id in options.peers.clone() {
+        let options = options.clone();
+        let semaphore = semaphore.clone();
+        tasks.push(tokio::spawn(async move {
+            let _permit = semaphore.acquire().await;
+            let start = Instant::now();
+            let mut result = PeerResult {
+                id: id.clone(),
+                ..Default::default()
+            };
+            let res = timeout(options.timeout * 1000, run_peer(&id, &options, &mut result)).await;
+            match res {
+                Ok(Ok(())) => {}
+                Ok(Err(err)) => result.error = err.to_string(),
+                Err(_) => result.error = "Timeout".to_owned(),
+            }
+            result.seconds = start.elapsed().as_secs_f32();
+            println!("{}: {}", id, if result.ok() { "done" } else { "failed" });
+            result
+        }));
+    }
+    let mut results = vec![];
+    for (task, id) in tasks.into_iter().zip(options.peers.iter()) {
+        results.push(task.await.unwrap_or_else(|err| PeerResult {
+            id: id.clone(),
+            error: err.to_string(),
+            ..Default::default()
+        }));
+    }
+    Report { results }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn bulk_options() {
+        let o = Options::parse(&args(&[
+            "--peers",
+            "123 456 789, 987654321",
+            "--exec",
+            "uptime",
+        ]))
+        .unwrap();
+        assert_eq!(o.peers, vec!["123456789", "987654321"]);
+        assert_eq!(o.operation, Operation::Exec("uptime".to_owned()));
+        assert_eq!((o.parallel, o.timeout), (1, DEFAULT_TIMEOUT));
+        let o = Options::parse(&args(&[
+            "--peers",
+            "1",
+            "--exec",
+            "ls",
+            "--parallel",
+            "100",
+            "--timeout",
+            "0",
+        ]))
+        .unwrap();
+        assert_eq!((o.parallel, o.timeout), (MAX_PARALLEL, 1));
+        assert!(Options::parse(&args(&["--exec", "ls"])).is_err());
+        assert!(Options::parse(&args(&["--peers", "1"])).is_err());
+        assert!(Options::parse(&args(&[
+            "--peers",
+            "1",
+            "--exec",
+            "ls",
+            "--file-push",
+            "a",
+            "b"
+        ]))
+        .is_err());
+        assert!(Options::parse(&args(&[
+            "--peers",
+            "1",
+            "--file-push",
+            "/no/such/file",
+            "/tmp"
+        ]))
+        .is_err());
+
+        assert_eq!(
+            plain_text(b"\x1b[1;32m$ \x1b[0muptime\r\n up 3 days\r\n"),
+            "$ uptime\n up 3 days"
+        );
+        let pi = PeerInfo {
+            platform: "Windows".to_owned(),
+            ..Default::default()
+        };
+        assert_eq!(
+            remote_join(&pi, "C:\\Users\\a\\", "b.txt"),
+            "C:\\Users\\a\\b.txt"
+        );
+
+        let report = Report {
+            results: vec![
+                PeerResult {
+                    id: "1".to_owned(),
+                    exit_code: Some(0),
+                    ..Default::default()
+                },
+                PeerResult {
+                    id: "2".to_owned(),
+                    error: "Wrong Password".to_owned(),
+                    ..Default::default()
+                },
+            ],
+        };
+        let text = report.to_string();
+        assert!(text.contains("2\t0.0s\tFAIL  Wrong Password"));
+        assert!(text.ends_with("1/2 peers succeeded"));
+    }
+}
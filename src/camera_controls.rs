@@ -0,0 +1,116 @@
+//! Camera controls for the viewers of the host cameras: zoom, pan, tilt, focus, exposure
+//! and white balance, as far as the camera supports them.
+//!
+//! The client sends a `Request` for a camera, as a plugin request, with a control to set or without
+//! one to query. The host answers with the controls of the camera after applying it. Only the
+//! hosts which capture their cameras handle it, with `PLATFORM_ADDITION_KEY` in their platform
+//! additions. The zoom, pan and tilt are digital, see `scrap::camera::Controls`.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+#[cfg(target_os = "macos")]
+use scrap::camera::Controls;
+use scrap::camera::{Control, ControlState};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "camera-controls";
+pub const PLATFORM_ADDITION_KEY: &str = "support_camera_controls";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SetControl {
+    pub control: Control,
+    pub auto: bool,
+    #[serde(default)]
+    pub value: Option<f32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub display: usize,
+    #[serde(default)]
+    pub set: Option<SetControl>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub display: usize,
+    pub controls: Vec<ControlState>,
+    #[serde(default)]
+    pub error: String,
+}
+
+pub fn make_request(display: usize, set: Option<SetControl>) -> Message {
+    plugin_request::make(ID, &Request { display, set })
+}
+
+/// Apply the request on the host, the answer has the controls of the camera and the error.
+#[cfg(target_os = "macos")]
+pub fn handle_request(content: &[u8]) -> Message {
+    let req: Request = match serde_json::from_slice(content) {
+        Ok(req) => req,
+        Err(e) => {
+            return plugin_request::make(
+                ID,
+                &Response {
+                    error: e.to_string(),
+                    ..Default::default()
+                },
+            )
+        }
+    };
+    let mut res = Response {
+        display: req.display,
+        ..Default::default()
+    };
+    if let Some(set) = req.set {
+        if let Err(e) = Controls::set(req.display, set.control, set.auto, set.value) {
+            res.error = e.to_string();
+        }
+    }
+    match Controls::get(req.display) {
+        Ok(controls) => res.controls = controls,
+        Err(e) => {
+            if res.error.is_empty() {
+                res.error = e.to_string();
+            }
+        }
+    }
+    plugin_request::make(ID, &res)
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{message, misc};
+
+    #[test]
+    fn camera_controls_request() {
+        let msg = make_request(
+            1,
+            Some(SetControl {
+                control: Control::WhiteBalance,
+                auto: false,
+                value: None,
+            }),
+        );
+        let Some(message::Union::Misc(m)) = msg.union else {
+            panic!("not a misc message");
+        };
+        let Some(misc::Union::PluginRequest(p)) = m.union else {
+            panic!("not a plugin request");
+        };
+        assert_eq!(p.id, ID);
+        let req: Request = serde_json::from_slice(&p.content).unwrap();
+        assert_eq!(req.display, 1);
+        assert_eq!(req.set.unwrap().control, Control::WhiteBalance);
+        assert!(String::from_utf8_lossy(&p.content).contains("\"white_balance\""));
+        let res = parse_response(br#"{"display":0,"controls":[{"control":"focus","auto":true}]}"#)
+            .unwrap();
+        assert_eq!(res.controls[0].control, Control::Focus);
+        assert_eq!(res.controls[0].value, None);
+    }
+}
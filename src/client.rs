@@ -82,6 +82,8 @@ use crate::ui_session_interface::SessionPermissionConfig;
 pub use super::lang::*;
 
 pub mod file_trait;
+pub mod frame_pacer;
+pub mod frame_transform;
 pub mod helper;
 pub mod io_loop;
 pub mod screenshot;
@@ -107,6 +109,8 @@ pub const LOGIN_MSG_2FA_WRONG: &str = "Wrong 2FA Code";
 pub const REQUIRE_2FA: &'static str = "2FA Required";
 pub const LOGIN_MSG_NO_PASSWORD_ACCESS: &str = "No Password Access";
 pub const LOGIN_MSG_OFFLINE: &str = "Offline";
+// "host-busy-{<position in queue>}-tip"
+pub const LOGIN_MSG_HOST_BUSY_PREFIX: &str = "host-busy-{";
 pub const LOGIN_SCREEN_WAYLAND: &str = "Wayland login screen is not supported";
 pub const SCRAP_X11_REQUIRED: &str = "x11 expected";
 pub const SCRAP_X11_REF_URL: &str = "https://rustdesk.com/docs/en/manual/linux/#x11-required";
@@ -1088,6 +1092,17 @@ impl ClientClipboardHandler {
                 return;
             }
 
+            let plain_text_only = ctx
+                .cfg
+                .lc
+                .read()
+                .unwrap()
+                .get_toggle_option(crate::clipboard::rich_text::OPTION_PLAIN_TEXT_ONLY);
+            let msg = if plain_text_only {
+                crate::clipboard::rich_text::plain_text_msg(&msg)
+            } else {
+                msg
+            };
             let pi = ctx.cfg.lc.read().unwrap().peer_info.clone();
             if let Some(pi) = pi.as_ref() {
                 if let Some(message::Union::MultiClipboards(multi_clipboards)) = &msg.union {
@@ -1520,6 +1535,8 @@ impl VideoHandler {
                 dir: crate::ui_interface::video_save_directory(false),
                 display_idx,
                 camera,
+                container: Default::default(),
+                rotate: None,
                 tx: None,
             })
             .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))));
@@ -1613,6 +1630,16 @@ pub struct LoginConfigHandler {
     pub mark_unsupported: Vec<CodecFormat>,
     pub selected_windows_session_id: Option<u32>,
     pub peer_info: Option<PeerInfo>,
+    // Current resolution of the displays, frames downscaled by the peer are scaled back to it.
+    pub display_sizes: HashMap<usize, (usize, usize)>,
+    // The peer encodes a key frame on request, without recreating the encoder.
+    pub support_key_frame_request: bool,
+    // The stable ids of the displays of the peer, by index, empty if it sends none.
+    pub display_ids: Vec<String>,
+    // The peer does not allow recording the session or taking screenshots.
+    pub do_not_record: bool,
+    // The microphone is sent to the peer outside of a voice call.
+    pub talk_back: bool,
     password_source: PasswordSource, // where the sent password comes from
     shared_password: Option<String>, // Store the shared password
     pub enable_trusted_devices: bool,
@@ -2411,6 +2438,14 @@ impl LoginConfigHandler {
                 .options
                 .insert("force-always-relay".to_owned(), "Y".to_owned());
         }
+        if let Ok(additions) =
+            serde_json::from_str::<HashMap<String, serde_json::Value>>(&pi.platform_additions)
+        {
+            let identity = crate::peer_identity::Identity::from_platform_additions(&additions);
+            if identity.save_to_peer_options(&mut config.options) {
+                log::info!("display name of {}: {}", self.id, identity.display_name);
+            }
+        }
         #[cfg(feature = "flutter")]
         {
             // sync connected password to personal ab automatically if it is not shared password
@@ -2640,6 +2675,7 @@ pub fn start_video_thread<F, T>(
     fps: Arc<RwLock<Option<usize>>>,
     chroma: Arc<RwLock<Option<Chroma>>>,
     discard_queue: Arc<RwLock<bool>>,
+    decode_dropped: Arc<RwLock<usize>>,
     video_callback: F,
 ) where
     F: 'static + FnMut(usize, &mut scrap::ImageRgb, *mut c_void, bool) + Send,
@@ -2655,6 +2691,10 @@ pub fn start_video_thread<F, T>(
         let mut count = 0;
         let mut duration = std::time::Duration::ZERO;
         let mut skip_beginning = 0;
+        let mut frame_pacer = (LocalConfig::get_option(frame_pacer::OPTION_FRAME_PACING) == "Y")
+            .then(frame_pacer::FramePacer::new);
+        let mut upscaled = scrap::ImageRgb::new(scrap::ImageFormat::ARGB, 0);
+        let mut upscale_mid = Vec::new();
         loop {
             if let Ok(data) = video_receiver.recv() {
                 match data {
@@ -2667,6 +2707,7 @@ pub fn start_video_thread<F, T>(
                             MediaData::VideoQueue => {
                                 if let Some(vf) = video_queue.read().unwrap().pop() {
                                     if discard_queue.read().unwrap().clone() {
+                                        *decode_dropped.write().unwrap() += 1;
                                         continue;
                                     }
                                     vf
@@ -2682,6 +2723,7 @@ pub fn start_video_thread<F, T>(
                         let display = vf.display as usize;
                         let start = std::time::Instant::now();
                         let format = CodecFormat::from(&vf);
+                        let pts = frame_pacer::frame_pts(&vf);
                         if video_handler.is_none() {
                             let mut handler = VideoHandler::new(format, display);
                             let record_state = session.lc.read().unwrap().record_state;
@@ -2698,9 +2740,49 @@ pub fn start_video_thread<F, T>(
                             let format_changed = handler.decoder.format() != format;
                             match handler.handle_frame(vf, &mut pixelbuffer, &mut tmp_chroma) {
                                 Ok(true) => {
+                                    let mut wait = Duration::ZERO;
+                                    if let (Some(pacer), Some(pts)) = (frame_pacer.as_mut(), pts) {
+                                        let queued = video_queue.read().unwrap().len();
+                                        wait = pacer.wait_time(pts, start, queued);
+                                        if !wait.is_zero() {
+                                            std::thread::sleep(wait);
+                                        }
+                                    }
+                                    let size = session
+                                        .lc
+                                        .read()
+                                        .unwrap()
+                                        .display_sizes
+                                        .get(&display)
+                                        .cloned();
+                                    let rgb = match size {
+                                        Some((w, h))
+                                            if pixelbuffer
+                                                && handler.rgb.w < w
+                                                && handler.rgb.h < h =>
+                                        {
+                                            // Downscaled by the peer under congestion.
+                                            upscaled.set_align(handler.rgb.align());
+                                            match scrap::convert::scale_rotate_rgb(
+                                                &handler.rgb,
+                                                &mut upscaled,
+                                                w,
+                                                h,
+                                                0,
+                                                &mut upscale_mid,
+                                            ) {
+                                                Ok(()) => &mut upscaled,
+                                                Err(e) => {
+                                                    log::error!("upscale frame error, {}", e);
+                                                    &mut handler.rgb
+                                                }
+                                            }
+                                        }
+                                        _ => &mut handler.rgb,
+                                    };
                                     video_callback(
                                         display,
-                                        &mut handler.rgb,
+                                        rgb,
                                         handler.texture.texture,
                                         pixelbuffer,
                                     );
@@ -2716,7 +2798,8 @@ pub fn start_video_thread<F, T>(
                                         &mut skip_beginning,
                                         &fps,
                                         format_changed,
-                                        start.elapsed(),
+                                        // The decoding speed, without the pacing.
+                                        start.elapsed().saturating_sub(wait),
                                         &mut count,
                                         &mut duration,
                                     );
@@ -2734,7 +2817,10 @@ pub fn start_video_thread<F, T>(
                                     //
                                     // to-do: fix the error
                                     log::error!("handle video frame error, {}", e);
-                                    session.refresh_video(display as _);
+                                    *decode_dropped.write().unwrap() += 1;
+                                    if !session.request_key_frame(display as _) {
+                                        session.refresh_video(display as _);
+                                    }
                                 }
                                 _ => {}
                             }
@@ -3137,6 +3223,16 @@ pub fn handle_login_error(
     err: &str,
     interface: &impl Interface,
 ) -> bool {
+    crate::ui_interface::push_a11y_event(
+        crate::ui_interface::A11Y_EVENT_PERMISSION_DENIED,
+        &lc.read().unwrap().id,
+        format!(
+            "{} {}",
+            translate("a11y-access-denied-tip".to_owned()),
+            translate(err.to_owned())
+        ),
+        serde_json::json!({ "error": err }),
+    );
     if err == LOGIN_MSG_PASSWORD_EMPTY {
         lc.write().unwrap().password = Default::default();
         interface.msgbox("input-password", "Password Required", "", "");
@@ -3154,6 +3250,12 @@ pub fn handle_login_error(
         }
         interface.msgbox("input-2fa", err, "", "");
         true
+    } else if let Some(challenge) = err.strip_prefix(crate::auth_provider::LOGIN_MSG_PREFIX) {
+        interface.msgbox("auth-provider", "Authentication", challenge, "");
+        true
+    } else if err.starts_with(LOGIN_MSG_HOST_BUSY_PREFIX) {
+        interface.msgbox("host-busy", "Host is busy", err, "");
+        false
     } else if LOGIN_ERROR_MAP.contains_key(err) {
         if let Some(msgbox_info) = LOGIN_ERROR_MAP.get(err) {
             interface.msgbox(
@@ -3490,6 +3592,8 @@ pub enum Data {
     Login((String, String, String, bool)),
     Message(Message),
     SendFiles((i32, JobType, String, String, i32, bool, bool)),
+    // The job id, the `folder_sync::Mode` and the path of the job on the peer.
+    SyncFiles((i32, crate::folder_sync::Mode, String)),
     RemoveDirAll((i32, String, bool, bool)),
     ConfirmDeleteFiles((i32, i32)),
     SetNoConfirm(i32),
@@ -3499,6 +3603,8 @@ pub enum Data {
     CancelJob(i32),
     RemovePortForward(i32),
     AddPortForward((i32, String, i32)),
+    AddMuxForward((i32, String, i32)),
+    RemoveMuxForward(i32),
     NewRDP,
     SetConfirmOverrideFile((i32, i32, bool, bool, bool)),
     AddJob((i32, JobType, String, String, i32, bool, bool)),
@@ -3508,9 +3614,11 @@ pub enum Data {
     ElevateWithLogon(String, String),
     NewVoiceCall,
     CloseVoiceCall,
+    TalkBack(bool),
     ResetDecoder(Option<usize>),
     RenameFile((i32, String, String, bool)),
     TakeScreenshot((i32, String)),
+    CancelFilePaste,
 }
 
 /// Keycode for key events.
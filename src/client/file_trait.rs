@@ -33,12 +33,15 @@ pub trait FileManager: Interface {
         fs::update_next_job_id(id);
     }
 
-    #[cfg(not(any(
-        target_os = "android",
-        target_os = "ios",
-        feature = "cli",
-        feature = "flutter"
-    )))]
+    #[cfg(all(
+        feature = "ui",
+        not(any(
+            target_os = "android",
+            target_os = "ios",
+            feature = "cli",
+            feature = "flutter"
+        ))
+    ))]
     fn read_dir(&self, path: String, include_hidden: bool) -> sciter::Value {
         match fs::read_dir(&fs::get_path(&path), include_hidden) {
             Err(_) => sciter::Value::null(),
@@ -140,6 +143,33 @@ pub trait FileManager: Interface {
         )));
     }
 
+    /// Send the files in the folder sync mode, `mode` is "meta" or "hash".
+    fn sync_files(
+        &self,
+        id: i32,
+        path: String,
+        to: String,
+        include_hidden: bool,
+        is_remote: bool,
+        mode: &str,
+    ) {
+        let Some(mode) = crate::folder_sync::Mode::parse(mode) else {
+            log::warn!("Invalid folder sync mode: {}", mode);
+            return;
+        };
+        let remote = if is_remote { path.clone() } else { to.clone() };
+        self.send(Data::SyncFiles((id, mode, remote)));
+        self.send_files(
+            id,
+            fs::JobType::Generic.into(),
+            path,
+            to,
+            0,
+            include_hidden,
+            is_remote,
+        );
+    }
+
     fn add_job(
         &self,
         id: i32,
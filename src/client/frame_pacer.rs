@@ -0,0 +1,125 @@
+//! Jitter buffered presentation of video frames.
+//!
+//! The host stamps every encoded frame with its capture time in milliseconds (`pts`).
+//! The pacer maps the pts to the local clock using the frame with the lowest network
+//! delay, and holds each frame until its pts plus a small delay that follows the
+//! measured jitter, so that frames are shown at the rate they were captured instead
+//! of as fast as they are received.
+
+use hbb_common::message_proto::{video_frame, VideoFrame};
+use std::time::{Duration, Instant};
+
+pub const OPTION_FRAME_PACING: &str = "allow-frame-pacing";
+
+const MAX_DELAY_MS: i64 = 100;
+// Do not wait if frames are piling up, render them as fast as possible to catch up.
+const MAX_QUEUED: usize = 2;
+// The base is moved forward to the lowest delay of each window, to follow clock drift.
+const WINDOW: Duration = Duration::from_secs(2);
+
+#[derive(Default)]
+pub struct FramePacer {
+    // Local time of pts 0.
+    base: Option<Instant>,
+    last_pts: i64,
+    // Jitter in ms, scaled by 16.
+    jitter: i64,
+    window_start: Option<Instant>,
+    window_min_lateness: i64,
+}
+
+impl FramePacer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// How long to wait before presenting the frame captured at `pts`, which was received at `now`.
+    pub fn wait_time(&mut self, pts: i64, now: Instant, queued: usize) -> Duration {
+        if pts < self.last_pts {
+            // The host restarted the capture.
+            self.base = None;
+        }
+        self.last_pts = pts;
+        let base = match self.base {
+            Some(base) => base,
+            None => {
+                self.rebase(now, pts);
+                return Duration::ZERO;
+            }
+        };
+        let expected = base + Duration::from_millis(pts as _);
+        let lateness = if now >= expected {
+            (now - expected).as_millis() as i64
+        } else {
+            // Faster than any frame before, this is the new base.
+            self.rebase(now, pts);
+            return Duration::ZERO;
+        };
+        self.jitter += lateness - self.jitter / 16;
+        self.window_min_lateness = self.window_min_lateness.min(lateness);
+        if let Some(window_start) = self.window_start {
+            if now.duration_since(window_start) >= WINDOW {
+                if let Some(base) = self.base.as_mut() {
+                    *base += Duration::from_millis(self.window_min_lateness as _);
+                }
+                self.window_start = Some(now);
+                self.window_min_lateness = i64::MAX;
+            }
+        }
+        if queued >= MAX_QUEUED {
+            return Duration::ZERO;
+        }
+        let delay = (self.jitter / 8).min(MAX_DELAY_MS);
+        Duration::from_millis((delay - lateness).max(0) as _)
+    }
+
+    fn rebase(&mut self, now: Instant, pts: i64) {
+        self.base = now.checked_sub(Duration::from_millis(pts as _));
+        self.window_start = Some(now);
+        self.window_min_lateness = i64::MAX;
+    }
+}
+
+/// The capture time of the last frame in `vf`, `None` for raw frames.
+pub fn frame_pts(vf: &VideoFrame) -> Option<i64> {
+    let frames = match vf.union.as_ref()? {
+        video_frame::Union::Vp8s(f)
+        | video_frame::Union::Vp9s(f)
+        | video_frame::Union::Av1s(f)
+        | video_frame::Union::H264s(f)
+        | video_frame::Union::H265s(f) => &f.frames,
+        _ => return None,
+    };
+    frames.last().map(|f| f.pts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_pacer() {
+        let mut pacer = FramePacer::new();
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        assert_eq!(pacer.wait_time(1000, at(0), 0), Duration::ZERO);
+        // On time, no jitter yet.
+        assert_eq!(pacer.wait_time(1016, at(16), 0), Duration::ZERO);
+        // Late frames raise the jitter, on time frames are then held back.
+        for i in 2..20 {
+            let pts = 1000 + i * 16;
+            let late = if i % 2 == 0 { 40 } else { 0 };
+            pacer.wait_time(pts as _, at(i * 16 + late), 0);
+        }
+        let wait = pacer.wait_time(1000 + 20 * 16, at(20 * 16), 0);
+        assert!(wait > Duration::ZERO);
+        assert!(wait <= Duration::from_millis(MAX_DELAY_MS as _));
+        // Piling up.
+        assert_eq!(
+            pacer.wait_time(1000 + 21 * 16, at(21 * 16), MAX_QUEUED),
+            Duration::ZERO
+        );
+        // Capture restarted.
+        assert_eq!(pacer.wait_time(0, at(22 * 16), 0), Duration::ZERO);
+    }
+}
@@ -0,0 +1,93 @@
+//! Scaling and rotation of decoded frames before they are handed to the UI textures.
+//!
+//! Scaling to the size shown on screen in the core avoids copying full size frames to
+//! the UI and scaling them there on every frame. The frames are transformed with the SIMD
+//! paths of libyuv.
+
+use hbb_common::{bail, ResultType};
+use scrap::{ImageFormat, ImageRgb};
+
+const MIN_SCALE: f64 = 0.05;
+const MAX_SCALE: f64 = 4.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameTransform {
+    pub scale: f64,
+    /// Clockwise, 0, 90, 180 or 270.
+    pub rotation: u32,
+}
+
+impl FrameTransform {
+    /// Returns `None` for the identity transform.
+    pub fn new(scale: f64, rotation: i32) -> ResultType<Option<Self>> {
+        if !(MIN_SCALE..=MAX_SCALE).contains(&scale) {
+            bail!("scale must be in [{}, {}]", MIN_SCALE, MAX_SCALE);
+        }
+        let rotation = rotation.rem_euclid(360) as u32;
+        if rotation % 90 != 0 {
+            bail!("rotation must be a multiple of 90");
+        }
+        if scale == 1.0 && rotation == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Self { scale, rotation }))
+    }
+
+    /// The size after scaling, before rotating.
+    pub fn scaled_size(&self, w: usize, h: usize) -> (usize, usize) {
+        let f = |x: usize| ((x as f64 * self.scale).round() as usize).max(1);
+        (f(w), f(h))
+    }
+}
+
+/// The transform of one display, with its output buffers.
+pub struct FrameTransformer {
+    transform: FrameTransform,
+    dst: ImageRgb,
+    scratch: Vec<u8>,
+}
+
+impl FrameTransformer {
+    pub fn new(transform: FrameTransform) -> Self {
+        Self {
+            transform,
+            dst: ImageRgb::new(ImageFormat::ARGB, 0),
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn transform(&self) -> &FrameTransform {
+        &self.transform
+    }
+
+    pub fn apply(&mut self, src: &ImageRgb) -> ResultType<&ImageRgb> {
+        self.dst.set_align(src.align());
+        let (w, h) = self.transform.scaled_size(src.w, src.h);
+        scrap::convert::scale_rotate_rgb(
+            src,
+            &mut self.dst,
+            w,
+            h,
+            self.transform.rotation,
+            &mut self.scratch,
+        )?;
+        Ok(&self.dst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frame_transform() {
+        assert!(FrameTransform::new(1.0, 0).unwrap().is_none());
+        assert!(FrameTransform::new(1.0, 360).unwrap().is_none());
+        assert!(FrameTransform::new(0.0, 0).is_err());
+        assert!(FrameTransform::new(1.0, 45).is_err());
+        let t = FrameTransform::new(0.5, -90).unwrap().unwrap();
+        assert_eq!(t.rotation, 270);
+        assert_eq!(t.scaled_size(1920, 1080), (960, 540));
+        assert_eq!(t.scaled_size(1, 1), (1, 1));
+    }
+}
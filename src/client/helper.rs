@@ -13,6 +13,8 @@ pub struct QualityStatus {
     pub target_bitrate: Option<i32>,
     pub codec_format: Option<CodecFormat>,
     pub chroma: Option<String>,
+    // Frames dropped by this side since the connection started: queue overflow, discarded or failed to decode.
+    pub decode_dropped: Option<usize>,
 }
 
 #[inline]
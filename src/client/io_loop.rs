@@ -7,6 +7,7 @@ use crate::{
     },
     common::get_default_sound_input,
     ui_session_interface::{InvokeUiSession, Session},
+    video_datagram::{self, ClientDatagram, ClientEvent, DatagramChannel},
 };
 #[cfg(feature = "unix-file-copy-paste")]
 use crate::{clipboard::try_empty_clipboard_files, clipboard_file::unix_file_clip};
@@ -37,7 +38,7 @@ use hbb_common::{
 use hbb_common::{tokio::sync::Mutex as TokioMutex, ResultType};
 use scrap::CodecFormat;
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     ffi::c_void,
     num::NonZeroI64,
     path::PathBuf,
@@ -54,16 +55,27 @@ pub struct Remote<T: InvokeUiSession> {
     sender: mpsc::UnboundedSender<Data>,
     // Stop sending local audio to remote client.
     stop_voice_call_sender: Option<std::sync::mpsc::Sender<()>>,
+    // Stop sending the microphone without a voice call.
+    stop_talk_back_sender: Option<std::sync::mpsc::Sender<()>>,
     voice_call_request_timestamp: Option<NonZeroI64>,
     read_jobs: Vec<fs::TransferJob>,
     write_jobs: Vec<fs::TransferJob>,
+    // The uploads compressed for the peer.
+    compress_jobs: crate::transfer_compress::Jobs,
     remove_jobs: HashMap<i32, RemoveJob>,
+    // The jobs in the folder sync mode, with their path on the peer.
+    folder_sync: HashMap<i32, (crate::folder_sync::Mode, String)>,
+    // Number of files that failed in each running job, for the a11y event when it finishes.
+    job_errors: HashMap<i32, usize>,
     timer: crate::RustDeskInterval,
     last_update_jobs_status: (Instant, HashMap<i32, u64>),
     is_connected: bool,
     first_frame: bool,
     #[cfg(feature = "unix-file-copy-paste")]
     client_conn_id: i32, // used for file clipboard
+    // The bytes of the files of the peer pasted, last shown.
+    #[cfg(feature = "unix-file-copy-paste")]
+    file_paste_progress: Option<(u64, u64)>,
     data_count: Arc<AtomicUsize>,
     video_format: CodecFormat,
     elevation_requested: bool,
@@ -72,6 +84,23 @@ pub struct Remote<T: InvokeUiSession> {
     chroma: Arc<RwLock<Option<Chroma>>>,
     last_record_state: bool,
     sent_close_reason: bool,
+    // The datagrams of the KCP socket, until the host offers the video over them.
+    video_datagram_channel: Option<DatagramChannel>,
+    video_datagram: Option<ClientDatagram>,
+    // The local ports forwarded over the channels of the connection, with their stop senders.
+    mux_channels: crate::mux::Channels,
+    mux_forwards: HashMap<i32, tokio::sync::oneshot::Sender<()>>,
+    bandwidth_probe: Option<crate::bandwidth_probe::Probe>,
+    // The displays of the peer, the base of the display deltas.
+    displays: Vec<DisplayInfo>,
+    clipboard_receiver: crate::clipboard::chunks::Receiver,
+    qos_history: Option<crate::qos_history::Recorder>,
+    // The last round trip time measured, in ms.
+    last_delay: u32,
+    // The game controllers of the client, if the peer takes them.
+    gamepad: Option<crate::gamepad::Capture>,
+    // The controllers forwarded with something pressed, to release when the forwarding stops.
+    gamepads_pressed: HashSet<u8>,
 }
 
 #[derive(Default)]
@@ -79,6 +108,11 @@ struct ParsedPeerInfo {
     platform: String,
     support_view_camera: bool,
     support_terminal: bool,
+    support_mux: bool,
+    support_clipboard_chunks: bool,
+    support_transfer_verify: bool,
+    support_transfer_compress: bool,
+    support_folder_sync: bool,
 }
 
 impl<T: InvokeUiSession> Remote<T> {
@@ -94,16 +128,22 @@ impl<T: InvokeUiSession> Remote<T> {
             sender,
             read_jobs: Vec::new(),
             write_jobs: Vec::new(),
+            compress_jobs: Default::default(),
             remove_jobs: Default::default(),
+            folder_sync: Default::default(),
+            job_errors: Default::default(),
             timer: crate::rustdesk_interval(time::interval(SEC30)),
             last_update_jobs_status: (Instant::now(), Default::default()),
             is_connected: false,
             first_frame: false,
             #[cfg(feature = "unix-file-copy-paste")]
             client_conn_id: 0,
+            #[cfg(feature = "unix-file-copy-paste")]
+            file_paste_progress: None,
             data_count: Arc::new(AtomicUsize::new(0)),
             video_format: CodecFormat::Unknown,
             stop_voice_call_sender: None,
+            stop_talk_back_sender: None,
             voice_call_request_timestamp: None,
             elevation_requested: false,
             peer_info: Default::default(),
@@ -111,6 +151,17 @@ impl<T: InvokeUiSession> Remote<T> {
             chroma: Default::default(),
             last_record_state: false,
             sent_close_reason: false,
+            video_datagram_channel: None,
+            video_datagram: None,
+            mux_channels: Default::default(),
+            mux_forwards: Default::default(),
+            bandwidth_probe: None,
+            displays: vec![],
+            clipboard_receiver: Default::default(),
+            qos_history: None,
+            last_delay: 0,
+            gamepad: None,
+            gamepads_pressed: Default::default(),
         }
     }
 
@@ -136,7 +187,8 @@ impl<T: InvokeUiSession> Remote<T> {
         )
         .await
         {
-            Ok(((mut peer, direct, pk, kcp, stream_type), (feedback, rendezvous_server))) => {
+            Ok(((mut peer, direct, pk, mut kcp, stream_type), (feedback, rendezvous_server))) => {
+                self.video_datagram_channel = kcp.as_mut().and_then(|kcp| kcp.take_datagram());
                 self.handler
                     .connection_round_state
                     .lock()
@@ -149,6 +201,9 @@ impl<T: InvokeUiSession> Remote<T> {
                     self.handler
                         .set_fingerprint(crate::common::pk_to_fingerprint(pk.unwrap_or_default()));
                 }
+                if conn_type == ConnType::DEFAULT_CONN {
+                    self.qos_history = crate::qos_history::start(&self.handler.get_id());
+                }
 
                 // just build for now
                 #[cfg(not(feature = "unix-file-copy-paste"))]
@@ -221,6 +276,25 @@ impl<T: InvokeUiSession> Remote<T> {
                                 }
                             }
                         }
+                        event = recv_video_datagram(&mut self.video_datagram) => {
+                            match event {
+                                ClientEvent::Frame(bytes) => {
+                                    last_recv_time = Instant::now();
+                                    self.data_count.fetch_add(bytes.len(), Ordering::Relaxed);
+                                    if !self.handle_msg_from_peer(&bytes, &mut peer).await {
+                                        break
+                                    }
+                                }
+                                ClientEvent::Lost(display) => {
+                                    if !self.handler.request_key_frame(display as _) {
+                                        self.handler.refresh_video(display as _);
+                                    }
+                                }
+                            }
+                        }
+                        state = recv_gamepad(&mut self.gamepad) => {
+                            self.forward_gamepad(state, &mut peer).await;
+                        }
                         _msg = rx_clip_client.recv() => {
                             #[cfg(feature = "unix-file-copy-paste")]
                             self.handle_local_clipboard_msg(&mut peer, _msg).await;
@@ -231,7 +305,7 @@ impl<T: InvokeUiSession> Remote<T> {
                                 break;
                             }
                             if !self.read_jobs.is_empty() {
-                                if let Err(err) = fs::handle_read_jobs(&mut self.read_jobs, &mut peer).await {
+                                if let Err(err) = crate::transfer_compress::handle_read_jobs(&mut self.read_jobs, &mut peer, &mut self.compress_jobs).await {
                                     self.handler.msgbox("error", "Connection Error", &err.to_string(), "");
                                     break;
                                 }
@@ -246,8 +320,14 @@ impl<T: InvokeUiSession> Remote<T> {
                                 continue;
                             }
                             fps_instant = Instant::now();
+                            if !self.gamepads_pressed.is_empty() && !self.is_gamepad_forwarded() {
+                                self.release_gamepads(&mut peer).await;
+                            }
+                            #[cfg(feature = "unix-file-copy-paste")]
+                            self.update_file_paste_progress();
                             let mut speed = self.data_count.swap(0, Ordering::Relaxed);
                             speed = speed * 1000 / elapsed as usize;
+                            let kbps = (speed * 8 / 1000) as u32;
                             let speed = format!("{:.2}kB/s", speed as f32 / 1024 as f32);
 
                             let fps = self.video_threads.iter().map(|(k, v)| {
@@ -270,11 +350,21 @@ impl<T: InvokeUiSession> Remote<T> {
                             } else {
                                 Some(self.video_format.clone())
                             };
+                            let decode_dropped: usize = self.video_threads.values().map(|v| *v.decode_dropped.read().unwrap()).sum();
+                            if let Some(recorder) = self.qos_history.as_mut() {
+                                let max_fps = fps.values().copied().max().unwrap_or_default();
+                                let rtt = self.last_delay.min(u16::MAX as _) as u16;
+                                if let Err(e) = recorder.push(kbps, max_fps.max(0) as _, rtt, decode_dropped) {
+                                    log::warn!("Failed to record the qos history: {}", e);
+                                    self.qos_history = None;
+                                }
+                            }
                             self.handler.update_quality_status(QualityStatus {
                                 speed: Some(speed),
                                 fps,
                                 chroma,
                                 codec_format,
+                                decode_dropped: Some(decode_dropped),
                                 ..Default::default()
                             });
                         }
@@ -285,6 +375,11 @@ impl<T: InvokeUiSession> Remote<T> {
                 if let Some(s) = self.stop_voice_call_sender.take() {
                     s.send(()).ok();
                 }
+                if let Some(s) = self.stop_talk_back_sender.take() {
+                    s.send(()).ok();
+                }
+                self.mux_forwards.clear();
+                self.mux_channels.close_all();
                 if kcp.is_some() {
                     // Send the close reason if it hasn't been sent yet, as KCP cannot detect the socket close event.
                     self.send_close_reason(&mut peer, "kcp").await;
@@ -361,6 +456,7 @@ impl<T: InvokeUiSession> Remote<T> {
     }
 
     fn handle_job_status(&mut self, id: i32, file_num: i32, err: Option<String>) {
+        self.push_job_a11y_event(id, file_num, err.is_some());
         if let Some(job) = self.remove_jobs.get_mut(&id) {
             if job.no_confirm {
                 let file_num = (file_num + 1) as usize;
@@ -387,6 +483,33 @@ impl<T: InvokeUiSession> Remote<T> {
         }
     }
 
+    fn push_job_a11y_event(&mut self, id: i32, file_num: i32, is_err: bool) {
+        // Only the deletion of multiple files reports the status of each file.
+        let in_progress = match self.remove_jobs.get(&id) {
+            Some(job) => ((file_num + 1) as usize) < job.files.len(),
+            None => false,
+        };
+        let errors = self.job_errors.entry(id).or_default();
+        if is_err {
+            *errors += 1;
+        }
+        if in_progress {
+            return;
+        }
+        let errors = self.job_errors.remove(&id).unwrap_or_default();
+        let text = if errors == 0 {
+            "a11y-file-job-done-tip".to_owned()
+        } else {
+            format!("a11y-file-job-errors-{{{}}}-tip", errors)
+        };
+        crate::ui_interface::push_a11y_event(
+            crate::ui_interface::A11Y_EVENT_FILE_JOB_FINISHED,
+            &self.handler.get_id(),
+            text,
+            serde_json::json!({ "id": id, "errors": errors }),
+        );
+    }
+
     fn stop_voice_call(&mut self) {
         let voice_call_sender = std::mem::replace(&mut self.stop_voice_call_sender, None);
         if let Some(stopper) = voice_call_sender {
@@ -408,6 +531,28 @@ impl<T: InvokeUiSession> Remote<T> {
         // But it' not necessary for now, because it's not a common case.
         // And it is immediately known when the input device is changed.
         crate::audio_service::set_voice_call_input_device(get_default_sound_input(), false);
+        Some(self.forward_local_audio(audio_service::NAME, true))
+    }
+
+    fn set_talk_back(&mut self, on: bool) {
+        if let Some(s) = self.stop_talk_back_sender.take() {
+            s.send(()).ok();
+        }
+        // The voice call already sends the microphone.
+        let on = on && self.stop_voice_call_sender.is_none();
+        if on {
+            self.stop_talk_back_sender =
+                Some(self.forward_local_audio(audio_service::CAMERA_NAME, false));
+        }
+        self.handler.lc.write().unwrap().talk_back = on;
+    }
+
+    // Subscribe the audio service of the client server and send its audio to remote.
+    fn forward_local_audio(
+        &self,
+        service: &'static str,
+        voice_call: bool,
+    ) -> std::sync::mpsc::Sender<()> {
         // Create a channel to receive error or closed message
         let (tx, rx) = std::sync::mpsc::channel();
         let (tx_audio_data, mut rx_audio_data) =
@@ -416,25 +561,25 @@ impl<T: InvokeUiSession> Remote<T> {
         let conn_id = CLIENT_SERVER.write().unwrap().get_new_id();
         let client_conn_inner = ConnInner::new(conn_id.clone(), Some(tx_audio_data), None);
         // now we subscribe
-        CLIENT_SERVER.write().unwrap().subscribe(
-            audio_service::NAME,
-            client_conn_inner.clone(),
-            true,
-        );
+        CLIENT_SERVER
+            .write()
+            .unwrap()
+            .subscribe(service, client_conn_inner.clone(), true);
         let tx_audio = self.sender.clone();
         std::thread::spawn(move || {
             loop {
                 // check if client is closed
                 match rx.try_recv() {
                     Ok(_) | Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                        log::debug!("Exit voice call audio service of client");
+                        log::debug!("Exit {service} audio service of client");
                         // unsubscribe
-                        CLIENT_SERVER.write().unwrap().subscribe(
-                            audio_service::NAME,
-                            client_conn_inner,
-                            false,
-                        );
-                        crate::audio_service::set_voice_call_input_device(None, true);
+                        CLIENT_SERVER
+                            .write()
+                            .unwrap()
+                            .subscribe(service, client_conn_inner, false);
+                        if voice_call {
+                            crate::audio_service::set_voice_call_input_device(None, true);
+                        }
                         break;
                     }
                     _ => {}
@@ -463,7 +608,37 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
             }
         });
-        return Some(tx);
+        tx
+    }
+
+    fn is_gamepad_forwarded(&self) -> bool {
+        self.handler
+            .get_toggle_option(crate::gamepad::OPTION_FORWARD.to_owned())
+            && !self.handler.get_toggle_option("view-only".to_owned())
+            && *self.handler.server_keyboard_enabled.read().unwrap()
+    }
+
+    async fn forward_gamepad(&mut self, state: crate::gamepad::GamepadState, peer: &mut Stream) {
+        if !self.is_gamepad_forwarded() {
+            return;
+        }
+        if state.is_released() {
+            self.gamepads_pressed.remove(&state.index);
+        } else {
+            self.gamepads_pressed.insert(state.index);
+        }
+        allow_err!(peer.send(&crate::gamepad::make_message(&state)).await);
+    }
+
+    // The host would hold the keys of the controllers until the session ends.
+    async fn release_gamepads(&mut self, peer: &mut Stream) {
+        for index in self.gamepads_pressed.drain() {
+            let state = crate::gamepad::GamepadState {
+                index,
+                ..Default::default()
+            };
+            allow_err!(peer.send(&crate::gamepad::make_message(&state)).await);
+        }
     }
 
     async fn send_close_reason(&mut self, peer: &mut Stream, reason: &str) {
@@ -478,6 +653,19 @@ impl<T: InvokeUiSession> Remote<T> {
         self.sent_close_reason = true;
     }
 
+    // Compress the job if the peer supports it, it compresses the downloads on request.
+    async fn negotiate_compress(&mut self, id: i32, is_remote: bool, peer: &mut Stream) {
+        if !self.peer_info.support_transfer_compress {
+            return;
+        }
+        if is_remote {
+            let req = crate::transfer_compress::Request { id };
+            allow_err!(peer.send(&req.to_message()).await);
+        } else {
+            self.compress_jobs.insert(id);
+        }
+    }
+
     async fn handle_msg_from_ui(&mut self, data: Data, peer: &mut Stream) -> bool {
         match data {
             Data::Close => {
@@ -490,6 +678,18 @@ impl<T: InvokeUiSession> Remote<T> {
                     .await;
             }
             Data::Message(msg) => {
+                if self.peer_info.support_clipboard_chunks {
+                    if let Some(message::Union::MultiClipboards(mcb)) = &msg.union {
+                        if let Some(chunks) = crate::clipboard::chunks::split(mcb) {
+                            // One by one through the loop, the messages of the peer are read in
+                            // between.
+                            for chunk in chunks {
+                                self.sender.send(Data::Message(chunk)).ok();
+                            }
+                            return true;
+                        }
+                    }
+                }
                 match &msg.union {
                     Some(message::Union::Misc(misc)) => match misc.union {
                         Some(misc::Union::RefreshVideo(_)) => {
@@ -510,6 +710,7 @@ impl<T: InvokeUiSession> Remote<T> {
             }
             Data::SendFiles((id, r#type, path, to, file_num, include_hidden, is_remote)) => {
                 log::info!("send files, is remote {}", is_remote);
+                self.negotiate_compress(id, is_remote, peer).await;
                 let od = can_enable_overwrite_detection(self.handler.lc.read().unwrap().version);
                 if is_remote {
                     log::debug!("New job {}, write to {} from remote {}", id, to, path);
@@ -628,6 +829,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             );
                             job.is_last_job = true;
                             self.read_jobs.push(job);
+                            self.negotiate_compress(id, is_remote, peer).await;
                             self.timer = crate::rustdesk_interval(time::interval(MILLI1));
                         }
                     }
@@ -638,6 +840,10 @@ impl<T: InvokeUiSession> Remote<T> {
                     if let Some(job) = get_job(id, &mut self.write_jobs) {
                         job.is_last_job = false;
                         job.is_resume = true;
+                        if self.peer_info.support_transfer_compress {
+                            let req = crate::transfer_compress::Request { id };
+                            allow_err!(peer.send(&req.to_message()).await);
+                        }
                         allow_err!(
                             peer.send(&fs::new_send(
                                 id,
@@ -769,6 +975,12 @@ impl<T: InvokeUiSession> Remote<T> {
                     }
                 }
             }
+            Data::SyncFiles((id, mut mode, remote)) => {
+                if mode == crate::folder_sync::Mode::Hash && !self.peer_info.support_folder_sync {
+                    mode = crate::folder_sync::Mode::Meta;
+                }
+                self.folder_sync.insert(id, (mode, remote));
+            }
             Data::CancelJob(id) => {
                 let mut msg_out = Message::new();
                 let mut file_action = FileAction::new();
@@ -783,6 +995,8 @@ impl<T: InvokeUiSession> Remote<T> {
                 }
                 let _ = fs::remove_job(id, &mut self.read_jobs);
                 self.remove_jobs.remove(&id);
+                self.job_errors.remove(&id);
+                self.folder_sync.remove(&id);
             }
             Data::RemoveDir((id, path)) => {
                 let mut msg_out = Message::new();
@@ -864,6 +1078,15 @@ impl<T: InvokeUiSession> Remote<T> {
                 self.handler.lc.write().unwrap().record_state = start;
                 self.update_record_state();
             }
+            #[cfg(feature = "unix-file-copy-paste")]
+            Data::CancelFilePaste => {
+                // The files may be pasted on either side.
+                crate::clipboard_file::unix_file_clip::cancel_paste(self.client_conn_id);
+                allow_err!(
+                    peer.send(&crate::clipboard_file::unix_file_clip::make_cancel_paste())
+                        .await
+                );
+            }
             Data::ElevateDirect => {
                 let mut request = ElevationRequest::new();
                 request.set_direct(true);
@@ -898,6 +1121,37 @@ impl<T: InvokeUiSession> Remote<T> {
                 allow_err!(peer.send(&msg).await);
                 self.handler.on_voice_call_waiting();
             }
+            Data::TalkBack(on) => {
+                self.set_talk_back(on);
+            }
+            Data::AddMuxForward((port, host, remote_port)) => {
+                if !self.peer_info.support_mux {
+                    log::warn!("mux is not supported by the peer");
+                    return true;
+                }
+                let (tx, rx) = tokio::sync::oneshot::channel();
+                // Replaces the forward of the port, the old listener stops on the drop of tx.
+                self.mux_forwards.insert(port, tx);
+                let sender = self.sender.clone();
+                let send = move |msg| {
+                    sender.send(Data::Message(msg)).ok();
+                };
+                let target = crate::mux::Target {
+                    kind: crate::mux::Kind::Tcp,
+                    host,
+                    port: remote_port,
+                };
+                tokio::spawn(crate::mux::listen(
+                    self.mux_channels.clone(),
+                    port,
+                    target,
+                    send,
+                    rx,
+                ));
+            }
+            Data::RemoveMuxForward(port) => {
+                self.mux_forwards.remove(&port);
+            }
             Data::CloseVoiceCall => {
                 self.stop_voice_call();
                 let msg = new_voice_call_request(false);
@@ -1179,6 +1433,164 @@ impl<T: InvokeUiSession> Remote<T> {
         return false;
     }
 
+    // Sends the checksums of the partial file of a download to resume, see `transfer_verify`.
+    fn verify_transfer(
+        sender: mpsc::UnboundedSender<Data>,
+        id: i32,
+        file_num: i32,
+        write_path: String,
+        offset: u64,
+    ) {
+        tokio::spawn(async move {
+            let partial = crate::transfer_verify::partial_path(&write_path);
+            let hashes = tokio::task::spawn_blocking(move || {
+                crate::transfer_verify::chunk_hashes(&partial, offset)
+            })
+            .await;
+            // Without the checksums, nothing is verified and the file is received again.
+            let hashes = match hashes {
+                Ok(Ok(hashes)) => hashes,
+                Ok(Err(e)) => {
+                    log::warn!("Failed to hash {}: {}", write_path, e);
+                    vec![]
+                }
+                Err(_) => vec![],
+            };
+            let req = crate::transfer_verify::Request {
+                id,
+                file_num,
+                offset,
+                hashes,
+            };
+            sender.send(Data::Message(req.to_message())).ok();
+        });
+    }
+
+    // Decides without asking whether a file of a job in the folder sync mode is sent.
+    async fn sync_file(&mut self, digest: FileTransferDigest, peer: &mut Stream) {
+        use crate::folder_sync::{action, Action, Request};
+        let Some((mode, remote)) = self.folder_sync.get(&digest.id).cloned() else {
+            return;
+        };
+        let jobs = if digest.is_upload {
+            &mut self.read_jobs
+        } else {
+            &mut self.write_jobs
+        };
+        let Some(job) = fs::get_job(digest.id, jobs) else {
+            return;
+        };
+        let Some(file) = job.files().get(digest.file_num as usize) else {
+            return;
+        };
+        let fs::DataSource::FilePath(p) = &job.data_source else {
+            return;
+        };
+        let local_path = get_string(&fs::TransferJob::join(p, &file.name));
+        let remote_path = get_string(&fs::TransferJob::join(&PathBuf::from(&remote), &file.name));
+        // The digest of an upload is answered by the peer for its file, the one of a download
+        // is the one of the file of the peer.
+        let (exists, is_identical) = if digest.is_upload {
+            (true, digest.is_identical)
+        } else {
+            job.set_digest(digest.file_size, digest.last_modified);
+            match fs::is_write_need_confirmation(false, &local_path, &digest) {
+                Ok(DigestCheckResult::IsSame) => (true, true),
+                Ok(DigestCheckResult::NeedConfirm(d)) => (true, d.is_identical),
+                Ok(DigestCheckResult::NoSuchFile) | Err(_) => (false, false),
+            }
+        };
+        match action(mode, exists, is_identical) {
+            Action::Skip => {
+                self.confirm_sync_file(digest.id, digest.file_num, true, peer)
+                    .await
+            }
+            Action::Send => {
+                self.confirm_sync_file(digest.id, digest.file_num, false, peer)
+                    .await
+            }
+            Action::Compare => {
+                let sender = self.sender.clone();
+                let (id, file_num) = (digest.id, digest.file_num);
+                tokio::spawn(async move {
+                    let hash = tokio::task::spawn_blocking(move || {
+                        crate::folder_sync::file_hash(&local_path)
+                    })
+                    .await;
+                    // A file which cannot be hashed is sent, as if it had changed.
+                    let hash = match hash {
+                        Ok(Ok(hash)) => hash,
+                        _ => String::new(),
+                    };
+                    let req = Request {
+                        id,
+                        file_num,
+                        path: remote_path,
+                        hash,
+                    };
+                    sender.send(Data::Message(req.to_message())).ok();
+                });
+            }
+        }
+    }
+
+    async fn confirm_sync_file(&mut self, id: i32, file_num: i32, skip: bool, peer: &mut Stream) {
+        let job = match fs::get_job(id, &mut self.read_jobs) {
+            Some(job) => Some(job),
+            None => fs::get_job(id, &mut self.write_jobs),
+        };
+        let Some(job) = job else {
+            return;
+        };
+        let req = FileTransferSendConfirmRequest {
+            id,
+            file_num,
+            union: Some(if skip {
+                file_transfer_send_confirm_request::Union::Skip(true)
+            } else {
+                file_transfer_send_confirm_request::Union::OffsetBlk(0)
+            }),
+            ..Default::default()
+        };
+        job.confirm(&req).await;
+        let msg = new_send_confirm(req);
+        allow_err!(peer.send(&msg).await);
+    }
+
+    async fn resume_verified_transfer(
+        &mut self,
+        res: crate::transfer_verify::Response,
+        peer: &mut Stream,
+    ) {
+        let Some(job) = fs::get_job(res.id, &mut self.write_jobs) else {
+            return;
+        };
+        let Some(file) = job.files().get(res.file_num as usize) else {
+            return;
+        };
+        let fs::DataSource::FilePath(p) = &job.data_source else {
+            return;
+        };
+        let write_path = get_string(&fs::TransferJob::join(p, &file.name));
+        let mut offset = res.offset;
+        if let Err(e) = crate::transfer_verify::truncate(&write_path, offset) {
+            log::warn!("Failed to truncate {}: {}", write_path, e);
+            offset = 0;
+        }
+        log::info!("Resume {} from {}", write_path, offset);
+        let req = FileTransferSendConfirmRequest {
+            id: res.id,
+            file_num: res.file_num,
+            union: Some(file_transfer_send_confirm_request::Union::OffsetBlk(
+                offset as _,
+            )),
+            ..Default::default()
+        };
+        job.confirm(&req).await;
+        let msg = new_send_confirm(req);
+        allow_err!(peer.send(&msg).await);
+    }
+
     async fn handle_msg_from_peer(&mut self, data: &[u8], peer: &mut Stream) -> bool {
         if let Ok(msg_in) = Message::parse_from_bytes(&data) {
             match msg_in.union {
@@ -1207,6 +1619,7 @@ impl<T: InvokeUiSession> Remote<T> {
                         let video_queue = thread.video_queue.read().unwrap();
                         if video_queue.force_push(vf).is_some() {
                             drop(video_queue);
+                            *thread.decode_dropped.write().unwrap() += 1;
                             self.handler.refresh_video(display as _);
                         } else {
                             thread.video_sender.send(MediaData::VideoQueue).ok();
@@ -1314,14 +1727,10 @@ impl<T: InvokeUiSession> Remote<T> {
                     self.handler.set_cursor_position(cp);
                 }
                 Some(message::Union::Clipboard(cb)) => {
-                    if !self.handler.lc.read().unwrap().disable_clipboard.v {
-                        update_clipboard(vec![cb], ClipboardSide::Client);
-                    }
+                    self.update_clipboard(vec![cb]);
                 }
                 Some(message::Union::MultiClipboards(_mcb)) => {
-                    if !self.handler.lc.read().unwrap().disable_clipboard.v {
-                        update_clipboard(_mcb.clipboards, ClipboardSide::Client);
-                    }
+                    self.update_clipboard(_mcb.clipboards);
                 }
                 #[cfg(feature = "unix-file-copy-paste")]
                 Some(message::Union::Cliprdr(clip)) => {
@@ -1348,7 +1757,9 @@ impl<T: InvokeUiSession> Remote<T> {
                             }
                         }
                         Some(file_response::Union::Digest(digest)) => {
-                            if digest.is_upload {
+                            if self.folder_sync.contains_key(&digest.id) {
+                                self.sync_file(digest, peer).await;
+                            } else if digest.is_upload {
                                 if let Some(job) = fs::get_job(digest.id, &mut self.read_jobs) {
                                     if let Some(file) = job.files().get(digest.file_num as usize) {
                                         if let fs::DataSource::FilePath(p) = &job.data_source {
@@ -1431,7 +1842,21 @@ impl<T: InvokeUiSession> Remote<T> {
                                                             overwrite_strategy = Some(true);
                                                             offset = digest.transferred_size as _;
                                                         }
-                                                        if let Some(overwrite) = overwrite_strategy
+                                                        if offset > 0
+                                                            && self
+                                                                .peer_info
+                                                                .support_transfer_verify
+                                                        {
+                                                            // Confirmed from the offset the peer verifies.
+                                                            Self::verify_transfer(
+                                                                self.sender.clone(),
+                                                                digest.id,
+                                                                digest.file_num,
+                                                                write_path,
+                                                                offset as _,
+                                                            );
+                                                        } else if let Some(overwrite) =
+                                                            overwrite_strategy
                                                         {
                                                             let req =
                                                                 FileTransferSendConfirmRequest {
@@ -1492,6 +1917,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             let mut err: Option<String> = None;
                             let mut job_type = fs::JobType::Generic;
                             let mut printer_data = None;
+                            self.folder_sync.remove(&d.id);
                             if let Some(job) = fs::remove_job(d.id, &mut self.write_jobs) {
                                 job.modify_time();
                                 err = job.job_error();
@@ -1521,6 +1947,7 @@ impl<T: InvokeUiSession> Remote<T> {
                             }
                         }
                         Some(file_response::Union::Error(e)) => {
+                            self.folder_sync.remove(&e.id);
                             let job_type = fs::remove_job(e.id, &mut self.write_jobs)
                                 .or_else(|| fs::remove_job(e.id, &mut self.read_jobs))
                                 .map(|j| j.r#type)
@@ -1546,6 +1973,18 @@ impl<T: InvokeUiSession> Remote<T> {
                     }
                     Some(misc::Union::PermissionInfo(p)) => {
                         log::info!("Change permission {:?} -> {}", p.permission, p.enabled);
+                        if let (false, Ok(permission)) = (p.enabled, p.permission.enum_value()) {
+                            let name = format!("{:?}", permission);
+                            crate::ui_interface::push_a11y_event(
+                                crate::ui_interface::A11Y_EVENT_PERMISSION_DENIED,
+                                &self.handler.get_id(),
+                                format!(
+                                    "a11y-permission-denied-{{{}}}-tip",
+                                    client::translate(name.clone())
+                                ),
+                                serde_json::json!({ "permission": name }),
+                            );
+                        }
                         // https://github.com/rustdesk/rustdesk/issues/3703#issuecomment-1474734754
                         match p.permission.enum_value() {
                             Ok(Permission::Keyboard) => {
@@ -1586,9 +2025,12 @@ impl<T: InvokeUiSession> Remote<T> {
                                 self.handler.set_permission("restart", p.enabled);
                             }
                             Ok(Permission::Recording) => {
-                                self.handler.lc.write().unwrap().record_permission = p.enabled;
+                                let mut lc = self.handler.lc.write().unwrap();
+                                let enabled = p.enabled && !lc.do_not_record;
+                                lc.record_permission = enabled;
+                                drop(lc);
                                 self.update_record_state();
-                                self.handler.set_permission("recording", p.enabled);
+                                self.handler.set_permission("recording", enabled);
                             }
                             Ok(Permission::BlockInput) => {
                                 self.handler.set_permission("block_input", p.enabled);
@@ -1601,6 +2043,8 @@ impl<T: InvokeUiSession> Remote<T> {
                         if let Some(thread) = self.video_threads.get_mut(&(s.display as usize)) {
                             thread.video_sender.send(MediaData::Reset).ok();
                         }
+                        // The reset decoder needs a key frame.
+                        self.handler.request_key_frame(s.display);
 
                         let mut scale = 1.0;
                         if let Some(pi) = &self.handler.lc.read().unwrap().peer_info {
@@ -1610,6 +2054,12 @@ impl<T: InvokeUiSession> Remote<T> {
                         }
 
                         if s.width > 0 && s.height > 0 {
+                            self.handler
+                                .lc
+                                .write()
+                                .unwrap()
+                                .display_sizes
+                                .insert(s.display as usize, (s.width as usize, s.height as usize));
                             self.handler.set_display(
                                 s.x,
                                 s.y,
@@ -1709,6 +2159,155 @@ impl<T: InvokeUiSession> Remote<T> {
                         #[cfg(feature = "flutter")]
                         self.handler.switch_back(&self.handler.get_id());
                     }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::camera_controls::ID => {
+                        if let Some(res) = crate::camera_controls::parse_response(&p.content) {
+                            self.handler.update_camera_controls(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::input_macro::ID => {
+                        if let Some(res) = crate::input_macro::parse_response(&p.content) {
+                            if !res.error.is_empty() {
+                                self.handler
+                                    .msgbox("custom-error", "Input macro", &res.error, "");
+                            }
+                            log::info!(
+                                "input macros: {:?}, recording: {:?}",
+                                res.macros,
+                                res.recording
+                            );
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::permission_request::ID =>
+                    {
+                        use crate::permission_request::Content;
+                        if let Some(Content::Reply {
+                            permission,
+                            granted,
+                        }) = Content::parse(&p.content)
+                        {
+                            log::info!("Permission request {} answered: {}", permission, granted);
+                            let tip = if granted {
+                                "permission-request-granted-tip"
+                            } else {
+                                "permission-request-denied-tip"
+                            };
+                            self.handler
+                                .msgbox("custom-nocancel", "Permission request", tip, "");
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::config_edit::ID => {
+                        if let Some(res) = crate::config_edit::parse_response(&p.content) {
+                            self.handler.update_config_edit(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::folder_sync::ID => {
+                        if let Some(res) = crate::folder_sync::Response::parse(&p.content) {
+                            self.confirm_sync_file(res.id, res.file_num, res.same, peer)
+                                .await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::privacy_displays::ID => {
+                        if let Some(res) = crate::privacy_displays::Response::parse(&p.content) {
+                            self.handler.privacy_displays(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::file_drop::ID => {
+                        if let Some(res) = crate::file_drop::Response::parse(&p.content) {
+                            self.handler.file_drop(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::file_trash::ID => {
+                        if let Some(res) = crate::file_trash::Response::parse(&p.content) {
+                            self.handler.file_trash(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_journal::ID => {
+                        if let Some(notice) = crate::transfer_journal::Notice::parse(&p.content) {
+                            self.handler.transfer_journal(notice);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_verify::ID => {
+                        if let Some(res) = crate::transfer_verify::Response::parse(&p.content) {
+                            self.resume_verified_transfer(res, peer).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::monitor_modes::ID => {
+                        if let Some(res) = crate::monitor_modes::parse_response(&p.content) {
+                            self.handler.update_monitor_modes(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::keyboard_layout::ID => {
+                        if let Some(res) = crate::keyboard_layout::parse_response(&p.content) {
+                            self.handler.update_keyboard_layouts(res);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::display_power::ID => {
+                        if let Some(notice) = crate::display_power::parse_notice(&p.content) {
+                            let wake = self
+                                .handler
+                                .get_option(crate::display_power::PEER_OPTION.to_owned());
+                            if notice.asleep && wake == "Y" {
+                                self.handler.wake_display(notice.display);
+                            }
+                            self.handler.update_display_power(notice);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::display_delta::ID => {
+                        match crate::display_delta::apply(&self.displays, &p.content) {
+                            Some(pi) => self.sync_peer_info(&pi),
+                            None => log::warn!("Ignore a display delta which does not apply"),
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::clipboard::chunks::ID => {
+                        use crate::clipboard::chunks::Received;
+                        match self.clipboard_receiver.on_chunk(&p.content) {
+                            Some(Received::Done(mcb)) => self.update_clipboard(mcb.clipboards),
+                            Some(Received::Progress(percent)) => {
+                                self.handler.update_clipboard_progress(percent)
+                            }
+                            None => {}
+                        }
+                    }
+                    #[cfg(feature = "unix-file-copy-paste")]
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::clipboard_file::unix_file_clip::CANCEL_PASTE_ID =>
+                    {
+                        crate::clipboard_file::unix_file_clip::cancel_paste(self.client_conn_id);
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::bandwidth_probe::ID => {
+                        use crate::bandwidth_probe::Frame;
+                        if let (Some(probe), Some(Frame::Chunk { seq, total })) =
+                            (self.bandwidth_probe.as_mut(), Frame::decode(&p.content))
+                        {
+                            if let Some(result) = probe.on_chunk(seq, total, p.content.len()) {
+                                log::info!("bandwidth probe: {:?}", result);
+                                self.bandwidth_probe = None;
+                                allow_err!(peer.send(&result.to_message()).await);
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::mux::ID => {
+                        if let Some(frame) = crate::mux::Frame::decode(&p.content) {
+                            if let crate::mux::Frame::Close { channel, reason } = &frame {
+                                if !reason.is_empty() {
+                                    log::warn!("mux channel {} closed: {}", channel, reason);
+                                }
+                            }
+                            let channel = frame.channel();
+                            if !self.mux_channels.dispatch(frame) {
+                                log::warn!("mux channel {} exceeded its window", channel);
+                                let reason = crate::mux::REASON_WINDOW_EXCEEDED.to_owned();
+                                let msg = crate::mux::Frame::Close { channel, reason }.to_message();
+                                allow_err!(peer.send(&msg).await);
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::audio_devices::ID => {
+                        if let Some(res) = crate::audio_devices::parse_response(&p.content) {
+                            self.handler.update_audio_devices(res);
+                        }
+                    }
                     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
                     Some(misc::Union::PluginRequest(p)) => {
                         allow_err!(crate::plugin::handle_server_event(
@@ -1737,6 +2336,9 @@ impl<T: InvokeUiSession> Remote<T> {
                     _ => {}
                 },
                 Some(message::Union::TestDelay(t)) => {
+                    if !t.from_client {
+                        self.last_delay = t.last_delay;
+                    }
                     self.handler.handle_test_delay(t, peer).await;
                 }
                 Some(message::Union::AudioFrame(frame)) => {
@@ -1786,6 +2388,9 @@ impl<T: InvokeUiSession> Remote<T> {
                             if response.accepted {
                                 // The peer accepted the voice call.
                                 self.handler.on_voice_call_started();
+                                if self.stop_talk_back_sender.is_some() {
+                                    self.set_talk_back(false);
+                                }
                                 self.stop_voice_call_sender = self.start_voice_call();
                             } else {
                                 // The peer refused the voice call.
@@ -1794,10 +2399,7 @@ impl<T: InvokeUiSession> Remote<T> {
                         }
                     }
                 }
-                Some(message::Union::PeerInfo(pi)) => {
-                    self.handler.set_displays(&pi.displays);
-                    self.handler.set_platform_additions(&pi.platform_additions);
-                }
+                Some(message::Union::PeerInfo(pi)) => self.sync_peer_info(&pi),
                 Some(message::Union::ScreenshotResponse(response)) => {
                     crate::client::screenshot::set_screenshot(response.data);
                     self.handler
@@ -1820,8 +2422,21 @@ impl<T: InvokeUiSession> Remote<T> {
         true
     }
 
+    // The displays of the peer changed.
+    fn sync_peer_info(&mut self, pi: &PeerInfo) {
+        self.displays = pi.displays.clone();
+        if let Some(ids) = crate::display_id::parse(&pi.platform_additions) {
+            self.handler.lc.write().unwrap().display_ids = ids;
+        }
+        self.handler.set_displays(&pi.displays);
+        self.handler.set_platform_additions(&pi.platform_additions);
+    }
+
     fn set_peer_info(&mut self, pi: &PeerInfo) {
         self.peer_info.platform = pi.platform.clone();
+        self.displays = pi.displays.clone();
+        self.handler.lc.write().unwrap().display_ids =
+            crate::display_id::parse(&pi.platform_additions).unwrap_or_default();
 
         // Check features field for terminal support
         if let Some(features) = pi.features.as_ref() {
@@ -1831,11 +2446,208 @@ impl<T: InvokeUiSession> Remote<T> {
         if let Ok(platform_additions) =
             serde_json::from_str::<HashMap<String, serde_json::Value>>(&pi.platform_additions)
         {
+            self.peer_info.support_mux = platform_additions
+                .get("support_mux")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.peer_info.support_clipboard_chunks = platform_additions
+                .get(crate::clipboard::chunks::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if self.peer_info.support_clipboard_chunks {
+                self.handler
+                    .send(Data::Message(crate::clipboard::chunks::make_request()));
+            }
+            self.peer_info.support_transfer_verify = platform_additions
+                .get(crate::transfer_verify::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.peer_info.support_transfer_compress = platform_additions
+                .get(crate::transfer_compress::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.peer_info.support_folder_sync = platform_additions
+                .get(crate::folder_sync::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if self.handler.is_file_transfer()
+                && platform_additions
+                    .get(crate::transfer_journal::PLATFORM_ADDITION_KEY)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+            {
+                let mut lc = self.handler.lc.write().unwrap();
+                let mut key = lc.get_option(crate::transfer_journal::PEER_OPTION);
+                if key.is_empty() {
+                    key = uuid::Uuid::new_v4().to_string();
+                    lc.set_option(crate::transfer_journal::PEER_OPTION.to_owned(), key.clone());
+                }
+                drop(lc);
+                let claim = crate::transfer_journal::Claim { key };
+                self.handler.send(Data::Message(claim.to_message()));
+            }
+            if self.handler.is_default()
+                && self.gamepad.is_none()
+                && platform_additions
+                    .get(crate::gamepad::PLATFORM_ADDITION_KEY)
+                    .and_then(|v| v.as_bool())
+                    .unwrap_or(false)
+            {
+                self.gamepad = Some(crate::gamepad::Capture::start());
+            }
             self.peer_info.support_view_camera = platform_additions
                 .get("support_view_camera")
                 .map(|v| v.as_bool())
                 .flatten()
                 .unwrap_or(false);
+            self.handler.lc.write().unwrap().support_key_frame_request = platform_additions
+                .get(crate::key_frame::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            let do_not_record = platform_additions
+                .get("do_not_record")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            self.handler.lc.write().unwrap().do_not_record = do_not_record;
+            if do_not_record {
+                self.handler.lc.write().unwrap().record_permission = false;
+                self.update_record_state();
+            }
+            if platform_additions
+                .get(crate::display_delta::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                self.handler
+                    .send(Data::Message(crate::display_delta::make_request()));
+            }
+            if platform_additions
+                .get(crate::scaled_frames::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                self.handler
+                    .send(Data::Message(crate::scaled_frames::make_request()));
+            }
+            if platform_additions
+                .get(crate::mouse_curve::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                let curve = self
+                    .handler
+                    .lc
+                    .read()
+                    .unwrap()
+                    .get_option(crate::mouse_curve::PEER_OPTION);
+                if let Ok(curve) = serde_json::from_str::<crate::mouse_curve::Curve>(&curve) {
+                    self.handler.send(Data::Message(curve.to_message()));
+                }
+            }
+            if platform_additions
+                .get(crate::wake_policy::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                let name = self
+                    .handler
+                    .lc
+                    .read()
+                    .unwrap()
+                    .get_option(crate::wake_policy::PEER_OPTION);
+                // The host does as without a policy otherwise.
+                if let Some(policy) = crate::wake_policy::Policy::from_name(&name) {
+                    let msg = crate::wake_policy::Request { policy }.to_message();
+                    self.handler.send(Data::Message(msg));
+                }
+            }
+            if platform_additions
+                .get(crate::scroll_transform::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                let transform = self
+                    .handler
+                    .lc
+                    .read()
+                    .unwrap()
+                    .get_option(crate::scroll_transform::PEER_OPTION);
+                if let Ok(transform) =
+                    serde_json::from_str::<crate::scroll_transform::Transform>(&transform)
+                {
+                    self.handler.send(Data::Message(transform.to_message()));
+                }
+            }
+            if platform_additions
+                .get(crate::transfer_throttle::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false)
+            {
+                let settings = self
+                    .handler
+                    .lc
+                    .read()
+                    .unwrap()
+                    .get_option(crate::transfer_throttle::PEER_OPTION);
+                if let Ok(settings) =
+                    serde_json::from_str::<crate::transfer_throttle::Settings>(&settings)
+                {
+                    self.handler.send(Data::Message(settings.to_message()));
+                }
+            }
+            let support_bandwidth_probe = platform_additions
+                .get(crate::bandwidth_probe::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if support_bandwidth_probe
+                && (self.handler.is_default() || self.handler.is_view_camera())
+                && LocalConfig::get_option(crate::bandwidth_probe::OPTION_BANDWIDTH_PROBE) != "N"
+            {
+                let (probe, msg) = crate::bandwidth_probe::Probe::start();
+                self.bandwidth_probe = Some(probe);
+                self.handler.send(Data::Message(msg));
+            }
+            let support_audio_devices = platform_additions
+                .get("support_audio_devices")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(false);
+            if support_audio_devices {
+                let device = self
+                    .handler
+                    .get_option(crate::audio_devices::PEER_OPTION.to_owned());
+                if !device.is_empty() {
+                    let msg = crate::audio_devices::make_request(Some(device));
+                    self.handler.send(Data::Message(msg));
+                }
+            }
+            if let Some(host_layout) = platform_additions
+                .get(crate::keyboard_layout::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_str())
+            {
+                let option = self
+                    .handler
+                    .get_option(crate::keyboard_layout::PEER_OPTION.to_owned());
+                let translate_mode = crate::keyboard::get_keyboard_mode_enum(
+                    &self.handler.lc.read().unwrap().keyboard_mode,
+                ) == KeyboardMode::Translate;
+                if let Some(layout) = crate::keyboard_layout::resolve(&option, translate_mode) {
+                    if layout != host_layout {
+                        let msg = crate::keyboard_layout::make_request(Some(layout));
+                        self.handler.send(Data::Message(msg));
+                    }
+                }
+            }
+            if let Some(offer) = platform_additions
+                .get(video_datagram::PLATFORM_ADDITION_KEY)
+                .and_then(|v| v.as_str())
+            {
+                if LocalConfig::get_option(video_datagram::OPTION_VIDEO_DATAGRAM) == "Y" {
+                    if let Some(channel) = self.video_datagram_channel.take() {
+                        self.video_datagram = ClientDatagram::accept(channel, offer);
+                        log::info!("video datagram mode: {}", self.video_datagram.is_some());
+                    }
+                }
+            }
         }
     }
 
@@ -1865,7 +2677,29 @@ impl<T: InvokeUiSession> Remote<T> {
         true
     }
 
+    fn update_clipboard(&self, clipboards: Vec<Clipboard>) {
+        if self.handler.lc.read().unwrap().disable_clipboard.v {
+            return;
+        }
+        if !crate::clipboard::chunks::is_within_limit(&clipboards) {
+            log::warn!("Ignore the clipboard of the peer, it is too large");
+            return;
+        }
+        update_clipboard(self.incoming_clipboards(clipboards), ClipboardSide::Client);
+    }
+
     #[inline(always)]
+    fn incoming_clipboards(&self, clipboards: Vec<Clipboard>) -> Vec<Clipboard> {
+        let plain_text_only = self
+            .handler
+            .get_toggle_option(crate::clipboard::rich_text::OPTION_PLAIN_TEXT_ONLY.to_owned());
+        if plain_text_only {
+            crate::clipboard::rich_text::plain_text(clipboards)
+        } else {
+            clipboards
+        }
+    }
+
     fn update_block_input_state(&mut self, on: bool) {
         self.handler.update_block_input_state(on);
     }
@@ -2013,6 +2847,22 @@ impl<T: InvokeUiSession> Remote<T> {
         true
     }
 
+    // Shown when it changes, the last one once the paste ends.
+    #[cfg(feature = "unix-file-copy-paste")]
+    fn update_file_paste_progress(&mut self) {
+        let Some(p) = crate::clipboard_file::unix_file_clip::paste_progress(self.client_conn_id)
+        else {
+            return;
+        };
+        let progress = Some((p.transferred, p.total));
+        if progress == self.file_paste_progress {
+            return;
+        }
+        self.file_paste_progress = progress;
+        self.handler
+            .update_file_paste_progress(p.transferred, p.total, p.is_canceled, p.is_failed);
+    }
+
     #[cfg(feature = "unix-file-copy-paste")]
     async fn handle_cliprdr_msg(
         &mut self,
@@ -2075,12 +2925,14 @@ impl<T: InvokeUiSession> Remote<T> {
         let (video_sender, video_receiver) = std::sync::mpsc::channel::<MediaData>();
         let decode_fps = Arc::new(RwLock::new(None));
         let frame_count = Arc::new(RwLock::new(0));
+        let decode_dropped = Arc::new(RwLock::new(0));
         let discard_queue = Arc::new(RwLock::new(false));
         let video_thread = VideoThread {
             video_queue: video_queue.clone(),
             video_sender,
             decode_fps: decode_fps.clone(),
             frame_count: frame_count.clone(),
+            decode_dropped: decode_dropped.clone(),
             fps_control: Default::default(),
             discard_queue: discard_queue.clone(),
         };
@@ -2093,6 +2945,7 @@ impl<T: InvokeUiSession> Remote<T> {
             decode_fps,
             self.chroma.clone(),
             discard_queue,
+            decode_dropped,
             move |display: usize,
                   data: &mut scrap::ImageRgb,
                   _texture: *mut c_void,
@@ -2185,6 +3038,7 @@ struct VideoThread {
     video_sender: MediaSender,
     decode_fps: Arc<RwLock<Option<usize>>>,
     frame_count: Arc<RwLock<usize>>,
+    decode_dropped: Arc<RwLock<usize>>,
     discard_queue: Arc<RwLock<bool>>,
     fps_control: FpsControl,
 }
@@ -2195,3 +3049,27 @@ impl Drop for VideoThread {
         *self.discard_queue.write().unwrap() = true;
     }
 }
+
+async fn recv_gamepad(
+    capture: &mut Option<crate::gamepad::Capture>,
+) -> crate::gamepad::GamepadState {
+    let state = match capture {
+        Some(capture) => capture.recv().await,
+        None => None,
+    };
+    match state {
+        Some(state) => state,
+        // The controllers can not be captured.
+        None => {
+            *capture = None;
+            std::future::pending().await
+        }
+    }
+}
+
+async fn recv_video_datagram(datagram: &mut Option<ClientDatagram>) -> ClientEvent {
+    match datagram {
+        Some(datagram) => datagram.recv().await,
+        None => std::future::pending().await,
+    }
+}
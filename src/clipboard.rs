@@ -5,6 +5,14 @@ use std::{
     time::Duration,
 };
 
+pub mod chunks;
+pub mod history;
+pub mod images;
+pub mod redaction;
+pub mod rich_text;
+#[cfg(target_os = "macos")]
+pub mod watcher;
+
 pub const CLIPBOARD_NAME: &'static str = "clipboard";
 #[cfg(feature = "unix-file-copy-paste")]
 pub const FILE_CLIPBOARD_NAME: &'static str = "file-clipboard";
@@ -37,6 +45,7 @@ const SUPPORTED_FORMATS: &[ClipboardFormat] = &[
     ClipboardFormat::ImageRgba,
     ClipboardFormat::ImagePng,
     ClipboardFormat::ImageSvg,
+    ClipboardFormat::Special(images::TIFF_FORMAT),
     #[cfg(feature = "unix-file-copy-paste")]
     ClipboardFormat::FileUrl,
     ClipboardFormat::Special(CLIPBOARD_FORMAT_EXCEL_XML_SPREADSHEET),
@@ -53,10 +62,21 @@ pub fn check_clipboard(
     }
     let ctx2 = ctx.as_mut()?;
     match ctx2.get(side, force) {
-        Ok(content) => {
+        Ok(mut content) => {
+            if side == ClipboardSide::Host {
+                content = redaction::filter(content);
+            }
+            content = images::outgoing(content);
             if !content.is_empty() {
                 let mut msg = Message::new();
                 let clipboards = proto::create_multi_clipboards(content);
+                if !chunks::is_within_limit(&clipboards.clipboards) {
+                    log::warn!("The clipboard is too large to be sent");
+                    return None;
+                }
+                if side == ClipboardSide::Client {
+                    history::push(&clipboards);
+                }
                 msg.set_multi_clipboards(clipboards.clone());
                 *LAST_MULTI_CLIPBOARDS.lock().unwrap() = clipboards;
                 return Some(msg);
@@ -140,7 +160,7 @@ pub fn try_empty_clipboard_files(_side: ClipboardSide, _conn_id: i32) {
 }
 
 fn update_clipboard_(multi_clipboards: Vec<Clipboard>, side: ClipboardSide) {
-    let to_update_data = proto::from_multi_clipbards(multi_clipboards);
+    let to_update_data = images::incoming(proto::from_multi_clipbards(multi_clipboards));
     if to_update_data.is_empty() {
         return;
     }
@@ -335,6 +355,31 @@ impl ClipboardContext {
     }
 }
 
+/// Set the clipboard, then read, encode, decode and set it again as a session does, for
+/// `--test-clipboard`. The clipboard of the user is restored after.
+pub fn roundtrip(data: Vec<ClipboardData>) -> ResultType<Vec<ClipboardData>> {
+    let mut ctx = ClipboardContext::new()?;
+    let saved = ctx.get(ClipboardSide::Client, true).unwrap_or_default();
+    let res = roundtrip_(&mut ctx, data);
+    if !saved.is_empty() {
+        ctx.set(&saved).ok();
+    }
+    res
+}
+
+fn roundtrip_(
+    ctx: &mut ClipboardContext,
+    data: Vec<ClipboardData>,
+) -> ResultType<Vec<ClipboardData>> {
+    ctx.set(&data)?;
+    let content = images::outgoing(ctx.get(ClipboardSide::Host, true)?);
+    let multi_clipboards = proto::create_multi_clipboards(content);
+    ctx.set(&images::incoming(proto::from_multi_clipbards(
+        multi_clipboards.clipboards,
+    )))?;
+    ctx.get(ClipboardSide::Client, true)
+}
+
 pub fn is_support_multi_clipboard(peer_version: &str, peer_platform: &str) -> bool {
     use hbb_common::get_version_number;
     if get_version_number(peer_version) < get_version_number("1.3.0") {
@@ -358,7 +403,15 @@ pub fn get_current_clipboard_msg(
     let mut multi_clipboards = LAST_MULTI_CLIPBOARDS.lock().unwrap();
     if multi_clipboards.clipboards.is_empty() {
         let mut ctx = ClipboardContext::new().ok()?;
-        *multi_clipboards = proto::create_multi_clipboards(ctx.get(side, true).ok()?);
+        let mut content = ctx.get(side, true).ok()?;
+        if side == ClipboardSide::Host {
+            content = redaction::filter(content);
+        }
+        content = images::outgoing(content);
+        *multi_clipboards = proto::create_multi_clipboards(content);
+    }
+    if !chunks::is_within_limit(&multi_clipboards.clipboards) {
+        return None;
     }
     if multi_clipboards.clipboards.is_empty() {
         return None;
@@ -582,7 +635,11 @@ mod proto {
 // Because only one clipboard master(listener) can trigger the clipboard change event multiple listeners are created on Linux(x11).
 // https://github.com/rustdesk-org/clipboard-master/blob/4fb62e5b62fb6350d82b571ec7ba94b3cd466695/src/master/x11.rs#L226
 pub mod clipboard_listener {
-    use clipboard_master::{CallbackResult, ClipboardHandler, Master, Shutdown};
+    #[cfg(target_os = "macos")]
+    use super::watcher::{self, Shutdown};
+    use clipboard_master::{CallbackResult, ClipboardHandler};
+    #[cfg(not(target_os = "macos"))]
+    use clipboard_master::{Master, Shutdown};
     use hbb_common::{bail, log, ResultType};
     use std::{
         collections::HashMap,
@@ -684,6 +741,20 @@ pub mod clipboard_listener {
         log::info!("Clipboard listener unsubscribed: {}", name);
     }
 
+    // The pasteboard has no change notification, see `watcher`.
+    #[cfg(target_os = "macos")]
+    fn start_clipbard_master_thread(
+        mut handler: impl ClipboardHandler + Send + 'static,
+        tx_start_res: Sender<(Option<Shutdown>, String)>,
+    ) -> JoinHandle<()> {
+        let (shutdown, h) = watcher::start(move || {
+            handler.on_clipboard_change();
+        });
+        tx_start_res.send((Some(shutdown), "".to_owned())).ok();
+        h
+    }
+
+    #[cfg(not(target_os = "macos"))]
     fn start_clipbard_master_thread(
         handler: impl ClipboardHandler + Send + 'static,
         tx_start_res: Sender<(Option<Shutdown>, String)>,
@@ -0,0 +1,192 @@
+//! The limit of the size of the clipboard, and the large clipboards sent in chunks.
+//!
+//! A clipboard larger than `OPTION_CLIPBOARD_MAX_MB` once encoded is not sent nor set. A
+//! clipboard sent as one message holds the connection until it is written, the input and the
+//! control messages wait behind it, so one larger than `CHUNK_THRESHOLD` is sent in chunks of
+//! `CHUNK` bytes between the other messages, with the progress shown on the client.
+//!
+//! A host with "support_clipboard_chunks" in its platform additions sends the chunks to the
+//! clients which asked for them with an empty `Misc::PluginRequest` of the reserved `ID`, and the
+//! client sends them to the host. The content of a chunk is the id of the clipboard and the size
+//! of the encoded `MultiClipboards` in big endian u32, then the next bytes of it.
+
+use crate::plugin_request;
+use hbb_common::{
+    bytes::{BufMut, Bytes, BytesMut},
+    config::Config,
+    message_proto::{Clipboard, Message, MultiClipboards, PluginRequest},
+    protobuf::Message as _,
+};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub const ID: &str = "clipboard-chunks";
+pub const PLATFORM_ADDITION_KEY: &str = "support_clipboard_chunks";
+pub const OPTION_CLIPBOARD_MAX_MB: &str = "clipboard-max-mb";
+const DEFAULT_MAX_MB: usize = 32;
+const CHUNK_THRESHOLD: usize = 1024 * 1024;
+const CHUNK: usize = 256 * 1024;
+const HEADER: usize = 8;
+
+static NEXT_ID: AtomicU32 = AtomicU32::new(1);
+
+fn max_bytes() -> usize {
+    Config::get_option(OPTION_CLIPBOARD_MAX_MB)
+        .trim()
+        .parse()
+        .ok()
+        .filter(|mb| *mb > 0)
+        .unwrap_or(DEFAULT_MAX_MB)
+        * 1024
+        * 1024
+}
+
+/// Whether the clipboard is within `OPTION_CLIPBOARD_MAX_MB`.
+pub fn is_within_limit(clipboards: &[Clipboard]) -> bool {
+    clipboards.iter().map(|c| c.content.len()).sum::<usize>() <= max_bytes()
+}
+
+fn make_message(content: Bytes) -> Message {
+    plugin_request::make_raw(ID, content)
+}
+
+/// The request of the client to get the chunks.
+pub fn make_request() -> Message {
+    make_message(Bytes::new())
+}
+
+/// The chunks to send instead of the clipboard, none if it is small enough to be sent at once.
+pub fn split(clipboards: &MultiClipboards) -> Option<Vec<Message>> {
+    let data = clipboards.write_to_bytes().ok()?;
+    if data.len() <= CHUNK_THRESHOLD {
+        return None;
+    }
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    Some(
+        data.chunks(CHUNK)
+            .map(|chunk| {
+                let mut buf = BytesMut::with_capacity(HEADER + chunk.len());
+                buf.put_u32(id);
+                buf.put_u32(data.len() as u32);
+                buf.put_slice(chunk);
+                make_message(buf.freeze())
+            })
+            .collect(),
+    )
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Received {
+    /// The percent received, by steps of 10.
+    Progress(u8),
+    Done(MultiClipboards),
+}
+
+/// The chunks of the clipboard being received, a new clipboard replaces it.
+#[derive(Debug, Default)]
+pub struct Receiver {
+    id: u32,
+    total: usize,
+    data: Vec<u8>,
+    percent: u8,
+    // Too large, or a chunk lost, the next chunks of the clipboard are dropped.
+    dropped: bool,
+}
+
+impl Receiver {
+    pub fn on_chunk(&mut self, content: &[u8]) -> Option<Received> {
+        self.on_chunk_(content, max_bytes())
+    }
+
+    fn on_chunk_(&mut self, content: &[u8], max_bytes: usize) -> Option<Received> {
+        let u32_at = |i: usize| Some(u32::from_be_bytes(content.get(i..i + 4)?.try_into().ok()?));
+        let id = u32_at(0)?;
+        let total = u32_at(4)? as usize;
+        if id != self.id || total != self.total {
+            *self = Self {
+                id,
+                total,
+                ..Default::default()
+            };
+        }
+        if self.dropped {
+            return None;
+        }
+        if total > max_bytes || self.data.len() + content.len() - HEADER > total {
+            self.data = vec![];
+            self.dropped = true;
+            return None;
+        }
+        self.data.extend_from_slice(&content[HEADER..]);
+        if self.data.len() == total {
+            let data = std::mem::take(&mut self.data);
+            return MultiClipboards::parse_from_bytes(&data)
+                .ok()
+                .map(Received::Done);
+        }
+        let percent = (self.data.len() * 100 / total) as u8;
+        if percent / 10 > self.percent / 10 {
+            self.percent = percent;
+            return Some(Received::Progress(percent / 10 * 10));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{misc, ClipboardFormat};
+
+    fn content(msg: &Message) -> &[u8] {
+        match &msg.misc().union {
+            Some(misc::Union::PluginRequest(p)) => &p.content,
+            _ => &[],
+        }
+    }
+
+    #[test]
+    fn clipboard_chunks() {
+        let small = MultiClipboards {
+            clipboards: vec![Clipboard {
+                content: vec![1; 100].into(),
+                format: ClipboardFormat::Text.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert!(split(&small).is_none());
+        let large = MultiClipboards {
+            clipboards: vec![Clipboard {
+                content: (0..CHUNK_THRESHOLD * 2)
+                    .map(|i| i as u8)
+                    .collect::<Vec<_>>()
+                    .into(),
+                format: ClipboardFormat::Text.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let chunks = split(&large).unwrap();
+        assert_eq!(chunks.len(), 9);
+
+        let mut receiver = Receiver::default();
+        let received: Vec<_> = chunks
+            .iter()
+            .filter_map(|c| receiver.on_chunk_(content(c), usize::MAX))
+            .collect();
+        assert_eq!(received.first(), Some(&Received::Progress(10)));
+        assert_eq!(received.last(), Some(&Received::Done(large.clone())));
+
+        // Too large, or a chunk missing.
+        let mut receiver = Receiver::default();
+        assert!(chunks
+            .iter()
+            .all(|c| receiver.on_chunk_(content(c), CHUNK_THRESHOLD).is_none()));
+        let mut receiver = Receiver::default();
+        assert!(!chunks
+            .iter()
+            .skip(1)
+            .filter_map(|c| receiver.on_chunk_(content(c), usize::MAX))
+            .any(|r| matches!(r, Received::Done(_))));
+    }
+}
@@ -0,0 +1,187 @@
+//! The history of the clipboard of this computer, sent again to a peer on demand.
+//!
+//! Each clipboard sent by the client side is kept, so an earlier one can be pasted on the peer
+//! without copying it again here. The history keeps the last `OPTION_CLIPBOARD_HISTORY_SIZE`
+//! clipboards, "0" disables it, within `MAX_BYTES` in total. A clipboard equal to one of the
+//! history moves it to the front. It is in memory only, so it is gone with the process.
+
+use hbb_common::{
+    compress::decompress,
+    config::LocalConfig,
+    message_proto::{Clipboard, ClipboardFormat, MultiClipboards},
+};
+use serde_derive::Serialize;
+use std::{collections::VecDeque, sync::Mutex};
+
+pub const OPTION_CLIPBOARD_HISTORY_SIZE: &str = "clipboard-history-size";
+const DEFAULT_SIZE: usize = 10;
+const MAX_BYTES: usize = 64 * 1024 * 1024;
+const PREVIEW_CHARS: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref HISTORY: Mutex<History> = Default::default();
+}
+
+#[derive(Default)]
+struct History {
+    // The most recent first.
+    items: VecDeque<Item>,
+    next_id: u64,
+}
+
+struct Item {
+    id: u64,
+    time: i64,
+    clipboards: MultiClipboards,
+}
+
+/// An item of the history, as listed to the UI.
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    pub id: u64,
+    pub time: i64,
+    pub formats: Vec<String>,
+    pub preview: String,
+    pub size: usize,
+}
+
+fn max_size() -> usize {
+    LocalConfig::get_option(OPTION_CLIPBOARD_HISTORY_SIZE)
+        .trim()
+        .parse()
+        .unwrap_or(DEFAULT_SIZE)
+}
+
+fn bytes(clipboards: &MultiClipboards) -> usize {
+    clipboards.clipboards.iter().map(|c| c.content.len()).sum()
+}
+
+impl History {
+    fn push(&mut self, clipboards: MultiClipboards, max_size: usize) {
+        let size = bytes(&clipboards);
+        if max_size == 0 || size == 0 || size > MAX_BYTES {
+            return;
+        }
+        self.items.retain(|i| i.clipboards != clipboards);
+        self.next_id += 1;
+        self.items.push_front(Item {
+            id: self.next_id,
+            time: hbb_common::get_time(),
+            clipboards,
+        });
+        self.items.truncate(max_size);
+        let mut total = 0;
+        let count = self
+            .items
+            .iter()
+            .take_while(|i| {
+                total += bytes(&i.clipboards);
+                total <= MAX_BYTES
+            })
+            .count();
+        self.items.truncate(count);
+    }
+
+    fn list(&self) -> Vec<Entry> {
+        self.items
+            .iter()
+            .map(|i| Entry {
+                id: i.id,
+                time: i.time,
+                formats: i.clipboards.clipboards.iter().map(format_name).collect(),
+                preview: preview(&i.clipboards),
+                size: bytes(&i.clipboards),
+            })
+            .collect()
+    }
+
+    fn get(&self, id: u64) -> Option<MultiClipboards> {
+        self.items
+            .iter()
+            .find(|i| i.id == id)
+            .map(|i| i.clipboards.clone())
+    }
+}
+
+fn format_name(c: &Clipboard) -> String {
+    match c.format.enum_value() {
+        Ok(ClipboardFormat::Special) => c.special_name.clone(),
+        Ok(format) => format!("{:?}", format),
+        Err(n) => n.to_string(),
+    }
+}
+
+// The start of the text, else the size of the image.
+fn preview(clipboards: &MultiClipboards) -> String {
+    let text = clipboards
+        .clipboards
+        .iter()
+        .find(|c| c.format.enum_value() == Ok(ClipboardFormat::Text));
+    if let Some(c) = text {
+        let data = if c.compress {
+            decompress(&c.content)
+        } else {
+            c.content.to_vec()
+        };
+        return String::from_utf8_lossy(&data)
+            .chars()
+            .take(PREVIEW_CHARS)
+            .collect();
+    }
+    clipboards
+        .clipboards
+        .iter()
+        .find(|c| c.width > 0 && c.height > 0)
+        .map(|c| format!("{}x{}", c.width, c.height))
+        .unwrap_or_default()
+}
+
+/// Keep a clipboard sent to the peers.
+pub fn push(clipboards: &MultiClipboards) {
+    let max_size = max_size();
+    HISTORY.lock().unwrap().push(clipboards.clone(), max_size);
+}
+
+pub fn list() -> Vec<Entry> {
+    HISTORY.lock().unwrap().list()
+}
+
+pub fn get(id: u64) -> Option<MultiClipboards> {
+    HISTORY.lock().unwrap().get(id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> MultiClipboards {
+        MultiClipboards {
+            clipboards: vec![Clipboard {
+                content: s.as_bytes().to_vec().into(),
+                format: ClipboardFormat::Text.into(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn clipboard_history() {
+        let mut h = History::default();
+        h.push(text("a"), 2);
+        h.push(text("b"), 2);
+        h.push(text("a"), 2);
+        let list = h.list();
+        assert_eq!(
+            list.iter().map(|e| e.preview.as_str()).collect::<Vec<_>>(),
+            vec!["a", "b"]
+        );
+        assert_eq!(list[0].formats, vec!["Text".to_owned()]);
+        h.push(text("c"), 2);
+        assert_eq!(h.list().len(), 2);
+        assert_eq!(h.get(h.list()[1].id), Some(text("a")));
+        assert_eq!(h.get(list[1].id), None);
+        h.push(text("d"), 0);
+        assert_eq!(h.list()[0].preview, "c");
+    }
+}
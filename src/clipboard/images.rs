@@ -0,0 +1,220 @@
+//! Images of the clipboard, checked and converted before they are sent or set.
+//!
+//! The same image is often on the clipboard in several formats, e.g. a screenshot on macOS is in
+//! PNG and TIFF, and a copied image in raw RGBA of tens of MB. One image is sent, as PNG: the PNG
+//! of the clipboard, else the TIFF or the RGBA converted to PNG. An image larger than
+//! `OPTION_CLIPBOARD_IMAGE_MAX_MB` once encoded is not sent nor set, and "0" disables the images.
+//! The images received are checked the same way, their size read before they are decoded, and a
+//! PNG is set with its TIFF too on macOS, for the applications which paste TIFF only.
+
+use arboard::{ClipboardData, ImageData};
+use hbb_common::{config::Config, log};
+use image::{
+    io::{Limits, Reader},
+    DynamicImage, ImageFormat, ImageOutputFormat, RgbaImage,
+};
+use std::io::Cursor;
+
+pub const OPTION_CLIPBOARD_IMAGE_MAX_MB: &str = "clipboard-image-max-mb";
+// The TIFF of the pasteboard of macOS.
+pub const TIFF_FORMAT: &str = "public.tiff";
+const DEFAULT_MAX_MB: usize = 16;
+// The memory of an image decoded, 8K RGBA.
+const MAX_DECODED_BYTES: u64 = 7680 * 4320 * 4;
+
+fn max_bytes() -> usize {
+    Config::get_option(OPTION_CLIPBOARD_IMAGE_MAX_MB)
+        .trim()
+        .parse()
+        .unwrap_or(DEFAULT_MAX_MB)
+        * 1024
+        * 1024
+}
+
+/// The clipboard to send, with one image as PNG at most.
+pub fn outgoing(data: Vec<ClipboardData>) -> Vec<ClipboardData> {
+    outgoing_(data, max_bytes())
+}
+
+fn outgoing_(data: Vec<ClipboardData>, max: usize) -> Vec<ClipboardData> {
+    let (images, mut data): (Vec<_>, Vec<_>) = data.into_iter().partition(|d| match d {
+        ClipboardData::Image(ImageData::Png(_) | ImageData::Rgba(_)) => true,
+        ClipboardData::Special((name, _)) => name == TIFF_FORMAT,
+        _ => false,
+    });
+    data.retain(|d| match d {
+        ClipboardData::Image(svg) if svg.bytes().len() > max => {
+            log::info!("Clipboard image of {} bytes not sent", svg.bytes().len());
+            false
+        }
+        _ => true,
+    });
+    if images.is_empty() || max == 0 {
+        return data;
+    }
+    let rank = |d: &ClipboardData| match d {
+        ClipboardData::Image(ImageData::Png(_)) => 0,
+        ClipboardData::Special(_) => 1,
+        _ => 2,
+    };
+    let Some(image) = images.into_iter().min_by_key(rank) else {
+        return data;
+    };
+    let png = match image {
+        ClipboardData::Image(ImageData::Png(png)) => Some(png.to_vec()),
+        ClipboardData::Special((_, tiff)) => {
+            decode(&tiff, ImageFormat::Tiff).and_then(|i| encode(&i, ImageOutputFormat::Png))
+        }
+        ClipboardData::Image(image) => {
+            from_rgba(&image).and_then(|i| encode(&i, ImageOutputFormat::Png))
+        }
+        _ => None,
+    };
+    match png {
+        Some(png) if png.len() <= max => {
+            data.push(ClipboardData::Image(ImageData::png(png.into())))
+        }
+        Some(png) => log::info!("Clipboard image of {} bytes not sent", png.len()),
+        None => log::warn!("Failed to convert the clipboard image to PNG"),
+    }
+    data
+}
+
+/// The clipboard to set, without the images too large.
+pub fn incoming(data: Vec<ClipboardData>) -> Vec<ClipboardData> {
+    incoming_(data, max_bytes())
+}
+
+fn incoming_(data: Vec<ClipboardData>, max: usize) -> Vec<ClipboardData> {
+    let mut out = Vec::with_capacity(data.len());
+    for d in data {
+        match d {
+            ClipboardData::Image(image) => {
+                if max == 0 || image.bytes().len() > max {
+                    log::info!("Clipboard image of {} bytes not set", image.bytes().len());
+                    continue;
+                }
+                let valid = match &image {
+                    ImageData::Rgba(rgba) => {
+                        rgba.width as u64 * rgba.height as u64 * 4 == image.bytes().len() as u64
+                    }
+                    ImageData::Png(png) => png_size(png)
+                        .map(|(w, h)| w as u64 * h as u64 * 4 <= MAX_DECODED_BYTES)
+                        .unwrap_or(false),
+                    ImageData::Svg(_) => true,
+                };
+                if !valid {
+                    log::warn!("Invalid clipboard image");
+                    continue;
+                }
+                #[cfg(target_os = "macos")]
+                if let ImageData::Png(png) = &image {
+                    let tiff = decode(png, ImageFormat::Png)
+                        .and_then(|i| encode(&i, ImageOutputFormat::Tiff));
+                    if let Some(tiff) = tiff {
+                        out.push(ClipboardData::Special((TIFF_FORMAT.to_owned(), tiff)));
+                    }
+                }
+                out.push(ClipboardData::Image(image));
+            }
+            ClipboardData::Special((name, _)) if name == TIFF_FORMAT => {}
+            d => out.push(d),
+        }
+    }
+    out
+}
+
+// The width and the height in the IHDR chunk, which comes first.
+fn png_size(png: &[u8]) -> Option<(u32, u32)> {
+    if png.get(..8)? != b"\x89PNG\r\n\x1a\n" || png.get(12..16)? != b"IHDR" {
+        return None;
+    }
+    let width = u32::from_be_bytes(png.get(16..20)?.try_into().ok()?);
+    let height = u32::from_be_bytes(png.get(20..24)?.try_into().ok()?);
+    Some((width, height))
+}
+
+/// The width and the height of the image, `None` for SVG.
+pub fn size(image: &ImageData) -> Option<(u32, u32)> {
+    match image {
+        ImageData::Rgba(rgba) => Some((rgba.width as _, rgba.height as _)),
+        ImageData::Png(png) => png_size(png),
+        ImageData::Svg(_) => None,
+    }
+}
+
+fn decode(data: &[u8], format: ImageFormat) -> Option<DynamicImage> {
+    let mut reader = Reader::with_format(Cursor::new(data), format);
+    let mut limits = Limits::default();
+    limits.max_alloc = Some(MAX_DECODED_BYTES);
+    reader.limits(limits);
+    reader.decode().ok()
+}
+
+fn from_rgba(image: &ImageData) -> Option<DynamicImage> {
+    let ImageData::Rgba(rgba) = image else {
+        return None;
+    };
+    if rgba.width as u64 * rgba.height as u64 * 4 > MAX_DECODED_BYTES {
+        return None;
+    }
+    let buf = RgbaImage::from_raw(rgba.width as _, rgba.height as _, image.bytes().to_vec())?;
+    Some(DynamicImage::ImageRgba8(buf))
+}
+
+fn encode(image: &DynamicImage, format: ImageOutputFormat) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    image.write_to(&mut Cursor::new(&mut out), format).ok()?;
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(format: ImageOutputFormat) -> Vec<u8> {
+        encode(
+            &DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 2, image::Rgba([1, 2, 3, 255]))),
+            format,
+        )
+        .unwrap()
+    }
+
+    fn pngs(data: &[ClipboardData]) -> Vec<(u32, u32)> {
+        data.iter()
+            .filter_map(|d| match d {
+                ClipboardData::Image(ImageData::Png(png)) => png_size(png),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn clipboard_images() {
+        let text = || ClipboardData::Text("a".to_owned());
+        let tiff =
+            || ClipboardData::Special((TIFF_FORMAT.to_owned(), encoded(ImageOutputFormat::Tiff)));
+        let rgba = || ClipboardData::Image(ImageData::rgba(4, 2, vec![0u8; 32].into()));
+        let png = || ClipboardData::Image(ImageData::png(encoded(ImageOutputFormat::Png).into()));
+
+        // The TIFF and the RGBA are converted, one image only is sent.
+        let sent = outgoing_(vec![text(), tiff(), rgba()], 1024 * 1024);
+        assert_eq!(sent.len(), 2);
+        assert_eq!(pngs(&sent), vec![(4, 2)]);
+        let sent = outgoing_(vec![rgba()], 1024 * 1024);
+        assert_eq!(pngs(&sent), vec![(4, 2)]);
+        let sent = outgoing_(vec![rgba(), tiff(), png()], 1024 * 1024);
+        assert_eq!(sent.len(), 1);
+        // Too large, or disabled.
+        assert_eq!(outgoing_(vec![text(), rgba()], 10).len(), 1);
+        assert_eq!(outgoing_(vec![png()], 0).len(), 0);
+
+        let received = incoming_(vec![text(), png()], 1024 * 1024);
+        assert_eq!(pngs(&received), vec![(4, 2)]);
+        assert_eq!(incoming_(vec![png()], 10).len(), 0);
+        // The size does not match the pixels.
+        let bad = ClipboardData::Image(ImageData::rgba(8, 8, vec![0u8; 32].into()));
+        assert_eq!(incoming_(vec![bad], 1024 * 1024).len(), 0);
+        assert_eq!(png_size(b"GIF89a"), None);
+    }
+}
@@ -0,0 +1,319 @@
+//! The plain text fallback of the formatted clipboard.
+//!
+//! The HTML and the RTF of the clipboard, e.g. copied in an office application, are forwarded
+//! with the text so the formatting is kept on the peer. They can carry more than the text, e.g.
+//! the links, the comments or the author, so a session with the `OPTION_PLAIN_TEXT_ONLY` toggle
+//! drops them in both directions, with the spreadsheet of Excel. If there is no text besides, it
+//! is extracted from the HTML or the RTF.
+
+use arboard::ClipboardData;
+use hbb_common::message_proto::{message, Clipboard, ClipboardFormat, Message};
+
+use super::{proto, CLIPBOARD_FORMAT_EXCEL_XML_SPREADSHEET};
+
+pub const OPTION_PLAIN_TEXT_ONLY: &str = "clipboard-plain-text-only";
+
+fn is_rich(format: ClipboardFormat, special_name: &str) -> bool {
+    match format {
+        ClipboardFormat::Html | ClipboardFormat::Rtf => true,
+        ClipboardFormat::Special => special_name == CLIPBOARD_FORMAT_EXCEL_XML_SPREADSHEET,
+        _ => false,
+    }
+}
+
+/// The clipboards without the formatted text, with a text extracted from it if there is none.
+pub fn plain_text(clipboards: Vec<Clipboard>) -> Vec<Clipboard> {
+    let rich = |c: &Clipboard| {
+        c.format
+            .enum_value()
+            .map(|f| is_rich(f, &c.special_name))
+            .unwrap_or(false)
+    };
+    if !clipboards.iter().any(rich) {
+        return clipboards;
+    }
+    let (rich, mut plain): (Vec<_>, Vec<_>) = clipboards.into_iter().partition(rich);
+    let has_text = plain
+        .iter()
+        .any(|c| c.format.enum_value() == Ok(ClipboardFormat::Text));
+    if !has_text {
+        let text = plain_text_data(proto::from_multi_clipbards(rich));
+        plain.extend(proto::create_multi_clipboards(text).clipboards);
+    }
+    plain
+}
+
+/// The clipboard message to send to a session with `OPTION_PLAIN_TEXT_ONLY`.
+pub fn plain_text_msg(msg: &Message) -> Message {
+    let mut msg = msg.clone();
+    if let Some(message::Union::MultiClipboards(multi_clipboards)) = &mut msg.union {
+        multi_clipboards.clipboards = plain_text(std::mem::take(&mut multi_clipboards.clipboards));
+    }
+    msg
+}
+
+fn plain_text_data(data: Vec<ClipboardData>) -> Vec<ClipboardData> {
+    let mut html = None;
+    let mut rtf = None;
+    for d in data {
+        match d {
+            ClipboardData::Html(s) => html = Some(s),
+            ClipboardData::Rtf(s) => rtf = Some(s),
+            _ => {}
+        }
+    }
+    let text = html
+        .map(|s| html_to_text(&s))
+        .or_else(|| rtf.map(|s| rtf_to_text(&s)));
+    text.filter(|s| !s.trim().is_empty())
+        .map(ClipboardData::Text)
+        .into_iter()
+        .collect()
+}
+
+fn html_to_text(html: &str) -> String {
+    // The fragment of the clipboard on Windows, between its comments.
+    let html = match (
+        html.find("<!--StartFragment-->"),
+        html.find("<!--EndFragment-->"),
+    ) {
+        (Some(start), Some(end)) if start < end => &html[start + 20..end],
+        _ => html,
+    };
+    let mut out = String::new();
+    let mut rest = html;
+    while let Some(open) = rest.find('<') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let Some(close) = rest.find('>') else {
+            rest = "";
+            break;
+        };
+        let tag = rest[1..close].trim().to_ascii_lowercase();
+        rest = &rest[close + 1..];
+        let name = tag
+            .trim_start_matches('/')
+            .split(|c: char| c.is_whitespace() || c == '/')
+            .next()
+            .unwrap_or_default()
+            .to_owned();
+        match name.as_str() {
+            "script" | "style" | "head" if !tag.starts_with('/') => {
+                let end = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&end) {
+                    Some(i) => &rest[i..],
+                    None => "",
+                };
+            }
+            "br" => out.push('\n'),
+            "p" | "div" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6"
+                if tag.starts_with('/') =>
+            {
+                out.push('\n')
+            }
+            "td" | "th" if tag.starts_with('/') => out.push('\t'),
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+    decode_entities(&out).trim().to_owned()
+}
+
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..]
+            .find(';')
+            .filter(|end| *end <= 10)
+            .map(|end| &rest[1..end + 1]);
+        let c = entity.and_then(|e| match e {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => {
+                let n = e.strip_prefix('#')?;
+                let n = match n.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                    None => n.parse().ok()?,
+                };
+                char::from_u32(n)
+            }
+        });
+        match (c, entity) {
+            (Some(c), Some(e)) => {
+                out.push(c);
+                rest = &rest[e.len() + 2..];
+            }
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+// The groups of the RTF which are not text.
+const RTF_SKIPPED: &[&str] = &[
+    "fonttbl",
+    "colortbl",
+    "stylesheet",
+    "info",
+    "pict",
+    "header",
+    "footer",
+    "listtable",
+    "listoverridetable",
+];
+
+fn rtf_to_text(rtf: &str) -> String {
+    let bytes = rtf.as_bytes();
+    let mut out = String::new();
+    // Whether each group is skipped, and the count of the characters after a \u.
+    let mut skipped = vec![false];
+    let mut uc = 1;
+    let mut fallback = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let skip = *skipped.last().unwrap_or(&false);
+        match bytes[i] {
+            b'{' => {
+                skipped.push(skip || bytes[i + 1..].starts_with(b"\\*"));
+                i += 1;
+            }
+            b'}' => {
+                skipped.pop();
+                i += 1;
+            }
+            b'\\' => {
+                i += 1;
+                let Some(&c) = bytes.get(i) else {
+                    break;
+                };
+                if !c.is_ascii_alphabetic() {
+                    i += 1;
+                    let literal = match c {
+                        b'\\' | b'{' | b'}' => Some(c as char),
+                        b'~' => Some(' '),
+                        b'\'' => {
+                            let hex = rtf.get(i..i + 2).unwrap_or_default();
+                            i += 2;
+                            u8::from_str_radix(hex, 16).ok().map(|b| b as char)
+                        }
+                        _ => None,
+                    };
+                    if let (Some(c), false) = (literal, skip) {
+                        if fallback > 0 {
+                            fallback -= 1;
+                        } else {
+                            out.push(c);
+                        }
+                    }
+                    continue;
+                }
+                let start = i;
+                while i < bytes.len() && bytes[i].is_ascii_alphabetic() {
+                    i += 1;
+                }
+                let word = &rtf[start..i];
+                let num_start = i;
+                if bytes.get(i) == Some(&b'-') {
+                    i += 1;
+                }
+                while i < bytes.len() && bytes[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let param: Option<i32> = rtf[num_start..i].parse().ok();
+                if bytes.get(i) == Some(&b' ') {
+                    i += 1;
+                }
+                if RTF_SKIPPED.contains(&word) {
+                    if let Some(s) = skipped.last_mut() {
+                        *s = true;
+                    }
+                    continue;
+                }
+                if skip {
+                    continue;
+                }
+                match word {
+                    "par" | "line" | "row" => out.push('\n'),
+                    "tab" | "cell" => out.push('\t'),
+                    "uc" => uc = param.unwrap_or(1).max(0),
+                    "u" => {
+                        let n = param.unwrap_or_default();
+                        let n = if n < 0 { n + 65536 } else { n };
+                        if let Some(c) = char::from_u32(n as u32) {
+                            out.push(c);
+                        }
+                        fallback = uc;
+                    }
+                    _ => {}
+                }
+            }
+            b'\r' | b'\n' => i += 1,
+            c => {
+                i += 1;
+                if skip {
+                    continue;
+                }
+                if fallback > 0 {
+                    fallback -= 1;
+                } else {
+                    out.push(c as char);
+                }
+            }
+        }
+    }
+    out.trim().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_plain_text() {
+        assert_eq!(
+            html_to_text(
+                "<html><head><style>p{}</style></head><body><!--StartFragment--><p>a &amp; <b>b</b></p><p>&#x4e2d;&lt;</p><!--EndFragment--></body></html>"
+            ),
+            "a & b\n中<"
+        );
+        assert_eq!(html_to_text("x &unknown y"), "x &unknown y");
+        assert_eq!(
+            rtf_to_text(
+                r"{\rtf1\ansi{\fonttbl{\f0 Arial;}}{\*\generator x;}\f0 Hello \b world\b0\par caf\'e9 \u20013?}"
+            ),
+            "Hello world\ncafé 中"
+        );
+
+        let rich = proto::create_multi_clipboards(vec![
+            ClipboardData::Html("<b>bold</b>".to_owned()),
+            ClipboardData::Rtf(r"{\rtf1 bold}".to_owned()),
+        ])
+        .clipboards;
+        let plain = plain_text(rich);
+        assert_eq!(plain.len(), 1);
+        assert!(matches!(
+            proto::from_multi_clipbards(plain).as_slice(),
+            [ClipboardData::Text(s)] if s == "bold"
+        ));
+        let text = proto::create_multi_clipboards(vec![
+            ClipboardData::Text("text".to_owned()),
+            ClipboardData::Html("<b>bold</b>".to_owned()),
+        ])
+        .clipboards;
+        assert!(matches!(
+            proto::from_multi_clipbards(plain_text(text)).as_slice(),
+            [ClipboardData::Text(s)] if s == "text"
+        ));
+    }
+}
@@ -0,0 +1,118 @@
+//! Change notifications of the macOS pasteboard, for the clipboard listener.
+//!
+//! NSPasteboard posts no notification, but its `changeCount` is increased by every write and is
+//! cheap to read, without the content. The watcher reads it on its own thread, every `FAST` after
+//! a change and backing off to `IDLE` while nothing changes, so an idle host wakes about once a
+//! second and the subscribers are only woken by a real change. `wake()` brings the interval back
+//! to `FAST` at once, it is called when a key is injected, which is how a remote copy starts.
+
+use cocoa::{base::id, foundation::NSInteger};
+use objc::{class, msg_send, sel, sel_impl};
+use std::{
+    sync::{
+        mpsc::{channel, Receiver, RecvTimeoutError, Sender},
+        Mutex,
+    },
+    time::Duration,
+};
+
+const FAST: Duration = Duration::from_millis(100);
+const IDLE: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    static ref WAKE: Mutex<Option<Sender<Signal>>> = Default::default();
+}
+
+enum Signal {
+    Wake,
+    Stop,
+}
+
+pub struct Shutdown(Sender<Signal>);
+
+impl Shutdown {
+    pub fn signal(self) {
+        self.0.send(Signal::Stop).ok();
+    }
+}
+
+/// The interval to the next read of the change count.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Backoff(Duration);
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self(FAST)
+    }
+}
+
+impl Backoff {
+    fn next(&mut self, changed: bool) -> Duration {
+        self.0 = if changed {
+            FAST
+        } else {
+            (self.0 * 3 / 2).min(IDLE)
+        };
+        self.0
+    }
+}
+
+fn change_count() -> NSInteger {
+    unsafe {
+        let pasteboard: id = msg_send![class!(NSPasteboard), generalPasteboard];
+        msg_send![pasteboard, changeCount]
+    }
+}
+
+/// Read the change count sooner, if the watcher is running.
+pub fn wake() {
+    if let Some(tx) = WAKE.lock().unwrap().as_ref() {
+        tx.send(Signal::Wake).ok();
+    }
+}
+
+/// Start the watcher thread, `on_change` is called on it for each change.
+pub fn start(
+    mut on_change: impl FnMut() + Send + 'static,
+) -> (Shutdown, std::thread::JoinHandle<()>) {
+    let (tx, rx) = channel();
+    *WAKE.lock().unwrap() = Some(tx.clone());
+    let h = std::thread::spawn(move || {
+        run(rx, &mut on_change);
+        WAKE.lock().unwrap().take();
+    });
+    (Shutdown(tx), h)
+}
+
+fn run(rx: Receiver<Signal>, on_change: &mut dyn FnMut()) {
+    let mut last = change_count();
+    let mut backoff = Backoff::default();
+    let mut interval = FAST;
+    loop {
+        match rx.recv_timeout(interval) {
+            Ok(Signal::Stop) | Err(RecvTimeoutError::Disconnected) => break,
+            Ok(Signal::Wake) => backoff = Backoff::default(),
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+        let count = change_count();
+        let changed = count != last;
+        if changed {
+            last = count;
+            on_change();
+        }
+        interval = backoff.next(changed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipboard_watcher_backoff() {
+        let mut backoff = Backoff::default();
+        let intervals: Vec<_> = (0..8).map(|_| backoff.next(false).as_millis()).collect();
+        assert_eq!(intervals, vec![150, 225, 337, 506, 759, 1000, 1000, 1000]);
+        assert_eq!(backoff.next(true), FAST);
+    }
+}
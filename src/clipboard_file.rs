@@ -371,4 +371,41 @@ pub mod unix_file_clip {
         }
         vec![]
     }
+
+    /// The id of the `Misc::PluginRequest` cancelling the paste of the files of the other side,
+    /// sent by either side. Its content is empty.
+    pub const CANCEL_PASTE_ID: &str = "file-paste-cancel";
+
+    pub fn make_cancel_paste() -> Message {
+        crate::plugin_request::make_raw(CANCEL_PASTE_ID, Default::default())
+    }
+
+    /// The progress of the paste of the files of the connection, none if none is pasted.
+    pub fn paste_progress(conn_id: i32) -> Option<clipboard::ProgressPercent> {
+        let mut progress = None;
+        clipboard::ContextSend::proc(|context| {
+            progress = context
+                .get_progress_percent()
+                .filter(|p| p.conn_id == conn_id);
+            Ok(())
+        })
+        .ok();
+        progress
+    }
+
+    /// Cancel the paste of the files of the connection, if they are being pasted.
+    pub fn cancel_paste(conn_id: i32) {
+        clipboard::ContextSend::proc(|context| {
+            let pasting = context
+                .get_progress_percent()
+                .map(|p| p.conn_id == conn_id && !p.is_canceled && !p.is_failed)
+                .unwrap_or(false);
+            if pasting {
+                log::info!("Paste of the files of connection {} canceled", conn_id);
+                context.cancel();
+            }
+            Ok(())
+        })
+        .ok();
+    }
 }
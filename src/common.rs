@@ -79,10 +79,14 @@ pub mod input {
     /// 2. Lack of pointer lock API in Sciter/TIS
     /// 3. No OS cursor control (hide/show/clip) FFI bindings in Sciter UI
     pub const MOUSE_TYPE_MOVE_RELATIVE: i32 = 5;
+    /// Wheel deltas in pixels, both axes at once, sent in relative mouse mode to the peers
+    /// with "support_precise_wheel" instead of the notches of `MOUSE_TYPE_WHEEL`.
+    /// The signs are the ones of `MOUSE_TYPE_WHEEL`: positive is up and left.
+    pub const MOUSE_TYPE_WHEEL_PRECISE: i32 = 6;
 
     /// Mask to extract the mouse event type from the mask field.
     /// The lower 3 bits contain the event type (MOUSE_TYPE_*), giving a valid range of 0-7.
-    /// Currently defined types use values 0-5; values 6 and 7 are reserved for future use.
+    /// Currently defined types use values 0-6; value 7 is reserved for future use.
     pub const MOUSE_TYPE_MASK: i32 = 0x7;
 
     pub const MOUSE_BUTTON_LEFT: i32 = 0x01;
@@ -184,7 +188,7 @@ pub fn is_support_file_transfer_resume_num(ver: i64) -> bool {
 
 /// Minimum server version required for relative mouse mode support.
 /// This constant must mirror Flutter's `kMinVersionForRelativeMouseMode` in `consts.dart`.
-const MIN_VERSION_RELATIVE_MOUSE_MODE: &str = "1.4.5";
+pub(crate) const MIN_VERSION_RELATIVE_MOUSE_MODE: &str = "1.4.5";
 
 #[inline]
 pub fn is_support_relative_mouse_mode(ver: &str) -> bool {
@@ -822,7 +826,8 @@ pub fn is_modifier(evt: &KeyEvent) -> bool {
 }
 
 pub fn check_software_update() {
-    if is_custom_client() {
+    // The custom clients are updated from their own manifest only.
+    if is_custom_client() && !crate::update_manifest::is_configured() {
         return;
     }
     let opt = LocalConfig::get_option(keys::OPTION_ENABLE_CHECK_UPDATE);
@@ -832,9 +837,13 @@ pub fn check_software_update() {
 }
 
 // No need to check `danger_accept_invalid_cert` for now.
-// Because the url is always `https://api.rustdesk.com/version/latest`.
+// Because the url is always `https://api.rustdesk.com/version/latest`, unless it is a manifest.
 #[tokio::main(flavor = "current_thread")]
 pub async fn do_check_software_update() -> hbb_common::ResultType<()> {
+    if crate::update_manifest::is_configured() {
+        set_software_update_url(crate::update_manifest::check().await?);
+        return Ok(());
+    }
     let (request, url) =
         hbb_common::version_check_request(hbb_common::VER_TYPE_RUSTDESK_CLIENT.to_string());
     let proxy_conf = Config::get_socks();
@@ -866,22 +875,27 @@ pub async fn do_check_software_update() -> hbb_common::ResultType<()> {
     let latest_release_version = response_url.rsplit('/').next().unwrap_or_default();
 
     if get_version_number(&latest_release_version) > get_version_number(crate::VERSION) {
-        #[cfg(feature = "flutter")]
-        {
-            let mut m = HashMap::new();
-            m.insert("name", "check_software_update_finish");
-            m.insert("url", &response_url);
-            if let Ok(data) = serde_json::to_string(&m) {
-                let _ = crate::flutter::push_global_event(crate::flutter::APP_TYPE_MAIN, data);
-            }
-        }
-        *SOFTWARE_UPDATE_URL.lock().unwrap() = response_url;
+        set_software_update_url(response_url);
     } else {
-        *SOFTWARE_UPDATE_URL.lock().unwrap() = "".to_string();
+        set_software_update_url("".to_string());
     }
     Ok(())
 }
 
+// The url of the newer version, empty if there is none.
+fn set_software_update_url(url: String) {
+    #[cfg(feature = "flutter")]
+    if !url.is_empty() {
+        let mut m = HashMap::new();
+        m.insert("name", "check_software_update_finish");
+        m.insert("url", &url);
+        if let Ok(data) = serde_json::to_string(&m) {
+            let _ = crate::flutter::push_global_event(crate::flutter::APP_TYPE_MAIN, data);
+        }
+    }
+    *SOFTWARE_UPDATE_URL.lock().unwrap() = url;
+}
+
 #[inline]
 pub fn get_app_name() -> String {
     hbb_common::config::APP_NAME.read().unwrap().clone()
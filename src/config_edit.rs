@@ -0,0 +1,364 @@
+//! Small config files of the host edited from the client, without a file transfer or a terminal.
+//!
+//! Only the files listed in `OPTION_CONFIG_EDIT_FILES` are read and written, and the environment
+//! variables listed in `OPTION_CONFIG_EDIT_ENV` read, both `;` separated lists where the built-in
+//! setting takes precedence over the local one. A file larger than `MAX_SIZE` is refused. Before
+//! a file is replaced, its content is copied to a backup next to it, and the answer has the diff
+//! of the change. The write carries the content the client edited, so the changes made on the
+//! host since it was read are not overwritten. The writes are file changes of the connection, the
+//! host allows them as it allows its other file actions.
+//!
+//! The client sends a `Request` to the hosts with "config_edit" in their platform additions, and
+//! the host answers with a `Response`.
+
+use crate::plugin_request;
+use hbb_common::{config::Config, log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{fs::OpenOptions, io::Write, path::Path};
+
+pub const ID: &str = "config-edit";
+pub const PLATFORM_ADDITION_KEY: &str = "config_edit";
+pub const OPTION_CONFIG_EDIT_FILES: &str = "config-edit-files";
+pub const OPTION_CONFIG_EDIT_ENV: &str = "config-edit-env";
+pub const MAX_SIZE: usize = 256 * 1024;
+const SEPARATOR: char = ';';
+// Above this, the lines between the common head and tail are all shown as changed.
+const MAX_DIFF_CELLS: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Request {
+    List,
+    Read {
+        path: String,
+    },
+    Write {
+        path: String,
+        base: String,
+        content: String,
+    },
+    Env,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileInfo {
+    pub path: String,
+    pub size: u64,
+    pub exists: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    #[serde(default)]
+    pub files: Vec<FileInfo>,
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub content: String,
+    /// Whether the file was written, with the diff of the change.
+    #[serde(default)]
+    pub written: bool,
+    #[serde(default)]
+    pub diff: String,
+    #[serde(default)]
+    pub backup: String,
+    #[serde(default)]
+    pub env: Vec<(String, String)>,
+    #[serde(default)]
+    pub error: String,
+}
+
+fn list_option(name: &str) -> Vec<String> {
+    let mut option = crate::get_builtin_option(name);
+    if option.trim().is_empty() {
+        option = Config::get_option(name);
+    }
+    option
+        .split(SEPARATOR)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.to_owned())
+        .collect()
+}
+
+/// Whether the host has any file or variable to edit.
+pub fn is_enabled() -> bool {
+    !list_option(OPTION_CONFIG_EDIT_FILES).is_empty()
+        || !list_option(OPTION_CONFIG_EDIT_ENV).is_empty()
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+}
+
+pub fn make_request(req: &Request) -> Message {
+    plugin_request::make(ID, req)
+}
+
+/// The answer to a request the host refused.
+pub fn make_error(path: &str, error: &str) -> Message {
+    plugin_request::make(
+        ID,
+        &Response {
+            path: path.to_owned(),
+            error: error.to_owned(),
+            ..Default::default()
+        },
+    )
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+fn check_path(path: &str) -> Result<(), String> {
+    if list_option(OPTION_CONFIG_EDIT_FILES)
+        .iter()
+        .any(|p| p == path)
+    {
+        Ok(())
+    } else {
+        Err(format!("{} is not allowed to be edited", path))
+    }
+}
+
+fn read(path: &str) -> Result<String, String> {
+    let len = std::fs::metadata(path).map_err(|e| e.to_string())?.len();
+    if len as usize > MAX_SIZE {
+        return Err(format!("{} is larger than {} bytes", path, MAX_SIZE));
+    }
+    std::fs::read_to_string(path).map_err(|e| e.to_string())
+}
+
+// A name no other backup has, the saves of the same millisecond are numbered.
+fn backup_path(path: &str) -> String {
+    let time = chrono::Local::now().format("%Y%m%d%H%M%S%3f").to_string();
+    let mut backup = format!("{}.{}.bak", path, time);
+    let mut n = 1;
+    while Path::new(&backup).symlink_metadata().is_ok() {
+        backup = format!("{}.{}-{}.bak", path, time, n);
+        n += 1;
+    }
+    backup
+}
+
+// The temporary file a crash left is removed, if it is a file of this process's user and not a
+// link, which would be written through.
+fn remove_stale_tmp(tmp: &str) -> Result<(), String> {
+    let Ok(meta) = Path::new(tmp).symlink_metadata() else {
+        return Ok(());
+    };
+    #[cfg(unix)]
+    let owned = {
+        use std::os::unix::fs::MetadataExt;
+        meta.uid() == unsafe { hbb_common::libc::geteuid() }
+    };
+    #[cfg(not(unix))]
+    let owned = true;
+    if !meta.file_type().is_file() || !owned {
+        return Err(format!("{} is in the way", tmp));
+    }
+    log::info!("Remove the stale {}", tmp);
+    std::fs::remove_file(tmp).map_err(|e| format!("{}: {}", tmp, e))
+}
+
+// Replaces the file through a temporary one, so it is never left half written.
+fn write(path: &str, base: &str, content: &str) -> Result<(String, String), String> {
+    if content.len() > MAX_SIZE {
+        return Err(format!("The content is larger than {} bytes", MAX_SIZE));
+    }
+    let exists = Path::new(path).exists();
+    let current = if exists { read(path)? } else { String::new() };
+    if current != base {
+        return Err(format!(
+            "{} was changed on the host since it was read",
+            path
+        ));
+    }
+    let mut backup = String::new();
+    if exists {
+        backup = backup_path(path);
+        std::fs::copy(path, &backup).map_err(|e| e.to_string())?;
+    }
+    // Not through a file or a link left there.
+    let tmp = format!("{}.tmp", path);
+    remove_stale_tmp(&tmp)?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&tmp)
+        .map_err(|e| format!("{}: {}", tmp, e))?;
+    if let Err(e) = file.write_all(content.as_bytes()) {
+        std::fs::remove_file(&tmp).ok();
+        return Err(e.to_string());
+    }
+    drop(file);
+    if let Ok(meta) = std::fs::metadata(path) {
+        std::fs::set_permissions(&tmp, meta.permissions()).ok();
+    }
+    if let Err(e) = std::fs::rename(&tmp, path) {
+        std::fs::remove_file(&tmp).ok();
+        return Err(e.to_string());
+    }
+    Ok((diff(&current, content), backup))
+}
+
+/// Answer the request of the connection on the host, blocking on the files and the diff.
+pub fn handle_request(conn_id: i32, req: Request) -> Message {
+    let mut res = Response::default();
+    let result = match req {
+        Request::List => {
+            res.files = list_option(OPTION_CONFIG_EDIT_FILES)
+                .into_iter()
+                .map(|path| {
+                    let meta = std::fs::metadata(&path).ok();
+                    FileInfo {
+                        exists: meta.is_some(),
+                        size: meta.map(|m| m.len()).unwrap_or(0),
+                        path,
+                    }
+                })
+                .collect();
+            Ok(())
+        }
+        Request::Read { path } => {
+            res.path = path.clone();
+            check_path(&path)
+                .and_then(|_| read(&path))
+                .map(|content| res.content = content)
+        }
+        Request::Write {
+            path,
+            base,
+            content,
+        } => {
+            res.path = path.clone();
+            check_path(&path)
+                .and_then(|_| write(&path, &base, &content))
+                .map(|(diff, backup)| {
+                    log::info!(
+                        "{} edited by connection {}, backup: {}",
+                        path,
+                        conn_id,
+                        backup
+                    );
+                    res.written = true;
+                    res.diff = diff;
+                    res.backup = backup;
+                    res.content = content;
+                })
+        }
+        Request::Env => {
+            res.env = list_option(OPTION_CONFIG_EDIT_ENV)
+                .into_iter()
+                .map(|name| {
+                    let value = std::env::var(&name).unwrap_or_default();
+                    (name, value)
+                })
+                .collect();
+            Ok(())
+        }
+    };
+    if let Err(e) = result {
+        res.error = e;
+    }
+    plugin_request::make(ID, &res)
+}
+
+/// The lines removed with "-" and added with "+", between the lines kept with " ".
+pub fn diff(old: &str, new: &str) -> String {
+    let old: Vec<&str> = old.lines().collect();
+    let new: Vec<&str> = new.lines().collect();
+    let head = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let tail = old[head..]
+        .iter()
+        .rev()
+        .zip(new[head..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (a, b) = (&old[head..old.len() - tail], &new[head..new.len() - tail]);
+    let mut lines: Vec<String> = old[..head].iter().map(|l| format!(" {}", l)).collect();
+    if a.len() * b.len() > MAX_DIFF_CELLS {
+        lines.extend(a.iter().map(|l| format!("-{}", l)));
+        lines.extend(b.iter().map(|l| format!("+{}", l)));
+    } else {
+        // The longest common subsequence of the lines, from the end.
+        let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push(format!(" {}", a[i]));
+                i += 1;
+                j += 1;
+            } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(format!("-{}", a[i]));
+                i += 1;
+            } else {
+                lines.push(format!("+{}", b[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(old[old.len() - tail..].iter().map(|l| format!(" {}", l)));
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_edit() {
+        assert_eq!(diff("a\nb\nc", "a\nb\nc"), " a\n b\n c");
+        assert_eq!(diff("a\nb\nc", "a\nx\nc"), " a\n-b\n+x\n c");
+        assert_eq!(diff("", "a"), "+a");
+        assert_eq!(diff("a\nb", "b"), "-a\n b");
+
+        let req: Request =
+            serde_json::from_str(r#"{"action":"read","path":"/etc/app.toml"}"#).unwrap();
+        assert_eq!(
+            req,
+            Request::Read {
+                path: "/etc/app.toml".to_owned()
+            }
+        );
+
+        let dir = std::env::temp_dir().join(format!("config-edit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("app.toml").to_string_lossy().to_string();
+        std::fs::write(&path, "a = 1\n").unwrap();
+        assert!(write(&path, "a = 2\n", "a = 3\n").is_err());
+        let (diff, backup) = write(&path, "a = 1\n", "a = 2\n").unwrap();
+        assert_eq!(diff, "-a = 1\n+a = 2");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a = 2\n");
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "a = 1\n");
+        // A file a crash left at the temporary path is replaced, the backups are not.
+        std::fs::write(format!("{}.tmp", path), "").unwrap();
+        let (_, backup2) = write(&path, "a = 2\n", "a = 3\n").unwrap();
+        assert_ne!(backup, backup2);
+        assert_eq!(std::fs::read_to_string(&backup).unwrap(), "a = 1\n");
+        assert_eq!(std::fs::read_to_string(&backup2).unwrap(), "a = 2\n");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "a = 3\n");
+        // A link there is not written through.
+        #[cfg(unix)]
+        {
+            let other = dir.join("other").to_string_lossy().to_string();
+            std::os::unix::fs::symlink(&other, format!("{}.tmp", path)).unwrap();
+            assert!(write(&path, "a = 3\n", "a = 4\n").is_err());
+            assert_eq!(std::fs::read_to_string(&path).unwrap(), "a = 3\n");
+            assert!(!Path::new(&other).exists());
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
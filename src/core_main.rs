@@ -30,6 +30,7 @@ pub fn core_main() -> Option<Vec<String>> {
     let mut _is_quick_support = false;
     let mut _is_flutter_invoke_new_connection = false;
     let mut no_server = false;
+    let mut auto_virtual_x = false;
     for arg in std::env::args() {
         if i == 0 {
             let _ = arg;
@@ -56,6 +57,8 @@ pub fn core_main() -> Option<Vec<String>> {
                 _is_quick_support = true;
             } else if arg == "--no-server" {
                 no_server = true;
+            } else if arg == crate::platform::virtual_display::ARG_AUTO_VIRTUAL_X {
+                auto_virtual_x = true;
             } else {
                 args.push(arg);
             }
@@ -76,6 +79,11 @@ pub fn core_main() -> Option<Vec<String>> {
     if args.contains(&"--noinstall".to_string()) {
         args.clear();
     }
+    // Without the UI, the process is the server itself.
+    #[cfg(feature = "headless-agent")]
+    if args.is_empty() {
+        args.push("--server".to_owned());
+    }
     if args.len() > 0 {
         if args[0] == "--version" {
             println!("{}", crate::VERSION);
@@ -143,6 +151,7 @@ pub fn core_main() -> Option<Vec<String>> {
                 return None;
             }
         } else if args[0] == "--tray" {
+            #[cfg(feature = "ui")]
             if !crate::check_process("--tray", true) {
                 crate::tray::start_tray();
             }
@@ -155,13 +164,29 @@ pub fn core_main() -> Option<Vec<String>> {
             log::info!("start --uninstall-service");
             crate::platform::uninstall_service(false, true);
             return None;
+        } else if args[0] == "--repair-service" {
+            log::info!("start --repair-service");
+            let report = platform::repair::run(args.iter().any(|a| a == "--dry-run"));
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&report).unwrap_or_default()
+            );
+            return None;
         } else if args[0] == "--service" {
             log::info!("start --service");
+            crate::secret_store::start_service();
             crate::start_os_service();
             return None;
         } else if args[0] == "--server" {
             log::info!("start --server with user {}", crate::username());
+            // Removed when the server stops.
+            let _virtual_display = if auto_virtual_x {
+                crate::platform::virtual_display::VirtualDisplay::provision_if_needed()
+            } else {
+                None
+            };
             let handler = std::thread::spawn(move || crate::start_server(true, false));
+            #[cfg(feature = "ui")]
             crate::tray::start_tray();
             // prevent server exit when encountering errors from tray
             hbb_common::allow_err!(handler.join());
@@ -222,6 +247,93 @@ pub fn core_main() -> Option<Vec<String>> {
         } else if args[0] == "--get-id" {
             println!("{}", crate::ipc::get_id());
             return None;
+        } else if args[0] == "--availability" {
+            use crate::hbbs_http::availability::*;
+            if args.len() == 1 {
+                let options = crate::ipc::get_options();
+                let get = |k: &str| options.get(k).cloned().unwrap_or_default();
+                println!(
+                    "{} {}",
+                    check(&get(OPTION_AVAILABILITY_STATUS), "")
+                        .map(|x| x.0)
+                        .unwrap_or_default(),
+                    get(OPTION_AVAILABILITY_NOTE)
+                );
+            } else {
+                let note = args[2..].join(" ");
+                match check(&args[1], &note) {
+                    Ok((status, note)) => {
+                        crate::ipc::set_option(OPTION_AVAILABILITY_STATUS, &status);
+                        crate::ipc::set_option(OPTION_AVAILABILITY_NOTE, &note);
+                        println!("Done!");
+                    }
+                    Err(e) => println!("{}", e),
+                }
+            }
+            return None;
+        } else if args[0] == "--session-stats" {
+            println!("{}", crate::ui_interface::get_session_stats());
+            return None;
+        } else if args[0] == "--doctor" {
+            println!("{}", crate::ui_interface::doctor());
+            return None;
+        } else if args[0] == "--test-keyboard-layout" {
+            println!("Typing the samples, do not use the keyboard until it is done...");
+            match crate::roundtrip::keyboard_layout(args.get(1).cloned()) {
+                Ok(report) => println!("{}", report),
+                Err(err) => println!("{}", err),
+            }
+            return None;
+        } else if args[0] == "--test-clipboard" {
+            match crate::roundtrip::clipboard() {
+                Ok(report) => println!("{}", report),
+                Err(err) => println!("{}", err),
+            }
+            return None;
+        } else if args[0] == "--bulk" {
+            match crate::bulk::Options::parse(&args[1..]) {
+                Ok(options) => println!("{}", crate::bulk::run(options)),
+                Err(err) => println!("{}", err),
+            }
+            return None;
+        } else if args[0] == "--simulate-clients" {
+            #[cfg(feature = "simulate-clients")]
+            {
+                use crate::server::simulation;
+                match simulation::Options::parse(&args[1..]) {
+                    Ok(options) => match simulation::run(options) {
+                        Ok(report) => println!("{}", report),
+                        Err(err) => println!("{}", err),
+                    },
+                    Err(err) => println!("{}", err),
+                }
+            }
+            #[cfg(not(feature = "simulate-clients"))]
+            println!("Built without the simulate-clients feature");
+            return None;
+        } else if args[0] == "--list-options" {
+            let json = args.iter().any(|a| a == "--json");
+            let query = args[1..]
+                .iter()
+                .filter(|a| *a != "--json")
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+            if json {
+                println!("{}", crate::ui_interface::list_options(query));
+            } else {
+                for o in crate::server::option_registry::search(&query) {
+                    println!(
+                        "{}\t{}\t{}\t{}\t{}",
+                        o.name,
+                        serde_json::to_string(&o.kind).unwrap_or_default(),
+                        o.default,
+                        if o.requires_restart { "restart" } else { "" },
+                        o.description
+                    );
+                }
+            }
+            return None;
         } else if args[0] == "--set-id" {
             if config::is_disable_settings() {
                 println!("Settings are disabled!");
@@ -263,6 +375,17 @@ pub fn core_main() -> Option<Vec<String>> {
                             crate::ui_interface::set_option("api-server".into(), lic.api);
                             crate::ui_interface::set_option("relay-server".into(), lic.relay);
                         }
+                        if !lic.update.is_empty() {
+                            use crate::update_manifest::*;
+                            crate::ui_interface::set_option(
+                                OPTION_UPDATE_MANIFEST_URL.into(),
+                                lic.update,
+                            );
+                            crate::ui_interface::set_option(
+                                OPTION_UPDATE_MANIFEST_KEY.into(),
+                                lic.update_key,
+                            );
+                        }
                     }
                 } else {
                     println!("Installation and administrative privileges required!");
@@ -285,6 +408,32 @@ pub fn core_main() -> Option<Vec<String>> {
                 println!("Installation and administrative privileges required!");
             }
             return None;
+        } else if args[0] == "--input-timeline" {
+            if !(crate::platform::is_installed() && is_root()) {
+                println!("Installation and administrative privileges required!");
+                return None;
+            }
+            match args.get(1).map(|s| s.as_str()) {
+                Some("export") => {
+                    let days = args
+                        .iter()
+                        .position(|x| x == "--since")
+                        .and_then(|i| args.get(i + 1))
+                        .and_then(|d| d.parse().ok());
+                    match crate::ipc::export_input_timeline(days) {
+                        Ok(records) => print!("{}", records),
+                        Err(e) => println!("Failed to export the input timeline: {}", e),
+                    }
+                }
+                Some("clear") => match crate::ipc::clear_input_timeline() {
+                    Ok(n) => println!("{} files removed", n),
+                    Err(e) => println!("Failed to clear the input timeline: {}", e),
+                },
+                _ => {
+                    println!("Usage: --input-timeline export [--since <days>] | clear");
+                }
+            }
+            return None;
         } else if args[0] == "--assign" {
             if config::Config::no_register_device() {
                 println!("Cannot assign an unregistrable device!");
@@ -402,14 +551,25 @@ pub fn core_main() -> Option<Vec<String>> {
             // call connection manager to establish connections
             // meanwhile, return true to call flutter window to show control panel
             crate::ui_interface::start_option_status_sync();
+            #[cfg(feature = "headless-agent")]
+            {
+                crate::headless::start_cm();
+                return None;
+            }
         } else if args[0] == "--cm-no-ui" {
             #[cfg(feature = "flutter")]
             {
                 crate::ui_interface::start_option_status_sync();
                 crate::flutter::connection_manager::start_cm_no_ui();
             }
+            #[cfg(feature = "headless-agent")]
+            {
+                crate::ui_interface::start_option_status_sync();
+                crate::headless::start_cm();
+            }
             return None;
         } else if args[0] == "--whiteboard" {
+            #[cfg(feature = "ui")]
             crate::whiteboard::run();
             return None;
         } else if args[0] == "-gtk-sudo" {
@@ -16,6 +16,12 @@ pub struct CustomServer {
     pub api: String,
     #[serde(default)]
     pub relay: String,
+    /// The url of the update manifest, see `update_manifest`.
+    #[serde(default)]
+    pub update: String,
+    /// The public key the releases of the manifest are signed with.
+    #[serde(default)]
+    pub update_key: String,
 }
 
 fn get_custom_server_from_config_string(s: &str) -> ResultType<CustomServer> {
@@ -84,6 +90,7 @@ pub fn get_custom_server_from_string(s: &str) -> ResultType<CustomServer> {
             key,
             api,
             relay,
+            ..Default::default()
         });
     } else {
         let s = s
@@ -122,6 +129,7 @@ mod test {
                 key: "".to_owned(),
                 api: "".to_owned(),
                 relay: "".to_owned(),
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -131,6 +139,7 @@ mod test {
                 key: "".to_owned(),
                 api: "".to_owned(),
                 relay: "".to_owned(),
+                ..Default::default()
             }
         );
         // key in these tests is "foobar.,2" base64 encoded
@@ -144,6 +153,7 @@ mod test {
                 key: "Zm9vYmFyLiwyCg==".to_owned(),
                 api: "abc".to_owned(),
                 relay: "".to_owned(),
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -156,6 +166,7 @@ mod test {
                 key: "Zm9vYmFyLiwyCg==".to_owned(),
                 api: "".to_owned(),
                 relay: "".to_owned(),
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -168,6 +179,7 @@ mod test {
                 key: "Zm9vYmFyLiwyCg==".to_owned(),
                 api: "".to_owned(),
                 relay: "server.example.net".to_owned(),
+                ..Default::default()
             }
         );
         assert_eq!(
@@ -180,6 +192,7 @@ mod test {
                 key: "Zm9vYmFyLiwyCg==".to_owned(),
                 api: "".to_owned(),
                 relay: "server.example.net".to_owned(),
+                ..Default::default()
             }
         );
         let lic = CustomServer {
@@ -187,6 +200,7 @@ mod test {
             key: "5Qbwsde3unUcJBtrx9ZkvUmwFNoExHzpryHuPUdqlWM=".to_owned(),
             api: "".to_owned(),
             relay: "".to_owned(),
+            ..Default::default()
         };
         assert_eq!(
             get_custom_server_from_string("rustdesk-licensed-0nI900VsFHZVBVdIlncwpHS4V0bOZ0dtVldrpVO4JHdCp0YV5WdzUGZzdnYRVjI6ISeltmIsISMuEjLx4SMiojI0N3boJye.exe")
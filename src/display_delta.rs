@@ -0,0 +1,185 @@
+//! Display changes sent as a delta instead of a full `PeerInfo`.
+//!
+//! The display service of the host sends the whole list of the displays in a `PeerInfo` each time
+//! one of them changes, and the client refreshes all of them. On a host with several monitors, a
+//! hot-plug or a change of resolution sends a burst of them. A host with "support_display_delta"
+//! in its platform additions sends a delta instead to the clients which asked for it with an
+//! empty `Misc::PluginRequest` of the reserved `ID`: the changed displays only, and the order of
+//! all of them, a display being identified by its name, stable across the changes.
+//!
+//! The content of a delta is the length of the json header in big endian u32, the header with the
+//! order, then a `PeerInfo` with the changed displays and the platform additions of the full one,
+//! encoded as protobuf.
+
+use crate::plugin_request;
+use hbb_common::{
+    bytes::{BufMut, Bytes, BytesMut},
+    message_proto::{DisplayInfo, Message, PeerInfo, PluginRequest},
+    protobuf::Message as _,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const ID: &str = "display-delta";
+pub const PLATFORM_ADDITION_KEY: &str = "support_display_delta";
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Header {
+    order: Vec<String>,
+}
+
+fn make_message(content: Bytes) -> Message {
+    plugin_request::make_raw(ID, content)
+}
+
+/// The request of the client to get the deltas.
+pub fn make_request() -> Message {
+    make_message(Bytes::new())
+}
+
+fn has_unique_names(displays: &[DisplayInfo]) -> bool {
+    let mut names = HashSet::new();
+    displays.iter().all(|d| names.insert(d.name.as_str()))
+}
+
+/// The displays sent to one client, on the host.
+#[derive(Debug, Default)]
+pub struct Sender {
+    enabled: bool,
+    sent: Vec<DisplayInfo>,
+}
+
+impl Sender {
+    pub fn enable(&mut self) {
+        self.enabled = true;
+    }
+
+    /// The displays of the login response.
+    pub fn set_sent(&mut self, displays: &[DisplayInfo]) {
+        self.sent = displays.to_vec();
+    }
+
+    /// What to send for the displays of the service: none to send them in full, an empty delta if
+    /// the client has them already.
+    pub fn diff(&mut self, pi: &PeerInfo) -> Option<Option<Message>> {
+        let displays = &pi.displays;
+        // A full peer info, or displays which can not be told apart.
+        if !self.enabled
+            || !pi.username.is_empty()
+            || !has_unique_names(displays)
+            || !has_unique_names(&self.sent)
+        {
+            self.set_sent(displays);
+            return None;
+        }
+        let order: Vec<String> = displays.iter().map(|d| d.name.clone()).collect();
+        let changed: Vec<DisplayInfo> = displays
+            .iter()
+            .filter(|d| !self.sent.contains(d))
+            .cloned()
+            .collect();
+        let same_order = self.sent.iter().map(|d| &d.name).eq(order.iter());
+        self.set_sent(displays);
+        if changed.is_empty() && same_order {
+            return Some(None);
+        }
+        let header = serde_json::to_vec(&Header { order }).ok()?;
+        let changed = PeerInfo {
+            displays: changed,
+            platform_additions: pi.platform_additions.clone(),
+            ..Default::default()
+        };
+        let mut buf = BytesMut::new();
+        buf.put_u32(header.len() as u32);
+        buf.put_slice(&header);
+        buf.put_slice(&changed.write_to_bytes().ok()?);
+        Some(Some(make_message(buf.freeze())))
+    }
+}
+
+/// The peer info of the displays after the delta, none if the delta does not apply to the
+/// displays of the client.
+pub fn apply(current: &[DisplayInfo], content: &[u8]) -> Option<PeerInfo> {
+    let len = u32::from_be_bytes(content.get(..4)?.try_into().ok()?) as usize;
+    let header: Header = serde_json::from_slice(content.get(4..4 + len)?).ok()?;
+    let mut pi = PeerInfo::parse_from_bytes(content.get(4 + len..)?).ok()?;
+    pi.displays = header
+        .order
+        .iter()
+        .map(|name| {
+            pi.displays
+                .iter()
+                .chain(current.iter())
+                .find(|d| &d.name == name)
+                .cloned()
+        })
+        .collect::<Option<_>>()?;
+    Some(pi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(name: &str, width: i32) -> DisplayInfo {
+        DisplayInfo {
+            name: name.to_owned(),
+            width,
+            ..Default::default()
+        }
+    }
+
+    fn content(msg: Message) -> Bytes {
+        match msg.misc().union.clone() {
+            Some(hbb_common::message_proto::misc::Union::PluginRequest(p)) => p.content,
+            _ => panic!("not a delta"),
+        }
+    }
+
+    #[test]
+    fn display_delta_apply() {
+        let peer_info = |displays: Vec<DisplayInfo>| PeerInfo {
+            displays,
+            ..Default::default()
+        };
+        let client = vec![display("1", 1920), display("2", 1280)];
+        let mut sender = Sender::default();
+        sender.set_sent(&client);
+        // Full until the client asks for the deltas.
+        assert!(sender.diff(&peer_info(client.clone())).is_none());
+        sender.enable();
+        assert!(matches!(
+            sender.diff(&peer_info(client.clone())),
+            Some(None)
+        ));
+
+        let host = vec![display("2", 2560), display("1", 1920), display("3", 800)];
+        let delta = content(sender.diff(&peer_info(host.clone())).unwrap().unwrap());
+        // The display "1" is not sent again.
+        let len = u32::from_be_bytes(delta[..4].try_into().unwrap()) as usize;
+        let changed = PeerInfo::parse_from_bytes(&delta[4 + len..]).unwrap();
+        assert_eq!(changed.displays, vec![host[0].clone(), host[2].clone()]);
+        assert_eq!(
+            apply(&client, &delta).map(|pi| pi.displays),
+            Some(host.clone())
+        );
+        // An unknown display.
+        assert_eq!(apply(&[], &delta), None);
+
+        // Unplugged.
+        let delta = content(
+            sender
+                .diff(&peer_info(host[..1].to_vec()))
+                .unwrap()
+                .unwrap(),
+        );
+        assert_eq!(
+            apply(&host, &delta).map(|pi| pi.displays),
+            Some(host[..1].to_vec())
+        );
+
+        let mut full = peer_info(host);
+        full.username = "user".to_owned();
+        assert!(sender.diff(&full).is_none());
+    }
+}
@@ -0,0 +1,127 @@
+//! Stable ids of the displays of the host.
+//!
+//! The messages of the protocol refer to a display by its index, which shifts when a monitor is
+//! plugged or unplugged, so a switch sent during a hot-plug could land on another display. The
+//! host sends the id of each display, in the order of the displays, in the "display_ids" of the
+//! platform additions, with the login and with every change of the displays. The id is made from
+//! the EDID when the platform has one, else it is the name of the display.
+//!
+//! A client which got the ids switches and captures the displays with a `Request` by id, and the
+//! host finds their current index. The client of an older host, which sends no ids, keeps the
+//! indexes.
+
+use crate::plugin_request;
+use hbb_common::message_proto::{DisplayInfo, Message};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+pub const ID: &str = "display-id";
+pub const PLATFORM_ADDITION_KEY: &str = "display_ids";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Request {
+    Switch {
+        id: String,
+        width: i32,
+        height: i32,
+    },
+    Capture {
+        add: Vec<String>,
+        sub: Vec<String>,
+        set: Vec<String>,
+    },
+}
+
+impl Request {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+}
+
+/// The id of a display of the host.
+pub fn id_of(display: &DisplayInfo) -> String {
+    display
+        .name
+        .parse()
+        .ok()
+        .and_then(crate::platform::display_uuid)
+        .unwrap_or_else(|| display.name.clone())
+}
+
+/// Add the ids of the displays to the platform additions of a `PeerInfo`.
+pub fn add_to(platform_additions: &mut String, displays: &[DisplayInfo]) {
+    let mut map: Map<String, Value> = serde_json::from_str(platform_additions).unwrap_or_default();
+    let ids: Vec<String> = displays.iter().map(id_of).collect();
+    map.insert(PLATFORM_ADDITION_KEY.to_owned(), json!(ids));
+    *platform_additions = serde_json::to_string(&map).unwrap_or_default();
+}
+
+/// The ids of the platform additions, none if they have no ids.
+pub fn parse(platform_additions: &str) -> Option<Vec<String>> {
+    let mut map: Map<String, Value> = serde_json::from_str(platform_additions).ok()?;
+    serde_json::from_value(map.remove(PLATFORM_ADDITION_KEY)?).ok()
+}
+
+/// The indexes of the displays, the ids not found are skipped.
+pub fn indexes(ids: &[String], wanted: &[String]) -> Vec<usize> {
+    wanted
+        .iter()
+        .filter_map(|id| ids.iter().position(|i| i == id))
+        .collect()
+}
+
+/// The ids of the displays at the indexes, none if the peer sends no ids or one is not known.
+pub fn ids_of(ids: &[String], indexes: &[i32]) -> Option<Vec<String>> {
+    if ids.is_empty() {
+        return None;
+    }
+    indexes
+        .iter()
+        .map(|i| ids.get(usize::try_from(*i).ok()?).cloned())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_id_request() {
+        let req = Request::Capture {
+            add: vec!["B".to_owned()],
+            sub: vec![],
+            set: vec![],
+        };
+        let content = serde_json::to_vec(&req).unwrap();
+        assert_eq!(Request::parse(&content), Some(req));
+        assert!(Request::parse(br#"{"type":"switch","id":"A"}"#).is_none());
+
+        let displays = vec![
+            DisplayInfo {
+                name: "main".to_owned(),
+                ..Default::default()
+            },
+            DisplayInfo {
+                name: "side".to_owned(),
+                ..Default::default()
+            },
+        ];
+        let mut platform_additions = r#"{"support_mux":true}"#.to_owned();
+        add_to(&mut platform_additions, &displays);
+        let ids = parse(&platform_additions).unwrap();
+        assert_eq!(ids, vec!["main", "side"]);
+        assert!(platform_additions.contains("support_mux"));
+        assert_eq!(parse(r#"{"support_mux":true}"#), None);
+
+        let wanted = ["side".to_owned(), "gone".to_owned(), "main".to_owned()];
+        assert_eq!(indexes(&ids, &wanted), vec![1, 0]);
+        assert_eq!(ids_of(&ids, &[1]), Some(vec!["side".to_owned()]));
+        assert_eq!(ids_of(&ids, &[2]), None);
+        assert_eq!(ids_of(&[], &[0]), None);
+    }
+}
@@ -0,0 +1,145 @@
+//! Sleep state of the displays of the host.
+//!
+//! A display which sleeps sends no frames, so the viewer kept the last one as if the session had
+//! frozen. The video service of each display checks its state every `CHECK_INTERVAL` and sends a
+//! `Notice` when it changes, or when a viewer joins while it sleeps, so the client can show a
+//! placeholder instead. The client may answer with a `WakeRequest`, automatically with
+//! `PEER_OPTION` on "Y" or from the placeholder.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+pub const ID: &str = "display-power";
+pub const PEER_OPTION: &str = "wake-display";
+pub const PLATFORM_ADDITION_KEY: &str = "support_display_power";
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Notice {
+    pub display: usize,
+    pub asleep: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct WakeRequest {
+    pub display: usize,
+}
+
+pub fn make_wake_request(display: usize) -> Message {
+    plugin_request::make(ID, &WakeRequest { display })
+}
+
+pub fn parse_notice(content: &[u8]) -> Option<Notice> {
+    serde_json::from_slice(content).ok()
+}
+
+/// Wake the displays of the host, for the request of a connection.
+pub fn handle_request(conn_id: i32, content: &[u8]) {
+    match serde_json::from_slice::<WakeRequest>(content) {
+        Ok(req) => {
+            log::info!("Connection {} wakes the display {}", conn_id, req.display);
+            if let Err(e) = crate::platform::wake_display() {
+                log::error!("Failed to wake the display: {}", e);
+            }
+        }
+        Err(e) => log::warn!("Invalid display wake request: {}", e),
+    }
+}
+
+/// The state of one display, checked by its video service.
+pub struct Watcher {
+    display: usize,
+    // The id of the platform, none if the display is not known.
+    id: Option<u32>,
+    asleep: bool,
+    notified: HashSet<i32>,
+    last_check: Option<Instant>,
+}
+
+impl Watcher {
+    pub fn new(display: usize) -> Self {
+        let id = scrap::Display::all()
+            .ok()
+            .and_then(|displays| displays.get(display).map(|d| d.name()))
+            .and_then(|name| name.parse().ok());
+        Self {
+            display,
+            id,
+            asleep: false,
+            notified: HashSet::new(),
+            last_check: None,
+        }
+    }
+
+    /// The notice to send to the viewers, if the state changed or a viewer is not told yet.
+    pub fn check(&mut self, subscribers: &HashSet<i32>) -> Option<Message> {
+        let id = self.id?;
+        if matches!(self.last_check, Some(t) if t.elapsed() < CHECK_INTERVAL) {
+            return None;
+        }
+        self.last_check = Some(Instant::now());
+        self.update(crate::platform::is_display_asleep(id), subscribers)
+    }
+
+    fn update(&mut self, asleep: bool, subscribers: &HashSet<i32>) -> Option<Message> {
+        let changed = asleep != self.asleep;
+        let joined = asleep && !subscribers.is_subset(&self.notified);
+        self.asleep = asleep;
+        self.notified = subscribers.clone();
+        if !changed && !joined {
+            return None;
+        }
+        if changed {
+            log::info!(
+                "Display {} is {}",
+                self.display,
+                if asleep { "asleep" } else { "awake" }
+            );
+        }
+        Some(plugin_request::make(
+            ID,
+            &Notice {
+                display: self.display,
+                asleep,
+            },
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_power_notice() {
+        let mut watcher = Watcher {
+            display: 1,
+            id: Some(1),
+            asleep: false,
+            notified: HashSet::new(),
+            last_check: None,
+        };
+        let conns: HashSet<i32> = [1].into_iter().collect();
+        assert!(watcher.update(false, &conns).is_none());
+        assert!(watcher.update(true, &conns).is_some());
+        assert!(watcher.update(true, &conns).is_none());
+        // A viewer joined while asleep.
+        let conns: HashSet<i32> = [1, 2].into_iter().collect();
+        assert!(watcher.update(true, &conns).is_some());
+        assert!(watcher.update(false, &conns).is_some());
+
+        let notice = parse_notice(br#"{"display":1,"asleep":true}"#).unwrap();
+        assert_eq!(
+            notice,
+            Notice {
+                display: 1,
+                asleep: true
+            }
+        );
+    }
+}
@@ -0,0 +1,161 @@
+//! The files dropped on the window of a remote desktop session, sent to the folder under the
+//! remote cursor.
+//!
+//! The client sends a `Request` to a host with "support_file_drop" in its platform additions, when
+//! the peer lets it transfer files. The host answers with the folder under its cursor: the Finder
+//! window there on macOS, the desktop otherwise. The client then sends the files to this folder as
+//! usual. The remote desktop sessions take no other file actions, so the host only takes the ones
+//! of these transfers, see `Drops`.
+
+use crate::plugin_request;
+use hbb_common::{
+    fs,
+    message_proto::{file_action, FileAction, Message},
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+pub const ID: &str = "file-drop";
+pub const PLATFORM_ADDITION_KEY: &str = "support_file_drop";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: i32,
+    /// The folder to send the files to, empty on error.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub error: String,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    /// The answer of the host, with the folder under its cursor.
+    pub fn answer(&self) -> Response {
+        match target_dir() {
+            Some(path) => Response {
+                id: self.id,
+                path,
+                ..Default::default()
+            },
+            None => Response {
+                id: self.id,
+                error: "No folder under the cursor".to_owned(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Response {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+fn desktop_dir() -> Option<String> {
+    let home = fs::get_home_as_string();
+    let desktop = Path::new(&home).join("Desktop");
+    let dir = if desktop.is_dir() {
+        desktop
+    } else {
+        PathBuf::from(home)
+    };
+    dir.is_dir().then(|| dir.to_string_lossy().to_string())
+}
+
+fn target_dir() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    if let Some(dir) = crate::platform::macos::folder_under_cursor() {
+        return Some(dir);
+    }
+    desktop_dir()
+}
+
+/// The folders given to the drops of a connection and the transfers to them.
+#[derive(Debug, Default)]
+pub struct Drops {
+    dirs: HashSet<PathBuf>,
+    jobs: HashSet<i32>,
+}
+
+impl Drops {
+    pub fn add_dir(&mut self, dir: &str) {
+        self.dirs.insert(PathBuf::from(dir));
+    }
+
+    /// Whether the action belongs to the transfer of a drop: a file received right in one of the
+    /// folders, or a later action of the same job.
+    pub fn allow(&mut self, fa: &FileAction) -> bool {
+        match &fa.union {
+            Some(file_action::Union::Receive(r)) => {
+                let allowed = Path::new(&r.path)
+                    .parent()
+                    .map(|dir| self.dirs.contains(dir))
+                    .unwrap_or(false);
+                if allowed {
+                    self.jobs.insert(r.id);
+                }
+                allowed
+            }
+            Some(file_action::Union::SendConfirm(c)) => self.jobs.contains(&c.id),
+            Some(file_action::Union::Cancel(c)) => self.jobs.remove(&c.id),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{FileTransferCancel, FileTransferReceiveRequest};
+
+    #[test]
+    fn file_drop() {
+        let receive = |id, path: &str| {
+            let mut fa = FileAction::new();
+            fa.set_receive(FileTransferReceiveRequest {
+                id,
+                path: path.to_owned(),
+                ..Default::default()
+            });
+            fa
+        };
+        let mut drops = Drops::default();
+        assert!(!drops.allow(&receive(1, "/Users/a/Desktop/a.txt")));
+        drops.add_dir("/Users/a/Desktop");
+        assert!(drops.allow(&receive(1, "/Users/a/Desktop/a.txt")));
+        assert!(!drops.allow(&receive(2, "/Users/a/Desktop/../b.txt")));
+        assert!(!drops.allow(&receive(3, "/Users/a/Documents/c.txt")));
+
+        let mut cancel = FileAction::new();
+        cancel.set_cancel(FileTransferCancel {
+            id: 1,
+            ..Default::default()
+        });
+        assert!(drops.allow(&cancel));
+        assert!(!drops.allow(&cancel));
+
+        let res = Response::parse(br#"{"id":7,"path":"/tmp"}"#).unwrap();
+        assert_eq!(res.error, "");
+    }
+}
@@ -0,0 +1,195 @@
+//! The files deleted from the client go to the trash of the host, so the client can undo it.
+//!
+//! The hosts with "support_file_trash" in their platform additions move the files and the folders
+//! removed by `FileRemoveFile` and `FileRemoveDir` to the trash, unless the client sent a
+//! `Request::Mode` with `permanent`, for the following removals of the connection. Each item moved
+//! to the trash is reported with a `Response::Trashed` before the removal is done, and a
+//! `Request::Restore` with them moves them back, the folders first. A connection only restores the
+//! items it moved to the trash itself, see `TRASHED`.
+
+use crate::plugin_request;
+use hbb_common::{bail, message_proto::Message, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+pub const ID: &str = "file-trash";
+pub const PLATFORM_ADDITION_KEY: &str = "support_file_trash";
+
+lazy_static::lazy_static! {
+    // The items each connection moved to the trash and did not restore yet, by connection id.
+    static ref TRASHED: Mutex<HashMap<i32, Vec<Trashed>>> = Default::default();
+}
+
+/// A file or folder in the trash and where it was.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Trashed {
+    pub path: String,
+    pub trash_path: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Request {
+    Mode { permanent: bool },
+    Restore { id: i32, items: Vec<Trashed> },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Response {
+    /// An item of the removal job `id` is in the trash.
+    Trashed { id: i32, item: Trashed },
+    Restored {
+        id: i32,
+        #[serde(default)]
+        error: String,
+    },
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+impl Response {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// Move the file or folder to the trash of its volume, for the connection.
+pub fn trash(conn_id: i32, path: &str) -> ResultType<Trashed> {
+    if !Path::new(path).exists() {
+        bail!("{} does not exist", path);
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let item = Trashed {
+            path: path.to_owned(),
+            trash_path: crate::platform::macos::trash_item(path)?,
+        };
+        TRASHED
+            .lock()
+            .unwrap()
+            .entry(conn_id)
+            .or_default()
+            .push(item.clone());
+        return Ok(item);
+    }
+    #[cfg(not(target_os = "macos"))]
+    {
+        let _ = conn_id;
+        bail!("The trash is not supported on this platform");
+    }
+}
+
+/// Forget the items of a connection which ended, they stay in the trash.
+pub fn remove(conn_id: i32) {
+    TRASHED.lock().unwrap().remove(&conn_id);
+}
+
+/// Whether the folder has no file left in it, only empty folders.
+pub fn is_empty_tree(dir: &Path) -> bool {
+    match std::fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|e| e.ok()).all(|e| {
+            let path = e.path();
+            // `symlink_metadata`, not to walk the folders the links point to.
+            match std::fs::symlink_metadata(&path) {
+                Ok(m) if m.is_dir() => is_empty_tree(&path),
+                _ => false,
+            }
+        }),
+        Err(_) => false,
+    }
+}
+
+/// Move the items of the connection back from the trash, none if one of them is not an item it
+/// trashed. The restored items are forgotten.
+pub fn restore(conn_id: i32, items: &[Trashed]) -> ResultType<()> {
+    let mut trashed = TRASHED.lock().unwrap();
+    let own = trashed.entry(conn_id).or_default();
+    if let Some(item) = items.iter().find(|i| !own.contains(i)) {
+        bail!(
+            "{} was not moved to the trash by this connection",
+            item.path
+        );
+    }
+    let res = restore_items(items);
+    // The items moved back before an error are not in the trash any more either.
+    own.retain(|i| !items.contains(i) || Path::new(&i.trash_path).symlink_metadata().is_ok());
+    res
+}
+
+// The last trashed first, so the folders are back before the files in them. Nothing is
+// overwritten.
+fn restore_items(items: &[Trashed]) -> ResultType<()> {
+    for item in items.iter().rev() {
+        let path = Path::new(&item.path);
+        if path.symlink_metadata().is_ok() {
+            if path.is_dir() && is_empty_tree(path) {
+                // Put back before, or recreated by the restore of the files in it.
+                std::fs::remove_dir_all(path)?;
+            } else {
+                bail!("{} already exists", item.path);
+            }
+        }
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::rename(&item.trash_path, path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_trash() {
+        let dir = std::env::temp_dir().join(format!("file_trash_{}", std::process::id()));
+        let trash = dir.join("trash");
+        std::fs::create_dir_all(dir.join("a/b")).unwrap();
+        std::fs::create_dir_all(&trash).unwrap();
+        assert!(is_empty_tree(&dir.join("a")));
+        std::fs::write(dir.join("a/b/c.txt"), b"c").unwrap();
+        assert!(!is_empty_tree(&dir.join("a")));
+
+        // A folder removed file by file: the file, then the emptied folder.
+        let s = |p: &Path| p.to_string_lossy().to_string();
+        let items = vec![
+            Trashed {
+                path: s(&dir.join("a/b/c.txt")),
+                trash_path: s(&trash.join("c.txt")),
+            },
+            Trashed {
+                path: s(&dir.join("a")),
+                trash_path: s(&trash.join("a")),
+            },
+        ];
+        std::fs::rename(dir.join("a/b/c.txt"), trash.join("c.txt")).unwrap();
+        std::fs::rename(dir.join("a"), trash.join("a")).unwrap();
+        // Only the items the connection trashed.
+        assert!(restore(1, &items).is_err());
+        TRASHED.lock().unwrap().insert(1, items.clone());
+        assert!(restore(2, &items).is_err());
+        restore(1, &items).unwrap();
+        assert_eq!(std::fs::read(dir.join("a/b/c.txt")).unwrap(), b"c");
+        assert!(restore(1, &items[..1]).is_err());
+        assert!(restore_items(&items[..1]).is_err());
+        remove(1);
+
+        let req = Request::parse(br#"{"action":"mode","permanent":true}"#).unwrap();
+        assert_eq!(req, Request::Mode { permanent: true });
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -139,6 +139,8 @@ struct DisplaySessionInfo {
 struct VideoRenderer {
     is_support_multi_ui_session: bool,
     map_display_sessions: Arc<RwLock<HashMap<usize, DisplaySessionInfo>>>,
+    // Scaling and rotation of the frames before they are copied to the textures.
+    map_display_transforms: Arc<RwLock<HashMap<usize, frame_transform::FrameTransformer>>>,
     on_rgba_func: Option<Symbol<'static, FlutterRgbaRendererPluginOnRgba>>,
     #[cfg(feature = "vram")]
     on_texture_func: Option<Symbol<'static, FlutterGpuTextureRendererPluginCApiSetTexture>>,
@@ -188,6 +190,7 @@ impl Default for VideoRenderer {
 
         Self {
             map_display_sessions: Default::default(),
+            map_display_transforms: Default::default(),
             is_support_multi_ui_session: false,
             on_rgba_func,
             #[cfg(feature = "vram")]
@@ -300,6 +303,20 @@ impl VideoRenderer {
         }
     }
 
+    fn set_transform(&self, display: usize, transform: Option<frame_transform::FrameTransform>) {
+        let mut lock = self.map_display_transforms.write().unwrap();
+        match transform {
+            Some(t) => {
+                if lock.get(&display).map(|x| *x.transform() != t).unwrap_or(true) {
+                    lock.insert(display, frame_transform::FrameTransformer::new(t));
+                }
+            }
+            None => {
+                lock.remove(&display);
+            }
+        }
+    }
+
     pub fn on_rgba(&self, display: usize, rgba: &scrap::ImageRgb) -> bool {
         let mut write_lock = self.map_display_sessions.write().unwrap();
         let opt_info = if !self.is_support_multi_ui_session {
@@ -328,6 +345,20 @@ impl VideoRenderer {
                 return false;
             }
         }
+        let mut transforms_lock = self.map_display_transforms.write().unwrap();
+        let transformer = if !self.is_support_multi_ui_session {
+            transforms_lock.values_mut().next()
+        } else {
+            transforms_lock.get_mut(&display)
+        };
+        let rgba = match transformer.map(|t| t.apply(rgba)) {
+            Some(Ok(transformed)) => transformed,
+            Some(Err(e)) => {
+                log::error!("Failed to transform frame of display {}, {}", display, e);
+                rgba
+            }
+            None => rgba,
+        };
         if let Some(func) = &self.on_rgba_func {
             unsafe {
                 func(
@@ -486,7 +517,10 @@ impl FlutterHandler {
         serde_json::ser::to_string(&msg_vec).unwrap_or("".to_owned())
     }
 
-    #[cfg(feature = "plugin_framework")]
+    pub(crate) fn session_ids(&self) -> Vec<SessionID> {
+        self.session_handlers.read().unwrap().keys().cloned().collect()
+    }
+
     pub(crate) fn add_session_hook(&self, key: String, hook: SessionHook) -> bool {
         let mut hooks = self.hooks.write().unwrap();
         if hooks.contains_key(&key) {
@@ -581,6 +615,10 @@ impl InvokeUiSession for FlutterHandler {
                     &status.codec_format.map_or(NULL, |it| it.to_string()),
                 ),
                 ("chroma", &status.chroma.map_or(NULL, |it| it.to_string())),
+                (
+                    "decode_dropped",
+                    &status.decode_dropped.map_or(NULL, |it| it.to_string()),
+                ),
             ],
             &[],
         );
@@ -947,6 +985,155 @@ impl InvokeUiSession for FlutterHandler {
         }
     }
 
+    fn update_audio_devices(&self, res: crate::audio_devices::Response) {
+        self.push_event(
+            "audio_devices",
+            &[
+                ("inputs", json!(res.inputs)),
+                ("outputs", json!(res.outputs)),
+                ("selected", json!(res.selected)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn update_keyboard_layouts(&self, res: crate::keyboard_layout::Response) {
+        self.push_event(
+            "keyboard_layouts",
+            &[
+                ("layouts", json!(res.layouts)),
+                ("current", json!(res.current)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn update_monitor_modes(&self, res: crate::monitor_modes::Response) {
+        self.push_event(
+            "monitor_modes",
+            &[
+                ("modes", json!(res.modes)),
+                ("set", json!(res.set)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn update_config_edit(&self, res: crate::config_edit::Response) {
+        self.push_event(
+            "config_edit",
+            &[
+                ("files", json!(res.files)),
+                ("path", json!(res.path)),
+                ("content", json!(res.content)),
+                ("written", json!(res.written)),
+                ("diff", json!(res.diff)),
+                ("backup", json!(res.backup)),
+                ("env", json!(res.env)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn file_drop(&self, res: crate::file_drop::Response) {
+        self.push_event(
+            "file_drop",
+            &[
+                ("id", json!(res.id)),
+                ("path", json!(res.path)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn privacy_displays(&self, res: crate::privacy_displays::Response) {
+        self.push_event(
+            "privacy_displays",
+            &[
+                ("displays", json!(res.displays)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
+    fn file_trash(&self, res: crate::file_trash::Response) {
+        match res {
+            crate::file_trash::Response::Trashed { id, item } => self.push_event(
+                "file_trash",
+                &[
+                    ("action", json!("trashed")),
+                    ("id", json!(id)),
+                    ("path", json!(item.path)),
+                    ("trash_path", json!(item.trash_path)),
+                ],
+                &[],
+            ),
+            crate::file_trash::Response::Restored { id, error } => self.push_event(
+                "file_trash",
+                &[
+                    ("action", json!("restored")),
+                    ("id", json!(id)),
+                    ("error", json!(error)),
+                ],
+                &[],
+            ),
+        }
+    }
+
+    fn transfer_journal(&self, notice: crate::transfer_journal::Notice) {
+        self.push_event(
+            "transfer_journal",
+            &[("resume", json!(notice.resume)), ("jobs", json!(notice.jobs))],
+            &[],
+        );
+    }
+
+    fn update_display_power(&self, notice: crate::display_power::Notice) {
+        self.push_event(
+            "display_power",
+            &[
+                ("display", json!(notice.display)),
+                ("asleep", json!(notice.asleep)),
+            ],
+            &[],
+        );
+    }
+
+    fn update_clipboard_progress(&self, percent: u8) {
+        self.push_event("clipboard_progress", &[("percent", json!(percent))], &[]);
+    }
+
+    fn update_file_paste_progress(&self, transferred: u64, total: u64, canceled: bool, failed: bool) {
+        self.push_event(
+            "file_paste_progress",
+            &[
+                ("transferred", json!(transferred)),
+                ("total", json!(total)),
+                ("canceled", json!(canceled)),
+                ("failed", json!(failed)),
+            ],
+            &[],
+        );
+    }
+
+    fn update_camera_controls(&self, res: crate::camera_controls::Response) {
+        self.push_event(
+            "camera_controls",
+            &[
+                ("display", json!(res.display)),
+                ("controls", json!(res.controls)),
+                ("error", json!(res.error)),
+            ],
+            &[],
+        );
+    }
+
     fn update_record_status(&self, start: bool) {
         self.push_event("record_status", &[("start", &start.to_string())], &[]);
     }
@@ -1206,11 +1393,17 @@ pub fn session_add(
         force_relay,
         get_adapter_luid(),
         shared_password,
-        conn_token,
+        conn_token.clone(),
     );
 
     let session = Arc::new(session.clone());
     sessions::insert_session(session_id.to_owned(), conn_type, session.clone());
+    if conn_type == ConnType::DEFAULT_CONN {
+        crate::tiling::add(&session_id);
+    }
+    if let Some(token) = conn_token {
+        crate::workspace::join(session_id, id, &token);
+    }
 
     Ok(session)
 }
@@ -1304,6 +1497,13 @@ pub fn send_clipboard_msg(msg: Message, _is_file: bool) {
             continue;
         }
         if s.is_text_clipboard_required() {
+            let msg = if s.get_toggle_option(
+                crate::clipboard::rich_text::OPTION_PLAIN_TEXT_ONLY.to_owned(),
+            ) {
+                crate::clipboard::rich_text::plain_text_msg(&msg)
+            } else {
+                msg.clone()
+            };
             // Check if the client supports multi clipboards
             if let Some(message::Union::MultiClipboards(multi_clipboards)) = &msg.union {
                 let version = s.ui_handler.peer_info.read().unwrap().version.clone();
@@ -1378,9 +1578,56 @@ pub mod connection_manager {
             self.push_event("update_voice_call_state", &[("client", &client_json)]);
         }
 
+        fn update_client_record_status(&self, client: &crate::ui_cm_interface::Client) {
+            let client_json = serde_json::to_string(&client).unwrap_or("".into());
+            self.push_event("update_client_record_status", &[("client", &client_json)]);
+        }
+
+        fn update_client_tunnels(&self, client: &crate::ui_cm_interface::Client) {
+            let client_json = serde_json::to_string(&client).unwrap_or("".into());
+            self.push_event("update_client_tunnels", &[("client", &client_json)]);
+        }
+
+        fn permission_request(&self, id: i32, name: String) {
+            self.push_event(
+                "cm_permission_request",
+                &[("id", &id.to_string()), ("name", &name)],
+            );
+        }
+
+        fn interrupted_transfer_jobs(&self, id: i32, jobs: &[crate::transfer_journal::Job]) {
+            self.push_event(
+                "cm_interrupted_transfer_jobs",
+                &[
+                    ("id", &id.to_string()),
+                    ("jobs", &serde_json::to_string(jobs).unwrap_or_default()),
+                ],
+            );
+        }
+
         fn file_transfer_log(&self, action: &str, log: &str) {
             self.push_event("cm_file_transfer_log", &[(action, log)]);
         }
+
+        fn file_paste_progress(
+            &self,
+            id: i32,
+            transferred: u64,
+            total: u64,
+            canceled: bool,
+            failed: bool,
+        ) {
+            self.push_event(
+                "cm_file_paste_progress",
+                &[
+                    ("id", &id.to_string()),
+                    ("transferred", &transferred.to_string()),
+                    ("total", &total.to_string()),
+                    ("canceled", &canceled.to_string()),
+                    ("failed", &failed.to_string()),
+                ],
+            );
+        }
     }
 
     impl FlutterHandler {
@@ -1574,6 +1821,29 @@ pub fn session_register_pixelbuffer_texture(session_id: SessionID, display: usiz
     }
 }
 
+/// Scale and rotate the frames of `display` in the core before they are copied to the texture.
+pub fn session_set_frame_transform(
+    session_id: SessionID,
+    display: usize,
+    scale: f64,
+    rotation: i32,
+) -> ResultType<()> {
+    let transform = frame_transform::FrameTransform::new(scale, rotation)?;
+    for s in sessions::get_sessions() {
+        if let Some(h) = s
+            .ui_handler
+            .session_handlers
+            .read()
+            .unwrap()
+            .get(&session_id)
+        {
+            h.renderer.set_transform(display, transform);
+            break;
+        }
+    }
+    Ok(())
+}
+
 #[inline]
 pub fn session_register_gpu_texture(_session_id: SessionID, _display: usize, _output_ptr: usize) {
     #[cfg(feature = "vram")]
@@ -1727,6 +1997,13 @@ fn session_send_touch_event(
 ) {
     match v.get("t").and_then(|t| t.as_str()) {
         Some("scale") => session_send_touch_scale(session_id, v, alt, ctrl, shift, command),
+        Some("rotate") => {
+            if let Some(rotation) = v.get("v").and_then(|s| s.as_i64()) {
+                if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+                    session.send_touch_rotate(rotation as _);
+                }
+            }
+        }
         Some(pan_event) => {
             session_send_touch_pan(session_id, v, pan_event, alt, ctrl, shift, command)
         }
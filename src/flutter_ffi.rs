@@ -55,6 +55,16 @@ pub fn start_global_event_stream(s: StreamSink<String>, app_type: String) -> Res
 pub fn stop_global_event_stream(app_type: String) {
     super::flutter::stop_global_event_stream(app_type)
 }
+
+/// Semantic session events for screen readers, see [`crate::ui_interface::A11yEvent`].
+pub fn start_accessibility_event_stream(s: StreamSink<String>, app_type: String) -> ResultType<()> {
+    ui_interface::start_a11y_event_stream(app_type, Box::new(move |e| s.add(e.to_owned())));
+    Ok(())
+}
+
+pub fn stop_accessibility_event_stream(app_type: String) {
+    ui_interface::stop_a11y_event_stream(&app_type)
+}
 pub enum EventToUI {
     Event(String),
     Rgba(usize),
@@ -222,6 +232,8 @@ pub fn will_session_close_close_session(session_id: SessionID) -> SyncReturn<boo
 }
 
 pub fn session_close(session_id: SessionID) {
+    crate::workspace::leave(&session_id);
+    crate::tiling::remove(&session_id);
     if let Some(session) = sessions::remove_session_by_session_id(&session_id) {
         session.close_event_stream(session_id);
         session.close();
@@ -234,6 +246,12 @@ pub fn session_refresh(session_id: SessionID, display: usize) {
     }
 }
 
+pub fn session_request_key_frame(session_id: SessionID, display: i32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_key_frame(display);
+    }
+}
+
 pub fn session_take_screenshot(session_id: SessionID, display: usize) {
     if let Some(s) = sessions::get_session_by_session_id(&session_id) {
         s.take_screenshot(display as _, session_id.to_string());
@@ -513,6 +531,34 @@ pub fn session_ctrl_alt_del(session_id: SessionID) {
     }
 }
 
+pub fn session_talk_back(session_id: SessionID, on: bool) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.talk_back(on);
+    }
+}
+
+pub fn session_get_toolbar_actions(session_id: SessionID) -> SyncReturn<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        SyncReturn(session.get_toolbar_actions())
+    } else {
+        SyncReturn("".to_owned())
+    }
+}
+
+pub fn session_set_toolbar_actions(session_id: SessionID, actions: String) -> String {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_toolbar_actions(actions)
+    } else {
+        "".to_owned()
+    }
+}
+
+pub fn session_run_toolbar_action(session_id: SessionID, id: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.run_toolbar_action(&id);
+    }
+}
+
 pub fn session_switch_display(is_desktop: bool, session_id: SessionID, value: Vec<i32>) {
     sessions::session_switch_display(is_desktop, session_id, value);
 }
@@ -601,6 +647,12 @@ pub fn session_input_string(session_id: SessionID, value: String) {
 }
 
 // chat_client_mode
+pub fn session_cancel_file_paste(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.cancel_file_paste();
+    }
+}
+
 pub fn session_send_chat(session_id: SessionID, text: String) {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         session.send_chat(text);
@@ -686,6 +738,20 @@ pub fn session_send_files(
     }
 }
 
+pub fn session_sync_files(
+    session_id: SessionID,
+    act_id: i32,
+    path: String,
+    to: String,
+    include_hidden: bool,
+    is_remote: bool,
+    mode: String,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.sync_files(act_id, path, to, include_hidden, is_remote, &mode);
+    }
+}
+
 pub fn session_set_confirm_override_file(
     session_id: SessionID,
     act_id: i32,
@@ -861,6 +927,235 @@ pub fn session_change_resolution(session_id: SessionID, display: i32, width: i32
     }
 }
 
+pub fn session_get_camera_controls(session_id: SessionID, display: usize) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.get_camera_controls(display);
+    }
+}
+
+pub fn session_set_camera_control(
+    session_id: SessionID,
+    display: usize,
+    control: String,
+    auto: bool,
+    value: Option<f64>,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_camera_control(display, &control, auto, value);
+    }
+}
+
+/// Create a workspace with the desktop session, returns its id, empty on failure.
+pub fn session_create_workspace(session_id: SessionID) -> SyncReturn<String> {
+    match crate::workspace::create(&session_id) {
+        Ok(id) => SyncReturn(id),
+        Err(e) => {
+            log::error!("Failed to create workspace: {}", e);
+            SyncReturn("".to_owned())
+        }
+    }
+}
+
+pub fn main_get_workspaces() -> SyncReturn<String> {
+    SyncReturn(crate::workspace::list())
+}
+
+/// Json list of the desktop sessions, with their displays and thumbnails, for a wall view.
+pub fn main_get_session_tiles() -> SyncReturn<String> {
+    SyncReturn(crate::tiling::list())
+}
+
+/// Json list of the recorded sessions of the peer, with the time they started.
+pub fn main_get_qos_sessions(id: String) -> SyncReturn<String> {
+    SyncReturn(serde_json::to_string(&crate::qos_history::sessions(&id)).unwrap_or_default())
+}
+
+/// Json list of the connection quality samples, one per second, of a recorded session.
+pub fn main_get_qos_history(id: String, start: i64) -> String {
+    match crate::qos_history::load(&id, start) {
+        Ok(samples) => serde_json::to_string(&samples).unwrap_or_default(),
+        Err(e) => {
+            log::error!("Failed to load the qos history of {}: {}", id, e);
+            "[]".to_owned()
+        }
+    }
+}
+
+pub fn session_set_tile(session_id: SessionID, width: u32, height: u32, visible: bool) {
+    crate::tiling::set_tile(&session_id, width, height, visible);
+}
+
+pub fn main_close_workspace(id: String) {
+    crate::workspace::close(&id);
+}
+
+pub fn session_send_stylus(session_id: SessionID, evt: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_stylus(&evt);
+    }
+}
+
+pub fn session_get_audio_devices(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.get_audio_devices();
+    }
+}
+
+pub fn session_select_audio_device(session_id: SessionID, device: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.select_audio_device(device);
+    }
+}
+
+pub fn session_get_keyboard_layouts(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.get_keyboard_layouts();
+    }
+}
+
+pub fn session_switch_keyboard_layout(session_id: SessionID, layout: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.switch_keyboard_layout(layout);
+    }
+}
+
+pub fn session_get_monitor_modes(session_id: SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.monitor_modes(None);
+    }
+}
+
+pub fn session_set_monitor_modes(session_id: SessionID, modes: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.monitor_modes(Some(modes));
+    }
+}
+
+pub fn session_config_edit(session_id: SessionID, request: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.config_edit(request);
+    }
+}
+
+pub fn main_config_edit_diff(old_content: String, new_content: String) -> SyncReturn<String> {
+    SyncReturn(crate::config_edit::diff(&old_content, &new_content))
+}
+
+pub fn session_wake_display(session_id: SessionID, display: usize) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.wake_display(display);
+    }
+}
+
+pub fn session_send_annotation(session_id: SessionID, annotation: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_annotation(&annotation);
+    }
+}
+
+pub fn session_send_special_key(session_id: SessionID, key: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_special_key(&key);
+    }
+}
+
+pub fn session_set_mouse_curve(session_id: SessionID, curve: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_mouse_curve(&curve);
+    }
+}
+
+pub fn session_set_scroll_transform(session_id: SessionID, transform: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_scroll_transform(&transform);
+    }
+}
+
+pub fn session_set_wake_policy(session_id: SessionID, policy: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_wake_policy(&policy);
+    }
+}
+
+pub fn session_set_window_visible(session_id: SessionID, visible: bool) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_window_visible(visible);
+    }
+}
+
+/// Json list of the features unavailable with the peer, why and the version to update it to.
+pub fn session_get_unavailable_features(session_id: SessionID) -> SyncReturn<String> {
+    SyncReturn(
+        sessions::get_session_by_session_id(&session_id)
+            .map(|s| s.get_unavailable_features())
+            .unwrap_or_else(|| "[]".to_owned()),
+    )
+}
+
+pub fn session_notify_unavailable_feature(session_id: SessionID, name: String) -> SyncReturn<bool> {
+    SyncReturn(
+        sessions::get_session_by_session_id(&session_id)
+            .map(|s| s.notify_unavailable_feature(&name))
+            .unwrap_or(false),
+    )
+}
+
+pub fn session_set_privacy_displays(session_id: SessionID, displays: Vec<i32>) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_privacy_displays(displays.into_iter().map(|d| d as usize).collect());
+    }
+}
+
+pub fn session_request_file_drop(session_id: SessionID, id: i32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_file_drop(id);
+    }
+}
+
+pub fn session_set_file_trash_permanent(session_id: SessionID, permanent: bool) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_file_trash_permanent(permanent);
+    }
+}
+
+pub fn session_restore_trashed(session_id: SessionID, id: i32, items: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.restore_trashed(id, &items);
+    }
+}
+
+pub fn session_set_transfer_throttle(session_id: SessionID, settings: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.set_transfer_throttle(&settings);
+    }
+}
+
+pub fn session_get_clipboard_history(session_id: SessionID) -> SyncReturn<String> {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        SyncReturn(session.get_clipboard_history())
+    } else {
+        SyncReturn("".to_owned())
+    }
+}
+
+pub fn session_send_clipboard_history(session_id: SessionID, id: u64) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_clipboard_history(id);
+    }
+}
+
+pub fn session_request_permission(session_id: SessionID, permission: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.request_permission(&permission);
+    }
+}
+
+pub fn session_send_auth_provider_answer(session_id: SessionID, answer: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.send_auth_provider_answer(&answer);
+    }
+}
+
 pub fn session_set_size(session_id: SessionID, display: usize, width: usize, height: usize) {
     super::flutter::session_set_size(session_id, display, width, height)
 }
@@ -1021,6 +1316,81 @@ pub fn main_http_request(url: String, method: String, body: Option<String>, head
     http_request(url, method, body, header)
 }
 
+pub fn main_send_peer_message(id: String, text: String) -> String {
+    send_peer_message(id, text)
+}
+
+pub fn main_get_peer_messages() -> String {
+    get_peer_messages()
+}
+
+pub fn main_mark_peer_messages_read(guid: String) {
+    crate::hbbs_http::peer_message::mark_read(&guid)
+}
+
+pub fn main_clear_peer_messages() {
+    crate::hbbs_http::peer_message::clear_inbox()
+}
+
+pub fn main_add_host_wait(id: String) -> String {
+    add_host_wait(id)
+}
+
+pub fn main_take_host_free_peers() -> String {
+    take_host_free_peers()
+}
+
+pub fn main_get_session_stats() -> String {
+    get_session_stats()
+}
+
+pub fn main_get_onboarding() -> String {
+    get_onboarding()
+}
+
+pub fn main_onboarding_action(action: String, value: String) -> String {
+    onboarding_action(action, value)
+}
+
+pub fn main_set_host_avatar(path: String) -> String {
+    match crate::peer_identity::set_avatar(&path) {
+        Ok(()) => "".to_owned(),
+        Err(e) => e.to_string(),
+    }
+}
+
+pub fn main_get_audio_devices() -> String {
+    get_audio_devices()
+}
+
+pub fn main_list_options(query: String) -> SyncReturn<String> {
+    SyncReturn(list_options(query))
+}
+
+pub fn main_get_hotkey_passthrough() -> SyncReturn<String> {
+    SyncReturn(get_hotkey_passthrough())
+}
+
+pub fn main_set_hotkey_passthrough(chord: String, target: String) -> String {
+    set_hotkey_passthrough(chord, target)
+}
+
+pub fn main_set_availability(status: String, note: String) -> String {
+    set_availability(status, note)
+}
+
+pub fn main_set_privacy_regions(regions: String) -> String {
+    set_privacy_regions(regions)
+}
+
+pub fn main_get_windows() -> String {
+    get_windows()
+}
+
+pub fn main_set_shared_window(id: String) -> String {
+    set_shared_window(id)
+}
+
 pub fn main_get_local_option(key: String) -> SyncReturn<String> {
     SyncReturn(get_local_option(key))
 }
@@ -1455,6 +1825,29 @@ pub fn session_remove_port_forward(session_id: SessionID, local_port: i32) {
     }
 }
 
+pub fn session_add_mux_forward(
+    session_id: SessionID,
+    local_port: i32,
+    remote_host: String,
+    remote_port: i32,
+) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.add_mux_forward(local_port, remote_host, remote_port);
+    }
+}
+
+pub fn session_remove_mux_forward(session_id: SessionID, local_port: i32) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.remove_mux_forward(local_port);
+    }
+}
+
+pub fn session_input_macro(session_id: SessionID, action: String, name: String) {
+    if let Some(session) = sessions::get_session_by_session_id(&session_id) {
+        session.input_macro(&action, name);
+    }
+}
+
 pub fn session_new_rdp(session_id: SessionID) {
     if let Some(session) = sessions::get_session_by_session_id(&session_id) {
         session.new_rdp();
@@ -1749,6 +2142,7 @@ pub fn session_send_mouse(session_id: SessionID, msg: String) {
                 "wheel" => MOUSE_TYPE_WHEEL,
                 "trackpad" => MOUSE_TYPE_TRACKPAD,
                 "move_relative" => MOUSE_TYPE_MOVE_RELATIVE,
+                "wheel_precise" => MOUSE_TYPE_WHEEL_PRECISE,
                 _ => 0,
             };
         }
@@ -1942,6 +2336,22 @@ pub fn cm_switch_permission(conn_id: i32, name: String, enabled: bool) {
     crate::ui_cm_interface::switch_permission(conn_id, name, enabled)
 }
 
+pub fn cm_answer_permission_request(conn_id: i32, name: String, granted: bool) {
+    crate::ui_cm_interface::answer_permission_request(conn_id, name, granted)
+}
+
+pub fn cm_set_view_only(conn_id: i32, view_only: bool) {
+    crate::ui_cm_interface::set_view_only(conn_id, view_only)
+}
+
+pub fn cm_resume_transfer_jobs(conn_id: i32, resume: bool) {
+    crate::ui_cm_interface::resume_transfer_jobs(conn_id, resume)
+}
+
+pub fn cm_cancel_file_paste(conn_id: i32) {
+    crate::ui_cm_interface::cancel_file_paste(conn_id)
+}
+
 pub fn cm_can_elevate() -> SyncReturn<bool> {
     SyncReturn(crate::ui_cm_interface::can_elevate())
 }
@@ -1972,6 +2382,18 @@ pub fn translate(name: String, locale: String) -> SyncReturn<String> {
     SyncReturn(crate::client::translate_locale(name, &locale))
 }
 
+pub fn main_format_bytes(size: f64) -> SyncReturn<String> {
+    SyncReturn(crate::locale_format::bytes(size))
+}
+
+pub fn main_format_duration(seconds: u64) -> SyncReturn<String> {
+    SyncReturn(crate::locale_format::duration(seconds))
+}
+
+pub fn main_format_date_time(ms: i64) -> SyncReturn<String> {
+    SyncReturn(crate::locale_format::date_time(ms))
+}
+
 pub fn session_get_rgba_size(session_id: SessionID, display: usize) -> SyncReturn<usize> {
     SyncReturn(super::flutter::session_get_rgba_size(session_id, display))
 }
@@ -1990,6 +2412,21 @@ pub fn session_register_pixelbuffer_texture(
     ))
 }
 
+/// Returns the error, or empty if the transform is set.
+pub fn session_set_frame_transform(
+    session_id: SessionID,
+    display: usize,
+    scale: f64,
+    rotation: i32,
+) -> SyncReturn<String> {
+    SyncReturn(
+        match super::flutter::session_set_frame_transform(session_id, display, scale, rotation) {
+            Ok(()) => "".to_owned(),
+            Err(e) => e.to_string(),
+        },
+    )
+}
+
 pub fn session_register_gpu_texture(
     session_id: SessionID,
     display: usize,
@@ -2492,6 +2929,10 @@ pub fn main_get_common(key: String) -> String {
         .to_string();
     } else if key == "has-gnome-shortcuts-inhibitor-permission" {
         return false.to_string();
+    } else if key == "update-manifest-download-url" {
+        return crate::update_manifest::pending()
+            .map(|r| r.url)
+            .unwrap_or_default();
     } else {
         if key.starts_with("download-data-") {
             let id = key.replace("download-data-", "");
@@ -2605,6 +3046,8 @@ pub fn session_get_common(
     if let Some(s) = sessions::get_session_by_session_id(&session_id) {
         let v = if key == "is_screenshot_supported" {
             s.is_screenshot_supported().to_string()
+        } else if key == "is_talking_back" {
+            s.is_talking_back().to_string()
         } else {
             "".to_owned()
         };
@@ -0,0 +1,164 @@
+//! The folder sync mode of the file transfers, only the files changed are sent again.
+//!
+//! A job sent in this mode skips, without asking, the files whose receiver already has the same
+//! version. With `Mode::Meta`, a file is the same if its size and its modification time are, as the
+//! digests of the transfer already tell. With `Mode::Hash`, the client sends the sha256 of its file
+//! in a `Request`, and a host with "support_folder_sync" in its platform additions answers whether
+//! its file has the same one. The files missing on the receiver are always sent.
+//!
+//! The host hashes the file of the job, or for an upload the regular file of the request within
+//! the sandbox of the file transfers, and at most `MAX_HASHING` files at once. The other files are
+//! answered as not the same, so they are sent.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io,
+    path::Path,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+pub const ID: &str = "folder-sync";
+pub const PLATFORM_ADDITION_KEY: &str = "support_folder_sync";
+const MAX_HASHING: usize = 4;
+
+// The files hashed now for the requests of all the connections.
+static HASHING: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Mode {
+    Meta,
+    Hash,
+}
+
+impl Mode {
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode {
+            "meta" => Some(Self::Meta),
+            "hash" => Some(Self::Hash),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Skip,
+    Send,
+    // Ask the host whether the hashes match.
+    Compare,
+}
+
+/// What to do with a file, `exists` if the receiver has it and `is_identical` if its size and its
+/// modification time are the ones of the sender.
+pub fn action(mode: Mode, exists: bool, is_identical: bool) -> Action {
+    match (mode, exists, is_identical) {
+        (_, false, _) => Action::Send,
+        (Mode::Meta, true, true) => Action::Skip,
+        (Mode::Meta, true, false) => Action::Send,
+        (Mode::Hash, true, _) => Action::Compare,
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: i32,
+    pub file_num: i32,
+    /// The file on the host.
+    pub path: String,
+    /// The sha256 of the file of the client, in hex.
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: i32,
+    pub file_num: i32,
+    pub same: bool,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    /// The answer of the host for its file `path`, checked by the caller. A file which is not a
+    /// regular one or cannot be read is not the same.
+    pub fn answer(&self, path: &str) -> Response {
+        let same = if HASHING.fetch_add(1, Ordering::SeqCst) < MAX_HASHING {
+            Path::new(path).is_file()
+                && file_hash(path)
+                    .map(|hash| hash == self.hash)
+                    .unwrap_or(false)
+        } else {
+            false
+        };
+        HASHING.fetch_sub(1, Ordering::SeqCst);
+        Response {
+            id: self.id,
+            file_num: self.file_num,
+            same,
+        }
+    }
+}
+
+impl Response {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The sha256 of the file, in hex.
+pub fn file_hash(path: &str) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(hex::encode(hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn folder_sync() {
+        assert_eq!(action(Mode::Meta, false, false), Action::Send);
+        assert_eq!(action(Mode::Meta, true, true), Action::Skip);
+        assert_eq!(action(Mode::Meta, true, false), Action::Send);
+        assert_eq!(action(Mode::Hash, true, true), Action::Compare);
+        assert_eq!(action(Mode::Hash, false, true), Action::Send);
+        assert_eq!(Mode::parse("hash"), Some(Mode::Hash));
+        assert_eq!(Mode::parse(""), None);
+
+        let path = std::env::temp_dir().join(format!("folder-sync-{}", std::process::id()));
+        let path = path.to_string_lossy().to_string();
+        std::fs::write(&path, b"abc").unwrap();
+        let hash = file_hash(&path).unwrap();
+        assert_eq!(
+            hash,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let req = Request {
+            id: 1,
+            file_num: 2,
+            path: path.clone(),
+            hash,
+        };
+        assert!(req.answer(&path).same);
+        assert!(!req.answer(&std::env::temp_dir().to_string_lossy()).same);
+        std::fs::write(&path, b"abd").unwrap();
+        assert!(!req.answer(&path).same);
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,355 @@
+//! Game controllers of the client, forwarded to the host.
+//!
+//! The protocol has no message for them, so the state of a controller is sent as a plugin request.
+//! The client captures its controllers with a `Capture` when the host has `PLATFORM_ADDITION_KEY`
+//! in its platform additions, and sends the state on each change, and every `REPEAT_MS` while the
+//! right stick is off center, if the "forward-gamepad" option of the session is on.
+//!
+//! A virtual HID controller needs a system extension on macOS, so the host maps the controller
+//! to the keyboard and the mouse instead, like the common game launchers do:
+//! the left stick to WASD, the d-pad to the arrows, the right stick moves the mouse, the right
+//! and left triggers are the left and right buttons, A Space, B Escape, X E, Y R, LB Q, RB F,
+//! Back Tab, Start Return, and the stick clicks Shift and Control.
+
+use crate::plugin_request;
+use enigo::{Key, MouseButton};
+use gilrs::{Axis, Button, EventType, Gilrs};
+use hbb_common::{log, message_proto::Message, tokio::sync::mpsc};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+pub const ID: &str = "gamepad";
+pub const PLATFORM_ADDITION_KEY: &str = "support_gamepad";
+pub const OPTION_FORWARD: &str = "forward-gamepad";
+pub const REPEAT_MS: u64 = 16;
+// How often the capture checks that it is still wanted, without events.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+// The buttons, in the order of XInput.
+pub const BUTTON_DPAD_UP: u32 = 1 << 0;
+pub const BUTTON_DPAD_DOWN: u32 = 1 << 1;
+pub const BUTTON_DPAD_LEFT: u32 = 1 << 2;
+pub const BUTTON_DPAD_RIGHT: u32 = 1 << 3;
+pub const BUTTON_START: u32 = 1 << 4;
+pub const BUTTON_BACK: u32 = 1 << 5;
+pub const BUTTON_LEFT_THUMB: u32 = 1 << 6;
+pub const BUTTON_RIGHT_THUMB: u32 = 1 << 7;
+pub const BUTTON_LEFT_SHOULDER: u32 = 1 << 8;
+pub const BUTTON_RIGHT_SHOULDER: u32 = 1 << 9;
+pub const BUTTON_A: u32 = 1 << 12;
+pub const BUTTON_B: u32 = 1 << 13;
+pub const BUTTON_X: u32 = 1 << 14;
+pub const BUTTON_Y: u32 = 1 << 15;
+
+// Stick values inside it are ignored, 30% of the range.
+const DEADZONE: i32 = 9830;
+const TRIGGER_THRESHOLD: u8 = 128;
+// Pixels of a mouse move for a full stick, per state.
+const MOUSE_SPEED: i32 = 16;
+
+/// The axes are in [-32768, 32767], up and right positive, the triggers in [0, 255].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamepadState {
+    #[serde(default)]
+    pub index: u8,
+    #[serde(default)]
+    pub buttons: u32,
+    #[serde(default)]
+    pub left_x: i16,
+    #[serde(default)]
+    pub left_y: i16,
+    #[serde(default)]
+    pub right_x: i16,
+    #[serde(default)]
+    pub right_y: i16,
+    #[serde(default)]
+    pub left_trigger: u8,
+    #[serde(default)]
+    pub right_trigger: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Target {
+    Key(Key),
+    Mouse(MouseButton),
+}
+
+const BUTTONS: [(u32, Target); 14] = [
+    (BUTTON_DPAD_UP, Target::Key(Key::UpArrow)),
+    (BUTTON_DPAD_DOWN, Target::Key(Key::DownArrow)),
+    (BUTTON_DPAD_LEFT, Target::Key(Key::LeftArrow)),
+    (BUTTON_DPAD_RIGHT, Target::Key(Key::RightArrow)),
+    (BUTTON_START, Target::Key(Key::Return)),
+    (BUTTON_BACK, Target::Key(Key::Tab)),
+    (BUTTON_LEFT_THUMB, Target::Key(Key::Shift)),
+    (BUTTON_RIGHT_THUMB, Target::Key(Key::Control)),
+    (BUTTON_LEFT_SHOULDER, Target::Key(Key::Layout('q'))),
+    (BUTTON_RIGHT_SHOULDER, Target::Key(Key::Layout('f'))),
+    (BUTTON_A, Target::Key(Key::Space)),
+    (BUTTON_B, Target::Key(Key::Escape)),
+    (BUTTON_X, Target::Key(Key::Layout('e'))),
+    (BUTTON_Y, Target::Key(Key::Layout('r'))),
+];
+
+impl GamepadState {
+    // The targets held down in this state.
+    fn pressed(&self) -> Vec<Target> {
+        let mut v: Vec<Target> = BUTTONS
+            .iter()
+            .filter(|(b, _)| self.buttons & b != 0)
+            .map(|(_, t)| *t)
+            .collect();
+        let (x, y) = (self.left_x as i32, self.left_y as i32);
+        if y > DEADZONE {
+            v.push(Target::Key(Key::Layout('w')));
+        }
+        if y < -DEADZONE {
+            v.push(Target::Key(Key::Layout('s')));
+        }
+        if x < -DEADZONE {
+            v.push(Target::Key(Key::Layout('a')));
+        }
+        if x > DEADZONE {
+            v.push(Target::Key(Key::Layout('d')));
+        }
+        if self.right_trigger >= TRIGGER_THRESHOLD {
+            v.push(Target::Mouse(MouseButton::Left));
+        }
+        if self.left_trigger >= TRIGGER_THRESHOLD {
+            v.push(Target::Mouse(MouseButton::Right));
+        }
+        v
+    }
+
+    /// Nothing is pressed, the host releases all.
+    pub fn is_released(&self) -> bool {
+        self.pressed().is_empty()
+    }
+
+    pub fn is_right_stick_centered(&self) -> bool {
+        (self.right_x as i32).abs() <= DEADZONE && (self.right_y as i32).abs() <= DEADZONE
+    }
+}
+
+/// The targets to release and to press, in this order, from the `old` state to the `new` one.
+pub fn changes(old: &GamepadState, new: &GamepadState) -> Vec<(Target, bool)> {
+    let (old, new) = (old.pressed(), new.pressed());
+    let mut v: Vec<(Target, bool)> = old
+        .iter()
+        .filter(|t| !new.contains(t))
+        .map(|t| (*t, false))
+        .collect();
+    v.extend(new.iter().filter(|t| !old.contains(t)).map(|t| (*t, true)));
+    v
+}
+
+/// The relative mouse move of the right stick.
+pub fn mouse_delta(state: &GamepadState) -> (i32, i32) {
+    let f = |v: i16| {
+        let v = v as i32;
+        if v.abs() <= DEADZONE {
+            0
+        } else {
+            v * MOUSE_SPEED / i16::MAX as i32
+        }
+    };
+    // The screen y grows downwards.
+    (f(state.right_x), -f(state.right_y))
+}
+
+fn button_mask(button: Button) -> u32 {
+    match button {
+        Button::DPadUp => BUTTON_DPAD_UP,
+        Button::DPadDown => BUTTON_DPAD_DOWN,
+        Button::DPadLeft => BUTTON_DPAD_LEFT,
+        Button::DPadRight => BUTTON_DPAD_RIGHT,
+        Button::Start => BUTTON_START,
+        Button::Select => BUTTON_BACK,
+        Button::LeftThumb => BUTTON_LEFT_THUMB,
+        Button::RightThumb => BUTTON_RIGHT_THUMB,
+        Button::LeftTrigger => BUTTON_LEFT_SHOULDER,
+        Button::RightTrigger => BUTTON_RIGHT_SHOULDER,
+        Button::South => BUTTON_A,
+        Button::East => BUTTON_B,
+        Button::West => BUTTON_X,
+        Button::North => BUTTON_Y,
+        _ => 0,
+    }
+}
+
+// From the [-1, 1] of gilrs, up and right positive too.
+fn axis_value(v: f32) -> i16 {
+    (v.clamp(-1.0, 1.0) * i16::MAX as f32) as i16
+}
+
+fn trigger_value(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * u8::MAX as f32) as u8
+}
+
+fn set_buttons(state: &mut GamepadState, mask: u32, pressed: bool) {
+    if pressed {
+        state.buttons |= mask;
+    } else {
+        state.buttons &= !mask;
+    }
+}
+
+// The d-pad is a pair of axes on some controllers.
+fn set_dpad(state: &mut GamepadState, negative: u32, positive: u32, v: f32) {
+    set_buttons(state, negative, v < -0.5);
+    set_buttons(state, positive, v > 0.5);
+}
+
+/// The state of a controller after an event of gilrs.
+pub fn apply_event(state: &mut GamepadState, event: &EventType) {
+    match *event {
+        EventType::ButtonChanged(Button::LeftTrigger2, v, _) => {
+            state.left_trigger = trigger_value(v)
+        }
+        EventType::ButtonChanged(Button::RightTrigger2, v, _) => {
+            state.right_trigger = trigger_value(v)
+        }
+        EventType::ButtonPressed(button, _) => set_buttons(state, button_mask(button), true),
+        EventType::ButtonReleased(button, _) => set_buttons(state, button_mask(button), false),
+        EventType::AxisChanged(axis, v, _) => match axis {
+            Axis::LeftStickX => state.left_x = axis_value(v),
+            Axis::LeftStickY => state.left_y = axis_value(v),
+            Axis::RightStickX => state.right_x = axis_value(v),
+            Axis::RightStickY => state.right_y = axis_value(v),
+            Axis::DPadX => set_dpad(state, BUTTON_DPAD_LEFT, BUTTON_DPAD_RIGHT, v),
+            Axis::DPadY => set_dpad(state, BUTTON_DPAD_DOWN, BUTTON_DPAD_UP, v),
+            _ => {}
+        },
+        // Release all, the host does not know of the controllers.
+        EventType::Disconnected => {
+            *state = GamepadState {
+                index: state.index,
+                ..Default::default()
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The game controllers of the client, their states are captured on a thread until it is
+/// dropped.
+pub struct Capture {
+    rx: mpsc::UnboundedReceiver<GamepadState>,
+}
+
+impl Capture {
+    pub fn start() -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        std::thread::spawn(move || capture(tx));
+        Self { rx }
+    }
+
+    /// The next state to send, a change or a repeat.
+    pub async fn recv(&mut self) -> Option<GamepadState> {
+        self.rx.recv().await
+    }
+}
+
+fn capture(tx: mpsc::UnboundedSender<GamepadState>) {
+    let mut gilrs = match Gilrs::new() {
+        Ok(gilrs) => gilrs,
+        Err(e) => {
+            log::warn!("Failed to capture the game controllers: {}", e);
+            return;
+        }
+    };
+    let repeat = Duration::from_millis(REPEAT_MS);
+    let mut states: HashMap<usize, GamepadState> = HashMap::new();
+    let mut last_repeat = Instant::now();
+    while !tx.is_closed() {
+        let moving = states.values().any(|s| !s.is_right_stick_centered());
+        let timeout = if moving { repeat } else { POLL_INTERVAL };
+        if let Some(gilrs::Event { id, event, .. }) = gilrs.next_event_blocking(Some(timeout)) {
+            let index = usize::from(id);
+            let state = states.entry(index).or_insert(GamepadState {
+                index: index as _,
+                ..Default::default()
+            });
+            let old = *state;
+            apply_event(state, &event);
+            if *state != old && tx.send(*state).is_err() {
+                break;
+            }
+            if matches!(event, EventType::Disconnected) {
+                states.remove(&index);
+            }
+        }
+        // The host moves the mouse by each state, so a held stick is repeated.
+        if last_repeat.elapsed() >= repeat {
+            last_repeat = Instant::now();
+            for state in states.values().filter(|s| !s.is_right_stick_centered()) {
+                tx.send(*state).ok();
+            }
+        }
+    }
+}
+
+pub fn make_message(state: &GamepadState) -> Message {
+    plugin_request::make(ID, state)
+}
+
+pub fn parse(content: &[u8]) -> Option<GamepadState> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gamepad_changes() {
+        let idle = GamepadState::default();
+        let state = GamepadState {
+            buttons: BUTTON_A | BUTTON_DPAD_UP,
+            left_y: 20000,
+            right_x: i16::MAX,
+            right_trigger: 255,
+            ..Default::default()
+        };
+        let pressed = changes(&idle, &state);
+        assert_eq!(pressed.len(), 4);
+        assert!(pressed.contains(&(Target::Key(Key::Space), true)));
+        assert!(pressed.contains(&(Target::Key(Key::Layout('w')), true)));
+        assert!(pressed.contains(&(Target::Mouse(MouseButton::Left), true)));
+
+        let next = GamepadState {
+            buttons: BUTTON_A,
+            left_y: 5000,
+            ..state
+        };
+        assert_eq!(
+            changes(&state, &next),
+            vec![
+                (Target::Key(Key::UpArrow), false),
+                (Target::Key(Key::Layout('w')), false)
+            ]
+        );
+        assert_eq!(changes(&next, &next), vec![]);
+        assert_eq!(changes(&next, &idle).len(), 2);
+
+        assert_eq!(mouse_delta(&state), (MOUSE_SPEED, 0));
+        assert_eq!(mouse_delta(&next), (MOUSE_SPEED, 0));
+        assert!(!state.is_right_stick_centered());
+        assert_eq!(mouse_delta(&idle), (0, 0));
+
+        let parsed = parse(br#"{"buttons":4096,"right_trigger":200}"#).unwrap();
+        assert_eq!(parsed.buttons, BUTTON_A);
+        assert_eq!(parsed.index, 0);
+
+        let mut captured = GamepadState::default();
+        set_dpad(&mut captured, BUTTON_DPAD_LEFT, BUTTON_DPAD_RIGHT, -1.0);
+        assert_eq!(captured.buttons, BUTTON_DPAD_LEFT);
+        set_dpad(&mut captured, BUTTON_DPAD_LEFT, BUTTON_DPAD_RIGHT, 0.0);
+        assert_eq!(captured.buttons, 0);
+        assert_eq!((axis_value(1.0), axis_value(-2.0)), (i16::MAX, -i16::MAX));
+        assert_eq!((trigger_value(1.0), trigger_value(-1.0)), (u8::MAX, 0));
+    }
+}
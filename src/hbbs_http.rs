@@ -4,8 +4,10 @@ use serde_json::{Map, Value};
 
 #[cfg(feature = "flutter")]
 pub mod account;
+pub mod availability;
 pub mod downloader;
 mod http_client;
+pub mod peer_message;
 pub mod record_upload;
 pub mod sync;
 pub use http_client::{
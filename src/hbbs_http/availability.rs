@@ -0,0 +1,64 @@
+//! Availability status published to the address book with the heartbeat.
+//!
+//! The status is set locally with `--availability <available|busy|maintenance> [note]`
+//! or the ipc options, and is shown to controllers in the peer grid. While an incoming
+//! remote control session is active, an "available" host is published as "busy".
+
+use hbb_common::{bail, config::Config, ResultType};
+use serde_json::{json, Value};
+
+pub const OPTION_AVAILABILITY_STATUS: &str = "availability-status";
+pub const OPTION_AVAILABILITY_NOTE: &str = "availability-note";
+
+const MAX_NOTE_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Availability {
+    Available,
+    Busy,
+    Maintenance,
+}
+
+impl Availability {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Availability::Available => "available",
+            Availability::Busy => "busy",
+            Availability::Maintenance => "maintenance",
+        }
+    }
+
+    fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "" | "available" => Some(Availability::Available),
+            "busy" => Some(Availability::Busy),
+            "maintenance" => Some(Availability::Maintenance),
+            _ => None,
+        }
+    }
+}
+
+/// Validate and normalize the status and note before saving them as options.
+pub fn check(status: &str, note: &str) -> ResultType<(String, String)> {
+    let Some(status) = Availability::parse(status) else {
+        bail!("invalid status, expected available, busy or maintenance");
+    };
+    let note = note.trim();
+    if note.chars().count() > MAX_NOTE_LEN {
+        bail!("note is too long, max {} characters", MAX_NOTE_LEN);
+    }
+    Ok((status.as_str().to_owned(), note.to_owned()))
+}
+
+/// The status to publish, `in_session` turns "available" into "busy".
+pub fn current(in_session: bool) -> Value {
+    let status = Availability::parse(&Config::get_option(OPTION_AVAILABILITY_STATUS))
+        .unwrap_or(Availability::Available);
+    let auto = status == Availability::Available && in_session;
+    let status = if auto { Availability::Busy } else { status };
+    json!({
+        "status": status.as_str(),
+        "note": Config::get_option(OPTION_AVAILABILITY_NOTE),
+        "auto": auto,
+    })
+}
@@ -0,0 +1,228 @@
+//! Short text messages between address book peers without an active session.
+//!
+//! The sender posts the message to the api server, which keeps it until the target
+//! peer is online. Pending messages are returned in the heartbeat response of the
+//! target, stored in a local inbox, shown as a notification, and acknowledged in
+//! the next heartbeat.
+//!
+//! The same channel is used to tell a peer rejected by a busy host that the host is
+//! free, if the peer asked for it with `register_host_wait`.
+
+use hbb_common::{
+    bail,
+    config::{Config, LocalConfig},
+    log, ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Mutex;
+
+const MAX_TEXT_LEN: usize = 1024;
+const MAX_INBOX_LEN: usize = 100;
+const INBOX_FILE: &str = "peer_messages.json";
+pub const KIND_HOST_FREE: &str = "host-free";
+
+lazy_static::lazy_static! {
+    static ref PENDING_ACKS: Mutex<Vec<String>> = Default::default();
+    static ref INBOX_LOCK: Mutex<()> = Default::default();
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PeerMessage {
+    pub guid: String,
+    pub from: String,
+    #[serde(default)]
+    pub from_name: String,
+    pub text: String,
+    #[serde(default)]
+    pub created_at: i64,
+    #[serde(default)]
+    pub read: bool,
+    // Empty for text messages.
+    #[serde(default)]
+    pub kind: String,
+}
+
+fn api_url(path: &str) -> String {
+    let url = crate::common::get_api_server(
+        Config::get_option("api-server"),
+        Config::get_option("custom-rendezvous-server"),
+    );
+    if url.is_empty() || crate::is_public(&url) {
+        return "".to_owned();
+    }
+    format!("{}{}", url, path)
+}
+
+fn post(path: &str, body: Value) -> ResultType<()> {
+    let url = api_url(path);
+    if url.is_empty() {
+        bail!("no api server");
+    }
+    let token = LocalConfig::get_option("access_token");
+    if token.is_empty() {
+        bail!("not logged in");
+    }
+    let resp = crate::post_request_sync(
+        url,
+        body.to_string(),
+        &format!("Authorization: Bearer {}", token),
+    )?;
+    if let Ok(v) = serde_json::from_str::<Value>(&resp) {
+        if let Some(err) = v.get("error").and_then(|e| e.as_str()) {
+            bail!("{}", err);
+        }
+    }
+    Ok(())
+}
+
+/// Send a message to `to`, it is delivered when the peer is online.
+///
+/// The user must be logged in, the api server checks that `to` is in one of their address books.
+pub fn send(to: &str, text: &str) -> ResultType<()> {
+    let text = text.trim();
+    if text.is_empty() {
+        bail!("empty message");
+    }
+    if text.chars().count() > MAX_TEXT_LEN {
+        bail!("message is too long, max {} characters", MAX_TEXT_LEN);
+    }
+    post(
+        "/api/peer-message",
+        json!({
+            "to": to,
+            "from": Config::get_id(),
+            "text": text,
+        }),
+    )
+}
+
+/// Ask to be told when the busy host `host` is free, see `crate::server::session_queue`.
+pub fn register_host_wait(host: &str) -> ResultType<()> {
+    post(
+        "/api/host-wait",
+        json!({
+            "host": host,
+            "from": Config::get_id(),
+        }),
+    )
+}
+
+/// Message ids to acknowledge in the next heartbeat.
+pub fn take_acks() -> Vec<String> {
+    std::mem::take(&mut *PENDING_ACKS.lock().unwrap())
+}
+
+/// Put the acks back if the heartbeat failed.
+pub fn restore_acks(mut acks: Vec<String>) {
+    let mut pending = PENDING_ACKS.lock().unwrap();
+    acks.append(&mut pending);
+    *pending = acks;
+}
+
+/// Handle the `messages` field of the heartbeat response.
+pub fn handle_heartbeat_messages(v: Value) {
+    let messages = match serde_json::from_value::<Vec<PeerMessage>>(v) {
+        Ok(messages) => messages,
+        Err(e) => {
+            log::error!("Failed to parse peer messages: {}", e);
+            return;
+        }
+    };
+    if messages.is_empty() {
+        return;
+    }
+    let _lock = INBOX_LOCK.lock().unwrap();
+    let mut inbox = load_inbox();
+    let mut acks = vec![];
+    for mut m in messages {
+        acks.push(m.guid.clone());
+        // The server may resend if the ack is lost.
+        if inbox.iter().any(|x| x.guid == m.guid) {
+            continue;
+        }
+        m.text = m.text.chars().take(MAX_TEXT_LEN).collect();
+        m.read = false;
+        notify(&m);
+        inbox.push(m);
+    }
+    if inbox.len() > MAX_INBOX_LEN {
+        inbox.drain(..inbox.len() - MAX_INBOX_LEN);
+    }
+    save_inbox(&inbox);
+    PENDING_ACKS.lock().unwrap().extend(acks);
+}
+
+fn notify(m: &PeerMessage) {
+    let from = if m.from_name.is_empty() {
+        m.from.clone()
+    } else {
+        format!("{} ({})", m.from_name, m.from)
+    };
+    let text = if m.kind == KIND_HOST_FREE {
+        crate::client::translate(format!("host-free-{{{}}}-tip", from))
+    } else {
+        m.text.clone()
+    };
+    log::info!("New message from {}", m.from);
+    crate::platform::show_notification(&format!("{} - {}", crate::get_app_name(), from), &text);
+}
+
+fn load_inbox() -> Vec<PeerMessage> {
+    std::fs::read_to_string(Config::path(INBOX_FILE))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
+fn save_inbox(inbox: &Vec<PeerMessage>) {
+    match serde_json::to_string(inbox) {
+        Ok(s) => {
+            if let Err(e) = std::fs::write(Config::path(INBOX_FILE), s) {
+                log::error!("Failed to save peer messages: {}", e);
+            }
+        }
+        Err(e) => log::error!("Failed to serialize peer messages: {}", e),
+    }
+}
+
+pub fn get_inbox() -> Vec<PeerMessage> {
+    let _lock = INBOX_LOCK.lock().unwrap();
+    load_inbox()
+}
+
+/// Mark messages as read, all messages if `guid` is empty.
+pub fn mark_read(guid: &str) {
+    let _lock = INBOX_LOCK.lock().unwrap();
+    let mut inbox = load_inbox();
+    inbox
+        .iter_mut()
+        .filter(|m| guid.is_empty() || m.guid == guid)
+        .for_each(|m| m.read = true);
+    save_inbox(&inbox);
+}
+
+/// Unread "host free" notifications, returns the host ids and marks them as read.
+pub fn take_host_free() -> Vec<String> {
+    let _lock = INBOX_LOCK.lock().unwrap();
+    let mut inbox = load_inbox();
+    let mut hosts = vec![];
+    for m in inbox
+        .iter_mut()
+        .filter(|m| m.kind == KIND_HOST_FREE && !m.read)
+    {
+        m.read = true;
+        if !hosts.contains(&m.from) {
+            hosts.push(m.from.clone());
+        }
+    }
+    if !hosts.is_empty() {
+        save_inbox(&inbox);
+    }
+    hosts
+}
+
+pub fn clear_inbox() {
+    let _lock = INBOX_LOCK.lock().unwrap();
+    save_inbox(&vec![]);
+}
@@ -4,7 +4,7 @@ use std::{
     time::Duration,
 };
 
-use crate::{ui_interface::get_builtin_option, Connection};
+use crate::{peer_identity, ui_interface::get_builtin_option, Connection};
 use hbb_common::{
     config::{self, keys, Config, LocalConfig},
     log,
@@ -83,6 +83,8 @@ async fn start_hbbs_sync_async() {
         TIME_CONN,
     ));
     let mut last_sent: Option<Instant> = None;
+    let mut last_availability = Value::Null;
+    let mut notified_waiters = vec![];
     let mut info_uploaded = InfoUploaded::default();
     let mut sysinfo_ver = "".to_owned();
     loop {
@@ -166,6 +168,19 @@ async fn start_hbbs_sync_async() {
                     if !device_name.is_empty() {
                         v["hostname"] = json!(device_name);
                     }
+                    if config::option2bool(
+                        peer_identity::OPTION_SYNC_IDENTITY,
+                        &Config::get_option(peer_identity::OPTION_SYNC_IDENTITY),
+                    ) {
+                        let identity = peer_identity::Identity::local();
+                        if !identity.display_name.is_empty() {
+                            v["display_name"] = json!(identity.display_name);
+                        }
+                        if !identity.avatar.is_empty() {
+                            v["avatar"] = json!(identity.avatar);
+                        }
+                    }
+                    crate::port_range::add_to(&mut v);
                     let note = Config::get_option(keys::OPTION_PRESET_NOTE);
                     if !note.is_empty() {
                         v[keys::OPTION_PRESET_NOTE] = json!(note);
@@ -222,10 +237,14 @@ async fn start_hbbs_sync_async() {
                         }
                     }
                 }
-                if conns.is_empty() && last_sent.map(|x| x.elapsed() < TIME_HEARTBEAT).unwrap_or(false) {
+                let availability = super::availability::current(!crate::server::remote_peers().is_empty());
+                // Publish availability changes without waiting for the next heartbeat.
+                let free_waiters = crate::server::session_queue::take_free_waiters(&mut notified_waiters);
+                if conns.is_empty() && availability == last_availability && free_waiters.is_empty() && last_sent.map(|x| x.elapsed() < TIME_HEARTBEAT).unwrap_or(false) {
                     continue;
                 }
                 last_sent = Some(Instant::now());
+                last_availability = availability.clone();
                 let mut v = Value::default();
                 v["id"] = json!(id);
                 v["uuid"] = json!(crate::encode64(hbb_common::get_uuid()));
@@ -235,7 +254,23 @@ async fn start_hbbs_sync_async() {
                 }
                 let modified_at = LocalConfig::get_option("strategy_timestamp").parse::<i64>().unwrap_or(0);
                 v["modified_at"] = json!(modified_at);
-                if let Ok(s) = crate::post_request(url.clone(), v.to_string(), "").await {
+                v["availability"] = availability;
+                if !free_waiters.is_empty() {
+                    // The api server notifies these peers that they can reconnect.
+                    v["queue_free"] = json!(free_waiters);
+                }
+                let msg_acks = super::peer_message::take_acks();
+                if !msg_acks.is_empty() {
+                    v["msg_ack"] = json!(msg_acks);
+                }
+                let resp = crate::post_request(url.clone(), v.to_string(), "").await;
+                if resp.is_err() && !msg_acks.is_empty() {
+                    super::peer_message::restore_acks(msg_acks);
+                }
+                if resp.is_ok() {
+                    notified_waiters.extend(free_waiters);
+                }
+                if let Ok(s) = resp {
                     if let Ok(mut rsp) = serde_json::from_str::<HashMap::<&str, Value>>(&s) {
                         if rsp.remove("sysinfo").is_some() {
                             info_uploaded.uploaded = false;
@@ -260,6 +295,9 @@ async fn start_hbbs_sync_async() {
                                 handle_config_options(strategy.config_options);
                             }
                         }
+                        if let Some(messages) = rsp.remove("messages") {
+                            super::peer_message::handle_heartbeat_messages(messages);
+                        }
                     }
                 }
             }
@@ -0,0 +1,95 @@
+//! The connection manager of the `headless-agent` build, which has no window to show it.
+//!
+//! The connections are logged instead. Nobody can accept a connection or a permission request,
+//! so a connection which is not authorized by its password is closed and the requests are denied.
+//! The file jobs a restart interrupted are resumed.
+
+use crate::ui_cm_interface::{self, start_ipc, Client, ConnectionManager, InvokeUiCM};
+use hbb_common::log;
+
+#[derive(Clone, Default)]
+struct HeadlessHandler;
+
+impl InvokeUiCM for HeadlessHandler {
+    fn add_connection(&self, client: &Client) {
+        log::info!(
+            "Connection {} from {} ({}), authorized: {}",
+            client.id,
+            client.peer_id,
+            client.name,
+            client.authorized
+        );
+        if !client.authorized {
+            ui_cm_interface::close(client.id);
+        }
+    }
+
+    fn remove_connection(&self, id: i32, _close: bool) {
+        log::info!("Connection {} removed", id);
+    }
+
+    fn new_message(&self, id: i32, _text: String) {
+        log::info!("Chat message of connection {} ignored", id);
+    }
+
+    fn change_theme(&self, _dark: String) {}
+
+    fn change_language(&self) {}
+
+    fn show_elevation(&self, _show: bool) {}
+
+    // Called with the clients locked, so an incoming call is left unanswered.
+    fn update_voice_call_state(&self, _client: &Client) {}
+
+    fn update_client_record_status(&self, _client: &Client) {}
+
+    fn update_client_tunnels(&self, client: &Client) {
+        let targets: Vec<&str> = client.tunnels.iter().map(|(_, t)| t.as_str()).collect();
+        log::info!("Tunnels of connection {}: {:?}", client.id, targets);
+    }
+
+    fn permission_request(&self, id: i32, name: String) {
+        log::info!("Permission {} requested by connection {} denied", name, id);
+        ui_cm_interface::answer_permission_request(id, name, false);
+    }
+
+    fn interrupted_transfer_jobs(&self, id: i32, jobs: &[crate::transfer_journal::Job]) {
+        log::info!(
+            "{} interrupted file jobs of connection {} resumed",
+            jobs.len(),
+            id
+        );
+        ui_cm_interface::resume_transfer_jobs(id, true);
+    }
+
+    fn file_transfer_log(&self, action: &str, log: &str) {
+        log::info!("File transfer {}: {}", action, log);
+    }
+
+    fn file_paste_progress(
+        &self,
+        id: i32,
+        transferred: u64,
+        total: u64,
+        canceled: bool,
+        failed: bool,
+    ) {
+        if canceled || failed || transferred == total {
+            log::info!(
+                "Paste of the files of connection {}: {}/{} bytes, canceled: {}, failed: {}",
+                id,
+                transferred,
+                total,
+                canceled,
+                failed
+            );
+        }
+    }
+}
+
+/// Run the connection manager, until the process ends.
+pub fn start_cm() {
+    start_ipc(ConnectionManager {
+        ui_handler: HeadlessHandler,
+    });
+}
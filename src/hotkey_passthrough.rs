@@ -0,0 +1,269 @@
+//! Local chords sent to the remote or kept on this computer.
+//!
+//! With the rdev input source, the grab loop sends every key to the remote while a session has
+//! the focus, the chords of the system included. `OPTION_HOTKEY_PASSTHROUGH` decides per chord,
+//! a local json object like:
+//!
+//! ```json
+//! { "Cmd+Space": "local", "Ctrl+Up": "local", "*": "remote" }
+//! ```
+//!
+//! "*" is the target of the chords not listed, a chord being a key pressed with Control, Alt or
+//! Command held. With "*" on "local", only the listed chords are sent to the remote. The keys
+//! without such a modifier are sent unless they are listed. A modifier is named "Ctrl", "Alt"
+//! ("Option"), "Shift" or "Cmd" ("Meta"), and a key like the `rdev::Key` ("Tab", "F3", "Up", "a").
+
+use hbb_common::config::LocalConfig;
+use rdev::Key;
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::Mutex,
+};
+
+pub const OPTION_HOTKEY_PASSTHROUGH: &str = "hotkey-passthrough";
+const ANY_CHORD: &str = "*";
+// The order of the modifiers in a chord.
+const MODIFIERS: [&str; 4] = ["ctrl", "alt", "shift", "cmd"];
+
+lazy_static::lazy_static! {
+    // The option and its rules, parsed again when the option changes.
+    static ref RULES: Mutex<(String, Rules)> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Target {
+    Remote,
+    Local,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Chord {
+    // Indexes in `MODIFIERS`, sorted.
+    modifiers: Vec<usize>,
+    key: String,
+}
+
+impl Chord {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let mut modifiers = vec![];
+        let mut key = None;
+        for part in s.split('+').map(|p| p.trim().to_lowercase()) {
+            if part.is_empty() {
+                return Err(format!("Invalid chord: {}", s));
+            }
+            match modifier_index(&part) {
+                Some(i) if key.is_none() => modifiers.push(i),
+                _ if key.is_some() => return Err(format!("Invalid chord: {}", s)),
+                _ => key = Some(normalize_key(&part)),
+            }
+        }
+        let Some(key) = key else {
+            return Err(format!("No key in chord: {}", s));
+        };
+        modifiers.sort_unstable();
+        modifiers.dedup();
+        Ok(Self { modifiers, key })
+    }
+
+    /// The chord of a key pressed while the modifiers of `state` are down.
+    pub fn pressed(key: Key, state: &HashMap<Key, bool>) -> Self {
+        let down = |keys: &[Key]| keys.iter().any(|k| state.get(k) == Some(&true));
+        let mut modifiers = vec![];
+        for (i, keys) in [
+            &[Key::ControlLeft, Key::ControlRight][..],
+            &[Key::Alt, Key::AltGr],
+            &[Key::ShiftLeft, Key::ShiftRight],
+            &[Key::MetaLeft, Key::MetaRight],
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            if down(keys) {
+                modifiers.push(i);
+            }
+        }
+        Self {
+            modifiers,
+            key: normalize_key(&format!("{:?}", key)),
+        }
+    }
+
+    fn is_shortcut(&self) -> bool {
+        // Shift alone types characters.
+        self.modifiers.iter().any(|i| MODIFIERS[*i] != "shift")
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for i in &self.modifiers {
+            write!(f, "{}+", MODIFIERS[*i])?;
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+fn modifier_index(name: &str) -> Option<usize> {
+    let name = match name {
+        "control" => "ctrl",
+        "option" | "opt" => "alt",
+        "command" | "meta" | "super" | "win" => "cmd",
+        name => name,
+    };
+    MODIFIERS.iter().position(|m| *m == name)
+}
+
+/// A key of the option or the debug name of a `rdev::Key`, to one name.
+fn normalize_key(name: &str) -> String {
+    let name = name.trim().to_lowercase();
+    if name.len() == 1 {
+        return name;
+    }
+    if name.len() == 4 && name.starts_with("key") {
+        return name[3..].to_owned();
+    }
+    if name.len() == 4 && name.starts_with("num") {
+        return name[3..].to_owned();
+    }
+    match name.as_str() {
+        "esc" => "escape".to_owned(),
+        "enter" => "return".to_owned(),
+        "del" => "delete".to_owned(),
+        "up" | "down" | "left" | "right" => format!("{}arrow", name),
+        _ => name,
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct Rules {
+    chords: HashMap<Chord, Target>,
+    any: Option<Target>,
+}
+
+impl Rules {
+    fn parse(option: &str) -> Result<Self, String> {
+        let mut rules = Self::default();
+        if option.trim().is_empty() {
+            return Ok(rules);
+        }
+        let map: BTreeMap<String, Target> =
+            serde_json::from_str(option).map_err(|e| e.to_string())?;
+        for (chord, target) in map {
+            if chord == ANY_CHORD {
+                rules.any = Some(target);
+            } else {
+                rules.chords.insert(Chord::parse(&chord)?, target);
+            }
+        }
+        Ok(rules)
+    }
+
+    fn target(&self, chord: &Chord) -> Target {
+        if let Some(target) = self.chords.get(chord) {
+            return *target;
+        }
+        match self.any {
+            Some(target) if chord.is_shortcut() => target,
+            _ => Target::Remote,
+        }
+    }
+}
+
+/// Check the value of an option before it is set, the other options are not checked.
+pub fn validate(name: &str, value: &str) -> Result<(), String> {
+    if name == OPTION_HOTKEY_PASSTHROUGH {
+        Rules::parse(value)?;
+    }
+    Ok(())
+}
+
+/// Whether the chord is kept on this computer instead of sent to the remote.
+pub fn is_local(chord: &Chord) -> bool {
+    let option = LocalConfig::get_option(OPTION_HOTKEY_PASSTHROUGH);
+    let mut rules = RULES.lock().unwrap();
+    if rules.0 != option {
+        let parsed = Rules::parse(&option).unwrap_or_else(|e| {
+            hbb_common::log::warn!("Ignore {}: {}", OPTION_HOTKEY_PASSTHROUGH, e);
+            Rules::default()
+        });
+        *rules = (option, parsed);
+    }
+    rules.1.target(chord) == Target::Local
+}
+
+/// The chords of the option, "*" included, with their target.
+pub fn list() -> BTreeMap<String, Target> {
+    serde_json::from_str(&LocalConfig::get_option(OPTION_HOTKEY_PASSTHROUGH)).unwrap_or_default()
+}
+
+fn canonical(chord: &str) -> Result<String, String> {
+    match chord.trim() {
+        ANY_CHORD => Ok(ANY_CHORD.to_owned()),
+        chord => Ok(Chord::parse(chord)?.to_string()),
+    }
+}
+
+/// Set the target of a chord, or of "*", none to remove it.
+pub fn set(chord: &str, target: Option<Target>) -> Result<(), String> {
+    let chord = canonical(chord)?;
+    let mut map: BTreeMap<String, Target> = list()
+        .into_iter()
+        .filter(|(c, _)| canonical(c).as_ref() != Ok(&chord))
+        .collect();
+    if let Some(target) = target {
+        map.insert(chord, target);
+    }
+    let value = if map.is_empty() {
+        String::new()
+    } else {
+        serde_json::to_string(&map).map_err(|e| e.to_string())?
+    };
+    LocalConfig::set_option(OPTION_HOTKEY_PASSTHROUGH.to_owned(), value);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hotkey_passthrough_rules() {
+        let chord = Chord::parse("Cmd+Shift+Tab").unwrap();
+        assert_eq!(chord.to_string(), "shift+cmd+tab");
+        assert_eq!(
+            Chord::parse("command + up").unwrap().to_string(),
+            "cmd+uparrow"
+        );
+        for invalid in ["Cmd+", "Cmd", "Tab+Cmd", "a+b"] {
+            assert!(Chord::parse(invalid).is_err(), "{}", invalid);
+        }
+
+        let mut state = HashMap::new();
+        state.insert(Key::MetaLeft, true);
+        assert_eq!(
+            Chord::pressed(Key::Space, &state),
+            Chord::parse("Cmd+Space").unwrap()
+        );
+        assert_eq!(Chord::pressed(Key::KeyA, &state).to_string(), "cmd+a");
+
+        let target = |rules: &Rules, chord| rules.target(&Chord::parse(chord).unwrap());
+        let rules = Rules::parse(r#"{"Cmd+Space":"local","F3":"local","*":"remote"}"#).unwrap();
+        assert_eq!(target(&rules, "cmd+space"), Target::Local);
+        assert_eq!(target(&rules, "F3"), Target::Local);
+        assert_eq!(target(&rules, "cmd+tab"), Target::Remote);
+
+        // Only the listed chords are sent.
+        let rules = Rules::parse(r#"{"Cmd+Tab":"remote","*":"local"}"#).unwrap();
+        assert_eq!(target(&rules, "cmd+tab"), Target::Remote);
+        assert_eq!(target(&rules, "ctrl+up"), Target::Local);
+        assert_eq!(target(&rules, "shift+a"), Target::Remote);
+        assert_eq!(target(&rules, "a"), Target::Remote);
+
+        assert!(validate(OPTION_HOTKEY_PASSTHROUGH, r#"{"Cmd+":"local"}"#).is_err());
+        assert!(validate(OPTION_HOTKEY_PASSTHROUGH, r#"{"Cmd+Tab":"both"}"#).is_err());
+        assert!(validate(OPTION_HOTKEY_PASSTHROUGH, "").is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+//! Recording and replay of the keyboard and mouse input of a connection.
+//!
+//! The viewer starts recording with `Request::Record`, the mouse and key events it injects from
+//! then on are kept with their delays, and `Request::Stop` saves them under the name in the
+//! `macros` directory of the host config. `Request::Replay` injects the steps again at the same
+//! pace, timed on a task of its own which passes them to the input thread of the connection, so
+//! the live input is not held up. `Request::Stop`, the view only mode, the keyboard permission
+//! turned off and the end of the connection cancel it.
+//!
+//! The events are kept as the protobuf bytes of `MouseEvent` and `KeyEvent`, so a macro replays
+//! the same way as the live input. The number of steps and the delay between two of them are
+//! limited, so a forgotten recording does not grow without bound.
+
+use crate::plugin_request;
+use hbb_common::{
+    bail,
+    config::Config,
+    message_proto::{KeyEvent, Message, MouseEvent},
+    protobuf::Message as _,
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{path::PathBuf, time::Instant};
+
+pub const ID: &str = "input-macro";
+const DIR: &str = "macros";
+const MAX_STEPS: usize = 10_000;
+pub const MAX_DELAY_MS: u64 = 10_000;
+const MAX_NAME_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum Request {
+    Record {
+        name: String,
+    },
+    /// Stop the recording and save it, or cancel the replay.
+    Stop,
+    Replay {
+        name: String,
+    },
+    Delete {
+        name: String,
+    },
+    List,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub macros: Vec<String>,
+    /// The name of the macro being recorded, if any.
+    #[serde(default)]
+    pub recording: Option<String>,
+    #[serde(default)]
+    pub error: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Mouse(Vec<u8>),
+    Key { event: Vec<u8>, press: bool },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Step {
+    pub delay_ms: u64,
+    pub event: Event,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Macro {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+impl Step {
+    pub fn mouse_event(&self) -> Option<MouseEvent> {
+        match &self.event {
+            Event::Mouse(bytes) => MouseEvent::parse_from_bytes(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn key_event(&self) -> Option<(KeyEvent, bool)> {
+        match &self.event {
+            Event::Key { event, press } => Some((KeyEvent::parse_from_bytes(event).ok()?, *press)),
+            _ => None,
+        }
+    }
+}
+
+/// The recording of a connection.
+pub struct Recorder {
+    name: String,
+    last: Instant,
+    steps: Vec<Step>,
+}
+
+impl Recorder {
+    pub fn new(name: &str) -> ResultType<Self> {
+        check_name(name)?;
+        Ok(Self {
+            name: name.to_owned(),
+            last: Instant::now(),
+            steps: vec![],
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn push(&mut self, event: Event) {
+        if self.steps.len() >= MAX_STEPS {
+            return;
+        }
+        let delay_ms = (self.last.elapsed().as_millis() as u64).min(MAX_DELAY_MS);
+        self.last = Instant::now();
+        self.steps.push(Step { delay_ms, event });
+    }
+
+    pub fn push_mouse(&mut self, evt: &MouseEvent) {
+        if let Ok(bytes) = evt.write_to_bytes() {
+            self.push(Event::Mouse(bytes));
+        }
+    }
+
+    pub fn push_key(&mut self, evt: &KeyEvent, press: bool) {
+        if let Ok(bytes) = evt.write_to_bytes() {
+            self.push(Event::Key {
+                event: bytes,
+                press,
+            });
+        }
+    }
+
+    pub fn finish(self) -> Macro {
+        Macro {
+            name: self.name,
+            steps: self.steps,
+        }
+    }
+}
+
+fn check_name(name: &str) -> ResultType<()> {
+    if name.is_empty()
+        || name.len() > MAX_NAME_LEN
+        || !name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ' ')
+    {
+        bail!("invalid macro name: {name}");
+    }
+    Ok(())
+}
+
+fn path(name: &str) -> ResultType<PathBuf> {
+    check_name(name)?;
+    Ok(Config::path(DIR).join(format!("{name}.json")))
+}
+
+pub fn save(m: &Macro) -> ResultType<()> {
+    let path = path(&m.name)?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, serde_json::to_vec(m)?)?;
+    Ok(())
+}
+
+pub fn load(name: &str) -> ResultType<Macro> {
+    Ok(serde_json::from_slice(&std::fs::read(path(name)?)?)?)
+}
+
+pub fn delete(name: &str) -> ResultType<()> {
+    std::fs::remove_file(path(name)?)?;
+    Ok(())
+}
+
+pub fn list() -> Vec<String> {
+    let mut v: Vec<String> = std::fs::read_dir(Config::path(DIR))
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    name.strip_suffix(".json").map(|s| s.to_owned())
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    v.sort();
+    v
+}
+
+pub fn make_request(req: &Request) -> Message {
+    plugin_request::make(ID, req)
+}
+
+pub fn make_response(recording: Option<&Recorder>, error: String) -> Message {
+    plugin_request::make(
+        ID,
+        &Response {
+            macros: list(),
+            recording: recording.map(|r| r.name().to_owned()),
+            error,
+        },
+    )
+}
+
+pub fn parse_request(content: &[u8]) -> Option<Request> {
+    serde_json::from_slice(content).ok()
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_macro_record() {
+        assert_eq!(
+            parse_request(br#"{"action":"replay","name":"login"}"#),
+            Some(Request::Replay {
+                name: "login".to_owned()
+            })
+        );
+        assert_eq!(parse_request(br#"{"action":"stop"}"#), Some(Request::Stop));
+        assert!(parse_request(br#"{"action":"loop"}"#).is_none());
+        assert!(Recorder::new("../etc").is_err());
+        assert!(Recorder::new("").is_err());
+
+        let mut recorder = Recorder::new("open terminal").unwrap();
+        let mouse = MouseEvent {
+            mask: 1,
+            x: 10,
+            y: 20,
+            ..Default::default()
+        };
+        recorder.push_mouse(&mouse);
+        let key = KeyEvent {
+            down: true,
+            ..Default::default()
+        };
+        recorder.push_key(&key, true);
+        let m = recorder.finish();
+        assert_eq!(m.name, "open terminal");
+        assert_eq!(m.steps.len(), 2);
+        assert!(m.steps.iter().all(|s| s.delay_ms <= MAX_DELAY_MS));
+        let json = serde_json::to_string(&m).unwrap();
+        let m: Macro = serde_json::from_str(&json).unwrap();
+        assert_eq!(m.steps[0].mouse_event(), Some(mouse));
+        assert_eq!(m.steps[0].key_event(), None);
+        assert_eq!(m.steps[1].key_event(), Some((key, true)));
+    }
+}
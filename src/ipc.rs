@@ -1,6 +1,7 @@
 use crate::{
     common::CheckTestNatType,
     privacy_mode::PrivacyModeState,
+    secret_store::{self, Secret},
     ui_interface::{get_local_option, set_local_option},
 };
 use bytes::Bytes;
@@ -57,11 +58,20 @@ pub enum FS {
         path: String,
         id: i32,
         recursive: bool,
+        // Move it to the trash, see `file_trash`.
+        #[serde(default)]
+        trash: bool,
     },
     RemoveFile {
         path: String,
         id: i32,
         file_num: i32,
+        #[serde(default)]
+        trash: bool,
+    },
+    RestoreTrashed {
+        id: i32,
+        items: Vec<crate::file_trash::Trashed>,
     },
     CreateDir {
         path: String,
@@ -182,6 +192,17 @@ pub enum DataControl {
     },
 }
 
+// The input timeline kept by the server, see `server::input_timeline`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "t", content = "c")]
+pub enum DataInputTimeline {
+    // The sessions of the last days, all with none.
+    Export(Option<u64>),
+    Exported(Result<String, String>),
+    Clear,
+    Cleared(usize),
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "t", content = "c")]
 pub enum DataPortableService {
@@ -225,6 +246,18 @@ pub enum Data {
         name: String,
         enabled: bool,
     },
+    // Asked by the peer, none until the user of the host answers.
+    PermissionRequest {
+        name: String,
+        granted: Option<bool>,
+    },
+    // Set by the connection manager, see `ui_cm_interface::set_view_only`.
+    ViewOnly(bool),
+    // The peer of the file jobs of the session, and its jobs a restart interrupted, see
+    // `transfer_journal`.
+    TransferJournal(String, Vec<crate::transfer_journal::Job>),
+    // Resume them or discard them, see `ui_cm_interface::resume_transfer_jobs`.
+    ResumeTransferJobs(bool),
     SystemInfo(Option<String>),
     ClickTime(i64),
     MouseMoveTime(i64),
@@ -241,6 +274,8 @@ pub enum Data {
     FS(FS),
     Test,
     SyncConfig(Option<Box<(Config, Config2)>>),
+    // A secret kept by the service in the keystore, see `secret_store`.
+    Secret((Secret, Option<String>)),
     ClipboardFileEnabled(bool),
     PrivacyModeState((i32, PrivacyModeState, String)),
     TestRendezvousServer,
@@ -260,9 +295,24 @@ pub enum Data {
     StartVoiceCall,
     VoiceCallResponse(bool),
     CloseVoiceCall(String),
+    ClientRecordStatus(bool),
+    // A channel of the tunnels of `mux` opened to the target or closed.
+    MuxTunnel {
+        channel: u32,
+        target: String,
+        open: bool,
+    },
     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
     Plugin(Plugin),
     FileTransferLog((String, String)),
+    // The files of the peer pasted on the host, and the CM cancelling it.
+    FilePasteProgress {
+        transferred: u64,
+        total: u64,
+        canceled: bool,
+        failed: bool,
+    },
+    CancelFilePaste,
     CmErr(String),
     // CM-side file reading responses (Windows only)
     // These are sent from CM back to Connection when CM handles file reading
@@ -344,6 +394,13 @@ pub enum Data {
     SocksWs(Option<Box<(Option<config::Socks5Server>, String)>>),
     Whiteboard((String, crate::whiteboard::CustomEvent)),
     ControlPermissionsRemoteModify(Option<bool>),
+    // Json of `video_qos::SessionStats` list
+    SessionStats(Option<String>),
+    // Json of `port_mapping::Status`
+    PortMapping(Option<String>),
+    // Json of `service_status::ServiceStatus`
+    ServiceStatus(Option<String>),
+    InputTimeline(DataInputTimeline),
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -562,6 +619,38 @@ async fn handle(data: Data, stream: &mut Connection) {
                 .count();
             allow_err!(stream.send(&Data::VideoConnCount(Some(n))).await);
         }
+        Data::SessionStats(None) => {
+            let stats = crate::video_service::VIDEO_QOS
+                .lock()
+                .unwrap()
+                .session_stats();
+            let stats = serde_json::to_string(&stats).unwrap_or_default();
+            allow_err!(stream.send(&Data::SessionStats(Some(stats))).await);
+        }
+        Data::ServiceStatus(None) => {
+            crate::service_status::serve(stream).await;
+        }
+        Data::InputTimeline(DataInputTimeline::Export(days)) => {
+            use crate::server::input_timeline;
+            let res = tokio::task::spawn_blocking(move || input_timeline::export(days))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|res| res.map_err(|e| e.to_string()));
+            let data = Data::InputTimeline(DataInputTimeline::Exported(res));
+            allow_err!(stream.send(&data).await);
+        }
+        Data::InputTimeline(DataInputTimeline::Clear) => {
+            use crate::server::input_timeline;
+            let n = tokio::task::spawn_blocking(input_timeline::clear)
+                .await
+                .unwrap_or_default();
+            let data = Data::InputTimeline(DataInputTimeline::Cleared(n));
+            allow_err!(stream.send(&data).await);
+        }
+        Data::PortMapping(None) => {
+            let status = serde_json::to_string(&crate::port_mapping::status()).unwrap_or_default();
+            allow_err!(stream.send(&Data::PortMapping(Some(status))).await);
+        }
         Data::Config((name, value)) => match value {
             None => {
                 let value;
@@ -570,7 +659,7 @@ async fn handle(data: Data, stream: &mut Connection) {
                 } else if name == "temporary-password" {
                     value = Some(password::temporary_password());
                 } else if name == "permanent-password" {
-                    value = Some(Config::get_permanent_password());
+                    value = Some(secret_store::get(Secret::PermanentPassword));
                 } else if name == "salt" {
                     value = Some(Config::get_salt());
                 } else if name == "rendezvous_server" {
@@ -597,7 +686,7 @@ async fn handle(data: Data, stream: &mut Connection) {
                 } else if name == "voice-call-input" {
                     value = crate::audio_service::get_voice_call_input_device();
                 } else if name == "unlock-pin" {
-                    value = Some(Config::get_unlock_pin());
+                    value = Some(secret_store::get(Secret::UnlockPin));
                 } else if name == "trusted-devices" {
                     value = Some(Config::get_trusted_devices_json());
                 } else {
@@ -612,13 +701,13 @@ async fn handle(data: Data, stream: &mut Connection) {
                 } else if name == "temporary-password" {
                     password::update_temporary_password();
                 } else if name == "permanent-password" {
-                    Config::set_permanent_password(&value);
+                    secret_store::set(Secret::PermanentPassword, &value);
                 } else if name == "salt" {
                     Config::set_salt(&value);
                 } else if name == "voice-call-input" {
                     crate::audio_service::set_voice_call_input_device(Some(value), true);
                 } else if name == "unlock-pin" {
-                    Config::set_unlock_pin(&value);
+                    secret_store::set(Secret::UnlockPin, &value);
                 } else {
                     return;
                 }
@@ -651,6 +740,18 @@ async fn handle(data: Data, stream: &mut Connection) {
             Config2::set(config2);
             allow_err!(stream.send(&Data::SyncConfig(None)).await);
         }
+        Data::Secret((secret, None)) => {
+            let value = tokio::task::spawn_blocking(move || secret_store::get(secret))
+                .await
+                .unwrap_or_default();
+            allow_err!(stream.send(&Data::Secret((secret, Some(value)))).await);
+        }
+        Data::Secret((secret, Some(value))) => {
+            tokio::task::spawn_blocking(move || secret_store::set(secret, &value))
+                .await
+                .ok();
+            allow_err!(stream.send(&Data::Secret((secret, None))).await);
+        }
         Data::SyncConfig(None) => {
             allow_err!(
                 stream
@@ -908,10 +1009,10 @@ pub fn update_temporary_password() -> ResultType<()> {
 
 pub fn get_permanent_password() -> String {
     if let Ok(Some(v)) = get_config("permanent-password") {
-        Config::set_permanent_password(&v);
+        secret_store::set(Secret::PermanentPassword, &v);
         v
     } else {
-        Config::get_permanent_password()
+        secret_store::get(Secret::PermanentPassword)
     }
 }
 
@@ -922,7 +1023,7 @@ pub fn get_fingerprint() -> String {
 }
 
 pub fn set_permanent_password(v: String) -> ResultType<()> {
-    Config::set_permanent_password(&v);
+    secret_store::set(Secret::PermanentPassword, &v);
     set_config("permanent-password", v)
 }
 
@@ -948,17 +1049,17 @@ pub fn set_unlock_pin(v: String, translate: bool) -> ResultType<()> {
             bail!("No more than {max_len} characters");
         }
     }
-    Config::set_unlock_pin(&v);
+    secret_store::set(Secret::UnlockPin, &v);
     set_config("unlock-pin", v)
 }
 
 #[cfg(feature = "flutter")]
 pub fn get_unlock_pin() -> String {
     if let Ok(Some(v)) = get_config("unlock-pin") {
-        Config::set_unlock_pin(&v);
+        secret_store::set(Secret::UnlockPin, &v);
         v
     } else {
-        Config::get_unlock_pin()
+        secret_store::get(Secret::UnlockPin)
     }
 }
 
@@ -1075,6 +1176,52 @@ async fn get_nat_type_(ms_timeout: u64) -> ResultType<i32> {
     }
 }
 
+pub async fn get_session_stats(ms_timeout: u64) -> ResultType<String> {
+    let mut c = connect(ms_timeout, "").await?;
+    c.send(&Data::SessionStats(None)).await?;
+    if let Some(Data::SessionStats(Some(value))) = c.next_timeout(ms_timeout).await? {
+        Ok(value)
+    } else {
+        bail!("no session stats")
+    }
+}
+
+pub async fn get_port_mapping(ms_timeout: u64) -> ResultType<String> {
+    let mut c = connect(ms_timeout, "").await?;
+    c.send(&Data::PortMapping(None)).await?;
+    if let Some(Data::PortMapping(Some(value))) = c.next_timeout(ms_timeout).await? {
+        Ok(value)
+    } else {
+        bail!("no port mapping status")
+    }
+}
+
+/// Export the input timeline of the server, which keeps the key of it.
+#[tokio::main(flavor = "current_thread")]
+pub async fn export_input_timeline(days: Option<u64>) -> ResultType<String> {
+    let ms_timeout = 30_000;
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::InputTimeline(DataInputTimeline::Export(days)))
+        .await?;
+    match c.next_timeout(ms_timeout).await? {
+        Some(Data::InputTimeline(DataInputTimeline::Exported(Ok(records)))) => Ok(records),
+        Some(Data::InputTimeline(DataInputTimeline::Exported(Err(e)))) => bail!(e),
+        _ => bail!("No input timeline from the server"),
+    }
+}
+
+/// Remove the input timeline of the server, the number of files removed.
+#[tokio::main(flavor = "current_thread")]
+pub async fn clear_input_timeline() -> ResultType<usize> {
+    let mut c = connect(1000, "").await?;
+    c.send(&Data::InputTimeline(DataInputTimeline::Clear))
+        .await?;
+    match c.next_timeout(30_000).await? {
+        Some(Data::InputTimeline(DataInputTimeline::Cleared(n))) => Ok(n),
+        _ => bail!("No answer of the server"),
+    }
+}
+
 pub async fn get_nat_type(ms_timeout: u64) -> i32 {
     get_nat_type_(ms_timeout)
         .await
@@ -1181,6 +1328,29 @@ pub async fn connect_to_user_session(usid: Option<u32>) -> ResultType<()> {
     Ok(())
 }
 
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_service_secret(secret: Secret) -> ResultType<Option<String>> {
+    let mut stream = connect(1000, crate::POSTFIX_SERVICE).await?;
+    stream.send(&Data::Secret((secret, None))).await?;
+    if let Some(Data::Secret((secret2, value))) = stream.next_timeout(1000).await? {
+        if secret == secret2 {
+            return Ok(value);
+        }
+    }
+    Ok(None)
+}
+
+// Answered once the secret is saved.
+#[tokio::main(flavor = "current_thread")]
+pub async fn set_service_secret(secret: Secret, value: String) -> ResultType<()> {
+    let mut stream = connect(1000, crate::POSTFIX_SERVICE).await?;
+    stream.send(&Data::Secret((secret, Some(value)))).await?;
+    match stream.next_timeout(1000).await? {
+        Some(Data::Secret((secret2, None))) if secret == secret2 => Ok(()),
+        _ => bail!("No answer of the service"),
+    }
+}
+
 #[tokio::main(flavor = "current_thread")]
 pub async fn notify_server_to_check_hwcodec() -> ResultType<()> {
     connect(1_000, "").await?.send(&&Data::CheckHwcodec).await?;
@@ -14,9 +14,12 @@ use kcp_sys::{
 };
 use std::{net::SocketAddr, sync::Arc};
 
+use crate::video_datagram::{self, DatagramChannel};
+
 pub struct KcpStream {
     _endpoint: KcpEndpoint,
     stop_sender: Option<oneshot::Sender<()>>,
+    datagram: Option<DatagramChannel>,
 }
 
 impl KcpStream {
@@ -49,7 +52,7 @@ impl KcpStream {
                 input.send(packet.into()).await?;
             }
         }
-        Self::kcp_io(udp_socket.clone(), input, output, stop_receiver).await;
+        let datagram = Self::kcp_io(udp_socket.clone(), input, output, stop_receiver).await;
 
         let conn_id = tokio::time::timeout(timeout, endpoint.accept()).await??;
         if let Some(stream) = stream::KcpStream::new(&endpoint, conn_id) {
@@ -57,6 +60,7 @@ impl KcpStream {
                 Self {
                     _endpoint: endpoint,
                     stop_sender: Some(stop_sender),
+                    datagram: Some(datagram),
                 },
                 Self::create_framed(stream, udp_socket.local_addr().ok()),
             ))
@@ -79,7 +83,7 @@ impl KcpStream {
                 .ok_or_else(|| anyhow::anyhow!("Failed to get output receiver"))?,
         );
         let (stop_sender, stop_receiver) = oneshot::channel();
-        Self::kcp_io(udp_socket.clone(), input, output, stop_receiver).await;
+        let datagram = Self::kcp_io(udp_socket.clone(), input, output, stop_receiver).await;
 
         let conn_id = endpoint.connect(timeout, 0, 0, Bytes::new()).await?;
         if let Some(stream) = stream::KcpStream::new(&endpoint, conn_id) {
@@ -87,6 +91,7 @@ impl KcpStream {
                 Self {
                     _endpoint: endpoint,
                     stop_sender: Some(stop_sender),
+                    datagram: Some(datagram),
                 },
                 Self::create_framed(stream, udp_socket.local_addr().ok()),
             ))
@@ -95,13 +100,20 @@ impl KcpStream {
         }
     }
 
+    /// The datagrams sent on the socket next to the KCP packets, see `video_datagram`.
+    pub fn take_datagram(&mut self) -> Option<DatagramChannel> {
+        self.datagram.take()
+    }
+
     async fn kcp_io(
         udp_socket: Arc<UdpSocket>,
         input: mpsc::Sender<KcpPacket>,
         mut output: mpsc::Receiver<KcpPacket>,
         mut stop_receiver: oneshot::Receiver<()>,
-    ) {
+    ) -> DatagramChannel {
         let udp = udp_socket.clone();
+        let (tx_datagram, mut rx_datagram_out) = mpsc::unbounded_channel::<Bytes>();
+        let (tx_datagram_in, rx_datagram) = video_datagram::received_channel();
         tokio::spawn(async move {
             let mut buf = vec![0; 1500];
             loop {
@@ -116,9 +128,20 @@ impl KcpStream {
                             break;
                         }
                     }
+                    Some(data) = rx_datagram_out.recv() => {
+                        // Unreliable, a failed datagram is not an error of the stream.
+                        if let Err(e) = udp.send(&data).await {
+                            log::trace!("KCP datagram send error: {:?}", e);
+                        }
+                    }
                     result = udp.recv_from(&mut buf) => {
                         match result {
                             Ok((size, _)) => {
+                                if video_datagram::is_datagram(&buf[..size]) {
+                                    // Unreliable too, dropped if they are not handled in time.
+                                    tx_datagram_in.try_send(Bytes::copy_from_slice(&buf[..size])).ok();
+                                    continue;
+                                }
                                 if size < std::mem::size_of::<KcpPacketHeader>() {
                                     continue;
                                 }
@@ -139,6 +162,10 @@ impl KcpStream {
                 }
             }
         });
+        DatagramChannel {
+            tx: tx_datagram,
+            rx: rx_datagram,
+        }
     }
 }
 
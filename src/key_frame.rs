@@ -0,0 +1,42 @@
+//! Key frames requested by the client, for one display.
+//!
+//! A decoder which lost frames or was reset needs a key frame, which the encoder of the host makes
+//! without being recreated, cheaper than a refresh of the video. A host with
+//! "support_key_frame_request" in its platform additions takes a `Request` of the reserved `ID`
+//! for a display the connection subscribes to. The encoder is shared by the viewers of the
+//! display, they all get the key frame, the other displays do not.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "key-frame";
+pub const PLATFORM_ADDITION_KEY: &str = "support_key_frame_request";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub display: i32,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_frame_request() {
+        let msg = Request { display: 1 }.to_message();
+        let content = &msg.misc().plugin_request().content;
+        assert_eq!(Request::parse(content), Some(Request { display: 1 }));
+        assert_eq!(Request::parse(b"{}"), None);
+    }
+}
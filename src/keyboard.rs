@@ -1,6 +1,6 @@
 #[cfg(feature = "flutter")]
 use crate::flutter;
-#[cfg(not(any(feature = "flutter", feature = "cli")))]
+#[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
 use crate::ui::CUR_SESSION;
 use crate::ui_session_interface::{InvokeUiSession, Session};
 use crate::{client::get_key_state, common::GrabState};
@@ -10,7 +10,7 @@ use rdev::KeyCode;
 use rdev::{Event, EventType, Key};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     sync::{Arc, Mutex},
 };
 
@@ -65,6 +65,8 @@ lazy_static::lazy_static! {
         m.insert(Key::MetaRight, false);
         Mutex::new(m)
     };
+    // The keys whose press was kept local by `hotkey_passthrough`, so is their release.
+    static ref LOCAL_KEYS: Mutex<HashSet<Key>> = Default::default();
 }
 
 pub mod client {
@@ -97,6 +99,7 @@ pub mod client {
                 release_remote_keys(keyboard_mode);
 
                 KEYBOARD_HOOKED.swap(false, Ordering::SeqCst);
+                LOCAL_KEYS.lock().unwrap().clear();
             }
             GrabState::Exit => {}
         }
@@ -221,7 +224,7 @@ pub mod client {
 static mut IS_LEFT_OPTION_DOWN: bool = false;
 
 fn get_keyboard_mode() -> String {
-    #[cfg(not(any(feature = "flutter", feature = "cli")))]
+    #[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
     if let Some(session) = CUR_SESSION.lock().unwrap().as_ref() {
         return session.get_keyboard_mode();
     }
@@ -327,6 +330,23 @@ fn should_block_relative_mouse_shortcut(key: Key, is_press: bool) -> bool {
     false
 }
 
+/// Whether the key is kept on this computer instead of sent to the remote.
+fn is_local_hotkey(key: Key, is_press: bool) -> bool {
+    if !is_press {
+        return LOCAL_KEYS.lock().unwrap().remove(&key);
+    }
+    if is_modifier(&key) {
+        return false;
+    }
+    let chord = crate::hotkey_passthrough::Chord::pressed(key, &MODIFIERS_STATE.lock().unwrap());
+    if crate::hotkey_passthrough::is_local(&chord) {
+        log::debug!("Hotkey {} is kept local", chord);
+        LOCAL_KEYS.lock().unwrap().insert(key);
+        return true;
+    }
+    false
+}
+
 fn start_grab_loop() {
     std::env::set_var("KEYBOARD_ONLY", "y");
     std::thread::spawn(move || {
@@ -344,6 +364,10 @@ fn start_grab_loop() {
                 return None;
             }
 
+            if KEYBOARD_HOOKED.load(Ordering::SeqCst) && is_local_hotkey(key, is_press) {
+                return Some(event);
+            }
+
             let res = if KEYBOARD_HOOKED.load(Ordering::SeqCst) {
                 client::process_event(&get_keyboard_mode(), &event, None);
                 if is_press {
@@ -641,7 +665,7 @@ pub fn event_to_key_events(
 }
 
 pub fn send_key_event(key_event: &KeyEvent) {
-    #[cfg(not(any(feature = "flutter", feature = "cli")))]
+    #[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
     if let Some(session) = CUR_SESSION.lock().unwrap().as_ref() {
         session.send_key_event(key_event);
     }
@@ -653,7 +677,7 @@ pub fn send_key_event(key_event: &KeyEvent) {
 }
 
 pub fn get_peer_platform() -> String {
-    #[cfg(not(any(feature = "flutter", feature = "cli")))]
+    #[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
     if let Some(session) = CUR_SESSION.lock().unwrap().as_ref() {
         return session.peer_platform();
     }
@@ -0,0 +1,150 @@
+//! Keyboard layout of the host for the session.
+//!
+//! In translate mode the characters of the viewer are typed with the layout of the host, so the two
+//! must agree. The host reports its active layout in the "keyboard_layout" of the platform
+//! additions. The client sends a `Request`, with a layout to select or without one to query, and
+//! the host answers with its layouts and the active one. The layout of the host before the first
+//! switch is selected again when the connection which made it closes.
+//!
+//! The client saves the pick in the `PeerConfig` options under `PEER_OPTION`, to select it again
+//! on the next connection. `SYNC` selects the layout of the client, in translate mode only.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::sync::Mutex;
+
+pub const ID: &str = "keyboard-layout";
+pub const PEER_OPTION: &str = "keyboard-layout";
+pub const PLATFORM_ADDITION_KEY: &str = "keyboard_layout";
+pub const SYNC: &str = "sync";
+
+lazy_static::lazy_static! {
+    // The connection which switched the layout, and the layout to restore.
+    static ref SWITCHED: Mutex<Option<(i32, String)>> = Default::default();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    /// The id of the layout, e.g. "com.apple.keylayout.German".
+    #[serde(default)]
+    pub select: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Layout {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub layouts: Vec<Layout>,
+    pub current: String,
+    #[serde(default)]
+    pub error: String,
+}
+
+pub fn make_request(select: Option<String>) -> Message {
+    plugin_request::make(ID, &Request { select })
+}
+
+/// The layout to ask the host for, from the saved pick of the peer.
+pub fn resolve(option: &str, translate_mode: bool) -> Option<String> {
+    match option {
+        "" => None,
+        SYNC if translate_mode => crate::platform::get_keyboard_layout(),
+        SYNC => None,
+        id => Some(id.to_owned()),
+    }
+}
+
+pub fn current() -> String {
+    crate::platform::get_keyboard_layout().unwrap_or_default()
+}
+
+fn select(conn_id: i32, id: &str) -> Result<(), String> {
+    let previous = current();
+    if previous == id {
+        return Ok(());
+    }
+    crate::platform::select_keyboard_layout(id).map_err(|e| e.to_string())?;
+    let mut switched = SWITCHED.lock().unwrap();
+    if switched.is_none() {
+        *switched = Some((conn_id, previous));
+    }
+    log::info!(
+        "Keyboard layout switched to {} by connection {}",
+        id,
+        conn_id
+    );
+    Ok(())
+}
+
+/// Apply the request of the connection on the host, the answer has the layouts and the error.
+pub fn handle_request(conn_id: i32, content: &[u8]) -> Message {
+    let mut res = Response::default();
+    match serde_json::from_slice::<Request>(content) {
+        Ok(Request { select: Some(id) }) => {
+            if let Err(e) = select(conn_id, &id) {
+                res.error = e;
+            }
+        }
+        Ok(Request { select: None }) => {}
+        Err(e) => res.error = e.to_string(),
+    }
+    res.layouts = crate::platform::get_keyboard_layouts()
+        .into_iter()
+        .map(|(id, name)| Layout { id, name })
+        .collect();
+    res.current = current();
+    plugin_request::make(ID, &res)
+}
+
+/// Select the layout of the host again, if the connection switched it.
+pub fn restore(conn_id: i32) {
+    let mut switched = SWITCHED.lock().unwrap();
+    if !matches!(switched.as_ref(), Some((id, _)) if *id == conn_id) {
+        return;
+    }
+    if let Some((_, previous)) = switched.take() {
+        if !previous.is_empty() && previous != current() {
+            log::info!("Keyboard layout restored to {}", previous);
+            hbb_common::allow_err!(crate::platform::select_keyboard_layout(&previous));
+        }
+    }
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyboard_layout_request() {
+        assert_eq!(resolve("", true), None);
+        assert_eq!(resolve(SYNC, false), None);
+        assert_eq!(
+            resolve("com.apple.keylayout.German", false).as_deref(),
+            Some("com.apple.keylayout.German")
+        );
+        let req: Request = serde_json::from_slice(b"{}").unwrap();
+        assert_eq!(req.select, None);
+        let res = parse_response(
+            br#"{"layouts":[{"id":"com.apple.keylayout.US","name":"U.S."}],"current":"com.apple.keylayout.US"}"#,
+        )
+        .unwrap();
+        assert_eq!(res.layouts[0].name, "U.S.");
+        assert!(res.error.is_empty());
+
+        // Only the connection which switched the layout restores it.
+        *SWITCHED.lock().unwrap() = Some((1, String::new()));
+        restore(2);
+        assert!(SWITCHED.lock().unwrap().is_some());
+        restore(1);
+        assert!(SWITCHED.lock().unwrap().is_none());
+    }
+}
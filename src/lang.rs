@@ -103,28 +103,7 @@ pub fn translate(name: String) -> String {
 }
 
 pub fn translate_locale(name: String, locale: &str) -> String {
-    let locale = locale.to_lowercase();
-    let mut lang = hbb_common::config::LocalConfig::get_option("lang").to_lowercase();
-    if lang.is_empty() {
-        // zh_CN on Linux, zh-Hans-CN on mac, zh_CN_#Hans on Android
-        if locale.starts_with("zh") {
-            lang = (if locale.contains("tw") {
-                "zh-tw"
-            } else {
-                "zh-cn"
-            })
-            .to_owned();
-        }
-    }
-    if lang.is_empty() {
-        lang = locale
-            .split("-")
-            .next()
-            .map(|x| x.split("_").next().unwrap_or_default())
-            .unwrap_or_default()
-            .to_owned();
-    }
-    let lang = lang.to_lowercase();
+    let lang = lang_of(locale);
     let m = match lang.as_str() {
         "fr" => fr::T.deref(),
         "zh-cn" => cn::T.deref(),
@@ -224,6 +203,36 @@ pub fn translate_locale(name: String, locale: &str) -> String {
     replace(&name.as_str())
 }
 
+/// The language of the UI, the one set in the settings, else the one of the system.
+pub fn current_lang() -> String {
+    lang_of(&sys_locale::get_locale().unwrap_or_default())
+}
+
+fn lang_of(locale: &str) -> String {
+    let locale = locale.to_lowercase();
+    let mut lang = hbb_common::config::LocalConfig::get_option("lang").to_lowercase();
+    if lang.is_empty() {
+        // zh_CN on Linux, zh-Hans-CN on mac, zh_CN_#Hans on Android
+        if locale.starts_with("zh") {
+            lang = (if locale.contains("tw") {
+                "zh-tw"
+            } else {
+                "zh-cn"
+            })
+            .to_owned();
+        }
+    }
+    if lang.is_empty() {
+        lang = locale
+            .split("-")
+            .next()
+            .map(|x| x.split("_").next().unwrap_or_default())
+            .unwrap_or_default()
+            .to_owned();
+    }
+    lang.to_lowercase()
+}
+
 // Matching pattern is {}
 // Write {value} in the UI and {} in the translation file
 //
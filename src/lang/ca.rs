@@ -739,5 +739,182 @@ pub static ref T: std::collections::HashMap<&'static str, &'static str> =
         ("keep-awake-during-outgoing-sessions-label", ""),
         ("keep-awake-during-incoming-sessions-label", ""),
         ("Continue with {}", "Continua amb {}"),
+        ("read-only-file-transfer-tip", ""),
+        ("Busy", ""),
+        ("Maintenance", ""),
+        ("host-busy-{}-tip", ""),
+        ("Host is busy", ""),
+        ("Notify me when available", ""),
+        ("host-free-{}-tip", ""),
+        ("a11y-connected-{}-tip", ""),
+        ("a11y-access-denied-tip", ""),
+        ("a11y-permission-denied-{}-tip", ""),
+        ("a11y-file-job-done-tip", ""),
+        ("a11y-file-job-errors-{}-tip", ""),
+        ("Smooth video playback", ""),
+        ("frame_pacing_tip", ""),
+        ("Lower resolution on slow networks", ""),
+        ("Skip lost video frames", ""),
+        ("video_datagram_tip", ""),
+        ("Separate stream for slow viewers", ""),
+        ("Do not allow viewers to record or take screenshots", ""),
+        ("screenshot-not-allowed-tip", ""),
+        ("peer-recording-tip", ""),
+        ("host-recording-tip", ""),
+        ("Send the microphone with the camera", ""),
+        ("Start talk-back", ""),
+        ("Stop talk-back", ""),
+        ("Play the microphone of the viewers", ""),
+        ("Lower the capture rate of a static screen", ""),
+        ("Share a single window", ""),
+        ("Regions hidden from the viewers", ""),
+        ("Show a watermark on the shared screen", ""),
+        ("Text of the watermark", ""),
+        ("AV1 screen content tools", ""),
+        ("AV1 speed preset", ""),
+        ("AV1 tile columns (log2)", ""),
+        ("AV1 tile rows (log2)", ""),
+        ("Recording container", ""),
+        ("Maximum disk usage of recordings (MB)", ""),
+        ("Maximum age of recordings (days)", ""),
+        ("Split recordings every N minutes", ""),
+        ("Do not disturb", ""),
+        ("Maximum concurrent sessions", ""),
+        ("Availability status", ""),
+        ("Availability note", ""),
+        ("Folders allowed for file transfer", ""),
+        ("Read-only file transfer", ""),
+        ("Clipboard redaction rules", ""),
+        ("Hide tray icon", ""),
+        ("Audio device", ""),
+        ("Loopback", ""),
+        ("remote-audio-device-tip", ""),
+        ("onboarding-step-not-done-tip", ""),
+        ("Open workspace", ""),
+        ("Close workspace", ""),
+        ("Maximum input events per second", ""),
+        ("Input events per second reported as a flood", ""),
+        ("Keyboard layout", ""),
+        ("Same as local", ""),
+        ("remote-keyboard-layout-tip", ""),
+        ("Display name of this device", ""),
+        ("Avatar of this device", ""),
+        ("Upload the display name and avatar to the server", ""),
+        ("Storage of the passwords", ""),
+        ("Direct access port range", ""),
+        ("Punch hole port range", ""),
+        ("Map the port on the router", ""),
+        ("Input permission profiles", ""),
+        ("The remote display is asleep", ""),
+        ("Wake up", ""),
+        ("Wake the display when it sleeps", ""),
+        ("Hotkeys kept on this computer", ""),
+        ("Measure the bandwidth when the session starts", ""),
+        ("Brightness up", ""),
+        ("Brightness down", ""),
+        ("Play/Pause", ""),
+        ("Next track", ""),
+        ("Previous track", ""),
+        ("Mission Control", ""),
+        ("Launchpad", ""),
+        ("Special keys", ""),
+        ("Mouse speed", ""),
+        ("Mouse acceleration", ""),
+        ("Scroll settings", ""),
+        ("Invert scrolling", ""),
+        ("Scroll speed", ""),
+        ("Pixels", ""),
+        ("Lines", ""),
+        ("Scroll momentum", ""),
+        ("Authentication provider", ""),
+        ("LDAP server", ""),
+        ("LDAP bind DN", ""),
+        ("OIDC device authorization endpoint", ""),
+        ("OIDC token endpoint", ""),
+        ("OIDC client ID", ""),
+        ("Authentication", ""),
+        ("Continue", ""),
+        ("Failed to reach the authentication provider", ""),
+        ("auth-provider-failed-tip", ""),
+        ("auth-provider-device-code-tip", ""),
+        ("auth-provider-credentials-tip", ""),
+        ("Maximum size of clipboard images (MB)", ""),
+        ("Permission request", ""),
+        ("Request permission", ""),
+        ("permission-request-tip", ""),
+        ("permission-request-granted-tip", ""),
+        ("permission-request-denied-tip", ""),
+        ("Allow", ""),
+        ("Deny", ""),
+        ("Plain text clipboard only", ""),
+        ("Clipboard history", ""),
+        ("clipboard-history-empty-tip", ""),
+        ("Size of the clipboard history", ""),
+        ("duration-days", ""),
+        ("duration-hours", ""),
+        ("duration-minutes", ""),
+        ("duration-seconds", ""),
+        ("Clipboard direction", ""),
+        ("Receiving clipboard", ""),
+        ("Maximum size of the clipboard (MB)", ""),
+        ("Pasting files", ""),
+        ("Failed to paste the files", ""),
+        ("Edit config files", ""),
+        ("Environment variables", ""),
+        ("New file", ""),
+        ("Saved", ""),
+        ("Config files editable by the peers", ""),
+        ("Environment variables readable by the peers", ""),
+        ("Transfer speed limit", ""),
+        ("Only transfer when idle", ""),
+        ("0 for no limit", ""),
+        ("Only send the changed files", ""),
+        ("Compare the file contents", ""),
+        ("Sending the files to", ""),
+        ("No folder under the cursor", ""),
+        ("Unavailable features", ""),
+        ("feature-unavailable-platform-tip", ""),
+        ("feature-unavailable-peer-tip", ""),
+        ("Delete permanently", ""),
+        ("file-trash-tip", ""),
+        ("Undo", ""),
+        ("Restored", ""),
+        ("privacy_mode_impl_macos_tip", ""),
+        ("privacy_mode_impl_macos_overlay_tip", ""),
+        ("Keep the host awake", ""),
+        ("wake-policy-host", ""),
+        ("wake-policy-system", ""),
+        ("wake-policy-display", ""),
+        ("wake-policy-none", ""),
+        ("Privacy mode displays", ""),
+        ("Record a timeline of the input of the sessions", ""),
+        ("Maximum age of the input timeline (days)", ""),
+        ("View only now", ""),
+        ("Add a virtual display when no monitor is attached", ""),
+        ("Modes of the virtual display", ""),
+        ("Update manifest URL", ""),
+        ("Public key of the update manifest", ""),
+        ("Interrupted file transfers", ""),
+        ("interrupted-file-transfers-tip", ""),
+        ("Discard", ""),
+        ("Allowed targets of the TCP tunnels", ""),
+        ("TCP tunnels", ""),
+        ("OIDC issuer", ""),
+        ("Allowed users and groups", ""),
+        ("Virtual display modes", ""),
+        ("virtual-display-modes-tip", ""),
+        ("Annotate", ""),
+        ("Laser pointer", ""),
+        ("Undo annotation", ""),
+        ("Clear annotations", ""),
+        ("Forward game controllers", ""),
+        ("Camera controls", ""),
+        ("Zoom", ""),
+        ("Pan", ""),
+        ("Tilt", ""),
+        ("Focus", ""),
+        ("Exposure", ""),
+        ("White balance", ""),
+        ("No user is allowed by the authentication provider", ""),
     ].iter().cloned().collect();
 }
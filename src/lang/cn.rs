@@ -739,5 +739,182 @@ pub static ref T: std::collections::HashMap<&'static str, &'static str> =
         ("keep-awake-during-outgoing-sessions-label", ""),
         ("keep-awake-during-incoming-sessions-label", ""),
         ("Continue with {}", "使用 {} 登录"),
+        ("read-only-file-transfer-tip", "被控端仅允许浏览和下载文件"),
+        ("Busy", "忙碌"),
+        ("Maintenance", "维护中"),
+        ("host-busy-{}-tip", "被控端正忙，您在队列中排第 {} 位。"),
+        ("Host is busy", "被控端正忙"),
+        ("Notify me when available", "空闲时通知我"),
+        ("host-free-{}-tip", "{} 现在空闲，是否连接？"),
+        ("a11y-connected-{}-tip", "已连接到 {}。"),
+        ("a11y-access-denied-tip", "被控端拒绝了访问。"),
+        ("a11y-permission-denied-{}-tip", "被控端关闭了 {} 权限。"),
+        ("a11y-file-job-done-tip", "文件传输已完成。"),
+        ("a11y-file-job-errors-{}-tip", "文件传输已完成，{} 个错误。"),
+        ("Smooth video playback", "平滑视频播放"),
+        ("frame_pacing_tip", "按被控端的采集节奏显示画面，可消除网络抖动，但会略微增加延迟。"),
+        ("Lower resolution on slow networks", "网络较慢时降低分辨率"),
+        ("Skip lost video frames", "跳过丢失的视频帧"),
+        ("video_datagram_tip", "在 UDP 直连时，丢失的视频帧将被跳过而不是重传，可避免丢包网络下的卡顿。控制和输入仍然可靠传输。"),
+        ("Separate stream for slow viewers", "为较慢的查看者单独编码视频流"),
+        ("Do not allow viewers to record or take screenshots", "不允许查看者录制或截图"),
+        ("screenshot-not-allowed-tip", "远端不允许对此会话截图。"),
+        ("peer-recording-tip", "对方正在录制"),
+        ("host-recording-tip", "远端正在录制此会话"),
+        ("Send the microphone with the camera", "随摄像头发送麦克风声音"),
+        ("Start talk-back", "开始对讲"),
+        ("Stop talk-back", "停止对讲"),
+        ("Play the microphone of the viewers", "播放查看者的麦克风声音"),
+        ("Lower the capture rate of a static screen", "静止画面时降低采集帧率"),
+        ("Share a single window", "仅共享单个窗口"),
+        ("Regions hidden from the viewers", "对访客隐藏的区域"),
+        ("Show a watermark on the shared screen", "在共享画面上显示水印"),
+        ("Text of the watermark", "水印文字"),
+        ("AV1 screen content tools", "AV1 屏幕内容编码工具"),
+        ("AV1 speed preset", "AV1 速度预设"),
+        ("AV1 tile columns (log2)", "AV1 分块列数 (log2)"),
+        ("AV1 tile rows (log2)", "AV1 分块行数 (log2)"),
+        ("Recording container", "录屏文件格式"),
+        ("Maximum disk usage of recordings (MB)", "录屏最大占用磁盘空间 (MB)"),
+        ("Maximum age of recordings (days)", "录屏最长保留天数"),
+        ("Split recordings every N minutes", "每 N 分钟分割录屏文件"),
+        ("Do not disturb", "勿扰模式"),
+        ("Maximum concurrent sessions", "最大并发会话数"),
+        ("Availability status", "在线状态"),
+        ("Availability note", "状态备注"),
+        ("Folders allowed for file transfer", "允许文件传输的文件夹"),
+        ("Read-only file transfer", "只读文件传输"),
+        ("Clipboard redaction rules", "剪贴板脱敏规则"),
+        ("Hide tray icon", "隐藏托盘图标"),
+        ("Audio device", "音频设备"),
+        ("Loopback", "内录"),
+        ("remote-audio-device-tip", "选择要收听的远端音频设备。系统支持时，输出设备会以内录方式采集。"),
+        ("onboarding-step-not-done-tip", "此步骤尚未完成，可以跳过。"),
+        ("Open workspace", "打开工作区"),
+        ("Close workspace", "关闭工作区"),
+        ("Maximum input events per second", "每秒最大输入事件数"),
+        ("Input events per second reported as a flood", "视为输入洪泛的每秒事件数"),
+        ("Keyboard layout", "键盘布局"),
+        ("Same as local", "与本地相同"),
+        ("remote-keyboard-layout-tip", "选择本次会话中远端使用的键盘布局，会话结束后会恢复原布局。"),
+        ("Display name of this device", "本设备的显示名称"),
+        ("Avatar of this device", "本设备的头像"),
+        ("Upload the display name and avatar to the server", "将显示名称和头像上传到服务器"),
+        ("Storage of the passwords", "密码的存储位置"),
+        ("Direct access port range", "直接访问端口范围"),
+        ("Punch hole port range", "打洞端口范围"),
+        ("Map the port on the router", "在路由器上映射端口"),
+        ("Input permission profiles", "输入权限配置"),
+        ("The remote display is asleep", "远程显示器已休眠"),
+        ("Wake up", "唤醒"),
+        ("Wake the display when it sleeps", "显示器休眠时唤醒"),
+        ("Hotkeys kept on this computer", "保留在本机的快捷键"),
+        ("Measure the bandwidth when the session starts", "会话开始时测量带宽"),
+        ("Brightness up", "调高亮度"),
+        ("Brightness down", "调低亮度"),
+        ("Play/Pause", "播放/暂停"),
+        ("Next track", "下一曲"),
+        ("Previous track", "上一曲"),
+        ("Mission Control", "调度中心"),
+        ("Launchpad", "启动台"),
+        ("Special keys", "特殊按键"),
+        ("Mouse speed", "鼠标速度"),
+        ("Mouse acceleration", "鼠标加速"),
+        ("Scroll settings", "滚动设置"),
+        ("Invert scrolling", "反转滚动方向"),
+        ("Scroll speed", "滚动速度"),
+        ("Pixels", "像素"),
+        ("Lines", "行"),
+        ("Scroll momentum", "滚动惯性"),
+        ("Authentication provider", "认证服务"),
+        ("LDAP server", "LDAP 服务器"),
+        ("LDAP bind DN", "LDAP 绑定 DN"),
+        ("OIDC device authorization endpoint", "OIDC 设备授权端点"),
+        ("OIDC token endpoint", "OIDC 令牌端点"),
+        ("OIDC client ID", "OIDC 客户端 ID"),
+        ("Authentication", "身份验证"),
+        ("Continue", "继续"),
+        ("Failed to reach the authentication provider", "无法连接认证服务"),
+        ("auth-provider-failed-tip", "验证失败，请重试。"),
+        ("auth-provider-device-code-tip", "打开下面的页面，输入代码并登录，然后点击继续。"),
+        ("auth-provider-credentials-tip", "请使用您所在组织的账户登录。"),
+        ("Maximum size of clipboard images (MB)", "剪贴板图片的最大大小（MB）"),
+        ("Permission request", "权限请求"),
+        ("Request permission", "请求权限"),
+        ("permission-request-tip", "对方请求权限"),
+        ("permission-request-granted-tip", "对方已授予本次会话的权限。"),
+        ("permission-request-denied-tip", "对方拒绝了该权限请求。"),
+        ("Allow", "允许"),
+        ("Deny", "拒绝"),
+        ("Plain text clipboard only", "剪贴板仅纯文本"),
+        ("Clipboard history", "剪贴板历史"),
+        ("clipboard-history-empty-tip", "本机尚未复制任何内容。"),
+        ("Size of the clipboard history", "剪贴板历史条数"),
+        ("duration-days", "{} 天"),
+        ("duration-hours", "{} 小时"),
+        ("duration-minutes", "{} 分"),
+        ("duration-seconds", "{} 秒"),
+        ("Clipboard direction", "剪贴板同步方向"),
+        ("Receiving clipboard", "正在接收剪贴板"),
+        ("Maximum size of the clipboard (MB)", "剪贴板最大大小 (MB)"),
+        ("Pasting files", "正在粘贴文件"),
+        ("Failed to paste the files", "粘贴文件失败"),
+        ("Edit config files", "编辑配置文件"),
+        ("Environment variables", "环境变量"),
+        ("New file", "新文件"),
+        ("Saved", "已保存"),
+        ("Config files editable by the peers", "对方可编辑的配置文件"),
+        ("Environment variables readable by the peers", "对方可读取的环境变量"),
+        ("Transfer speed limit", "传输限速"),
+        ("Only transfer when idle", "仅在空闲时传输"),
+        ("0 for no limit", "0 表示不限速"),
+        ("Only send the changed files", "仅发送有改动的文件"),
+        ("Compare the file contents", "比较文件内容"),
+        ("Sending the files to", "正在发送文件到"),
+        ("No folder under the cursor", "光标下没有文件夹"),
+        ("Unavailable features", "不可用的功能"),
+        ("feature-unavailable-platform-tip", "远端的系统不支持此功能。"),
+        ("feature-unavailable-peer-tip", "远端未提供此功能，可能已被禁用或未包含在其版本中。"),
+        ("Delete permanently", "永久删除"),
+        ("file-trash-tip", "文件将被移到远端的废纸篓，可在传输列表中撤销。"),
+        ("Undo", "撤销"),
+        ("Restored", "已恢复"),
+        ("privacy_mode_impl_macos_tip", "模式 1"),
+        ("privacy_mode_impl_macos_overlay_tip", "模式 2"),
+        ("Keep the host awake", "保持被控端唤醒"),
+        ("wake-policy-host", "由被控端决定"),
+        ("wake-policy-system", "保持唤醒，允许屏幕保护"),
+        ("wake-policy-display", "保持唤醒且屏幕常亮"),
+        ("wake-policy-none", "允许休眠"),
+        ("Privacy mode displays", "隐私模式的显示器"),
+        ("Record a timeline of the input of the sessions", "记录会话的输入时间线"),
+        ("Maximum age of the input timeline (days)", "输入时间线的最长保留时间（天）"),
+        ("View only now", "立即设为仅查看"),
+        ("Add a virtual display when no monitor is attached", "未连接显示器时添加虚拟显示器"),
+        ("Modes of the virtual display", "虚拟显示器的显示模式"),
+        ("Update manifest URL", "更新清单地址"),
+        ("Public key of the update manifest", "更新清单公钥"),
+        ("Interrupted file transfers", "中断的文件传输"),
+        ("interrupted-file-transfers-tip", "服务在对方的这些文件传输过程中重启。继续传输，还是放弃它们及已接收的内容？"),
+        ("Discard", "放弃"),
+        ("Allowed targets of the TCP tunnels", "允许的 TCP 隧道目标"),
+        ("TCP tunnels", "TCP 隧道"),
+        ("OIDC issuer", "OIDC 颁发者"),
+        ("Allowed users and groups", "允许的用户和组"),
+        ("Virtual display modes", "虚拟显示器模式"),
+        ("virtual-display-modes-tip", "远端虚拟显示器的模式，以逗号分隔，格式为 宽x高@刷新率。使用第一个模式。"),
+        ("Annotate", "标注"),
+        ("Laser pointer", "激光笔"),
+        ("Undo annotation", "撤销标注"),
+        ("Clear annotations", "清除标注"),
+        ("Forward game controllers", "转发游戏手柄"),
+        ("Camera controls", "摄像头控制"),
+        ("Zoom", "缩放"),
+        ("Pan", "水平移动"),
+        ("Tilt", "垂直移动"),
+        ("Focus", "对焦"),
+        ("Exposure", "曝光"),
+        ("White balance", "白平衡"),
+        ("No user is allowed by the authentication provider", "认证服务未允许任何用户"),
     ].iter().cloned().collect();
 }
@@ -269,5 +269,43 @@ pub static ref T: std::collections::HashMap<&'static str, &'static str> =
         ("rel-mouse-permission-lost-tip", "Keyboard permission was revoked. Relative Mouse Mode has been disabled."),
         ("keep-awake-during-outgoing-sessions-label", "Keep screen awake during outgoing sessions"),
         ("keep-awake-during-incoming-sessions-label", "Keep screen awake during incoming sessions"),
+        ("read-only-file-transfer-tip", "The controlled side only allows browsing and downloading files."),
+        ("host-busy-{}-tip", "The remote host is busy, you are number {} in the queue."),
+        ("host-free-{}-tip", "{} is available now, do you want to connect?"),
+        ("a11y-connected-{}-tip", "Connected to {}."),
+        ("a11y-access-denied-tip", "Access denied by the remote host."),
+        ("a11y-permission-denied-{}-tip", "The remote host turned off the {} permission."),
+        ("a11y-file-job-done-tip", "File transfer finished."),
+        ("a11y-file-job-errors-{}-tip", "File transfer finished with {} errors."),
+        ("frame_pacing_tip", "Show remote frames at the rate they were captured, this smooths out network jitter at the cost of a little latency."),
+        ("video_datagram_tip", "On direct UDP connections, lost video frames are skipped instead of resent, which avoids freezes on lossy networks. Control and input are still reliable."),
+        ("screenshot-not-allowed-tip", "The remote side does not allow screenshots of this session."),
+        ("peer-recording-tip", "The peer is recording"),
+        ("host-recording-tip", "The remote side is recording this session"),
+        ("remote-audio-device-tip", "Choose the audio device of the remote machine to hear. Output devices are captured by loopback where the system supports it."),
+        ("onboarding-step-not-done-tip", "This step is not done yet, you can skip it."),
+        ("remote-keyboard-layout-tip", "Choose the keyboard layout of the remote machine for this session. It is switched back when the session ends."),
+        ("auth-provider-failed-tip", "The authentication failed, please try again."),
+        ("auth-provider-device-code-tip", "Open the page below, enter the code and sign in, then click Continue."),
+        ("auth-provider-credentials-tip", "Sign in with the account of your organization."),
+        ("permission-request-tip", "The remote user requests the permission"),
+        ("permission-request-granted-tip", "The remote user granted the permission for this session."),
+        ("permission-request-denied-tip", "The remote user denied the permission."),
+        ("clipboard-history-empty-tip", "Nothing was copied on this computer yet."),
+        ("duration-days", "{} d"),
+        ("duration-hours", "{} h"),
+        ("duration-minutes", "{} min"),
+        ("duration-seconds", "{} s"),
+        ("feature-unavailable-platform-tip", "The remote side does not support this feature on its system."),
+        ("feature-unavailable-peer-tip", "The remote side does not provide this feature, it may be disabled or not included in its build."),
+        ("file-trash-tip", "The files are moved to the trash of the remote side, you can undo it in the transfer list."),
+        ("privacy_mode_impl_macos_tip", "Mode 1"),
+        ("privacy_mode_impl_macos_overlay_tip", "Mode 2"),
+        ("wake-policy-host", "As the remote device decides"),
+        ("wake-policy-system", "Keep it awake, allow the screensaver"),
+        ("wake-policy-display", "Keep it awake with the screen on"),
+        ("wake-policy-none", "Let it sleep"),
+        ("interrupted-file-transfers-tip", "The service restarted during these file transfers of the remote side. Resume them, or discard them and what was received?"),
+        ("virtual-display-modes-tip", "The modes of the virtual display of the remote, separated by commas, as width x height @ refresh rate. The first one is used."),
     ].iter().cloned().collect();
 }
@@ -15,7 +15,7 @@ pub use self::rendezvous_mediator::*;
 /// cbindgen:ignore
 pub mod common;
 pub mod ipc;
-#[cfg(not(any(feature = "cli", feature = "flutter")))]
+#[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
 pub mod ui;
 mod version;
 pub use version::*;
@@ -26,6 +26,10 @@ pub mod flutter;
 #[cfg(feature = "flutter")]
 pub mod flutter_ffi;
 use common::*;
+#[cfg(all(feature = "headless-agent", any(feature = "flutter", feature = "ui")))]
+compile_error!(
+    "The feature \"headless-agent\" has no UI, it can not be built with \"flutter\" or \"ui\"."
+);
 mod auth_2fa;
 #[cfg(feature = "cli")]
 pub mod cli;
@@ -39,10 +43,16 @@ mod port_forward;
 #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
 pub mod plugin;
 
+mod roundtrip;
+mod service_status;
+#[cfg(not(feature = "cli"))]
+mod bulk;
+#[cfg(feature = "ui")]
 mod tray;
 
 mod whiteboard;
 
+mod update_manifest;
 mod updater;
 
 mod ui_cm_interface;
@@ -56,3 +66,85 @@ pub mod clipboard_file;
 pub mod privacy_mode;
 
 mod kcp_stream;
+
+mod video_datagram;
+
+mod camera_controls;
+
+mod toolbar_actions;
+
+mod audio_devices;
+
+mod onboarding;
+
+mod gamepad;
+
+mod stylus;
+
+mod mux;
+
+mod touch_gesture;
+
+mod input_macro;
+
+mod keyboard_layout;
+
+mod peer_identity;
+
+mod secret_store;
+
+mod port_range;
+
+mod port_mapping;
+
+mod display_power;
+
+mod hotkey_passthrough;
+
+mod bandwidth_probe;
+
+mod display_delta;
+
+mod scaled_frames;
+
+mod key_frame;
+
+mod monitor_modes;
+
+mod special_keys;
+
+mod display_id;
+
+mod mouse_curve;
+
+mod scroll_transform;
+
+mod permission_request;
+
+mod plugin_request;
+
+mod peer_features;
+
+pub mod locale_format;
+
+mod config_edit;
+mod transfer_verify;
+mod folder_sync;
+mod transfer_throttle;
+mod transfer_compress;
+mod transfer_journal;
+mod file_drop;
+mod file_trash;
+mod qos_history;
+mod window_visibility;
+mod wake_policy;
+mod privacy_displays;
+
+#[cfg(feature = "flutter")]
+mod workspace;
+
+#[cfg(feature = "flutter")]
+mod tiling;
+
+#[cfg(feature = "headless-agent")]
+mod headless;
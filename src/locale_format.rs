@@ -0,0 +1,130 @@
+//! Sizes, speeds, durations and dates formatted for the language of the UI.
+//!
+//! The language is the one of `lang::current_lang`, so the Flutter and the Sciter UIs show the
+//! same "1,50 MB" or "17.10.2026 14:03:05" instead of formatting them each their own way. The
+//! units of the durations are translated, the others are the same in every language.
+
+use crate::lang::current_lang;
+use chrono::{Local, TimeZone};
+
+const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+
+// The languages which write "1,5" rather than "1.5".
+const DECIMAL_COMMA: &[&str] = &[
+    "be", "bg", "br", "ca", "cs", "da", "de", "el", "eo", "es", "et", "eu", "fi", "fr", "ge", "hr",
+    "hu", "id", "it", "kz", "lt", "lv", "nb", "nl", "pl", "pt", "ro", "ru", "sc", "sk", "sl", "sq",
+    "sr", "sv", "tr", "uk", "vi",
+];
+
+fn date_pattern(lang: &str) -> &'static str {
+    match lang {
+        "be" | "bg" | "cs" | "da" | "de" | "et" | "fi" | "ge" | "hr" | "kz" | "lv" | "nb"
+        | "pl" | "ro" | "ru" | "sk" | "sl" | "sr" | "tr" | "uk" => "%d.%m.%Y",
+        "ar" | "br" | "ca" | "el" | "es" | "eu" | "fr" | "he" | "id" | "it" | "pt" | "sc"
+        | "sq" | "ta" | "th" | "vi" => "%d/%m/%Y",
+        "nl" => "%d-%m-%Y",
+        "en" => "%m/%d/%Y",
+        _ => "%Y-%m-%d",
+    }
+}
+
+fn number(value: f64, decimals: usize, lang: &str) -> String {
+    let s = format!("{:.*}", decimals, value);
+    if DECIMAL_COMMA.contains(&lang) {
+        s.replace('.', ",")
+    } else {
+        s
+    }
+}
+
+fn bytes_(size: f64, lang: &str) -> String {
+    let mut size = size.max(0.);
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+    let decimals = if unit == 0 { 0 } else { 2 };
+    format!("{} {}", number(size, decimals, lang), UNITS[unit])
+}
+
+/// A size in bytes, e.g. "1.50 MB".
+pub fn bytes(size: f64) -> String {
+    bytes_(size, &current_lang())
+}
+
+/// A speed in bytes per second, e.g. "1.50 MB/s".
+pub fn speed(bytes_per_second: f64) -> String {
+    format!("{}/s", bytes(bytes_per_second))
+}
+
+// The largest two units, e.g. "1 h 5 min", the seconds under one minute only.
+fn duration_(seconds: u64, unit: impl Fn(&str, u64) -> String) -> String {
+    let parts = [
+        ("duration-days", seconds / 86400),
+        ("duration-hours", seconds % 86400 / 3600),
+        ("duration-minutes", seconds % 3600 / 60),
+        ("duration-seconds", seconds % 60),
+    ];
+    let first = parts
+        .iter()
+        .position(|(_, n)| *n > 0)
+        .unwrap_or(parts.len() - 1);
+    parts[first..]
+        .iter()
+        .take(2)
+        .enumerate()
+        .filter(|(i, (_, n))| *i == 0 || *n > 0)
+        .map(|(_, (key, n))| unit(key, *n))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// A duration, e.g. "1 h 5 min", with the units translated.
+pub fn duration(seconds: u64) -> String {
+    duration_(seconds, |key, n| {
+        crate::lang::translate(key.to_owned()).replace("{}", &n.to_string())
+    })
+}
+
+fn date_time_(ms: i64, lang: &str) -> String {
+    match Local.timestamp_millis_opt(ms).single() {
+        Some(t) => t
+            .format(&format!("{} %H:%M:%S", date_pattern(lang)))
+            .to_string(),
+        None => String::new(),
+    }
+}
+
+/// A local date and time of the milliseconds since the epoch, e.g. "10/17/2026 14:03:05".
+pub fn date_time(ms: i64) -> String {
+    date_time_(ms, &current_lang())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locale_format() {
+        assert_eq!(bytes_(512., "en"), "512 B");
+        assert_eq!(bytes_(1536., "en"), "1.50 KB");
+        assert_eq!(bytes_(1536. * 1024., "de"), "1,50 MB");
+        assert_eq!(bytes_(-1., "en"), "0 B");
+
+        let unit = |key: &str, n: u64| format!("{}{}", n, &key[9..10]);
+        assert_eq!(duration_(0, unit), "0s");
+        assert_eq!(duration_(59, unit), "59s");
+        assert_eq!(duration_(3600 + 5 * 60 + 7, unit), "1h 5m");
+        assert_eq!(duration_(3600 + 7, unit), "1h");
+        assert_eq!(duration_(2 * 86400 + 3 * 3600, unit), "2d 3h");
+
+        let ms = Local
+            .with_ymd_and_hms(2026, 10, 17, 14, 3, 5)
+            .unwrap()
+            .timestamp_millis();
+        assert_eq!(date_time_(ms, "de"), "17.10.2026 14:03:05");
+        assert_eq!(date_time_(ms, "en"), "10/17/2026 14:03:05");
+        assert_eq!(date_time_(ms, "zh-cn"), "2026-10-17 14:03:05");
+    }
+}
@@ -11,7 +11,7 @@ fn main() {
     common::global_clean();
 }
 
-#[cfg(not(any(feature = "cli", feature = "flutter")))]
+#[cfg(all(feature = "ui", not(any(feature = "cli", feature = "flutter"))))]
 fn main() {
     if let Some(args) = crate::core_main::core_main().as_mut() {
         ui::start(args);
@@ -19,6 +19,14 @@ fn main() {
     common::global_clean();
 }
 
+#[cfg(feature = "headless-agent")]
+fn main() {
+    if let Some(args) = crate::core_main::core_main() {
+        eprintln!("Not supported without the UI: {}", args.join(" "));
+    }
+    common::global_clean();
+}
+
 #[cfg(feature = "cli")]
 fn main() {
     if !common::global_init() {
@@ -0,0 +1,78 @@
+//! The modes of the virtual display of the host, set by the client.
+//!
+//! The virtual display of a host without a monitor, see `platform::virtual_display`, has the
+//! modes of an option the client can not reach. A host with "support_monitor_modes" in its
+//! platform additions takes a `Request` from the client, with a list of modes as
+//! "3440x1440@100,1920x1080" to set or without one to query, and answers with the modes it has.
+//! They are saved in the config of the host, the first is the mode of the display.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "monitor-modes";
+pub const PLATFORM_ADDITION_KEY: &str = "support_monitor_modes";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    #[serde(default)]
+    pub modes: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub modes: String,
+    /// The modes of the request were set.
+    #[serde(default)]
+    pub set: bool,
+    #[serde(default)]
+    pub error: String,
+}
+
+pub fn make_request(modes: Option<String>) -> Message {
+    plugin_request::make(ID, &Request { modes })
+}
+
+/// Apply the request of the connection on the host, the answer has the modes and the error.
+pub fn handle_request(conn_id: i32, content: &[u8]) -> Message {
+    use crate::platform::virtual_display;
+    let mut res = Response::default();
+    match serde_json::from_slice::<Request>(content) {
+        Ok(Request { modes: Some(modes) }) => {
+            log::info!(
+                "Connection {} sets the virtual display modes {}",
+                conn_id,
+                modes
+            );
+            match virtual_display::set_modes(&modes) {
+                Ok(()) => res.set = true,
+                Err(e) => res.error = e.to_string(),
+            }
+        }
+        Ok(Request { modes: None }) => {}
+        Err(e) => res.error = e.to_string(),
+    }
+    res.modes = virtual_display::get_modes();
+    plugin_request::make(ID, &res)
+}
+
+pub fn parse_response(content: &[u8]) -> Option<Response> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_modes_request() {
+        let msg = make_request(Some("1920x1080@60".to_owned()));
+        let req: Request = serde_json::from_slice(&msg.misc().plugin_request().content).unwrap();
+        assert_eq!(req.modes.as_deref(), Some("1920x1080@60"));
+        let req: Request = serde_json::from_slice(b"{}").unwrap();
+        assert_eq!(req.modes, None);
+        let res = parse_response(br#"{"modes":"1920x1080@60"}"#).unwrap();
+        assert_eq!(res.modes, "1920x1080@60");
+        assert!(!res.set && res.error.is_empty());
+    }
+}
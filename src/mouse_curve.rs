@@ -0,0 +1,185 @@
+//! Speed and acceleration of the mouse movements of a viewer, applied by the host.
+//!
+//! The pointer settings of the host and of the client differ, so the remote pointer may feel too
+//! fast or too slow. The client saves a `Curve` per peer in the `PeerConfig` options under
+//! `PEER_OPTION` and sends it to a host with "support_mouse_curve" in its platform additions. The
+//! host scales the relative movements of the connection, and its absolute ones from the position of
+//! the pointer, a jump of more than `JUMP` being taken as is, e.g. when the pointer enters the
+//! remote view again.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+pub const ID: &str = "mouse-curve";
+pub const PEER_OPTION: &str = "mouse-curve";
+pub const PLATFORM_ADDITION_KEY: &str = "support_mouse_curve";
+const MIN_SCALE: f32 = 0.1;
+const MAX_SCALE: f32 = 5.0;
+const MAX_ACCEL: f32 = 2.0;
+// The movement of one event, in pixels, which the full acceleration doubles.
+const ACCEL_SPEED: f32 = 20.0;
+const JUMP: i32 = 200;
+
+lazy_static::lazy_static! {
+    static ref STATES: Mutex<HashMap<i32, State>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Curve {
+    pub scale: f32,
+    #[serde(default)]
+    pub accel: f32,
+}
+
+impl Default for Curve {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            accel: 0.0,
+        }
+    }
+}
+
+impl Curve {
+    fn clamped(self) -> Self {
+        Self {
+            scale: self.scale.clamp(MIN_SCALE, MAX_SCALE),
+            accel: self.accel.clamp(0.0, MAX_ACCEL),
+        }
+    }
+
+    fn apply(&self, delta: f32) -> f32 {
+        delta * self.scale * (1.0 + self.accel * delta.abs() / ACCEL_SPEED)
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    curve: Curve,
+    // The fractions of pixel not moved yet.
+    remainder: (f32, f32),
+    // The last absolute position of the client.
+    last: Option<(i32, i32)>,
+}
+
+impl State {
+    fn scale(&mut self, dx: i32, dy: i32) -> (i32, i32) {
+        let x = self.curve.apply(dx as f32) + self.remainder.0;
+        let y = self.curve.apply(dy as f32) + self.remainder.1;
+        self.remainder = (x - x.trunc(), y - y.trunc());
+        (x.trunc() as i32, y.trunc() as i32)
+    }
+
+    fn absolute(&mut self, x: i32, y: i32, current: Option<(i32, i32)>) -> (i32, i32) {
+        let last = self.last.replace((x, y));
+        match (last, current) {
+            (Some((lx, ly)), Some((cx, cy))) if (x - lx).abs() + (y - ly).abs() <= JUMP => {
+                let (dx, dy) = self.scale(x - lx, y - ly);
+                (cx + dx, cy + dy)
+            }
+            _ => {
+                self.remainder = (0.0, 0.0);
+                (x, y)
+            }
+        }
+    }
+}
+
+/// Set the curve of a connection from its request.
+pub fn handle_request(conn: i32, content: &[u8]) {
+    let curve = match serde_json::from_slice::<Curve>(content) {
+        Ok(curve) => curve.clamped(),
+        Err(e) => {
+            log::warn!("Invalid mouse curve: {}", e);
+            return;
+        }
+    };
+    log::info!("Mouse curve of connection {}: {:?}", conn, curve);
+    let mut states = STATES.lock().unwrap();
+    if curve == Curve::default() {
+        states.remove(&conn);
+    } else {
+        states.insert(
+            conn,
+            State {
+                curve,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+pub fn remove(conn: i32) {
+    STATES.lock().unwrap().remove(&conn);
+}
+
+/// The relative movement to inject.
+pub fn relative(conn: i32, dx: i32, dy: i32) -> (i32, i32) {
+    match STATES.lock().unwrap().get_mut(&conn) {
+        Some(state) => state.scale(dx, dy),
+        None => (dx, dy),
+    }
+}
+
+/// The position to move the pointer to, `current` is its position.
+pub fn absolute(
+    conn: i32,
+    x: i32,
+    y: i32,
+    current: impl FnOnce() -> Option<(i32, i32)>,
+) -> (i32, i32) {
+    match STATES.lock().unwrap().get_mut(&conn) {
+        Some(state) => state.absolute(x, y, current()),
+        None => (x, y),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mouse_curve_scale() {
+        let mut state = State {
+            curve: Curve {
+                scale: 0.5,
+                accel: 0.0,
+            },
+            ..Default::default()
+        };
+        assert_eq!(state.scale(3, -3), (1, -1));
+        // The halves left are moved with the next event.
+        assert_eq!(state.scale(3, -3), (2, -2));
+
+        let mut state = State {
+            curve: Curve {
+                scale: 1.0,
+                accel: 1.0,
+            },
+            ..Default::default()
+        };
+        assert_eq!(state.scale(2, 0), (2, 0));
+        assert_eq!(state.scale(20, 0), (40, 0));
+
+        let mut state = State {
+            curve: Curve {
+                scale: 2.0,
+                accel: 0.0,
+            },
+            ..Default::default()
+        };
+        assert_eq!(state.absolute(100, 100, Some((500, 500))), (100, 100));
+        assert_eq!(state.absolute(110, 95, Some((100, 100))), (120, 90));
+        // A jump.
+        assert_eq!(state.absolute(900, 900, Some((120, 90))), (900, 900));
+
+        let curve: Curve = serde_json::from_str(r#"{"scale":9}"#).unwrap();
+        assert_eq!(curve.clamped().scale, MAX_SCALE);
+    }
+}
@@ -0,0 +1,499 @@
+//! Logical channels over the encrypted connection of a session.
+//!
+//! A channel carries a byte stream, like a forwarded tcp connection, beside the video and the other
+//! traffic of the session, so it needs no connection and no login of its own. The frames are plugin
+//! requests to the hosts which advertise "support_mux", with a binary content: the kind of the
+//! frame, the channel id in big endian and the payload.
+//!
+//! Each side has at most `WINDOW` bytes in flight per channel. The receiver grants them back with
+//! `Frame::Credit` once they are written out, so a slow channel stops reading its socket instead
+//! of filling the connection and holding up the rest of the session. The receiver counts the bytes
+//! not written out yet too, and closes a channel whose peer sends more than `WINDOW` of them, as
+//! they would only pile up in memory.
+//!
+//! The host opens a channel with the permission of the tunnels, to the targets of
+//! `OPTION_TUNNEL_TARGETS` only, as it opens a port forward, and the connection manager shows the
+//! channels open in the session.
+
+use crate::plugin_request;
+use hbb_common::{
+    bytes::Bytes,
+    config::Config,
+    log,
+    message_proto::Message,
+    tcp::new_listener,
+    timeout,
+    tokio::{
+        self,
+        io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+        net::TcpStream,
+        sync::{mpsc, oneshot},
+    },
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+};
+
+pub const ID: &str = "mux";
+pub const OPTION_TUNNEL_TARGETS: &str = "tunnel-targets";
+pub const WINDOW: usize = 256 * 1024;
+const CHUNK: usize = 16 * 1024;
+const CONNECT_TIMEOUT: u64 = 3000;
+
+const FRAME_OPEN: u8 = 0;
+const FRAME_DATA: u8 = 1;
+const FRAME_CREDIT: u8 = 2;
+const FRAME_CLOSE: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Tcp,
+}
+
+/// What the channel is opened to on the host.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Target {
+    pub kind: Kind,
+    #[serde(default)]
+    pub host: String,
+    #[serde(default)]
+    pub port: i32,
+}
+
+impl Target {
+    pub fn host(&self) -> &str {
+        if self.host.is_empty() {
+            "localhost"
+        } else {
+            &self.host
+        }
+    }
+
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host(), self.port)
+    }
+}
+
+/// Whether the tunnels of the host may reach the target. `OPTION_TUNNEL_TARGETS` is a `;` separated
+/// list of `host:port`, either of them `*` for any, where the built-in setting takes precedence over
+/// the local one. Without a list, any target is allowed.
+pub fn is_target_allowed(host: &str, port: i32) -> bool {
+    let mut option = crate::get_builtin_option(OPTION_TUNNEL_TARGETS);
+    if option.trim().is_empty() {
+        option = Config::get_option(OPTION_TUNNEL_TARGETS);
+    }
+    is_target_in(&option, host, port)
+}
+
+fn is_target_in(targets: &str, host: &str, port: i32) -> bool {
+    let mut targets = targets
+        .split(';')
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .peekable();
+    if targets.peek().is_none() {
+        return true;
+    }
+    targets.any(|t| {
+        let Some((h, p)) = t.rsplit_once(':') else {
+            return false;
+        };
+        (h == "*" || h.eq_ignore_ascii_case(host)) && (p == "*" || p.parse() == Ok(port))
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Frame {
+    Open {
+        channel: u32,
+        target: Target,
+    },
+    Data {
+        channel: u32,
+        data: Bytes,
+    },
+    Credit {
+        channel: u32,
+        bytes: u32,
+    },
+    /// The reason is empty if the stream just ended.
+    Close {
+        channel: u32,
+        reason: String,
+    },
+}
+
+impl Frame {
+    pub fn channel(&self) -> u32 {
+        match self {
+            Frame::Open { channel, .. }
+            | Frame::Data { channel, .. }
+            | Frame::Credit { channel, .. }
+            | Frame::Close { channel, .. } => *channel,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let (kind, payload) = match self {
+            Frame::Open { target, .. } => {
+                (FRAME_OPEN, serde_json::to_vec(target).unwrap_or_default())
+            }
+            Frame::Data { data, .. } => (FRAME_DATA, data.to_vec()),
+            Frame::Credit { bytes, .. } => (FRAME_CREDIT, bytes.to_be_bytes().to_vec()),
+            Frame::Close { reason, .. } => (FRAME_CLOSE, reason.as_bytes().to_vec()),
+        };
+        let mut buf = Vec::with_capacity(5 + payload.len());
+        buf.push(kind);
+        buf.extend_from_slice(&self.channel().to_be_bytes());
+        buf.extend_from_slice(&payload);
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Frame> {
+        if buf.len() < 5 {
+            return None;
+        }
+        let channel = u32::from_be_bytes(buf[1..5].try_into().ok()?);
+        let payload = &buf[5..];
+        Some(match buf[0] {
+            FRAME_OPEN => Frame::Open {
+                channel,
+                target: serde_json::from_slice(payload).ok()?,
+            },
+            FRAME_DATA => Frame::Data {
+                channel,
+                data: Bytes::copy_from_slice(payload),
+            },
+            FRAME_CREDIT => Frame::Credit {
+                channel,
+                bytes: u32::from_be_bytes(payload.try_into().ok()?),
+            },
+            FRAME_CLOSE => Frame::Close {
+                channel,
+                reason: String::from_utf8_lossy(payload).to_string(),
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make_raw(ID, Bytes::from(self.encode()))
+    }
+}
+
+/// A frame from the peer, for the task of a channel.
+#[derive(Debug)]
+pub enum Input {
+    Data(Bytes),
+    Credit(u32),
+    Close,
+}
+
+pub const REASON_WINDOW_EXCEEDED: &str = "The window of the channel was exceeded";
+
+// The task of a channel, and the bytes of the peer it has not written out yet.
+struct Task {
+    tx: mpsc::UnboundedSender<Input>,
+    unwritten: Arc<AtomicUsize>,
+}
+
+/// The open channels of a connection.
+#[derive(Clone, Default)]
+pub struct Channels {
+    inputs: Arc<Mutex<HashMap<u32, Task>>>,
+    next_id: Arc<AtomicU32>,
+}
+
+impl Channels {
+    /// A new id for a channel opened on this side.
+    pub fn next_id(&self) -> u32 {
+        self.next_id.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    fn insert(&self, channel: u32) -> Option<(mpsc::UnboundedReceiver<Input>, Arc<AtomicUsize>)> {
+        let mut inputs = self.inputs.lock().unwrap();
+        if inputs.contains_key(&channel) {
+            return None;
+        }
+        let (tx, rx) = mpsc::unbounded_channel();
+        let unwritten = Arc::new(AtomicUsize::new(0));
+        inputs.insert(
+            channel,
+            Task {
+                tx,
+                unwritten: unwritten.clone(),
+            },
+        );
+        Some((rx, unwritten))
+    }
+
+    fn remove(&self, channel: u32) {
+        self.inputs.lock().unwrap().remove(&channel);
+    }
+
+    /// Pass a frame of the peer to its channel, except `Frame::Open` which the caller handles.
+    /// False if the peer sent more than `WINDOW` bytes the channel has not written out, the channel
+    /// is closed then and the caller tells the peer.
+    pub fn dispatch(&self, frame: Frame) -> bool {
+        let mut inputs = self.inputs.lock().unwrap();
+        let channel = frame.channel();
+        let Some(Task { tx, unwritten }) = inputs.get(&channel) else {
+            return true;
+        };
+        match frame {
+            Frame::Data { data, .. } => {
+                if unwritten.fetch_add(data.len(), Ordering::SeqCst) + data.len() > WINDOW {
+                    tx.send(Input::Close).ok();
+                    inputs.remove(&channel);
+                    return false;
+                }
+                tx.send(Input::Data(data)).ok();
+            }
+            Frame::Credit { bytes, .. } => {
+                tx.send(Input::Credit(bytes)).ok();
+            }
+            Frame::Close { .. } => {
+                tx.send(Input::Close).ok();
+                inputs.remove(&channel);
+            }
+            Frame::Open { .. } => {}
+        }
+        true
+    }
+
+    /// Stop the tasks of all the channels, returns their ids for the peer to be told.
+    pub fn close_all(&self) -> Vec<u32> {
+        self.inputs
+            .lock()
+            .unwrap()
+            .drain()
+            .map(|(id, _)| id)
+            .collect()
+    }
+}
+
+/// Pump a stream through the channel until either side closes it.
+async fn run<S, F>(
+    channels: Channels,
+    channel: u32,
+    stream: S,
+    (mut rx, unwritten): (mpsc::UnboundedReceiver<Input>, Arc<AtomicUsize>),
+    send: F,
+) where
+    S: AsyncRead + AsyncWrite,
+    F: Fn(Message),
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let mut credit = WINDOW;
+    let mut buf = vec![0u8; CHUNK];
+    let reason = loop {
+        let n = credit.min(CHUNK);
+        tokio::select! {
+            res = reader.read(&mut buf[..n]), if credit > 0 => match res {
+                Ok(0) => break String::new(),
+                Ok(n) => {
+                    credit -= n;
+                    send(Frame::Data {
+                        channel,
+                        data: Bytes::copy_from_slice(&buf[..n]),
+                    }.to_message());
+                }
+                Err(e) => break e.to_string(),
+            },
+            input = rx.recv() => match input {
+                Some(Input::Data(data)) => {
+                    if let Err(e) = writer.write_all(&data).await {
+                        break e.to_string();
+                    }
+                    unwritten.fetch_sub(data.len(), Ordering::SeqCst);
+                    send(Frame::Credit {
+                        channel,
+                        bytes: data.len() as _,
+                    }.to_message());
+                }
+                Some(Input::Credit(bytes)) => credit = (credit + bytes as usize).min(WINDOW),
+                // Closed by the peer, or the connection is gone.
+                Some(Input::Close) | None => {
+                    channels.remove(channel);
+                    return;
+                }
+            },
+        }
+    };
+    channels.remove(channel);
+    send(Frame::Close { channel, reason }.to_message());
+}
+
+/// Connect the channel opened by the peer to its target, on the host, `on_close` is called when it
+/// is closed. The caller checks the target.
+pub fn accept<F, C>(channels: &Channels, channel: u32, target: Target, send: F, on_close: C)
+where
+    F: Fn(Message) + Send + 'static,
+    C: FnOnce() + Send + 'static,
+{
+    let Some(input) = channels.insert(channel) else {
+        return;
+    };
+    let channels = channels.clone();
+    tokio::spawn(async move {
+        let addr = target.addr();
+        let res = match target.kind {
+            Kind::Tcp => timeout(CONNECT_TIMEOUT, TcpStream::connect(&addr)).await,
+        };
+        match res {
+            Ok(Ok(stream)) => {
+                log::info!("mux channel {} connected to {}", channel, addr);
+                run(channels, channel, stream, input, send).await;
+            }
+            _ => {
+                channels.remove(channel);
+                let reason = format!(
+                    "Failed to access remote {}, please make sure if it is open",
+                    addr
+                );
+                send(Frame::Close { channel, reason }.to_message());
+            }
+        }
+        on_close();
+    });
+}
+
+/// Forward the local tcp port to the target on the host, a channel per accepted connection,
+/// until `stop` is sent or dropped.
+pub async fn listen<F>(
+    channels: Channels,
+    port: i32,
+    target: Target,
+    send: F,
+    mut stop: oneshot::Receiver<()>,
+) where
+    F: Fn(Message) + Clone + Send + 'static,
+{
+    let listener = match new_listener(format!("127.0.0.1:{}", port), true).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            log::error!("mux failed to listen on port {}: {}", port, err);
+            return;
+        }
+    };
+    loop {
+        tokio::select! {
+            _ = &mut stop => break,
+            res = listener.accept() => match res {
+                Ok((stream, addr)) => {
+                    stream.set_nodelay(true).ok();
+                    let channel = channels.next_id();
+                    let Some(input) = channels.insert(channel) else {
+                        continue;
+                    };
+                    log::info!("mux channel {} from {} on port {}", channel, addr, port);
+                    send(Frame::Open {
+                        channel,
+                        target: target.clone(),
+                    }.to_message());
+                    tokio::spawn(run(channels.clone(), channel, stream, input, send.clone()));
+                }
+                Err(err) => {
+                    log::error!("mux accept on port {}: {}", port, err);
+                    break;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mux_frame() {
+        let frames = [
+            Frame::Open {
+                channel: 1,
+                target: Target {
+                    kind: Kind::Tcp,
+                    host: "localhost".to_owned(),
+                    port: 22,
+                },
+            },
+            Frame::Data {
+                channel: 2,
+                data: Bytes::from_static(b"hello"),
+            },
+            Frame::Credit {
+                channel: u32::MAX,
+                bytes: 5,
+            },
+            Frame::Close {
+                channel: 3,
+                reason: String::new(),
+            },
+        ];
+        for frame in frames {
+            assert_eq!(Frame::decode(&frame.encode()), Some(frame));
+        }
+        assert_eq!(Frame::decode(&[FRAME_DATA, 0, 0]), None);
+        assert_eq!(Frame::decode(&[FRAME_CREDIT, 0, 0, 0, 1, 5]), None);
+        assert_eq!(Frame::decode(&[9, 0, 0, 0, 1]), None);
+    }
+
+    #[test]
+    fn mux_targets() {
+        assert!(is_target_in("", "10.0.0.1", 22));
+        let targets = "localhost:22; *:3389;db.lan:*";
+        assert!(is_target_in(targets, "localhost", 22));
+        assert!(!is_target_in(targets, "localhost", 23));
+        assert!(is_target_in(targets, "10.0.0.1", 3389));
+        assert!(is_target_in(targets, "DB.lan", 5432));
+        assert!(!is_target_in(targets, "10.0.0.1", 22));
+        assert!(!is_target_in("localhost", "localhost", 22));
+    }
+
+    #[test]
+    fn mux_channels() {
+        let channels = Channels::default();
+        let id = channels.next_id();
+        assert_ne!(id, channels.next_id());
+        let (mut rx, _) = channels.insert(id).unwrap();
+        assert!(channels.insert(id).is_none());
+        channels.dispatch(Frame::Credit {
+            channel: id,
+            bytes: 10,
+        });
+        assert!(matches!(rx.try_recv(), Ok(Input::Credit(10))));
+        channels.dispatch(Frame::Close {
+            channel: id,
+            reason: String::new(),
+        });
+        assert!(matches!(rx.try_recv(), Ok(Input::Close)));
+        assert!(channels.inputs.lock().unwrap().is_empty());
+        // Frames of unknown channels are dropped.
+        assert!(channels.dispatch(Frame::Data {
+            channel: id,
+            data: Bytes::new(),
+        }));
+
+        // A peer which sends past the window closes the channel.
+        let id = channels.next_id();
+        let (mut rx, unwritten) = channels.insert(id).unwrap();
+        assert!(channels.dispatch(Frame::Data {
+            channel: id,
+            data: Bytes::from(vec![0u8; WINDOW]),
+        }));
+        assert_eq!(unwritten.load(Ordering::SeqCst), WINDOW);
+        assert!(!channels.dispatch(Frame::Data {
+            channel: id,
+            data: Bytes::from_static(b"x"),
+        }));
+        assert!(matches!(rx.try_recv(), Ok(Input::Data(_))));
+        assert!(matches!(rx.try_recv(), Ok(Input::Close)));
+        assert!(channels.inputs.lock().unwrap().is_empty());
+    }
+}
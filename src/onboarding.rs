@@ -0,0 +1,310 @@
+//! First-run setup shared by the UIs: the permissions, the service, the permanent password and
+//! the update channel.
+//!
+//! The steps run in order. A step is left with `Next` once its check passes, or with `Skip`,
+//! and the steps already done are passed over. The progress is saved as json in the
+//! `LocalConfig` option `OPTION_ONBOARDING`, so the setup resumes where it stopped after a
+//! restart. The UIs show `status()` and call `apply()`, see `ui_interface::get_onboarding`.
+
+use hbb_common::{
+    bail,
+    config::{keys, LocalConfig},
+    log, ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+
+pub const OPTION_ONBOARDING: &str = "onboarding";
+const MIN_PASSWORD_LEN: usize = 6;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    #[default]
+    Permissions,
+    Service,
+    Password,
+    UpdateChannel,
+    Done,
+}
+
+const STEPS: [Step; 5] = [
+    Step::Permissions,
+    Step::Service,
+    Step::Password,
+    Step::UpdateChannel,
+    Step::Done,
+];
+
+impl Step {
+    fn next(self) -> Step {
+        let i = STEPS.iter().position(|s| *s == self).unwrap_or_default();
+        STEPS[(i + 1).min(STEPS.len() - 1)]
+    }
+
+    fn prev(self) -> Step {
+        let i = STEPS.iter().position(|s| *s == self).unwrap_or_default();
+        STEPS[i.saturating_sub(1)]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateChannel {
+    /// No check for updates.
+    Off,
+    /// Check for updates and notify.
+    Notify,
+    /// Download and install the updates.
+    Auto,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    Next,
+    Skip,
+    Back,
+    Reset,
+    /// Ask the system for the permissions, or install the service.
+    Prompt,
+    SetPassword(String),
+    SetUpdateChannel(UpdateChannel),
+}
+
+impl Action {
+    pub fn parse(action: &str, value: &str) -> ResultType<Action> {
+        Ok(match action {
+            "next" => Action::Next,
+            "skip" => Action::Skip,
+            "back" => Action::Back,
+            "reset" => Action::Reset,
+            "prompt" => Action::Prompt,
+            "set_password" => Action::SetPassword(value.to_owned()),
+            "set_update_channel" => Action::SetUpdateChannel(serde_json::from_value(
+                serde_json::Value::String(value.to_owned()),
+            )?),
+            _ => bail!("unknown onboarding action: {action}"),
+        })
+    }
+}
+
+/// The checks of the system, taken before each action.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub struct Checks {
+    pub screen_recording: bool,
+    pub accessibility: bool,
+    pub input_monitoring: bool,
+    pub service: bool,
+    pub password: bool,
+}
+
+impl Checks {
+    pub fn current() -> Self {
+        #[cfg(target_os = "macos")]
+        use crate::platform::macos;
+        Checks {
+            #[cfg(target_os = "macos")]
+            screen_recording: macos::is_can_screen_recording(false),
+            #[cfg(target_os = "macos")]
+            accessibility: macos::is_process_trusted(false),
+            #[cfg(target_os = "macos")]
+            input_monitoring: macos::is_can_input_monitoring(false),
+            #[cfg(target_os = "macos")]
+            service: macos::is_installed_daemon(false),
+            #[cfg(not(target_os = "macos"))]
+            screen_recording: true,
+            #[cfg(not(target_os = "macos"))]
+            accessibility: true,
+            #[cfg(not(target_os = "macos"))]
+            input_monitoring: true,
+            #[cfg(not(target_os = "macos"))]
+            service: crate::platform::is_installed(),
+            password: !crate::ipc::get_permanent_password().is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    pub step: Step,
+    #[serde(default)]
+    pub skipped: Vec<Step>,
+    #[serde(default)]
+    pub update_channel: Option<UpdateChannel>,
+}
+
+impl State {
+    pub fn load() -> Self {
+        serde_json::from_str(&LocalConfig::get_option(OPTION_ONBOARDING)).unwrap_or_default()
+    }
+
+    fn store(&self) {
+        LocalConfig::set_option(
+            OPTION_ONBOARDING.to_owned(),
+            serde_json::to_string(self).unwrap_or_default(),
+        );
+    }
+
+    fn is_done(&self, step: Step, checks: &Checks) -> bool {
+        match step {
+            Step::Permissions => {
+                checks.screen_recording && checks.accessibility && checks.input_monitoring
+            }
+            Step::Service => checks.service,
+            Step::Password => checks.password,
+            Step::UpdateChannel => self.update_channel.is_some(),
+            Step::Done => true,
+        }
+    }
+
+    // Move on, passing over the steps done already.
+    fn advance(&mut self, checks: &Checks) {
+        self.step = self.step.next();
+        while self.step != Step::Done && self.is_done(self.step, checks) {
+            self.skipped.retain(|s| *s != self.step);
+            self.step = self.step.next();
+        }
+    }
+
+    /// The transition of the state, the side effects of the action are done by the caller.
+    pub fn transit(&mut self, action: &Action, checks: &Checks) -> ResultType<()> {
+        match action {
+            Action::Next => {
+                if self.step == Step::Done {
+                    return Ok(());
+                }
+                if !self.is_done(self.step, checks) {
+                    bail!("onboarding-step-not-done-tip");
+                }
+                self.skipped.retain(|s| *s != self.step);
+                self.advance(checks);
+            }
+            Action::Skip => {
+                if self.step == Step::Done {
+                    return Ok(());
+                }
+                if !self.skipped.contains(&self.step) {
+                    self.skipped.push(self.step);
+                }
+                self.advance(checks);
+            }
+            Action::Back => self.step = self.step.prev(),
+            Action::Reset => *self = State::default(),
+            Action::SetUpdateChannel(channel) => self.update_channel = Some(*channel),
+            Action::Prompt | Action::SetPassword(_) => {}
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Status {
+    pub step: Step,
+    pub finished: bool,
+    pub skipped: Vec<Step>,
+    pub update_channel: Option<UpdateChannel>,
+    pub checks: Checks,
+}
+
+pub fn status() -> Status {
+    let state = State::load();
+    Status {
+        step: state.step,
+        finished: state.step == Step::Done,
+        skipped: state.skipped,
+        update_channel: state.update_channel,
+        checks: Checks::current(),
+    }
+}
+
+fn set_update_channel(channel: UpdateChannel) {
+    let (check, auto) = match channel {
+        UpdateChannel::Off => ("N", "N"),
+        UpdateChannel::Notify => ("Y", "N"),
+        UpdateChannel::Auto => ("Y", "Y"),
+    };
+    LocalConfig::set_option(
+        keys::OPTION_ENABLE_CHECK_UPDATE.to_owned(),
+        check.to_owned(),
+    );
+    crate::ipc::set_option(keys::OPTION_ALLOW_AUTO_UPDATE, auto);
+}
+
+/// Run the action on the current step and save the progress.
+pub fn apply(action: Action) -> ResultType<()> {
+    let mut state = State::load();
+    match &action {
+        Action::Prompt => match state.step {
+            #[cfg(target_os = "macos")]
+            Step::Permissions => {
+                use crate::platform::macos;
+                // One prompt at a time, for the first missing permission.
+                let _ = macos::is_can_screen_recording(true)
+                    && macos::is_process_trusted(true)
+                    && macos::is_can_input_monitoring(true);
+            }
+            #[cfg(target_os = "macos")]
+            Step::Service => {
+                crate::platform::macos::is_installed_daemon(true);
+            }
+            _ => {}
+        },
+        Action::SetPassword(password) => {
+            if password.chars().count() < MIN_PASSWORD_LEN {
+                bail!("Too short, at least 6 characters.");
+            }
+            crate::ipc::set_permanent_password(password.to_owned())?;
+        }
+        Action::SetUpdateChannel(channel) => set_update_channel(*channel),
+        _ => {}
+    }
+    let old = state.step;
+    state.transit(&action, &Checks::current())?;
+    if old != state.step {
+        log::info!("onboarding: {:?} -> {:?}", old, state.step);
+    }
+    state.store();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn onboarding_transit() {
+        let mut checks = Checks::default();
+        let mut state = State::default();
+        assert!(state.transit(&Action::Next, &checks).is_err());
+        state.transit(&Action::Skip, &checks).unwrap();
+        assert_eq!(state.step, Step::Service);
+        assert_eq!(state.skipped, vec![Step::Permissions]);
+
+        // The password is set already, so the step is passed over.
+        checks.service = true;
+        checks.password = true;
+        state.transit(&Action::Next, &checks).unwrap();
+        assert_eq!(state.step, Step::UpdateChannel);
+        assert!(state.transit(&Action::Next, &checks).is_err());
+        state
+            .transit(&Action::SetUpdateChannel(UpdateChannel::Notify), &checks)
+            .unwrap();
+        state.transit(&Action::Next, &checks).unwrap();
+        assert_eq!(state.step, Step::Done);
+
+        state.transit(&Action::Back, &checks).unwrap();
+        assert_eq!(state.step, Step::UpdateChannel);
+        state.transit(&Action::Reset, &checks).unwrap();
+        assert_eq!(state, State::default());
+
+        let json = r#"{"step":"password","skipped":["permissions"],"update_channel":"auto"}"#;
+        let state: State = serde_json::from_str(json).unwrap();
+        assert_eq!(state.step, Step::Password);
+        assert_eq!(state.update_channel, Some(UpdateChannel::Auto));
+        assert_eq!(
+            Action::parse("set_update_channel", "off").unwrap(),
+            Action::SetUpdateChannel(UpdateChannel::Off)
+        );
+        assert!(Action::parse("set_update_channel", "beta").is_err());
+        assert!(Action::parse("jump", "").is_err());
+    }
+}
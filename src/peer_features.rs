@@ -0,0 +1,286 @@
+//! The features which depend on the peer, so the UIs can tell why one is unavailable and whether
+//! updating the peer helps, instead of only hiding it.
+//!
+//! A feature of `FEATURES` is available if the peer is at least `min_version`, or, for the ones
+//! its platform additions or its features tell, if they do. The features added without a release
+//! have no `min_version`, their peer only tells them.
+
+use hbb_common::{get_version_number, message_proto::PeerInfo};
+use serde_derive::Serialize;
+use serde_json::{Map, Value};
+
+struct Peer<'a> {
+    pi: &'a PeerInfo,
+    additions: Map<String, Value>,
+}
+
+impl Peer<'_> {
+    fn has_addition(&self, key: &str) -> bool {
+        match self.additions.get(key) {
+            None | Some(Value::Null) | Some(Value::Bool(false)) => false,
+            Some(_) => true,
+        }
+    }
+}
+
+enum Check {
+    /// The version of the peer only.
+    Version,
+    PlatformAddition(&'static str),
+    Terminal,
+}
+
+pub struct Feature {
+    pub name: &'static str,
+    /// The lang key of the name shown to the user.
+    pub label: &'static str,
+    pub min_version: Option<&'static str>,
+    /// The platforms of the peer which never have it.
+    pub excluded_platforms: &'static [&'static str],
+    check: Check,
+}
+
+const FEATURES: &[Feature] = &[
+    Feature {
+        name: "relative_mouse",
+        label: "Relative mouse mode",
+        min_version: Some(crate::common::MIN_VERSION_RELATIVE_MOUSE_MODE),
+        excluded_platforms: &["Linux"],
+        check: Check::Version,
+    },
+    Feature {
+        name: "terminal",
+        label: "Terminal",
+        min_version: Some("1.4.1"),
+        excluded_platforms: &[],
+        check: Check::Terminal,
+    },
+    Feature {
+        name: "view_camera",
+        label: "View camera",
+        min_version: Some("1.3.9"),
+        excluded_platforms: &[],
+        check: Check::PlatformAddition("support_view_camera"),
+    },
+    Feature {
+        name: "remote_printer",
+        label: "Printer",
+        min_version: Some("1.3.9"),
+        excluded_platforms: &[],
+        check: Check::Version,
+    },
+    Feature {
+        name: "keyboard_layout",
+        label: "Keyboard layout",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::keyboard_layout::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "special_keys",
+        label: "Special keys",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::special_keys::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "mouse_curve",
+        label: "Mouse acceleration",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::mouse_curve::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "scroll_transform",
+        label: "Scroll settings",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::scroll_transform::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "privacy_displays",
+        label: "Privacy mode displays",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::privacy_displays::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "wake_policy",
+        label: "Keep the host awake",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::wake_policy::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "display_power",
+        label: "Wake the display when it sleeps",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::display_power::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "permission_request",
+        label: "Request permission",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::permission_request::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "config_edit",
+        label: "Edit config files",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::config_edit::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "transfer_throttle",
+        label: "Transfer speed limit",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::transfer_throttle::PLATFORM_ADDITION_KEY),
+    },
+    Feature {
+        name: "folder_sync",
+        label: "Only send the changed files",
+        min_version: None,
+        excluded_platforms: &[],
+        check: Check::PlatformAddition(crate::folder_sync::PLATFORM_ADDITION_KEY),
+    },
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Reason {
+    /// The peer is older than `min_version`, updating it helps.
+    Version,
+    /// The platform of the peer never has it.
+    Platform,
+    /// The peer does not have it, turned off or not built with it.
+    Missing,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Unavailable {
+    pub name: String,
+    pub label: String,
+    pub reason: Reason,
+    pub peer_version: String,
+    pub peer_platform: String,
+    /// The version to update the peer to, with `Reason::Version`.
+    pub min_version: Option<String>,
+}
+
+impl Unavailable {
+    /// The lang key of the text of the notification.
+    pub fn tip(&self) -> String {
+        match (self.reason, &self.min_version) {
+            (Reason::Version, Some(v)) => format!("upgrade_remote_rustdesk_client_to_{{{v}}}_tip"),
+            (Reason::Platform, _) => "feature-unavailable-platform-tip".to_owned(),
+            _ => "feature-unavailable-peer-tip".to_owned(),
+        }
+    }
+}
+
+fn check(f: &Feature, peer: &Peer) -> Option<Unavailable> {
+    let version = get_version_number(&peer.pi.version);
+    let new_enough = f
+        .min_version
+        .map(|v| version >= get_version_number(v))
+        .unwrap_or(false);
+    let reason = if f.excluded_platforms.contains(&peer.pi.platform.as_str()) {
+        Reason::Platform
+    } else {
+        let has = match f.check {
+            Check::Version => new_enough,
+            Check::PlatformAddition(key) => peer.has_addition(key),
+            Check::Terminal => peer
+                .pi
+                .features
+                .as_ref()
+                .map(|f| f.terminal)
+                .unwrap_or(false),
+        };
+        if has {
+            return None;
+        }
+        if f.min_version.is_some() && !new_enough {
+            Reason::Version
+        } else {
+            Reason::Missing
+        }
+    };
+    Some(Unavailable {
+        name: f.name.to_owned(),
+        label: f.label.to_owned(),
+        reason,
+        peer_version: peer.pi.version.clone(),
+        peer_platform: peer.pi.platform.clone(),
+        min_version: f
+            .min_version
+            .filter(|_| reason == Reason::Version)
+            .map(|v| v.to_owned()),
+    })
+}
+
+fn peer(pi: &PeerInfo) -> Peer {
+    Peer {
+        pi,
+        additions: serde_json::from_str(&pi.platform_additions).unwrap_or_default(),
+    }
+}
+
+/// Why the feature `name` is unavailable with the peer, `None` if it is available or unknown.
+pub fn unavailable(name: &str, pi: &PeerInfo) -> Option<Unavailable> {
+    let peer = peer(pi);
+    FEATURES
+        .iter()
+        .find(|f| f.name == name)
+        .and_then(|f| check(f, &peer))
+}
+
+/// All the features unavailable with the peer.
+pub fn all_unavailable(pi: &PeerInfo) -> Vec<Unavailable> {
+    let peer = peer(pi);
+    FEATURES.iter().filter_map(|f| check(f, &peer)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_features() {
+        let mut pi = PeerInfo {
+            version: "1.3.0".to_owned(),
+            platform: "Windows".to_owned(),
+            platform_additions: r#"{"support_special_keys":true,"keyboard_layout":""}"#.to_owned(),
+            ..Default::default()
+        };
+        let terminal = unavailable("terminal", &pi).unwrap();
+        assert_eq!(terminal.reason, Reason::Version);
+        assert_eq!(
+            terminal.tip(),
+            "upgrade_remote_rustdesk_client_to_{1.4.1}_tip"
+        );
+        assert_eq!(unavailable("special_keys", &pi), None);
+        assert_eq!(unavailable("keyboard_layout", &pi), None);
+        assert_eq!(
+            unavailable("mouse_curve", &pi).map(|u| u.reason),
+            Some(Reason::Missing)
+        );
+        assert_eq!(unavailable("no_such_feature", &pi), None);
+
+        pi.version = "1.4.5".to_owned();
+        assert_eq!(unavailable("relative_mouse", &pi), None);
+        assert_eq!(
+            unavailable("terminal", &pi).map(|u| (u.reason, u.min_version)),
+            Some((Reason::Missing, None))
+        );
+        pi.platform = "Linux".to_owned();
+        assert_eq!(
+            unavailable("relative_mouse", &pi).map(|u| u.reason),
+            Some(Reason::Platform)
+        );
+        assert!(all_unavailable(&pi).iter().any(|u| u.name == "terminal"));
+    }
+}
@@ -0,0 +1,149 @@
+//! Display name and avatar of the host, shown by the viewers instead of its id.
+//!
+//! The host sets them in `OPTION_DISPLAY_NAME` and `OPTION_AVATAR`, the avatar as a small PNG in
+//! base64, and publishes them in the platform additions of the handshake. The client keeps them
+//! in the options of the `PeerConfig` of the host, so the peer lists show them when the host is
+//! offline, and updates them on each connection. With `OPTION_SYNC_IDENTITY` the host uploads
+//! them with its sysinfo to the api server too.
+
+use hbb_common::{bail, config::Config, ResultType};
+use serde_derive::Serialize;
+use serde_json::Value;
+use std::{collections::HashMap, io::Cursor};
+
+pub const OPTION_DISPLAY_NAME: &str = "host-display-name";
+pub const OPTION_AVATAR: &str = "host-avatar";
+pub const OPTION_SYNC_IDENTITY: &str = "allow-sync-host-identity";
+pub const PEER_OPTION_DISPLAY_NAME: &str = "peer-display-name";
+pub const PEER_OPTION_AVATAR: &str = "peer-avatar";
+const KEY_DISPLAY_NAME: &str = "display_name";
+const KEY_AVATAR: &str = "avatar";
+const AVATAR_SIZE: u32 = 64;
+const MAX_NAME_LEN: usize = 64;
+const MAX_AVATAR_LEN: usize = 32 * 1024;
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Identity {
+    pub display_name: String,
+    /// A PNG in base64.
+    pub avatar: String,
+}
+
+fn clean_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(MAX_NAME_LEN)
+        .collect()
+}
+
+fn is_valid_avatar(avatar: &str) -> bool {
+    avatar.len() <= MAX_AVATAR_LEN
+        && crate::decode64(avatar)
+            .map(|png| png.starts_with(PNG_SIGNATURE))
+            .unwrap_or(false)
+}
+
+impl Identity {
+    pub fn new(display_name: &str, avatar: &str) -> Self {
+        Self {
+            display_name: clean_name(display_name),
+            avatar: if is_valid_avatar(avatar) {
+                avatar.to_owned()
+            } else {
+                String::new()
+            },
+        }
+    }
+
+    /// The identity of this host.
+    pub fn local() -> Self {
+        Self::new(
+            &Config::get_option(OPTION_DISPLAY_NAME),
+            &Config::get_option(OPTION_AVATAR),
+        )
+    }
+
+    pub fn from_platform_additions(additions: &HashMap<String, Value>) -> Self {
+        let get = |key| additions.get(key).and_then(|v| v.as_str()).unwrap_or("");
+        Self::new(get(KEY_DISPLAY_NAME), get(KEY_AVATAR))
+    }
+
+    pub fn add_to(&self, map: &mut serde_json::Map<String, Value>) {
+        if !self.display_name.is_empty() {
+            map.insert(KEY_DISPLAY_NAME.into(), self.display_name.clone().into());
+        }
+        if !self.avatar.is_empty() {
+            map.insert(KEY_AVATAR.into(), self.avatar.clone().into());
+        }
+    }
+
+    /// Keep the identity of a peer in the options of its `PeerConfig`, true if it changed.
+    pub fn save_to_peer_options(&self, options: &mut HashMap<String, String>) -> bool {
+        let mut changed = false;
+        for (key, value) in [
+            (PEER_OPTION_DISPLAY_NAME, &self.display_name),
+            (PEER_OPTION_AVATAR, &self.avatar),
+        ] {
+            if options.get(key).map(|v| v.as_str()).unwrap_or("") == value {
+                continue;
+            }
+            if value.is_empty() {
+                options.remove(key);
+            } else {
+                options.insert(key.to_owned(), value.clone());
+            }
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// Set the avatar of this host from an image file, scaled down to fit `AVATAR_SIZE`. An empty
+/// path removes it.
+pub fn set_avatar(path: &str) -> ResultType<()> {
+    if path.is_empty() {
+        Config::set_option(OPTION_AVATAR.to_owned(), "".to_owned());
+        return Ok(());
+    }
+    let image = image::open(path)?.thumbnail(AVATAR_SIZE, AVATAR_SIZE);
+    let mut png = vec![];
+    image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png)?;
+    let avatar = crate::encode64(&png);
+    if avatar.len() > MAX_AVATAR_LEN {
+        bail!("The avatar is too large");
+    }
+    Config::set_option(OPTION_AVATAR.to_owned(), avatar);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peer_identity_cache() {
+        let avatar = crate::encode64(PNG_SIGNATURE);
+        let identity = Identity::new("  Front desk\n", &avatar);
+        assert_eq!(identity.display_name, "Front desk");
+        assert_eq!(identity.avatar, avatar);
+        assert!(Identity::new("", "bm90IGEgcG5n").avatar.is_empty());
+        assert_eq!(
+            Identity::new(&"x".repeat(100), "").display_name.len(),
+            MAX_NAME_LEN
+        );
+
+        let mut map = serde_json::Map::new();
+        identity.add_to(&mut map);
+        let additions: HashMap<String, Value> = map.into_iter().collect();
+        assert_eq!(Identity::from_platform_additions(&additions), identity);
+
+        let mut options = HashMap::new();
+        assert!(identity.save_to_peer_options(&mut options));
+        assert!(!identity.save_to_peer_options(&mut options));
+        assert_eq!(options[PEER_OPTION_DISPLAY_NAME], "Front desk");
+        assert!(Identity::default().save_to_peer_options(&mut options));
+        assert!(options.is_empty());
+    }
+}
@@ -0,0 +1,77 @@
+//! Permissions asked by the viewer during the session.
+//!
+//! A viewer without, e.g., the file transfer of the host could only fail. To a host with
+//! "support_permission_request" in its platform additions, it sends a `Request` with the name of
+//! the permission. The host asks its user in the connection manager, which switches the permission
+//! on for this session like its own toggles, then the host sends a `Reply` back. The permissions
+//! the administrator disabled by the control permissions are denied without asking.
+
+use crate::plugin_request;
+use hbb_common::{config::keys, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "permission-request";
+pub const PLATFORM_ADDITION_KEY: &str = "support_permission_request";
+
+/// The names of `ipc::Data::SwitchPermission`, with their option.
+const PERMISSIONS: &[(&str, &str)] = &[
+    ("keyboard", keys::OPTION_ENABLE_KEYBOARD),
+    ("clipboard", keys::OPTION_ENABLE_CLIPBOARD),
+    ("audio", keys::OPTION_ENABLE_AUDIO),
+    ("file", keys::OPTION_ENABLE_FILE_TRANSFER),
+    ("restart", keys::OPTION_ENABLE_REMOTE_RESTART),
+    ("recording", keys::OPTION_ENABLE_RECORD_SESSION),
+    ("block_input", keys::OPTION_ENABLE_BLOCK_INPUT),
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Content {
+    Request { permission: String },
+    Reply { permission: String, granted: bool },
+}
+
+impl Content {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+}
+
+/// The option which enables the permission, none if it is not one.
+pub fn option_of(permission: &str) -> Option<&'static str> {
+    PERMISSIONS
+        .iter()
+        .find(|(name, _)| *name == permission)
+        .map(|(_, option)| *option)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permission_request_content() {
+        let reply = Content::Reply {
+            permission: "file".to_owned(),
+            granted: true,
+        };
+        let json = serde_json::to_string(&reply).unwrap();
+        assert_eq!(
+            json,
+            r#"{"type":"reply","permission":"file","granted":true}"#
+        );
+        assert_eq!(Content::parse(json.as_bytes()), Some(reply));
+        assert_eq!(
+            Content::parse(br#"{"type":"request","permission":"audio"}"#),
+            Some(Content::Request {
+                permission: "audio".to_owned()
+            })
+        );
+        assert_eq!(option_of("file"), Some(keys::OPTION_ENABLE_FILE_TRANSFER));
+        assert_eq!(option_of("tunnel"), None);
+    }
+}
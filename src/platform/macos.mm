@@ -304,6 +304,15 @@ static CFMachPortRef g_eventTap = NULL;
 static CFRunLoopSourceRef g_runLoopSource = NULL;
 static std::mutex g_privacyModeMutex;
 static bool g_privacyModeActive = false;
+// Whether the privacy mode by overlay windows is on, see MacSetPrivacyModeOverlay.
+static bool g_overlayActive = false;
+// The displays the privacy mode blanks, all of them if empty. Only changed while it is off, see
+// MacSetPrivacyModeDisplays.
+static std::set<CGDirectDisplayID> g_privacyDisplays;
+
+static bool IsPrivacyDisplay(CGDirectDisplayID display) {
+    return g_privacyDisplays.empty() || g_privacyDisplays.count(display) > 0;
+}
 
 // Flag to request asynchronous shutdown of privacy mode.
 // This is set by DisplayReconfigurationCallback when an error occurs, instead of calling
@@ -456,7 +465,9 @@ static void ApplyBlackoutToAllDisplays() {
     CGGetOnlineDisplayList(onlineCount, onlineDisplays.data(), &onlineCount);
     
     for (uint32_t i = 0; i < onlineCount; i++) {
-        ApplyBlackoutToDisplay(onlineDisplays[i]);
+        if (IsPrivacyDisplay(onlineDisplays[i])) {
+            ApplyBlackoutToDisplay(onlineDisplays[i]);
+        }
     }
 }
 
@@ -513,7 +524,7 @@ static void RunBlackoutMonitor() {
         
         bool needsReapply = false;
         for (uint32_t i = 0; i < onlineCount; i++) {
-            if (!IsDisplayBlackedOut(onlineDisplays[i])) {
+            if (IsPrivacyDisplay(onlineDisplays[i]) && !IsDisplayBlackedOut(onlineDisplays[i])) {
                 needsReapply = true;
                 break;
             }
@@ -581,7 +592,10 @@ static void DisplayReconfigurationCallback(CGDirectDisplayID display, CGDisplayC
         return;
     }
     
-    if (flags & kCGDisplayAddFlag) {
+    if ((flags & kCGDisplayAddFlag) && !IsPrivacyDisplay(display)) {
+        // A display was added which was not chosen, left as is
+        NSLog(@"Display %u added during privacy mode, not chosen", (unsigned)display);
+    } else if (flags & kCGDisplayAddFlag) {
         // A display was added - apply blackout to it
         NSLog(@"Display %u added during privacy mode, applying blackout", (unsigned)display);
         std::string uuid = GetDisplayUUID(display);
@@ -806,6 +820,10 @@ extern "C" bool MacSetPrivacyMode(bool on) {
         if (g_privacyModeActive) {
             return true;
         }
+        if (g_overlayActive) {
+            NSLog(@"MacSetPrivacyMode: Privacy mode by overlay windows is on");
+            return false;
+        }
         
         // 1. Input Blocking - set up EventTap on main thread
         if (!SetupEventTapOnMainThread()) {
@@ -826,6 +844,9 @@ extern "C" bool MacSetPrivacyMode(bool on) {
 
         for (uint32_t i = 0; i < count; i++) {
             CGDirectDisplayID d = displays[i];
+            if (!IsPrivacyDisplay(d)) {
+                continue;
+            }
             std::string uuid = GetDisplayUUID(d);
             
             if (uuid.empty()) {
@@ -907,3 +928,151 @@ extern "C" bool MacSetPrivacyMode(bool on) {
         return TurnOffPrivacyModeInternal();
     }
 }
+
+// The black windows of the privacy mode by overlay, one per screen, only used on the main thread.
+// Unlike the gamma blackout, they also cover the displays without a gamma table, e.g. the
+// DisplayLink and AirPlay ones. They are left out of the screen capture by NSWindowSharingNone.
+static NSMutableArray<NSWindow *> *g_overlayWindows = nil;
+static id g_screenObserver = nil;
+
+static void RemoveOverlayWindows() {
+    for (NSWindow *window in g_overlayWindows) {
+        [window orderOut:nil];
+        [window close];
+    }
+    [g_overlayWindows removeAllObjects];
+}
+
+// Cover each chosen screen with a window above everything but the cursor, which neither takes
+// the input nor follows the Spaces switches. Must be called on the main thread.
+static void ShowOverlayWindows() {
+    RemoveOverlayWindows();
+    if (g_overlayWindows == nil) {
+        g_overlayWindows = [[NSMutableArray alloc] init];
+    }
+    for (NSScreen *screen in [NSScreen screens]) {
+        NSNumber *screenNumber = [[screen deviceDescription] objectForKey:@"NSScreenNumber"];
+        if (screenNumber == nil || !IsPrivacyDisplay([screenNumber unsignedIntValue])) {
+            continue;
+        }
+        NSWindow *window = [[NSWindow alloc] initWithContentRect:screen.frame
+                                                       styleMask:NSWindowStyleMaskBorderless
+                                                         backing:NSBackingStoreBuffered
+                                                           defer:NO];
+        [window setReleasedWhenClosed:NO];
+        [window setBackgroundColor:[NSColor blackColor]];
+        [window setOpaque:YES];
+        [window setHasShadow:NO];
+        [window setIgnoresMouseEvents:YES];
+        [window setLevel:CGShieldingWindowLevel()];
+        [window setSharingType:NSWindowSharingNone];
+        [window setCollectionBehavior:NSWindowCollectionBehaviorCanJoinAllSpaces |
+                                      NSWindowCollectionBehaviorStationary |
+                                      NSWindowCollectionBehaviorIgnoresCycle |
+                                      NSWindowCollectionBehaviorFullScreenAuxiliary];
+        [window setFrame:screen.frame display:NO];
+        [window orderFrontRegardless];
+        [g_overlayWindows addObject:window];
+        [window release];
+    }
+}
+
+static void RunOnMainThread(void (^block)(void)) {
+    // See SetupEventTapOnMainThread for why dispatch_sync does not deadlock here.
+    if ([NSThread isMainThread]) {
+        block();
+    } else {
+        dispatch_sync(dispatch_get_main_queue(), block);
+    }
+}
+
+// The privacy mode by overlay windows, the input is blocked as with MacSetPrivacyMode.
+// The windows follow the screens added, removed or resized while it is on.
+extern "C" bool MacSetPrivacyModeOverlay(bool on) {
+    std::lock_guard<std::mutex> lock(g_privacyModeMutex);
+    if (on) {
+        if (g_overlayActive) {
+            return true;
+        }
+        if (g_privacyModeActive) {
+            NSLog(@"MacSetPrivacyModeOverlay: Privacy mode by gamma blackout is on");
+            return false;
+        }
+        if (!SetupEventTapOnMainThread()) {
+            return false;
+        }
+        RunOnMainThread(^{
+            @autoreleasepool {
+                ShowOverlayWindows();
+                g_screenObserver = [[[NSNotificationCenter defaultCenter]
+                    addObserverForName:NSApplicationDidChangeScreenParametersNotification
+                                object:nil
+                                 queue:[NSOperationQueue mainQueue]
+                            usingBlock:^(NSNotification *note) {
+                                (void)note;
+                                // Removed when turned off, on the main thread too.
+                                if (g_screenObserver != nil) {
+                                    NSLog(@"Screens changed during privacy mode, covering them again");
+                                    ShowOverlayWindows();
+                                }
+                            }] retain];
+            }
+        });
+        g_overlayActive = true;
+        return true;
+    } else {
+        if (!g_overlayActive) {
+            return true;
+        }
+        RunOnMainThread(^{
+            if (g_screenObserver != nil) {
+                [[NSNotificationCenter defaultCenter] removeObserver:g_screenObserver];
+                [g_screenObserver release];
+                g_screenObserver = nil;
+            }
+            RemoveOverlayWindows();
+        });
+        TeardownEventTapOnMainThread();
+        g_overlayActive = false;
+        return true;
+    }
+}
+
+// Choose the displays the privacy mode blanks the next time it is turned on, all of them with no
+// display. The input stays blocked on all of them.
+extern "C" void MacSetPrivacyModeDisplays(const uint32_t *displays, uint32_t count) {
+    std::lock_guard<std::mutex> lock(g_privacyModeMutex);
+    g_privacyDisplays.clear();
+    for (uint32_t i = 0; i < count; i++) {
+        g_privacyDisplays.insert(displays[i]);
+    }
+}
+
+// The vendor of the monitors of the virtual display library, see virtual_display.m.
+static const uint32_t kVirtualDisplayVendor = 0x5253;
+
+static uint32_t countOnlineDisplays(bool isVirtual) {
+    uint32_t count = 0;
+    if (CGGetOnlineDisplayList(0, NULL, &count) != kCGErrorSuccess || count == 0) {
+        return 0;
+    }
+    std::vector<CGDirectDisplayID> displays(count);
+    CGGetOnlineDisplayList(count, displays.data(), &count);
+    uint32_t n = 0;
+    for (uint32_t i = 0; i < count; i++) {
+        if ((CGDisplayVendorNumber(displays[i]) == kVirtualDisplayVendor) == isVirtual) {
+            n++;
+        }
+    }
+    return n;
+}
+
+// The online monitors of the virtual display library.
+extern "C" uint32_t MacVirtualDisplayCount() {
+    return countOnlineDisplays(true);
+}
+
+// The online displays other than the virtual ones, the asleep and the mirrored ones included.
+extern "C" uint32_t MacPhysicalDisplayCount() {
+    return countOnlineDisplays(false);
+}
@@ -55,6 +55,8 @@ extern "C" {
     fn CGSCurrentCursorSeed() -> i32;
     fn CGEventCreate(r: *const c_void) -> *const c_void;
     fn CGEventGetLocation(e: *const c_void) -> CGPoint;
+    fn CGEventPost(tap: u32, e: *const c_void);
+    fn CGEventSetIntegerValueField(e: *const c_void, field: u32, value: i64);
     static kAXTrustedCheckOptionPrompt: CFStringRef;
     fn AXIsProcessTrustedWithOptions(options: CFDictionaryRef) -> BOOL;
     fn InputMonitoringAuthStatus(_: BOOL) -> BOOL;
@@ -75,6 +77,9 @@ extern "C" {
     fn MacSetMode(display: u32, width: u32, height: u32, tryHiDPI: bool) -> BOOL;
     fn CGWarpMouseCursorPosition(newCursorPosition: CGPoint) -> CGError;
     fn CGAssociateMouseAndMouseCursorPosition(connected: BooleanT) -> CGError;
+    fn CGDisplayIsAsleep(display: u32) -> BooleanT;
+    fn CGDisplayCreateUUIDFromDisplayID(display: u32) -> *const c_void;
+    fn CFUUIDCreateString(alloc: *const c_void, uuid: *const c_void) -> CFStringRef;
 }
 
 pub fn major_version() -> u32 {
@@ -307,6 +312,14 @@ fn update_daemon_agent(agent_plist_file: String, update_source_dir: String, sync
     }
 }
 
+/// A script or a plist of `privileges_scripts`, for this app.
+pub(super) fn privileges_script(name: &str) -> Option<String> {
+    PRIVILEGES_SCRIPTS_DIR
+        .get_file(name)?
+        .contents_utf8()
+        .map(correct_app_name)
+}
+
 fn correct_app_name(s: &str) -> String {
     let mut s = s.to_owned();
     if let Some(bundleid) = get_bundle_id() {
@@ -709,6 +722,36 @@ pub fn is_locked() -> bool {
     }
 }
 
+pub fn is_display_asleep(display: u32) -> bool {
+    unsafe { CGDisplayIsAsleep(display) != 0 }
+}
+
+/// The UUID of the display, made from its EDID, which is kept across the reconnections and
+/// the restarts, unlike its id.
+pub fn display_uuid(display: u32) -> Option<String> {
+    use core_foundation::{base::TCFType, string::CFString};
+    unsafe {
+        let uuid = CGDisplayCreateUUIDFromDisplayID(display);
+        if uuid.is_null() {
+            return None;
+        }
+        let s = CFUUIDCreateString(std::ptr::null(), uuid);
+        CFRelease(uuid);
+        if s.is_null() {
+            return None;
+        }
+        Some(CFString::wrap_under_create_rule(s).to_string())
+    }
+}
+
+/// Declare user activity, which wakes the displays like a key press.
+pub fn wake_display() -> ResultType<()> {
+    std::process::Command::new("caffeinate")
+        .args(["-u", "-t", "2"])
+        .spawn()?;
+    Ok(())
+}
+
 pub fn is_root() -> bool {
     crate::username() == "root"
 }
@@ -800,8 +843,123 @@ pub fn toggle_blank_screen(_v: bool) {
     // https://unix.stackexchange.com/questions/17115/disable-keyboard-mouse-temporarily
 }
 
-pub fn block_input(_v: bool) -> (bool, String) {
-    (true, "".to_owned())
+static BLOCKING_INPUT: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+static BLOCK_INPUT_ESCAPES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+// kVK_Escape
+const ESCAPE_KEYCODE: i64 = 53;
+
+/// Drop the physical keyboard and mouse events with an event tap, the injected ones carry
+/// `ENIGO_INPUT_EXTRA_VALUE` and pass. Control+Option+Command+Escape on the host ends the block,
+/// which is counted in `block_input_escapes`.
+pub fn block_input(v: bool) -> (bool, String) {
+    use std::sync::atomic::Ordering;
+    if !v {
+        BLOCKING_INPUT.store(false, Ordering::SeqCst);
+        return (true, "".to_owned());
+    }
+    if BLOCKING_INPUT.swap(true, Ordering::SeqCst) {
+        return (true, "".to_owned());
+    }
+    let (tx, rx) = std::sync::mpsc::channel();
+    std::thread::spawn(move || run_input_blocker(tx));
+    match rx.recv_timeout(std::time::Duration::from_secs(3)) {
+        Ok(Ok(())) => (true, "".to_owned()),
+        Ok(Err(e)) => {
+            BLOCKING_INPUT.store(false, Ordering::SeqCst);
+            (false, e)
+        }
+        Err(e) => {
+            BLOCKING_INPUT.store(false, Ordering::SeqCst);
+            (false, e.to_string())
+        }
+    }
+}
+
+/// The number of times the block was ended by the escape chord.
+pub fn block_input_escapes() -> usize {
+    BLOCK_INPUT_ESCAPES.load(std::sync::atomic::Ordering::SeqCst)
+}
+
+fn run_input_blocker(tx: std::sync::mpsc::Sender<Result<(), String>>) {
+    use core_foundation::runloop::{kCFRunLoopCommonModes, kCFRunLoopDefaultMode, CFRunLoop};
+    use core_graphics::event::{
+        CGEventFlags, CGEventTap, CGEventTapLocation, CGEventTapOptions, CGEventTapPlacement,
+        CGEventType, EventField,
+    };
+    use std::sync::atomic::Ordering;
+
+    let tap = CGEventTap::new(
+        CGEventTapLocation::Session,
+        CGEventTapPlacement::HeadInsertEventTap,
+        CGEventTapOptions::Default,
+        vec![
+            CGEventType::KeyDown,
+            CGEventType::KeyUp,
+            CGEventType::FlagsChanged,
+            CGEventType::MouseMoved,
+            CGEventType::LeftMouseDown,
+            CGEventType::LeftMouseUp,
+            CGEventType::LeftMouseDragged,
+            CGEventType::RightMouseDown,
+            CGEventType::RightMouseUp,
+            CGEventType::RightMouseDragged,
+            CGEventType::OtherMouseDown,
+            CGEventType::OtherMouseUp,
+            CGEventType::OtherMouseDragged,
+            CGEventType::ScrollWheel,
+        ],
+        |_proxy, event_type, event| {
+            if !BLOCKING_INPUT.load(Ordering::SeqCst)
+                || event.get_integer_value_field(EventField::EVENT_SOURCE_USER_DATA)
+                    == enigo::ENIGO_INPUT_EXTRA_VALUE
+            {
+                return None;
+            }
+            let chord = CGEventFlags::CGEventFlagControl
+                | CGEventFlags::CGEventFlagAlternate
+                | CGEventFlags::CGEventFlagCommand;
+            if matches!(event_type, CGEventType::KeyDown)
+                && event.get_integer_value_field(EventField::KEYBOARD_EVENT_KEYCODE)
+                    == ESCAPE_KEYCODE
+                && event.get_flags().contains(chord)
+            {
+                log::info!("Input block ended by the escape chord");
+                BLOCKING_INPUT.store(false, Ordering::SeqCst);
+                BLOCK_INPUT_ESCAPES.fetch_add(1, Ordering::SeqCst);
+            }
+            // The event is dropped as a null event.
+            event.set_type(CGEventType::Null);
+            None
+        },
+    );
+    let Ok(tap) = tap else {
+        tx.send(Err(
+            "Failed to create the event tap, check the accessibility permission".to_owned(),
+        ))
+        .ok();
+        return;
+    };
+    let Ok(source) = tap.mach_port.create_runloop_source(0) else {
+        tx.send(Err("Failed to create the run loop source".to_owned()))
+            .ok();
+        return;
+    };
+    unsafe {
+        CFRunLoop::get_current().add_source(&source, kCFRunLoopCommonModes);
+    }
+    tap.enable();
+    tx.send(Ok(())).ok();
+    log::info!("Input blocked");
+    while BLOCKING_INPUT.load(Ordering::SeqCst) {
+        CFRunLoop::run_in_mode(
+            unsafe { kCFRunLoopDefaultMode },
+            std::time::Duration::from_millis(500),
+            true,
+        );
+        // Enabled again if the system disabled it for a slow callback.
+        tap.enable();
+    }
+    log::info!("Input unblocked");
 }
 
 pub fn is_installed() -> bool {
@@ -913,7 +1071,9 @@ pub fn update_to(_file: &str) -> ResultType<()> {
 pub fn extract_update_dmg(file: &str) {
     let mut evt: HashMap<&str, String> =
         HashMap::from([("name", "extract-update-dmg".to_string())]);
-    match extract_dmg(file, UPDATE_TEMP_DIR) {
+    let res = crate::update_manifest::verify_file(Path::new(file))
+        .and_then(|_| extract_dmg(file, UPDATE_TEMP_DIR));
+    match res {
         Ok(_) => {
             log::info!("Extracted dmg file to {}", UPDATE_TEMP_DIR);
         }
@@ -1017,6 +1177,105 @@ pub fn hide_dock() {
     }
 }
 
+/// Show a system notification, the title and text are passed as arguments so no escaping is needed.
+pub fn show_notification(title: &str, text: &str) {
+    let title = title.to_owned();
+    let text = text.to_owned();
+    std::thread::spawn(move || {
+        if let Err(e) = std::process::Command::new("osascript")
+            .arg("-e")
+            .arg("on run argv")
+            .arg("-e")
+            .arg("display notification (item 2 of argv) with title (item 1 of argv)")
+            .arg("-e")
+            .arg("end run")
+            .arg(title)
+            .arg(text)
+            .status()
+        {
+            log::error!("run osascript failed: {}", e);
+        }
+    });
+}
+
+// The folder of the frontmost Finder window containing the point, the desktop if none does.
+const FOLDER_AT_POINT_SCRIPT: &str = r#"
+on run argv
+    set x to (item 1 of argv) as integer
+    set y to (item 2 of argv) as integer
+    tell application "Finder"
+        repeat with w in (every Finder window whose collapsed is false)
+            set b to bounds of w
+            if x >= item 1 of b and x < item 3 of b and y >= item 2 of b and y < item 4 of b then
+                return POSIX path of (target of w as alias)
+            end if
+        end repeat
+        return POSIX path of (desktop as alias)
+    end tell
+end run
+"#;
+
+/// Move the item to the trash of its volume, as Finder does, and return its path in the trash.
+pub fn trash_item(path: &str) -> ResultType<String> {
+    autoreleasepool(|| unsafe {
+        let path = NSString::alloc(nil).init_str(path);
+        let url: id = msg_send![class!(NSURL), fileURLWithPath: path];
+        let manager: id = msg_send![class!(NSFileManager), defaultManager];
+        let mut trashed: id = nil;
+        let mut error: id = nil;
+        let ok: BOOL = msg_send![manager, trashItemAtURL: url resultingItemURL: &mut trashed error: &mut error];
+        if ok == NO || trashed.is_null() {
+            let mut reason = "unknown error".to_owned();
+            if !error.is_null() {
+                let desc: id = msg_send![error, localizedDescription];
+                let c_str: *const std::os::raw::c_char = msg_send![desc, UTF8String];
+                if !c_str.is_null() {
+                    reason = std::ffi::CStr::from_ptr(c_str)
+                        .to_string_lossy()
+                        .to_string();
+                }
+            }
+            bail!("Failed to move to the trash: {}", reason);
+        }
+        let trashed: id = msg_send![trashed, path];
+        let c_str: *const std::os::raw::c_char = msg_send![trashed, UTF8String];
+        if c_str.is_null() {
+            bail!("Failed to get the path in the trash");
+        }
+        Ok(std::ffi::CStr::from_ptr(c_str)
+            .to_string_lossy()
+            .to_string())
+    })
+}
+
+/// The folder under the cursor, for the files dropped by a remote desktop session.
+pub fn folder_under_cursor() -> Option<String> {
+    let (x, y) = get_cursor_pos()?;
+    let output = std::process::Command::new("osascript")
+        .arg("-e")
+        .arg(FOLDER_AT_POINT_SCRIPT)
+        .arg(x.to_string())
+        .arg(y.to_string())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => {
+            let dir = String::from_utf8_lossy(&output.stdout).trim().to_owned();
+            Some(dir).filter(|dir| !dir.is_empty())
+        }
+        Ok(output) => {
+            log::warn!(
+                "Failed to get the folder under the cursor: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            None
+        }
+        Err(e) => {
+            log::error!("run osascript failed: {}", e);
+            None
+        }
+    }
+}
+
 #[inline]
 #[allow(dead_code)]
 fn get_server_start_time_of(p: &Process, path: &Path) -> Option<i64> {
@@ -1146,6 +1405,348 @@ pub fn change_resolution_directly(name: &str, width: usize, height: usize) -> Re
     Ok(())
 }
 
+// kCGEventMouseSubtypeTabletPoint
+const MOUSE_SUBTYPE_TABLET_POINT: i64 = 1;
+// NX_TABLET_POINTER_PEN, NX_TABLET_POINTER_ERASER
+const TABLET_POINTER_PEN: i64 = 1;
+const TABLET_POINTER_ERASER: i64 = 3;
+
+/// Post a proximity event, sent when the pen enters or leaves the range of the tablet, or
+/// turns to the eraser.
+pub fn post_tablet_proximity(enter: bool, eraser: bool) -> ResultType<()> {
+    use core_graphics::{
+        event::{CGEvent, CGEventTapLocation, CGEventType, EventField},
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| anyhow!("Failed to create event source"))?;
+    let event = CGEvent::new(source).map_err(|_| anyhow!("Failed to create event"))?;
+    event.set_type(CGEventType::TabletProximity);
+    event.set_integer_value_field(
+        EventField::TABLET_PROXIMITY_EVENT_POINTER_TYPE,
+        if eraser {
+            TABLET_POINTER_ERASER
+        } else {
+            TABLET_POINTER_PEN
+        },
+    );
+    event.set_integer_value_field(
+        EventField::TABLET_PROXIMITY_EVENT_ENTER_PROXIMITY,
+        enter as i64,
+    );
+    event.set_integer_value_field(
+        EventField::EVENT_SOURCE_USER_DATA,
+        enigo::ENIGO_INPUT_EXTRA_VALUE,
+    );
+    event.post(CGEventTapLocation::HID);
+    Ok(())
+}
+
+/// Post a mouse event with the tablet fields, `down` and `up` press and release the left
+/// button, a move while it is pressed is a drag.
+pub fn post_tablet_point(
+    x: i32,
+    y: i32,
+    pressure: f64,
+    tilt: (f64, f64),
+    down: bool,
+    up: bool,
+    pressed: bool,
+) -> ResultType<()> {
+    use core_graphics::{
+        event::{CGEvent, CGEventTapLocation, CGEventType, CGMouseButton, EventField},
+        event_source::{CGEventSource, CGEventSourceStateID},
+        geometry::CGPoint,
+    };
+    let event_type = if down {
+        CGEventType::LeftMouseDown
+    } else if up {
+        CGEventType::LeftMouseUp
+    } else if pressed {
+        CGEventType::LeftMouseDragged
+    } else {
+        CGEventType::MouseMoved
+    };
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| anyhow!("Failed to create event source"))?;
+    let event = CGEvent::new_mouse_event(
+        source,
+        event_type,
+        CGPoint::new(x as _, y as _),
+        CGMouseButton::Left,
+    )
+    .map_err(|_| anyhow!("Failed to create mouse event"))?;
+    event.set_integer_value_field(EventField::MOUSE_EVENT_SUB_TYPE, MOUSE_SUBTYPE_TABLET_POINT);
+    event.set_double_value_field(EventField::MOUSE_EVENT_PRESSURE, pressure);
+    event.set_double_value_field(EventField::TABLET_EVENT_POINT_PRESSURE, pressure);
+    event.set_double_value_field(EventField::TABLET_EVENT_TILT_X, tilt.0);
+    event.set_double_value_field(EventField::TABLET_EVENT_TILT_Y, tilt.1);
+    event.set_integer_value_field(
+        EventField::EVENT_SOURCE_USER_DATA,
+        enigo::ENIGO_INPUT_EXTRA_VALUE,
+    );
+    event.post(CGEventTapLocation::HID);
+    Ok(())
+}
+
+// The private fields of the gesture events, as posted by the trackpad driver.
+// kCGSEventTypeField, kCGSEventGesture
+const EVENT_TYPE_FIELD: u32 = 55;
+const EVENT_TYPE_GESTURE: i64 = 29;
+// kCGSEventGestureHIDType, with kIOHIDEventTypeRotation and kIOHIDEventTypeZoom
+const GESTURE_HID_TYPE_FIELD: u32 = 110;
+const GESTURE_HID_TYPE_ROTATION: i64 = 5;
+const GESTURE_HID_TYPE_ZOOM: i64 = 8;
+const GESTURE_ZOOM_VALUE_FIELD: u32 = 113;
+const GESTURE_ROTATION_VALUE_FIELD: u32 = 114;
+const GESTURE_PHASE_FIELD: u32 = 132;
+// kCGScrollWheelEventScrollPhase
+const SCROLL_PHASE_FIELD: u32 = 99;
+
+/// Post a magnify, a rotate or a continuous scroll event, as from the trackpad.
+pub fn post_gesture(
+    gesture: crate::touch_gesture::Gesture,
+    phase: crate::touch_gesture::Phase,
+) -> ResultType<()> {
+    use crate::touch_gesture::{Gesture, Phase};
+    use core_graphics::{
+        event::{CGEvent, CGEventTapLocation, CGEventType, EventField},
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+    // IOHIDEventPhaseBits, the same values as kCGScrollPhase.
+    let phase: i64 = match phase {
+        Phase::Began => 1,
+        Phase::Changed => 2,
+        Phase::Ended => 4,
+    };
+    let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+        .map_err(|_| anyhow!("Failed to create event source"))?;
+    let event = CGEvent::new(source).map_err(|_| anyhow!("Failed to create event"))?;
+    match gesture {
+        Gesture::Magnify(scale) => {
+            event.set_integer_value_field(EVENT_TYPE_FIELD, EVENT_TYPE_GESTURE);
+            event.set_integer_value_field(GESTURE_HID_TYPE_FIELD, GESTURE_HID_TYPE_ZOOM);
+            event.set_double_value_field(GESTURE_ZOOM_VALUE_FIELD, scale);
+            event.set_integer_value_field(GESTURE_PHASE_FIELD, phase);
+        }
+        Gesture::Rotate(degrees) => {
+            event.set_integer_value_field(EVENT_TYPE_FIELD, EVENT_TYPE_GESTURE);
+            event.set_integer_value_field(GESTURE_HID_TYPE_FIELD, GESTURE_HID_TYPE_ROTATION);
+            // Counterclockwise on macOS.
+            event.set_double_value_field(GESTURE_ROTATION_VALUE_FIELD, -degrees);
+            event.set_integer_value_field(GESTURE_PHASE_FIELD, phase);
+        }
+        Gesture::Scroll(x, y) => {
+            event.set_type(CGEventType::ScrollWheel);
+            event.set_integer_value_field(EventField::SCROLL_WHEEL_EVENT_IS_CONTINUOUS, 1);
+            event
+                .set_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_1, y as _);
+            event
+                .set_integer_value_field(EventField::SCROLL_WHEEL_EVENT_POINT_DELTA_AXIS_2, x as _);
+            event.set_integer_value_field(SCROLL_PHASE_FIELD, phase);
+        }
+    }
+    event.set_integer_value_field(
+        EventField::EVENT_SOURCE_USER_DATA,
+        enigo::ENIGO_INPUT_EXTRA_VALUE,
+    );
+    event.post(CGEventTapLocation::HID);
+    Ok(())
+}
+
+// NSEventTypeSystemDefined, and its subtype of the auxiliary control buttons.
+const NS_EVENT_TYPE_SYSTEM_DEFINED: u64 = 14;
+const NX_SUBTYPE_AUX_CONTROL_BUTTONS: i16 = 8;
+
+/// Post the press and the release of a key without a key code of its own.
+pub fn post_special_key(key: crate::special_keys::Key) -> ResultType<()> {
+    use crate::special_keys::Key;
+    use core_graphics::{
+        event::{CGEvent, CGEventTapLocation, EventField},
+        event_source::{CGEventSource, CGEventSourceStateID},
+    };
+    // NX_KEYTYPE_* of IOKit/hidsystem/ev_keymap.h, or the virtual key code.
+    let (media, key_code) = match key {
+        Key::VolumeUp => (Some(0), 0),
+        Key::VolumeDown => (Some(1), 0),
+        Key::BrightnessUp => (Some(2), 0),
+        Key::BrightnessDown => (Some(3), 0),
+        Key::Mute => (Some(7), 0),
+        Key::PlayPause => (Some(16), 0),
+        Key::NextTrack => (Some(17), 0),
+        Key::PreviousTrack => (Some(18), 0),
+        Key::MissionControl => (None, 0xa0),
+        Key::Launchpad => (None, 0x83),
+    };
+    for down in [true, false] {
+        match media {
+            Some(code) => post_media_key(code, down)?,
+            None => {
+                let source = CGEventSource::new(CGEventSourceStateID::HIDSystemState)
+                    .map_err(|_| anyhow!("Failed to create event source"))?;
+                let event = CGEvent::new_keyboard_event(source, key_code, down)
+                    .map_err(|_| anyhow!("Failed to create event"))?;
+                // Passed by the block of the input, as the other events of the peers.
+                event.set_integer_value_field(
+                    EventField::EVENT_SOURCE_USER_DATA,
+                    enigo::ENIGO_INPUT_EXTRA_VALUE,
+                );
+                event.post(CGEventTapLocation::HID);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn post_media_key(code: i64, down: bool) -> ResultType<()> {
+    let state: i64 = if down { 0xa } else { 0xb };
+    unsafe {
+        let event: id = msg_send![class!(NSEvent),
+            otherEventWithType: NS_EVENT_TYPE_SYSTEM_DEFINED
+            location: NSPoint::new(0., 0.)
+            modifierFlags: (state << 8) as u64
+            timestamp: 0f64
+            windowNumber: 0i64
+            context: nil
+            subtype: NX_SUBTYPE_AUX_CONTROL_BUTTONS
+            data1: (code << 16) | (state << 8)
+            data2: -1i64];
+        if event == nil {
+            bail!("Failed to create the event of the media key");
+        }
+        let cg_event: *const c_void = msg_send![event, CGEvent];
+        CGEventSetIntegerValueField(
+            cg_event,
+            core_graphics::event::EventField::EVENT_SOURCE_USER_DATA,
+            enigo::ENIGO_INPUT_EXTRA_VALUE,
+        );
+        // kCGHIDEventTap
+        CGEventPost(0, cg_event);
+    }
+    Ok(())
+}
+
+#[link(name = "Carbon", kind = "framework")]
+extern "C" {
+    static kTISPropertyInputSourceID: CFStringRef;
+    static kTISPropertyLocalizedName: CFStringRef;
+    static kTISPropertyInputSourceCategory: CFStringRef;
+    static kTISPropertyInputSourceIsSelectCapable: CFStringRef;
+    static kTISCategoryKeyboardInputSource: CFStringRef;
+    fn TISCopyCurrentKeyboardInputSource() -> *mut c_void;
+    fn TISCreateInputSourceList(
+        properties: CFDictionaryRef,
+        include_all_installed: u8,
+    ) -> core_foundation::array::CFArrayRef;
+    fn TISGetInputSourceProperty(source: *mut c_void, key: CFStringRef) -> *const c_void;
+    fn TISSelectInputSource(source: *mut c_void) -> i32;
+}
+
+// The input sources must be used on the main thread since macOS 14.
+fn on_main_thread<T: Send>(f: impl FnOnce() -> T + Send) -> T {
+    let is_main: BOOL = unsafe { msg_send![class!(NSThread), isMainThread] };
+    if is_main == YES {
+        f()
+    } else {
+        dispatch::Queue::main().exec_sync(f)
+    }
+}
+
+unsafe fn input_source_string(source: *mut c_void, key: CFStringRef) -> String {
+    let s = TISGetInputSourceProperty(source, key) as CFStringRef;
+    if s.is_null() {
+        return String::new();
+    }
+    core_foundation::string::CFString::wrap_under_get_rule(s).to_string()
+}
+
+// The enabled keyboard layouts, with the id if any, the caller releases the list.
+unsafe fn keyboard_input_sources(id: Option<&str>) -> core_foundation::array::CFArrayRef {
+    use core_foundation::{
+        base::{CFType, TCFType},
+        boolean::CFBoolean,
+        dictionary::CFDictionary,
+        string::CFString,
+    };
+    let mut pairs = vec![
+        (
+            CFString::wrap_under_get_rule(kTISPropertyInputSourceCategory),
+            CFString::wrap_under_get_rule(kTISCategoryKeyboardInputSource).as_CFType(),
+        ),
+        (
+            CFString::wrap_under_get_rule(kTISPropertyInputSourceIsSelectCapable),
+            CFBoolean::true_value().as_CFType(),
+        ),
+    ];
+    if let Some(id) = id {
+        pairs.push((
+            CFString::wrap_under_get_rule(kTISPropertyInputSourceID),
+            CFString::new(id).as_CFType(),
+        ));
+    }
+    let filter = CFDictionary::<CFString, CFType>::from_CFType_pairs(&pairs);
+    TISCreateInputSourceList(filter.as_concrete_TypeRef(), 0)
+}
+
+/// The id and the name of the enabled keyboard layouts.
+pub fn get_keyboard_layouts() -> Vec<(String, String)> {
+    on_main_thread(|| unsafe {
+        let list = keyboard_input_sources(None);
+        if list.is_null() {
+            return vec![];
+        }
+        let v = (0..CFArrayGetCount(list))
+            .map(|i| {
+                let source = CFArrayGetValueAtIndex(list, i) as *mut c_void;
+                (
+                    input_source_string(source, kTISPropertyInputSourceID),
+                    input_source_string(source, kTISPropertyLocalizedName),
+                )
+            })
+            .filter(|(id, _)| !id.is_empty())
+            .collect();
+        core_foundation::base::CFRelease(list as _);
+        v
+    })
+}
+
+/// The id of the active keyboard layout, e.g. "com.apple.keylayout.US".
+pub fn get_keyboard_layout() -> Option<String> {
+    on_main_thread(|| unsafe {
+        let source = TISCopyCurrentKeyboardInputSource();
+        if source.is_null() {
+            return None;
+        }
+        let id = input_source_string(source, kTISPropertyInputSourceID);
+        core_foundation::base::CFRelease(source as _);
+        Some(id).filter(|id| !id.is_empty())
+    })
+}
+
+pub fn select_keyboard_layout(id: &str) -> ResultType<()> {
+    let id = id.to_owned();
+    let status = on_main_thread(move || unsafe {
+        let list = keyboard_input_sources(Some(&id));
+        if list.is_null() {
+            return None;
+        }
+        let status = if CFArrayGetCount(list) > 0 {
+            Some(TISSelectInputSource(
+                CFArrayGetValueAtIndex(list, 0) as *mut c_void
+            ))
+        } else {
+            None
+        };
+        core_foundation::base::CFRelease(list as _);
+        status
+    });
+    match status {
+        Some(0) => Ok(()),
+        Some(status) => bail!("Failed to select the keyboard layout, status {}", status),
+        None => bail!("No such keyboard layout"),
+    }
+}
+
 pub fn check_super_user_permission() -> ResultType<bool> {
     unsafe { Ok(MacCheckAdminAuthorization() == YES) }
 }
@@ -1,6 +1,9 @@
 pub use macos::*;
-pub mod macos;
+#[cfg(feature = "ui")]
 pub mod delegate;
+pub mod macos;
+pub mod repair;
+pub mod virtual_display;
 use hbb_common::{
     message_proto::CursorData,
     sysinfo::{Pid, System},
@@ -0,0 +1,368 @@
+//! `--repair-service`: the checks and the repairs of an installation which stopped working,
+//! without the prompt of the install script, so it must be run as root, e.g. with sudo over ssh
+//! or by an MDM.
+//!
+//! Each step checks one thing: the executables in the bundle, the processes of an old version,
+//! the plists of the daemon and of the agent, the ipc sockets left by dead processes, then the
+//! daemon and the agent themselves. A step is "ok", or "repaired", "failed", or "broken" with
+//! `--dry-run`, with what was wrong in its detail. The report is printed as json.
+
+use hbb_common::{
+    config::Config,
+    log,
+    sysinfo::{Pid, System},
+    ResultType,
+};
+use serde_derive::Serialize;
+use std::{
+    fs,
+    os::unix::{
+        fs::{MetadataExt, PermissionsExt},
+        net::UnixStream,
+    },
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+
+const PLIST_MODE: u32 = 0o644;
+const EXEC_MODE: u32 = 0o755;
+// The ipc of the server and of the service.
+const IPC_POSTFIXES: [&str; 2] = ["", "_service"];
+const START_WAIT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Ok,
+    Broken,
+    Repaired,
+    Failed,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Step {
+    pub name: &'static str,
+    pub status: Status,
+    pub detail: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct Report {
+    pub ok: bool,
+    pub steps: Vec<Step>,
+}
+
+struct Repair {
+    dry_run: bool,
+    steps: Vec<Step>,
+}
+
+impl Repair {
+    // `broken` is what is wrong, none if it is fine.
+    fn step(
+        &mut self,
+        name: &'static str,
+        broken: Option<String>,
+        fix: impl FnOnce() -> ResultType<()>,
+    ) -> Status {
+        let (status, detail) = match broken {
+            None => (Status::Ok, String::new()),
+            Some(detail) if self.dry_run => (Status::Broken, detail),
+            Some(detail) => match fix() {
+                Ok(()) => (Status::Repaired, detail),
+                Err(e) => (Status::Failed, format!("{}: {}", detail, e)),
+            },
+        };
+        log::info!("Repair step {}: {:?} {}", name, status, detail);
+        self.steps.push(Step {
+            name,
+            status,
+            detail,
+        });
+        status
+    }
+
+    fn fail(&mut self, name: &'static str, detail: String) {
+        self.steps.push(Step {
+            name,
+            status: Status::Failed,
+            detail,
+        });
+    }
+
+    fn finish(self) -> Report {
+        Report {
+            ok: self
+                .steps
+                .iter()
+                .all(|s| matches!(s.status, Status::Ok | Status::Repaired)),
+            steps: self.steps,
+        }
+    }
+}
+
+pub fn run(dry_run: bool) -> Report {
+    let mut r = Repair {
+        dry_run,
+        steps: vec![],
+    };
+    if !super::is_root() {
+        r.fail("root", "Run it as root, e.g. with sudo".to_owned());
+        return r.finish();
+    }
+    let app_name = crate::get_app_name();
+    let full_name = crate::get_full_name();
+    let bin_dir = PathBuf::from(format!("/Applications/{}.app/Contents/MacOS", app_name));
+    if !bin_dir.join(&app_name).exists() {
+        r.fail("app", format!("{} is not installed", bin_dir.display()));
+        return r.finish();
+    }
+
+    let not_executable = not_executable(&bin_dir);
+    r.step(
+        "executables",
+        (!not_executable.is_empty()).then(|| format!("Not executable: {:?}", not_executable)),
+        || {
+            for path in &not_executable {
+                fs::set_permissions(path, fs::Permissions::from_mode(EXEC_MODE))?;
+            }
+            Ok(())
+        },
+    );
+
+    let stale = stale_processes(&app_name, &bin_dir);
+    r.step(
+        "old_processes",
+        (!stale.is_empty()).then(|| format!("Running outside of the bundle: {:?}", stale)),
+        || {
+            let mut sys = System::new();
+            sys.refresh_processes();
+            for (pid, _) in &stale {
+                if let Some(p) = sys.process(*pid) {
+                    p.kill();
+                }
+            }
+            Ok(())
+        },
+    );
+
+    let daemon = Unit {
+        label: format!("{}_service", full_name),
+        file: format!("/Library/LaunchDaemons/{}_service.plist", full_name),
+        template: "daemon.plist",
+    };
+    let agent = Unit {
+        label: format!("{}_server", full_name),
+        file: format!("/Library/LaunchAgents/{}_server.plist", full_name),
+        template: "agent.plist",
+    };
+    let mut reload = false;
+    for (name, unit) in [("daemon_plist", &daemon), ("agent_plist", &agent)] {
+        let Some(expected) = super::macos::privileges_script(unit.template) else {
+            r.fail(name, format!("No {}", unit.template));
+            continue;
+        };
+        let status = r.step(name, check_plist(&unit.file, &expected), || {
+            write_plist(&unit.file, &expected)
+        });
+        reload |= status == Status::Repaired;
+    }
+
+    for postfix in IPC_POSTFIXES {
+        let path = Config::ipc_path(postfix);
+        let stale = Path::new(&path).exists() && UnixStream::connect(&path).is_err();
+        r.step(
+            "ipc_socket",
+            stale.then(|| format!("Nobody listens on {}", path)),
+            || {
+                fs::remove_file(&path)?;
+                fs::remove_file(format!("{}.pid", path)).ok();
+                Ok(())
+            },
+        );
+    }
+
+    let state = launchctl_list(&daemon.label);
+    let broken = match (&state, reload) {
+        (None, _) => Some("Not loaded".to_owned()),
+        (Some(_), true) => Some("Loaded with the old plist".to_owned()),
+        (Some(None), false) => Some("Not running".to_owned()),
+        (Some(Some(_)), false) => None,
+    };
+    r.step("daemon", broken, || {
+        if state.is_some() {
+            launchctl(&["unload", "-w", &daemon.file]).ok();
+        }
+        launchctl(&["load", "-w", &daemon.file])?;
+        launchctl(&["kickstart", "-k", &format!("system/{}", daemon.label)])?;
+        std::thread::sleep(START_WAIT);
+        match launchctl_list(&daemon.label) {
+            Some(Some(_)) => Ok(()),
+            _ => hbb_common::bail!("Still not running"),
+        }
+    });
+
+    let uid = super::get_active_userid();
+    let running = !super::get_pids_of_process_with_first_arg(&app_name, "--server").is_empty();
+    let broken = if uid.is_empty() {
+        None
+    } else if !running {
+        Some(format!("Not running for the user {}", uid))
+    } else if reload {
+        Some("Running with the old plist".to_owned())
+    } else {
+        None
+    };
+    r.step("agent", broken, || {
+        launchctl(&["asuser", &uid, "launchctl", "unload", "-w", &agent.file]).ok();
+        launchctl(&["asuser", &uid, "launchctl", "load", "-w", &agent.file])?;
+        std::thread::sleep(START_WAIT);
+        if super::get_pids_of_process_with_first_arg(&app_name, "--server").is_empty() {
+            hbb_common::bail!("Still not running");
+        }
+        Ok(())
+    });
+    r.finish()
+}
+
+struct Unit {
+    label: String,
+    file: String,
+    template: &'static str,
+}
+
+fn not_executable(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .flatten()
+        .filter(|e| {
+            e.metadata()
+                .map(|m| m.is_file() && m.permissions().mode() & 0o111 == 0)
+                .unwrap_or(false)
+        })
+        .map(|e| e.path())
+        .collect()
+}
+
+// The processes of the app which do not run the executables of the bundle, e.g. left by an
+// update, but this one.
+fn stale_processes(app_name: &str, bin_dir: &Path) -> Vec<(Pid, PathBuf)> {
+    let mut sys = System::new();
+    sys.refresh_processes();
+    let bin_dir = fs::canonicalize(bin_dir).unwrap_or(bin_dir.to_path_buf());
+    sys.processes()
+        .iter()
+        .filter(|(pid, p)| {
+            pid.to_string() != std::process::id().to_string()
+                && p.name().eq_ignore_ascii_case(app_name)
+                && !p.exe().as_os_str().is_empty()
+                && !fs::canonicalize(p.exe())
+                    .unwrap_or(p.exe().to_path_buf())
+                    .starts_with(&bin_dir)
+        })
+        .map(|(pid, p)| (*pid, p.exe().to_path_buf()))
+        .collect()
+}
+
+fn check_plist(file: &str, expected: &str) -> Option<String> {
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(_) => return Some(format!("Missing {}", file)),
+    };
+    if content.trim() != expected.trim() {
+        return Some(format!("Outdated {}", file));
+    }
+    // launchd ignores the plists which others can write.
+    match fs::metadata(file) {
+        Ok(m) if wrong_owner_or_mode(m.uid(), m.gid(), m.permissions().mode()) => {
+            Some(format!("Wrong owner or mode of {}", file))
+        }
+        _ => None,
+    }
+}
+
+fn wrong_owner_or_mode(uid: u32, gid: u32, mode: u32) -> bool {
+    uid != 0 || gid != 0 || mode & 0o777 != PLIST_MODE
+}
+
+fn write_plist(file: &str, content: &str) -> ResultType<()> {
+    fs::write(file, content)?;
+    std::os::unix::fs::chown(file, Some(0), Some(0))?;
+    fs::set_permissions(file, fs::Permissions::from_mode(PLIST_MODE))?;
+    Ok(())
+}
+
+fn launchctl(args: &[&str]) -> ResultType<()> {
+    let output = Command::new("launchctl").args(args).output()?;
+    if !output.status.success() {
+        hbb_common::bail!(
+            "launchctl {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(())
+}
+
+// None if the job is not loaded, then its pid if it is running.
+fn launchctl_list(label: &str) -> Option<Option<u32>> {
+    let output = Command::new("launchctl")
+        .args(["list", label])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    Some(parse_pid(&String::from_utf8_lossy(&output.stdout)))
+}
+
+// The `"PID" = 123;` line of `launchctl list <label>`.
+fn parse_pid(output: &str) -> Option<u32> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once('=')?;
+        if key.trim() != "\"PID\"" {
+            return None;
+        }
+        value.trim().trim_end_matches(';').trim().parse().ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn repair_service() {
+        let output = "{\n\t\"Label\" = \"com.carriez.RustDesk_service\";\n\t\"LastExitStatus\" = 0;\n\t\"PID\" = 321;\n};";
+        assert_eq!(parse_pid(output), Some(321));
+        assert_eq!(parse_pid("{\n\t\"LastExitStatus\" = 256;\n};"), None);
+        assert!(!wrong_owner_or_mode(0, 0, 0o100644));
+        assert!(wrong_owner_or_mode(501, 0, 0o100644));
+        assert!(wrong_owner_or_mode(0, 0, 0o100666));
+
+        let mut r = Repair {
+            dry_run: true,
+            steps: vec![],
+        };
+        assert_eq!(r.step("a", None, || unreachable!()), Status::Ok);
+        assert_eq!(
+            r.step("b", Some("x".to_owned()), || unreachable!()),
+            Status::Broken
+        );
+        r.dry_run = false;
+        assert_eq!(
+            r.step("c", Some("x".to_owned()), || Ok(())),
+            Status::Repaired
+        );
+        assert!(!r.finish().ok);
+        let r = Repair {
+            dry_run: false,
+            steps: vec![],
+        };
+        let json = serde_json::to_value(r.finish()).unwrap();
+        assert_eq!(json["ok"], true);
+    }
+}
@@ -0,0 +1,196 @@
+//! Awareness of the virtual machines, and a virtual display for the hosts without a monitor.
+//!
+//! A Mac in a virtual machine or a server farm often runs without a monitor, so there is no
+//! display to capture. With `ARG_AUTO_VIRTUAL_X`, the server process adds a virtual display when
+//! there is no active one, the way Xvfb is started for the Linux hosts of a CI, and removes it
+//! when it stops. The monitors are the ones of the `virtual_display` library, with
+//! `CGVirtualDisplay` of macOS 11+, they go away with the process anyway.
+//!
+//! With `OPTION_AUTO_VIRTUAL_DISPLAY`, `display_service` also adds one whenever no monitor is
+//! online, e.g. after the last one is unplugged, and removes it when a monitor is back.
+//!
+//! Both are the same monitor of the library, with the modes of `OPTION_VIRTUAL_DISPLAY_MODES`.
+//! A client changes them with `crate::monitor_modes`, the monitor plugged in switches to the first.
+
+use core_graphics::display::CGDisplay;
+use hbb_common::{bail, config::Config, log, ResultType};
+use std::sync::Mutex;
+use virtual_display::MonitorMode;
+
+pub const ARG_AUTO_VIRTUAL_X: &str = "--auto-virtual-x";
+pub const OPTION_AUTO_VIRTUAL_DISPLAY: &str = "allow-auto-virtual-display";
+/// The modes as "3440x1440@100,1920x1080", see `virtual_display::parse_modes`.
+pub const OPTION_VIRTUAL_DISPLAY_MODES: &str = "virtual-display-modes";
+pub const DEFAULT_MODES: &str = "1920x1080@60";
+// The monitor of the library. `check_auto` adds it only when no virtual display is online, so
+// it is never the one of `ARG_AUTO_VIRTUAL_X` too.
+const MONITOR_INDEX: u32 = 0;
+
+lazy_static::lazy_static! {
+    // The display added by `check_auto`, not the one of `ARG_AUTO_VIRTUAL_X`.
+    static ref AUTO: Mutex<Option<VirtualDisplay>> = Default::default();
+    // Held by the calls to the library, the monitor is shared by `ARG_AUTO_VIRTUAL_X`,
+    // `check_auto` and `set_modes`. Taken after `AUTO`.
+    static ref MONITOR: Mutex<()> = Default::default();
+}
+
+extern "C" {
+    fn MacVirtualDisplayCount() -> u32;
+    fn MacPhysicalDisplayCount() -> u32;
+}
+
+/// Whether this Mac is a virtual machine.
+pub fn is_vm() -> bool {
+    std::process::Command::new("sysctl")
+        .args(["-n", "kern.hv_vmm_present"])
+        .output()
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim() == "1")
+        .unwrap_or(false)
+}
+
+pub fn active_display_count() -> usize {
+    CGDisplay::active_displays()
+        .map(|displays| displays.len())
+        .unwrap_or(0)
+}
+
+/// The modes of a list, the error tells the first one not valid.
+pub fn parse_modes(list: &str) -> ResultType<Vec<MonitorMode>> {
+    let modes = virtual_display::parse_modes(list)?;
+    if modes.is_empty() {
+        bail!("No mode");
+    }
+    if let Some(m) = modes.iter().find(|m| {
+        !(640..=8192).contains(&m.width)
+            || !(480..=8192).contains(&m.height)
+            || !(1..=240).contains(&m.sync)
+    }) {
+        bail!("Invalid mode {}x{}@{}", m.width, m.height, m.sync);
+    }
+    Ok(modes)
+}
+
+/// The modes of the option, the default ones if it is not valid.
+fn modes() -> Vec<MonitorMode> {
+    parse_modes(&Config::get_option(OPTION_VIRTUAL_DISPLAY_MODES))
+        .or_else(|_| parse_modes(DEFAULT_MODES))
+        .unwrap_or_default()
+}
+
+/// The modes of the option, as a list.
+pub fn get_modes() -> String {
+    let value = Config::get_option(OPTION_VIRTUAL_DISPLAY_MODES);
+    if parse_modes(&value).is_ok() {
+        value
+    } else {
+        DEFAULT_MODES.to_owned()
+    }
+}
+
+/// Save the modes of the virtual display, the monitor switches to the first if it is plugged in.
+pub fn set_modes(list: &str) -> ResultType<()> {
+    let modes = parse_modes(list)?;
+    let _lock = MONITOR.lock().unwrap();
+    virtual_display::update_monitor_modes(MONITOR_INDEX, &modes)?;
+    Config::set_option(
+        OPTION_VIRTUAL_DISPLAY_MODES.to_owned(),
+        list.trim().to_owned(),
+    );
+    log::info!("Virtual display modes set to {}", list);
+    Ok(())
+}
+
+/// Add a virtual display when no monitor is online and `OPTION_AUTO_VIRTUAL_DISPLAY` is on, and
+/// remove it when one is back or the option is off.
+pub fn check_auto() {
+    let mut auto = AUTO.lock().unwrap();
+    let enabled = Config::get_bool_option(OPTION_AUTO_VIRTUAL_DISPLAY);
+    let physical = unsafe { MacPhysicalDisplayCount() };
+    if auto.is_some() {
+        if !enabled || physical > 0 {
+            log::info!(
+                "Remove the automatic virtual display, monitors: {}",
+                physical
+            );
+            *auto = None;
+        }
+    } else if enabled && physical == 0 && unsafe { MacVirtualDisplayCount() } == 0 {
+        *auto = VirtualDisplay::create();
+    }
+}
+
+/// Remove the display added by `check_auto`, when the display service stops.
+pub fn remove_auto() {
+    if AUTO.lock().unwrap().take().is_some() {
+        log::info!("Remove the automatic virtual display, the service stops");
+    }
+}
+
+/// The monitor of the library added, plugged out when it is dropped.
+pub struct VirtualDisplay(u32);
+
+impl VirtualDisplay {
+    fn create() -> Option<Self> {
+        let modes = modes();
+        let _lock = MONITOR.lock().unwrap();
+        let res = virtual_display::create_device()
+            .and_then(|_| virtual_display::update_monitor_modes(MONITOR_INDEX, &modes))
+            .and_then(|_| virtual_display::plug_in_monitor(MONITOR_INDEX, 0, 0));
+        if let Err(e) = res {
+            log::error!("Failed to add the virtual display: {}", e);
+            return None;
+        }
+        log::info!("Virtual display added, modes: {:?}", modes);
+        Some(Self(MONITOR_INDEX))
+    }
+
+    /// Add a virtual display if there is no active display.
+    pub fn provision_if_needed() -> Option<Self> {
+        let displays = active_display_count();
+        log::info!(
+            "Virtual machine: {}, active displays: {}",
+            is_vm(),
+            displays
+        );
+        if displays > 0 {
+            return None;
+        }
+        Self::create()
+    }
+}
+
+impl Drop for VirtualDisplay {
+    fn drop(&mut self) {
+        let _lock = MONITOR.lock().unwrap();
+        match virtual_display::plug_out_monitor(self.0) {
+            Ok(()) => log::info!("Virtual display {} removed", self.0),
+            Err(e) => log::error!("Failed to remove the virtual display {}: {}", self.0, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn virtual_display_modes() {
+        assert_eq!(
+            modes_of("3440x1440@100, 1080 x 1920"),
+            vec![(3440, 1440, 100), (1080, 1920, 60)]
+        );
+        assert!(parse_modes("").is_err());
+        assert!(parse_modes("100x100").is_err());
+        assert!(parse_modes("1920x1080@0").is_err());
+        assert!(parse_modes("1920*1080").is_err());
+        assert_eq!(modes_of(DEFAULT_MODES), vec![(1920, 1080, 60)]);
+    }
+
+    fn modes_of(list: &str) -> Vec<(u32, u32, u32)> {
+        parse_modes(list)
+            .unwrap()
+            .iter()
+            .map(|m| (m.width as _, m.height as _, m.sync as _))
+            .collect()
+    }
+}
@@ -0,0 +1,32 @@
+//! The extensions of the protocol carried by `Misc::PluginRequest`.
+//!
+//! The protocol has no message for most of the features added over it, so each of them reserves
+//! an id and sends its messages as a `PluginRequest` with this id, mostly with a json content. A
+//! peer which does not know the id ignores the message, and a host tells the ones it supports in
+//! the platform additions, so the client does not send what could not be handled.
+
+use hbb_common::{
+    bytes::Bytes,
+    message_proto::{Message, Misc, PluginRequest},
+};
+
+/// The message with the json of `content`.
+pub fn make<T: serde::Serialize + ?Sized>(id: &str, content: &T) -> Message {
+    make_raw(
+        id,
+        Bytes::from(serde_json::to_vec(content).unwrap_or_default()),
+    )
+}
+
+/// The message with a content already encoded.
+pub fn make_raw(id: &str, content: Bytes) -> Message {
+    let mut misc = Misc::new();
+    misc.set_plugin_request(PluginRequest {
+        id: id.to_owned(),
+        content,
+        ..Default::default()
+    });
+    let mut msg = Message::new();
+    msg.set_misc(misc);
+    msg
+}
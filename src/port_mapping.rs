@@ -0,0 +1,546 @@
+//! Port mapping of the direct server on the gateway, with PCP, NAT-PMP or UPnP.
+//!
+//! A mapping opens the host to the internet, so it is only done with `OPTION_PORT_MAPPING` set
+//! and the direct server listening. The TCP port of the direct server is mapped to the same
+//! external port if the gateway accepts it, with PCP, then NAT-PMP, then UPnP IGD, and renewed
+//! at half of its lifetime. It is deleted when the option or the direct server is turned off,
+//! and when the port changes. The state is kept in `status()`, for `--doctor`.
+
+use hbb_common::{
+    anyhow::anyhow,
+    bail,
+    config::{option2bool, Config},
+    log, ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    sync::{Mutex, Once},
+    time::{Duration, Instant},
+};
+
+pub const OPTION_PORT_MAPPING: &str = "enable-port-mapping";
+
+const LIFETIME: u32 = 3600;
+const GATEWAY_PORT: u16 = 5351;
+const UDP_TIMEOUT: Duration = Duration::from_millis(1000);
+const HTTP_TIMEOUT: Duration = Duration::from_secs(3);
+const RETRY: Duration = Duration::from_secs(300);
+const CHECK_INTERVAL: Duration = Duration::from_secs(3);
+const PROTO_TCP: u8 = 6;
+const SSDP_ADDR: &str = "239.255.255.250:1900";
+const IGD: &str = "urn:schemas-upnp-org:device:InternetGatewayDevice:1";
+const WAN_SERVICES: [&str; 2] = [
+    "urn:schemas-upnp-org:service:WANIPConnection:1",
+    "urn:schemas-upnp-org:service:WANPPPConnection:1",
+];
+
+lazy_static::lazy_static! {
+    static ref STATUS: Mutex<Status> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Protocol {
+    Pcp,
+    NatPmp,
+    Upnp,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub enabled: bool,
+    pub protocol: Option<Protocol>,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub external_ip: String,
+    /// Seconds to the end of the lifetime of the mapping.
+    pub expires_in: u64,
+    pub error: String,
+}
+
+pub fn status() -> Status {
+    STATUS.lock().unwrap().clone()
+}
+
+#[derive(Debug, Clone)]
+struct Mapping {
+    protocol: Protocol,
+    gateway: IpAddr,
+    /// The control url, for UPnP.
+    control: Option<(String, &'static str)>,
+    internal_port: u16,
+    external_port: u16,
+    external_ip: String,
+    lifetime: u32,
+    created: Instant,
+}
+
+impl Mapping {
+    fn expires_in(&self) -> u64 {
+        (self.lifetime as u64).saturating_sub(self.created.elapsed().as_secs())
+    }
+
+    fn needs_renewal(&self) -> bool {
+        self.created.elapsed().as_secs() >= self.lifetime as u64 / 2
+    }
+}
+
+fn local_ipv4() -> ResultType<Ipv4Addr> {
+    let interface = default_net::get_default_interface().map_err(|e| anyhow!(e))?;
+    match interface.ipv4.first() {
+        Some(net) => Ok(net.addr),
+        None => bail!("No IPv4 address on {}", interface.name),
+    }
+}
+
+fn gateway() -> ResultType<IpAddr> {
+    Ok(default_net::get_default_gateway()
+        .map_err(|e| anyhow!(e))?
+        .ip_addr)
+}
+
+fn udp_exchange(gateway: IpAddr, request: &[u8], buf: &mut [u8]) -> ResultType<usize> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.connect(SocketAddr::new(gateway, GATEWAY_PORT))?;
+    socket.set_read_timeout(Some(UDP_TIMEOUT))?;
+    // RFC 6886 retransmits with a doubling timeout, two tries are enough on a LAN.
+    for _ in 0..2 {
+        socket.send(request)?;
+        if let Ok(n) = socket.recv(buf) {
+            return Ok(n);
+        }
+    }
+    bail!("No answer from {}", gateway)
+}
+
+// NAT-PMP, RFC 6886.
+
+fn natpmp_map_request(internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut req = [0u8; 12];
+    // Version 0, opcode 2 for TCP.
+    req[1] = 2;
+    req[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    req[6..8].copy_from_slice(&external_port.to_be_bytes());
+    req[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    req
+}
+
+/// The external port and the lifetime.
+fn natpmp_parse_map(res: &[u8]) -> ResultType<(u16, u32)> {
+    if res.len() < 16 || res[0] != 0 || res[1] != 128 + 2 {
+        bail!("Invalid NAT-PMP answer");
+    }
+    let result = u16::from_be_bytes([res[2], res[3]]);
+    if result != 0 {
+        bail!("NAT-PMP error {}", result);
+    }
+    let external_port = u16::from_be_bytes([res[10], res[11]]);
+    let lifetime = u32::from_be_bytes([res[12], res[13], res[14], res[15]]);
+    Ok((external_port, lifetime))
+}
+
+fn natpmp_external_ip(gateway: IpAddr) -> String {
+    let mut buf = [0u8; 16];
+    match udp_exchange(gateway, &[0, 0], &mut buf) {
+        Ok(n) if n >= 12 && buf[1] == 128 && buf[2..4] == [0, 0] => {
+            Ipv4Addr::new(buf[8], buf[9], buf[10], buf[11]).to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+fn natpmp_map(
+    gateway: IpAddr,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> ResultType<(u16, u32)> {
+    let mut buf = [0u8; 16];
+    let n = udp_exchange(
+        gateway,
+        &natpmp_map_request(internal_port, external_port, lifetime),
+        &mut buf,
+    )?;
+    natpmp_parse_map(&buf[..n])
+}
+
+// PCP, RFC 6887.
+
+fn pcp_map_request(
+    client: Ipv4Addr,
+    nonce: [u8; 12],
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> [u8; 60] {
+    let mut req = [0u8; 60];
+    req[0] = 2;
+    // MAP
+    req[1] = 1;
+    req[4..8].copy_from_slice(&lifetime.to_be_bytes());
+    req[8..24].copy_from_slice(&client.to_ipv6_mapped().octets());
+    req[24..36].copy_from_slice(&nonce);
+    req[36] = PROTO_TCP;
+    req[40..42].copy_from_slice(&internal_port.to_be_bytes());
+    req[42..44].copy_from_slice(&external_port.to_be_bytes());
+    req[44..60].copy_from_slice(&Ipv4Addr::UNSPECIFIED.to_ipv6_mapped().octets());
+    req
+}
+
+/// The external port, the external ip and the lifetime.
+fn pcp_parse_map(res: &[u8], nonce: [u8; 12]) -> ResultType<(u16, String, u32)> {
+    if res.len() < 60 || res[0] != 2 || res[1] != 0x80 | 1 {
+        bail!("Invalid PCP answer");
+    }
+    if res[3] != 0 {
+        bail!("PCP error {}", res[3]);
+    }
+    if res[24..36] != nonce {
+        bail!("PCP answer to another request");
+    }
+    let lifetime = u32::from_be_bytes([res[4], res[5], res[6], res[7]]);
+    let external_port = u16::from_be_bytes([res[42], res[43]]);
+    let mut ip = [0u8; 16];
+    ip.copy_from_slice(&res[44..60]);
+    let ip = Ipv6Addr::from(ip);
+    let ip = ip
+        .to_ipv4_mapped()
+        .map(|ip| ip.to_string())
+        .unwrap_or(ip.to_string());
+    Ok((external_port, ip, lifetime))
+}
+
+fn pcp_map(
+    gateway: IpAddr,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> ResultType<(u16, String, u32)> {
+    // The nonce must be kept across the renewals, the client ip and the port are stable enough.
+    let client = local_ipv4()?;
+    let mut nonce = [0u8; 12];
+    nonce[..4].copy_from_slice(&client.octets());
+    nonce[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    for (n, b) in nonce[6..].iter_mut().zip(hbb_common::get_uuid()) {
+        *n = b;
+    }
+    let mut buf = [0u8; 1100];
+    let n = udp_exchange(
+        gateway,
+        &pcp_map_request(client, nonce, internal_port, external_port, lifetime),
+        &mut buf,
+    )?;
+    pcp_parse_map(&buf[..n], nonce)
+}
+
+// UPnP IGD.
+
+fn http_request(url: &str, method: &str, headers: &str, body: &str) -> ResultType<String> {
+    let rest = url.strip_prefix("http://").unwrap_or(url);
+    let (host, path) = match rest.find('/') {
+        Some(i) => (&rest[..i], &rest[i..]),
+        None => (rest, "/"),
+    };
+    let addr: SocketAddr = host.parse()?;
+    let mut stream = TcpStream::connect_timeout(&addr, HTTP_TIMEOUT)?;
+    stream.set_read_timeout(Some(HTTP_TIMEOUT))?;
+    write!(
+        stream,
+        "{method} {path} HTTP/1.0\r\nHost: {host}\r\n{headers}Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut res = String::new();
+    stream.read_to_string(&mut res)?;
+    let (head, body) = res.split_once("\r\n\r\n").unwrap_or((&res, ""));
+    if !head.starts_with("HTTP/1.1 200") && !head.starts_with("HTTP/1.0 200") {
+        bail!("{}", head.lines().next().unwrap_or_default());
+    }
+    Ok(body.to_owned())
+}
+
+fn header_value<'a>(res: &'a str, name: &str) -> Option<&'a str> {
+    res.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        key.trim()
+            .eq_ignore_ascii_case(name)
+            .then_some(value.trim())
+    })
+}
+
+fn xml_text<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let start = xml.find(&format!("<{}>", tag))? + tag.len() + 2;
+    let end = xml[start..].find(&format!("</{}>", tag))? + start;
+    Some(xml[start..end].trim())
+}
+
+/// The control url of the WAN connection service in the description of the gateway.
+fn control_url(location: &str, description: &str) -> Option<(String, &'static str)> {
+    for service in WAN_SERVICES {
+        let Some(pos) = description.find(&format!("<serviceType>{}</serviceType>", service)) else {
+            continue;
+        };
+        let rest = &description[pos..];
+        let rest = &rest[..rest.find("</service>").unwrap_or(rest.len())];
+        let path = xml_text(rest, "controlURL")?;
+        if path.starts_with("http://") {
+            return Some((path.to_owned(), service));
+        }
+        let base = location.strip_prefix("http://").unwrap_or(location);
+        let host = &base[..base.find('/').unwrap_or(base.len())];
+        let slash = if path.starts_with('/') { "" } else { "/" };
+        return Some((format!("http://{}{}{}", host, slash, path), service));
+    }
+    None
+}
+
+fn upnp_discover() -> ResultType<(String, &'static str)> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    socket.set_read_timeout(Some(UDP_TIMEOUT))?;
+    let search = format!(
+        "M-SEARCH * HTTP/1.1\r\nHOST: {SSDP_ADDR}\r\nST: {IGD}\r\nMAN: \"ssdp:discover\"\r\nMX: 1\r\n\r\n"
+    );
+    socket.send_to(search.as_bytes(), SSDP_ADDR)?;
+    let mut buf = [0u8; 2048];
+    let n = socket.recv(&mut buf)?;
+    let res = String::from_utf8_lossy(&buf[..n]);
+    let Some(location) = header_value(&res, "location") else {
+        bail!("No location in the answer of the gateway");
+    };
+    let description = http_request(location, "GET", "", "")?;
+    match control_url(location, &description) {
+        Some(control) => Ok(control),
+        None => bail!("No WAN connection service on the gateway"),
+    }
+}
+
+fn soap(control: &(String, &'static str), action: &str, args: &str) -> ResultType<String> {
+    let (url, service) = control;
+    let body = format!(
+        "<?xml version=\"1.0\"?>\r\n<s:Envelope xmlns:s=\"http://schemas.xmlsoap.org/soap/envelope/\" s:encodingStyle=\"http://schemas.xmlsoap.org/soap/encoding/\"><s:Body><u:{action} xmlns:u=\"{service}\">{args}</u:{action}></s:Body></s:Envelope>"
+    );
+    let headers = format!(
+        "Content-Type: text/xml; charset=\"utf-8\"\r\nSOAPAction: \"{service}#{action}\"\r\n"
+    );
+    http_request(url, "POST", &headers, &body)
+}
+
+fn upnp_map(
+    control: &(String, &'static str),
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> ResultType<String> {
+    let client = local_ipv4()?;
+    let args = format!(
+        "<NewRemoteHost></NewRemoteHost><NewExternalPort>{external_port}</NewExternalPort><NewProtocol>TCP</NewProtocol><NewInternalPort>{internal_port}</NewInternalPort><NewInternalClient>{client}</NewInternalClient><NewEnabled>1</NewEnabled><NewPortMappingDescription>{}</NewPortMappingDescription><NewLeaseDuration>{lifetime}</NewLeaseDuration>",
+        crate::get_app_name()
+    );
+    soap(control, "AddPortMapping", &args)?;
+    let res = soap(control, "GetExternalIPAddress", "").unwrap_or_default();
+    Ok(xml_text(&res, "NewExternalIPAddress")
+        .unwrap_or_default()
+        .to_owned())
+}
+
+fn upnp_unmap(control: &(String, &'static str), external_port: u16) -> ResultType<()> {
+    let args = format!(
+        "<NewRemoteHost></NewRemoteHost><NewExternalPort>{external_port}</NewExternalPort><NewProtocol>TCP</NewProtocol>"
+    );
+    soap(control, "DeletePortMapping", &args)?;
+    Ok(())
+}
+
+/// Map `port`, or renew the mapping `previous`, with the protocol which worked last.
+fn map(port: u16, previous: Option<&Mapping>) -> ResultType<Mapping> {
+    let gateway = match previous {
+        Some(m) => m.gateway,
+        None => gateway()?,
+    };
+    let external_port = previous.map(|m| m.external_port).unwrap_or(port);
+    let mut errors = vec![];
+    let protocols = match previous {
+        Some(m) => vec![m.protocol],
+        None => vec![Protocol::Pcp, Protocol::NatPmp, Protocol::Upnp],
+    };
+    for protocol in protocols {
+        let res = match protocol {
+            Protocol::Pcp => pcp_map(gateway, port, external_port, LIFETIME)
+                .map(|(external_port, ip, lifetime)| (external_port, ip, lifetime, None)),
+            Protocol::NatPmp => natpmp_map(gateway, port, external_port, LIFETIME).map(
+                |(external_port, lifetime)| {
+                    let ip = natpmp_external_ip(gateway);
+                    (external_port, ip, lifetime, None)
+                },
+            ),
+            Protocol::Upnp => previous
+                .and_then(|m| m.control.clone())
+                .map(Ok)
+                .unwrap_or_else(upnp_discover)
+                .and_then(|control| {
+                    let ip = upnp_map(&control, port, external_port, LIFETIME)?;
+                    Ok((external_port, ip, LIFETIME, Some(control)))
+                }),
+        };
+        match res {
+            Ok((external_port, external_ip, lifetime, control)) => {
+                return Ok(Mapping {
+                    protocol,
+                    gateway,
+                    control,
+                    internal_port: port,
+                    external_port,
+                    external_ip,
+                    lifetime: lifetime.max(60),
+                    created: Instant::now(),
+                })
+            }
+            Err(e) => errors.push(format!("{:?}: {}", protocol, e)),
+        }
+    }
+    bail!("{}", errors.join(", "))
+}
+
+fn unmap(m: &Mapping) {
+    let res = match m.protocol {
+        Protocol::Pcp => pcp_map(m.gateway, m.internal_port, 0, 0).map(|_| ()),
+        Protocol::NatPmp => natpmp_map(m.gateway, m.internal_port, 0, 0).map(|_| ()),
+        Protocol::Upnp => match m.control.as_ref() {
+            Some(control) => upnp_unmap(control, m.external_port),
+            None => Ok(()),
+        },
+    };
+    match res {
+        Ok(()) => log::info!("Port mapping of {} deleted", m.internal_port),
+        Err(e) => log::warn!(
+            "Failed to delete the port mapping of {}: {}",
+            m.internal_port,
+            e
+        ),
+    }
+}
+
+fn set_status(enabled: bool, mapping: Option<&Mapping>, port: u16, error: String) {
+    *STATUS.lock().unwrap() = Status {
+        enabled,
+        protocol: mapping.map(|m| m.protocol),
+        internal_port: port,
+        external_port: mapping.map(|m| m.external_port).unwrap_or_default(),
+        external_ip: mapping.map(|m| m.external_ip.clone()).unwrap_or_default(),
+        expires_in: mapping.map(|m| m.expires_in()).unwrap_or_default(),
+        error,
+    };
+}
+
+/// Start the thread of the mapping, once.
+pub fn start() {
+    static START: Once = Once::new();
+    START.call_once(|| {
+        std::thread::spawn(run);
+    });
+}
+
+fn run() {
+    let mut mapping: Option<Mapping> = None;
+    // The port, the time and the error of the last failure, to retry after `RETRY`.
+    let mut failure: Option<(u16, Instant, String)> = None;
+    loop {
+        let enabled = option2bool(
+            OPTION_PORT_MAPPING,
+            &Config::get_option(OPTION_PORT_MAPPING),
+        );
+        let port = if enabled {
+            crate::port_range::direct_port()
+        } else {
+            0
+        };
+        if let Some(m) = mapping.take() {
+            if m.internal_port != port {
+                unmap(&m);
+            } else if m.needs_renewal() {
+                match map(port, Some(&m)) {
+                    Ok(renewed) => mapping = Some(renewed),
+                    // Mapped again from scratch below, the gateway may have changed.
+                    Err(e) => log::warn!("Failed to renew the port mapping of {}: {}", port, e),
+                }
+            } else {
+                mapping = Some(m);
+            }
+        }
+        let retry = match failure.as_ref() {
+            Some((p, t, _)) => *p != port || t.elapsed() >= RETRY,
+            None => true,
+        };
+        if mapping.is_none() && port > 0 && retry {
+            match map(port, None) {
+                Ok(m) => {
+                    log::info!(
+                        "Port {} mapped to {}:{} with {:?} for {}s",
+                        port,
+                        m.external_ip,
+                        m.external_port,
+                        m.protocol,
+                        m.lifetime
+                    );
+                    failure = None;
+                    mapping = Some(m);
+                }
+                Err(e) => {
+                    log::warn!("Failed to map port {}: {}", port, e);
+                    failure = Some((port, Instant::now(), e.to_string()));
+                }
+            }
+        }
+        let error = match (&mapping, &failure) {
+            (Some(_), _) => String::new(),
+            (None, _) if enabled && port == 0 => "The direct server is not listening".to_owned(),
+            (None, Some((p, _, e))) if *p == port => e.clone(),
+            _ => String::new(),
+        };
+        set_status(enabled, mapping.as_ref(), port, error);
+        std::thread::sleep(CHECK_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_mapping_messages() {
+        let req = natpmp_map_request(21118, 21118, LIFETIME);
+        assert_eq!(req[..4], [0, 2, 0, 0]);
+        let mut res = [0u8; 16];
+        res[1] = 130;
+        res[10..12].copy_from_slice(&21119u16.to_be_bytes());
+        res[12..16].copy_from_slice(&7200u32.to_be_bytes());
+        assert_eq!(natpmp_parse_map(&res).unwrap(), (21119, 7200));
+        res[3] = 3;
+        assert!(natpmp_parse_map(&res).is_err());
+
+        let nonce = [7u8; 12];
+        let client = Ipv4Addr::new(192, 168, 1, 2);
+        let mut res = pcp_map_request(client, nonce, 21118, 21118, LIFETIME);
+        res[1] |= 0x80;
+        res[44..60].copy_from_slice(&Ipv4Addr::new(1, 2, 3, 4).to_ipv6_mapped().octets());
+        assert_eq!(
+            pcp_parse_map(&res, nonce).unwrap(),
+            (21118, "1.2.3.4".to_owned(), LIFETIME)
+        );
+        assert!(pcp_parse_map(&res, [0u8; 12]).is_err());
+
+        let description = "<root><device><serviceList><service><serviceType>urn:schemas-upnp-org:service:WANIPConnection:1</serviceType><controlURL>/ctl/IPConn</controlURL></service></serviceList></device></root>";
+        assert_eq!(
+            control_url("http://192.168.1.1:5000/rootDesc.xml", description),
+            Some((
+                "http://192.168.1.1:5000/ctl/IPConn".to_owned(),
+                WAN_SERVICES[0]
+            ))
+        );
+        assert_eq!(
+            header_value("HTTP/1.1 200 OK\r\nLOCATION: http://a/b\r\n", "location"),
+            Some("http://a/b")
+        );
+    }
+}
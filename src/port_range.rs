@@ -0,0 +1,153 @@
+//! Local ports of the direct connections, so the rules of a firewall can be set to them.
+//!
+//! `OPTION_DIRECT_PORT_RANGE` is where the direct server listens, on the first free port of the
+//! range, instead of the single "direct-access-port". `OPTION_PUNCH_PORT_RANGE` is where the TCP
+//! and UDP sockets of the punch-hole attempts are bound, instead of a port of the system. A range
+//! is "first-last" or one port. An invalid range is rejected when it is set, and ignored with a
+//! warning when it is read. The port of the direct server and the punch-hole range are uploaded
+//! with the sysinfo.
+
+use hbb_common::{config::Config, log};
+use std::{
+    fmt,
+    sync::atomic::{AtomicU16, AtomicUsize, Ordering},
+};
+
+pub const OPTION_DIRECT_PORT_RANGE: &str = "direct-access-port-range";
+pub const OPTION_PUNCH_PORT_RANGE: &str = "punch-hole-port-range";
+
+/// The ports tried for one punch-hole socket.
+pub const MAX_ATTEMPTS: usize = 8;
+
+static DIRECT_PORT: AtomicU16 = AtomicU16::new(0);
+static NEXT_PUNCH: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortRange {
+    pub first: u16,
+    pub last: u16,
+}
+
+impl PortRange {
+    pub fn single(port: u16) -> Self {
+        Self {
+            first: port,
+            last: port,
+        }
+    }
+
+    /// None if `s` is empty.
+    pub fn parse(s: &str) -> Result<Option<Self>, String> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(None);
+        }
+        let (first, last) = s.split_once('-').unwrap_or((s, s));
+        let port = |p: &str| match p.trim().parse::<u16>() {
+            Ok(p) if p > 0 => Ok(p),
+            _ => Err(format!("Invalid port: {}", p.trim())),
+        };
+        let (first, last) = (port(first)?, port(last)?);
+        if first > last {
+            return Err(format!("Invalid port range: {}", s));
+        }
+        Ok(Some(Self { first, last }))
+    }
+
+    pub fn from_option(name: &str) -> Option<Self> {
+        match Self::parse(&Config::get_option(name)) {
+            Ok(range) => range,
+            Err(e) => {
+                log::warn!("Ignore {}: {}", name, e);
+                None
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        (self.last - self.first) as usize + 1
+    }
+
+    /// The ports of the range from the `start`-th, wrapping around.
+    pub fn ports_from(&self, start: usize) -> impl Iterator<Item = u16> {
+        let (first, len) = (self.first as usize, self.len());
+        (0..len).map(move |i| (first + (start + i) % len) as u16)
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.first == self.last {
+            write!(f, "{}", self.first)
+        } else {
+            write!(f, "{}-{}", self.first, self.last)
+        }
+    }
+}
+
+/// Check the value of an option before it is set, the other options are not checked.
+pub fn validate(name: &str, value: &str) -> Result<(), String> {
+    if name == OPTION_DIRECT_PORT_RANGE || name == OPTION_PUNCH_PORT_RANGE {
+        PortRange::parse(value)?;
+    }
+    Ok(())
+}
+
+/// The ports to bind the next punch-hole socket to, each attempt starts at the port after the
+/// one of the previous attempt, so a port still in TIME_WAIT is not tried first.
+pub fn punch_ports() -> Option<(PortRange, Vec<u16>)> {
+    let range = PortRange::from_option(OPTION_PUNCH_PORT_RANGE)?;
+    let start = NEXT_PUNCH.fetch_add(1, Ordering::Relaxed);
+    Some((range, range.ports_from(start).take(MAX_ATTEMPTS).collect()))
+}
+
+/// The port the direct server listens on, 0 if it is not listening.
+pub fn direct_port() -> u16 {
+    DIRECT_PORT.load(Ordering::Relaxed)
+}
+
+pub fn set_direct_port(port: u16) {
+    DIRECT_PORT.store(port, Ordering::Relaxed);
+}
+
+/// The ports in use, for the sysinfo.
+pub fn add_to(v: &mut serde_json::Value) {
+    let port = direct_port();
+    if port > 0 {
+        v["direct_port"] = serde_json::json!(port);
+    }
+    if let Some(range) = PortRange::from_option(OPTION_PUNCH_PORT_RANGE) {
+        v["punch_port_range"] = serde_json::json!(range.to_string());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_range_parse() {
+        assert_eq!(PortRange::parse(" "), Ok(None));
+        assert_eq!(
+            PortRange::parse("21118 - 21120"),
+            Ok(Some(PortRange {
+                first: 21118,
+                last: 21120,
+            }))
+        );
+        assert_eq!(
+            PortRange::parse("21118"),
+            Ok(Some(PortRange::single(21118)))
+        );
+        for invalid in ["0", "70000", "21120-21118", "a-b", "21118-"] {
+            assert!(PortRange::parse(invalid).is_err(), "{}", invalid);
+        }
+        assert!(validate(OPTION_PUNCH_PORT_RANGE, "1-2-3").is_err());
+        assert!(validate("direct-access-port", "1-2-3").is_ok());
+
+        let range = PortRange::parse("100-102").unwrap().unwrap();
+        assert_eq!(range.to_string(), "100-102");
+        assert_eq!(range.ports_from(4).collect::<Vec<_>>(), vec![101, 102, 100]);
+        assert_eq!(PortRange::single(65535).ports_from(0).count(), 1);
+    }
+}
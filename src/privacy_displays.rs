@@ -0,0 +1,80 @@
+//! The displays of the host the privacy mode blanks, instead of all of them.
+//!
+//! The client sends a `Request` with the indexes of the displays to a host with
+//! "support_privacy_displays" in its platform additions. The host keeps them for the connection,
+//! blanks them the next time it turns the privacy mode on, or at once if it is on for the
+//! connection, and answers with a `Response`. No display means all of them, as with the older
+//! hosts.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "privacy-displays";
+pub const PLATFORM_ADDITION_KEY: &str = "support_privacy_displays";
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub displays: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub displays: Vec<usize>,
+    #[serde(default)]
+    pub error: String,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    /// The displays sorted, without the ones out of the `count` displays of the host, nor the
+    /// duplicates. All of them are none.
+    pub fn valid_displays(&self, count: usize) -> Vec<usize> {
+        let mut displays: Vec<usize> = self
+            .displays
+            .iter()
+            .copied()
+            .filter(|&d| d < count)
+            .collect();
+        displays.sort_unstable();
+        displays.dedup();
+        if displays.len() == count {
+            displays.clear();
+        }
+        displays
+    }
+}
+
+impl Response {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn privacy_displays() {
+        let req = Request::parse(br#"{"displays":[2,0,2,5]}"#).unwrap();
+        assert_eq!(req.valid_displays(3), vec![0, 2]);
+        assert_eq!(req.valid_displays(2), vec![0]);
+        let req = Request::parse(br#"{"displays":[1,0]}"#).unwrap();
+        assert_eq!(req.valid_displays(2), vec![]);
+        assert_eq!(Request::default().valid_displays(3), vec![]);
+        let res = Response::parse(br#"{"displays":[1]}"#).unwrap();
+        assert_eq!(res.error, "");
+    }
+}
@@ -34,6 +34,11 @@ pub trait PrivacyMode: Sync + Send {
 
     fn get_impl_key(&self) -> &str;
 
+    /// Blank only the displays of these indexes, all of them if empty, again if it is on.
+    fn set_displays(&mut self, _displays: &[usize]) -> ResultType<()> {
+        bail!("Choosing the displays is not supported")
+    }
+
     #[inline]
     fn check_on_conn_id(&self, conn_id: i32) -> ResultType<bool> {
         let pre_conn_id = self.pre_conn_id();
@@ -85,6 +90,9 @@ lazy_static::lazy_static! {
         map.insert(macos::PRIVACY_MODE_IMPL, |impl_key: &str| {
             Box::new(macos::PrivacyModeImpl::new(impl_key))
         });
+        map.insert(macos::PRIVACY_MODE_IMPL_OVERLAY, |impl_key: &str| {
+            Box::new(macos::PrivacyModeImpl::new(impl_key))
+        });
         Arc::new(Mutex::new(map))
     };
 }
@@ -235,7 +243,13 @@ pub fn check_on_conn_id(conn_id: i32) -> Option<ResultType<bool>> {
 }
 
 pub fn get_supported_privacy_mode_impl() -> Vec<(&'static str, &'static str)> {
-    vec![(macos::PRIVACY_MODE_IMPL, "privacy_mode_impl_macos_tip")]
+    vec![
+        (macos::PRIVACY_MODE_IMPL, "privacy_mode_impl_macos_tip"),
+        (
+            macos::PRIVACY_MODE_IMPL_OVERLAY,
+            "privacy_mode_impl_macos_overlay_tip",
+        ),
+    ]
 }
 
 #[inline]
@@ -271,6 +285,17 @@ pub fn is_privacy_mode_supported() -> bool {
     !DEFAULT_PRIVACY_MODE_IMPL.is_empty()
 }
 
+/// Choose the displays of the privacy mode of the connection, see `PrivacyMode::set_displays`.
+pub fn set_displays(conn_id: i32, displays: &[usize]) -> Option<ResultType<()>> {
+    let mut privacy_mode = PRIVACY_MODE.lock().unwrap();
+    let privacy_mode = privacy_mode.as_mut()?;
+    Some(
+        privacy_mode
+            .check_off_conn_id(conn_id)
+            .and_then(|_| privacy_mode.set_displays(displays)),
+    )
+}
+
 #[inline]
 pub fn get_privacy_mode_conn_id() -> Option<i32> {
     PRIVACY_MODE
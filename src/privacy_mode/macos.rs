@@ -1,11 +1,17 @@
-use super::{PrivacyMode, PrivacyModeState};
-use hbb_common::{anyhow::anyhow, ResultType};
+use super::{PrivacyMode, PrivacyModeState, INVALID_PRIVACY_MODE_CONN_ID};
+use hbb_common::{anyhow::anyhow, bail, ResultType};
 
 extern "C" {
     fn MacSetPrivacyMode(on: bool) -> bool;
+    fn MacSetPrivacyModeOverlay(on: bool) -> bool;
+    fn MacSetPrivacyModeDisplays(displays: *const u32, count: u32);
 }
 
+/// Blackens the displays by their gamma table.
 pub const PRIVACY_MODE_IMPL: &str = "privacy_mode_impl_macos";
+/// Covers the displays with black windows left out of the capture, for the displays without a
+/// gamma table.
+pub const PRIVACY_MODE_IMPL_OVERLAY: &str = "privacy_mode_impl_macos_overlay";
 
 pub struct PrivacyModeImpl {
     impl_key: String,
@@ -19,6 +25,16 @@ impl PrivacyModeImpl {
             conn_id: 0,
         }
     }
+
+    fn set_privacy_mode(&self, on: bool) -> bool {
+        unsafe {
+            if self.impl_key == PRIVACY_MODE_IMPL_OVERLAY {
+                MacSetPrivacyModeOverlay(on)
+            } else {
+                MacSetPrivacyMode(on)
+            }
+        }
+    }
 }
 
 impl PrivacyMode for PrivacyModeImpl {
@@ -31,9 +47,7 @@ impl PrivacyMode for PrivacyModeImpl {
     }
 
     fn clear(&mut self) {
-        unsafe {
-            MacSetPrivacyMode(false);
-        }
+        self.set_privacy_mode(false);
         self.conn_id = 0;
     }
 
@@ -41,7 +55,7 @@ impl PrivacyMode for PrivacyModeImpl {
         if self.check_on_conn_id(conn_id)? {
             return Ok(true);
         }
-        let success = unsafe { MacSetPrivacyMode(true) };
+        let success = self.set_privacy_mode(true);
         if !success {
             return Err(anyhow!("Failed to turn on privacy mode"));
         }
@@ -52,10 +66,10 @@ impl PrivacyMode for PrivacyModeImpl {
     fn turn_off_privacy(&mut self, conn_id: i32, _state: Option<PrivacyModeState>) -> ResultType<()> {
         // Note: The `_state` parameter is intentionally ignored on macOS.
         // On Windows, it's used to notify the connection manager about privacy mode state changes
-        // (see win_topmost_window.rs). macOS currently has a simpler single-mode implementation
+        // (see win_topmost_window.rs). macOS currently has simpler implementations
         // without the need for such cross-component state synchronization.
         self.check_off_conn_id(conn_id)?;
-        let success = unsafe { MacSetPrivacyMode(false) };
+        let success = self.set_privacy_mode(false);
         if !success {
             return Err(anyhow!("Failed to turn off privacy mode"));
         }
@@ -70,6 +84,31 @@ impl PrivacyMode for PrivacyModeImpl {
     fn get_impl_key(&self) -> &str {
         &self.impl_key
     }
+
+    fn set_displays(&mut self, displays: &[usize]) -> ResultType<()> {
+        // The name of a display is its CGDirectDisplayID.
+        let all = crate::server::display_service::try_get_displays()?;
+        let ids = displays
+            .iter()
+            .map(|&i| {
+                all.get(i)
+                    .and_then(|d| d.name().parse::<u32>().ok())
+                    .ok_or(anyhow!("No display {}", i))
+            })
+            .collect::<ResultType<Vec<u32>>>()?;
+        let on = self.conn_id != INVALID_PRIVACY_MODE_CONN_ID;
+        if on {
+            self.set_privacy_mode(false);
+        }
+        unsafe {
+            MacSetPrivacyModeDisplays(ids.as_ptr(), ids.len() as u32);
+        }
+        if on && !self.set_privacy_mode(true) {
+            self.conn_id = INVALID_PRIVACY_MODE_CONN_ID;
+            bail!("Failed to turn on privacy mode");
+        }
+        Ok(())
+    }
 }
 
 impl Drop for PrivacyModeImpl {
@@ -0,0 +1,222 @@
+//! The connection quality of the past sessions, one sample per second, for the bug reports.
+//!
+//! Each remote control session writes its samples to `<peer>_<start>.qos` in the `DIR` of the
+//! config, a ring of `CAPACITY` fixed size records behind a small header, so a long session keeps
+//! its last hour and the file never grows past it. Only the last `MAX_SESSIONS` files are kept.
+
+use hbb_common::{bail, config::Config, get_time, log, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom, Write},
+    path::PathBuf,
+};
+
+const DIR: &str = "qos";
+const EXT: &str = ".qos";
+const MAGIC: &[u8; 4] = b"QOS1";
+// The magic and the index of the next record.
+const HEADER_SIZE: u64 = 8;
+const RECORD_SIZE: u64 = 16;
+const CAPACITY: u32 = 3600;
+const MAX_SESSIONS: usize = 50;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Sample {
+    /// Seconds since the start of the session.
+    pub t: u32,
+    pub kbps: u32,
+    pub fps: u16,
+    /// The round trip time in ms, 0 before the first measure.
+    pub rtt: u16,
+    /// The frames lost in the second: dropped or failed to decode.
+    pub loss: u16,
+}
+
+impl Sample {
+    fn to_bytes(&self) -> [u8; RECORD_SIZE as usize] {
+        let mut b = [0u8; RECORD_SIZE as usize];
+        b[0..4].copy_from_slice(&self.t.to_le_bytes());
+        b[4..8].copy_from_slice(&self.kbps.to_le_bytes());
+        b[8..10].copy_from_slice(&self.fps.to_le_bytes());
+        b[10..12].copy_from_slice(&self.rtt.to_le_bytes());
+        b[12..14].copy_from_slice(&self.loss.to_le_bytes());
+        b
+    }
+
+    fn from_bytes(b: &[u8]) -> Self {
+        let u16_at = |i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+        Self {
+            t: u32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+            kbps: u32::from_le_bytes([b[4], b[5], b[6], b[7]]),
+            fps: u16_at(8),
+            rtt: u16_at(10),
+            loss: u16_at(12),
+        }
+    }
+}
+
+/// The session of a peer, by the time it started, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    pub start: i64,
+}
+
+fn file_prefix(peer: &str) -> String {
+    let peer: String = peer
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    format!("{peer}_")
+}
+
+fn path(peer: &str, start: i64) -> PathBuf {
+    Config::path(DIR).join(format!("{}{start}{EXT}", file_prefix(peer)))
+}
+
+/// The files of the sessions, oldest first.
+fn files() -> Vec<(i64, PathBuf)> {
+    let mut v: Vec<(i64, PathBuf)> = fs::read_dir(Config::path(DIR))
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let start = name.strip_suffix(EXT)?.rsplit('_').next()?.parse().ok()?;
+                    Some((start, e.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    v.sort();
+    v
+}
+
+fn remove_old_sessions() {
+    let files = files();
+    let n = files.len().saturating_sub(MAX_SESSIONS);
+    for (_, path) in files.into_iter().take(n) {
+        fs::remove_file(path).ok();
+    }
+}
+
+pub struct Recorder {
+    file: File,
+    start: i64,
+    next: u32,
+    dropped: usize,
+}
+
+impl Recorder {
+    pub fn new(peer: &str) -> ResultType<Self> {
+        let start = get_time() / 1000;
+        let path = path(peer, start);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = File::create(&path)?;
+        file.write_all(MAGIC)?;
+        file.write_all(&0u32.to_le_bytes())?;
+        remove_old_sessions();
+        Ok(Self {
+            file,
+            start,
+            next: 0,
+            dropped: 0,
+        })
+    }
+
+    /// Add the sample of the last second, `dropped` is the total of the session.
+    pub fn push(&mut self, kbps: u32, fps: u16, rtt: u16, dropped: usize) -> ResultType<()> {
+        let sample = Sample {
+            t: (get_time() / 1000 - self.start).max(0) as _,
+            kbps,
+            fps,
+            rtt,
+            loss: dropped.saturating_sub(self.dropped).min(u16::MAX as _) as _,
+        };
+        self.dropped = dropped;
+        self.file.seek(SeekFrom::Start(
+            HEADER_SIZE + self.next as u64 * RECORD_SIZE,
+        ))?;
+        self.file.write_all(&sample.to_bytes())?;
+        self.next = (self.next + 1) % CAPACITY;
+        self.file.seek(SeekFrom::Start(MAGIC.len() as _))?;
+        self.file.write_all(&self.next.to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// The recorded sessions of the peer, oldest first.
+pub fn sessions(peer: &str) -> Vec<Session> {
+    let prefix = file_prefix(peer);
+    files()
+        .into_iter()
+        .filter(|(_, path)| {
+            path.file_name()
+                .map(|name| name.to_string_lossy().starts_with(&prefix))
+                .unwrap_or_default()
+        })
+        .map(|(start, _)| Session { start })
+        .collect()
+}
+
+fn parse(data: &[u8]) -> ResultType<Vec<Sample>> {
+    if data.len() < HEADER_SIZE as usize || &data[..MAGIC.len()] != MAGIC {
+        bail!("invalid qos history");
+    }
+    let next = u32::from_le_bytes([data[4], data[5], data[6], data[7]]) as usize;
+    let mut samples: Vec<Sample> = data[HEADER_SIZE as usize..]
+        .chunks_exact(RECORD_SIZE as usize)
+        .map(Sample::from_bytes)
+        .collect();
+    if samples.len() == CAPACITY as usize {
+        samples.rotate_left(next.min(samples.len()));
+    }
+    Ok(samples)
+}
+
+/// The samples of a session of the peer, in time order.
+pub fn load(peer: &str, start: i64) -> ResultType<Vec<Sample>> {
+    let mut data = Vec::new();
+    File::open(path(peer, start))?.read_to_end(&mut data)?;
+    parse(&data)
+}
+
+/// Open the recorder of a new session of the peer, `None` if the file cannot be created.
+pub fn start(peer: &str) -> Option<Recorder> {
+    match Recorder::new(peer) {
+        Ok(recorder) => Some(recorder),
+        Err(e) => {
+            log::warn!("Failed to record the qos history of {}: {}", peer, e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn qos_history() {
+        let sample = Sample {
+            t: 12,
+            kbps: 3000,
+            fps: 30,
+            rtt: 45,
+            loss: 2,
+        };
+        assert_eq!(Sample::from_bytes(&sample.to_bytes()), sample);
+
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&2u32.to_le_bytes());
+        for t in 0..CAPACITY {
+            let t = if t < 2 { CAPACITY + t } else { t };
+            data.extend_from_slice(&Sample { t, ..sample }.to_bytes());
+        }
+        let samples = parse(&data).unwrap();
+        assert_eq!(samples.first().map(|s| s.t), Some(2));
+        assert_eq!(samples.last().map(|s| s.t), Some(CAPACITY + 1));
+        assert!(parse(b"QOS0").is_err());
+    }
+}
@@ -28,6 +28,7 @@ use hbb_common::{
 
 use crate::{
     check_port,
+    port_range::{self, PortRange},
     server::{check_zombie, new as new_server, ServerPtr},
 };
 
@@ -73,6 +74,7 @@ impl RendezvousMediator {
         tokio::spawn(async move {
             direct_server(server_cloned).await;
         });
+        crate::port_mapping::start();
         let start_lan_listening = crate::platform::is_installed();
         if start_lan_listening {
             std::thread::spawn(move || {
@@ -615,7 +617,7 @@ impl RendezvousMediator {
         }
         log::debug!("Punch tcp hole to {:?}", peer_addr);
         let mut socket = {
-            let socket = connect_tcp(&*self.host, CONNECT_TIMEOUT).await?;
+            let socket = self.connect_tcp_for_punch().await?;
             let local_addr = socket.local_addr();
             // key important here for punch hole to tell my gateway incoming peer is safe.
             // it can not be async here, because local_addr can not be reused, we must close the connection before use it again.
@@ -640,7 +642,7 @@ impl RendezvousMediator {
     ) -> ResultType<()> {
         let mut msg_out = Message::new();
         msg_out.set_punch_hole_sent(msg_punch);
-        let (socket, addr) = new_direct_udp_for(&self.host).await?;
+        let (socket, addr) = self.new_udp_for_punch().await?;
         let data = msg_out.write_to_bytes()?;
         socket.send_to(&data, addr).await?;
         let socket_cloned = socket.clone();
@@ -662,6 +664,42 @@ impl RendezvousMediator {
         Ok(())
     }
 
+    /// The connection to the rendezvous server whose local port is reused for the punch hole,
+    /// bound in the punch-hole port range if there is one.
+    async fn connect_tcp_for_punch(&self) -> ResultType<Stream> {
+        let Some((range, ports)) = port_range::punch_ports() else {
+            return connect_tcp(&*self.host, CONNECT_TIMEOUT).await;
+        };
+        let mut local_addr = Config::get_any_listen_addr(is_ipv4(&self.addr));
+        for port in ports {
+            local_addr.set_port(port);
+            match socket_client::connect_tcp_local(&*self.host, Some(local_addr), CONNECT_TIMEOUT)
+                .await
+            {
+                Ok(socket) => return Ok(socket),
+                Err(err) => log::debug!("Failed to punch tcp hole from port {}: {}", port, err),
+            }
+        }
+        bail!("No free port for the tcp punch hole in {}", range)
+    }
+
+    async fn new_udp_for_punch(&self) -> ResultType<(Arc<tokio::net::UdpSocket>, SocketAddr)> {
+        let (socket, addr) = new_direct_udp_for(&self.host).await?;
+        let Some((range, ports)) = port_range::punch_ports() else {
+            return Ok((socket, addr));
+        };
+        drop(socket);
+        let mut local_addr = Config::get_any_listen_addr(addr.is_ipv4());
+        for port in ports {
+            local_addr.set_port(port);
+            match tokio::net::UdpSocket::bind(local_addr).await {
+                Ok(socket) => return Ok((Arc::new(socket), addr)),
+                Err(err) => log::debug!("Failed to bind udp punch hole to port {}: {}", port, err),
+            }
+        }
+        bail!("No free port for the udp punch hole in {}", range)
+    }
+
     async fn register_pk(&mut self, socket: Sink<'_>) -> ResultType<()> {
         let mut msg_out = Message::new();
         let pk = Config::get_key_pair().1;
@@ -745,33 +783,49 @@ fn get_direct_port() -> i32 {
     port
 }
 
+/// The ports the direct server may listen on, the range takes precedence over the port.
+fn get_direct_ports() -> PortRange {
+    PortRange::from_option(port_range::OPTION_DIRECT_PORT_RANGE)
+        .unwrap_or_else(|| PortRange::single(get_direct_port() as _))
+}
+
+async fn listen_any_in(ports: PortRange) -> ResultType<tokio::net::TcpListener> {
+    let mut last_err = None;
+    for port in ports.ports_from(0) {
+        match hbb_common::tcp::listen_any(port as _).await {
+            Ok(l) => return Ok(l),
+            Err(err) => last_err = Some(err),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| anyhow::anyhow!("No port in {}", ports)))
+}
+
 async fn direct_server(server: ServerPtr) {
     let mut listener = None;
-    let mut port = 0;
+    let mut ports = PortRange::single(0);
     loop {
         let disabled = !option2bool(
             OPTION_DIRECT_SERVER,
             &Config::get_option(OPTION_DIRECT_SERVER),
         ) || option2bool("stop-service", &Config::get_option("stop-service"));
         if !disabled && listener.is_none() {
-            port = get_direct_port();
-            match hbb_common::tcp::listen_any(port as _).await {
+            ports = get_direct_ports();
+            match listen_any_in(ports).await {
                 Ok(l) => {
+                    let local_addr = l.local_addr();
+                    log::info!("Direct server listening on: {:?}", local_addr);
+                    port_range::set_direct_port(local_addr.map(|a| a.port()).unwrap_or(0));
                     listener = Some(l);
-                    log::info!(
-                        "Direct server listening on: {:?}",
-                        listener.as_ref().map(|l| l.local_addr())
-                    );
                 }
                 Err(err) => {
                     // to-do: pass to ui
                     log::error!(
                         "Failed to start direct server on port: {}, error: {}",
-                        port,
+                        ports,
                         err
                     );
                     loop {
-                        if port != get_direct_port() {
+                        if ports != get_direct_ports() {
                             break;
                         }
                         sleep(1.).await;
@@ -780,8 +834,9 @@ async fn direct_server(server: ServerPtr) {
             }
         }
         if let Some(l) = listener.as_mut() {
-            if disabled || port != get_direct_port() {
+            if disabled || ports != get_direct_ports() {
                 log::info!("Exit direct access listen");
+                port_range::set_direct_port(0);
                 listener = None;
                 continue;
             }
@@ -800,6 +855,7 @@ async fn direct_server(server: ServerPtr) {
                             addr,
                             false,
                             None, // Direct connections don't have control_permissions
+                            None,
                         )
                         .await
                     );
@@ -865,18 +921,20 @@ async fn udp_nat_listen(
     let func = async {
         socket.connect(peer_addr).await?;
         let res = crate::punch_udp(socket.clone(), true).await?;
-        let stream = crate::kcp_stream::KcpStream::accept(
+        let mut stream = crate::kcp_stream::KcpStream::accept(
             socket,
             Duration::from_millis(CONNECT_TIMEOUT as _),
             res,
         )
         .await?;
+        let datagram = stream.0.take_datagram();
         crate::server::create_tcp_connection(
             server,
             stream.1,
             peer_addr_v4,
             true,
             control_permissions,
+            datagram,
         )
         .await?;
         Ok(())
@@ -0,0 +1,253 @@
+//! The local round trips of `--test-keyboard-layout` and `--test-clipboard`, for the layout, IME
+//! and clipboard issues: what the user types or copies, what reaches the other side.
+//!
+//! The keyboard test types each sample with the two ways the server injects the input, by the key
+//! of the current layout (`Key::Layout`) and as unicode (`key_sequence`), and captures the text of
+//! the key events with a grab which swallows them, so nothing is typed in the focused window. The
+//! clipboard test sets each sample, then reads, encodes, decodes and sets it the way a session
+//! does, see `clipboard::roundtrip`, and the clipboard of the user is restored after.
+
+use arboard::{ClipboardData, ImageData};
+use enigo::{Enigo, Key, KeyboardControllable};
+use hbb_common::{log, ResultType};
+use rdev::{Event, EventType};
+use std::{
+    fmt,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+
+// The wait for the key events of a sample.
+const KEY_TIMEOUT: Duration = Duration::from_millis(500);
+// The time to release the keys, e.g. the Enter which started the command, before the grab.
+const KEY_START_DELAY: Duration = Duration::from_secs(1);
+
+const KEY_SAMPLES: &[&str] = &[
+    "abcxyz",
+    "ABCXYZ",
+    "0123456789",
+    "`~!@#$%^&*()-_=+",
+    "[]{}\\|;:'\",.<>/?",
+    "éèêëàâçùüö",
+    "ñßæøå",
+    "€£¥§°",
+    "ąęłńśźż",
+    "абвгд",
+    "αβγδε",
+    "中文",
+    "日本語",
+    "한국어",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub sent: String,
+    pub received: String,
+}
+
+impl Check {
+    pub fn ok(&self) -> bool {
+        self.sent == self.received
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for c in self.checks.iter() {
+            if c.ok() {
+                writeln!(f, "ok    {}: {}", c.name, c.sent)?;
+            } else {
+                writeln!(
+                    f,
+                    "FAIL  {}: sent {:?}, received {:?}",
+                    c.name, c.sent, c.received
+                )?;
+            }
+        }
+        let ok = self.checks.iter().filter(|c| c.ok()).count();
+        write!(f, "{}/{} survived the round trip", ok, self.checks.len())
+    }
+}
+
+/// Type the samples, or the text `only`, and report what the key events carry.
+pub fn keyboard_layout(only: Option<String>) -> ResultType<Report> {
+    let samples = match only {
+        Some(s) => vec![s],
+        None => KEY_SAMPLES.iter().map(|s| s.to_string()).collect(),
+    };
+    std::thread::sleep(KEY_START_DELAY);
+    let (tx, rx) = mpsc::channel::<String>();
+    std::thread::spawn(move || {
+        let func = move |event: Event| match event.event_type {
+            EventType::KeyPress(_) => {
+                if let Some(name) = event.unicode.as_ref().and_then(|u| u.name.clone()) {
+                    tx.send(name).ok();
+                }
+                None
+            }
+            EventType::KeyRelease(_) => None,
+            _ => Some(event),
+        };
+        rdev::set_is_main_thread(false);
+        if let Err(e) = rdev::grab(func) {
+            log::error!("rdev Error: {:?}", e);
+        }
+    });
+    let mut en = Enigo::new();
+    let mut report = Report::default();
+    for sample in samples {
+        for layout in [true, false] {
+            while rx.try_recv().is_ok() {}
+            if layout {
+                sample.chars().for_each(|c| en.key_click(Key::Layout(c)));
+            } else {
+                en.key_sequence(&sample);
+            }
+            report.checks.push(Check {
+                name: if layout { "layout" } else { "unicode" }.to_owned(),
+                received: collect_keys(&rx, &sample),
+                sent: sample.clone(),
+            });
+        }
+    }
+    rdev::exit_grab().ok();
+    Ok(report)
+}
+
+// The text of the key events, once it is as long as the sample or they stop.
+fn collect_keys(rx: &mpsc::Receiver<String>, sample: &str) -> String {
+    let mut received = String::new();
+    let deadline = Instant::now() + KEY_TIMEOUT;
+    while received.chars().count() < sample.chars().count() {
+        let now = Instant::now();
+        if now >= deadline {
+            break;
+        }
+        match rx.recv_timeout(deadline - now) {
+            Ok(s) => received.push_str(&s.replace('\r', "")),
+            Err(_) => break,
+        }
+    }
+    received
+}
+
+fn clipboard_samples() -> Vec<(&'static str, ClipboardData)> {
+    let text = |s: &str| ClipboardData::Text(s.to_owned());
+    vec![
+        ("text", text("The quick brown fox")),
+        ("latin", text("Grüße, ça va? ¿Señor? Øre")),
+        ("cjk", text("中文 日本語 한국어")),
+        ("rtl", text("שלום مرحبا")),
+        ("emoji", text("👍🏽 👨‍👩‍👧 🇫🇷")),
+        ("crlf", text("line 1\r\nline 2\r\n")),
+        ("tabs", text("a\tb\t\tc")),
+        ("long", text(&"0123456789abcdef".repeat(64 * 1024))),
+        (
+            "html",
+            ClipboardData::Html("<p><b>bold</b> <i>ünïcødé</i></p>".to_owned()),
+        ),
+        (
+            "rtf",
+            ClipboardData::Rtf(r"{\rtf1\ansi{\b bold} text}".to_owned()),
+        ),
+        (
+            "image",
+            ClipboardData::Image(ImageData::rgba(2, 2, vec![255u8; 16].into())),
+        ),
+    ]
+}
+
+fn describe(data: &ClipboardData) -> String {
+    match data {
+        ClipboardData::Text(s) | ClipboardData::Html(s) | ClipboardData::Rtf(s) => {
+            if s.chars().count() > 64 {
+                format!("{} chars", s.chars().count())
+            } else {
+                s.clone()
+            }
+        }
+        ClipboardData::Image(ImageData::Rgba(i)) => format!("{}x{} rgba", i.width, i.height),
+        ClipboardData::Image(ImageData::Png(_)) => "png".to_owned(),
+        ClipboardData::Image(ImageData::Svg(_)) => "svg".to_owned(),
+        ClipboardData::Special((name, _)) => name.clone(),
+        _ => "-".to_owned(),
+    }
+}
+
+// What came back in the format of the sample, the size of an image, which may come back as PNG.
+fn find_received(sent: &ClipboardData, received: &[ClipboardData]) -> String {
+    let found = received.iter().find(|r| match (sent, r) {
+        (ClipboardData::Image(_), ClipboardData::Image(_)) => true,
+        _ => std::mem::discriminant(sent) == std::mem::discriminant(*r),
+    });
+    match (sent, found) {
+        (ClipboardData::Image(_), Some(r @ ClipboardData::Image(image))) => {
+            match crate::clipboard::images::size(image) {
+                Some((w, h)) => format!("{}x{} rgba", w, h),
+                None => describe(r),
+            }
+        }
+        (_, Some(r)) => describe(r),
+        (_, None) => format!(
+            "no such format, got [{}]",
+            received.iter().map(describe).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
+/// Set each sample on the clipboard, send it back as a session does and report what is left.
+pub fn clipboard() -> ResultType<Report> {
+    let mut report = Report::default();
+    for (name, sample) in clipboard_samples() {
+        let received = match crate::clipboard::roundtrip(vec![sample.clone()]) {
+            Ok(data) => find_received(&sample, &data),
+            Err(e) => format!("error: {}", e),
+        };
+        report.checks.push(Check {
+            name: name.to_owned(),
+            sent: describe(&sample),
+            received,
+        });
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roundtrip() {
+        let (tx, rx) = mpsc::channel();
+        for s in ["é", "\r", "a"] {
+            tx.send(s.to_owned()).unwrap();
+        }
+        assert_eq!(collect_keys(&rx, "éa"), "éa");
+
+        let sent = ClipboardData::Text("中文".to_owned());
+        let received = vec![
+            ClipboardData::Html("<p>中文</p>".to_owned()),
+            ClipboardData::Text("中文".to_owned()),
+        ];
+        assert_eq!(find_received(&sent, &received), "中文");
+        assert_eq!(
+            find_received(&sent, &received[..1]),
+            "no such format, got [<p>中文</p>]"
+        );
+
+        let report = Report {
+            checks: vec![Check {
+                name: "text".to_owned(),
+                sent: "a".to_owned(),
+                received: "b".to_owned(),
+            }],
+        };
+        assert!(report.to_string().ends_with("0/1 survived the round trip"));
+    }
+}
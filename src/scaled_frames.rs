@@ -0,0 +1,18 @@
+//! Frames encoded smaller than the display.
+//!
+//! The host lowers the encoded resolution under congestion with `OPTION_ADAPTIVE_RESOLUTION`, and
+//! encodes the low simulcast tier at half of it. The client scales these frames back to the size
+//! of the display, an older one would show them smaller. A host with "support_scaled_frames" in
+//! its platform additions only sends them to the clients which asked for them with an empty
+//! `Misc::PluginRequest` of the reserved `ID`.
+
+use crate::plugin_request;
+use hbb_common::{bytes::Bytes, message_proto::Message};
+
+pub const ID: &str = "scaled-frames";
+pub const PLATFORM_ADDITION_KEY: &str = "support_scaled_frames";
+
+/// The request of the client to get the scaled frames.
+pub fn make_request() -> Message {
+    plugin_request::make_raw(ID, Bytes::new())
+}
@@ -0,0 +1,241 @@
+//! Direction, speed, unit and momentum of the scrolling of a viewer, applied by the host.
+//!
+//! A client with natural scrolling and a host without it scroll the other way round, a mouse wheel
+//! scrolls lines where a trackpad scrolls pixels, and a client which sends no momentum stops the
+//! page dead when the fingers leave the trackpad. The client saves a `Transform` per peer in the
+//! `PeerConfig` options under `PEER_OPTION` and sends it to a host with "support_scroll_transform"
+//! in its platform additions. The host transforms the wheel and trackpad events of the connection
+//! before they are posted, and glides on after the trackpad scrolling if asked to.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const ID: &str = "scroll-transform";
+pub const PEER_OPTION: &str = "scroll-transform";
+pub const PLATFORM_ADDITION_KEY: &str = "support_scroll_transform";
+pub const GLIDE_INTERVAL: Duration = Duration::from_millis(16);
+const MAX_MULTIPLIER: f32 = 10.0;
+const LINE_PIXELS: f32 = 10.0;
+// The pause after the last trackpad event which starts the glide.
+const GLIDE_DELAY: Duration = Duration::from_millis(50);
+const GLIDE_DECAY: f32 = 0.92;
+const GLIDE_MIN_SPEED: f32 = 0.5;
+
+lazy_static::lazy_static! {
+    static ref STATES: Mutex<HashMap<i32, State>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    // Lines for the wheel, pixels for the trackpad.
+    Auto,
+    Pixel,
+    Line,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Transform {
+    pub invert: bool,
+    pub multiplier: f32,
+    pub unit: Unit,
+    pub momentum: bool,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            invert: false,
+            multiplier: 1.0,
+            unit: Unit::Auto,
+            momentum: false,
+        }
+    }
+}
+
+impl Transform {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// A scroll to post, in the direction of the `MouseEvent`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scroll {
+    pub x: i32,
+    pub y: i32,
+    pub pixel: bool,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    transform: Transform,
+    // The fractions not scrolled yet.
+    remainder: (f32, f32),
+    // The last trackpad scroll, per `GLIDE_INTERVAL`.
+    velocity: (f32, f32),
+    last_trackpad: Option<Instant>,
+    glide_pixel: bool,
+    gliding: bool,
+}
+
+impl State {
+    fn scroll(&mut self, x: i32, y: i32, is_trackpad: bool, now: Instant) -> Option<Scroll> {
+        let t = &self.transform;
+        let pixel = match t.unit {
+            Unit::Auto => is_trackpad,
+            Unit::Pixel => true,
+            Unit::Line => false,
+        };
+        let unit = match (is_trackpad, pixel) {
+            (false, true) => LINE_PIXELS,
+            (true, false) => 1.0 / LINE_PIXELS,
+            _ => 1.0,
+        };
+        let factor = t.multiplier * unit * if t.invert { -1.0 } else { 1.0 };
+        let (dx, dy) = (x as f32 * factor, y as f32 * factor);
+        if is_trackpad && t.momentum {
+            self.velocity = (dx, dy);
+            self.last_trackpad = Some(now);
+            self.glide_pixel = pixel;
+        }
+        self.emit(dx, dy, pixel)
+    }
+
+    fn emit(&mut self, dx: f32, dy: f32, pixel: bool) -> Option<Scroll> {
+        let (x, y) = (dx + self.remainder.0, dy + self.remainder.1);
+        self.remainder = (x - x.trunc(), y - y.trunc());
+        let (x, y) = (x.trunc() as i32, y.trunc() as i32);
+        if x == 0 && y == 0 {
+            return None;
+        }
+        Some(Scroll { x, y, pixel })
+    }
+
+    fn glide(&mut self, now: Instant) -> Option<Scroll> {
+        if now.duration_since(self.last_trackpad?) < GLIDE_DELAY {
+            return None;
+        }
+        let (vx, vy) = (self.velocity.0 * GLIDE_DECAY, self.velocity.1 * GLIDE_DECAY);
+        if vx.abs().max(vy.abs()) < GLIDE_MIN_SPEED {
+            self.last_trackpad = None;
+            self.velocity = (0.0, 0.0);
+            return None;
+        }
+        self.velocity = (vx, vy);
+        self.emit(vx, vy, self.glide_pixel)
+    }
+}
+
+/// Set the transform of a connection from its request.
+pub fn handle_request(conn: i32, content: &[u8]) {
+    let mut transform = match serde_json::from_slice::<Transform>(content) {
+        Ok(transform) => transform,
+        Err(e) => {
+            log::warn!("Invalid scroll transform: {}", e);
+            return;
+        }
+    };
+    transform.multiplier = transform.multiplier.clamp(0.0, MAX_MULTIPLIER);
+    log::info!("Scroll transform of connection {}: {:?}", conn, transform);
+    let mut states = STATES.lock().unwrap();
+    // The glide thread of the connection, if any, keeps running.
+    let gliding = states.get(&conn).map(|s| s.gliding).unwrap_or(false);
+    states.insert(
+        conn,
+        State {
+            transform,
+            gliding,
+            ..Default::default()
+        },
+    );
+}
+
+pub fn remove(conn: i32) {
+    STATES.lock().unwrap().remove(&conn);
+}
+
+/// The scroll to post for a wheel or a trackpad event, none if it is too small yet.
+pub fn transform(conn: i32, x: i32, y: i32, is_trackpad: bool) -> Option<Scroll> {
+    match STATES.lock().unwrap().get_mut(&conn) {
+        Some(state) => state.scroll(x, y, is_trackpad, Instant::now()),
+        None => Some(Scroll {
+            x,
+            y,
+            pixel: is_trackpad,
+        }),
+    }
+}
+
+/// Whether to start the glide thread of the connection, true once.
+pub fn start_glide(conn: i32) -> bool {
+    match STATES.lock().unwrap().get_mut(&conn) {
+        Some(state) if state.transform.momentum && !state.gliding => {
+            state.gliding = true;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// The scroll of the glide thread each `GLIDE_INTERVAL`, none to stop the thread.
+pub fn glide(conn: i32) -> Option<Option<Scroll>> {
+    let mut states = STATES.lock().unwrap();
+    let state = states.get_mut(&conn)?;
+    if !state.transform.momentum {
+        state.gliding = false;
+        return None;
+    }
+    Some(state.glide(Instant::now()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scroll_transform() {
+        let now = Instant::now();
+        let mut state = State {
+            transform: Transform {
+                invert: true,
+                multiplier: 0.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let scroll = |x, y, pixel| Some(Scroll { x, y, pixel });
+        assert_eq!(state.scroll(0, 1, false, now), None);
+        assert_eq!(state.scroll(0, 1, false, now), scroll(0, -1, false));
+        assert_eq!(state.scroll(4, 0, true, now), scroll(-2, 0, true));
+
+        state.transform = Transform {
+            unit: Unit::Pixel,
+            momentum: true,
+            ..Default::default()
+        };
+        assert_eq!(state.scroll(0, 2, false, now), scroll(0, 20, true));
+        assert_eq!(state.scroll(0, 20, true, now), scroll(0, 20, true));
+        // No glide while the fingers are on the trackpad.
+        assert_eq!(state.glide(now), None);
+        let later = now + GLIDE_DELAY;
+        assert_eq!(state.glide(later), scroll(0, 18, true));
+        let mut glided = 18;
+        while let Some(s) = state.glide(later) {
+            glided += s.y;
+        }
+        assert!(glided > 20 && glided < 20 * 13);
+        assert_eq!(state.last_trackpad, None);
+
+        let transform: Transform = serde_json::from_str(r#"{"unit":"line"}"#).unwrap();
+        assert_eq!(transform.multiplier, 1.0);
+        assert_eq!(transform.unit, Unit::Line);
+    }
+}
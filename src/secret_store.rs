@@ -0,0 +1,370 @@
+//! Storage of the secrets of the host in the keystore of the platform.
+//!
+//! The permanent password and the unlock PIN are kept in the config files by default, the key of
+//! the input timeline in a file of its own, readable by its owner only, not among the options the
+//! UI reads and syncs. With `OPTION_SECRET_STORE` on "keychain", the default where the platform has
+//! one, they are kept in the keystore instead, the System keychain of macOS, and removed from the
+//! files. The key pair stays in the config, hbb_common makes a new one whenever it is missing there.
+//!
+//! Only the service, the daemon which runs as root, uses the keystore, `start_service` when it
+//! starts moves all of the secrets there, or back to the files when the option is set to "file".
+//! The other processes, the servers of the login window and of the user, find the secrets missing
+//! from the config the service syncs to them, and ask it for them over ipc, `ipc::Data::Secret`.
+//! If the keystore fails, or the service can not be reached, the files are used as before.
+
+use hbb_common::{config::Config, log, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const OPTION_SECRET_STORE: &str = "secret-store";
+pub const STORE_KEYCHAIN: &str = "keychain";
+pub const STORE_FILE: &str = "file";
+
+// This process is the service, the one with the keystore.
+static IS_SERVICE: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Secret {
+    PermanentPassword,
+    UnlockPin,
+    /// The key the input timeline is encrypted with, see `server::input_timeline`.
+    InputTimelineKey,
+}
+
+impl Secret {
+    const ALL: [Secret; 3] = [
+        Self::PermanentPassword,
+        Self::UnlockPin,
+        Self::InputTimelineKey,
+    ];
+
+    fn account(self) -> &'static str {
+        match self {
+            Self::PermanentPassword => "permanent-password",
+            Self::UnlockPin => "unlock-pin",
+            Self::InputTimelineKey => "input-timeline-key",
+        }
+    }
+
+    fn get_file(self) -> String {
+        match self {
+            Self::PermanentPassword => Config::get_permanent_password(),
+            Self::UnlockPin => Config::get_unlock_pin(),
+            Self::InputTimelineKey => {
+                let value = read_own_file(self.account());
+                // It was an option before.
+                let option = Config::get_option(self.account());
+                if value.is_empty() && !option.is_empty() {
+                    self.set_file(&option);
+                    Config::set_option(self.account().to_owned(), "".to_owned());
+                    return option;
+                }
+                value
+            }
+        }
+    }
+
+    fn set_file(self, value: &str) {
+        match self {
+            Self::PermanentPassword => Config::set_permanent_password(value),
+            Self::UnlockPin => Config::set_unlock_pin(value),
+            Self::InputTimelineKey => write_own_file(self.account(), value),
+        }
+    }
+}
+
+fn own_file_path(account: &str) -> std::path::PathBuf {
+    Config::path(format!("{}.key", account))
+}
+
+fn read_own_file(account: &str) -> String {
+    std::fs::read_to_string(own_file_path(account))
+        .map(|v| v.trim().to_owned())
+        .unwrap_or_default()
+}
+
+// Readable and writable by the owner only, an empty value removes the file.
+fn write_own_file(account: &str, value: &str) {
+    let path = own_file_path(account);
+    if value.is_empty() {
+        std::fs::remove_file(&path).ok();
+        return;
+    }
+    let res = (|| -> std::io::Result<()> {
+        use std::io::Write;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut file = options.open(&path)?;
+        // The mode is only the one of a new file.
+        #[cfg(unix)]
+        file.set_permissions(std::os::unix::fs::PermissionsExt::from_mode(0o600))?;
+        file.write_all(value.as_bytes())
+    })();
+    if let Err(e) = res {
+        log::error!("Failed to save {}: {}", account, e);
+    }
+}
+
+trait Store {
+    fn get(&self, account: &str) -> ResultType<Option<String>>;
+    fn set(&self, account: &str, value: &str) -> ResultType<()>;
+    fn delete(&self, account: &str) -> ResultType<()>;
+}
+
+#[cfg(target_os = "macos")]
+struct Keychain;
+
+#[cfg(target_os = "macos")]
+impl Keychain {
+    // errSecItemNotFound
+    const NOT_FOUND: i32 = -25300;
+    const PATH: &'static str = "/Library/Keychains/System.keychain";
+
+    fn service() -> String {
+        crate::get_app_name()
+    }
+
+    fn open() -> ResultType<security_framework::os::macos::keychain::SecKeychain> {
+        Ok(security_framework::os::macos::keychain::SecKeychain::open(
+            Self::PATH,
+        )?)
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl Store for Keychain {
+    fn get(&self, account: &str) -> ResultType<Option<String>> {
+        match Self::open()?.find_generic_password(&Self::service(), account) {
+            Ok((v, _)) => Ok(Some(String::from_utf8(v.to_vec())?)),
+            Err(e) if e.code() == Self::NOT_FOUND => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn set(&self, account: &str, value: &str) -> ResultType<()> {
+        Ok(Self::open()?.set_generic_password(&Self::service(), account, value.as_bytes())?)
+    }
+
+    fn delete(&self, account: &str) -> ResultType<()> {
+        match Self::open()?.find_generic_password(&Self::service(), account) {
+            Ok((_, item)) => {
+                item.delete();
+                Ok(())
+            }
+            Err(e) if e.code() == Self::NOT_FOUND => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn keystore() -> Option<Box<dyn Store>> {
+    if !IS_SERVICE.load(Ordering::SeqCst) || !crate::platform::is_root() {
+        return None;
+    }
+    Some(Box::new(Keychain))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keystore() -> Option<Box<dyn Store>> {
+    None
+}
+
+fn use_keystore() -> bool {
+    Config::get_option(OPTION_SECRET_STORE) != STORE_FILE
+}
+
+// The secrets are in the keystore of the service, not in the files of this process.
+fn is_kept_by_service() -> bool {
+    cfg!(target_os = "macos") && !IS_SERVICE.load(Ordering::SeqCst) && use_keystore()
+}
+
+// In a thread of its own, the callers may run in a runtime already.
+fn get_from_service(secret: Secret) -> Option<String> {
+    match std::thread::spawn(move || crate::ipc::get_service_secret(secret)).join() {
+        Ok(Ok(value)) => value,
+        Ok(Err(e)) => {
+            log::warn!("Failed to get {} from the service: {}", secret.account(), e);
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+fn set_in_service(secret: Secret, value: &str) -> bool {
+    let value = value.to_owned();
+    match std::thread::spawn(move || crate::ipc::set_service_secret(secret, value)).join() {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            log::warn!("Failed to save {} in the service: {}", secret.account(), e);
+            false
+        }
+        Err(_) => false,
+    }
+}
+
+/// The secrets live in one place, `file` is where they are without the keystore.
+fn load(
+    store: &dyn Store,
+    use_store: bool,
+    account: &str,
+    file: &dyn Fn() -> String,
+    set_file: &dyn Fn(&str),
+) -> String {
+    if use_store {
+        match store.get(account) {
+            Ok(Some(v)) => return v,
+            Ok(None) => {
+                let v = file();
+                if !v.is_empty() {
+                    match store.set(account, &v) {
+                        Ok(()) => {
+                            set_file("");
+                            log::info!("{} moved to the keystore", account);
+                        }
+                        Err(e) => log::warn!("Failed to move {} to the keystore: {}", account, e),
+                    }
+                }
+                return v;
+            }
+            Err(e) => log::warn!("Failed to read {} from the keystore: {}", account, e),
+        }
+        return file();
+    }
+    let v = file();
+    if v.is_empty() {
+        if let Ok(Some(stored)) = store.get(account) {
+            set_file(&stored);
+            hbb_common::allow_err!(store.delete(account));
+            log::info!("{} moved back to the config file", account);
+            return stored;
+        }
+    }
+    v
+}
+
+fn save(store: &dyn Store, use_store: bool, account: &str, value: &str, set_file: &dyn Fn(&str)) {
+    if use_store {
+        let res = if value.is_empty() {
+            store.delete(account)
+        } else {
+            store.set(account, value)
+        };
+        match res {
+            Ok(()) => {
+                set_file("");
+                return;
+            }
+            Err(e) => log::warn!("Failed to save {} in the keystore: {}", account, e),
+        }
+    } else {
+        hbb_common::allow_err!(store.delete(account));
+    }
+    set_file(value);
+}
+
+pub fn get(secret: Secret) -> String {
+    match keystore() {
+        Some(store) => load(
+            store.as_ref(),
+            use_keystore(),
+            secret.account(),
+            &|| secret.get_file(),
+            &|v| secret.set_file(v),
+        ),
+        None => {
+            let value = secret.get_file();
+            if value.is_empty() && is_kept_by_service() {
+                return get_from_service(secret).unwrap_or_default();
+            }
+            value
+        }
+    }
+}
+
+/// Called by the service as it starts, before it answers over ipc. The secrets are moved to the
+/// keystore, or back to the files, as the option says.
+pub fn start_service() {
+    IS_SERVICE.store(true, Ordering::SeqCst);
+    if keystore().is_none() {
+        return;
+    }
+    for secret in Secret::ALL {
+        get(secret);
+    }
+}
+
+pub fn set(secret: Secret, value: &str) {
+    match keystore() {
+        Some(store) => save(
+            store.as_ref(),
+            use_keystore(),
+            secret.account(),
+            value,
+            &|v| secret.set_file(v),
+        ),
+        None => {
+            if is_kept_by_service() && set_in_service(secret, value) {
+                secret.set_file("");
+                return;
+            }
+            secret.set_file(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, collections::HashMap};
+
+    #[derive(Default)]
+    struct Memory(RefCell<HashMap<String, String>>);
+
+    impl Store for Memory {
+        fn get(&self, account: &str) -> ResultType<Option<String>> {
+            Ok(self.0.borrow().get(account).cloned())
+        }
+
+        fn set(&self, account: &str, value: &str) -> ResultType<()> {
+            self.0
+                .borrow_mut()
+                .insert(account.to_owned(), value.to_owned());
+            Ok(())
+        }
+
+        fn delete(&self, account: &str) -> ResultType<()> {
+            self.0.borrow_mut().remove(account);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn secret_store_migration() {
+        let store = Memory::default();
+        let file = RefCell::new("secret".to_owned());
+        let get = || file.borrow().clone();
+        let set = |v: &str| *file.borrow_mut() = v.to_owned();
+
+        // Moved to the keystore on the first read.
+        assert_eq!(load(&store, true, "pw", &get, &set), "secret");
+        assert_eq!(file.borrow().as_str(), "");
+        assert_eq!(load(&store, true, "pw", &get, &set), "secret");
+
+        save(&store, true, "pw", "changed", &set);
+        assert_eq!(store.get("pw").unwrap().as_deref(), Some("changed"));
+        assert_eq!(file.borrow().as_str(), "");
+
+        // And back to the file without the keystore.
+        assert_eq!(load(&store, false, "pw", &get, &set), "changed");
+        assert_eq!(file.borrow().as_str(), "changed");
+        assert_eq!(store.get("pw").unwrap(), None);
+        save(&store, false, "pw", "", &set);
+        assert_eq!(load(&store, false, "pw", &get, &set), "");
+    }
+}
@@ -31,13 +31,30 @@ use video_service::VideoSource;
 use crate::ipc::Data;
 
 pub mod audio_service;
+pub mod auth_provider;
 pub mod terminal_service;
 mod clipboard_service;
 pub mod input_service;
 
 mod connection;
 pub mod display_service;
+mod file_sandbox;
+mod idle_detector;
+pub mod input_timeline;
+mod input_limiter;
+mod input_profile;
+pub mod option_registry;
+mod record_retention;
+pub mod region_filter;
+mod send_queue;
+mod watermark;
 mod service;
+pub mod session_queue;
+mod session_hooks;
+#[cfg(feature = "simulate-clients")]
+pub mod simulation;
+pub mod shared_window;
+mod simulcast;
 mod video_qos;
 pub mod video_service;
 
@@ -74,6 +91,7 @@ pub fn new() -> ServerPtr {
         id_count: hbb_common::rand::random::<i32>() % 1000 + 1000, // ensure positive
     };
     server.add_service(Box::new(audio_service::new()));
+    server.add_service(Box::new(audio_service::new_camera()));
     server.add_service(Box::new(display_service::new()));
     server.add_service(Box::new(clipboard_service::new(
         clipboard_service::NAME.to_owned(),
@@ -113,6 +131,7 @@ async fn accept_connection_(
             addr,
             secure,
             control_permissions,
+            None,
         )
         .await?;
     }
@@ -125,6 +144,7 @@ pub async fn create_tcp_connection(
     addr: SocketAddr,
     secure: bool,
     control_permissions: Option<ControlPermissions>,
+    datagram: Option<crate::video_datagram::DatagramChannel>,
 ) -> ResultType<()> {
     let mut stream = stream;
     let id = server.write().unwrap().get_new_id();
@@ -195,6 +215,7 @@ pub async fn create_tcp_connection(
         id,
         Arc::downgrade(&server),
         control_permissions,
+        datagram,
     )
     .await;
     Ok(())
@@ -263,7 +284,7 @@ async fn create_relay_connection_(
         ..Default::default()
     });
     stream.send(&msg_out).await?;
-    create_tcp_connection(server, stream, peer_addr, secure, control_permissions).await?;
+    create_tcp_connection(server, stream, peer_addr, secure, control_permissions, None).await?;
     Ok(())
 }
 
@@ -271,6 +292,7 @@ impl Server {
     fn is_video_service_name(name: &str) -> bool {
         name.starts_with(VideoSource::Monitor.service_name_prefix())
             || name.starts_with(VideoSource::Camera.service_name_prefix())
+            || name.starts_with(VideoSource::Window.service_name_prefix())
     }
 
     pub fn try_add_primary_camera_service(&mut self) {
@@ -300,6 +322,17 @@ impl Server {
         }
     }
 
+    pub fn try_add_window_service(&mut self) {
+        let window_service_name =
+            video_service::get_service_name(VideoSource::Window, shared_window::WINDOW_IDX);
+        if !self.contains(&window_service_name) {
+            self.add_service(Box::new(video_service::new(
+                VideoSource::Window,
+                shared_window::WINDOW_IDX,
+            )));
+        }
+    }
+
     pub fn add_camera_connection(&mut self, conn: ConnInner) {
         if camera::primary_camera_exists() {
             let primary_camera_name =
@@ -311,11 +344,20 @@ impl Server {
         self.connections.insert(conn.id(), conn);
     }
 
-    pub fn add_connection(&mut self, conn: ConnInner, noperms: &Vec<&'static str>) {
-        let primary_video_service_name = video_service::get_service_name(
-            VideoSource::Monitor,
-            *display_service::PRIMARY_DISPLAY_IDX,
-        );
+    pub fn add_connection(
+        &mut self,
+        conn: ConnInner,
+        noperms: &Vec<&'static str>,
+        source: VideoSource,
+    ) {
+        let primary_video_service_name = if source.is_window() {
+            video_service::get_service_name(VideoSource::Window, shared_window::WINDOW_IDX)
+        } else {
+            video_service::get_service_name(
+                VideoSource::Monitor,
+                *display_service::PRIMARY_DISPLAY_IDX,
+            )
+        };
         for s in self.services.values() {
             let name = s.name();
             if Self::is_video_service_name(&name) && name != primary_video_service_name {
@@ -396,6 +438,27 @@ impl Server {
         }
     }
 
+    // Set the option of the video services of the source the connection subscribes to, of one
+    // display or of all.
+    pub fn set_subbed_video_service_opt(
+        &self,
+        conn_id: i32,
+        source: VideoSource,
+        display: Option<usize>,
+        opt: &str,
+        value: &str,
+    ) {
+        for (k, v) in self.services.iter() {
+            let matched = match display {
+                Some(display) => k == &video_service::get_service_name(source, display),
+                None => k.starts_with(source.service_name_prefix()),
+            };
+            if matched && v.is_subed(conn_id) {
+                v.set_option(opt, value);
+            }
+        }
+    }
+
     fn get_subbed_displays_count(&self, conn_id: i32) -> usize {
         self.services
             .keys()
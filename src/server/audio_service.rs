@@ -18,11 +18,21 @@ use magnum_opus::{Application::*, Channels::*, Encoder};
 use std::sync::atomic::{AtomicBool, Ordering};
 
 pub const NAME: &'static str = "audio";
+/// The microphone of the host in the view camera sessions, next to the camera.
+pub const CAMERA_NAME: &'static str = "camera_audio";
+/// Host option, "Y" to send the microphone to the viewers of the cameras with the audio
+/// permission.
+pub const OPTION_CAMERA_AUDIO: &str = "allow-camera-audio";
+/// Host option, "Y" to play the microphone of the viewers with the audio permission without
+/// a voice call.
+pub const OPTION_TALK_BACK: &str = "allow-talk-back";
 pub const AUDIO_DATA_SIZE_U8: usize = 960 * 4; // 10ms in 48000 stereo
 static RESTARTING: AtomicBool = AtomicBool::new(false);
 
 lazy_static::lazy_static! {
     static ref VOICE_CALL_INPUT_DEVICE: Arc::<Mutex::<Option<String>>> = Default::default();
+    // (conn id, device) picked by the viewers, the last one wins.
+    static ref SESSION_INPUT_DEVICES: Arc::<Mutex::<Vec<(i32, String)>>> = Default::default();
 }
 
 pub fn new() -> GenericService {
@@ -31,6 +41,12 @@ pub fn new() -> GenericService {
     svc.sp
 }
 
+pub fn new_camera() -> GenericService {
+    let svc = EmptyExtraFieldService::new(CAMERA_NAME.to_owned(), true);
+    GenericService::repeat::<cpal_impl::State, _, _>(&svc.clone(), 33, cpal_impl::run_camera);
+    svc.sp
+}
+
 #[inline]
 pub fn get_voice_call_input_device() -> Option<String> {
     VOICE_CALL_INPUT_DEVICE.lock().unwrap().clone()
@@ -49,15 +65,47 @@ pub fn set_voice_call_input_device(device: Option<String>, set_if_present: bool)
     restart();
 }
 
+#[inline]
+pub fn get_session_input_device() -> Option<String> {
+    SESSION_INPUT_DEVICES
+        .lock()
+        .unwrap()
+        .last()
+        .map(|(_, device)| device.clone())
+}
+
+/// The device picked by a viewer instead of the audio input option, `None` to remove the
+/// pick of the connection.
+pub fn set_session_input_device(conn_id: i32, device: Option<String>) {
+    let old = get_audio_input();
+    {
+        let mut devices = SESSION_INPUT_DEVICES.lock().unwrap();
+        devices.retain(|(id, _)| *id != conn_id);
+        if let Some(device) = device {
+            devices.push((conn_id, device));
+        }
+    }
+    if get_audio_input() != old {
+        restart();
+    }
+}
+
 #[inline]
 fn get_audio_input() -> String {
     VOICE_CALL_INPUT_DEVICE
         .lock()
         .unwrap()
         .clone()
+        .or_else(get_session_input_device)
         .unwrap_or(Config::get_option("audio-input"))
 }
 
+/// Names of the input and the output devices. An output device is captured by loopback, if
+/// the platform supports it.
+pub fn get_devices() -> (Vec<String>, Vec<String>) {
+    cpal_impl::get_devices()
+}
+
 pub fn restart() {
     log::info!("restart the audio service, freezing now...");
     if RESTARTING.load(Ordering::SeqCst) {
@@ -84,7 +132,6 @@ mod cpal_impl {
 
     lazy_static::lazy_static! {
         static ref HOST: Host = cpal::default_host();
-        static ref INPUT_BUFFER: Arc<Mutex<std::collections::VecDeque<f32>>> = Default::default();
     }
 
     #[cfg(feature = "screencapturekit")]
@@ -108,7 +155,7 @@ mod cpal_impl {
         sp.snapshot(|_sps: ServiceSwap<_>| Ok(()))?;
         match &state.stream {
             None => {
-                state.stream = Some(play(&sp)?);
+                state.stream = Some(play(&sp, false)?);
             }
             _ => {}
         }
@@ -119,11 +166,15 @@ mod cpal_impl {
         Ok(())
     }
 
-    fn run_serv_snapshot(sp: EmptyExtraFieldService, state: &mut State) -> ResultType<()> {
+    fn run_serv_snapshot(
+        sp: EmptyExtraFieldService,
+        state: &mut State,
+        camera: bool,
+    ) -> ResultType<()> {
         sp.snapshot(|sps| {
             match &state.stream {
                 None => {
-                    state.stream = Some(play(&sp)?);
+                    state.stream = Some(play(&sp, camera)?);
                 }
                 _ => {}
             }
@@ -137,12 +188,17 @@ mod cpal_impl {
 
     pub fn run(sp: EmptyExtraFieldService, state: &mut State) -> ResultType<()> {
         if !RESTARTING.load(Ordering::SeqCst) {
-            run_serv_snapshot(sp, state)
+            run_serv_snapshot(sp, state, false)
         } else {
             run_restart(sp, state)
         }
     }
 
+    // The microphone does not follow the audio input option and the voice calls.
+    pub fn run_camera(sp: EmptyExtraFieldService, state: &mut State) -> ResultType<()> {
+        run_serv_snapshot(sp, state, true)
+    }
+
     fn send(
         data: Vec<f32>,
         sample_rate0: u32,
@@ -150,6 +206,7 @@ mod cpal_impl {
         device_channel: u16,
         encode_channel: u16,
         encoder: &mut Encoder,
+        zero_count: &mut u16,
         sp: &GenericService,
     ) {
         let mut data = data;
@@ -165,7 +222,7 @@ mod cpal_impl {
                 encode_channel,
             )
         }
-        send_f32(&data, encoder, sp);
+        send_f32(&data, encoder, zero_count, sp);
     }
 
     #[cfg(feature = "screencapturekit")]
@@ -226,17 +283,62 @@ mod cpal_impl {
                 .with_context(|| "Failed to get default input device for loopback")?,
         );
         log::info!("Input device: {}", device.name().unwrap_or("".to_owned()));
-        let format = device
-            .default_input_config()
-            .map_err(|e| anyhow!(e))
-            .with_context(|| "Failed to get default input format")?;
+        let format = match device.default_input_config() {
+            Ok(format) => format,
+            // An output device, captured by loopback.
+            Err(e) => device
+                .default_output_config()
+                .map_err(|_| anyhow!(e))
+                .with_context(|| "Failed to get default input format")?,
+        };
         log::info!("Default input format: {:?}", format);
         Ok((device, format))
     }
 
-    fn play(sp: &GenericService) -> ResultType<(Box<dyn StreamTrait>, Arc<Message>)> {
+    pub fn get_devices() -> (Vec<String>, Vec<String>) {
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        #[cfg(feature = "screencapturekit")]
+        if is_screen_capture_kit_available() {
+            if let Ok(devices) = HOST_SCREEN_CAPTURE_KIT.as_ref().map(|h| h.input_devices()) {
+                for d in devices.into_iter().flatten() {
+                    if let Ok(name) = d.name() {
+                        inputs.push(name);
+                    }
+                }
+            }
+        }
+        if let Ok(devices) = HOST.input_devices() {
+            for d in devices {
+                if d.default_input_config().is_err() {
+                    continue;
+                }
+                if let Ok(name) = d.name() {
+                    if !inputs.contains(&name) {
+                        inputs.push(name);
+                    }
+                }
+            }
+        }
+        if let Ok(devices) = HOST.output_devices() {
+            for d in devices {
+                if let Ok(name) = d.name() {
+                    if !inputs.contains(&name) && !outputs.contains(&name) {
+                        outputs.push(name);
+                    }
+                }
+            }
+        }
+        (inputs, outputs)
+    }
+
+    fn play(sp: &GenericService, camera: bool) -> ResultType<(Box<dyn StreamTrait>, Arc<Message>)> {
         use cpal::SampleFormat::*;
-        let (device, config) = get_device()?;
+        let (device, config) = if camera {
+            get_audio_input("")?
+        } else {
+            get_device()?
+        };
         let sp = sp.clone();
         // Sample rate must be one of 8000, 12000, 16000, 24000, or 48000.
         let sample_rate_0 = config.sample_rate().0;
@@ -288,9 +390,7 @@ mod cpal_impl {
         };
         let sample_rate_0 = config.sample_rate().0;
         log::debug!("Audio sample rate : {}", sample_rate);
-        unsafe {
-            AUDIO_ZERO_COUNT = 0;
-        }
+        let mut zero_count = 0;
         let device_channel = config.channels();
         let mut encoder = Encoder::new(sample_rate, encode_channel, LowDelay)?;
         // https://www.opus-codec.org/docs/html_api/group__opusencoder.html#gace941e4ef26ed844879fde342ffbe546
@@ -301,7 +401,7 @@ mod cpal_impl {
         let frame_size = sample_rate_0 as usize / 100; // 10 ms
         let encode_len = frame_size * encode_channel as usize;
         let rechannel_len = encode_len * device_channel as usize / encode_channel as usize;
-        INPUT_BUFFER.lock().unwrap().clear();
+        let mut input_buffer = std::collections::VecDeque::new();
         let timeout = None;
         let stream_config = StreamConfig {
             channels: device_channel,
@@ -311,11 +411,9 @@ mod cpal_impl {
         let stream = device.build_input_stream(
             &stream_config,
             move |data: &[T], _: &InputCallbackInfo| {
-                let buffer: Vec<f32> = data.iter().map(|s| T::to_sample(*s)).collect();
-                let mut lock = INPUT_BUFFER.lock().unwrap();
-                lock.extend(buffer);
-                while lock.len() >= rechannel_len {
-                    let frame: Vec<f32> = lock.drain(0..rechannel_len).collect();
+                input_buffer.extend(data.iter().map(|s| T::to_sample(*s)));
+                while input_buffer.len() >= rechannel_len {
+                    let frame: Vec<f32> = input_buffer.drain(0..rechannel_len).collect();
                     send(
                         frame,
                         sample_rate_0,
@@ -323,6 +421,7 @@ mod cpal_impl {
                         device_channel,
                         encode_channel as _,
                         &mut encoder,
+                        &mut zero_count,
                         &sp,
                     );
                 }
@@ -347,28 +446,23 @@ fn create_format_msg(sample_rate: u32, channels: u16) -> Message {
     msg
 }
 
-// use AUDIO_ZERO_COUNT for the Noise(Zero) Gate Attack Time
+// use zero_count for the Noise(Zero) Gate Attack Time, one per stream
 // every audio data length is set to 480
 // MAX_AUDIO_ZERO_COUNT=800 is similar as Gate Attack Time 3~5s(Linux) || 6~8s(Windows)
 const MAX_AUDIO_ZERO_COUNT: u16 = 800;
-static mut AUDIO_ZERO_COUNT: u16 = 0;
 
-fn send_f32(data: &[f32], encoder: &mut Encoder, sp: &GenericService) {
+fn send_f32(data: &[f32], encoder: &mut Encoder, zero_count: &mut u16, sp: &GenericService) {
     if data.iter().filter(|x| **x != 0.).next().is_some() {
-        unsafe {
-            AUDIO_ZERO_COUNT = 0;
-        }
+        *zero_count = 0;
     } else {
-        unsafe {
-            if AUDIO_ZERO_COUNT > MAX_AUDIO_ZERO_COUNT {
-                if AUDIO_ZERO_COUNT == MAX_AUDIO_ZERO_COUNT + 1 {
-                    log::debug!("Audio Zero Gate Attack");
-                    AUDIO_ZERO_COUNT += 1;
-                }
-                return;
+        if *zero_count > MAX_AUDIO_ZERO_COUNT {
+            if *zero_count == MAX_AUDIO_ZERO_COUNT + 1 {
+                log::debug!("Audio Zero Gate Attack");
+                *zero_count += 1;
             }
-            AUDIO_ZERO_COUNT += 1;
+            return;
         }
+        *zero_count += 1;
     }
     match encoder.encode_vec_float(data, data.len() * 6) {
         Ok(data) => {
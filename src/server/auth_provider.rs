@@ -0,0 +1,718 @@
+//! Verification of the viewers by a directory of the enterprise instead of the local passwords.
+//!
+//! `OPTION_AUTH_PROVIDER` picks the `Provider`: "ldap" binds to `OPTION_LDAP_URL` as the user,
+//! over ldaps, "oidc" runs the device authorization flow of `OPTION_OIDC_DEVICE_ENDPOINT`, over https, and
+//! checks the signature, the issuer `OPTION_OIDC_ISSUER` and the audience, the client id, of the id
+//! token with the keys the issuer publishes. When one is set, a login which is not a recent session
+//! gets the login error `LOGIN_MSG_PREFIX` followed by the json `Challenge` instead, and the viewer
+//! sends its `Answer` as a plugin request, before it is authorized. The failures count like the
+//! wrong passwords, the attempts blocked by them are not sent to the provider, and the name of the
+//! provider and the user it verified go to the audit of the connection.
+//!
+//! `OPTION_AUTH_ALLOWED` limits the users let in to the ones listed, by name or by a group as
+//! "group:<name>": the `memberOf` of the LDAP entry of the user, or the `groups` claim of the id
+//! token. Without it no user is let in, the directory alone does not open the host to all of its
+//! users.
+//!
+//! Another provider is a `Provider` added to `from_policy`.
+
+use crate::plugin_request;
+use hbb_common::{
+    anyhow::anyhow,
+    bail,
+    config::Config,
+    log,
+    message_proto::{message, misc, Message},
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, Instant},
+};
+
+pub const OPTION_AUTH_PROVIDER: &str = "auth-provider";
+pub const OPTION_LDAP_URL: &str = "auth-ldap-url";
+// The DN to bind as, "{user}" is replaced by the escaped user name.
+pub const OPTION_LDAP_BIND_DN: &str = "auth-ldap-bind-dn";
+pub const OPTION_OIDC_DEVICE_ENDPOINT: &str = "auth-oidc-device-endpoint";
+pub const OPTION_OIDC_TOKEN_ENDPOINT: &str = "auth-oidc-token-endpoint";
+pub const OPTION_OIDC_CLIENT_ID: &str = "auth-oidc-client-id";
+pub const OPTION_OIDC_ISSUER: &str = "auth-oidc-issuer";
+// `;` separated users and "group:<name>", no user when empty.
+pub const OPTION_AUTH_ALLOWED: &str = "auth-allowed";
+const GROUP_PREFIX: &str = "group:";
+pub const ID: &str = "auth-provider";
+pub const LOGIN_MSG_PREFIX: &str = "auth-provider:";
+
+const LDAP_TIMEOUT: Duration = Duration::from_secs(10);
+const LDAP_RESULT_SUCCESS: u8 = 0;
+// How long a device code answer waits for the authorization before the viewer is asked again.
+const OIDC_WAIT: Duration = Duration::from_secs(10);
+
+/// What the viewer is asked for.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Prompt {
+    Credentials,
+    DeviceCode {
+        verification_uri: String,
+        user_code: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Challenge {
+    pub provider: String,
+    pub prompt: Prompt,
+    // The last answer was wrong.
+    #[serde(default)]
+    pub failed: bool,
+}
+
+impl Challenge {
+    pub fn to_login_error(&self) -> String {
+        format!(
+            "{}{}",
+            LOGIN_MSG_PREFIX,
+            serde_json::to_string(self).unwrap_or_default()
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Answer {
+    Credentials { user: String, password: String },
+    // The user authorized the device in the browser.
+    DeviceCode,
+}
+
+impl Answer {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+
+    /// The answer of a message of the viewer, if it is one.
+    pub fn of(msg: &Message) -> Option<Self> {
+        match &msg.union {
+            Some(message::Union::Misc(m)) => match &m.union {
+                Some(misc::Union::PluginRequest(p)) if p.id == ID => {
+                    serde_json::from_slice(&p.content).ok()
+                }
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// A user verified by the directory.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Identity {
+    pub user: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Passed(Identity),
+    // Not authorized yet, the viewer is asked again.
+    Pending,
+    Failed,
+}
+
+/// A source of the identities of the viewers. The calls block, on the network.
+pub trait Provider: Send {
+    fn name(&self) -> &'static str;
+    fn prompt(&mut self) -> ResultType<Prompt>;
+    fn verify(&mut self, answer: &Answer) -> ResultType<Verdict>;
+}
+
+/// The provider of the policy, none to check the local passwords.
+pub fn from_policy() -> Option<Box<dyn Provider>> {
+    match Config::get_option(OPTION_AUTH_PROVIDER).as_str() {
+        "ldap" => Some(Box::new(Ldap {
+            url: Config::get_option(OPTION_LDAP_URL),
+            bind_dn: Config::get_option(OPTION_LDAP_BIND_DN),
+            with_groups: allowed().iter().any(|a| a.starts_with(GROUP_PREFIX)),
+        })),
+        "oidc" => Some(Box::new(Oidc {
+            device_endpoint: Config::get_option(OPTION_OIDC_DEVICE_ENDPOINT),
+            token_endpoint: Config::get_option(OPTION_OIDC_TOKEN_ENDPOINT),
+            client_id: Config::get_option(OPTION_OIDC_CLIENT_ID),
+            issuer: Config::get_option(OPTION_OIDC_ISSUER),
+            device_code: None,
+        })),
+        _ => None,
+    }
+}
+
+/// Some user may be let in, `OPTION_AUTH_ALLOWED` is set.
+pub fn is_any_allowed() -> bool {
+    !allowed().is_empty()
+}
+
+fn allowed() -> Vec<String> {
+    Config::get_option(OPTION_AUTH_ALLOWED)
+        .split(';')
+        .map(|a| a.trim().to_lowercase())
+        .filter(|a| !a.is_empty())
+        .collect()
+}
+
+// A group is matched by its DN or by the value of its first RDN, "admins" of "cn=admins,dc=a".
+fn is_allowed_in(allowed: &[String], identity: &Identity) -> bool {
+    allowed.iter().any(|a| match a.strip_prefix(GROUP_PREFIX) {
+        Some(group) => identity.groups.iter().any(|g| {
+            let g = g.to_lowercase();
+            let name = g
+                .split(',')
+                .next()
+                .and_then(|rdn| rdn.split_once('='))
+                .map(|(_, v)| v.trim());
+            g == group || name == Some(group)
+        }),
+        None => identity.user.to_lowercase() == *a,
+    })
+}
+
+/// Verify the answer with the provider, the users not allowed by `OPTION_AUTH_ALLOWED` fail.
+pub fn verify(provider: &mut dyn Provider, answer: &Answer) -> ResultType<Verdict> {
+    let verdict = provider.verify(answer)?;
+    if let Verdict::Passed(identity) = &verdict {
+        if !is_allowed_in(&allowed(), identity) {
+            log::warn!("{} is not allowed to connect", identity.user);
+            return Ok(Verdict::Failed);
+        }
+    }
+    Ok(verdict)
+}
+
+/// A simple bind to an LDAP server, "ldaps://host[:636]", and the read of the groups of the user if
+/// they are needed. The bind sends the password, "ldap://" is refused not to send it in clear.
+struct Ldap {
+    url: String,
+    bind_dn: String,
+    with_groups: bool,
+}
+
+impl Provider for Ldap {
+    fn name(&self) -> &'static str {
+        "ldap"
+    }
+
+    fn prompt(&mut self) -> ResultType<Prompt> {
+        Ok(Prompt::Credentials)
+    }
+
+    fn verify(&mut self, answer: &Answer) -> ResultType<Verdict> {
+        let Answer::Credentials { user, password } = answer else {
+            return Ok(Verdict::Failed);
+        };
+        // An empty password is an unauthenticated bind, which most servers accept.
+        if user.is_empty() || password.is_empty() {
+            return Ok(Verdict::Failed);
+        }
+        let dn = self.bind_dn.replace("{user}", &escape_dn_value(user));
+        let groups = if let Some(host) = self.url.strip_prefix("ldaps://") {
+            let host = host.trim_end_matches('/');
+            let addr = with_port(host, 636);
+            let name = host.split(':').next().unwrap_or_default();
+            let tls = native_tls::TlsConnector::new()?;
+            let mut stream = tls.connect(name, connect(&addr)?)?;
+            ldap_session(&mut stream, &dn, password, self.with_groups)?
+        } else {
+            bail!("Not an ldaps url: {}", self.url);
+        };
+        Ok(match groups {
+            Some(groups) => Verdict::Passed(Identity {
+                user: user.clone(),
+                groups,
+            }),
+            None => Verdict::Failed,
+        })
+    }
+}
+
+// Bind as the user, and read its groups. None if the bind failed.
+fn ldap_session<S: Read + Write>(
+    stream: &mut S,
+    dn: &str,
+    password: &str,
+    with_groups: bool,
+) -> ResultType<Option<Vec<String>>> {
+    let mut pending = Vec::new();
+    stream.write_all(&ldap_bind_request(dn, password))?;
+    match ldap_result_code(&read_message(stream, &mut pending)?) {
+        Some(LDAP_RESULT_SUCCESS) => {}
+        Some(_) => return Ok(None),
+        None => bail!("Invalid LDAP response"),
+    }
+    let mut groups = Vec::new();
+    if !with_groups {
+        return Ok(Some(groups));
+    }
+    stream.write_all(&ldap_search_request(dn))?;
+    loop {
+        match ldap_search_response(&read_message(stream, &mut pending)?) {
+            Some(SearchResponse::Entry(values)) => groups.extend(values),
+            Some(SearchResponse::Done) => return Ok(Some(groups)),
+            Some(SearchResponse::Other) => {}
+            None => bail!("Invalid LDAP response"),
+        }
+    }
+}
+
+fn with_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        host.to_owned()
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+fn connect(addr: &str) -> ResultType<TcpStream> {
+    let addr = addr
+        .to_socket_addrs()?
+        .next()
+        .ok_or(anyhow!("Failed to resolve {}", addr))?;
+    let stream = TcpStream::connect_timeout(&addr, LDAP_TIMEOUT)?;
+    stream.set_read_timeout(Some(LDAP_TIMEOUT))?;
+    stream.set_write_timeout(Some(LDAP_TIMEOUT))?;
+    Ok(stream)
+}
+
+// Read the next message of the server, `pending` keeps what was read after it.
+fn read_message<S: Read>(stream: &mut S, pending: &mut Vec<u8>) -> ResultType<Vec<u8>> {
+    let mut buf = [0u8; 1024];
+    loop {
+        if let Some((_, _, end)) = read_tlv(pending, 0) {
+            let rest = pending.split_off(end);
+            return Ok(std::mem::replace(pending, rest));
+        }
+        let n = stream.read(&mut buf)?;
+        if n == 0 || pending.len() > 64 * 1024 {
+            bail!("Incomplete LDAP response");
+        }
+        pending.extend_from_slice(&buf[..n]);
+    }
+}
+
+// The special characters of RFC 4514.
+fn escape_dn_value(value: &str) -> String {
+    let mut escaped = String::new();
+    for (i, c) in value.chars().enumerate() {
+        let special = matches!(c, ',' | '+' | '"' | '\\' | '<' | '>' | ';' | '=')
+            || (i == 0 && (c == '#' || c == ' '))
+            || (i == value.chars().count() - 1 && c == ' ');
+        if special {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut out = vec![tag];
+    let len = content.len();
+    if len < 0x80 {
+        out.push(len as u8);
+    } else {
+        let bytes: Vec<u8> = len
+            .to_be_bytes()
+            .into_iter()
+            .skip_while(|b| *b == 0)
+            .collect();
+        out.push(0x80 | bytes.len() as u8);
+        out.extend(bytes);
+    }
+    out.extend_from_slice(content);
+    out
+}
+
+// The tag, the content and the end of the BER element at `pos`.
+fn read_tlv(buf: &[u8], pos: usize) -> Option<(u8, &[u8], usize)> {
+    let tag = *buf.get(pos)?;
+    let first = *buf.get(pos + 1)? as usize;
+    let (len, start) = if first < 0x80 {
+        (first, pos + 2)
+    } else {
+        let n = first & 0x7f;
+        if n == 0 || n > 4 {
+            return None;
+        }
+        let len = buf
+            .get(pos + 2..pos + 2 + n)?
+            .iter()
+            .fold(0usize, |len, b| len << 8 | *b as usize);
+        (len, pos + 2 + n)
+    };
+    Some((tag, buf.get(start..start + len)?, start + len))
+}
+
+// LDAPMessage { messageID 1, BindRequest { version 3, name, simple password } }
+fn ldap_bind_request(dn: &str, password: &str) -> Vec<u8> {
+    let mut bind = tlv(0x02, &[3]);
+    bind.extend(tlv(0x04, dn.as_bytes()));
+    bind.extend(tlv(0x80, password.as_bytes()));
+    let mut message = tlv(0x02, &[1]);
+    message.extend(tlv(0x60, &bind));
+    tlv(0x30, &message)
+}
+
+// LDAPMessage { messageID 2, SearchRequest { dn, baseObject, neverDerefAliases, no limits,
+// (objectClass=*), memberOf } }
+fn ldap_search_request(dn: &str) -> Vec<u8> {
+    let mut search = tlv(0x04, dn.as_bytes());
+    search.extend(tlv(0x0a, &[0]));
+    search.extend(tlv(0x0a, &[0]));
+    search.extend(tlv(0x02, &[0]));
+    search.extend(tlv(0x02, &[0]));
+    search.extend(tlv(0x01, &[0]));
+    search.extend(tlv(0x87, b"objectClass"));
+    search.extend(tlv(0x30, &tlv(0x04, b"memberOf")));
+    let mut message = tlv(0x02, &[2]);
+    message.extend(tlv(0x63, &search));
+    tlv(0x30, &message)
+}
+
+#[derive(Debug, PartialEq)]
+enum SearchResponse {
+    // The values of the attributes of the entry.
+    Entry(Vec<String>),
+    Done,
+    Other,
+}
+
+fn ldap_search_response(response: &[u8]) -> Option<SearchResponse> {
+    let (0x30, message, _) = read_tlv(response, 0)? else {
+        return None;
+    };
+    let (0x02, _, next) = read_tlv(message, 0)? else {
+        return None;
+    };
+    let (tag, op, _) = read_tlv(message, next)?;
+    match tag {
+        0x64 => {
+            let (0x04, _, next) = read_tlv(op, 0)? else {
+                return None;
+            };
+            let (0x30, attributes, _) = read_tlv(op, next)? else {
+                return None;
+            };
+            let mut values = Vec::new();
+            let mut pos = 0;
+            while let Some((0x30, attribute, end)) = read_tlv(attributes, pos) {
+                if let Some((0x04, _, next)) = read_tlv(attribute, 0) {
+                    if let Some((0x31, set, _)) = read_tlv(attribute, next) {
+                        let mut pos = 0;
+                        while let Some((0x04, value, end)) = read_tlv(set, pos) {
+                            values.push(String::from_utf8_lossy(value).into_owned());
+                            pos = end;
+                        }
+                    }
+                }
+                pos = end;
+            }
+            Some(SearchResponse::Entry(values))
+        }
+        0x65 => Some(SearchResponse::Done),
+        _ => Some(SearchResponse::Other),
+    }
+}
+
+// The resultCode of the BindResponse.
+fn ldap_result_code(response: &[u8]) -> Option<u8> {
+    let (0x30, message, _) = read_tlv(response, 0)? else {
+        return None;
+    };
+    let (0x02, _, next) = read_tlv(message, 0)? else {
+        return None;
+    };
+    let (0x61, bind, _) = read_tlv(message, next)? else {
+        return None;
+    };
+    match read_tlv(bind, 0)? {
+        (0x0a, code, _) if code.len() == 1 => Some(code[0]),
+        _ => None,
+    }
+}
+
+/// The OAuth 2.0 device authorization grant of RFC 8628, with the OpenID Connect id token.
+struct Oidc {
+    device_endpoint: String,
+    token_endpoint: String,
+    client_id: String,
+    issuer: String,
+    device_code: Option<(String, Duration)>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Discovery {
+    jwks_uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceAuthorization {
+    device_code: String,
+    user_code: String,
+    verification_uri: String,
+    #[serde(default)]
+    verification_uri_complete: Option<String>,
+    #[serde(default)]
+    interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    #[serde(default)]
+    id_token: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+}
+
+impl Provider for Oidc {
+    fn name(&self) -> &'static str {
+        "oidc"
+    }
+
+    fn prompt(&mut self) -> ResultType<Prompt> {
+        require_https(&self.device_endpoint)?;
+        let client = crate::hbbs_http::create_http_client_with_url(&self.device_endpoint);
+        let auth: DeviceAuthorization = client
+            .post(&self.device_endpoint)
+            .form(&[
+                ("client_id", self.client_id.as_str()),
+                ("scope", "openid profile email"),
+            ])
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let interval = Duration::from_secs(auth.interval.unwrap_or(5).max(1));
+        self.device_code = Some((auth.device_code, interval));
+        Ok(Prompt::DeviceCode {
+            verification_uri: auth
+                .verification_uri_complete
+                .unwrap_or(auth.verification_uri),
+            user_code: auth.user_code,
+        })
+    }
+
+    fn verify(&mut self, answer: &Answer) -> ResultType<Verdict> {
+        let device_code = self.device_code.clone();
+        let (Answer::DeviceCode, Some((device_code, interval))) = (answer, device_code) else {
+            return Ok(Verdict::Failed);
+        };
+        require_https(&self.token_endpoint)?;
+        let client = crate::hbbs_http::create_http_client_with_url(&self.token_endpoint);
+        let start = Instant::now();
+        loop {
+            let token: TokenResponse = client
+                .post(&self.token_endpoint)
+                .form(&[
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                    ("device_code", device_code.as_str()),
+                    ("client_id", self.client_id.as_str()),
+                ])
+                .send()?
+                .json()?;
+            match (token.id_token, token.error.as_deref()) {
+                (Some(id_token), _) => {
+                    self.device_code = None;
+                    let claims = self.validate(&id_token)?;
+                    return Ok(match identity_of(&claims) {
+                        Some(identity) => Verdict::Passed(identity),
+                        None => Verdict::Failed,
+                    });
+                }
+                (None, Some("authorization_pending" | "slow_down")) => {
+                    if start.elapsed() + interval > OIDC_WAIT {
+                        return Ok(Verdict::Pending);
+                    }
+                    std::thread::sleep(interval);
+                }
+                _ => {
+                    self.device_code = None;
+                    return Ok(Verdict::Failed);
+                }
+            }
+        }
+    }
+}
+
+impl Oidc {
+    // The claims of the id token, signed by a key of the issuer for this client.
+    fn validate(&self, id_token: &str) -> ResultType<serde_json::Value> {
+        use jsonwebtoken::{jwk::JwkSet, Algorithm::*};
+        let issuer = self.issuer.trim_end_matches('/');
+        require_https(issuer)?;
+        let client = crate::hbbs_http::create_http_client_with_url(issuer);
+        let discovery: Discovery = client
+            .get(format!("{}/.well-known/openid-configuration", issuer))
+            .send()?
+            .error_for_status()?
+            .json()?;
+        require_https(&discovery.jwks_uri)?;
+        let jwks: JwkSet = client
+            .get(&discovery.jwks_uri)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        let header = jsonwebtoken::decode_header(id_token)?;
+        if !matches!(
+            header.alg,
+            RS256 | RS384 | RS512 | PS256 | PS384 | PS512 | ES256 | ES384 | EdDSA
+        ) {
+            bail!("Unsupported algorithm of the id token: {:?}", header.alg);
+        }
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None => jwks.keys.first(),
+        }
+        .ok_or(anyhow!("No key of the issuer for the id token"))?;
+        let key = jsonwebtoken::DecodingKey::from_jwk(jwk)?;
+        let mut validation = jsonwebtoken::Validation::new(header.alg);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_issuer(&[&self.issuer]);
+        Ok(jsonwebtoken::decode::<serde_json::Value>(id_token, &key, &validation)?.claims)
+    }
+}
+
+fn require_https(url: &str) -> ResultType<()> {
+    if !url
+        .get(..8)
+        .map(|s| s.eq_ignore_ascii_case("https://"))
+        .unwrap_or_default()
+    {
+        bail!("Not an https url: {}", url);
+    }
+    Ok(())
+}
+
+// The user and the groups of the claims of a valid id token.
+fn identity_of(claims: &serde_json::Value) -> Option<Identity> {
+    let user = ["preferred_username", "email", "sub"]
+        .iter()
+        .find_map(|k| claims.get(k)?.as_str().map(|s| s.to_owned()))?;
+    let groups = claims
+        .get("groups")
+        .and_then(|g| g.as_array())
+        .map(|g| {
+            g.iter()
+                .filter_map(|g| g.as_str().map(|s| s.to_owned()))
+                .collect()
+        })
+        .unwrap_or_default();
+    Some(Identity { user, groups })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auth_provider_ldap() {
+        let request = ldap_bind_request("uid=a,dc=b", "pw");
+        let (tag, message, end) = read_tlv(&request, 0).unwrap();
+        assert_eq!((tag, end), (0x30, request.len()));
+        assert_eq!(read_tlv(message, 0), Some((0x02, &[1u8][..], 3)));
+        assert_eq!(read_tlv(message, 3).unwrap().0, 0x60);
+        let long = tlv(0x04, &[0; 300]);
+        assert_eq!(&long[..4], &[0x04, 0x82, 0x01, 0x2c]);
+        assert_eq!(read_tlv(&long, 0).unwrap().1.len(), 300);
+
+        // invalidCredentials
+        let response = [
+            0x30, 0x0c, 0x02, 0x01, 0x01, 0x61, 0x07, 0x0a, 0x01, 0x31, 0x04, 0x00, 0x04, 0x00,
+        ];
+        assert_eq!(ldap_result_code(&response), Some(49));
+        assert_eq!(ldap_result_code(&response[..8]), None);
+
+        assert_eq!(escape_dn_value("a,b=c"), "a\\,b\\=c");
+        assert_eq!(escape_dn_value("#a "), "\\#a\\ ");
+        assert_eq!(with_port("host", 389), "host:389");
+
+        let request = ldap_search_request("uid=a,dc=b");
+        let (_, message, _) = read_tlv(&request, 0).unwrap();
+        assert_eq!(read_tlv(message, 3).unwrap().0, 0x63);
+        let value = tlv(0x04, b"cn=admins,dc=b");
+        let attribute = [tlv(0x04, b"memberOf"), tlv(0x31, &value)].concat();
+        let entry = [tlv(0x04, b"uid=a,dc=b"), tlv(0x30, &tlv(0x30, &attribute))].concat();
+        let response = tlv(0x30, &[tlv(0x02, &[2]), tlv(0x64, &entry)].concat());
+        assert_eq!(
+            ldap_search_response(&response),
+            Some(SearchResponse::Entry(vec!["cn=admins,dc=b".to_owned()]))
+        );
+        let done = tlv(0x30, &[tlv(0x02, &[2]), tlv(0x65, &[])].concat());
+        assert_eq!(ldap_search_response(&done), Some(SearchResponse::Done));
+        let mut pending = [response.clone(), done].concat();
+        let mut stream: &[u8] = &[];
+        assert_eq!(read_message(&mut stream, &mut pending).unwrap(), response);
+        assert_eq!(read_message(&mut stream, &mut pending).unwrap().len(), 7);
+        assert!(read_message(&mut stream, &mut pending).is_err());
+    }
+
+    #[test]
+    fn auth_provider_allowed() {
+        let identity = Identity {
+            user: "Alice".to_owned(),
+            groups: vec!["cn=Admins,ou=groups,dc=b".to_owned(), "ops".to_owned()],
+        };
+        let allowed = |s: &[&str]| s.iter().map(|a| a.to_lowercase()).collect::<Vec<_>>();
+        assert!(!is_allowed_in(&[], &identity));
+        assert!(is_allowed_in(&allowed(&["bob", "alice"]), &identity));
+        assert!(!is_allowed_in(&allowed(&["bob"]), &identity));
+        assert!(is_allowed_in(&allowed(&["group:admins"]), &identity));
+        assert!(is_allowed_in(&allowed(&["group:ops"]), &identity));
+        assert!(is_allowed_in(
+            &allowed(&["group:cn=admins,ou=groups,dc=b"]),
+            &identity
+        ));
+        assert!(!is_allowed_in(&allowed(&["group:alice"]), &identity));
+        let mut ldap = Ldap {
+            url: "ldap://directory".to_owned(),
+            bind_dn: "uid={user},dc=b".to_owned(),
+            with_groups: false,
+        };
+        let answer = Answer::Credentials {
+            user: "alice".to_owned(),
+            password: "pw".to_owned(),
+        };
+        assert!(ldap.verify(&answer).is_err());
+        assert!(require_https("http://idp").is_err());
+        assert!(require_https("https://idp/token").is_ok());
+    }
+
+    #[test]
+    fn auth_provider_messages() {
+        let answer = Answer::Credentials {
+            user: "a".to_owned(),
+            password: "b".to_owned(),
+        };
+        assert_eq!(Answer::of(&answer.to_message()), Some(answer));
+        let challenge = Challenge {
+            provider: "oidc".to_owned(),
+            prompt: Prompt::DeviceCode {
+                verification_uri: "https://a".to_owned(),
+                user_code: "B".to_owned(),
+            },
+            failed: true,
+        };
+        let err = challenge.to_login_error();
+        let json = err.strip_prefix(LOGIN_MSG_PREFIX).unwrap();
+        assert_eq!(serde_json::from_str::<Challenge>(json).unwrap(), challenge);
+
+        let claims = serde_json::json!({"sub": "1", "email": "a@b.c", "groups": ["g"]});
+        assert_eq!(
+            identity_of(&claims),
+            Some(Identity {
+                user: "a@b.c".to_owned(),
+                groups: vec!["g".to_owned()],
+            })
+        );
+        assert_eq!(identity_of(&serde_json::json!({})), None);
+    }
+}
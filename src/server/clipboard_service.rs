@@ -14,6 +14,40 @@ use std::{
     time::Duration,
 };
 
+/// The direction the clipboard is synced in, to keep the data from leaving this computer or from
+/// entering it. A built-in value wins over the one of the config, so a provisioned policy can't be
+/// lifted locally.
+pub const OPTION_CLIPBOARD_DIRECTION: &str = "clipboard-direction";
+pub const DIRECTION_BOTH: &str = "both";
+pub const DIRECTION_HOST_TO_CLIENT: &str = "host-to-client";
+pub const DIRECTION_CLIENT_TO_HOST: &str = "client-to-host";
+pub const DIRECTION_OFF: &str = "off";
+
+fn direction() -> String {
+    let builtin = crate::get_builtin_option(OPTION_CLIPBOARD_DIRECTION);
+    if builtin.is_empty() {
+        Config::get_option(OPTION_CLIPBOARD_DIRECTION)
+    } else {
+        builtin
+    }
+}
+
+/// Whether the clipboard of this computer may be sent to the peers.
+pub fn is_host_to_client_allowed() -> bool {
+    !matches!(
+        direction().as_str(),
+        DIRECTION_CLIENT_TO_HOST | DIRECTION_OFF
+    )
+}
+
+/// Whether the clipboard of the peers may be set on this computer.
+pub fn is_client_to_host_allowed() -> bool {
+    !matches!(
+        direction().as_str(),
+        DIRECTION_HOST_TO_CLIENT | DIRECTION_OFF
+    )
+}
+
 struct Handler {
     ctx: Option<ClipboardContext>,
 }
@@ -35,6 +69,10 @@ fn run(sp: EmptyExtraFieldService) -> ResultType<()> {
     while sp.ok() {
         match rx_cb_result.recv_timeout(Duration::from_millis(INTERVAL)) {
             Ok(CallbackResult::Next) => {
+                // Checked on each change, the subscriptions are not updated with the option.
+                if !is_host_to_client_allowed() {
+                    continue;
+                }
                 #[cfg(feature = "unix-file-copy-paste")]
                 if sp.name() == FILE_NAME {
                     handler.check_clipboard_file();
@@ -6,7 +6,9 @@ use crate::{
     client::{
         new_voice_call_request, new_voice_call_response, start_audio_thread, MediaData, MediaSender,
     },
-    display_service, ipc, privacy_mode, video_service, VERSION,
+    display_service, ipc, privacy_mode,
+    video_datagram::{DatagramChannel, HostDatagram},
+    video_service, VERSION,
 };
 use cidr_utils::cidr::IpCidr;
 use hbb_common::{
@@ -48,7 +50,7 @@ lazy_static::lazy_static! {
     pub static ref AUTHED_CONNS: Arc::<Mutex<Vec<AuthedConn>>> = Default::default();
     pub static ref CONTROL_PERMISSIONS_ARRAY: Arc::<Mutex<Vec<(i32, ControlPermissions)>>> = Default::default();
     static ref SWITCH_SIDES_UUID: Arc::<Mutex<HashMap<String, (Instant, uuid::Uuid)>>> = Default::default();
-    static ref WAKELOCK_SENDER: Arc::<Mutex<std::sync::mpsc::Sender<(usize, usize)>>> = Arc::new(Mutex::new(start_wakelock_thread()));
+    static ref WAKELOCK_SENDER: Arc::<Mutex<std::sync::mpsc::Sender<crate::wake_policy::Demand>>> = Arc::new(Mutex::new(start_wakelock_thread()));
     static ref WAKELOCK_KEEP_AWAKE_OPTION: Arc::<Mutex<Option<bool>>> = Default::default();
 }
 
@@ -97,6 +99,59 @@ pub struct ConnInner {
     tx_video: Option<Sender>,
 }
 
+// The replay of an input macro, on its own task, which passes the steps to the input thread at
+// their pace. Canceled when it is dropped.
+struct MacroReplay {
+    _cancel: tokio::sync::oneshot::Sender<()>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+impl MacroReplay {
+    fn start(
+        steps: Vec<crate::input_macro::Step>,
+        conn_id: i32,
+        username: String,
+        argb: u32,
+        tx_input: std_mpsc::Sender<MessageInput>,
+    ) -> Self {
+        let (tx_cancel, mut rx_cancel) = tokio::sync::oneshot::channel::<()>();
+        let task = tokio::spawn(async move {
+            for step in steps {
+                let delay = step.delay_ms.min(crate::input_macro::MAX_DELAY_MS);
+                tokio::select! {
+                    _ = &mut rx_cancel => break,
+                    _ = tokio::time::sleep(Duration::from_millis(delay)) => {}
+                }
+                let input = if let Some(msg) = step.mouse_event() {
+                    MessageInput::Mouse(InputMouse {
+                        msg,
+                        conn_id,
+                        username: username.clone(),
+                        argb,
+                        simulate: true,
+                        show_cursor: false,
+                    })
+                } else if let Some((msg, press)) = step.key_event() {
+                    MessageInput::Key((msg, press, conn_id))
+                } else {
+                    continue;
+                };
+                if tx_input.send(input).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            _cancel: tx_cancel,
+            task,
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        !self.task.is_finished()
+    }
+}
+
 struct InputMouse {
     msg: MouseEvent,
     conn_id: i32,
@@ -108,8 +163,11 @@ struct InputMouse {
 
 enum MessageInput {
     Mouse(InputMouse),
-    Key((KeyEvent, bool)),
+    Key((KeyEvent, bool, i32)),
     Pointer((PointerDeviceEvent, i32)),
+    Gamepad((crate::gamepad::GamepadState, i32)),
+    Stylus((crate::stylus::StylusEvent, i32)),
+    TouchRotate((crate::touch_gesture::RotateEvent, i32)),
     BlockOn,
     BlockOff,
     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
@@ -169,21 +227,44 @@ pub struct Connection {
     read_jobs: Vec<fs::TransferJob>,
     timer: crate::RustDeskInterval,
     file_timer: crate::RustDeskInterval,
+    transfer_throttle: crate::transfer_throttle::Throttle,
+    // The folders given to the files dropped on the remote desktop, and their transfers.
+    file_drops: Arc<Mutex<crate::file_drop::Drops>>,
+    // The removals are permanent, not to the trash, see `file_trash`.
+    file_trash_permanent: bool,
+    // The displays the privacy mode of the connection blanks, all if empty, see `privacy_displays`.
+    privacy_displays: Vec<usize>,
+    input_timeline: Option<super::input_timeline::Recorder>,
+    // The read jobs of a file transfer session, see `transfer_journal`.
+    transfer_journal: Option<crate::transfer_journal::Journal>,
     file_transfer: Option<(String, bool)>,
     view_camera: bool,
+    // Only one window is shared, see `shared_window`.
+    shared_window: bool,
     terminal: bool,
     port_forward_socket: Option<Framed<TcpStream, BytesCodec>>,
     port_forward_address: String,
+    mux_channels: crate::mux::Channels,
     tx_to_cm: mpsc::UnboundedSender<ipc::Data>,
     authorized: bool,
     require_2fa: Option<totp_rs::TOTP>,
+    // The provider of the policy and its challenge, until the viewer answers it.
+    auth_provider: Option<(Box<dyn auth_provider::Provider>, auth_provider::Challenge)>,
+    // The provider and the user it verified.
+    auth_provider_identity: Option<(&'static str, String)>,
     keyboard: bool,
     clipboard: bool,
     audio: bool,
     file: bool,
     restart: bool,
     recording: bool,
+    do_not_record: bool,
     block_input: bool,
+    // Set by the user of the host, the input, the clipboard and the changes of the files are
+    // refused until it is restored, without changing the permissions.
+    view_only: bool,
+    // The permissions requested by the peer, until the user of the host answers.
+    permission_requests: HashSet<String>,
     control_permissions: Option<ControlPermissions>,
     last_test_delay: Option<Instant>,
     network_delay: u32,
@@ -207,6 +288,9 @@ pub struct Connection {
     tx_input: std_mpsc::Sender<MessageInput>,
     // handle input messages
     video_ack_required: bool,
+    send_queue: send_queue::SendQueue,
+    // Unreliable video over KCP, see `video_datagram`.
+    video_datagram: Option<HostDatagram>,
     server_audit_conn: String,
     server_audit_file: String,
     lr: LoginRequest,
@@ -219,6 +303,20 @@ pub struct Connection {
     voice_calling: bool,
     options_in_login: Option<OptionMessage>,
     pressed_modifiers: HashSet<rdev::Key>,
+    macro_recorder: Option<crate::input_macro::Recorder>,
+    macro_replay: Option<MacroReplay>,
+    // The input counters of the connection, kept for the close audit once it is closed.
+    input_counters: super::input_limiter::Counters,
+    bandwidth_probed: bool,
+    display_delta: crate::display_delta::Sender,
+    // The peer takes the large clipboards in chunks.
+    clipboard_chunks: bool,
+    clipboard_receiver: crate::clipboard::chunks::Receiver,
+    // The bytes of the files of the peer pasted, last sent to the CM.
+    #[cfg(feature = "unix-file-copy-paste")]
+    file_paste_progress: Option<(u64, u64)>,
+    // The whiteboard shows the annotations of the peer.
+    annotating: bool,
     closed: bool,
     start_cm_ipc_para: Option<StartCmIpcPara>,
     auto_disconnect_timer: Option<(Instant, u64)>,
@@ -241,6 +339,9 @@ pub struct Connection {
     // Used to filter stale responses (FileBlockFromCM, FileReadDone, etc.) for
     // cancelled or unknown jobs.
     cm_read_job_ids: HashSet<i32>,
+    // The read jobs the client asked to compress.
+    compress_jobs: crate::transfer_compress::Jobs,
+    window_throttle: crate::window_visibility::Throttle,
     terminal_service_id: String,
     terminal_persistent: bool,
     // The user token must be set when terminal is enabled.
@@ -284,6 +385,9 @@ impl Subscriber for ConnInner {
     }
 }
 
+/// Host option, "Y" to not allow the viewers to record the sessions or take screenshots.
+/// Best effort, the client is told to disable its recording and screenshots.
+pub const OPTION_DO_NOT_RECORD: &str = "allow-do-not-record";
 const TEST_DELAY_TIMEOUT: Duration = Duration::from_secs(1);
 const SEC30: Duration = Duration::from_secs(30);
 const H1: Duration = Duration::from_secs(3600);
@@ -299,6 +403,7 @@ impl Connection {
         id: i32,
         server: super::ServerPtrWeak,
         control_permissions: Option<ControlPermissions>,
+        datagram: Option<DatagramChannel>,
     ) {
         let _raii_id = raii::ConnectionID::new(id);
         let _raii_control_permissions_id =
@@ -325,6 +430,7 @@ impl Connection {
             Self::post_seq_loop(rx_post_seq).await;
         });
 
+        let do_not_record = Config::get_option(OPTION_DO_NOT_RECORD) == "Y";
         let tx_cloned = tx.clone();
         let mut conn = Self {
             inner: ConnInner {
@@ -333,6 +439,8 @@ impl Connection {
                 tx_video: Some(tx_video),
             },
             require_2fa: crate::auth_2fa::get_2fa(None),
+            auth_provider: None,
+            auth_provider_identity: None,
             display_idx: *display_service::PRIMARY_DISPLAY_IDX,
             stream,
             server,
@@ -340,11 +448,19 @@ impl Connection {
             read_jobs: Vec::new(),
             timer: crate::rustdesk_interval(time::interval(SEC30)),
             file_timer: crate::rustdesk_interval(time::interval(SEC30)),
+            transfer_throttle: Default::default(),
+            file_drops: Default::default(),
+            file_trash_permanent: false,
+            privacy_displays: Vec::new(),
+            input_timeline: None,
+            transfer_journal: None,
             file_transfer: None,
             view_camera: false,
+            shared_window: false,
             terminal: false,
             port_forward_socket: None,
             port_forward_address: "".to_owned(),
+            mux_channels: Default::default(),
             tx_to_cm,
             authorized: false,
             keyboard: Self::permission(keys::OPTION_ENABLE_KEYBOARD, &control_permissions),
@@ -353,8 +469,12 @@ impl Connection {
             // to-do: make sure is the option correct here
             file: Self::permission(keys::OPTION_ENABLE_FILE_TRANSFER, &control_permissions),
             restart: Self::permission(keys::OPTION_ENABLE_REMOTE_RESTART, &control_permissions),
-            recording: Self::permission(keys::OPTION_ENABLE_RECORD_SESSION, &control_permissions),
+            recording: Self::permission(keys::OPTION_ENABLE_RECORD_SESSION, &control_permissions)
+                && !do_not_record,
+            do_not_record,
             block_input: Self::permission(keys::OPTION_ENABLE_BLOCK_INPUT, &control_permissions),
+            view_only: false,
+            permission_requests: Default::default(),
             control_permissions,
             last_test_delay: None,
             network_delay: 0,
@@ -371,6 +491,8 @@ impl Connection {
             show_my_cursor: false,
             tx_input,
             video_ack_required: false,
+            send_queue: Default::default(),
+            video_datagram: datagram.map(HostDatagram::new),
             server_audit_conn: "".to_owned(),
             server_audit_file: "".to_owned(),
             lr: Default::default(),
@@ -384,6 +506,16 @@ impl Connection {
             voice_calling: false,
             options_in_login: None,
             pressed_modifiers: Default::default(),
+            macro_recorder: None,
+            macro_replay: None,
+            input_counters: Default::default(),
+            bandwidth_probed: false,
+            display_delta: Default::default(),
+            clipboard_chunks: false,
+            clipboard_receiver: Default::default(),
+            #[cfg(feature = "unix-file-copy-paste")]
+            file_paste_progress: None,
+            annotating: false,
             closed: false,
             start_cm_ipc_para: Some(StartCmIpcPara {
                 rx_to_cm,
@@ -402,6 +534,8 @@ impl Connection {
             printer_data: Vec::new(),
             tx_post_seq,
             cm_read_job_ids: HashSet::new(),
+            compress_jobs: Default::default(),
+            window_throttle: Default::default(),
             terminal_service_id: "".to_owned(),
             terminal_persistent: false,
             terminal_user_token: None,
@@ -480,7 +614,9 @@ impl Connection {
                     match data {
                         ipc::Data::Authorize => {
                             conn.require_2fa.take();
-                            conn.send_logon_response().await;
+                            if !conn.send_logon_response().await {
+                                break;
+                            }
                             if conn.port_forward_socket.is_some() {
                                 break;
                             }
@@ -510,11 +646,29 @@ impl Connection {
                             conn.send(msg_out).await;
                             conn.chat_unanswered = false;
                         }
+                        #[cfg(feature = "unix-file-copy-paste")]
+                        ipc::Data::CancelFilePaste => {
+                            // The files may be pasted on either side.
+                            crate::clipboard_file::unix_file_clip::cancel_paste(conn.inner.id());
+                            conn.send(crate::clipboard_file::unix_file_clip::make_cancel_paste()).await;
+                        }
+                        ipc::Data::PermissionRequest{name, granted: Some(granted)} => {
+                            if conn.permission_requests.remove(&name) {
+                                log::info!("Permission request {} answered: {}", name, granted);
+                                conn.send(crate::permission_request::Content::Reply {
+                                    permission: name,
+                                    granted,
+                                }.to_message()).await;
+                            }
+                        }
                         ipc::Data::SwitchPermission{name, enabled} => {
                             log::info!("Change permission {} -> {}", name, enabled);
                             if &name == "keyboard" {
                                 conn.keyboard = enabled;
-                                conn.send_permission(Permission::Keyboard, enabled).await;
+                                if !enabled {
+                                    conn.macro_replay = None;
+                                }
+                                conn.send_permission(Permission::Keyboard, enabled && !conn.view_only).await;
                                 if let Some(s) = conn.server.upgrade() {
                                     s.write().unwrap().subscribe(
                                         super::clipboard_service::NAME,
@@ -527,11 +681,11 @@ impl Connection {
                                     );
                                     s.write().unwrap().subscribe(
                                         NAME_CURSOR,
-                                        conn.inner.clone(), enabled || conn.show_remote_cursor);
+                                        conn.inner.clone(), conn.peer_keyboard_enabled() || conn.show_remote_cursor);
                                 }
                             } else if &name == "clipboard" {
                                 conn.clipboard = enabled;
-                                conn.send_permission(Permission::Clipboard, enabled).await;
+                                conn.send_permission(Permission::Clipboard, enabled && !conn.view_only).await;
                                 if let Some(s) = conn.server.upgrade() {
                                     s.write().unwrap().subscribe(
                                         super::clipboard_service::NAME,
@@ -548,6 +702,7 @@ impl Connection {
                                                     super::audio_service::NAME,
                                                     conn.inner.clone(), conn.audio_enabled());
                                             }
+                                            conn.update_camera_audio();
                                         } else {
                                             s.write().unwrap().subscribe(
                                                 super::audio_service::NAME,
@@ -574,13 +729,23 @@ impl Connection {
                                 conn.restart = enabled;
                                 conn.send_permission(Permission::Restart, enabled).await;
                             } else if &name == "recording" {
-                                conn.recording = enabled;
-                                conn.send_permission(Permission::Recording, enabled).await;
+                                if enabled && conn.do_not_record {
+                                    log::info!("recording is not allowed by the host");
+                                } else {
+                                    conn.recording = enabled;
+                                    conn.send_permission(Permission::Recording, enabled).await;
+                                }
                             } else if &name == "block_input" {
                                 conn.block_input = enabled;
                                 conn.send_permission(Permission::BlockInput, enabled).await;
                             }
                         }
+                        ipc::Data::ViewOnly(on) => {
+                            conn.set_view_only(on).await;
+                        }
+                        ipc::Data::ResumeTransferJobs(resume) => {
+                            conn.resume_transfer_jobs(resume).await;
+                        }
                         ipc::Data::RawMessage(bytes) => {
                             allow_err!(conn.stream.send_raw(bytes).await);
                         }
@@ -687,9 +852,20 @@ impl Connection {
                     }
                 },
                 _ = conn.file_timer.tick() => {
+                    if let Some(journal) = conn.transfer_journal.as_mut() {
+                        journal.sync(&conn.read_jobs);
+                    }
                     if !conn.read_jobs.is_empty() {
+                        if let Some(wait) = conn.transfer_throttle.wait() {
+                            conn.file_timer = crate::rustdesk_interval(time::interval_at(Instant::now() + wait, MILLI1));
+                            continue;
+                        }
                         conn.send_to_cm(ipc::Data::FileTransferLog(("transfer".to_string(), fs::serialize_transfer_jobs(&conn.read_jobs))));
-                        match fs::handle_read_jobs(&mut conn.read_jobs, &mut conn.stream).await {
+                        let transferred: u64 = conn.read_jobs.iter().map(|job| job.transferred()).sum();
+                        let res = crate::transfer_compress::handle_read_jobs(&mut conn.read_jobs, &mut conn.stream, &mut conn.compress_jobs).await;
+                        let read: u64 = conn.read_jobs.iter().map(|job| job.transferred()).sum();
+                        conn.transfer_throttle.consume(read.saturating_sub(transferred));
+                        match res {
                             Ok(log) => {
                                 if !log.is_empty() {
                                     conn.send_to_cm(ipc::Data::FileTransferLog(("transfer".to_string(), log)));
@@ -712,69 +888,23 @@ impl Connection {
                     }
                 }
                 Some((instant, value)) = rx_video.recv() => {
-                    if !conn.video_ack_required {
-                        if let Some(message::Union::VideoFrame(vf)) = &value.union {
-                            video_service::notify_video_frame_fetched(vf.display as usize, id, Some(instant.into()));
-                        }
-                    }
-                    if let Err(err) = conn.stream.send(&value as &Message).await {
-                        conn.on_close(&err.to_string(), false).await;
-                        break;
-                    }
+                    conn.queue_send(instant.into(), value);
                 },
                 Some((instant, value)) = rx.recv() => {
-                    let latency = instant.elapsed().as_millis() as i64;
-                    #[allow(unused_mut)]
-                    let mut msg = value;
-
-                    if latency > 1000 {
-                        match &msg.union {
-                            Some(message::Union::AudioFrame(_)) => {
-                                // log::info!("audio frame latency {}", instant.elapsed().as_secs_f32());
-                                continue;
-                            }
-                            _ => {}
-                        }
+                    conn.queue_send(instant.into(), value);
+                },
+                _ = std::future::ready(()), if !conn.send_queue.is_empty() => {
+                    // Take the ready messages first, so that a higher class overtakes the queued ones.
+                    while let Ok((instant, value)) = rx.try_recv() {
+                        conn.queue_send(instant.into(), value);
                     }
-                    match &msg.union {
-                        Some(message::Union::Misc(m)) => {
-                            match &m.union {
-                                Some(misc::Union::StopService(_)) => {
-                                    conn.send_close_reason_no_retry("").await;
-                                    conn.on_close("stop service", false).await;
-                                    break;
-                                }
-                                _ => {},
-                            }
-                        }
-                        Some(message::Union::PeerInfo(_pi)) => {
-                            conn.refresh_video_display(None);
-                            conn.retina.set_displays(&_pi.displays);
-                        }
-                        Some(message::Union::CursorPosition(pos)) => {
-                            if conn.follow_remote_cursor {
-                                conn.handle_cursor_switch_display(pos.clone()).await;
-                            }
-                            if let Some(new_msg) = conn.retina.on_cursor_pos(&pos, conn.display_idx) {
-                                msg = Arc::new(new_msg);
-                            }
-                        }
-                        Some(message::Union::MultiClipboards(_multi_clipboards)) => {
-                            if let Some(msg_out) = crate::clipboard::get_msg_if_not_support_multi_clip(&conn.lr.version, &conn.lr.my_platform, _multi_clipboards) {
-                                if let Err(err) = conn.stream.send(&msg_out).await {
-                                    conn.on_close(&err.to_string(), false).await;
-                                    break;
-                                }
-                                continue;
-                            }
-                        }
-                        _ => {}
+                    while let Ok((instant, value)) = rx_video.try_recv() {
+                        conn.queue_send(instant.into(), value);
                     }
-
-                    let msg: &Message = &msg;
-                    if let Err(err) = conn.stream.send(msg).await {
-                        conn.on_close(&err.to_string(), false).await;
-                        break;
+                    if let Some((instant, value)) = conn.send_queue.pop() {
+                        if !conn.send_queued(instant, value).await {
+                            break;
+                        }
                     }
                 },
                 Some(_data) = rx_from_authed.recv() => {
@@ -789,6 +919,8 @@ impl Connection {
                         }
                     }
                     conn.file_remove_log_control.on_timer().drain(..).map(|x| conn.send_to_cm(x)).count();
+                    #[cfg(feature = "unix-file-copy-paste")]
+                    conn.update_file_paste_progress();
                     #[cfg(feature = "hwcodec")]
                     conn.update_supported_encoding();
                 }
@@ -853,8 +985,11 @@ impl Connection {
             raii::AuthedConnID::check_remove_session(conn.inner.id(), conn.session_key());
         }
 
+        // `on_close` may have removed the limiter of the connection already.
+        let input = super::input_limiter::remove(id).unwrap_or(conn.input_counters);
         conn.post_conn_audit(json!({
             "action": "close",
+            "input": input,
         }));
         if let Some(s) = conn.server.upgrade() {
             let mut s = s.write().unwrap();
@@ -867,6 +1002,7 @@ impl Connection {
 
     fn handle_input(receiver: std_mpsc::Receiver<MessageInput>, tx: Sender) {
         let mut block_input_mode = false;
+        let mut block_input_escapes = crate::platform::block_input_escapes();
         rdev::set_mouse_extra_info(enigo::ENIGO_INPUT_EXTRA_VALUE);
         rdev::set_keyboard_extra_info(enigo::ENIGO_INPUT_EXTRA_VALUE);
         reset_input_ondisconn();
@@ -883,25 +1019,26 @@ impl Connection {
                             mouse_input.show_cursor,
                         );
                     }
-                    MessageInput::Key((mut msg, press)) => {
-                        // Set the press state to false, use `down` only in `handle_key()`.
-                        msg.press = false;
-                        if press {
-                            msg.down = true;
-                        }
-                        handle_key(&msg);
-                        if press {
-                            msg.down = false;
-                            handle_key(&msg);
-                        }
+                    MessageInput::Key((msg, press, id)) => {
+                        Self::handle_input_key(msg, press, id);
                     }
                     MessageInput::Pointer((msg, id)) => {
                         handle_pointer(&msg, id);
                     }
+                    MessageInput::Gamepad((state, id)) => {
+                        handle_gamepad(state, id);
+                    }
+                    MessageInput::Stylus((evt, id)) => {
+                        handle_stylus(evt, id);
+                    }
+                    MessageInput::TouchRotate((evt, id)) => {
+                        handle_touch_rotate(evt, id);
+                    }
                     MessageInput::BlockOn => {
                         let (ok, msg) = crate::platform::block_input(true);
                         if ok {
                             block_input_mode = true;
+                            block_input_escapes = crate::platform::block_input_escapes();
                         } else {
                             Self::send_block_input_error(
                                 &tx,
@@ -949,7 +1086,17 @@ impl Connection {
                 },
                 Err(err) => {
                     if block_input_mode {
-                        let _ = crate::platform::block_input(true);
+                        if crate::platform::block_input_escapes() != block_input_escapes {
+                            // Ended by the escape chord on the host, not blocked again.
+                            block_input_mode = false;
+                            Self::send_block_input_error(
+                                &tx,
+                                back_notification::BlockInputState::BlkOffSucceeded,
+                                "".to_owned(),
+                            );
+                        } else {
+                            let _ = crate::platform::block_input(true);
+                        }
                     }
                     if std_mpsc::RecvTimeoutError::Disconnected == err {
                         break;
@@ -957,9 +1104,25 @@ impl Connection {
                 }
             }
         }
+        if block_input_mode {
+            let _ = crate::platform::block_input(false);
+        }
         log::debug!("Input thread exited");
     }
 
+    fn handle_input_key(mut msg: KeyEvent, press: bool, conn: i32) {
+        // Set the press state to false, use `down` only in `handle_key()`.
+        msg.press = false;
+        if press {
+            msg.down = true;
+        }
+        handle_key(&msg, conn);
+        if press {
+            msg.down = false;
+            handle_key(&msg, conn);
+        }
+    }
+
     async fn post_seq_loop(mut rx: mpsc::UnboundedReceiver<(String, Value)>) {
         while let Some((url, v)) = rx.recv().await {
             allow_err!(Self::post_audit_async(url, v).await);
@@ -1023,6 +1186,86 @@ impl Connection {
         Ok(())
     }
 
+    // The peer is told its keyboard and its clipboard are off, the permissions are kept for the
+    // restore.
+    async fn set_view_only(&mut self, on: bool) {
+        if self.view_only == on {
+            return;
+        }
+        log::info!("#{} View only: {}", self.inner.id(), on);
+        self.view_only = on;
+        if on {
+            self.macro_replay = None;
+            self.release_pressed_modifiers();
+            release_keys(self.inner.id());
+            for channel in self.mux_channels.close_all() {
+                let reason = "View only".to_owned();
+                self.send(crate::mux::Frame::Close { channel, reason }.to_message())
+                    .await;
+            }
+        }
+        if let Some(s) = self.server.upgrade() {
+            let mut s = s.write().unwrap();
+            s.subscribe(
+                super::clipboard_service::NAME,
+                self.inner.clone(),
+                self.can_sub_clipboard_service(),
+            );
+            #[cfg(feature = "unix-file-copy-paste")]
+            s.subscribe(
+                super::clipboard_service::FILE_NAME,
+                self.inner.clone(),
+                self.can_sub_file_clipboard_service(),
+            );
+            s.subscribe(
+                NAME_CURSOR,
+                self.inner.clone(),
+                self.peer_keyboard_enabled() || self.show_remote_cursor,
+            );
+        }
+        if self.is_authed_remote_conn() {
+            self.send_permission(Permission::Keyboard, self.keyboard && !on)
+                .await;
+            self.send_permission(Permission::Clipboard, self.clipboard && !on)
+                .await;
+        }
+        self.post_conn_audit(json!({
+            "action": "view_only",
+            "enabled": on,
+        }));
+    }
+
+    // The jobs of the session are kept for the key the client claims, and the cm is asked to
+    // resume or discard the ones of the key a restart interrupted.
+    async fn check_interrupted_transfer_jobs(&mut self, key: &str) {
+        let peer_id = crate::transfer_journal::peer(&self.lr.my_id, key);
+        self.transfer_journal = Some(crate::transfer_journal::Journal::new(
+            &peer_id,
+            self.inner.id(),
+            false,
+        ));
+        let jobs = crate::transfer_journal::interrupted(&peer_id).await;
+        if !jobs.is_empty() {
+            log::info!("#{} {} interrupted file jobs", self.inner.id(), jobs.len());
+        }
+        self.send_to_cm(ipc::Data::TransferJournal(peer_id, jobs));
+    }
+
+    async fn resume_transfer_jobs(&mut self, resume: bool) {
+        let Some(peer_id) = self
+            .transfer_journal
+            .as_ref()
+            .map(|j| j.peer_id().to_owned())
+        else {
+            return;
+        };
+        let jobs = crate::transfer_journal::take(&peer_id, resume).await;
+        if !jobs.is_empty() {
+            let notice = crate::transfer_journal::Notice { resume, jobs };
+            self.send(notice.to_message()).await;
+        }
+    }
+
     async fn send_permission(&mut self, permission: Permission, enabled: bool) {
         let mut misc = Misc::new();
         misc.set_permission_info(PermissionInfo {
@@ -1123,6 +1366,336 @@ impl Connection {
         allow_err!(self.tx_post_seq.send((url, v)));
     }
 
+    fn run_session_hooks(
+        &self,
+        event: super::session_hooks::Event,
+        name: &str,
+        commands: Vec<String>,
+    ) {
+        let env = vec![
+            ("RUSTDESK_PEER_ID", self.lr.my_id.clone()),
+            ("RUSTDESK_PEER_NAME", self.lr.my_name.clone()),
+            ("RUSTDESK_CONN_ID", self.inner.id.to_string()),
+            ("RUSTDESK_PROFILE", name.to_owned()),
+        ];
+        let url = self.server_audit_conn.clone();
+        let tx = self.tx_post_seq.clone();
+        let (conn_id, session_id) = (self.inner.id, self.lr.session_id);
+        super::session_hooks::run(event, name.to_owned(), commands, env, move |mut v| {
+            if url.is_empty() {
+                return;
+            }
+            v["id"] = json!(Config::get_id());
+            v["uuid"] = json!(crate::encode64(hbb_common::get_uuid()));
+            v["conn_id"] = json!(conn_id);
+            v["session_id"] = json!(session_id);
+            allow_err!(tx.send((url.clone(), v)));
+        });
+    }
+
+    fn handle_annotation(&mut self, content: &[u8]) {
+        use crate::whiteboard;
+        let (snapshot, laser) = match serde_json::from_slice(content) {
+            Ok(whiteboard::Annotation::Snapshot(sid)) => (Some(sid), None),
+            Ok(whiteboard::Annotation::Laser(on)) => (None, Some(on)),
+            _ => (None, None),
+        };
+        let evt = whiteboard::Annotation::parse(content);
+        if snapshot.is_none() && laser.is_none() && evt.is_none() {
+            log::warn!("Invalid annotation");
+            return;
+        }
+        let key = whiteboard::get_key_annotation(self.inner.id);
+        if !self.annotating {
+            self.annotating = true;
+            whiteboard::register_whiteboard(key.clone());
+        }
+        if let Some(evt) = evt {
+            whiteboard::update_whiteboard(key, evt);
+        } else if let Some(sid) = snapshot {
+            self.snapshot_whiteboard(key, sid);
+        } else if let Some(on) = laser {
+            super::input_service::set_laser(self.inner.id(), on);
+        }
+    }
+
+    // Save the annotations next to the recordings, and send them back to the session `sid`.
+    fn snapshot_whiteboard(&self, key: String, sid: String) {
+        let name = format!(
+            "Whiteboard_{}_{}.png",
+            self.lr.my_id,
+            chrono::Local::now().format("%Y%m%d%H%M%S")
+        );
+        let dir = crate::ui_interface::video_save_directory(crate::platform::is_root());
+        let path = std::path::Path::new(&dir)
+            .join(name)
+            .to_string_lossy()
+            .to_string();
+        let tx = self.inner.tx.clone();
+        tokio::spawn(async move {
+            let res = crate::whiteboard::snapshot(key, path).await;
+            let (Some(tx), false) = (tx, sid.is_empty()) else {
+                return;
+            };
+            let mut response = ScreenshotResponse {
+                sid,
+                ..Default::default()
+            };
+            match res {
+                Ok(data) => response.data = data.into(),
+                Err(e) => response.msg = e.to_string(),
+            }
+            let mut msg_out = Message::new();
+            msg_out.set_screenshot_response(response);
+            allow_err!(tx.send((Instant::now(), Arc::new(msg_out))));
+        });
+    }
+
+    // The file is hashed aside, the answer is sent when it is done.
+    fn handle_transfer_verify(&mut self, content: &[u8]) {
+        use crate::transfer_verify::{verified_offset, Request, Response};
+        let Some(req) = Request::parse(content) else {
+            log::warn!("Invalid transfer verify request");
+            return;
+        };
+        let Some(job) = fs::get_job(req.id, &mut self.read_jobs) else {
+            return;
+        };
+        let (fs::DataSource::FilePath(p), Some(file)) =
+            (&job.data_source, job.files().get(req.file_num as usize))
+        else {
+            return;
+        };
+        let path = fs::get_string(&fs::TransferJob::join(p, &file.name));
+        let (id, file_num) = (req.id, req.file_num);
+        let tx = self.inner.tx.clone();
+        tokio::spawn(async move {
+            let Ok(offset) =
+                tokio::task::spawn_blocking(move || verified_offset(&path, &req)).await
+            else {
+                return;
+            };
+            if let Some(tx) = tx {
+                let res = Response {
+                    id,
+                    file_num,
+                    offset,
+                };
+                allow_err!(tx.send((Instant::now(), Arc::new(res.to_message()))));
+            }
+        });
+    }
+
+    // The file of a download of the connection is hashed, else the one of an upload, the file the
+    // request names within the sandbox. It is hashed aside, the answer is sent when it is done.
+    async fn handle_folder_sync(&mut self, content: &[u8]) {
+        let Some(req) = crate::folder_sync::Request::parse(content) else {
+            log::warn!("Invalid folder sync request");
+            return;
+        };
+        let path = match fs::get_job(req.id, &mut self.read_jobs) {
+            Some(job) => {
+                let (fs::DataSource::FilePath(p), Some(file)) =
+                    (&job.data_source, job.files().get(req.file_num as usize))
+                else {
+                    return;
+                };
+                fs::get_string(&fs::TransferJob::join(p, &file.name))
+            }
+            None => {
+                if let Err(e) = file_sandbox::check_path(&req.path) {
+                    self.post_sandbox_alarm_audit("folder_sync", &req.path, &e.to_string());
+                    self.send(req.answer("").to_message()).await;
+                    return;
+                }
+                req.path.clone()
+            }
+        };
+        let tx = self.inner.tx.clone();
+        tokio::spawn(async move {
+            if let Ok(res) = tokio::task::spawn_blocking(move || req.answer(&path)).await {
+                if let Some(tx) = tx {
+                    allow_err!(tx.send((Instant::now(), Arc::new(res.to_message()))));
+                }
+            }
+        });
+    }
+
+    // A write is checked as the file actions are. The files are read, written and diffed aside,
+    // the answer is sent when it is done.
+    async fn handle_config_edit(&mut self, content: &[u8]) {
+        use crate::config_edit::{handle_request, make_error, Request};
+        let Some(req) = Request::parse(content) else {
+            log::warn!("Invalid config edit request");
+            return;
+        };
+        if let Request::Write { path, content, .. } = &req {
+            let error = if crate::get_builtin_option(keys::OPTION_ONE_WAY_FILE_TRANSFER) == "Y" {
+                Some("one-way-file-transfer-tip".to_owned())
+            } else if !self.may_modify_files() {
+                log::warn!(
+                    "#{} Rejected config_edit in read-only file transfer mode",
+                    self.inner.id
+                );
+                Some("read-only-file-transfer-tip".to_owned())
+            } else if let Err(e) = file_sandbox::check_path(path) {
+                self.post_sandbox_alarm_audit("config_edit", path, &e.to_string());
+                Some(e.to_string())
+            } else {
+                None
+            };
+            if let Some(error) = error {
+                self.send(make_error(path, &error)).await;
+                return;
+            }
+            self.post_file_audit(
+                FileAuditType::RemoteReceive,
+                path,
+                vec![("".to_owned(), content.len() as i64)],
+                json!({ "action": "config_edit" }),
+            );
+        }
+        let conn_id = self.inner.id();
+        let tx = self.inner.tx.clone();
+        tokio::spawn(async move {
+            if let Ok(msg) = tokio::task::spawn_blocking(move || handle_request(conn_id, req)).await
+            {
+                if let Some(tx) = tx {
+                    allow_err!(tx.send((Instant::now(), Arc::new(msg))));
+                }
+            }
+        });
+    }
+
+    // The cm only restores the items this connection moved to the trash.
+    async fn restore_trashed(&mut self, id: i32, items: Vec<crate::file_trash::Trashed>) {
+        let error = if !self.may_modify_files() {
+            log::warn!(
+                "#{} Rejected restore_trashed in read-only file transfer mode",
+                self.inner.id
+            );
+            Some("read-only-file-transfer-tip".to_owned())
+        } else {
+            items.iter().find_map(|item| {
+                let e = file_sandbox::check_path(&item.path).err()?;
+                self.post_sandbox_alarm_audit("restore_trashed", &item.path, &e.to_string());
+                Some(e.to_string())
+            })
+        };
+        if let Some(error) = error {
+            let res = crate::file_trash::Response::Restored { id, error };
+            self.send(res.to_message()).await;
+            return;
+        }
+        self.post_file_audit(
+            FileAuditType::RemoteReceive,
+            "",
+            items.iter().map(|i| (i.path.clone(), 0)).collect(),
+            json!({ "action": "restore_trashed" }),
+        );
+        self.send_fs(ipc::FS::RestoreTrashed { id, items });
+    }
+
+    async fn handle_display_id_request(&mut self, content: &[u8]) {
+        use crate::display_id::*;
+        let Some(req) = Request::parse(content) else {
+            log::warn!("Invalid display id request");
+            return;
+        };
+        let ids: Vec<String> = display_service::get_sync_displays()
+            .iter()
+            .map(id_of)
+            .collect();
+        match req {
+            Request::Switch { id, width, height } => match indexes(&ids, &[id.clone()]).first() {
+                Some(display) => {
+                    self.handle_switch_display(SwitchDisplay {
+                        display: *display as _,
+                        width,
+                        height,
+                        ..Default::default()
+                    })
+                    .await;
+                }
+                None => log::warn!("Switch to the unknown display {}", id),
+            },
+            Request::Capture { add, sub, set } => {
+                let (add, sub, set) = (
+                    indexes(&ids, &add),
+                    indexes(&ids, &sub),
+                    indexes(&ids, &set),
+                );
+                self.capture_displays(&add, &sub, &set).await;
+            }
+        }
+    }
+
+    async fn handle_permission_request(&mut self, content: &[u8]) {
+        use crate::permission_request::*;
+        let Some(Content::Request { permission }) = Content::parse(content) else {
+            log::warn!("Invalid permission request");
+            return;
+        };
+        let Some(option) = option_of(&permission) else {
+            log::warn!("Request of the unknown permission {}", permission);
+            return;
+        };
+        let enabled = match permission.as_str() {
+            "keyboard" => self.keyboard,
+            "clipboard" => self.clipboard,
+            "audio" => self.audio,
+            "file" => self.file,
+            "restart" => self.restart,
+            "recording" => self.recording,
+            _ => self.block_input,
+        };
+        // Only the control permissions deny without asking, the local options are what the
+        // user of the host can override for this session.
+        let denied = Self::control_permission(option, &self.control_permissions) == Some(false)
+            || (permission == "recording" && self.do_not_record);
+        if enabled || denied {
+            self.send(
+                Content::Reply {
+                    permission,
+                    granted: enabled,
+                }
+                .to_message(),
+            )
+            .await;
+        } else if self.permission_requests.insert(permission.clone()) {
+            self.send_to_cm(ipc::Data::PermissionRequest {
+                name: permission,
+                granted: None,
+            });
+        }
+    }
+
+    async fn handle_bandwidth_probe(&mut self, content: &[u8]) {
+        use crate::bandwidth_probe::*;
+        match Frame::decode(content) {
+            // Once, a burst on each request would be an easy way to flood the link.
+            Some(Frame::Request { chunks }) if !self.bandwidth_probed => {
+                self.bandwidth_probed = true;
+                for msg in burst(chunks) {
+                    self.send(msg).await;
+                }
+            }
+            Some(Frame::Result { kbps, rtt_ms }) => {
+                log::info!(
+                    "#{} bandwidth probe: {} kbps, rtt {} ms",
+                    self.inner.id(),
+                    kbps,
+                    rtt_ms
+                );
+                video_service::VIDEO_QOS
+                    .lock()
+                    .unwrap()
+                    .user_bandwidth_probe(self.inner.id(), kbps, rtt_ms);
+            }
+            _ => {}
+        }
+    }
+
     fn get_files_for_audit(job_type: fs::JobType, mut files: Vec<FileEntry>) -> Vec<(String, i64)> {
         files
             .drain(..)
@@ -1188,9 +1761,16 @@ impl Connection {
         v["uuid"] = json!(crate::encode64(hbb_common::get_uuid()));
         v["typ"] = json!(typ as i8);
         v["info"] = serde_json::Value::String(info.to_string());
-        tokio::spawn(async move {
-            allow_err!(Self::post_audit_async(url, v).await);
-        });
+        if tokio::runtime::Handle::try_current().is_ok() {
+            tokio::spawn(async move {
+                allow_err!(Self::post_audit_async(url, v).await);
+            });
+        } else {
+            // Called from a service thread, e.g. the clipboard service.
+            std::thread::spawn(move || {
+                allow_err!(crate::post_request_sync(url, v.to_string(), ""));
+            });
+        }
     }
 
     #[inline]
@@ -1198,9 +1778,10 @@ impl Connection {
         crate::post_request(url, v.to_string(), "").await
     }
 
-    async fn send_logon_response(&mut self) {
+    // Returns false if the login is rejected, the host being busy.
+    async fn send_logon_response(&mut self) -> bool {
         if self.authorized {
-            return;
+            return true;
         }
         if self.require_2fa.is_some() && !self.is_recent_session(true) && !self.from_switch {
             self.require_2fa.as_ref().map(|totp| {
@@ -1231,9 +1812,8 @@ impl Connection {
                 }
             });
             self.send_login_error(crate::client::REQUIRE_2FA).await;
-            return;
+            return true;
         }
-        self.authorized = true;
         let (conn_type, auth_conn_type) = if self.file_transfer.is_some() {
             (1, AuthConnType::FileTransfer)
         } else if self.port_forward_socket.is_some() {
@@ -1245,6 +1825,18 @@ impl Connection {
         } else {
             (0, AuthConnType::Remote)
         };
+        if auth_conn_type == AuthConnType::Remote {
+            if let Some(position) = session_queue::check_busy(&self.lr.my_id) {
+                self.send_login_error(format!(
+                    "{}{}}}-tip",
+                    crate::client::LOGIN_MSG_HOST_BUSY_PREFIX,
+                    position
+                ))
+                .await;
+                return false;
+            }
+        }
+        self.authorized = true;
         self.authed_conn_id = Some(self::raii::AuthedConnID::new(
             self.inner.id(),
             auth_conn_type,
@@ -1252,13 +1844,33 @@ impl Connection {
             self.tx_from_authed.clone(),
             self.lr.clone(),
         ));
+        if auth_conn_type == AuthConnType::Remote {
+            self.input_timeline = super::input_timeline::Recorder::new(
+                self.inner.id(),
+                self.lr.session_id,
+                &self.lr.my_id,
+                &self.lr.my_name,
+            );
+        }
         self.session_last_recv_time = SESSIONS
             .lock()
             .unwrap()
             .get(&self.session_key())
             .map(|s| s.last_recv_time.clone());
-        self.post_conn_audit(
-            json!({"peer": ((&self.lr.my_id, &self.lr.my_name)), "type": conn_type}),
+        let (input_profile, profile) = super::input_profile::set(self.inner.id(), &self.lr.my_id);
+        self.post_conn_audit(json!({
+            "peer": ((&self.lr.my_id, &self.lr.my_name)),
+            "type": conn_type,
+            "input_profile": input_profile,
+            "auth_provider": self.auth_provider_identity.as_ref().map(|(name, user)| json!({
+                "name": name,
+                "user": user,
+            })),
+        }));
+        self.run_session_hooks(
+            super::session_hooks::Event::Connect,
+            &input_profile,
+            profile.on_connect,
         );
         #[allow(unused_mut)]
         let mut username = crate::platform::get_active_username();
@@ -1290,25 +1902,154 @@ impl Connection {
         }
 
         platform_additions.insert("support_view_camera".into(), json!(true));
-
-        if !platform_additions.is_empty() {
-            pi.platform_additions = serde_json::to_string(&platform_additions).unwrap_or("".into());
-        }
-
-        if self.port_forward_socket.is_some() {
-            let mut msg_out = Message::new();
-            res.set_peer_info(pi);
-            msg_out.set_login_response(res);
-            self.send(msg_out).await;
-            return;
-        }
-        let sas_enabled = false;
-        if self.file_transfer.is_some() {
-            if crate::platform::is_prelogin() {
-                // }|| self.tx_to_cm.send(ipc::Data::Test).is_err() {
-                username = "".to_owned();
-            }
-        }
+        #[cfg(target_os = "macos")]
+        platform_additions.insert(
+            crate::camera_controls::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(crate::key_frame::PLATFORM_ADDITION_KEY.into(), json!(true));
+        if self.do_not_record {
+            platform_additions.insert("do_not_record".into(), json!(true));
+        }
+        if self.talk_back_enabled() {
+            platform_additions.insert("support_talk_back".into(), json!(true));
+        }
+        if self.audio_enabled() {
+            platform_additions.insert("support_audio_devices".into(), json!(true));
+        }
+        platform_additions.insert(crate::gamepad::PLATFORM_ADDITION_KEY.into(), json!(true));
+        platform_additions.insert("support_stylus".into(), json!(true));
+        platform_additions.insert("support_mux".into(), json!(true));
+        platform_additions.insert("support_touch_gesture".into(), json!(true));
+        platform_additions.insert("support_input_macro".into(), json!(true));
+        platform_additions.insert("support_block_input".into(), json!(true));
+        platform_additions.insert("support_precise_wheel".into(), json!(true));
+        platform_additions.insert(
+            crate::display_power::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::bandwidth_probe::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::display_delta::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::scaled_frames::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::special_keys::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        #[cfg(feature = "ui")]
+        platform_additions.insert(
+            crate::whiteboard::ANNOTATION_PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::mouse_curve::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::wake_policy::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::privacy_displays::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::scroll_transform::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::permission_request::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::clipboard::chunks::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::transfer_verify::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::transfer_journal::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::transfer_compress::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::transfer_throttle::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::folder_sync::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(
+            crate::window_visibility::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        platform_additions.insert(crate::file_drop::PLATFORM_ADDITION_KEY.into(), json!(true));
+        platform_additions.insert(
+            crate::file_trash::PLATFORM_ADDITION_KEY.into(),
+            json!(cfg!(target_os = "macos")),
+        );
+        platform_additions.insert(
+            crate::keyboard_layout::PLATFORM_ADDITION_KEY.into(),
+            json!(crate::keyboard_layout::current()),
+        );
+        platform_additions.insert(
+            crate::monitor_modes::PLATFORM_ADDITION_KEY.into(),
+            json!(true),
+        );
+        if crate::config_edit::is_enabled() {
+            platform_additions.insert(
+                crate::config_edit::PLATFORM_ADDITION_KEY.into(),
+                json!(true),
+            );
+        }
+        crate::peer_identity::Identity::local().add_to(&mut platform_additions);
+        if config::option2bool(
+            "allow-auto-record-incoming",
+            &Config::get_option("allow-auto-record-incoming"),
+        ) {
+            platform_additions.insert("host_recording".into(), json!(true));
+        }
+        if let Some(datagram) = self.video_datagram.as_ref() {
+            if self.file_transfer.is_none() && !self.terminal {
+                platform_additions.insert(
+                    crate::video_datagram::PLATFORM_ADDITION_KEY.into(),
+                    json!(datagram.offer()),
+                );
+            }
+        }
+
+        if !platform_additions.is_empty() {
+            pi.platform_additions = serde_json::to_string(&platform_additions).unwrap_or("".into());
+        }
+
+        if self.port_forward_socket.is_some() {
+            let mut msg_out = Message::new();
+            res.set_peer_info(pi);
+            msg_out.set_login_response(res);
+            self.send(msg_out).await;
+            return true;
+        }
+        let sas_enabled = false;
+        if self.file_transfer.is_some() {
+            if crate::platform::is_prelogin() {
+                // }|| self.tx_to_cm.send(ipc::Data::Test).is_err() {
+                username = "".to_owned();
+            }
+        }
         #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
         PLUGIN_BLOCK_INPUT_TXS
             .lock()
@@ -1340,12 +2081,8 @@ impl Connection {
             pi.displays = camera::Cameras::all_info().unwrap_or(Vec::new());
             pi.current_display = camera::PRIMARY_CAMERA_IDX as _;
             pi.resolutions = Some(SupportedResolutions {
-                resolutions: camera::Cameras::get_camera_resolution(
-                    pi.current_display as usize,
-                )
-                .ok()
-                .into_iter()
-                .collect(),
+                resolutions: camera::Cameras::get_camera_resolutions(pi.current_display as usize)
+                    .unwrap_or_default(),
                 ..Default::default()
             })
             .into();
@@ -1362,27 +2099,41 @@ impl Connection {
 
             try_activate_screen();
 
-            match super::display_service::update_get_sync_displays_on_login().await {
-                Err(err) => {
-                    res.set_error(format!("{}", err));
-                }
-                Ok(displays) => {
-                    // For compatibility with old versions, we need to send the displays to the peer.
-                    // But the displays may be updated later, before creating the video capturer.
-                    self.retina.set_displays(&displays);
-                    pi.displays = displays;
-                    pi.current_display = self.display_idx as _;
-                    pi.resolutions = Some(SupportedResolutions {
-                        resolutions: pi
-                            .displays
-                            .get(self.display_idx)
-                            .map(|d| crate::platform::resolutions(&d.name))
-                            .unwrap_or(vec![]),
-                        ..Default::default()
-                    })
-                    .into();
-                    res.set_peer_info(pi);
-                    sub_service = true;
+            if let Some(window) = super::shared_window::display_info() {
+                log::info!("share window {}", window.name);
+                self.shared_window = true;
+                self.display_idx = super::shared_window::WINDOW_IDX;
+                let displays = vec![window];
+                self.retina.set_displays(&displays);
+                pi.displays = displays;
+                pi.current_display = self.display_idx as _;
+                res.set_peer_info(pi);
+                sub_service = true;
+            } else {
+                match super::display_service::update_get_sync_displays_on_login().await {
+                    Err(err) => {
+                        res.set_error(format!("{}", err));
+                    }
+                    Ok(displays) => {
+                        // For compatibility with old versions, we need to send the displays to the peer.
+                        // But the displays may be updated later, before creating the video capturer.
+                        self.retina.set_displays(&displays);
+                        self.display_delta.set_sent(&displays);
+                        crate::display_id::add_to(&mut pi.platform_additions, &displays);
+                        pi.displays = displays;
+                        pi.current_display = self.display_idx as _;
+                        pi.resolutions = Some(SupportedResolutions {
+                            resolutions: pi
+                                .displays
+                                .get(self.display_idx)
+                                .map(|d| crate::platform::resolutions(&d.name))
+                                .unwrap_or(vec![]),
+                            ..Default::default()
+                        })
+                        .into();
+                        res.set_peer_info(pi);
+                        sub_service = true;
+                    }
                 }
             }
             self.on_remote_authorized();
@@ -1418,6 +2169,7 @@ impl Connection {
                 self.try_sub_monitor_services();
             }
         }
+        true
     }
 
     fn try_sub_camera_displays(&mut self) {
@@ -1427,6 +2179,23 @@ impl Connection {
             s.try_add_primary_camera_service();
             s.add_camera_connection(self.inner.clone());
         }
+        self.update_camera_audio();
+    }
+
+    // The microphone goes with the cameras, except during a voice call which has its own audio.
+    fn update_camera_audio(&self) {
+        if !self.view_camera {
+            return;
+        }
+        let on = self.authorized
+            && self.audio_enabled()
+            && !self.voice_calling
+            && Config::get_option(super::audio_service::OPTION_CAMERA_AUDIO) == "Y";
+        if let Some(s) = self.server.upgrade() {
+            s.write()
+                .unwrap()
+                .subscribe(super::audio_service::CAMERA_NAME, self.inner.clone(), on);
+        }
     }
 
     #[inline]
@@ -1465,8 +2234,12 @@ impl Connection {
                 let mut s = s.write().unwrap();
                 let _h = try_start_record_cursor_pos();
                 self.auto_disconnect_timer = Self::get_auto_disconenct_timer();
-                s.try_add_primay_video_service();
-                s.add_connection(self.inner.clone(), &noperms);
+                if self.shared_window {
+                    s.try_add_window_service();
+                } else {
+                    s.try_add_primay_video_service();
+                }
+                s.add_connection(self.inner.clone(), &noperms, self.video_source());
             }
         }
     }
@@ -1476,11 +2249,16 @@ impl Connection {
     }
 
     fn peer_keyboard_enabled(&self) -> bool {
-        self.keyboard && !self.disable_keyboard
+        self.keyboard && !self.disable_keyboard && !self.view_only
     }
 
     fn clipboard_enabled(&self) -> bool {
-        self.clipboard && !self.disable_clipboard
+        self.clipboard && !self.disable_clipboard && !self.view_only
+    }
+
+    // The uploads, the removals, the restores from the trash and the config writes.
+    fn may_modify_files(&self) -> bool {
+        !file_sandbox::is_read_only() && !self.view_only
     }
 
     #[inline]
@@ -1488,12 +2266,23 @@ impl Connection {
         self.clipboard_enabled()
             && self.peer_keyboard_enabled()
             && crate::get_builtin_option(keys::OPTION_ONE_WAY_CLIPBOARD_REDIRECTION) != "Y"
+            && super::clipboard_service::is_host_to_client_allowed()
     }
 
     fn audio_enabled(&self) -> bool {
         self.audio && !self.disable_audio
     }
 
+    // The viewer may send its microphone outside of a voice call.
+    fn talk_back_enabled(&self) -> bool {
+        self.audio && Config::get_option(super::audio_service::OPTION_TALK_BACK) == "Y"
+    }
+
+    // The files the peer may drop or trash, with or without the file clipboard.
+    fn may_transfer_files(&self) -> bool {
+        self.file && self.enable_file_transfer
+    }
+
     #[cfg(feature = "unix-file-copy-paste")]
     fn file_transfer_enabled(&self) -> bool {
         self.file && self.enable_file_transfer
@@ -1504,6 +2293,7 @@ impl Connection {
         self.clipboard_enabled()
             && self.file_transfer_enabled()
             && crate::get_builtin_option(keys::OPTION_ONE_WAY_FILE_TRANSFER) != "Y"
+            && super::clipboard_service::is_host_to_client_allowed()
     }
 
     fn try_start_cm(&mut self, peer_id: String, name: String, authorized: bool) {
@@ -1569,7 +2359,7 @@ impl Connection {
 
     #[inline]
     fn input_mouse(
-        &self,
+        &mut self,
         msg: MouseEvent,
         conn_id: i32,
         username: String,
@@ -1577,6 +2367,12 @@ impl Connection {
         simulate: bool,
         show_cursor: bool,
     ) {
+        idle_detector::wake();
+        if simulate {
+            if let Some(recorder) = self.macro_recorder.as_mut() {
+                recorder.push_mouse(&msg);
+            }
+        }
         self.tx_input
             .send(MessageInput::Mouse(InputMouse {
                 msg,
@@ -1591,16 +2387,47 @@ impl Connection {
 
     #[inline]
     fn input_pointer(&self, msg: PointerDeviceEvent, conn_id: i32) {
+        idle_detector::wake();
         self.tx_input
             .send(MessageInput::Pointer((msg, conn_id)))
             .ok();
     }
 
     #[inline]
-    fn input_key(&self, msg: KeyEvent, press: bool) {
+    fn input_gamepad(&self, state: crate::gamepad::GamepadState, conn_id: i32) {
+        idle_detector::wake();
+        self.tx_input
+            .send(MessageInput::Gamepad((state, conn_id)))
+            .ok();
+    }
+
+    #[inline]
+    fn input_stylus(&self, evt: crate::stylus::StylusEvent, conn_id: i32) {
+        idle_detector::wake();
+        self.tx_input
+            .send(MessageInput::Stylus((evt, conn_id)))
+            .ok();
+    }
+
+    #[inline]
+    fn input_touch_rotate(&self, evt: crate::touch_gesture::RotateEvent, conn_id: i32) {
+        idle_detector::wake();
+        self.tx_input
+            .send(MessageInput::TouchRotate((evt, conn_id)))
+            .ok();
+    }
+
+    #[inline]
+    fn input_key(&mut self, msg: KeyEvent, press: bool) {
         // to-do: if is the legacy mode, and the key is function key "LockScreen".
         // Switch to the primary display.
-        self.tx_input.send(MessageInput::Key((msg, press))).ok();
+        idle_detector::wake();
+        if let Some(recorder) = self.macro_recorder.as_mut() {
+            recorder.push_key(&msg, press);
+        }
+        self.tx_input
+            .send(MessageInput::Key((msg, press, self.inner.id())))
+            .ok();
     }
 
     fn validate_one_password(&self, password: String) -> bool {
@@ -1629,7 +2456,9 @@ impl Connection {
             }
         }
         if password::permanent_enabled() {
-            if self.validate_one_password(Config::get_permanent_password()) {
+            if self.validate_one_password(crate::secret_store::get(
+                crate::secret_store::Secret::PermanentPassword,
+            )) {
                 return true;
             }
         }
@@ -1680,6 +2509,15 @@ impl Connection {
         enable_prefix_option: &str,
         control_permissions: &Option<ControlPermissions>,
     ) -> bool {
+        Self::control_permission(enable_prefix_option, control_permissions)
+            .unwrap_or_else(|| Self::is_permission_enabled_locally(enable_prefix_option))
+    }
+
+    // The permission set by the control permissions, none if they do not set it.
+    fn control_permission(
+        enable_prefix_option: &str,
+        control_permissions: &Option<ControlPermissions>,
+    ) -> Option<bool> {
         use hbb_common::rendezvous_proto::control_permissions::Permission;
         if let Some(control_permissions) = control_permissions {
             let permission = match enable_prefix_option {
@@ -1696,14 +2534,10 @@ impl Connection {
                 _ => None,
             };
             if let Some(permission) = permission {
-                if let Some(enabled) =
-                    crate::get_control_permission(control_permissions.permissions, permission)
-                {
-                    return enabled;
-                }
+                return crate::get_control_permission(control_permissions.permissions, permission);
             }
         }
-        Self::is_permission_enabled_locally(enable_prefix_option)
+        None
     }
 
     fn update_codec_on_login(&self) {
@@ -1749,6 +2583,83 @@ impl Connection {
         self.video_ack_required = lr.video_ack_required;
     }
 
+    async fn challenge_auth_provider(
+        &mut self,
+        mut provider: Box<dyn auth_provider::Provider>,
+        failed: bool,
+    ) {
+        let res = tokio::task::spawn_blocking(move || {
+            let prompt = provider.prompt();
+            (provider, prompt)
+        })
+        .await;
+        let Ok((provider, prompt)) = res else {
+            return;
+        };
+        match prompt {
+            Ok(prompt) => {
+                let challenge = auth_provider::Challenge {
+                    provider: provider.name().to_owned(),
+                    prompt,
+                    failed,
+                };
+                self.send_login_error(challenge.to_login_error()).await;
+                self.auth_provider = Some((provider, challenge));
+            }
+            Err(e) => {
+                log::error!("Failed to get the challenge of {}: {}", provider.name(), e);
+                self.send_login_error("Failed to reach the authentication provider")
+                    .await;
+            }
+        }
+    }
+
+    async fn handle_auth_provider_answer(&mut self, answer: auth_provider::Answer) {
+        let (failure, res) = self.check_failure(0).await;
+        if !res {
+            return;
+        }
+        let Some((mut provider, challenge)) = self.auth_provider.take() else {
+            return;
+        };
+        let res = tokio::task::spawn_blocking(move || {
+            let verdict = auth_provider::verify(provider.as_mut(), &answer);
+            (provider, verdict)
+        })
+        .await;
+        let Ok((provider, verdict)) = res else {
+            return;
+        };
+        match verdict {
+            Ok(auth_provider::Verdict::Passed(identity)) => {
+                log::info!("{} verified the user {}", provider.name(), identity.user);
+                self.update_failure(failure, true, 0);
+                self.auth_provider_identity = Some((provider.name(), identity.user));
+                if !self.send_logon_response().await {
+                    return;
+                }
+                self.try_start_cm(
+                    self.lr.my_id.to_owned(),
+                    self.lr.my_name.to_owned(),
+                    self.authorized,
+                );
+            }
+            Ok(auth_provider::Verdict::Pending) => {
+                self.send_login_error(challenge.to_login_error()).await;
+                self.auth_provider = Some((provider, challenge));
+            }
+            Ok(auth_provider::Verdict::Failed) => {
+                self.update_failure(failure, false, 0);
+                self.challenge_auth_provider(provider, true).await;
+            }
+            Err(e) => {
+                log::error!("Failed to verify with {}: {}", provider.name(), e);
+                self.send_login_error("Failed to reach the authentication provider")
+                    .await;
+            }
+        }
+    }
+
     fn try_start_cm_ipc(&mut self) {
         if let Some(p) = self.start_cm_ipc_para.take() {
             tokio::spawn(async move {
@@ -1857,6 +2768,15 @@ impl Connection {
                     if pf.host.is_empty() {
                         pf.host = "localhost".to_owned();
                     }
+                    if !crate::mux::is_target_allowed(&pf.host, pf.port) {
+                        self.send_login_error(format!(
+                            "Target {}:{} not allowed",
+                            pf.host, pf.port
+                        ))
+                        .await;
+                        sleep(1.).await;
+                        return false;
+                    }
                     let mut addr = format!("{}:{}", pf.host, pf.port);
                     self.port_forward_address = addr.clone();
                     match timeout(3000, TcpStream::connect(&addr)).await {
@@ -1918,11 +2838,31 @@ impl Connection {
                 return true;
             } else if self.is_recent_session(false) {
                 if err_msg.is_empty() {
-                    self.send_logon_response().await;
+                    if !self.send_logon_response().await {
+                        return false;
+                    }
                     self.try_start_cm(lr.my_id.clone(), lr.my_name.clone(), self.authorized);
                 } else {
                     self.send_login_error(err_msg).await;
                 }
+            } else if let Some(provider) = auth_provider::from_policy() {
+                // The local passwords are not checked, the blocked attempts do not reach the
+                // provider.
+                let (_, res) = self.check_failure(0).await;
+                if !res {
+                    return true;
+                }
+                if !auth_provider::is_any_allowed() {
+                    log::error!(
+                        "{} is set without {}, no user is let in",
+                        provider.name(),
+                        auth_provider::OPTION_AUTH_ALLOWED
+                    );
+                    self.send_login_error("No user is allowed by the authentication provider")
+                        .await;
+                    return true;
+                }
+                self.challenge_auth_provider(provider, false).await;
             } else if lr.password.is_empty() {
                 if err_msg.is_empty() {
                     self.try_start_cm(lr.my_id, lr.my_name, false);
@@ -1952,7 +2892,9 @@ impl Connection {
                 } else {
                     self.update_failure(failure, true, 0);
                     if err_msg.is_empty() {
-                        self.send_logon_response().await;
+                        if !self.send_logon_response().await {
+                            return false;
+                        }
                         self.try_start_cm(lr.my_id, lr.my_name, self.authorized);
                     } else {
                         self.send_login_error(err_msg).await;
@@ -1970,7 +2912,9 @@ impl Connection {
                         self.update_failure(failure, true, 1);
                         self.require_2fa.take();
                         raii::AuthedConnID::set_session_2fa(self.session_key());
-                        self.send_logon_response().await;
+                        if !self.send_logon_response().await {
+                            return false;
+                        }
                         self.try_start_cm(
                             self.lr.my_id.to_owned(),
                             self.lr.my_name.to_owned(),
@@ -1992,6 +2936,12 @@ impl Connection {
                     }
                 }
             }
+        } else if let Some(answer) = self
+            .auth_provider
+            .as_ref()
+            .and_then(|_| auth_provider::Answer::of(&msg))
+        {
+            self.handle_auth_provider_answer(answer).await;
         } else if let Some(message::Union::TestDelay(t)) = msg.union {
             if t.from_client {
                 let mut msg_out = Message::new();
@@ -2021,7 +2971,9 @@ impl Connection {
                     if let Some((_instant, uuid_old)) = uuid_old {
                         if uuid == uuid_old {
                             self.from_switch = true;
-                            self.send_logon_response().await;
+                            if !self.send_logon_response().await {
+                                return false;
+                            }
                             self.try_start_cm(
                                 lr.my_id.clone(),
                                 lr.my_name.clone(),
@@ -2042,6 +2994,11 @@ impl Connection {
                     if self.is_authed_view_camera_conn() {
                         return true;
                     }
+                    if self.shared_window {
+                        if let Some(window) = self.retina.displays.get(self.display_idx) {
+                            super::shared_window::clip(window, &mut me);
+                        }
+                    }
                     if self.peer_keyboard_enabled() {
                         if is_left_up(&me) {
                             CLICK_TIME.store(get_time(), Ordering::SeqCst);
@@ -2049,6 +3006,9 @@ impl Connection {
                             MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
                         }
                         self.retina.on_mouse_event(&mut me, self.display_idx);
+                        if let Some(timeline) = self.input_timeline.as_mut() {
+                            timeline.mouse(&me);
+                        }
                         self.input_mouse(
                             me,
                             self.inner.id(),
@@ -2068,6 +3028,7 @@ impl Connection {
                             true,
                         );
                     }
+                    crate::transfer_throttle::on_activity();
                     self.update_auto_disconnect_timer();
                 }
                 Some(message::Union::PointerDeviceEvent(pde)) => {
@@ -2078,6 +3039,7 @@ impl Connection {
                         MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
                         self.input_pointer(pde, self.inner.id());
                     }
+                    crate::transfer_throttle::on_activity();
                     self.update_auto_disconnect_timer();
                 }
                 Some(message::Union::KeyEvent(me)) => {
@@ -2117,6 +3079,12 @@ impl Connection {
                         }
 
                         if is_press {
+                            if let Some(timeline) = self.input_timeline.as_mut() {
+                                let shortcut = self.pressed_modifiers.iter().any(|k| {
+                                    !matches!(k, rdev::Key::ShiftLeft | rdev::Key::ShiftRight)
+                                });
+                                timeline.key(&me, shortcut);
+                            }
                             match me.union {
                                 Some(key_event::Union::Unicode(_))
                                 | Some(key_event::Union::Seq(_)) => {
@@ -2130,17 +3098,26 @@ impl Connection {
                             self.input_key(me, false);
                         }
                     }
+                    crate::transfer_throttle::on_activity();
                     self.update_auto_disconnect_timer();
                 }
                 Some(message::Union::Clipboard(cb)) => {
-                    if self.clipboard {
-                        update_clipboard(vec![cb], ClipboardSide::Host);
-                    }
+                    self.update_clipboard(vec![cb]);
                 }
                 Some(message::Union::MultiClipboards(mcb)) => {
-                    if self.clipboard {
-                        update_clipboard(mcb.clipboards, ClipboardSide::Host);
-                    }
+                    self.update_clipboard(mcb.clipboards);
+                }
+                // The files of the peer are only pasted here if its clipboard may be set.
+                #[cfg(feature = "unix-file-copy-paste")]
+                Some(message::Union::Cliprdr(clip))
+                    if matches!(
+                        clip.union,
+                        Some(cliprdr::Union::FormatList(_))
+                            | Some(cliprdr::Union::FormatDataResponse(_))
+                            | Some(cliprdr::Union::FileContentsResponse(_))
+                    ) && !super::clipboard_service::is_client_to_host_allowed() =>
+                {
+                    log::debug!("Ignore the clipboard files of the peer, not allowed");
                 }
                 #[cfg(feature = "unix-file-copy-paste")]
                 Some(message::Union::Cliprdr(clip)) => {
@@ -2210,6 +3187,9 @@ impl Connection {
                             }
                         }
                     }
+                    if !handle_fa && self.may_transfer_files() {
+                        handle_fa = self.file_drops.lock().unwrap().allow(&fa);
+                    }
                     if handle_fa {
                         if self.delayed_read_dir.is_some() {
                             if let Some(file_action::Union::ReadDir(rd)) = fa.union {
@@ -2243,6 +3223,34 @@ impl Connection {
                                 return true;
                             }
                         }
+                        if !self.may_modify_files() {
+                            let rejected = match &fa.union {
+                                Some(file_action::Union::Receive(r)) => Some(("receive", r.id)),
+                                Some(file_action::Union::RemoveFile(rf)) => {
+                                    Some(("remove_file", rf.id))
+                                }
+                                Some(file_action::Union::Rename(r)) => Some(("rename", r.id)),
+                                Some(file_action::Union::Create(c)) => Some(("create_dir", c.id)),
+                                Some(file_action::Union::RemoveDir(rd)) => {
+                                    Some(("remove_dir", rd.id))
+                                }
+                                _ => None,
+                            };
+                            if let Some((action, job_id)) = rejected {
+                                log::warn!(
+                                    "#{} Rejected {} in read-only file transfer mode",
+                                    self.inner.id,
+                                    action
+                                );
+                                self.send(fs::new_error(job_id, "read-only-file-transfer-tip", 0))
+                                    .await;
+                                return true;
+                            }
+                        }
+                        if let Err((job_id, err)) = self.check_file_action_sandbox(&fa) {
+                            self.send(fs::new_error(job_id, err, 0)).await;
+                            return true;
+                        }
                         match fa.union {
                             Some(file_action::Union::ReadEmptyDirs(rd)) => {
                                 self.read_empty_dirs(&rd.path, rd.include_hidden);
@@ -2384,6 +3392,7 @@ impl Connection {
                                     path: d.path.clone(),
                                     id: d.id,
                                     recursive: d.recursive,
+                                    trash: !self.file_trash_permanent,
                                 });
                                 self.file_remove_log_control.on_remove_dir(d);
                             }
@@ -2392,6 +3401,7 @@ impl Connection {
                                     path: f.path.clone(),
                                     id: f.id,
                                     file_num: f.file_num,
+                                    trash: !self.file_trash_permanent,
                                 });
                                 self.file_remove_log_control.on_remove_file(f);
                             }
@@ -2414,6 +3424,7 @@ impl Connection {
                             Some(file_action::Union::Cancel(c)) => {
                                 self.send_fs(ipc::FS::CancelWrite { id: c.id });
                                 let _ = self.cm_read_job_ids.remove(&c.id);
+                                self.compress_jobs.remove(c.id);
                                 self.send_fs(ipc::FS::CancelRead {
                                     id: c.id,
                                     conn_id: self.inner.id(),
@@ -2505,70 +3516,312 @@ impl Connection {
                         let set = displays.set.iter().map(|d| *d as usize).collect::<Vec<_>>();
                         self.capture_displays(&add, &sub, &set).await;
                     }
-                    Some(misc::Union::TogglePrivacyMode(t)) => {
-                        self.toggle_privacy_mode(t).await;
+                    Some(misc::Union::TogglePrivacyMode(t)) => {
+                        self.toggle_privacy_mode(t).await;
+                    }
+                    Some(misc::Union::ChatMessage(c)) => {
+                        self.send_to_cm(ipc::Data::ChatMessage { text: c.text });
+                        self.chat_unanswered = true;
+                        self.update_auto_disconnect_timer();
+                    }
+                    Some(misc::Union::Option(o)) => {
+                        self.update_options(&o).await;
+                    }
+                    Some(misc::Union::RefreshVideo(r)) => {
+                        if r {
+                            // Refresh all videos.
+                            // Compatibility with old versions and sciter(remote).
+                            self.refresh_video_display(None);
+                        } else {
+                            // A key frame request of the displays of the connection, ignored by
+                            // old versions.
+                            self.request_key_frame(None);
+                        }
+                        self.update_auto_disconnect_timer();
+                    }
+                    Some(misc::Union::RefreshVideoDisplay(display)) => {
+                        self.refresh_video_display(Some(display as usize));
+                        self.update_auto_disconnect_timer();
+                    }
+                    Some(misc::Union::VideoReceived(_)) => {
+                        video_service::notify_video_frame_fetched_by_conn_id(
+                            self.inner.id,
+                            Some(Instant::now().into()),
+                        );
+                    }
+                    Some(misc::Union::RestartRemoteDevice(_)) => {
+                        if self.restart {
+                            match system_shutdown::reboot() {
+                                Ok(_) => log::info!("Restart by the peer"),
+                                Err(e) => log::error!("Failed to restart: {}", e),
+                            }
+                        }
+                    }
+                    Some(misc::Union::AudioFormat(format)) => {
+                        if !self.disable_audio && (self.voice_calling || self.talk_back_enabled()) {
+                            // Drop the audio sender previously.
+                            drop(std::mem::replace(&mut self.audio_sender, None));
+                            self.audio_sender = Some(start_audio_thread());
+                            self.audio_sender
+                                .as_ref()
+                                .map(|a| allow_err!(a.send(MediaData::AudioFormat(format))));
+                        }
+                    }
+                    #[cfg(feature = "flutter")]
+                    Some(misc::Union::SwitchSidesRequest(s)) => {
+                        if let Ok(uuid) = uuid::Uuid::from_slice(&s.uuid.to_vec()[..]) {
+                            crate::run_me(vec![
+                                "--connect",
+                                &self.lr.my_id,
+                                "--switch_uuid",
+                                uuid.to_string().as_ref(),
+                            ])
+                            .ok();
+                            self.on_close("switch sides", false).await;
+                            return false;
+                        }
+                    }
+                    Some(misc::Union::ChangeResolution(r)) => self.change_resolution(None, &r),
+                    Some(misc::Union::ChangeDisplayResolution(dr)) => {
+                        self.change_resolution(Some(dr.display as _), &dr.resolution)
+                    }
+                    #[cfg(target_os = "macos")]
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::camera_controls::ID => {
+                        if self.is_authed_view_camera_conn() {
+                            self.send(crate::camera_controls::handle_request(&p.content))
+                                .await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::gamepad::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            if let Some(state) = crate::gamepad::parse(&p.content) {
+                                MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
+                                self.input_gamepad(state, self.inner.id());
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::stylus::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            if let Some(evt) = crate::stylus::parse(&p.content) {
+                                MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
+                                self.input_stylus(evt, self.inner.id());
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::touch_gesture::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            if let Some(evt) = crate::touch_gesture::parse(&p.content) {
+                                MOUSE_MOVE_TIME.store(get_time(), Ordering::SeqCst);
+                                self.input_touch_rotate(evt, self.inner.id());
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::input_macro::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            if let Some(req) = crate::input_macro::parse_request(&p.content) {
+                                let error = match self.handle_input_macro(req) {
+                                    Ok(()) => String::new(),
+                                    Err(e) => e.to_string(),
+                                };
+                                let msg = crate::input_macro::make_response(
+                                    self.macro_recorder.as_ref(),
+                                    error,
+                                );
+                                self.send(msg).await;
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::keyboard_layout::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            let msg =
+                                crate::keyboard_layout::handle_request(self.inner.id(), &p.content);
+                            self.send(msg).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::key_frame::ID => {
+                        if let Some(req) = crate::key_frame::Request::parse(&p.content) {
+                            self.request_key_frame(Some(req.display as _));
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::monitor_modes::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            let msg =
+                                crate::monitor_modes::handle_request(self.inner.id(), &p.content);
+                            self.send(msg).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::folder_sync::ID => {
+                        if self.file_transfer.is_some() {
+                            self.handle_folder_sync(&p.content).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::file_drop::ID => {
+                        if self.is_authed_remote_conn() && self.may_transfer_files() {
+                            if let Some(req) = crate::file_drop::Request::parse(&p.content) {
+                                // Finder is asked aside, the answer is sent when it is done.
+                                let tx = self.inner.tx.clone();
+                                let drops = self.file_drops.clone();
+                                tokio::spawn(async move {
+                                    if let Ok(res) =
+                                        tokio::task::spawn_blocking(move || req.answer()).await
+                                    {
+                                        if !res.path.is_empty() {
+                                            drops.lock().unwrap().add_dir(&res.path);
+                                        }
+                                        if let Some(tx) = tx {
+                                            allow_err!(tx.send((
+                                                Instant::now(),
+                                                Arc::new(res.to_message())
+                                            )));
+                                        }
+                                    }
+                                });
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::file_trash::ID => {
+                        if self.file_transfer.is_some() || self.may_transfer_files() {
+                            match crate::file_trash::Request::parse(&p.content) {
+                                Some(crate::file_trash::Request::Mode { permanent }) => {
+                                    self.file_trash_permanent = permanent;
+                                }
+                                Some(crate::file_trash::Request::Restore { id, items }) => {
+                                    self.restore_trashed(id, items).await;
+                                }
+                                None => {}
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_journal::ID => {
+                        if self.file_transfer.is_some() && self.transfer_journal.is_none() {
+                            if let Some(claim) = crate::transfer_journal::Claim::parse(&p.content) {
+                                self.check_interrupted_transfer_jobs(&claim.key).await;
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_verify::ID => {
+                        if self.file_transfer.is_some() {
+                            self.handle_transfer_verify(&p.content);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::config_edit::ID => {
+                        if self.is_authed_remote_conn() && self.file && self.enable_file_transfer {
+                            self.handle_config_edit(&p.content).await;
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::display_power::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            crate::display_power::handle_request(self.inner.id(), &p.content);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::whiteboard::ANNOTATION_ID =>
+                    {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            self.handle_annotation(&p.content);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::permission_request::ID =>
+                    {
+                        if self.is_authed_remote_conn() {
+                            self.handle_permission_request(&p.content).await;
+                        }
+                    }
+                    #[cfg(feature = "unix-file-copy-paste")]
+                    Some(misc::Union::PluginRequest(p))
+                        if p.id == crate::clipboard_file::unix_file_clip::CANCEL_PASTE_ID =>
+                    {
+                        if self.is_authed_remote_conn() {
+                            crate::clipboard_file::unix_file_clip::cancel_paste(self.inner.id());
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::scroll_transform::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            crate::scroll_transform::handle_request(self.inner.id(), &p.content);
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::window_visibility::ID => {
+                        if let Some(v) = crate::window_visibility::Visibility::parse(&p.content) {
+                            video_service::VIDEO_QOS
+                                .lock()
+                                .unwrap()
+                                .user_hidden(self.inner.id(), !v.visible);
+                            for display in self.window_throttle.set_visible(v.visible) {
+                                self.request_key_frame(Some(display as _));
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_compress::ID => {
+                        self.compress_jobs.handle_request(&p.content);
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::transfer_throttle::ID => {
+                        self.transfer_throttle
+                            .handle_request(self.inner.id(), &p.content);
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::privacy_displays::ID => {
+                        if self.is_authed_remote_conn() {
+                            if let Some(req) = crate::privacy_displays::Request::parse(&p.content) {
+                                self.set_privacy_displays(req).await;
+                            }
+                        }
+                    }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::wake_policy::ID => {
+                        if crate::wake_policy::handle_request(self.inner.id(), &p.content) {
+                            raii::AuthedConnID::check_wake_lock();
+                        }
                     }
-                    Some(misc::Union::ChatMessage(c)) => {
-                        self.send_to_cm(ipc::Data::ChatMessage { text: c.text });
-                        self.chat_unanswered = true;
-                        self.update_auto_disconnect_timer();
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::mouse_curve::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            crate::mouse_curve::handle_request(self.inner.id(), &p.content);
+                        }
                     }
-                    Some(misc::Union::Option(o)) => {
-                        self.update_options(&o).await;
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::special_keys::ID => {
+                        if self.is_authed_remote_conn() && self.peer_keyboard_enabled() {
+                            let conn_id = self.inner.id();
+                            crate::special_keys::handle_request(conn_id, &p.content, |key| {
+                                super::input_limiter::allow(conn_id, false)
+                                    && super::input_profile::allow_named_key(conn_id, &key.name())
+                            });
+                        }
                     }
-                    Some(misc::Union::RefreshVideo(r)) => {
-                        if r {
-                            // Refresh all videos.
-                            // Compatibility with old versions and sciter(remote).
-                            self.refresh_video_display(None);
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::display_id::ID => {
+                        if self.is_authed_remote_conn() {
+                            self.handle_display_id_request(&p.content).await;
                         }
-                        self.update_auto_disconnect_timer();
                     }
-                    Some(misc::Union::RefreshVideoDisplay(display)) => {
-                        self.refresh_video_display(Some(display as usize));
-                        self.update_auto_disconnect_timer();
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::display_delta::ID => {
+                        if self.is_authed_remote_conn() {
+                            self.display_delta.enable();
+                        }
                     }
-                    Some(misc::Union::VideoReceived(_)) => {
-                        video_service::notify_video_frame_fetched_by_conn_id(
-                            self.inner.id,
-                            Some(Instant::now().into()),
-                        );
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::scaled_frames::ID => {
+                        video_service::VIDEO_QOS
+                            .lock()
+                            .unwrap()
+                            .user_scaled_frames(self.inner.id(), true);
                     }
-                    Some(misc::Union::RestartRemoteDevice(_)) => {
-                        if self.restart {
-                            match system_shutdown::reboot() {
-                                Ok(_) => log::info!("Restart by the peer"),
-                                Err(e) => log::error!("Failed to restart: {}", e),
-                            }
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::clipboard::chunks::ID => {
+                        if self.is_authed_remote_conn() {
+                            self.handle_clipboard_chunk(&p.content);
                         }
                     }
-                    Some(misc::Union::AudioFormat(format)) => {
-                        if !self.disable_audio {
-                            // Drop the audio sender previously.
-                            drop(std::mem::replace(&mut self.audio_sender, None));
-                            self.audio_sender = Some(start_audio_thread());
-                            self.audio_sender
-                                .as_ref()
-                                .map(|a| allow_err!(a.send(MediaData::AudioFormat(format))));
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::bandwidth_probe::ID => {
+                        if self.is_authed_remote_conn() {
+                            self.handle_bandwidth_probe(&p.content).await;
                         }
                     }
-                    #[cfg(feature = "flutter")]
-                    Some(misc::Union::SwitchSidesRequest(s)) => {
-                        if let Ok(uuid) = uuid::Uuid::from_slice(&s.uuid.to_vec()[..]) {
-                            crate::run_me(vec![
-                                "--connect",
-                                &self.lr.my_id,
-                                "--switch_uuid",
-                                uuid.to_string().as_ref(),
-                            ])
-                            .ok();
-                            self.on_close("switch sides", false).await;
-                            return false;
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::mux::ID => {
+                        if self.is_authed_remote_conn() {
+                            self.handle_mux_frame(&p.content);
                         }
                     }
-                    Some(misc::Union::ChangeResolution(r)) => self.change_resolution(None, &r),
-                    Some(misc::Union::ChangeDisplayResolution(dr)) => {
-                        self.change_resolution(Some(dr.display as _), &dr.resolution)
+                    Some(misc::Union::PluginRequest(p)) if p.id == crate::audio_devices::ID => {
+                        if self.is_authed_remote_conn() && self.audio_enabled() {
+                            let msg =
+                                crate::audio_devices::handle_request(self.inner.id(), &p.content);
+                            self.send(msg).await;
+                        }
                     }
                     #[cfg(all(feature = "flutter", feature = "plugin_framework"))]
                     Some(misc::Union::PluginRequest(p)) => {
@@ -2580,10 +3833,13 @@ impl Connection {
                         .lock()
                         .unwrap()
                         .user_auto_adjust_fps(self.inner.id(), fps),
-                    Some(misc::Union::ClientRecordStatus(status)) => video_service::VIDEO_QOS
-                        .lock()
-                        .unwrap()
-                        .user_record(self.inner.id(), status),
+                    Some(misc::Union::ClientRecordStatus(status)) => {
+                        video_service::VIDEO_QOS
+                            .lock()
+                            .unwrap()
+                            .user_record(self.inner.id(), status);
+                        self.send_to_cm(ipc::Data::ClientRecordStatus(status));
+                    }
 
                     Some(misc::Union::MessageQuery(mq)) => {
                         if let Some(msg_out) = video_service::make_display_changed_msg(
@@ -2597,7 +3853,7 @@ impl Connection {
                     _ => {}
                 },
                 Some(message::Union::AudioFrame(frame)) => {
-                    if !self.disable_audio {
+                    if !self.disable_audio && (self.voice_calling || self.talk_back_enabled()) {
                         if let Some(sender) = &self.audio_sender {
                             allow_err!(sender.send(MediaData::AudioFrame(Box::new(frame))));
                         } else {
@@ -2623,7 +3879,15 @@ impl Connection {
                     // TODO: Maybe we can do a voice call from cm directly.
                 }
                 Some(message::Union::ScreenshotRequest(request)) => {
-                    if let Some(tx) = self.inner.tx.clone() {
+                    if self.do_not_record {
+                        let mut msg_out = Message::new();
+                        msg_out.set_screenshot_response(ScreenshotResponse {
+                            sid: request.sid,
+                            msg: "screenshot-not-allowed-tip".to_owned(),
+                            ..Default::default()
+                        });
+                        self.send(msg_out).await;
+                    } else if let Some(tx) = self.inner.tx.clone() {
                         crate::video_service::set_take_screenshot(
                             request.display as _,
                             request.sid.clone(),
@@ -2633,6 +3897,11 @@ impl Connection {
                     }
                 }
                 Some(message::Union::TerminalAction(action)) => {
+                    if self.view_only
+                        && matches!(action.union, Some(terminal_action::Union::Data(_)))
+                    {
+                        return true;
+                    }
                     allow_err!(self.handle_terminal_action(action).await);
                 }
                 _ => {}
@@ -2823,6 +4092,20 @@ impl Connection {
         });
     }
 
+    // Cheaper than a refresh, the capturer and the encoder are kept. Only the displays of the
+    // connection, its other viewers get the key frame too.
+    fn request_key_frame(&self, display: Option<usize>) {
+        self.server.upgrade().map(|s| {
+            s.read().unwrap().set_subbed_video_service_opt(
+                self.inner.id(),
+                self.video_source(),
+                display,
+                video_service::OPTION_KEY_FRAME,
+                super::service::SERVICE_OPTION_VALUE_TRUE,
+            );
+        });
+    }
+
     async fn handle_switch_display(&mut self, s: SwitchDisplay) {
         let display_idx = s.display as usize;
         if self.display_idx != display_idx {
@@ -2856,12 +4139,18 @@ impl Connection {
     fn video_source(&self) -> VideoSource {
         if self.view_camera {
             VideoSource::Camera
+        } else if self.shared_window {
+            VideoSource::Window
         } else {
             VideoSource::Monitor
         }
     }
 
     fn switch_display_to(&mut self, display_idx: usize, server: Arc<RwLock<Server>>) {
+        if self.view_camera && !camera::Cameras::exists(display_idx) {
+            log::warn!("Camera of index {} doesn't exist", display_idx);
+            return;
+        }
         let new_service_name = video_service::get_service_name(self.video_source(), display_idx);
         let old_service_name =
             video_service::get_service_name(self.video_source(), self.display_idx);
@@ -2885,6 +4174,17 @@ impl Connection {
 
     async fn capture_displays(&mut self, add: &[usize], sub: &[usize], set: &[usize]) {
         let video_source = self.video_source();
+        // Each camera is captured by its own service, skip the unplugged ones.
+        let (add, set) = if video_source.is_camera() {
+            let ncamera = camera::Cameras::all_info().map(|c| c.len()).unwrap_or(0);
+            (
+                add.iter().filter(|d| **d < ncamera).cloned().collect(),
+                set.iter().filter(|d| **d < ncamera).cloned().collect(),
+            )
+        } else {
+            (add.to_vec(), set.to_vec())
+        };
+        let (add, set) = (&add[..], &set[..]);
         if let Some(sever) = self.server.upgrade() {
             let mut lock = sever.write().unwrap();
             for display in add.iter() {
@@ -2918,6 +4218,35 @@ impl Connection {
         }
     }
 
+    async fn set_privacy_displays(&mut self, req: crate::privacy_displays::Request) {
+        let count = display_service::try_get_displays()
+            .map(|d| d.len())
+            .unwrap_or_default();
+        self.privacy_displays = req.valid_displays(count);
+        let mut error = String::new();
+        if privacy_mode::get_privacy_mode_conn_id() == Some(self.inner.id()) {
+            if let Some(Err(e)) =
+                privacy_mode::set_displays(self.inner.id(), &self.privacy_displays)
+            {
+                log::error!("Failed to set the displays of privacy mode: {}", e);
+                error = e.to_string();
+                if !privacy_mode::is_in_privacy_mode() {
+                    let impl_key = privacy_mode::get_cur_impl_key().unwrap_or_default();
+                    self.send(crate::common::make_privacy_mode_msg(
+                        back_notification::PrivacyModeState::PrvOffUnknown,
+                        impl_key,
+                    ))
+                    .await;
+                }
+            }
+        }
+        let res = crate::privacy_displays::Response {
+            displays: self.privacy_displays.clone(),
+            error,
+        };
+        self.send(res.to_message()).await;
+    }
+
     async fn toggle_privacy_mode(&mut self, t: TogglePrivacyMode) {
         if t.on {
             self.turn_on_privacy(t.impl_key).await;
@@ -2927,6 +4256,25 @@ impl Connection {
     }
 
     fn change_resolution(&mut self, d: Option<usize>, r: &Resolution) {
+        if self.view_camera {
+            let display_idx = d.unwrap_or(self.display_idx);
+            if let Ok(current) = camera::Cameras::get_camera_resolution(display_idx) {
+                if (current.width, current.height) == (r.width, r.height) {
+                    return;
+                }
+            }
+            match camera::Cameras::set_camera_resolution(display_idx, r) {
+                Ok(()) => self.refresh_video_display(Some(display_idx)),
+                Err(e) => log::error!(
+                    "Failed to change camera {} resolution to ({},{}): {:?}",
+                    display_idx,
+                    r.width,
+                    r.height,
+                    e
+                ),
+            }
+            return;
+        }
         if self.keyboard {
             if let Ok(displays) = display_service::try_get_displays() {
                 let display_idx = d.unwrap_or(self.display_idx);
@@ -2983,6 +4331,7 @@ impl Connection {
                         self.audio_enabled() && accepted,
                     );
                 }
+                self.update_camera_audio();
             }
         } else {
             log::warn!("Possible a voice call attack.");
@@ -3000,6 +4349,7 @@ impl Connection {
                     .unwrap()
                     .subscribe(super::audio_service::NAME, self.inner.clone(), false);
             }
+            self.update_camera_audio();
         }
     }
 
@@ -3083,6 +4433,7 @@ impl Connection {
                                 self.audio_enabled(),
                             );
                         }
+                        self.update_camera_audio();
                     } else {
                         s.write().unwrap().subscribe(
                             super::audio_service::NAME,
@@ -3254,6 +4605,13 @@ impl Connection {
                 }
             }
 
+            if privacy_mode::get_privacy_mode_conn_id() != Some(self.inner.id) {
+                if let Some(Err(e)) =
+                    privacy_mode::set_displays(self.inner.id, &self.privacy_displays)
+                {
+                    log::warn!("Failed to set the displays of privacy mode: {}", e);
+                }
+            }
             let turn_on_res = privacy_mode::turn_on_privacy(&impl_key, self.inner.id).await;
             match turn_on_res {
                 Some(Ok(res)) => {
@@ -3362,6 +4720,26 @@ impl Connection {
         // We can add a (Vec<conn_id>, input device) to avoid this.
         // But it's not necessary now and we have to consider two audio services(client, server).
         crate::audio_service::set_voice_call_input_device(None, true);
+        crate::audio_service::set_session_input_device(self.inner.id(), None);
+        reset_gamepads(self.inner.id());
+        release_keys(self.inner.id());
+        reset_stylus(self.inner.id());
+        reset_gestures(self.inner.id());
+        if let Some(counters) = super::input_limiter::remove(self.inner.id()) {
+            self.input_counters = counters;
+        }
+        crate::mouse_curve::remove(self.inner.id());
+        crate::scroll_transform::remove(self.inner.id());
+        super::input_service::set_laser(self.inner.id(), false);
+        if let Some((name, profile)) = super::input_profile::remove(self.inner.id()) {
+            self.run_session_hooks(
+                super::session_hooks::Event::Disconnect,
+                &name,
+                profile.on_disconnect,
+            );
+        }
+        crate::keyboard_layout::restore(self.inner.id());
+        self.macro_replay = None;
         log::info!("#{} Connection closed: {}", self.inner.id(), reason);
         if lock && self.lock_after_session_end && self.keyboard {
             lock_screen().await;
@@ -3373,6 +4751,10 @@ impl Connection {
         };
         self.tx_to_cm.send(data).ok();
         self.port_forward_socket.take();
+        self.mux_channels.close_all();
+        if let Some(journal) = self.transfer_journal.as_mut() {
+            journal.clear();
+        }
     }
 
     // The `reason` should be consistent with `check_if_retry` if not empty
@@ -3425,6 +4807,8 @@ impl Connection {
 
                 let path_str = dir.path.clone();
                 let file_entries: Vec<FileEntry> = dir.entries.into();
+                self.compress_jobs
+                    .set_files(id, file_entries.iter().map(|f| f.name.clone()).collect());
 
                 // Send file directory to client
                 self.send(fs::new_dir(id, path_str.clone(), file_entries.clone()))
@@ -3467,6 +4851,7 @@ impl Connection {
         block.file_num = file_num;
         block.data = data.to_vec().into();
         block.compressed = compressed;
+        self.compress_jobs.compress_cm_block(&mut block);
 
         let mut msg = Message::new();
         let mut fr = FileResponse::new();
@@ -3477,6 +4862,7 @@ impl Connection {
 
     async fn handle_file_read_done(&mut self, id: i32, file_num: i32) {
         // Drop stale completions for cancelled/unknown jobs
+        self.compress_jobs.remove(id);
         if !self.cm_read_job_ids.remove(&id) {
             log::debug!(
                 "Dropping FileReadDone for cancelled/unknown job id={}, file_num={}",
@@ -3500,6 +4886,7 @@ impl Connection {
 
     async fn handle_file_read_error(&mut self, id: i32, file_num: i32, err: String) {
         // Drop stale errors for cancelled/unknown jobs
+        self.compress_jobs.remove(id);
         if !self.cm_read_job_ids.remove(&id) {
             log::debug!(
                 "Dropping FileReadError for cancelled/unknown job id={}, file_num={}",
@@ -3603,6 +4990,10 @@ impl Connection {
     }
 
     fn read_empty_dirs(&mut self, dir: &str, include_hidden: bool) {
+        if let Err(e) = file_sandbox::check_path(dir) {
+            self.post_sandbox_alarm_audit("read_empty_dirs", dir, &e.to_string());
+            return;
+        }
         let dir = dir.to_string();
         self.send_fs(ipc::FS::ReadEmptyDirs {
             dir,
@@ -3611,13 +5002,93 @@ impl Connection {
     }
 
     fn read_dir(&mut self, dir: &str, include_hidden: bool) {
-        let dir = dir.to_string();
+        let mut dir = dir.to_string();
+        if let Some(default_dir) = file_sandbox::default_dir() {
+            // The home directory is not necessarily in the sandbox,
+            // so the default path is the first root.
+            if dir.is_empty() {
+                dir = default_dir;
+            } else if let Err(e) = file_sandbox::check_path(&dir) {
+                self.post_sandbox_alarm_audit("read_dir", &dir, &e.to_string());
+                dir = default_dir;
+            }
+        }
         self.send_fs(ipc::FS::ReadDir {
             dir,
             include_hidden,
         });
     }
 
+    /// Check the paths of an incoming file action against the sandbox roots.
+    ///
+    /// `ReadDir` and `ReadEmptyDirs` are checked in `read_dir()` and `read_empty_dirs()`.
+    /// Returns the job id and the error to send back if any path escapes the roots.
+    fn check_file_action_sandbox(&self, fa: &FileAction) -> Result<(), (i32, String)> {
+        let roots = file_sandbox::get_roots();
+        if roots.is_empty() {
+            return Ok(());
+        }
+        let (action, id, paths) = match &fa.union {
+            Some(file_action::Union::AllFiles(f)) => ("all_files", f.id, vec![f.path.clone()]),
+            Some(file_action::Union::Send(s)) => {
+                if JobType::from_proto(s.file_type) == JobType::Printer {
+                    return Ok(());
+                }
+                ("send", s.id, vec![s.path.clone()])
+            }
+            Some(file_action::Union::Receive(r)) => {
+                let base = PathBuf::from(&r.path);
+                let mut paths = vec![r.path.clone()];
+                paths.extend(
+                    r.files
+                        .iter()
+                        .filter(|f| !f.name.is_empty())
+                        .map(|f| fs::get_string(&fs::TransferJob::join(&base, &f.name))),
+                );
+                ("receive", r.id, paths)
+            }
+            Some(file_action::Union::RemoveDir(d)) => ("remove_dir", d.id, vec![d.path.clone()]),
+            Some(file_action::Union::RemoveFile(f)) => ("remove_file", f.id, vec![f.path.clone()]),
+            Some(file_action::Union::Create(c)) => ("create_dir", c.id, vec![c.path.clone()]),
+            Some(file_action::Union::Rename(r)) => {
+                let mut paths = vec![r.path.clone()];
+                if let Some(parent) = std::path::Path::new(&r.path).parent() {
+                    paths.push(fs::get_string(&parent.join(&r.new_name)));
+                }
+                ("rename", r.id, paths)
+            }
+            _ => return Ok(()),
+        };
+        for path in paths.iter() {
+            if let Err(e) = file_sandbox::check_path_in_roots(path, &roots) {
+                self.post_sandbox_alarm_audit(action, path, &e.to_string());
+                return Err((id, e.to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    fn post_sandbox_alarm_audit(&self, action: &str, path: &str, reason: &str) {
+        log::warn!(
+            "#{} Rejected {} outside of file transfer sandbox: {}, {}",
+            self.inner.id,
+            action,
+            path,
+            reason
+        );
+        Self::post_alarm_audit(
+            AlarmAuditType::FileSandboxViolation,
+            json!({
+                "ip": self.ip,
+                "peer_id": self.lr.my_id,
+                "name": self.lr.my_name,
+                "action": action,
+                "path": path,
+                "reason": reason,
+            }),
+        );
+    }
+
     /// Create a new read job and start processing it (Connection-side).
     ///
     /// This is a generic Connection-side read job creation helper used for:
@@ -3656,6 +5127,21 @@ impl Connection {
                 self.send(fs::new_error(id, err, 0)).await;
             }
             Ok(job) => {
+                if job_type == fs::JobType::Generic {
+                    // Files found by walking the directory may be symlinks out of the sandbox.
+                    let roots = file_sandbox::get_roots();
+                    if !roots.is_empty() {
+                        let base = PathBuf::from(&path);
+                        for f in job.files().iter().filter(|f| !f.name.is_empty()) {
+                            let p = fs::get_string(&fs::TransferJob::join(&base, &f.name));
+                            if let Err(e) = file_sandbox::check_path_in_roots(&p, &roots) {
+                                self.post_sandbox_alarm_audit("send", &p, &e.to_string());
+                                self.send(fs::new_error(id, e, 0)).await;
+                                return;
+                            }
+                        }
+                    }
+                }
                 if check_file_limit {
                     if let Err(msg) =
                         crate::ui_cm_interface::check_file_count_limit(job.files().len())
@@ -3674,6 +5160,140 @@ impl Connection {
         allow_err!(self.stream.send(&msg).await);
     }
 
+    fn queue_send(&mut self, instant: std::time::Instant, msg: Arc<Message>) {
+        if self.clipboard_chunks {
+            if let Some(message::Union::MultiClipboards(mcb)) = &msg.union {
+                if let Some(chunks) = crate::clipboard::chunks::split(mcb) {
+                    for chunk in chunks {
+                        self.send_queue.push(instant, Arc::new(chunk));
+                    }
+                    return;
+                }
+            }
+        }
+        let dropped = self.send_queue.push(instant, msg);
+        if dropped.is_empty() {
+            return;
+        }
+        let id = self.inner.id;
+        let conn_ids: HashSet<i32> = [id].into_iter().collect();
+        let mut video_qos = video_service::VIDEO_QOS.lock().unwrap();
+        for display in dropped.iter() {
+            // The video service waits for the frame to be sent.
+            video_service::notify_video_frame_fetched(*display, id, None);
+            video_qos.on_frame_dropped(&conn_ids);
+        }
+        drop(video_qos);
+        for display in self.send_queue.take_key_frame_requests() {
+            log::debug!(
+                "video frames of display {} dropped, request a key frame",
+                display
+            );
+            self.request_key_frame(Some(display));
+        }
+    }
+
+    // Send a message taken from the send queue, returns false if the connection is closed.
+    async fn send_queued(&mut self, instant: std::time::Instant, value: Arc<Message>) -> bool {
+        if let Some(message::Union::VideoFrame(vf)) = &value.union {
+            crate::transfer_throttle::on_activity();
+            if self.window_throttle.skip(vf.display) {
+                // The video service waits for the frame to be sent.
+                video_service::notify_video_frame_fetched(
+                    vf.display as usize,
+                    self.inner.id,
+                    Some(instant),
+                );
+                return true;
+            }
+            if let Some(datagram) = self.video_datagram.as_mut() {
+                if datagram.is_active() {
+                    if let Ok(bytes) = value.write_to_bytes() {
+                        let key = send_queue::is_key_frame(vf);
+                        if datagram.send_frame(vf.display as _, key, &bytes) {
+                            // Lost datagrams are never acknowledged, do not wait for them.
+                            video_service::notify_video_frame_fetched(
+                                vf.display as usize,
+                                self.inner.id,
+                                Some(instant),
+                            );
+                            return true;
+                        }
+                    }
+                }
+            }
+            if !self.video_ack_required {
+                video_service::notify_video_frame_fetched(
+                    vf.display as usize,
+                    self.inner.id,
+                    Some(instant),
+                );
+            }
+        }
+        let latency = instant.elapsed().as_millis() as i64;
+        #[allow(unused_mut)]
+        let mut msg = value;
+
+        if latency > 1000 {
+            match &msg.union {
+                Some(message::Union::AudioFrame(_)) => {
+                    // log::info!("audio frame latency {}", instant.elapsed().as_secs_f32());
+                    return true;
+                }
+                _ => {}
+            }
+        }
+        match &msg.union {
+            Some(message::Union::Misc(m)) => match &m.union {
+                Some(misc::Union::StopService(_)) => {
+                    self.send_close_reason_no_retry("").await;
+                    self.on_close("stop service", false).await;
+                    return false;
+                }
+                _ => {}
+            },
+            Some(message::Union::PeerInfo(_pi)) => {
+                self.refresh_video_display(None);
+                self.retina.set_displays(&_pi.displays);
+                match self.display_delta.diff(_pi) {
+                    Some(Some(delta)) => msg = Arc::new(delta),
+                    // The client has these displays already.
+                    Some(None) => return true,
+                    None => {}
+                }
+            }
+            Some(message::Union::CursorPosition(pos)) => {
+                if self.follow_remote_cursor {
+                    self.handle_cursor_switch_display(pos.clone()).await;
+                }
+                if let Some(new_msg) = self.retina.on_cursor_pos(&pos, self.display_idx) {
+                    msg = Arc::new(new_msg);
+                }
+            }
+            Some(message::Union::MultiClipboards(_multi_clipboards)) => {
+                if let Some(msg_out) = crate::clipboard::get_msg_if_not_support_multi_clip(
+                    &self.lr.version,
+                    &self.lr.my_platform,
+                    _multi_clipboards,
+                ) {
+                    if let Err(err) = self.stream.send(&msg_out).await {
+                        self.on_close(&err.to_string(), false).await;
+                        return false;
+                    }
+                    return true;
+                }
+            }
+            _ => {}
+        }
+
+        let msg: &Message = &msg;
+        if let Err(err) = self.stream.send(msg).await {
+            self.on_close(&err.to_string(), false).await;
+            return false;
+        }
+        true
+    }
+
     pub fn alive_conns() -> Vec<i32> {
         ALIVE_CONNS.lock().unwrap().clone()
     }
@@ -3768,6 +5388,111 @@ impl Connection {
         }
     }
 
+    fn handle_input_macro(&mut self, req: crate::input_macro::Request) -> ResultType<()> {
+        use crate::input_macro::{self as input_macro, Recorder, Request};
+        match req {
+            Request::Record { name } => {
+                if self.macro_recorder.is_some() {
+                    bail!("A macro is being recorded");
+                }
+                self.macro_recorder = Some(Recorder::new(&name)?);
+            }
+            Request::Stop => {
+                self.macro_replay = None;
+                if let Some(recorder) = self.macro_recorder.take() {
+                    let m = recorder.finish();
+                    log::info!("input macro {} recorded, {} steps", m.name, m.steps.len());
+                    input_macro::save(&m)?;
+                }
+            }
+            Request::Replay { name } => {
+                if self.macro_recorder.is_some() {
+                    bail!("A macro is being recorded");
+                }
+                if self.macro_replay.as_ref().map_or(false, |r| r.is_running()) {
+                    bail!("A macro is being replayed");
+                }
+                let m = input_macro::load(&name)?;
+                log::info!("replay input macro {}, {} steps", m.name, m.steps.len());
+                self.macro_replay = Some(MacroReplay::start(
+                    m.steps,
+                    self.inner.id(),
+                    self.lr.my_name.clone(),
+                    self.peer_argb,
+                    self.tx_input.clone(),
+                ));
+            }
+            Request::Delete { name } => input_macro::delete(&name)?,
+            Request::List => {}
+        }
+        Ok(())
+    }
+
+    fn handle_mux_frame(&mut self, content: &[u8]) {
+        let Some(frame) = crate::mux::Frame::decode(content) else {
+            return;
+        };
+        let crate::mux::Frame::Open { channel, target } = frame else {
+            let channel = frame.channel();
+            if !self.mux_channels.dispatch(frame) {
+                log::warn!("mux channel {} exceeded its window", channel);
+                self.post_conn_audit(json!({
+                    "action": "mux_window_exceeded",
+                    "channel": channel,
+                }));
+                let reason = crate::mux::REASON_WINDOW_EXCEEDED.to_owned();
+                if let Some(tx) = self.inner.tx.as_ref() {
+                    let msg = crate::mux::Frame::Close { channel, reason }.to_message();
+                    tx.send((Instant::now(), Arc::new(msg))).ok();
+                }
+            }
+            return;
+        };
+        let tx = self.inner.tx.clone();
+        let send = move |msg: Message| {
+            if let Some(tx) = tx.as_ref() {
+                tx.send((Instant::now(), Arc::new(msg))).ok();
+            }
+        };
+        if !Self::permission(keys::OPTION_ENABLE_TUNNEL, &self.control_permissions) {
+            let reason = "No permission of IP tunneling".to_owned();
+            send(crate::mux::Frame::Close { channel, reason }.to_message());
+            return;
+        }
+        if self.view_only {
+            let reason = "View only".to_owned();
+            send(crate::mux::Frame::Close { channel, reason }.to_message());
+            return;
+        }
+        if !crate::mux::is_target_allowed(target.host(), target.port) {
+            let reason = format!("Target {} not allowed", target.addr());
+            send(crate::mux::Frame::Close { channel, reason }.to_message());
+            return;
+        }
+        let addr = target.addr();
+        self.post_conn_audit(json!({
+            "action": "mux_open",
+            "channel": channel,
+            "target": &addr,
+        }));
+        self.send_to_cm(ipc::Data::MuxTunnel {
+            channel,
+            target: addr.clone(),
+            open: true,
+        });
+        let tx_to_cm = self.tx_to_cm.clone();
+        let on_close = move || {
+            tx_to_cm
+                .send(ipc::Data::MuxTunnel {
+                    channel,
+                    target: addr,
+                    open: false,
+                })
+                .ok();
+        };
+        crate::mux::accept(&self.mux_channels, channel, target, send, on_close);
+    }
+
     fn is_authed_remote_conn(&self) -> bool {
         if let Some(id) = self.authed_conn_id.as_ref() {
             return id.conn_type() == AuthConnType::Remote;
@@ -3810,6 +5535,55 @@ impl Connection {
         }
     }
 
+    fn update_clipboard(&self, clipboards: Vec<Clipboard>) {
+        if !self.clipboard
+            || self.view_only
+            || !super::clipboard_service::is_client_to_host_allowed()
+        {
+            return;
+        }
+        if !crate::clipboard::chunks::is_within_limit(&clipboards) {
+            log::warn!("Ignore the clipboard of the peer, it is too large");
+            return;
+        }
+        update_clipboard(clipboards, ClipboardSide::Host);
+    }
+
+    // An empty chunk is the request of the client to get the large clipboards in chunks.
+    fn handle_clipboard_chunk(&mut self, content: &[u8]) {
+        use crate::clipboard::chunks::Received;
+        if content.is_empty() {
+            self.clipboard_chunks = true;
+            return;
+        }
+        match self.clipboard_receiver.on_chunk(content) {
+            Some(Received::Done(mcb)) => self.update_clipboard(mcb.clipboards),
+            Some(Received::Progress(percent)) => {
+                log::debug!("Clipboard of the peer {}% received", percent)
+            }
+            None => {}
+        }
+    }
+
+    // Sent when it changes, the last one once the paste ends.
+    #[cfg(feature = "unix-file-copy-paste")]
+    fn update_file_paste_progress(&mut self) {
+        let Some(p) = crate::clipboard_file::unix_file_clip::paste_progress(self.inner.id()) else {
+            return;
+        };
+        let progress = Some((p.transferred, p.total));
+        if progress == self.file_paste_progress {
+            return;
+        }
+        self.file_paste_progress = progress;
+        self.send_to_cm(ipc::Data::FilePasteProgress {
+            transferred: p.transferred,
+            total: p.total,
+            canceled: p.is_canceled,
+            failed: p.is_failed,
+        });
+    }
+
     #[inline]
     #[cfg(feature = "unix-file-copy-paste")]
     fn try_empty_file_clipboard(&mut self) {
@@ -4024,6 +5798,9 @@ pub enum AlarmAuditType {
     // MultipleLoginsAttemptsWithinOneMinute = 4,
     // MultipleLoginsAttemptsWithinOneHour = 5,
     ExceedIPv6PrefixAttempts = 6,
+    FileSandboxViolation = 7,
+    ClipboardRedaction = 8,
+    InputFlood = 9,
 }
 
 pub enum FileAuditType {
@@ -4147,27 +5924,27 @@ impl FileRemoveLogControl {
     }
 }
 
-fn start_wakelock_thread() -> std::sync::mpsc::Sender<(usize, usize)> {
+fn start_wakelock_thread() -> std::sync::mpsc::Sender<crate::wake_policy::Demand> {
     // Check if we should keep awake during incoming sessions
     use crate::platform::{get_wakelock, WakeLock};
-    let (tx, rx) = std::sync::mpsc::channel::<(usize, usize)>();
+    let (tx, rx) = std::sync::mpsc::channel::<crate::wake_policy::Demand>();
     std::thread::spawn(move || {
         let mut wakelock: Option<WakeLock> = None;
         let mut last_display = false;
         loop {
             match rx.recv() {
-                Ok((conn_count, remote_count)) => {
+                Ok(demand) => {
                     let keep_awake = config::Config::get_bool_option(
                         keys::OPTION_KEEP_AWAKE_DURING_INCOMING_SESSIONS,
                     );
                     *WAKELOCK_KEEP_AWAKE_OPTION.lock().unwrap() = Some(keep_awake);
-                    if conn_count == 0 || !keep_awake {
+                    if !demand.system || !keep_awake {
                         if wakelock.is_some() {
                             wakelock = None;
                             log::info!("drop wakelock");
                         }
                     } else {
-                        let mut display = remote_count > 0;
+                        let display = demand.display;
                         if let Some(_w) = wakelock.as_mut() {
                             if display != last_display {
                                 {
@@ -4235,6 +6012,7 @@ impl Retina {
         if evt_type == crate::input::MOUSE_TYPE_WHEEL
             || evt_type == crate::input::MOUSE_TYPE_TRACKPAD
             || evt_type == crate::input::MOUSE_TYPE_MOVE_RELATIVE
+            || evt_type == crate::input::MOUSE_TYPE_WHEEL_PRECISE
         {
             return;
         }
@@ -4314,6 +6092,17 @@ pub struct AuthedConn {
     pub printer: bool,
 }
 
+/// Peer id and name of the authorized remote control connections.
+pub fn remote_peers() -> Vec<(String, String)> {
+    AUTHED_CONNS
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|c| c.conn_type == AuthConnType::Remote)
+        .map(|c| (c.session_key.peer_id.clone(), c.session_key.name.clone()))
+        .collect()
+}
+
 mod raii {
     // ALIVE_CONNS: all connections, including unauthorized connections
     // AUTHED_CONNS: all authorized connections
@@ -4363,26 +6152,24 @@ mod raii {
                 shutdown_hooks::add_shutdown_hook(connection_shutdown_hook);
             });
             if conn_type == AuthConnType::Remote || conn_type == AuthConnType::ViewCamera {
-                video_service::VIDEO_QOS
-                    .lock()
-                    .unwrap()
-                    .on_connection_open(conn_id);
+                let mobile = lr.my_platform == hbb_common::whoami::Platform::Android.to_string()
+                    || lr.my_platform == hbb_common::whoami::Platform::Ios.to_string();
+                let mut video_qos = video_service::VIDEO_QOS.lock().unwrap();
+                video_qos.on_connection_open(conn_id);
+                video_qos.user_weak_device(conn_id, mobile);
             }
             Self(conn_id, conn_type)
         }
 
-        fn check_wake_lock() {
-            let conn_count = AUTHED_CONNS.lock().unwrap().len();
-            let remote_count = AUTHED_CONNS
-                .lock()
-                .unwrap()
-                .iter()
-                .filter(|c| c.conn_type == AuthConnType::Remote)
-                .count();
-            allow_err!(WAKELOCK_SENDER
-                .lock()
-                .unwrap()
-                .send((conn_count, remote_count)));
+        pub fn check_wake_lock() {
+            let demand = crate::wake_policy::demand(
+                AUTHED_CONNS
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|c| (c.conn_id, c.conn_type == AuthConnType::Remote)),
+            );
+            allow_err!(WAKELOCK_SENDER.lock().unwrap().send(demand));
         }
 
         pub fn check_wake_lock_on_setting_changed() {
@@ -4486,6 +6273,7 @@ mod raii {
             // Clear per-connection state to avoid stale behavior if conn ids are reused.
             clear_relative_mouse_active(self.0);
             AUTHED_CONNS.lock().unwrap().retain(|c| c.conn_id != self.0);
+            crate::wake_policy::remove(self.0);
             let remote_count = AUTHED_CONNS
                 .lock()
                 .unwrap()
@@ -4499,6 +6287,7 @@ mod raii {
             {
                 use crate::whiteboard;
                 whiteboard::unregister_whiteboard(whiteboard::get_key_cursor(self.0));
+                whiteboard::unregister_whiteboard(whiteboard::get_key_annotation(self.0));
             }
         }
     }
@@ -24,6 +24,8 @@ lazy_static::lazy_static! {
 // https://github.com/rustdesk/rustdesk/pull/8537
 static TEMP_IGNORE_DISPLAYS_CHANGED: AtomicBool = AtomicBool::new(false);
 
+const AUTO_VIRTUAL_DISPLAY_INTERVAL: Duration = Duration::from_secs(2);
+
 #[derive(Default)]
 struct SyncDisplaysInfo {
     displays: Vec<DisplayInfo>,
@@ -140,14 +142,29 @@ pub fn capture_cursor_embedded() -> bool {
 pub fn new() -> GenericService {
     let svc = EmptyExtraFieldService::new(NAME.to_owned(), true);
     GenericService::run(&svc.clone(), run);
+    start_auto_virtual_display(svc.clone());
     svc.sp
 }
 
+// Also without subscriber, the display has to be there before the first connection captures it.
+// The changes of the displays are sent by `run` as for the monitors. The display is removed when
+// the service stops.
+fn start_auto_virtual_display(sp: EmptyExtraFieldService) {
+    std::thread::spawn(move || {
+        while sp.active() {
+            crate::platform::virtual_display::check_auto();
+            std::thread::sleep(AUTO_VIRTUAL_DISPLAY_INTERVAL);
+        }
+        crate::platform::virtual_display::remove_auto();
+    });
+}
+
 fn displays_to_msg(displays: Vec<DisplayInfo>) -> Message {
     let mut pi = PeerInfo {
         ..Default::default()
     };
     pi.displays = displays.clone();
+    crate::display_id::add_to(&mut pi.platform_additions, &pi.displays);
 
     // current_display should not be used in server.
     // It is set to 0 for compatibility with old clients.
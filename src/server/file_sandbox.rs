@@ -0,0 +1,171 @@
+//! Sandbox roots for incoming file operations.
+//!
+//! When `OPTION_FILE_TRANSFER_SANDBOX_ROOTS` is set, every path a remote peer
+//! touches through the file service must resolve, after canonicalization, to a
+//! location inside one of the configured roots. `..` components and symlinks
+//! pointing outside the roots are rejected.
+//!
+//! The option is a `;` separated list of absolute directories. The built-in
+//! setting takes precedence over the local one, so that custom clients can
+//! enforce the roots without letting the local user widen them.
+//!
+//! `OPTION_FILE_TRANSFER_READ_ONLY` exposes the filesystem read-only: the peer can
+//! browse and download, but uploads, deletions, renames and new directories are
+//! rejected on this side whatever the controlling UI allows.
+
+use hbb_common::{bail, config::Config, log, ResultType};
+use std::path::{Component, Path, PathBuf};
+
+pub const OPTION_FILE_TRANSFER_SANDBOX_ROOTS: &str = "file-transfer-sandbox-roots";
+pub const OPTION_FILE_TRANSFER_READ_ONLY: &str = "file-transfer-read-only";
+
+const ROOTS_SEPARATOR: char = ';';
+
+/// Returns the canonicalized sandbox roots, or an empty list if the sandbox is disabled.
+///
+/// Roots that do not exist are skipped with a warning. If every configured root is
+/// invalid, a non-existent root is returned so that all operations are rejected
+/// instead of silently falling back to full access.
+pub fn get_roots() -> Vec<PathBuf> {
+    let mut option = crate::get_builtin_option(OPTION_FILE_TRANSFER_SANDBOX_ROOTS);
+    if option.trim().is_empty() {
+        option = Config::get_option(OPTION_FILE_TRANSFER_SANDBOX_ROOTS);
+    }
+    parse_roots(&option)
+}
+
+fn parse_roots(option: &str) -> Vec<PathBuf> {
+    let configured: Vec<&str> = option
+        .split(ROOTS_SEPARATOR)
+        .map(|s| s.trim())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if configured.is_empty() {
+        return vec![];
+    }
+    let roots: Vec<PathBuf> = configured
+        .iter()
+        .filter_map(|r| match std::fs::canonicalize(r) {
+            Ok(p) => Some(p),
+            Err(e) => {
+                log::warn!("Ignore invalid file transfer sandbox root {}: {}", r, e);
+                None
+            }
+        })
+        .collect();
+    if roots.is_empty() {
+        log::error!("No valid file transfer sandbox root, all file operations will be rejected");
+        return vec![PathBuf::from("/nonexistent-rustdesk-sandbox-root")];
+    }
+    roots
+}
+
+/// Built-in "Y" always wins, so a provisioned read-only profile can't be lifted locally.
+pub fn is_read_only() -> bool {
+    crate::get_builtin_option(OPTION_FILE_TRANSFER_READ_ONLY) == "Y"
+        || Config::get_bool_option(OPTION_FILE_TRANSFER_READ_ONLY)
+}
+
+/// The directory shown when the peer asks for the default (empty) path.
+pub fn default_dir() -> Option<String> {
+    get_roots().first().map(|p| p.to_string_lossy().to_string())
+}
+
+/// Resolve `path` the way the file service would and check that it stays inside
+/// one of `roots`.
+///
+/// The path does not need to exist. The longest existing ancestor is canonicalized
+/// (resolving symlinks), and the remaining components are appended literally,
+/// so creating `root/new_dir/file` is allowed while `root/link_to_etc/passwd` is not.
+pub fn check_path_in_roots(path: &str, roots: &[PathBuf]) -> ResultType<PathBuf> {
+    if roots.is_empty() {
+        return Ok(PathBuf::from(path));
+    }
+    if path.bytes().any(|b| b == 0) {
+        bail!("path contains null bytes");
+    }
+    let path = hbb_common::fs::get_path(path);
+    if !path.is_absolute() {
+        bail!("relative path is not allowed in sandbox");
+    }
+    if path.components().any(|c| c == Component::ParentDir) {
+        bail!("path traversal detected");
+    }
+    let resolved = resolve(&path)?;
+    if roots.iter().any(|r| resolved.starts_with(r)) {
+        Ok(resolved)
+    } else {
+        bail!("path is outside of the allowed directories")
+    }
+}
+
+#[inline]
+pub fn check_path(path: &str) -> ResultType<PathBuf> {
+    check_path_in_roots(path, &get_roots())
+}
+
+fn resolve(path: &Path) -> ResultType<PathBuf> {
+    let mut existing = path.to_path_buf();
+    let mut rest = vec![];
+    loop {
+        // `symlink_metadata` so that dangling symlinks are canonicalized (and fail) here,
+        // instead of being treated as a missing component.
+        if existing.symlink_metadata().is_ok() {
+            break;
+        }
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name.to_owned());
+                existing = parent.to_path_buf();
+            }
+            _ => bail!("failed to resolve path"),
+        }
+    }
+    let mut resolved = std::fs::canonicalize(&existing)?;
+    for name in rest.into_iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn sandbox_roots() {
+        let base = std::env::temp_dir().join("rustdesk_sandbox_test");
+        let _ = fs::remove_dir_all(&base);
+        let root = base.join("root");
+        let outside = base.join("outside");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        fs::write(outside.join("secret.txt"), b"secret").unwrap();
+
+        let roots = parse_roots(&format!("{};", root.to_string_lossy()));
+        assert_eq!(roots.len(), 1);
+        let p = |s: &Path| s.to_string_lossy().to_string();
+
+        assert!(check_path_in_roots(&p(&root), &roots).is_ok());
+        assert!(check_path_in_roots(&p(&root.join("sub")), &roots).is_ok());
+        assert!(check_path_in_roots(&p(&root.join("new/file.txt")), &roots).is_ok());
+        assert!(check_path_in_roots(&p(&outside), &roots).is_err());
+        assert!(check_path_in_roots(&p(&root.join("../outside")), &roots).is_err());
+        assert!(check_path_in_roots("relative/path", &roots).is_err());
+
+        #[cfg(unix)]
+        {
+            let link = root.join("link");
+            if std::os::unix::fs::symlink(&outside, &link).is_ok() {
+                assert!(check_path_in_roots(&p(&link), &roots).is_err());
+                assert!(check_path_in_roots(&p(&link.join("secret.txt")), &roots).is_err());
+                assert!(check_path_in_roots(&p(&link.join("new.txt")), &roots).is_err());
+            }
+        }
+
+        assert!(check_path_in_roots(&p(&outside), &[]).is_ok());
+        assert!(!parse_roots(&p(&base.join("missing"))).is_empty());
+        let _ = fs::remove_dir_all(&base);
+    }
+}
@@ -0,0 +1,188 @@
+//! Lowers the capture rate while the screen is static.
+//!
+//! Captured frames are compared with a hash of sampled rows. When nothing changed for
+//! `IDLE_AFTER`, the video service captures at `KEEPALIVE_SPF` only, which cuts the cpu
+//! usage of a session left connected overnight. It goes back to the normal rate as soon
+//! as a change is captured or a controller sends input.
+
+use hbb_common::{config::Config, log};
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::{Duration, Instant},
+};
+
+/// "N" to capture at the normal rate even if the screen is static.
+pub const OPTION_IDLE_CAPTURE: &str = "idle-capture";
+
+const IDLE_AFTER: Duration = Duration::from_secs(10);
+const KEEPALIVE_SPF: Duration = Duration::from_secs(1);
+// How often the wake flag is checked while idle.
+const WAKE_CHECK: Duration = Duration::from_millis(20);
+// Rows hashed in each frame, the offset rotates so that all rows are covered in `ROW_STEP` frames.
+const ROW_STEP: usize = 2;
+
+lazy_static::lazy_static! {
+    // The wake flags of the detectors, one for the capture of each display.
+    static ref WAKES: Mutex<Vec<Weak<AtomicBool>>> = Default::default();
+}
+
+/// Leave the idle mode now on all the displays, e.g. on input from a controller.
+pub fn wake() {
+    WAKES.lock().unwrap().retain(|w| match w.upgrade() {
+        Some(wake) => {
+            wake.store(true, Ordering::Relaxed);
+            true
+        }
+        None => false,
+    });
+}
+
+pub struct IdleDetector {
+    enabled: bool,
+    wake: Arc<AtomicBool>,
+    hashes: [Option<u64>; ROW_STEP],
+    frame_count: usize,
+    last_change: Instant,
+    idle: bool,
+}
+
+impl IdleDetector {
+    pub fn new() -> Self {
+        let wake = Arc::new(AtomicBool::new(false));
+        WAKES.lock().unwrap().push(Arc::downgrade(&wake));
+        Self {
+            enabled: Config::get_option(OPTION_IDLE_CAPTURE) != "N",
+            wake,
+            hashes: [None; ROW_STEP],
+            frame_count: 0,
+            last_change: Instant::now(),
+            idle: false,
+        }
+    }
+
+    /// Feed a captured frame.
+    pub fn on_frame(&mut self, data: &[u8], stride: usize, height: usize) {
+        if !self.enabled {
+            return;
+        }
+        let offset = self.frame_count % ROW_STEP;
+        self.frame_count += 1;
+        let hash = hash_rows(data, stride, height, offset);
+        if self.hashes[offset].replace(hash) != Some(hash) {
+            self.on_change();
+        } else {
+            self.check_idle();
+        }
+    }
+
+    /// The capturer reported that nothing changed.
+    pub fn on_no_change(&mut self) {
+        if self.enabled {
+            self.check_idle();
+        }
+    }
+
+    /// The frame can not be sampled, e.g. a texture.
+    pub fn on_unknown(&mut self) {
+        self.on_change();
+    }
+
+    #[inline]
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+
+    /// Wait for the next capture. While idle, it waits at least `KEEPALIVE_SPF` unless woken up.
+    pub fn wait(&mut self, spf: Duration, elapsed: Duration) {
+        if !self.idle {
+            if elapsed < spf {
+                std::thread::sleep(spf - elapsed);
+            }
+            return;
+        }
+        let deadline = Instant::now() + KEEPALIVE_SPF.max(spf).saturating_sub(elapsed);
+        while Instant::now() < deadline {
+            if self.wake.swap(false, Ordering::Relaxed) {
+                self.on_change();
+                return;
+            }
+            std::thread::sleep(WAKE_CHECK.min(deadline.saturating_duration_since(Instant::now())));
+        }
+    }
+
+    fn on_change(&mut self) {
+        if self.idle {
+            log::debug!("screen changed, leave idle capture");
+        }
+        self.idle = false;
+        self.last_change = Instant::now();
+    }
+
+    fn check_idle(&mut self) {
+        if !self.idle && self.last_change.elapsed() >= IDLE_AFTER {
+            log::debug!("screen is static, enter idle capture");
+            self.idle = true;
+            self.wake.store(false, Ordering::Relaxed);
+        }
+    }
+}
+
+// FNV-1a over 8-byte words of every `ROW_STEP` row starting at `offset`.
+fn hash_rows(data: &[u8], stride: usize, height: usize, offset: usize) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    if stride == 0 {
+        return hash;
+    }
+    for row in (offset..height).step_by(ROW_STEP) {
+        let Some(row) = data.get(row * stride..(row + 1) * stride) else {
+            break;
+        };
+        for word in row.chunks_exact(8) {
+            let mut w = [0u8; 8];
+            w.copy_from_slice(word);
+            hash ^= u64::from_ne_bytes(w);
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_detector() {
+        let (stride, height) = (64, 8);
+        let mut data = vec![0u8; stride * height];
+        let mut d = IdleDetector::new();
+        d.enabled = true;
+        let mut other = IdleDetector::new();
+        other.idle = true;
+        for _ in 0..4 {
+            d.on_frame(&data, stride, height);
+        }
+        assert!(!d.is_idle());
+        d.last_change -= IDLE_AFTER;
+        d.on_frame(&data, stride, height);
+        assert!(d.is_idle());
+        // A change in any row is seen within `ROW_STEP` frames.
+        data[stride * 5 + 3] = 1;
+        for _ in 0..ROW_STEP {
+            d.on_frame(&data, stride, height);
+        }
+        assert!(!d.is_idle());
+        d.last_change -= IDLE_AFTER;
+        d.on_no_change();
+        assert!(d.is_idle());
+        wake();
+        d.wait(Duration::from_millis(30), Duration::ZERO);
+        assert!(!d.is_idle());
+        // The wake is not taken by the capture of another display.
+        other.wait(Duration::from_millis(30), Duration::ZERO);
+        assert!(!other.is_idle());
+    }
+}
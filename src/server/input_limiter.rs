@@ -0,0 +1,220 @@
+//! Rate limit of the mouse and keyboard input of each connection.
+//!
+//! The events of a connection are counted in windows of a second. Above
+//! `OPTION_INPUT_MAX_EVENTS_PER_SEC` the rest of the window is dropped, and above
+//! `OPTION_INPUT_FLOOD_EVENTS_PER_SEC` the connection is flooding: it is logged and sent to the
+//! alarm audit once per window. The releases of the buttons and the keys always pass, so a drop
+//! never leaves one pressed on the host. An empty option takes the default, "0" disables the
+//! limit.
+//!
+//! The counters of a connection are sent with its close audit.
+
+use super::{AlarmAuditType, Connection};
+use hbb_common::{config::Config, log};
+use serde_derive::Serialize;
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const OPTION_INPUT_MAX_EVENTS_PER_SEC: &str = "input-max-events-per-sec";
+pub const OPTION_INPUT_FLOOD_EVENTS_PER_SEC: &str = "input-flood-events-per-sec";
+
+const DEFAULT_MAX_EVENTS_PER_SEC: u32 = 1_000;
+const DEFAULT_FLOOD_EVENTS_PER_SEC: u32 = 10_000;
+const WINDOW: Duration = Duration::from_secs(1);
+
+lazy_static::lazy_static! {
+    static ref LIMITERS: Mutex<HashMap<i32, Limiter>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// 0 is unlimited.
+    pub max: u32,
+    /// 0 is unlimited.
+    pub flood: u32,
+}
+
+fn get_limit(name: &str, default: u32) -> u32 {
+    let v = Config::get_option(name);
+    if v.trim().is_empty() {
+        return default;
+    }
+    v.trim().parse().unwrap_or(default)
+}
+
+impl Limits {
+    pub fn from_options() -> Self {
+        Self {
+            max: get_limit(OPTION_INPUT_MAX_EVENTS_PER_SEC, DEFAULT_MAX_EVENTS_PER_SEC),
+            flood: get_limit(
+                OPTION_INPUT_FLOOD_EVENTS_PER_SEC,
+                DEFAULT_FLOOD_EVENTS_PER_SEC,
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Counters {
+    pub accepted: u64,
+    pub dropped: u64,
+    /// The windows in which the connection was flooding.
+    pub floods: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Drop,
+    /// Dropped, and the first event above the flood limit of this window.
+    Flood,
+}
+
+#[derive(Debug)]
+pub struct Limiter {
+    limits: Limits,
+    start: Instant,
+    events: u32,
+    flooding: bool,
+    counters: Counters,
+}
+
+impl Limiter {
+    pub fn new(limits: Limits, now: Instant) -> Self {
+        Self {
+            limits,
+            start: now,
+            events: 0,
+            flooding: false,
+            counters: Counters::default(),
+        }
+    }
+
+    /// `limits` is called when a window starts, so a change of the options takes effect within
+    /// a second.
+    pub fn check(
+        &mut self,
+        now: Instant,
+        release: bool,
+        limits: impl FnOnce() -> Limits,
+    ) -> Verdict {
+        if now.saturating_duration_since(self.start) >= WINDOW {
+            self.limits = limits();
+            self.start = now;
+            self.events = 0;
+            self.flooding = false;
+        }
+        self.events = self.events.saturating_add(1);
+        let verdict = if self.limits.flood > 0 && self.events > self.limits.flood {
+            if self.flooding {
+                Verdict::Drop
+            } else {
+                self.flooding = true;
+                self.counters.floods += 1;
+                Verdict::Flood
+            }
+        } else if self.limits.max > 0 && self.events > self.limits.max {
+            Verdict::Drop
+        } else {
+            Verdict::Accept
+        };
+        if verdict == Verdict::Accept || release {
+            self.counters.accepted += 1;
+            Verdict::Accept
+        } else {
+            self.counters.dropped += 1;
+            verdict
+        }
+    }
+
+    pub fn counters(&self) -> Counters {
+        self.counters
+    }
+}
+
+/// Whether to inject an event of the connection. `conn` 0 is the input of the host itself,
+/// which is not limited.
+pub fn allow(conn: i32, release: bool) -> bool {
+    if conn == 0 {
+        return true;
+    }
+    let now = Instant::now();
+    let verdict = LIMITERS
+        .lock()
+        .unwrap()
+        .entry(conn)
+        .or_insert_with(|| Limiter::new(Limits::from_options(), now))
+        .check(now, release, Limits::from_options);
+    match verdict {
+        Verdict::Accept => true,
+        Verdict::Drop => false,
+        Verdict::Flood => {
+            let limits = Limits::from_options();
+            log::warn!(
+                "Input flood of connection {}, more than {} events per second",
+                conn,
+                limits.flood
+            );
+            Connection::post_alarm_audit(
+                AlarmAuditType::InputFlood,
+                json!({
+                    "conn_id": conn,
+                    "flood_events_per_sec": limits.flood,
+                }),
+            );
+            false
+        }
+    }
+}
+
+/// Remove the limiter of the connection, with its counters.
+pub fn remove(conn: i32) -> Option<Counters> {
+    LIMITERS.lock().unwrap().remove(&conn).map(|l| l.counters())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_limiter_windows() {
+        let limits = Limits { max: 3, flood: 5 };
+        let start = Instant::now();
+        let mut limiter = Limiter::new(limits, start);
+        let verdicts: Vec<_> = (0..7)
+            .map(|_| limiter.check(start, false, || limits))
+            .collect();
+        assert_eq!(
+            verdicts,
+            vec![
+                Verdict::Accept,
+                Verdict::Accept,
+                Verdict::Accept,
+                Verdict::Drop,
+                Verdict::Drop,
+                Verdict::Flood,
+                Verdict::Drop,
+            ]
+        );
+        // A release is never dropped.
+        assert_eq!(limiter.check(start, true, || limits), Verdict::Accept);
+        assert_eq!(
+            limiter.counters(),
+            Counters {
+                accepted: 4,
+                dropped: 4,
+                floods: 1,
+            }
+        );
+
+        // The next window takes the new limits.
+        let next = start + WINDOW;
+        let unlimited = Limits { max: 0, flood: 0 };
+        assert!((0..100).all(|_| limiter.check(next, false, || unlimited) == Verdict::Accept));
+        assert_eq!(limiter.counters().accepted, 104);
+    }
+}
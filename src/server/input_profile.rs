@@ -0,0 +1,363 @@
+//! Input permission profiles of the connections.
+//!
+//! The keyboard permission lets a peer send all of the mouse and the keyboard. A profile narrows
+//! it, per peer id, with `OPTION_INPUT_PROFILES`, a json like:
+//!
+//! ```json
+//! {
+//!     "peers": { "123456789": "mouse-only", "*": "kiosk" },
+//!     "profiles": { "kiosk": { "clipboard_shortcuts": false, "blocked_keys": ["F4", "Escape"] } }
+//! }
+//! ```
+//!
+//! "*" is the profile of the peers not listed. The built-in profiles are `FULL`, `MOUSE_ONLY`
+//! and `NO_CLIPBOARD_SHORTCUTS`, the ones of "profiles" may replace them. A key is named as in
+//! `ControlKey` ("Delete", "F4", "Meta") or by its character ("q"), the side of the modifiers is
+//! ignored. A peer with an unknown profile gets no input, rather than all of it.
+//!
+//! The profile is resolved when the connection is authorized and is enforced in `handle_key_`,
+//! `handle_mouse_`, and for the other input of the peers, the game controllers, the pens, the
+//! gestures and the `special_keys`. The releases always pass, so a change never leaves a key
+//! pressed.
+//!
+//! A profile may also have "on_connect" and "on_disconnect", the commands run on the host when
+//! the session starts and ends, see `session_hooks`.
+
+use hbb_common::{
+    config::Config,
+    log,
+    message_proto::{key_event, ControlKey, KeyEvent, KeyboardMode},
+};
+use serde_derive::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Mutex,
+};
+
+pub const OPTION_INPUT_PROFILES: &str = "input-profiles";
+pub const FULL: &str = "full";
+pub const MOUSE_ONLY: &str = "mouse-only";
+pub const NO_CLIPBOARD_SHORTCUTS: &str = "no-clipboard-shortcuts";
+const ANY_PEER: &str = "*";
+const SHORTCUT_MODIFIERS: [&str; 2] = ["control", "meta"];
+const CLIPBOARD_KEYS: [&str; 3] = ["c", "v", "x"];
+
+lazy_static::lazy_static! {
+    static ref STATES: Mutex<HashMap<i32, State>> = Default::default();
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub keyboard: bool,
+    pub mouse: bool,
+    pub clipboard_shortcuts: bool,
+    pub blocked_keys: Vec<String>,
+    pub on_connect: Vec<String>,
+    pub on_disconnect: Vec<String>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            keyboard: true,
+            mouse: true,
+            clipboard_shortcuts: true,
+            blocked_keys: vec![],
+            on_connect: vec![],
+            on_disconnect: vec![],
+        }
+    }
+}
+
+impl Profile {
+    fn builtin(name: &str) -> Option<Self> {
+        match name {
+            FULL => Some(Self::default()),
+            MOUSE_ONLY => Some(Self {
+                keyboard: false,
+                ..Default::default()
+            }),
+            NO_CLIPBOARD_SHORTCUTS => Some(Self {
+                clipboard_shortcuts: false,
+                ..Default::default()
+            }),
+            _ => None,
+        }
+    }
+
+    fn none() -> Self {
+        Self {
+            keyboard: false,
+            mouse: false,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Profiles {
+    peers: HashMap<String, String>,
+    profiles: HashMap<String, Profile>,
+}
+
+/// The name and the profile of the peer.
+fn resolve(option: &str, peer_id: &str) -> (String, Profile) {
+    if option.trim().is_empty() {
+        return (FULL.to_owned(), Profile::default());
+    }
+    let profiles: Profiles = match serde_json::from_str(option) {
+        Ok(p) => p,
+        Err(e) => {
+            log::error!(
+                "Invalid {}, no input is allowed: {}",
+                OPTION_INPUT_PROFILES,
+                e
+            );
+            return (String::new(), Profile::none());
+        }
+    };
+    let Some(name) = profiles
+        .peers
+        .get(peer_id)
+        .or_else(|| profiles.peers.get(ANY_PEER))
+    else {
+        return (FULL.to_owned(), Profile::default());
+    };
+    let mut profile = match profiles.profiles.get(name) {
+        Some(p) => p.clone(),
+        None => Profile::builtin(name).unwrap_or_else(|| {
+            log::error!("Unknown input profile {}, no input is allowed", name);
+            Profile::none()
+        }),
+    };
+    profile.blocked_keys = profile.blocked_keys.iter().map(|k| normalize(k)).collect();
+    (name.clone(), profile)
+}
+
+/// "KeyC", "ControlLeft" or "RControl" to "c" or "control".
+fn normalize(name: &str) -> String {
+    let mut name = name.trim().to_lowercase();
+    for suffix in ["left", "right"] {
+        if name.len() > suffix.len() && name.ends_with(suffix) {
+            name.truncate(name.len() - suffix.len());
+        }
+    }
+    for prefix in ["key", "num"] {
+        if name.len() == prefix.len() + 1 && name.starts_with(prefix) {
+            name = name[prefix.len()..].to_owned();
+        }
+    }
+    match name.as_str() {
+        "ctrl" | "rcontrol" => "control".to_owned(),
+        "ralt" | "altgr" | "option" => "alt".to_owned(),
+        "rshift" => "shift".to_owned(),
+        "rwin" | "command" | "cmd" | "super" => "meta".to_owned(),
+        _ => name,
+    }
+}
+
+fn key_name(evt: &KeyEvent) -> Option<String> {
+    let chr = |c: u32| char::from_u32(c).map(|c| c.to_lowercase().to_string());
+    match &evt.union {
+        Some(key_event::Union::ControlKey(ck)) => Some(normalize(&format!(
+            "{:?}",
+            ck.enum_value_or(ControlKey::Unknown)
+        ))),
+        Some(key_event::Union::Chr(code)) => {
+            if evt.mode.enum_value_or(KeyboardMode::Legacy) == KeyboardMode::Legacy {
+                chr(*code)
+            } else {
+                Some(normalize(&format!(
+                    "{:?}",
+                    crate::keyboard::keycode_to_rdev_key(*code)
+                )))
+            }
+        }
+        Some(key_event::Union::Unicode(code)) => chr(*code),
+        Some(key_event::Union::Seq(seq)) => Some(seq.to_lowercase()),
+        _ => None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    name: String,
+    profile: Profile,
+    /// The modifiers held, in map mode they come as keys of their own.
+    held: HashSet<String>,
+}
+
+impl State {
+    fn allow_key(&mut self, name: Option<String>, evt: &KeyEvent) -> bool {
+        let down = evt.down || evt.press;
+        if let Some(name) = name.as_ref() {
+            if SHORTCUT_MODIFIERS.contains(&name.as_str()) {
+                if evt.down {
+                    self.held.insert(name.clone());
+                } else if !evt.press {
+                    self.held.remove(name);
+                }
+            }
+        }
+        if !down {
+            return true;
+        }
+        if !self.profile.keyboard {
+            return false;
+        }
+        let Some(name) = name else {
+            return true;
+        };
+        if self.profile.blocked_keys.contains(&name) {
+            return false;
+        }
+        if !self.profile.clipboard_shortcuts && CLIPBOARD_KEYS.contains(&name.as_str()) {
+            let modifier = !self.held.is_empty()
+                || evt.modifiers.iter().any(|m| {
+                    let m = normalize(&format!("{:?}", m.enum_value_or(ControlKey::Unknown)));
+                    SHORTCUT_MODIFIERS.contains(&m.as_str())
+                });
+            if modifier {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Resolve the profile of the connection.
+pub fn set(conn: i32, peer_id: &str) -> (String, Profile) {
+    let (name, profile) = resolve(&Config::get_option(OPTION_INPUT_PROFILES), peer_id);
+    if !profile.is_full() {
+        log::info!(
+            "Input profile of connection {} from {}: {} {:?}",
+            conn,
+            peer_id,
+            name,
+            profile
+        );
+    }
+    STATES.lock().unwrap().insert(
+        conn,
+        State {
+            name: name.clone(),
+            profile: profile.clone(),
+            ..Default::default()
+        },
+    );
+    (name, profile)
+}
+
+/// The name and the profile of the connection, only the first time.
+pub fn remove(conn: i32) -> Option<(String, Profile)> {
+    STATES
+        .lock()
+        .unwrap()
+        .remove(&conn)
+        .map(|s| (s.name, s.profile))
+}
+
+pub fn allow_mouse(conn: i32, release: bool) -> bool {
+    release
+        || STATES
+            .lock()
+            .unwrap()
+            .get(&conn)
+            .map(|s| s.profile.mouse)
+            .unwrap_or(true)
+}
+
+/// The input of a device the host maps to the keys and the buttons, a game controller, allowed
+/// if the profile allows what it presses.
+pub fn allow_mapped(conn: i32, release: bool, keyboard: bool, mouse: bool) -> bool {
+    release
+        || STATES
+            .lock()
+            .unwrap()
+            .get(&conn)
+            .map(|s| (!keyboard || s.profile.keyboard) && (!mouse || s.profile.mouse))
+            .unwrap_or(true)
+}
+
+/// A key of the host pressed by its name, e.g. "volume_up" of `special_keys`.
+pub fn allow_named_key(conn: i32, name: &str) -> bool {
+    STATES
+        .lock()
+        .unwrap()
+        .get(&conn)
+        .map(|s| s.profile.keyboard && !s.profile.blocked_keys.contains(&normalize(name)))
+        .unwrap_or(true)
+}
+
+pub fn allow_key(conn: i32, evt: &KeyEvent) -> bool {
+    let mut states = STATES.lock().unwrap();
+    match states.get_mut(&conn) {
+        Some(state) => state.allow_key(key_name(evt), evt),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str, down: bool) -> (Option<String>, KeyEvent) {
+        let evt = KeyEvent {
+            down,
+            ..Default::default()
+        };
+        (Some(normalize(name)), evt)
+    }
+
+    #[test]
+    fn input_profile_keys() {
+        let option = r#"{"peers":{"1":"mouse-only","2":"kiosk","3":"typo","*":"no-clipboard-shortcuts"},"profiles":{"kiosk":{"blocked_keys":["F4","Escape"]}}}"#;
+        assert_eq!(resolve("", "1").1, Profile::default());
+        assert!(!resolve(option, "1").1.keyboard);
+        assert_eq!(resolve(option, "2").1.blocked_keys, vec!["f4", "escape"]);
+        assert_eq!(resolve(option, "3").1, Profile::none());
+        assert!(!resolve(option, "4").1.clipboard_shortcuts);
+        assert_eq!(resolve("{", "4").1, Profile::none());
+        let option = r#"{"peers":{"*":"rec"},"profiles":{"rec":{"on_connect":["rec start"]}}}"#;
+        let (name, profile) = resolve(option, "1");
+        assert_eq!(name, "rec");
+        assert!(profile.keyboard);
+        assert_eq!(profile.on_connect, vec!["rec start"]);
+        assert!(profile.on_disconnect.is_empty());
+
+        assert_eq!(normalize("KeyC"), "c");
+        assert_eq!(normalize("ControlLeft"), "control");
+        assert_eq!(normalize("RWin"), "meta");
+        assert_eq!(normalize("Num1"), "1");
+
+        let mut state = State {
+            profile: resolve(option, "2").1,
+            ..Default::default()
+        };
+        let (name, evt) = key("F4", true);
+        assert!(!state.allow_key(name, &evt));
+        let (name, evt) = key("F4", false);
+        assert!(state.allow_key(name, &evt));
+
+        let mut state = State {
+            profile: resolve(option, "4").1,
+            ..Default::default()
+        };
+        let (name, evt) = key("c", true);
+        assert!(state.allow_key(name, &evt));
+        let (name, evt) = key("MetaLeft", true);
+        assert!(state.allow_key(name, &evt));
+        let (name, evt) = key("c", true);
+        assert!(!state.allow_key(name, &evt));
+        let (name, evt) = key("MetaLeft", false);
+        assert!(state.allow_key(name, &evt));
+        let (name, evt) = key("v", true);
+        assert!(state.allow_key(name, &evt));
+    }
+}
@@ -1,12 +1,13 @@
 use super::*;
+use crate::gamepad::{GamepadState, Target};
 use crate::input::*;
+use crate::stylus::{Phase, StylusEvent};
+use crate::touch_gesture::{RotateEvent, Tracker};
 use crate::whiteboard;
 use dispatch::Queue;
 use enigo::{Enigo, Key, KeyboardControllable, MouseButton, MouseControllable};
 use hbb_common::{
-    get_time,
-    message_proto::{pointer_device_event::Union::TouchEvent, touch_event::Union::ScaleUpdate},
-    protobuf::EnumOrUnknown,
+    get_time, message_proto::pointer_device_event::Union::TouchEvent, protobuf::EnumOrUnknown,
 };
 use rdev::{self, EventType, Key as RdevKey, KeyCode, RawKey};
 use rdev::{CGEventSourceStateID, CGEventTapLocation, VirtualInput};
@@ -329,12 +330,21 @@ lazy_static::lazy_static! {
     static ref ENIGO: Arc<Mutex<Enigo>> = {
         Arc::new(Mutex::new(Enigo::new()))
     };
-    static ref KEYS_DOWN: Arc<Mutex<HashMap<KeysDown, Instant>>> = Default::default();
+    // The keys pressed by each connection, released when it closes or after a timeout.
+    static ref KEYS_DOWN: Arc<Mutex<HashMap<i32, HashMap<KeysDown, Instant>>>> = Default::default();
     static ref LATEST_PEER_INPUT_CURSOR: Arc<Mutex<Input>> = Default::default();
     static ref LATEST_SYS_CURSOR_POS: Arc<Mutex<(Option<Instant>, (i32, i32))>> = Arc::new(Mutex::new((None, (INVALID_CURSOR_POS, INVALID_CURSOR_POS))));
     // Track connections that are currently using relative mouse movement.
     // Used to disable whiteboard/cursor display for all events while in relative mode.
     static ref RELATIVE_MOUSE_CONNS: Arc<Mutex<std::collections::HashSet<i32>>> = Default::default();
+    // The last state of the game controllers, by (conn id, controller index).
+    static ref GAMEPADS: Arc<Mutex<HashMap<(i32, u8), GamepadState>>> = Default::default();
+    // The stylus of each connection: (eraser, pressed), absent when out of range.
+    static ref STYLUS: Arc<Mutex<HashMap<i32, (bool, bool)>>> = Default::default();
+    // The touch gestures in progress of each connection.
+    static ref GESTURES: Arc<Mutex<HashMap<i32, Tracker>>> = Default::default();
+    // The connections whose pointer draws a laser trail on the whiteboard.
+    static ref LASER_CONNS: Arc<Mutex<std::collections::HashSet<i32>>> = Default::default();
 }
 
 #[inline]
@@ -362,6 +372,16 @@ pub(crate) fn clear_relative_mouse_active(conn: i32) {
     set_relative_mouse_active(conn, false);
 }
 
+/// Draw a fading trail following the pointer of the connection on the whiteboard, or stop.
+pub fn set_laser(conn: i32, on: bool) {
+    let mut lock = LASER_CONNS.lock().unwrap();
+    if on {
+        lock.insert(conn);
+    } else {
+        lock.remove(&conn);
+    }
+}
+
 static EXITING: AtomicBool = AtomicBool::new(false);
 
 const MOUSE_MOVE_PROTECTION_TIMEOUT: Duration = Duration::from_millis(1_000);
@@ -532,6 +552,79 @@ pub fn handle_pointer(evt: &PointerDeviceEvent, conn: i32) {
     QUEUE.exec_async(move || handle_pointer_(&evt, conn));
 }
 
+pub fn handle_gamepad(state: GamepadState, conn: i32) {
+    QUEUE.exec_async(move || handle_gamepad_(state, conn));
+}
+
+/// Release what the game controllers of the connection hold down.
+pub fn reset_gamepads(conn: i32) {
+    QUEUE.exec_async(move || {
+        let states: Vec<GamepadState> = {
+            let mut gamepads = GAMEPADS.lock().unwrap();
+            let states = gamepads
+                .iter()
+                .filter(|((id, _), _)| *id == conn)
+                .map(|(_, s)| *s)
+                .collect();
+            gamepads.retain(|(id, _), _| *id != conn);
+            states
+        };
+        let mut en = ENIGO.lock().unwrap();
+        for state in states {
+            apply_gamepad_changes(&mut en, &state, &GamepadState::default());
+        }
+    });
+}
+
+fn apply_gamepad_changes(en: &mut Enigo, old: &GamepadState, new: &GamepadState) {
+    for (target, down) in crate::gamepad::changes(old, new) {
+        match (target, down) {
+            (Target::Key(key), true) => {
+                en.key_down(key).ok();
+            }
+            (Target::Key(key), false) => en.key_up(key),
+            (Target::Mouse(button), true) => {
+                en.mouse_down(button).ok();
+            }
+            (Target::Mouse(button), false) => en.mouse_up(button),
+        }
+    }
+}
+
+fn handle_gamepad_(state: GamepadState, conn: i32) {
+    if EXITING.load(Ordering::SeqCst) {
+        return;
+    }
+    let mut gamepads = GAMEPADS.lock().unwrap();
+    let old = gamepads
+        .get(&(conn, state.index))
+        .copied()
+        .unwrap_or_default();
+    let changes = crate::gamepad::changes(&old, &state);
+    let (dx, dy) = crate::gamepad::mouse_delta(&state);
+    let moves = dx != 0 || dy != 0;
+    let presses = |mouse| {
+        changes
+            .iter()
+            .any(|(t, down)| *down && matches!(t, Target::Mouse(_)) == mouse)
+    };
+    // Releasing what is held is never refused.
+    let release = !moves && !changes.iter().any(|(_, down)| *down);
+    let (keyboard, mouse) = (presses(false), moves || presses(true));
+    if !super::input_limiter::allow(conn, release)
+        || !super::input_profile::allow_mapped(conn, release, keyboard, mouse)
+    {
+        return;
+    }
+    gamepads.insert((conn, state.index), state);
+    drop(gamepads);
+    let mut en = ENIGO.lock().unwrap();
+    apply_gamepad_changes(&mut en, &old, &state);
+    if moves {
+        en.mouse_move_relative(dx, dy);
+    }
+}
+
 pub fn fix_key_down_timeout_loop() {
     std::thread::spawn(move || loop {
         std::thread::sleep(std::time::Duration::from_millis(10_000));
@@ -619,14 +712,37 @@ fn fix_key_down_timeout(force: bool) {
     let cloned = (*key_down).clone();
     drop(key_down);
 
-    for (record_key, time) in cloned.into_iter() {
-        if force || time.elapsed().as_millis() >= 360_000 {
-            record_pressed_key(record_key, false);
-            release_record_key(record_key);
+    for (conn, keys) in cloned.into_iter() {
+        for (record_key, time) in keys.into_iter() {
+            if force || time.elapsed().as_millis() >= 360_000 {
+                record_pressed_key(conn, record_key, false);
+                release_record_key(record_key);
+            }
         }
     }
 }
 
+/// Release the keys the connection left pressed, except the ones another connection holds too.
+pub fn release_keys(conn: i32) {
+    QUEUE.exec_async(move || {
+        let released: Vec<KeysDown> = {
+            let mut key_down = KEYS_DOWN.lock().unwrap();
+            let Some(keys) = key_down.remove(&conn) else {
+                return;
+            };
+            keys.into_keys()
+                .filter(|k| !key_down.values().any(|held| held.contains_key(k)))
+                .collect()
+        };
+        if !released.is_empty() {
+            log::info!("Release {} keys of connection {}", released.len(), conn);
+        }
+        for record_key in released {
+            release_record_key(record_key);
+        }
+    });
+}
+
 // e.g. current state of ctrl is down, but ctrl not in modifier, we should change ctrl to up, to make modifier state sync between remote and local
 #[inline]
 fn fix_modifier(
@@ -752,11 +868,151 @@ pub fn handle_pointer_(evt: &PointerDeviceEvent, conn: i32) {
     }
 
     match &evt.union {
-        Some(TouchEvent(_evt)) => {}
+        Some(TouchEvent(evt)) => {
+            let Some(evt) = evt.union.as_ref() else {
+                return;
+            };
+            let gesture = GESTURES
+                .lock()
+                .unwrap()
+                .entry(conn)
+                .or_default()
+                .on_touch(evt);
+            if let Some((gesture, phase)) = gesture {
+                allow_err!(crate::platform::macos::post_gesture(gesture, phase));
+            }
+        }
         _ => {}
     }
 }
 
+pub fn handle_touch_rotate(evt: RotateEvent, conn: i32) {
+    QUEUE.exec_async(move || handle_touch_rotate_(evt, conn));
+}
+
+fn handle_touch_rotate_(evt: RotateEvent, conn: i32) {
+    if !active_mouse_(conn) {
+        return;
+    }
+    if EXITING.load(Ordering::SeqCst) {
+        return;
+    }
+    let release = evt.rotation == 0;
+    if !super::input_limiter::allow(conn, release)
+        || !super::input_profile::allow_mouse(conn, release)
+    {
+        return;
+    }
+    let gesture = GESTURES
+        .lock()
+        .unwrap()
+        .entry(conn)
+        .or_default()
+        .on_rotate(&evt);
+    if let Some((gesture, phase)) = gesture {
+        allow_err!(crate::platform::macos::post_gesture(gesture, phase));
+    }
+}
+
+/// End the touch gestures left in progress by the connection.
+pub fn reset_gestures(conn: i32) {
+    QUEUE.exec_async(move || {
+        if let Some(mut tracker) = GESTURES.lock().unwrap().remove(&conn) {
+            for (gesture, phase) in tracker.end_all() {
+                allow_err!(crate::platform::macos::post_gesture(gesture, phase));
+            }
+        }
+    });
+}
+
+pub fn handle_stylus(evt: StylusEvent, conn: i32) {
+    QUEUE.exec_async(move || handle_stylus_(evt, conn));
+}
+
+// The pointer events of a pen, on the main thread like `handle_pointer_`.
+fn handle_stylus_(evt: StylusEvent, conn: i32) {
+    if !active_mouse_(conn) {
+        return;
+    }
+    if EXITING.load(Ordering::SeqCst) {
+        return;
+    }
+    let release = matches!(evt.phase, Phase::Up | Phase::Leave);
+    if !super::input_limiter::allow(conn, release)
+        || !super::input_profile::allow_mouse(conn, release)
+    {
+        return;
+    }
+    let mut stylus = STYLUS.lock().unwrap();
+    let state = stylus.get(&conn).copied();
+    if evt.phase == Phase::Leave {
+        if let Some((eraser, pressed)) = state {
+            if pressed {
+                allow_err!(crate::platform::macos::post_tablet_point(
+                    evt.x,
+                    evt.y,
+                    0.,
+                    (0., 0.),
+                    false,
+                    true,
+                    true
+                ));
+            }
+            allow_err!(crate::platform::macos::post_tablet_proximity(false, eraser));
+            stylus.remove(&conn);
+        }
+        return;
+    }
+    let (eraser, mut pressed) = state.unwrap_or((evt.eraser, false));
+    if state.is_none() || eraser != evt.eraser {
+        if state.is_some() {
+            allow_err!(crate::platform::macos::post_tablet_proximity(false, eraser));
+        }
+        allow_err!(crate::platform::macos::post_tablet_proximity(
+            true, evt.eraser
+        ));
+    }
+    let down = evt.phase == Phase::Down && !pressed;
+    let up = evt.phase == Phase::Up && pressed;
+    allow_err!(crate::platform::macos::post_tablet_point(
+        evt.x,
+        evt.y,
+        evt.pressure,
+        (evt.tilt_x, evt.tilt_y),
+        down,
+        up,
+        pressed
+    ));
+    if down {
+        pressed = true;
+    } else if up {
+        pressed = false;
+    }
+    stylus.insert(conn, (evt.eraser, pressed));
+    *LATEST_PEER_INPUT_CURSOR.lock().unwrap() = Input {
+        conn,
+        time: get_time(),
+        x: evt.x,
+        y: evt.y,
+    };
+}
+
+/// Lift the pen of the connection and take it out of the range of the tablet.
+pub fn reset_stylus(conn: i32) {
+    if STYLUS.lock().unwrap().contains_key(&conn) {
+        let (x, y) = get_last_input_cursor_pos();
+        handle_stylus(
+            StylusEvent {
+                phase: Phase::Leave,
+                x,
+                y,
+                ..Default::default()
+            },
+            conn,
+        );
+    }
+}
+
 pub fn handle_mouse_(
     evt: &MouseEvent,
     conn: i32,
@@ -765,6 +1021,12 @@ pub fn handle_mouse_(
     simulate: bool,
     _show_cursor: bool,
 ) {
+    let release = evt.mask & MOUSE_TYPE_MASK == MOUSE_TYPE_UP;
+    if !super::input_limiter::allow(conn, release)
+        || !super::input_profile::allow_mouse(conn, release)
+    {
+        return;
+    }
     if simulate {
         handle_mouse_simulation_(evt, conn);
     }
@@ -778,6 +1040,12 @@ pub fn handle_mouse_(
         if _show_cursor && !is_relative_mouse_active(conn) && evt_type != MOUSE_TYPE_MOVE_RELATIVE {
             handle_mouse_show_cursor_(evt, conn, _username, _argb);
         }
+        if evt_type == MOUSE_TYPE_MOVE && LASER_CONNS.lock().unwrap().contains(&conn) {
+            whiteboard::update_whiteboard(
+                whiteboard::get_key_annotation(conn),
+                whiteboard::CustomEvent::Laser(evt.x as _, evt.y as _),
+            );
+        }
     }
 }
 
@@ -807,12 +1075,13 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
         MOUSE_TYPE_MOVE => {
             // Switching back to absolute movement implicitly disables relative mouse mode.
             set_relative_mouse_active(conn, false);
-            en.mouse_move_to(evt.x, evt.y);
+            let (x, y) = crate::mouse_curve::absolute(conn, evt.x, evt.y, crate::get_cursor_pos);
+            en.mouse_move_to(x, y);
             *LATEST_PEER_INPUT_CURSOR.lock().unwrap() = Input {
                 conn,
                 time: get_time(),
-                x: evt.x,
-                y: evt.y,
+                x,
+                y,
             };
         }
         // MOUSE_TYPE_MOVE_RELATIVE: Relative mouse movement for gaming/3D applications.
@@ -826,6 +1095,7 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
             const MAX_RELATIVE_MOUSE_DELTA: i32 = 10000;
             let dx = evt.x.clamp(-MAX_RELATIVE_MOUSE_DELTA, MAX_RELATIVE_MOUSE_DELTA);
             let dy = evt.y.clamp(-MAX_RELATIVE_MOUSE_DELTA, MAX_RELATIVE_MOUSE_DELTA);
+            let (dx, dy) = crate::mouse_curve::relative(conn, dx, dy);
             en.mouse_move_relative(dx, dy);
             // Get actual cursor position after relative movement for tracking
             if let Some((x, y)) = crate::get_cursor_pos() {
@@ -874,10 +1144,16 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
             _ => {}
         },
         MOUSE_TYPE_WHEEL | MOUSE_TYPE_TRACKPAD => {
-            let mut x = -evt.x;
-            let mut y = -evt.y;
-
             let is_track_pad = evt_type == MOUSE_TYPE_TRACKPAD;
+            let scroll = crate::scroll_transform::transform(conn, evt.x, evt.y, is_track_pad);
+            let Some(scroll) = scroll else {
+                return;
+            };
+            if is_track_pad && crate::scroll_transform::start_glide(conn) {
+                start_scroll_glide(conn);
+            }
+            let mut x = -scroll.x;
+            let mut y = -scroll.y;
 
             // fix shift + scroll(down/up)
             if !is_track_pad
@@ -890,16 +1166,52 @@ pub fn handle_mouse_simulation_(evt: &MouseEvent, conn: i32) {
             }
 
             if x != 0 {
-                en.mouse_scroll_x(x, is_track_pad);
+                en.mouse_scroll_x(x, scroll.pixel);
             }
             if y != 0 {
-                en.mouse_scroll_y(y, is_track_pad);
+                en.mouse_scroll_y(y, scroll.pixel);
+            }
+        }
+        // Posted as is, without the notches and the shift swap of MOUSE_TYPE_WHEEL, so the
+        // applications in pointer lock get the deltas of the wheel of the client.
+        MOUSE_TYPE_WHEEL_PRECISE => {
+            const MAX_WHEEL_DELTA: i32 = 10000;
+            let x = evt.x.clamp(-MAX_WHEEL_DELTA, MAX_WHEEL_DELTA);
+            let y = evt.y.clamp(-MAX_WHEEL_DELTA, MAX_WHEEL_DELTA);
+            if x != 0 || y != 0 {
+                en.mouse_scroll_pixels(x, y);
             }
         }
         _ => {}
     }
 }
 
+// Post the momentum of the trackpad scrolling of the connection, until it stops gliding.
+fn start_scroll_glide(conn: i32) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(crate::scroll_transform::GLIDE_INTERVAL);
+        let scroll = match crate::scroll_transform::glide(conn) {
+            Some(Some(scroll)) => scroll,
+            Some(None) => continue,
+            None => break,
+        };
+        if !active_mouse_(conn) || EXITING.load(Ordering::SeqCst) {
+            continue;
+        }
+        let mut en = ENIGO.lock().unwrap();
+        if scroll.pixel {
+            en.mouse_scroll_pixels(scroll.x, scroll.y);
+        } else {
+            if scroll.x != 0 {
+                en.mouse_scroll_x(-scroll.x, false);
+            }
+            if scroll.y != 0 {
+                en.mouse_scroll_y(-scroll.y, false);
+            }
+        }
+    });
+}
+
 pub fn handle_mouse_show_cursor_(evt: &MouseEvent, conn: i32, username: String, argb: u32) {
     let buttons = evt.mask >> 3;
     let evt_type = evt.mask & MOUSE_TYPE_MASK;
@@ -966,17 +1278,17 @@ pub async fn lock_screen() {
         key_event.mode = KeyboardMode::Legacy.into();
 
         key_event.down = true;
-        handle_key(&key_event);
+        handle_key(&key_event, 0);
         key_event.down = false;
-        handle_key(&key_event);
+        handle_key(&key_event, 0);
     });
 }
 
 #[inline]
-pub fn handle_key(evt: &KeyEvent) {
+pub fn handle_key(evt: &KeyEvent, conn: i32) {
     // having GUI, run main GUI thread, otherwise crash
     let evt = evt.clone();
-    QUEUE.exec_async(move || handle_key_(&evt));
+    QUEUE.exec_async(move || handle_key_(&evt, conn));
     // Key sleep is required for macOS.
     // If we don't sleep, the key press/release events may not take effect.
     //
@@ -999,11 +1311,11 @@ pub fn reset_input_ondisconn() {
     QUEUE.exec_async(reset_input);
 }
 
-fn sim_rdev_rawkey_position(code: KeyCode, keydown: bool) {
+fn sim_rdev_rawkey_position(conn: i32, code: KeyCode, keydown: bool) {
     let rawkey = RawKey::MacVirtualKeycode(code);
 
     // map mode(1): Send keycode according to the peer platform.
-    record_pressed_key(KeysDown::RdevKey(rawkey), keydown);
+    record_pressed_key(conn, KeysDown::RdevKey(rawkey), keydown);
 
     let event_type = if keydown {
         EventType::KeyPress(RdevKey::RawKey(rawkey))
@@ -1061,8 +1373,8 @@ fn char_value_to_key(value: u32) -> Key {
     Key::Layout(std::char::from_u32(value).unwrap_or('\0'))
 }
 
-fn map_keyboard_mode(evt: &KeyEvent) {
-    sim_rdev_rawkey_position(evt.chr() as _, evt.down);
+fn map_keyboard_mode(evt: &KeyEvent, conn: i32) {
+    sim_rdev_rawkey_position(conn, evt.chr() as _, evt.down);
 }
 
 fn add_flags_to_enigo(en: &mut Enigo, key_event: &KeyEvent) {
@@ -1141,12 +1453,19 @@ fn process_seq(en: &mut Enigo, sequence: &str) {
     en.key_sequence(&sequence);
 }
 
-fn record_pressed_key(record_key: KeysDown, down: bool) {
+fn record_pressed_key(conn: i32, record_key: KeysDown, down: bool) {
     let mut key_down = KEYS_DOWN.lock().unwrap();
     if down {
-        key_down.insert(record_key, Instant::now());
+        key_down
+            .entry(conn)
+            .or_default()
+            .insert(record_key, Instant::now());
     } else {
-        key_down.remove(&record_key);
+        // The key is up on the host, whichever connection pressed it.
+        key_down.retain(|_, keys| {
+            keys.remove(&record_key);
+            !keys.is_empty()
+        });
     }
 }
 
@@ -1163,7 +1482,7 @@ fn is_function_key(ck: &EnumOrUnknown<ControlKey>) -> bool {
     return res;
 }
 
-fn legacy_keyboard_mode(evt: &KeyEvent) {
+fn legacy_keyboard_mode(evt: &KeyEvent, conn: i32) {
     let mut to_release: Vec<Key> = Vec::new();
 
     let mut en = ENIGO.lock().unwrap();
@@ -1176,12 +1495,12 @@ fn legacy_keyboard_mode(evt: &KeyEvent) {
                 return;
             }
             let record_key = ck.value() as u64;
-            record_pressed_key(KeysDown::EnigoKey(record_key), down);
+            record_pressed_key(conn, KeysDown::EnigoKey(record_key), down);
             process_control_key(&mut en, &ck, down)
         }
         Some(key_event::Union::Chr(chr)) => {
             let record_key = chr as u64 + KEY_CHAR_START;
-            record_pressed_key(KeysDown::EnigoKey(record_key), down);
+            record_pressed_key(conn, KeysDown::EnigoKey(record_key), down);
             process_chr(&mut en, chr, down)
         }
         Some(key_event::Union::Unicode(chr)) => process_unicode(&mut en, chr),
@@ -1191,14 +1510,14 @@ fn legacy_keyboard_mode(evt: &KeyEvent) {
 
 }
 
-fn translate_keyboard_mode(evt: &KeyEvent) {
+fn translate_keyboard_mode(evt: &KeyEvent, conn: i32) {
     match &evt.union {
         Some(key_event::Union::Seq(seq)) => {
             let mut en = ENIGO.lock().unwrap();
             en.key_sequence(seq);
         }
         Some(key_event::Union::Chr(..)) => {
-            sim_rdev_rawkey_position(evt.chr() as _, evt.down);
+            sim_rdev_rawkey_position(conn, evt.chr() as _, evt.down);
         }
         Some(key_event::Union::Unicode(..)) => {
             // Do not handle unicode for now.
@@ -1222,10 +1541,19 @@ fn is_legacy_mode(evt: &KeyEvent) -> bool {
     evt.mode.enum_value_or(KeyboardMode::Legacy) == KeyboardMode::Legacy
 }
 
-pub fn handle_key_(evt: &KeyEvent) {
+pub fn handle_key_(evt: &KeyEvent, conn: i32) {
     if EXITING.load(Ordering::SeqCst) {
         return;
     }
+    if !super::input_limiter::allow(conn, !evt.down && !evt.press)
+        || !super::input_profile::allow_key(conn, evt)
+    {
+        return;
+    }
+    if evt.down || evt.press {
+        // A copy of the peer, let the clipboard watcher see it soon.
+        crate::clipboard::watcher::wake();
+    }
 
     let mut _lock_mode_handler = None;
     match &evt.union {
@@ -1256,15 +1584,15 @@ pub fn handle_key_(evt: &KeyEvent) {
     match evt.mode.enum_value() {
         Ok(KeyboardMode::Map) => {
             set_last_legacy_mode(false);
-            map_keyboard_mode(evt);
+            map_keyboard_mode(evt, conn);
         }
         Ok(KeyboardMode::Translate) => {
             set_last_legacy_mode(false);
-            translate_keyboard_mode(evt);
+            translate_keyboard_mode(evt, conn);
         }
         _ => {
             set_last_legacy_mode(true);
-            legacy_keyboard_mode(evt);
+            legacy_keyboard_mode(evt, conn);
         }
     }
 }
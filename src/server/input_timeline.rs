@@ -0,0 +1,458 @@
+//! Timeline of the input of the remote control sessions, for a review after an incident without
+//! a video recording.
+//!
+//! With `OPTION_INPUT_TIMELINE` on, each remote control session writes its clicks with their
+//! position, the category of its keys, never the key itself, and the title of the window in front
+//! when it changes, to `<start>_<conn id>.itl` in the `DIR` of the config. Each line is a `Record`
+//! sealed with the secretbox key of `secret_store::Secret::InputTimelineKey`, kept in the keystore
+//! or in a file of its own readable by its owner only, so the timeline is of no use without it. The files older than `OPTION_INPUT_TIMELINE_MAX_AGE_DAYS` are removed, 30 days
+//! by default, 0 keeps them. `rustdesk --input-timeline export|clear` reads or removes them, through
+//! the server, the process which has the key.
+
+use crate::{
+    input::*,
+    secret_store::{self, Secret},
+};
+use hbb_common::{
+    bail,
+    config::Config,
+    get_time, log,
+    message_proto::{key_event, ControlKey, KeyEvent, MouseEvent},
+    sodiumoxide::crypto::secretbox,
+    ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::PathBuf,
+    sync::Once,
+    time::{Duration, Instant, SystemTime},
+};
+
+pub const OPTION_INPUT_TIMELINE: &str = "enable-input-timeline";
+pub const OPTION_INPUT_TIMELINE_MAX_AGE_DAYS: &str = "input-timeline-max-age-days";
+
+const DIR: &str = "input_timeline";
+const EXT: &str = ".itl";
+const DEFAULT_MAX_AGE_DAYS: u64 = 30;
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+// The window in front is checked with the keys at most this often, and with each click.
+const WINDOW_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+static START: Once = Once::new();
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCategory {
+    /// A letter, digit, symbol or space, also typed as text.
+    Character,
+    /// A character with Control, Option or Command.
+    Shortcut,
+    Modifier,
+    Function,
+    Navigation,
+    /// Return, Tab, Backspace, Delete and Escape.
+    Editing,
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    Start { peer_id: String, name: String },
+    Click { button: String, x: i32, y: i32 },
+    Key { category: KeyCategory },
+    Window { app: String, title: String },
+    End,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    /// Milliseconds since the epoch.
+    pub time: i64,
+    /// The session id of the client, the same for the connections of one session.
+    pub session: u64,
+    pub conn_id: i32,
+    #[serde(flatten)]
+    pub event: Event,
+}
+
+fn is_enabled() -> bool {
+    Config::get_bool_option(OPTION_INPUT_TIMELINE)
+}
+
+fn max_age() -> Option<Duration> {
+    let days = Config::get_option(OPTION_INPUT_TIMELINE_MAX_AGE_DAYS)
+        .trim()
+        .parse::<u64>()
+        .unwrap_or(DEFAULT_MAX_AGE_DAYS);
+    (days > 0).then(|| Duration::from_secs(days * 86400))
+}
+
+/// The key of the timeline, made on the first use.
+fn key() -> ResultType<secretbox::Key> {
+    let stored = secret_store::get(Secret::InputTimelineKey);
+    if !stored.is_empty() {
+        if let Some(key) = crate::decode64(&stored)
+            .ok()
+            .and_then(|k| secretbox::Key::from_slice(&k))
+        {
+            return Ok(key);
+        }
+        bail!("Invalid key of the input timeline");
+    }
+    let key = secretbox::gen_key();
+    secret_store::set(Secret::InputTimelineKey, &crate::encode64(&key.0));
+    Ok(key)
+}
+
+fn seal(key: &secretbox::Key, record: &Record) -> String {
+    let nonce = secretbox::gen_nonce();
+    let mut v = nonce.0.to_vec();
+    v.extend(secretbox::seal(
+        &serde_json::to_vec(record).unwrap_or_default(),
+        &nonce,
+        key,
+    ));
+    crate::encode64(v)
+}
+
+fn open(key: &secretbox::Key, line: &str) -> Option<Record> {
+    let v = crate::decode64(line.trim()).ok()?;
+    if v.len() < secretbox::NONCEBYTES {
+        return None;
+    }
+    let nonce = secretbox::Nonce::from_slice(&v[..secretbox::NONCEBYTES])?;
+    let plain = secretbox::open(&v[secretbox::NONCEBYTES..], &nonce, key).ok()?;
+    serde_json::from_slice(&plain).ok()
+}
+
+/// The files of the sessions, by their start in seconds, oldest first.
+fn files() -> Vec<(i64, PathBuf)> {
+    let mut v: Vec<(i64, PathBuf)> = fs::read_dir(Config::path(DIR))
+        .map(|dir| {
+            dir.filter_map(|e| e.ok())
+                .filter_map(|e| {
+                    let name = e.file_name().to_string_lossy().to_string();
+                    let start = name.strip_suffix(EXT)?.split('_').next()?.parse().ok()?;
+                    Some((start, e.path()))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    v.sort();
+    v
+}
+
+/// The files last written more than `max_age` ago.
+fn select_files_to_prune(
+    files: Vec<(PathBuf, SystemTime)>,
+    now: SystemTime,
+    max_age: Duration,
+) -> Vec<PathBuf> {
+    files
+        .into_iter()
+        .filter(|(_, modified)| now.duration_since(*modified).unwrap_or_default() > max_age)
+        .map(|(path, _)| path)
+        .collect()
+}
+
+fn prune() {
+    let Some(max_age) = max_age() else {
+        return;
+    };
+    let files = files()
+        .into_iter()
+        .filter_map(|(_, path)| {
+            let modified = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+            Some((path, modified))
+        })
+        .collect();
+    for path in select_files_to_prune(files, SystemTime::now(), max_age) {
+        match fs::remove_file(&path) {
+            Ok(_) => log::info!("Pruned input timeline {}", path.display()),
+            Err(e) => log::error!("Failed to prune {}: {}", path.display(), e),
+        }
+    }
+}
+
+/// Start the pruning task once, it keeps running for the lifetime of the server.
+fn start_pruning() {
+    START.call_once(|| {
+        std::thread::spawn(|| loop {
+            prune();
+            std::thread::sleep(PRUNE_INTERVAL);
+        });
+    });
+}
+
+fn control_key_category(ck: ControlKey) -> KeyCategory {
+    use ControlKey::*;
+    match ck {
+        Alt | Control | Meta | Shift | RAlt | RControl | RShift | RWin | CapsLock | NumLock => {
+            KeyCategory::Modifier
+        }
+        F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 => KeyCategory::Function,
+        LeftArrow | RightArrow | UpArrow | DownArrow | Home | End | PageUp | PageDown => {
+            KeyCategory::Navigation
+        }
+        Return | NumpadEnter | Tab | Backspace | Delete | Escape => KeyCategory::Editing,
+        Space | Numpad0 | Numpad1 | Numpad2 | Numpad3 | Numpad4 | Numpad5 | Numpad6 | Numpad7
+        | Numpad8 | Numpad9 | Multiply | Add | Subtract | Decimal | Divide | Equals => {
+            KeyCategory::Character
+        }
+        _ => KeyCategory::Other,
+    }
+}
+
+fn rdev_key_category(key: rdev::Key) -> KeyCategory {
+    use rdev::Key::*;
+    if crate::keyboard::is_modifier(&key) {
+        return KeyCategory::Modifier;
+    }
+    match key {
+        F1 | F2 | F3 | F4 | F5 | F6 | F7 | F8 | F9 | F10 | F11 | F12 => KeyCategory::Function,
+        LeftArrow | RightArrow | UpArrow | DownArrow | Home | End | PageUp | PageDown => {
+            KeyCategory::Navigation
+        }
+        Return | KpReturn | Tab | Backspace | Delete | Escape => KeyCategory::Editing,
+        CapsLock | NumLock => KeyCategory::Modifier,
+        Unknown(_) => KeyCategory::Other,
+        _ => KeyCategory::Character,
+    }
+}
+
+/// The category of a pressed key, `shortcut` if Control, Option or Command are down.
+pub fn key_category(ke: &KeyEvent, shortcut: bool) -> KeyCategory {
+    let shortcut = shortcut
+        || ke.modifiers.iter().any(|m| {
+            matches!(
+                m.enum_value(),
+                Ok(ControlKey::Control
+                    | ControlKey::RControl
+                    | ControlKey::Alt
+                    | ControlKey::RAlt
+                    | ControlKey::Meta
+                    | ControlKey::RWin)
+            )
+        });
+    let category = match &ke.union {
+        Some(key_event::Union::ControlKey(ck)) => ck
+            .enum_value()
+            .map(control_key_category)
+            .unwrap_or(KeyCategory::Other),
+        Some(key_event::Union::Chr(code)) => {
+            rdev_key_category(crate::keyboard::keycode_to_rdev_key(code & 0x0000FFFF))
+        }
+        Some(key_event::Union::Unicode(_)) | Some(key_event::Union::Seq(_)) => {
+            KeyCategory::Character
+        }
+        _ => KeyCategory::Other,
+    };
+    if category == KeyCategory::Character && shortcut {
+        KeyCategory::Shortcut
+    } else {
+        category
+    }
+}
+
+fn button_name(buttons: i32) -> &'static str {
+    match buttons {
+        MOUSE_BUTTON_LEFT => "left",
+        MOUSE_BUTTON_RIGHT => "right",
+        MOUSE_BUTTON_WHEEL => "middle",
+        MOUSE_BUTTON_BACK => "back",
+        MOUSE_BUTTON_FORWARD => "forward",
+        _ => "other",
+    }
+}
+
+/// The timeline of one connection, the end is written when it is dropped.
+pub struct Recorder {
+    file: File,
+    key: secretbox::Key,
+    session: u64,
+    conn_id: i32,
+    window: Option<(String, String)>,
+    window_check: Option<Instant>,
+}
+
+impl Recorder {
+    /// `None` if the timeline is off, or if it can not be written.
+    pub fn new(conn_id: i32, session: u64, peer_id: &str, name: &str) -> Option<Self> {
+        if !is_enabled() {
+            return None;
+        }
+        start_pruning();
+        match Self::create(conn_id, session) {
+            Ok(mut recorder) => {
+                recorder.write(Event::Start {
+                    peer_id: peer_id.to_owned(),
+                    name: name.to_owned(),
+                });
+                Some(recorder)
+            }
+            Err(e) => {
+                log::error!("Failed to start the input timeline: {}", e);
+                None
+            }
+        }
+    }
+
+    fn create(conn_id: i32, session: u64) -> ResultType<Self> {
+        let key = key()?;
+        let dir = Config::path(DIR);
+        fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}_{}{EXT}", get_time() / 1000, conn_id));
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file,
+            key,
+            session,
+            conn_id,
+            window: None,
+            window_check: None,
+        })
+    }
+
+    fn write(&mut self, event: Event) {
+        let record = Record {
+            time: get_time(),
+            session: self.session,
+            conn_id: self.conn_id,
+            event,
+        };
+        if let Err(e) = writeln!(self.file, "{}", seal(&self.key, &record)) {
+            log::error!("Failed to write the input timeline: {}", e);
+        }
+    }
+
+    fn check_window(&mut self, force: bool) {
+        if !force
+            && self
+                .window_check
+                .map_or(false, |t| t.elapsed() < WINDOW_CHECK_INTERVAL)
+        {
+            return;
+        }
+        self.window_check = Some(Instant::now());
+        let Some(w) = scrap::quartz::window::windows().into_iter().next() else {
+            return;
+        };
+        let window = (w.owner, w.title);
+        if self.window.as_ref() != Some(&window) {
+            self.window = Some(window.clone());
+            self.write(Event::Window {
+                app: window.0,
+                title: window.1,
+            });
+        }
+    }
+
+    pub fn mouse(&mut self, me: &MouseEvent) {
+        if me.mask & MOUSE_TYPE_MASK != MOUSE_TYPE_DOWN {
+            return;
+        }
+        self.check_window(true);
+        self.write(Event::Click {
+            button: button_name(me.mask >> 3).to_owned(),
+            x: me.x,
+            y: me.y,
+        });
+    }
+
+    /// A pressed key, `shortcut` if Control, Option or Command are down.
+    pub fn key(&mut self, ke: &KeyEvent, shortcut: bool) {
+        self.check_window(false);
+        self.write(Event::Key {
+            category: key_category(ke, shortcut),
+        });
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        self.write(Event::End);
+    }
+}
+
+/// The records of the sessions started in the last `days` days, all with none, as json lines.
+pub fn export(days: Option<u64>) -> ResultType<String> {
+    let key = key()?;
+    let since = days.map(|d| get_time() / 1000 - (d * 86400) as i64);
+    let mut out = String::new();
+    let mut invalid = 0;
+    for (start, path) in files() {
+        if since.map_or(false, |since| start < since) {
+            continue;
+        }
+        for line in BufReader::new(File::open(&path)?).lines() {
+            match open(&key, &line?) {
+                Some(record) => {
+                    out.push_str(&serde_json::to_string(&record)?);
+                    out.push('\n');
+                }
+                None => invalid += 1,
+            }
+        }
+    }
+    if invalid > 0 {
+        log::warn!(
+            "{} records of the input timeline could not be read",
+            invalid
+        );
+    }
+    Ok(out)
+}
+
+/// Remove all the timeline, the number of files removed.
+pub fn clear() -> usize {
+    files()
+        .into_iter()
+        .filter(|(_, path)| fs::remove_file(path).is_ok())
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_timeline() {
+        let key = secretbox::gen_key();
+        let record = Record {
+            time: 1,
+            session: 2,
+            conn_id: 3,
+            event: Event::Key {
+                category: KeyCategory::Shortcut,
+            },
+        };
+        let line = seal(&key, &record);
+        assert_eq!(open(&key, &line), Some(record));
+        assert_eq!(open(&secretbox::gen_key(), &line), None);
+        assert!(!line.contains("shortcut"));
+
+        let mut ke = KeyEvent::new();
+        ke.set_seq("a".to_owned());
+        assert_eq!(key_category(&ke, false), KeyCategory::Character);
+        assert_eq!(key_category(&ke, true), KeyCategory::Shortcut);
+        ke.set_control_key(ControlKey::PageDown);
+        assert_eq!(key_category(&ke, true), KeyCategory::Navigation);
+        ke.modifiers.push(ControlKey::Meta.into());
+        ke.set_control_key(ControlKey::Space);
+        assert_eq!(key_category(&ke, false), KeyCategory::Shortcut);
+
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86400);
+        let files = vec![
+            (PathBuf::from("old"), now - day * 3),
+            (PathBuf::from("new"), now - day),
+        ];
+        assert_eq!(
+            select_files_to_prune(files, now, day * 2),
+            vec![PathBuf::from("old")]
+        );
+    }
+}
@@ -0,0 +1,592 @@
+//! Registry of the options, for settings pages built at runtime and for scripts.
+//!
+//! Each entry has the name, the type, the default value, the language key of its description,
+//! whether a restart is needed for a change to take effect, and where it is stored: the host
+//! options of `Config` or the local options of `LocalConfig`. It is listed by
+//! `ui_interface::list_options` and `rustdesk --list-options [--json] [query]`.
+
+use super::{
+    audio_service, auth_provider, clipboard_service, connection, file_sandbox, idle_detector,
+    input_limiter, input_profile, input_timeline, record_retention, region_filter, session_queue,
+    shared_window, simulcast, video_qos, video_service, watermark,
+};
+use crate::{
+    bandwidth_probe,
+    client::frame_pacer,
+    clipboard::{chunks, history, images, redaction},
+    config_edit,
+    hbbs_http::availability,
+    hotkey_passthrough, peer_identity,
+    platform::virtual_display,
+    port_mapping, port_range, secret_store, update_manifest, video_datagram,
+};
+use hbb_common::config::keys;
+use serde_derive::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Kind {
+    Bool,
+    Int,
+    String,
+    Enum(&'static [&'static str]),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Scope {
+    Host,
+    Local,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct OptionMeta {
+    pub name: &'static str,
+    #[serde(rename = "type")]
+    pub kind: Kind,
+    pub default: &'static str,
+    pub description: &'static str,
+    pub requires_restart: bool,
+    pub scope: Scope,
+}
+
+const fn host(
+    name: &'static str,
+    kind: Kind,
+    default: &'static str,
+    description: &'static str,
+) -> OptionMeta {
+    OptionMeta {
+        name,
+        kind,
+        default,
+        description,
+        requires_restart: false,
+        scope: Scope::Host,
+    }
+}
+
+const fn local(
+    name: &'static str,
+    kind: Kind,
+    default: &'static str,
+    description: &'static str,
+) -> OptionMeta {
+    OptionMeta {
+        scope: Scope::Local,
+        ..host(name, kind, default, description)
+    }
+}
+
+const fn restart(meta: OptionMeta) -> OptionMeta {
+    OptionMeta {
+        requires_restart: true,
+        ..meta
+    }
+}
+
+const BOOL: Kind = Kind::Bool;
+const INT: Kind = Kind::Int;
+const STRING: Kind = Kind::String;
+
+pub const OPTIONS: &[OptionMeta] = &[
+    // permissions
+    host(
+        keys::OPTION_ENABLE_KEYBOARD,
+        BOOL,
+        "Y",
+        "Enable keyboard/mouse",
+    ),
+    host(keys::OPTION_ENABLE_CLIPBOARD, BOOL, "Y", "Enable clipboard"),
+    host(
+        keys::OPTION_ENABLE_FILE_TRANSFER,
+        BOOL,
+        "Y",
+        "Enable file transfer",
+    ),
+    host(keys::OPTION_ENABLE_AUDIO, BOOL, "Y", "Enable audio"),
+    host(keys::OPTION_ENABLE_CAMERA, BOOL, "Y", "Enable camera"),
+    host(keys::OPTION_ENABLE_TERMINAL, BOOL, "Y", "Enable terminal"),
+    host(
+        keys::OPTION_ENABLE_TUNNEL,
+        BOOL,
+        "Y",
+        "Enable TCP tunneling",
+    ),
+    host(
+        crate::mux::OPTION_TUNNEL_TARGETS,
+        STRING,
+        "",
+        "Allowed targets of the TCP tunnels",
+    ),
+    host(
+        keys::OPTION_ENABLE_REMOTE_RESTART,
+        BOOL,
+        "Y",
+        "Enable remote restart",
+    ),
+    host(
+        keys::OPTION_ENABLE_RECORD_SESSION,
+        BOOL,
+        "Y",
+        "Enable recording session",
+    ),
+    host(
+        keys::OPTION_ENABLE_BLOCK_INPUT,
+        BOOL,
+        "Y",
+        "Enable blocking user input",
+    ),
+    host(
+        input_limiter::OPTION_INPUT_MAX_EVENTS_PER_SEC,
+        INT,
+        "1000",
+        "Maximum input events per second",
+    ),
+    host(
+        input_limiter::OPTION_INPUT_FLOOD_EVENTS_PER_SEC,
+        INT,
+        "10000",
+        "Input events per second reported as a flood",
+    ),
+    host(
+        input_profile::OPTION_INPUT_PROFILES,
+        STRING,
+        "",
+        "Input permission profiles",
+    ),
+    host(
+        audio_service::OPTION_CAMERA_AUDIO,
+        BOOL,
+        "N",
+        "Send the microphone with the camera",
+    ),
+    host(
+        audio_service::OPTION_TALK_BACK,
+        BOOL,
+        "N",
+        "Play the microphone of the viewers",
+    ),
+    // authentication
+    host(
+        auth_provider::OPTION_AUTH_PROVIDER,
+        Kind::Enum(&["", "ldap", "oidc"]),
+        "",
+        "Authentication provider",
+    ),
+    host(auth_provider::OPTION_LDAP_URL, STRING, "", "LDAP server"),
+    host(
+        auth_provider::OPTION_LDAP_BIND_DN,
+        STRING,
+        "",
+        "LDAP bind DN",
+    ),
+    host(
+        auth_provider::OPTION_OIDC_DEVICE_ENDPOINT,
+        STRING,
+        "",
+        "OIDC device authorization endpoint",
+    ),
+    host(
+        auth_provider::OPTION_OIDC_TOKEN_ENDPOINT,
+        STRING,
+        "",
+        "OIDC token endpoint",
+    ),
+    host(
+        auth_provider::OPTION_OIDC_CLIENT_ID,
+        STRING,
+        "",
+        "OIDC client ID",
+    ),
+    host(auth_provider::OPTION_OIDC_ISSUER, STRING, "", "OIDC issuer"),
+    host(
+        auth_provider::OPTION_AUTH_ALLOWED,
+        STRING,
+        "",
+        "Allowed users and groups",
+    ),
+    // network
+    local(
+        keys::OPTION_ENABLE_UDP_PUNCH,
+        BOOL,
+        "Y",
+        "Enable UDP hole punching",
+    ),
+    local(
+        keys::OPTION_ENABLE_IPV6_PUNCH,
+        BOOL,
+        "Y",
+        "Enable IPv6 P2P connection",
+    ),
+    host(
+        keys::OPTION_DIRECT_SERVER,
+        BOOL,
+        "N",
+        "Enable direct IP access",
+    ),
+    host("direct-access-port", INT, "21118", "Port"),
+    host(
+        port_range::OPTION_DIRECT_PORT_RANGE,
+        STRING,
+        "",
+        "Direct access port range",
+    ),
+    host(
+        port_mapping::OPTION_PORT_MAPPING,
+        BOOL,
+        "N",
+        "Map the port on the router",
+    ),
+    host(
+        port_range::OPTION_PUNCH_PORT_RANGE,
+        STRING,
+        "",
+        "Punch hole port range",
+    ),
+    host("whitelist", STRING, "", "Use IP Whitelisting"),
+    local(
+        video_datagram::OPTION_VIDEO_DATAGRAM,
+        BOOL,
+        "N",
+        "Skip lost video frames",
+    ),
+    // video
+    host(
+        keys::OPTION_ENABLE_HWCODEC,
+        BOOL,
+        "Y",
+        "Enable hardware codec",
+    ),
+    host(keys::OPTION_ENABLE_ABR, BOOL, "Y", "Adaptive bitrate"),
+    host(
+        video_qos::OPTION_ADAPTIVE_RESOLUTION,
+        BOOL,
+        "N",
+        "Lower resolution on slow networks",
+    ),
+    host(
+        simulcast::OPTION_SIMULCAST,
+        BOOL,
+        "N",
+        "Separate stream for slow viewers",
+    ),
+    host(
+        idle_detector::OPTION_IDLE_CAPTURE,
+        BOOL,
+        "Y",
+        "Lower the capture rate of a static screen",
+    ),
+    host(
+        shared_window::OPTION_SHARED_WINDOW,
+        STRING,
+        "",
+        "Share a single window",
+    ),
+    host(
+        region_filter::OPTION_PRIVACY_REGIONS,
+        STRING,
+        "",
+        "Regions hidden from the viewers",
+    ),
+    host(
+        watermark::OPTION_WATERMARK,
+        BOOL,
+        "N",
+        "Show a watermark on the shared screen",
+    ),
+    host(
+        watermark::OPTION_WATERMARK_TEXT,
+        STRING,
+        "",
+        "Text of the watermark",
+    ),
+    host(
+        video_service::OPTION_AV1_SCREEN_CONTENT,
+        BOOL,
+        "Y",
+        "AV1 screen content tools",
+    ),
+    host(
+        video_service::OPTION_AV1_CPU_USED,
+        INT,
+        "",
+        "AV1 speed preset",
+    ),
+    host(
+        video_service::OPTION_AV1_TILE_COLUMNS,
+        INT,
+        "",
+        "AV1 tile columns (log2)",
+    ),
+    host(
+        video_service::OPTION_AV1_TILE_ROWS,
+        INT,
+        "",
+        "AV1 tile rows (log2)",
+    ),
+    local(
+        frame_pacer::OPTION_FRAME_PACING,
+        BOOL,
+        "N",
+        "Smooth video playback",
+    ),
+    local(
+        bandwidth_probe::OPTION_BANDWIDTH_PROBE,
+        BOOL,
+        "Y",
+        "Measure the bandwidth when the session starts",
+    ),
+    host(
+        virtual_display::OPTION_AUTO_VIRTUAL_DISPLAY,
+        BOOL,
+        "N",
+        "Add a virtual display when no monitor is attached",
+    ),
+    host(
+        virtual_display::OPTION_VIRTUAL_DISPLAY_MODES,
+        STRING,
+        virtual_display::DEFAULT_MODES,
+        "Modes of the virtual display",
+    ),
+    // recording
+    host(
+        keys::OPTION_ALLOW_AUTO_RECORD_INCOMING,
+        BOOL,
+        "N",
+        "Automatically record incoming sessions",
+    ),
+    local(
+        keys::OPTION_ALLOW_AUTO_RECORD_OUTGOING,
+        BOOL,
+        "N",
+        "Automatically record outgoing sessions",
+    ),
+    host(
+        connection::OPTION_DO_NOT_RECORD,
+        BOOL,
+        "N",
+        "Do not allow viewers to record or take screenshots",
+    ),
+    host(
+        video_service::OPTION_RECORD_CONTAINER,
+        Kind::Enum(&["", "mp4", "mkv"]),
+        "",
+        "Recording container",
+    ),
+    host(
+        record_retention::OPTION_RECORD_MAX_DISK_USAGE_MB,
+        INT,
+        "",
+        "Maximum disk usage of recordings (MB)",
+    ),
+    host(
+        record_retention::OPTION_RECORD_MAX_AGE_DAYS,
+        INT,
+        "",
+        "Maximum age of recordings (days)",
+    ),
+    host(
+        record_retention::OPTION_RECORD_ROTATE_MINUTES,
+        INT,
+        "",
+        "Split recordings every N minutes",
+    ),
+    host(
+        input_timeline::OPTION_INPUT_TIMELINE,
+        BOOL,
+        "N",
+        "Record a timeline of the input of the sessions",
+    ),
+    host(
+        input_timeline::OPTION_INPUT_TIMELINE_MAX_AGE_DAYS,
+        INT,
+        "",
+        "Maximum age of the input timeline (days)",
+    ),
+    // sessions
+    host(
+        session_queue::OPTION_DO_NOT_DISTURB,
+        BOOL,
+        "N",
+        "Do not disturb",
+    ),
+    host(
+        session_queue::OPTION_MAX_CONCURRENT_SESSIONS,
+        INT,
+        "",
+        "Maximum concurrent sessions",
+    ),
+    host(
+        availability::OPTION_AVAILABILITY_STATUS,
+        Kind::Enum(&["available", "busy", "maintenance"]),
+        "available",
+        "Availability status",
+    ),
+    host(
+        availability::OPTION_AVAILABILITY_NOTE,
+        STRING,
+        "",
+        "Availability note",
+    ),
+    host(
+        file_sandbox::OPTION_FILE_TRANSFER_SANDBOX_ROOTS,
+        STRING,
+        "",
+        "Folders allowed for file transfer",
+    ),
+    host(
+        file_sandbox::OPTION_FILE_TRANSFER_READ_ONLY,
+        BOOL,
+        "N",
+        "Read-only file transfer",
+    ),
+    host(
+        config_edit::OPTION_CONFIG_EDIT_FILES,
+        STRING,
+        "",
+        "Config files editable by the peers",
+    ),
+    host(
+        config_edit::OPTION_CONFIG_EDIT_ENV,
+        STRING,
+        "",
+        "Environment variables readable by the peers",
+    ),
+    host(
+        clipboard_service::OPTION_CLIPBOARD_DIRECTION,
+        Kind::Enum(&[
+            clipboard_service::DIRECTION_BOTH,
+            clipboard_service::DIRECTION_HOST_TO_CLIENT,
+            clipboard_service::DIRECTION_CLIENT_TO_HOST,
+            clipboard_service::DIRECTION_OFF,
+        ]),
+        clipboard_service::DIRECTION_BOTH,
+        "Clipboard direction",
+    ),
+    host(
+        redaction::OPTION_CLIPBOARD_REDACTION_RULES,
+        STRING,
+        "",
+        "Clipboard redaction rules",
+    ),
+    host(
+        chunks::OPTION_CLIPBOARD_MAX_MB,
+        INT,
+        "32",
+        "Maximum size of the clipboard (MB)",
+    ),
+    host(
+        images::OPTION_CLIPBOARD_IMAGE_MAX_MB,
+        INT,
+        "16",
+        "Maximum size of clipboard images (MB)",
+    ),
+    local(
+        history::OPTION_CLIPBOARD_HISTORY_SIZE,
+        INT,
+        "10",
+        "Size of the clipboard history",
+    ),
+    // keyboard
+    local(
+        hotkey_passthrough::OPTION_HOTKEY_PASSTHROUGH,
+        STRING,
+        "",
+        "Hotkeys kept on this computer",
+    ),
+    // general
+    host(
+        secret_store::OPTION_SECRET_STORE,
+        Kind::Enum(&[secret_store::STORE_KEYCHAIN, secret_store::STORE_FILE]),
+        secret_store::STORE_KEYCHAIN,
+        "Storage of the passwords",
+    ),
+    host(
+        peer_identity::OPTION_DISPLAY_NAME,
+        STRING,
+        "",
+        "Display name of this device",
+    ),
+    host(
+        peer_identity::OPTION_AVATAR,
+        STRING,
+        "",
+        "Avatar of this device",
+    ),
+    host(
+        peer_identity::OPTION_SYNC_IDENTITY,
+        BOOL,
+        "N",
+        "Upload the display name and avatar to the server",
+    ),
+    host("audio-input", STRING, "", "Audio Input"),
+    host(
+        keys::OPTION_ENABLE_CHECK_UPDATE,
+        BOOL,
+        "Y",
+        "Check for software update on startup",
+    ),
+    host(keys::OPTION_ALLOW_AUTO_UPDATE, BOOL, "N", "Auto update"),
+    host(
+        update_manifest::OPTION_UPDATE_MANIFEST_URL,
+        STRING,
+        "",
+        "Update manifest URL",
+    ),
+    host(
+        update_manifest::OPTION_UPDATE_MANIFEST_KEY,
+        STRING,
+        "",
+        "Public key of the update manifest",
+    ),
+    restart(host(keys::OPTION_HIDE_TRAY, BOOL, "N", "Hide tray icon")),
+];
+
+pub fn get(name: &str) -> Option<&'static OptionMeta> {
+    OPTIONS.iter().find(|o| o.name == name)
+}
+
+/// The options whose name, description or translated description contains the query,
+/// case insensitive. All options for an empty query.
+pub fn search(query: &str) -> Vec<&'static OptionMeta> {
+    let query = query.trim().to_lowercase();
+    OPTIONS
+        .iter()
+        .filter(|o| {
+            query.is_empty()
+                || o.name.to_lowercase().contains(&query)
+                || o.description.to_lowercase().contains(&query)
+                || crate::client::translate(o.description.to_owned())
+                    .to_lowercase()
+                    .contains(&query)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn option_registry_entries() {
+        let mut names = HashSet::new();
+        for o in OPTIONS {
+            assert!(names.insert(o.name), "duplicated option {}", o.name);
+            assert!(!o.description.is_empty());
+            if o.kind == Kind::Bool {
+                assert!(o.default == "Y" || o.default == "N", "{}", o.name);
+            }
+            if let Kind::Enum(values) = o.kind {
+                assert!(values.contains(&o.default), "{}", o.name);
+            }
+        }
+        assert_eq!(get(simulcast::OPTION_SIMULCAST).unwrap().scope, Scope::Host);
+        assert!(search("watermark").len() >= 2);
+        assert_eq!(search("").len(), OPTIONS.len());
+        let json = serde_json::to_value(get(video_service::OPTION_RECORD_CONTAINER)).unwrap();
+        assert_eq!(json["type"]["enum"][1], "mp4");
+        assert_eq!(json["scope"], "host");
+    }
+}
@@ -0,0 +1,177 @@
+//! Rotation and retention of auto-recorded incoming sessions.
+//!
+//! Recordings are split into a new file every `OPTION_RECORD_ROTATE_MINUTES` minutes,
+//! and a background task prunes `incoming_*` recordings in the save directory which are
+//! older than `OPTION_RECORD_MAX_AGE_DAYS` days, then the oldest ones until the total size
+//! is below `OPTION_RECORD_MAX_DISK_USAGE_MB`. Empty or invalid values, or ones too large to be
+//! counted in bytes or seconds, disable the limit.
+
+use hbb_common::{config::Config, log};
+use std::{
+    path::PathBuf,
+    sync::Once,
+    time::{Duration, SystemTime},
+};
+
+pub const OPTION_RECORD_MAX_DISK_USAGE_MB: &str = "record-max-disk-usage-mb";
+pub const OPTION_RECORD_MAX_AGE_DAYS: &str = "record-max-age-days";
+pub const OPTION_RECORD_ROTATE_MINUTES: &str = "record-rotate-minutes";
+
+const PRUNE_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// Files modified recently may still be written by a recorder.
+const ACTIVE_FILE_GRACE: Duration = Duration::from_secs(60);
+const INCOMING_PREFIX: &str = "incoming_";
+
+static START: Once = Once::new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Policy {
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+}
+
+// The value of the option in `unit`s, `None` if it is not a positive number of them or overflows.
+fn parse_option(value: &str, unit: u64) -> Option<u64> {
+    value
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .filter(|v| *v > 0)
+        .and_then(|v| v.checked_mul(unit))
+}
+
+impl Policy {
+    fn load() -> Self {
+        let parse = |key: &str, unit: u64| parse_option(&Config::get_option(key), unit);
+        Self {
+            max_bytes: parse(OPTION_RECORD_MAX_DISK_USAGE_MB, 1024 * 1024),
+            max_age: parse(OPTION_RECORD_MAX_AGE_DAYS, 86400).map(Duration::from_secs),
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.max_bytes.is_none() && self.max_age.is_none()
+    }
+}
+
+/// Max duration of one recording file, `None` if recordings are not rotated.
+pub fn rotate_duration() -> Option<Duration> {
+    parse_option(&Config::get_option(OPTION_RECORD_ROTATE_MINUTES), 60).map(Duration::from_secs)
+}
+
+/// Start the pruning task once, it keeps running for the lifetime of the server.
+pub fn start(root: bool) {
+    START.call_once(move || {
+        std::thread::spawn(move || loop {
+            let policy = Policy::load();
+            if !policy.is_empty() {
+                prune(&crate::ui_interface::video_save_directory(root), &policy);
+            }
+            std::thread::sleep(PRUNE_INTERVAL);
+        });
+    });
+}
+
+fn prune(dir: &str, policy: &Policy) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+    let files: Vec<(PathBuf, u64, SystemTime)> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with(INCOMING_PREFIX))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            if !meta.is_file() {
+                return None;
+            }
+            Some((e.path(), meta.len(), meta.modified().ok()?))
+        })
+        .collect();
+    for path in select_files_to_prune(files, SystemTime::now(), policy) {
+        match std::fs::remove_file(&path) {
+            Ok(_) => log::info!("Pruned recording {}", path.display()),
+            Err(e) => log::error!("Failed to prune recording {}: {}", path.display(), e),
+        }
+    }
+}
+
+fn select_files_to_prune(
+    mut files: Vec<(PathBuf, u64, SystemTime)>,
+    now: SystemTime,
+    policy: &Policy,
+) -> Vec<PathBuf> {
+    let age = |t: &SystemTime| now.duration_since(*t).unwrap_or_default();
+    files.retain(|(_, _, modified)| age(modified) > ACTIVE_FILE_GRACE);
+    // Oldest first.
+    files.sort_by(|a, b| a.2.cmp(&b.2));
+    let mut pruned = vec![];
+    let mut kept = vec![];
+    for (path, size, modified) in files {
+        if policy.max_age.map_or(false, |max| age(&modified) > max) {
+            pruned.push(path);
+        } else {
+            kept.push((path, size));
+        }
+    }
+    if let Some(max_bytes) = policy.max_bytes {
+        let mut total: u64 = kept.iter().map(|(_, size)| size).sum();
+        for (path, size) in kept {
+            if total <= max_bytes {
+                break;
+            }
+            total = total.saturating_sub(size);
+            pruned.push(path);
+        }
+    }
+    pruned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prune_selection() {
+        let now = SystemTime::now();
+        let day = Duration::from_secs(86400);
+        let file = |name: &str, size: u64, age: Duration| (PathBuf::from(name), size, now - age);
+        let files = vec![
+            file("a", 100, day * 10),
+            file("b", 100, day * 3),
+            file("c", 100, day * 2),
+            file("d", 100, day),
+            file("active", 1000, Duration::from_secs(1)),
+        ];
+
+        let policy = Policy {
+            max_bytes: None,
+            max_age: Some(day * 5),
+        };
+        assert_eq!(
+            select_files_to_prune(files.clone(), now, &policy),
+            vec![PathBuf::from("a")]
+        );
+
+        let policy = Policy {
+            max_bytes: Some(150),
+            max_age: Some(day * 5),
+        };
+        assert_eq!(
+            select_files_to_prune(files.clone(), now, &policy),
+            vec![PathBuf::from("a"), PathBuf::from("b"), PathBuf::from("c")]
+        );
+
+        assert!(select_files_to_prune(files, now, &Policy::default()).is_empty());
+    }
+
+    #[test]
+    fn record_retention_options() {
+        assert_eq!(parse_option(" 2 ", 86400), Some(2 * 86400));
+        assert_eq!(parse_option("0", 86400), None);
+        assert_eq!(parse_option("-1", 86400), None);
+        assert_eq!(parse_option("abc", 60), None);
+        assert_eq!(parse_option(&u64::MAX.to_string(), 1024 * 1024), None);
+        assert_eq!(parse_option("213503982334602", 86400), None);
+    }
+}
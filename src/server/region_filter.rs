@@ -0,0 +1,275 @@
+//! Blanks or blurs configured screen regions before the frames are encoded.
+//!
+//! `OPTION_PRIVACY_REGIONS` is a json list of rectangles in pixels of the captured display,
+//! e.g. a taskbar or the notification corner:
+//!
+//! `[{"display": 0, "x": 0, "y": 1040, "w": 1920, "h": 40, "mode": "blank"}]`
+//!
+//! `mode` is "blank" (default) or "blur". Blurred regions are pixelated with coarse blocks,
+//! so that text can not be read. The filter is applied to the converted frame, so it also
+//! covers recordings. The built-in settings take precedence over the local ones.
+
+use hbb_common::{bail, config::Config, ResultType};
+use scrap::{EncodeYuvFormat, Pixfmt};
+use serde_derive::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+pub const OPTION_PRIVACY_REGIONS: &str = "privacy-regions";
+
+const MAX_REGIONS: usize = 32;
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// Block size of the blur, in luma pixels.
+const BLUR_BLOCK: usize = 16;
+const BLANK_LUMA: u8 = 16;
+const BLANK_CHROMA: u8 = 128;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RegionMode {
+    #[default]
+    Blank,
+    Blur,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+    #[serde(default)]
+    pub display: usize,
+    pub x: usize,
+    pub y: usize,
+    pub w: usize,
+    pub h: usize,
+    #[serde(default)]
+    pub mode: RegionMode,
+}
+
+fn get_option(key: &str) -> String {
+    let v = crate::get_builtin_option(key);
+    if v.is_empty() {
+        Config::get_option(key)
+    } else {
+        v
+    }
+}
+
+/// Parse and validate the option value, empty for no regions.
+pub fn parse(s: &str) -> ResultType<Vec<Region>> {
+    if s.trim().is_empty() {
+        return Ok(vec![]);
+    }
+    let regions: Vec<Region> = serde_json::from_str(s)?;
+    if regions.len() > MAX_REGIONS {
+        bail!("too many regions, max {}", MAX_REGIONS);
+    }
+    if regions.iter().any(|r| r.w == 0 || r.h == 0) {
+        bail!("empty region");
+    }
+    Ok(regions)
+}
+
+pub fn is_enabled() -> bool {
+    parse(&get_option(OPTION_PRIVACY_REGIONS))
+        .map(|r| !r.is_empty())
+        .unwrap_or(false)
+}
+
+// A plane of the converted frame.
+struct Plane {
+    offset: usize,
+    stride: usize,
+    w: usize,
+    h: usize,
+    // Subsampling of the plane.
+    sub: usize,
+    // Bytes per sample and the byte of this plane, for interleaved chroma.
+    step: usize,
+    channel: usize,
+    blank: u8,
+}
+
+fn planes(fmt: &EncodeYuvFormat) -> Vec<Plane> {
+    let (w, h) = (fmt.w, fmt.h);
+    let plane = |offset, stride, sub: usize, step, channel, blank| Plane {
+        offset,
+        stride,
+        w: (w + sub - 1) / sub,
+        h: (h + sub - 1) / sub,
+        sub,
+        step,
+        channel,
+        blank,
+    };
+    let stride = |i: usize| fmt.stride.get(i).copied().unwrap_or_default();
+    match fmt.pixfmt {
+        Pixfmt::I420 => vec![
+            plane(0, stride(0), 1, 1, 0, BLANK_LUMA),
+            plane(fmt.u, stride(1), 2, 1, 0, BLANK_CHROMA),
+            plane(fmt.v, stride(2), 2, 1, 0, BLANK_CHROMA),
+        ],
+        Pixfmt::NV12 => vec![
+            plane(0, stride(0), 1, 1, 0, BLANK_LUMA),
+            plane(fmt.u, stride(1), 2, 2, 0, BLANK_CHROMA),
+            plane(fmt.u, stride(1), 2, 2, 1, BLANK_CHROMA),
+        ],
+        Pixfmt::I444 => vec![
+            plane(0, stride(0), 1, 1, 0, BLANK_LUMA),
+            plane(fmt.u, stride(1), 1, 1, 0, BLANK_CHROMA),
+            plane(fmt.v, stride(2), 1, 1, 0, BLANK_CHROMA),
+        ],
+        _ => vec![],
+    }
+}
+
+/// The regions of one display, refreshed when the option changes.
+pub struct RegionFilter {
+    display: usize,
+    option: String,
+    regions: Vec<Region>,
+    last_refresh: Instant,
+}
+
+impl RegionFilter {
+    pub fn new(display: usize) -> Self {
+        let mut filter = Self {
+            display,
+            option: "".to_owned(),
+            regions: vec![],
+            last_refresh: Instant::now(),
+        };
+        filter.load();
+        filter
+    }
+
+    fn load(&mut self) {
+        let option = get_option(OPTION_PRIVACY_REGIONS);
+        if option == self.option {
+            return;
+        }
+        self.regions = match parse(&option) {
+            Ok(regions) => regions
+                .into_iter()
+                .filter(|r| r.display == self.display)
+                .collect(),
+            Err(e) => {
+                hbb_common::log::error!("Invalid {}: {}", OPTION_PRIVACY_REGIONS, e);
+                vec![]
+            }
+        };
+        self.option = option;
+    }
+
+    pub fn is_empty(&mut self) -> bool {
+        if self.last_refresh.elapsed() >= REFRESH_INTERVAL {
+            self.last_refresh = Instant::now();
+            self.load();
+        }
+        self.regions.is_empty()
+    }
+
+    /// Filter the regions of a converted frame.
+    pub fn apply(&self, yuv: &mut [u8], fmt: &EncodeYuvFormat) {
+        for plane in planes(fmt) {
+            if plane.w == 0
+                || plane.h == 0
+                || plane.stride < plane.w * plane.step
+                || yuv.len() < plane.offset + plane.stride * plane.h
+            {
+                continue;
+            }
+            let data = &mut yuv[plane.offset..plane.offset + plane.stride * plane.h];
+            for r in self.regions.iter() {
+                // Round outwards so that subsampled planes cover the whole region.
+                let x0 = (r.x / plane.sub).min(plane.w);
+                let y0 = (r.y / plane.sub).min(plane.h);
+                let x1 = ((r.x + r.w + plane.sub - 1) / plane.sub).min(plane.w);
+                let y1 = ((r.y + r.h + plane.sub - 1) / plane.sub).min(plane.h);
+                match r.mode {
+                    RegionMode::Blank => {
+                        for_each_block(&plane, data, (x0, y0, x1, y1), usize::MAX, |_| plane.blank)
+                    }
+                    RegionMode::Blur => {
+                        let block = (BLUR_BLOCK / plane.sub).max(1);
+                        for_each_block(&plane, data, (x0, y0, x1, y1), block, |samples| {
+                            (samples.iter().map(|&s| s as usize).sum::<usize>()
+                                / samples.len().max(1)) as u8
+                        })
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Fill each `block` x `block` square of the rectangle with the value computed from its samples.
+fn for_each_block(
+    plane: &Plane,
+    data: &mut [u8],
+    (x0, y0, x1, y1): (usize, usize, usize, usize),
+    block: usize,
+    value: impl Fn(&[u8]) -> u8,
+) {
+    let index = |x: usize, y: usize| y * plane.stride + x * plane.step + plane.channel;
+    let mut samples = vec![];
+    let mut by = y0;
+    while by < y1 {
+        let ey = by.saturating_add(block).min(y1);
+        let mut bx = x0;
+        while bx < x1 {
+            let ex = bx.saturating_add(block).min(x1);
+            samples.clear();
+            if block != usize::MAX {
+                for y in by..ey {
+                    samples.extend((bx..ex).map(|x| data[index(x, y)]));
+                }
+            }
+            let v = value(&samples);
+            for y in by..ey {
+                for x in bx..ex {
+                    data[index(x, y)] = v;
+                }
+            }
+            bx = ex;
+        }
+        by = ey;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn region_filter() {
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse(r#"[{"x":0,"y":0,"w":0,"h":1}]"#).is_err());
+        let regions =
+            parse(r#"[{"x":2,"y":2,"w":4,"h":4},{"x":8,"y":0,"w":8,"h":8,"mode":"blur"}]"#)
+                .unwrap();
+        assert_eq!(regions[0].mode, RegionMode::Blank);
+        assert_eq!(regions[1].mode, RegionMode::Blur);
+
+        let (w, h) = (16, 8);
+        let fmt = EncodeYuvFormat {
+            pixfmt: Pixfmt::I420,
+            w,
+            h,
+            stride: vec![w, w / 2, w / 2],
+            u: w * h,
+            v: w * h + w * h / 4,
+        };
+        let mut yuv: Vec<u8> = (0..w * h * 3 / 2).map(|i| (i % 200) as u8 + 50).collect();
+        let filter = RegionFilter {
+            display: 0,
+            option: "".to_owned(),
+            regions,
+            last_refresh: Instant::now(),
+        };
+        filter.apply(&mut yuv, &fmt);
+        assert_eq!(yuv[2 * w + 2], BLANK_LUMA);
+        assert_eq!(yuv[5 * w + 5], BLANK_LUMA);
+        assert_ne!(yuv[w + 1], BLANK_LUMA);
+        assert_eq!(yuv[fmt.u + w / 2 + 1], BLANK_CHROMA);
+        // The blurred region has a single value per block.
+        assert!((0..h).all(|y| (8..16).all(|x| yuv[y * w + x] == yuv[8])));
+    }
+}
@@ -0,0 +1,205 @@
+//! Outgoing messages of a connection, sent by priority class.
+//!
+//! When the network stalls, the messages queue up. Sending them by class keeps the
+//! session responsive: control > input (cursor, clipboard, chat) > audio > video.
+//! Stale audio and video are dropped instead of delaying the newer ones. A video frame
+//! can not be decoded without the frames before it, so when one is dropped, the frames
+//! of its display are dropped until the next key frame, which is requested at once.
+
+use hbb_common::message_proto::{message, misc, video_frame, Message, VideoFrame};
+use std::{
+    collections::{HashSet, VecDeque},
+    sync::Arc,
+    time::Instant,
+};
+
+// Queued frames per display, frames beyond it are late anyway.
+const MAX_VIDEO_FRAMES: usize = 2;
+// About one second of audio.
+const MAX_AUDIO_FRAMES: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Priority {
+    Control = 0,
+    Input = 1,
+    Audio = 2,
+    /// Video frames and bulk data, e.g. file blocks.
+    Video = 3,
+}
+
+pub fn priority(msg: &Message) -> Priority {
+    match &msg.union {
+        Some(message::Union::VideoFrame(_)) | Some(message::Union::FileResponse(_)) => {
+            Priority::Video
+        }
+        Some(message::Union::AudioFrame(_)) => Priority::Audio,
+        Some(message::Union::CursorData(_))
+        | Some(message::Union::CursorId(_))
+        | Some(message::Union::CursorPosition(_))
+        | Some(message::Union::Clipboard(_))
+        | Some(message::Union::MultiClipboards(_)) => Priority::Input,
+        Some(message::Union::Misc(m)) => match &m.union {
+            // Keep the order with the frames.
+            Some(misc::Union::SwitchDisplay(_)) => Priority::Video,
+            Some(misc::Union::AudioFormat(_)) => Priority::Audio,
+            Some(misc::Union::ChatMessage(_)) => Priority::Input,
+            // Bulk data, sent between the frames.
+            Some(misc::Union::PluginRequest(p)) if p.id == crate::clipboard::chunks::ID => {
+                Priority::Video
+            }
+            _ => Priority::Control,
+        },
+        _ => Priority::Control,
+    }
+}
+
+fn video_frame(msg: &Message) -> Option<&VideoFrame> {
+    match &msg.union {
+        Some(message::Union::VideoFrame(vf)) => Some(vf),
+        _ => None,
+    }
+}
+
+pub(super) fn is_key_frame(vf: &VideoFrame) -> bool {
+    match &vf.union {
+        Some(video_frame::Union::Vp8s(f))
+        | Some(video_frame::Union::Vp9s(f))
+        | Some(video_frame::Union::Av1s(f))
+        | Some(video_frame::Union::H264s(f))
+        | Some(video_frame::Union::H265s(f)) => f.frames.iter().any(|f| f.key),
+        // Raw frames do not depend on each other.
+        _ => true,
+    }
+}
+
+pub type QueuedMessage = (Instant, Arc<Message>);
+
+#[derive(Default)]
+pub struct SendQueue {
+    queues: [VecDeque<QueuedMessage>; 4],
+    // Displays whose frames are dropped until the next key frame.
+    wait_key_frame: HashSet<usize>,
+    key_frame_requests: Vec<usize>,
+}
+
+impl SendQueue {
+    /// Queue a message, returns the displays of the dropped video frames.
+    pub fn push(&mut self, instant: Instant, msg: Arc<Message>) -> Vec<usize> {
+        let class = priority(&msg);
+        let mut dropped = vec![];
+        if let Some(vf) = video_frame(&msg) {
+            let display = vf.display as usize;
+            if self.wait_key_frame.contains(&display) {
+                if !is_key_frame(vf) {
+                    return vec![display];
+                }
+                self.wait_key_frame.remove(&display);
+            }
+            let queue = &mut self.queues[class as usize];
+            let queued = queue
+                .iter()
+                .filter(|(_, m)| video_frame(m).map(|f| f.display as usize) == Some(display))
+                .count();
+            if queued >= MAX_VIDEO_FRAMES {
+                // The queued frames of the display are stale.
+                queue.retain(|(_, m)| {
+                    if video_frame(m).map(|f| f.display as usize) == Some(display) {
+                        dropped.push(display);
+                        false
+                    } else {
+                        true
+                    }
+                });
+                if !is_key_frame(vf) {
+                    // The new frame depends on them.
+                    dropped.push(display);
+                    self.wait_key_frame.insert(display);
+                    self.key_frame_requests.push(display);
+                    return dropped;
+                }
+            }
+        }
+        let queue = &mut self.queues[class as usize];
+        queue.push_back((instant, msg));
+        if class == Priority::Audio {
+            while queue
+                .iter()
+                .filter(|(_, m)| matches!(m.union, Some(message::Union::AudioFrame(_))))
+                .count()
+                > MAX_AUDIO_FRAMES
+            {
+                match queue
+                    .iter()
+                    .position(|(_, m)| matches!(m.union, Some(message::Union::AudioFrame(_))))
+                {
+                    Some(i) => {
+                        queue.remove(i);
+                    }
+                    None => break,
+                }
+            }
+        }
+        dropped
+    }
+
+    /// The next message to send, by priority.
+    pub fn pop(&mut self) -> Option<QueuedMessage> {
+        self.queues.iter_mut().find_map(|q| q.pop_front())
+    }
+
+    /// Displays that need a key frame since their frames were dropped.
+    pub fn take_key_frame_requests(&mut self) -> Vec<usize> {
+        std::mem::take(&mut self.key_frame_requests)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(|q| q.is_empty())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{EncodedVideoFrame, EncodedVideoFrames};
+
+    fn frame(display: i32, key: bool) -> Arc<Message> {
+        let mut frames = EncodedVideoFrames::new();
+        frames.frames.push(EncodedVideoFrame {
+            key,
+            ..Default::default()
+        });
+        let mut vf = VideoFrame::new();
+        vf.display = display;
+        vf.set_vp9s(frames);
+        let mut msg = Message::new();
+        msg.set_video_frame(vf);
+        Arc::new(msg)
+    }
+
+    #[test]
+    fn send_queue() {
+        let now = Instant::now();
+        let mut q = SendQueue::default();
+        assert!(q.push(now, frame(0, true)).is_empty());
+        assert!(q.push(now, frame(0, false)).is_empty());
+        let mut chat = Message::new();
+        let mut m = hbb_common::message_proto::Misc::new();
+        m.set_chat_message(Default::default());
+        chat.set_misc(m);
+        assert!(q.push(now, Arc::new(chat)).is_empty());
+        // Input overtakes the video.
+        assert_eq!(priority(&q.pop().unwrap().1), Priority::Input);
+        assert!(q.push(now, frame(1, false)).is_empty());
+        // Too many frames of display 0, drop them until the next key frame.
+        assert_eq!(q.push(now, frame(0, false)), vec![0, 0, 0]);
+        assert_eq!(q.take_key_frame_requests(), vec![0]);
+        assert_eq!(q.push(now, frame(0, false)), vec![0]);
+        assert!(q.take_key_frame_requests().is_empty());
+        assert!(q.push(now, frame(0, true)).is_empty());
+        let displays: Vec<i32> = std::iter::from_fn(|| q.pop())
+            .map(|(_, m)| video_frame(&m).unwrap().display)
+            .collect();
+        assert_eq!(displays, vec![1, 0]);
+        assert!(q.is_empty());
+    }
+}
@@ -233,15 +233,30 @@ impl<T: Subscriber + From<ConnInner>> ServiceTmpl<T> {
     }
 
     pub fn send_video_frame_shared(&self, msg: Arc<Message>) -> HashSet<i32> {
+        self.send_video_frame_filter(msg, |_| true)
+    }
+
+    /// Send the frame to the subscribers accepted by `filter`, e.g. the viewers of a simulcast tier.
+    pub fn send_video_frame_filter(
+        &self,
+        msg: Arc<Message>,
+        filter: impl Fn(i32) -> bool,
+    ) -> HashSet<i32> {
         let mut conn_ids = HashSet::new();
         let mut lock = self.0.write().unwrap();
         for s in lock.subscribes.values_mut() {
-            s.send(msg.clone());
-            conn_ids.insert(s.id());
+            if filter(s.id()) {
+                s.send(msg.clone());
+                conn_ids.insert(s.id());
+            }
         }
         conn_ids
     }
 
+    pub fn subscriber_ids(&self) -> HashSet<i32> {
+        self.0.read().unwrap().subscribes.keys().cloned().collect()
+    }
+
     pub fn send_without(&self, msg: Message, sub: i32) {
         let mut lock = self.0.write().unwrap();
         let msg = Arc::new(msg);
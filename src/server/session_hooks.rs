@@ -0,0 +1,119 @@
+//! Commands run on the host when a session starts or ends.
+//!
+//! They are set in the input profile of the peer, in "on_connect" and "on_disconnect" (see
+//! `input_profile`), so a profile can start a screen recorder or turn off the notifications for
+//! the peers it applies to. The commands of an event run one after the other with `sh -c`, as
+//! the user of the service, with the peer in the environment, and each is stopped after
+//! `TIMEOUT`. Every run is logged and sent to the connection audit with its result, whether it
+//! succeeded or not.
+
+use hbb_common::log;
+use serde_derive::Serialize;
+use serde_json::{json, Value};
+use std::{
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+const TIMEOUT: Duration = Duration::from_secs(60);
+const POLL: Duration = Duration::from_millis(100);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Event {
+    Connect,
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Outcome {
+    pub exit_code: Option<i32>,
+    pub timed_out: bool,
+    pub error: String,
+    pub elapsed_ms: u128,
+}
+
+fn run_one(command: &str, env: &[(&str, String)], timeout: Duration) -> Outcome {
+    let start = Instant::now();
+    let mut outcome = Outcome::default();
+    let child = Command::new("/bin/sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(k, v)| (*k, v.as_str())))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => loop {
+            match child.try_wait() {
+                Ok(Some(status)) => {
+                    outcome.exit_code = status.code();
+                    break;
+                }
+                Ok(None) if start.elapsed() >= timeout => {
+                    child.kill().ok();
+                    child.wait().ok();
+                    outcome.timed_out = true;
+                    break;
+                }
+                Ok(None) => std::thread::sleep(POLL),
+                Err(e) => {
+                    outcome.error = e.to_string();
+                    break;
+                }
+            }
+        },
+        Err(e) => outcome.error = e.to_string(),
+    }
+    outcome.elapsed_ms = start.elapsed().as_millis();
+    outcome
+}
+
+/// Run the commands of the event on a thread, `audit` is called with the record of each.
+pub fn run(
+    event: Event,
+    profile: String,
+    commands: Vec<String>,
+    env: Vec<(&'static str, String)>,
+    audit: impl Fn(Value) + Send + 'static,
+) {
+    if commands.is_empty() {
+        return;
+    }
+    std::thread::spawn(move || {
+        for command in commands {
+            let outcome = run_one(&command, &env, TIMEOUT);
+            log::info!(
+                "Session hook of {} on {:?}: {}, {:?}",
+                profile,
+                event,
+                command,
+                outcome
+            );
+            audit(json!({
+                "action": "session_hook",
+                "event": event,
+                "profile": profile,
+                "command": command,
+                "result": outcome,
+            }));
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn session_hook_outcome() {
+        let env = [("RUSTDESK_PEER_ID", "123".to_owned())];
+        let outcome = run_one("test \"$RUSTDESK_PEER_ID\" = 123", &env, TIMEOUT);
+        assert_eq!(outcome.exit_code, Some(0));
+        assert_eq!(run_one("exit 3", &env, TIMEOUT).exit_code, Some(3));
+        let outcome = run_one("sleep 5", &env, Duration::from_millis(200));
+        assert!(outcome.timed_out);
+        assert_eq!(outcome.exit_code, None);
+    }
+}
@@ -0,0 +1,101 @@
+//! Waiting list of peers rejected because this host is busy.
+//!
+//! A remote control login is rejected when "do not disturb" is on or the number of
+//! remote control sessions reached `OPTION_MAX_CONCURRENT_SESSIONS`. The peer, once it
+//! passed the verification of the login, is put in the waiting list and told its position,
+//! the list holding at most `MAX_WAITERS` peers. When a slot becomes free, the peers at the
+//! head of the list are reported with the heartbeat, and the api server notifies them
+//! so that they can reconnect in one click.
+
+use hbb_common::{config::Config, log};
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+pub const OPTION_DO_NOT_DISTURB: &str = "do-not-disturb";
+pub const OPTION_MAX_CONCURRENT_SESSIONS: &str = "max-concurrent-sessions";
+
+// Peers that do not retry in this time are dropped from the list.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+const MAX_WAITERS: usize = 100;
+
+lazy_static::lazy_static! {
+    static ref QUEUE: Mutex<VecDeque<(String, Instant)>> = Default::default();
+}
+
+fn get_option(key: &str) -> String {
+    let v = crate::get_builtin_option(key);
+    if v.is_empty() {
+        Config::get_option(key)
+    } else {
+        v
+    }
+}
+
+/// Number of new remote control sessions allowed now.
+fn free_slots() -> usize {
+    if get_option(OPTION_DO_NOT_DISTURB) == "Y" {
+        return 0;
+    }
+    let max = get_option(OPTION_MAX_CONCURRENT_SESSIONS)
+        .trim()
+        .parse::<usize>()
+        .unwrap_or(0);
+    if max == 0 {
+        return usize::MAX;
+    }
+    max.saturating_sub(super::connection::remote_peers().len())
+}
+
+/// Returns the 1-based position of `peer_id` in the waiting list if the host is busy, the peer of
+/// an authorized login only. When the list is full, the peer is told the position after it.
+pub fn check_busy(peer_id: &str) -> Option<usize> {
+    let mut queue = QUEUE.lock().unwrap();
+    queue.retain(|(_, t)| t.elapsed() < WAIT_TIMEOUT);
+    let position = queue.iter().position(|(id, _)| id == peer_id);
+    let slots = free_slots();
+    // Peers that were notified before or are at the head of the list can take the free slots.
+    match position {
+        Some(i) if i < slots => {
+            queue.remove(i);
+            return None;
+        }
+        None if slots > queue.len() => return None,
+        _ => {}
+    }
+    let position = match position {
+        Some(i) => {
+            queue[i].1 = Instant::now();
+            i
+        }
+        None if queue.len() >= MAX_WAITERS => queue.len(),
+        None => {
+            queue.push_back((peer_id.to_owned(), Instant::now()));
+            queue.len() - 1
+        }
+    };
+    log::info!(
+        "Host is busy, {} is number {} in the queue",
+        peer_id,
+        position + 1
+    );
+    Some(position + 1)
+}
+
+/// Peers to notify that the host is free, reported with the heartbeat.
+///
+/// They stay in the list so that they keep their slot when they reconnect.
+pub fn take_free_waiters(notified: &mut Vec<String>) -> Vec<String> {
+    let mut queue = QUEUE.lock().unwrap();
+    queue.retain(|(_, t)| t.elapsed() < WAIT_TIMEOUT);
+    notified.retain(|id| queue.iter().any(|(x, _)| x == id));
+    let slots = free_slots();
+    queue
+        .iter()
+        .take(slots)
+        .map(|(id, _)| id.clone())
+        .filter(|id| !notified.contains(id))
+        .collect()
+}
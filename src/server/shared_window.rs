@@ -0,0 +1,138 @@
+//! Sharing a single application window instead of the whole screen.
+//!
+//! When `OPTION_SHARED_WINDOW` holds the id of an on-screen window, remote sessions get
+//! the window as their only display, captured by `VideoSource::Window`. The display info
+//! uses the window origin, and the pointer of the controller is kept within the window, so
+//! its input lands on the window only. Other windows are never captured, even if they overlap
+//! it. Only macOS can capture a window, elsewhere no window is shared.
+
+use hbb_common::{
+    config::Config,
+    message_proto::{DisplayInfo, MouseEvent},
+};
+#[cfg(target_os = "macos")]
+use scrap::quartz::window;
+#[cfg(target_os = "macos")]
+use serde_json::json;
+
+pub const OPTION_SHARED_WINDOW: &str = "shared-window";
+
+/// The only index of the window video service.
+pub const WINDOW_IDX: usize = 0;
+
+pub fn parse_id(s: &str) -> Option<u32> {
+    s.trim().parse().ok().filter(|id| *id != 0)
+}
+
+/// The shared window, `None` if not set or it is closed.
+#[cfg(target_os = "macos")]
+pub fn get() -> Option<window::Window> {
+    let id = parse_id(&Config::get_option(OPTION_SHARED_WINDOW))?;
+    window::window(id)
+}
+
+/// The display info of the shared window, the size is in captured pixels.
+#[cfg(target_os = "macos")]
+pub fn display_info() -> Option<DisplayInfo> {
+    let w = get()?;
+    let mut buf = Vec::new();
+    let (width, height, _) = window::capture(w.id, &mut buf)?;
+    let scale = if w.width > 0. {
+        width as f64 / w.width
+    } else {
+        1.
+    };
+    Some(DisplayInfo {
+        x: w.x.round() as _,
+        y: w.y.round() as _,
+        width: width as _,
+        height: height as _,
+        name: if w.title.is_empty() { w.owner } else { w.title },
+        online: true,
+        cursor_embedded: false,
+        scale,
+        ..Default::default()
+    })
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn display_info() -> Option<DisplayInfo> {
+    None
+}
+
+/// The windows that can be shared, as json for the settings page.
+#[cfg(target_os = "macos")]
+pub fn list() -> String {
+    let windows: Vec<_> = window::windows()
+        .into_iter()
+        .map(|w| {
+            json!({
+                "id": w.id,
+                "owner": w.owner,
+                "title": w.title,
+                "width": w.width.round() as i64,
+                "height": w.height.round() as i64,
+            })
+        })
+        .collect();
+    json!(windows).to_string()
+}
+
+#[cfg(not(target_os = "macos"))]
+pub fn list() -> String {
+    "[]".to_owned()
+}
+
+/// Move the pointer of an event out of the window to its nearest edge, in the coordinates of
+/// the controller, before they are scaled to the screen.
+pub fn clip(window: &DisplayInfo, e: &mut MouseEvent) {
+    let evt_type = e.mask & crate::input::MOUSE_TYPE_MASK;
+    // The events by a delta have no position.
+    if evt_type == crate::input::MOUSE_TYPE_WHEEL
+        || evt_type == crate::input::MOUSE_TYPE_TRACKPAD
+        || evt_type == crate::input::MOUSE_TYPE_MOVE_RELATIVE
+        || evt_type == crate::input::MOUSE_TYPE_WHEEL_PRECISE
+    {
+        return;
+    }
+    e.x = e.x.clamp(window.x, window.x + (window.width - 1).max(0));
+    e.y = e.y.clamp(window.y, window.y + (window.height - 1).max(0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_window_option() {
+        assert_eq!(parse_id(""), None);
+        assert_eq!(parse_id("0"), None);
+        assert_eq!(parse_id("abc"), None);
+        assert_eq!(parse_id(" 1234 "), Some(1234));
+    }
+
+    #[test]
+    fn shared_window_clip() {
+        let window = DisplayInfo {
+            x: 100,
+            y: 50,
+            width: 200,
+            height: 100,
+            ..Default::default()
+        };
+        let clipped = |x, y, mask| {
+            let mut e = MouseEvent {
+                x,
+                y,
+                mask,
+                ..Default::default()
+            };
+            clip(&window, &mut e);
+            (e.x, e.y)
+        };
+        assert_eq!(clipped(150, 80, 0), (150, 80));
+        assert_eq!(clipped(0, 0, 0), (100, 50));
+        assert_eq!(clipped(500, 500, 0), (299, 149));
+        assert_eq!(clipped(-3, 4, crate::input::MOUSE_TYPE_WHEEL), (-3, 4));
+    }
+}
@@ -0,0 +1,238 @@
+//! Loopback viewers for load testing, `rustdesk --simulate-clients N [seconds] [delay-ms]`.
+//!
+//! The viewers subscribe to the primary display of an in-process server like view-only
+//! connections, without a network or a login. They take each frame at once, so the report shows
+//! the cost of the capture, the encode and the fan-out on this host. The delay, if any, is
+//! reported to the QoS as the network delay of every viewer, to see how it adjusts the fps and
+//! the quality. Built with the "simulate-clients" feature only.
+
+use super::{
+    display_service,
+    video_service::{self, VideoSource, VIDEO_QOS},
+    ConnInner, Subscriber,
+};
+use hbb_common::{
+    bail, log,
+    message_proto::{message, Message},
+    protobuf::Message as _,
+    tokio::{
+        self,
+        sync::mpsc,
+        time::{interval, Duration, Instant},
+    },
+    ResultType,
+};
+use std::{fmt, sync::Arc};
+
+const DEFAULT_SECONDS: u64 = 30;
+const MAX_CLIENTS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Options {
+    pub clients: usize,
+    pub seconds: u64,
+    pub delay_ms: Option<u32>,
+}
+
+impl Options {
+    /// Parse the arguments after `--simulate-clients`.
+    pub fn parse(args: &[String]) -> ResultType<Self> {
+        let Some(clients) = args.first().and_then(|a| a.parse::<usize>().ok()) else {
+            bail!("Usage: --simulate-clients N [seconds] [delay-ms]");
+        };
+        if clients == 0 || clients > MAX_CLIENTS {
+            bail!("The number of clients must be in [1, {}]", MAX_CLIENTS);
+        }
+        let seconds = match args.get(1) {
+            Some(s) => s.parse::<u64>()?.max(1),
+            None => DEFAULT_SECONDS,
+        };
+        let delay_ms = match args.get(2) {
+            Some(s) => Some(s.parse::<u32>()?),
+            None => None,
+        };
+        Ok(Self {
+            clients,
+            seconds,
+            delay_ms,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ClientReport {
+    pub conn_id: i32,
+    pub frames: u64,
+    pub bytes: u64,
+    pub dropped: u64,
+    pub encode_ms: f32,
+    pub target_fps: u32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub seconds: f32,
+    pub clients: Vec<ClientReport>,
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let secs = self.seconds.max(0.001);
+        writeln!(f, "{} clients, {:.1}s", self.clients.len(), self.seconds)?;
+        writeln!(f, "conn\tfps\tkbps\tframes\tdropped\tencode_ms\ttarget_fps")?;
+        for c in self.clients.iter() {
+            writeln!(
+                f,
+                "{}\t{:.1}\t{:.0}\t{}\t{}\t{:.1}\t{}",
+                c.conn_id,
+                c.frames as f32 / secs,
+                c.bytes as f32 * 8. / 1000. / secs,
+                c.frames,
+                c.dropped,
+                c.encode_ms,
+                c.target_fps
+            )?;
+        }
+        let frames: u64 = self.clients.iter().map(|c| c.frames).sum();
+        let bytes: u64 = self.clients.iter().map(|c| c.bytes).sum();
+        write!(
+            f,
+            "total\t{:.1}\t{:.0}\t{}",
+            frames as f32 / secs,
+            bytes as f32 * 8. / 1000. / secs,
+            frames
+        )
+    }
+}
+
+// Take the frames of a viewer until the deadline, acknowledging them like a fast connection.
+async fn receive(
+    conn_id: i32,
+    mut rx: mpsc::UnboundedReceiver<(std::time::Instant, Arc<Message>)>,
+    mut rx_video: mpsc::UnboundedReceiver<(std::time::Instant, Arc<Message>)>,
+    deadline: Instant,
+) -> ClientReport {
+    let mut report = ClientReport {
+        conn_id,
+        ..Default::default()
+    };
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep_until(deadline) => break,
+            Some((instant, msg)) = rx_video.recv() => {
+                if let Some(message::Union::VideoFrame(vf)) = &msg.union {
+                    report.frames += 1;
+                    report.bytes += msg.compute_size();
+                    video_service::notify_video_frame_fetched(
+                        vf.display as usize,
+                        conn_id,
+                        Some(instant),
+                    );
+                }
+            }
+            // The other messages of the services are dropped.
+            Some(_) = rx.recv() => {}
+        }
+    }
+    report
+}
+
+#[tokio::main(flavor = "multi_thread")]
+pub async fn run(options: Options) -> ResultType<Report> {
+    let server = super::new();
+    let service_name = video_service::get_service_name(
+        VideoSource::Monitor,
+        *display_service::PRIMARY_DISPLAY_IDX,
+    );
+    server.write().unwrap().try_add_primay_video_service();
+    let start = Instant::now();
+    let deadline = start + Duration::from_secs(options.seconds);
+    let mut conns = vec![];
+    let mut tasks = vec![];
+    for _ in 0..options.clients {
+        let id = server.write().unwrap().get_new_id();
+        let (tx, rx) = mpsc::unbounded_channel();
+        let (tx_video, rx_video) = mpsc::unbounded_channel();
+        let inner = ConnInner::new(id, Some(tx), Some(tx_video));
+        VIDEO_QOS.lock().unwrap().on_connection_open(id);
+        scrap::codec::Encoder::update(scrap::codec::EncodingUpdate::NewOnlyVP9(id));
+        server
+            .write()
+            .unwrap()
+            .subscribe(&service_name, inner.clone(), true);
+        tasks.push(tokio::spawn(receive(id, rx, rx_video, deadline)));
+        conns.push(inner);
+    }
+    log::info!(
+        "simulating {} clients for {}s",
+        options.clients,
+        options.seconds
+    );
+    if let Some(delay) = options.delay_ms {
+        let mut timer = interval(Duration::from_secs(1));
+        while Instant::now() < deadline {
+            timer.tick().await;
+            let mut video_qos = VIDEO_QOS.lock().unwrap();
+            for c in conns.iter() {
+                video_qos.user_network_delay(c.id(), delay);
+            }
+        }
+    }
+    let mut clients = vec![];
+    for task in tasks {
+        clients.push(task.await?);
+    }
+    let seconds = start.elapsed().as_secs_f32();
+    let stats = VIDEO_QOS.lock().unwrap().session_stats();
+    for c in clients.iter_mut() {
+        if let Some(s) = stats.iter().find(|s| s.conn_id == c.conn_id) {
+            c.dropped = s.dropped;
+            c.encode_ms = s.encode_ms;
+            c.target_fps = s.target_fps;
+        }
+    }
+    for inner in conns {
+        let id = inner.id();
+        server.write().unwrap().remove_connection(&inner);
+        VIDEO_QOS.lock().unwrap().on_connection_close(id);
+        scrap::codec::Encoder::update(scrap::codec::EncodingUpdate::Remove(id));
+    }
+    Ok(Report { seconds, clients })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(v: &[&str]) -> Vec<String> {
+        v.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn simulation_options() {
+        let o = Options::parse(&args(&["4"])).unwrap();
+        assert_eq!(o.clients, 4);
+        assert_eq!(o.seconds, DEFAULT_SECONDS);
+        assert_eq!(o.delay_ms, None);
+        let o = Options::parse(&args(&["2", "0", "150"])).unwrap();
+        assert_eq!(o.seconds, 1);
+        assert_eq!(o.delay_ms, Some(150));
+        assert!(Options::parse(&args(&[])).is_err());
+        assert!(Options::parse(&args(&["0"])).is_err());
+        assert!(Options::parse(&args(&["1000"])).is_err());
+        assert!(Options::parse(&args(&["2", "ten"])).is_err());
+
+        let report = Report {
+            seconds: 2.,
+            clients: vec![ClientReport {
+                conn_id: 1001,
+                frames: 60,
+                bytes: 250_000,
+                ..Default::default()
+            }],
+        };
+        let text = report.to_string();
+        assert!(text.contains("1001\t30.0\t1000\t60"));
+        assert!(text.ends_with("total\t30.0\t1000\t60"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Simulcast, a second low quality stream of a display for weak viewers.
+//!
+//! With `OPTION_SIMULCAST`, `VideoQoS` puts mobile peers and viewers on congested networks
+//! into the low tier, as long as another viewer stays in the high tier. The video service then
+//! encodes the frames twice: the high tier is adjusted to the other viewers only, the low tier
+//! is half the resolution at a low bitrate. A low tier frame is only encoded when its viewers
+//! fetched the previous one, so they never slow down the high tier.
+//!
+//! The frames of the low tier are smaller than the display, the same as with
+//! `OPTION_ADAPTIVE_RESOLUTION`, so only the viewers which asked for the scaled frames, see
+//! `crate::scaled_frames`, are put into it. The others stay in the high tier, whatever their
+//! device or their network.
+
+use super::{service::GenericService, video_service::VIDEO_QOS};
+use hbb_common::{bail, log, message_proto::Message, ResultType};
+use scrap::{codec::Encoder, EncodeInput, EncodeYuvFormat};
+use std::{
+    collections::HashSet,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+/// Host option, "Y" to encode a low tier for weak viewers.
+pub const OPTION_SIMULCAST: &str = "allow-simulcast";
+pub const LOW_TIER_SCALE: f32 = 0.5;
+pub const LOW_TIER_RATIO: f32 = scrap::codec::BR_SPEED;
+// Stop waiting for a viewer that does not fetch the frames.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(3);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Tier {
+    #[default]
+    High,
+    Low,
+}
+
+/// The encoded size of the low tier, even for the encoders.
+pub fn low_tier_size(width: usize, height: usize) -> (usize, usize) {
+    let scale = |x: usize| ((x as f32 * LOW_TIER_SCALE) as usize / 2 * 2).max(2);
+    (scale(width), scale(height))
+}
+
+/// The encoder of the low tier of one display and its viewers.
+pub struct LowTier {
+    encoder: Encoder,
+    yuv: Vec<u8>,
+    conn_ids: HashSet<i32>,
+    // Viewers that have not fetched the last frame.
+    waiting: HashSet<i32>,
+    sent: Instant,
+}
+
+impl LowTier {
+    pub fn new(encoder: Encoder) -> Self {
+        Self {
+            encoder,
+            yuv: Vec::new(),
+            conn_ids: HashSet::new(),
+            waiting: HashSet::new(),
+            sent: Instant::now(),
+        }
+    }
+
+    pub fn conn_ids(&self) -> &HashSet<i32> {
+        &self.conn_ids
+    }
+
+    pub fn waiting(&self) -> &HashSet<i32> {
+        &self.waiting
+    }
+
+    /// Update the viewers of the tier. The new ones need a key frame of this tier, returns
+    /// whether the ones going back to the high tier need a key frame of it.
+    pub fn set_conn_ids(&mut self, conn_ids: HashSet<i32>) -> ResultType<bool> {
+        if conn_ids.iter().any(|id| !self.conn_ids.contains(id))
+            && !self.encoder.request_key_frame()
+        {
+            log::info!("switch to get a key frame for the low tier");
+            bail!("SWITCH");
+        }
+        let back = self.conn_ids.iter().any(|id| !conn_ids.contains(id));
+        self.waiting.retain(|id| conn_ids.contains(id));
+        self.conn_ids = conn_ids;
+        Ok(back)
+    }
+
+    /// Record the viewers that fetched the last frame, returns the ones that timed out.
+    pub fn on_fetched(&mut self, fetched: &HashSet<i32>) -> HashSet<i32> {
+        self.waiting.retain(|id| !fetched.contains(id));
+        if !self.waiting.is_empty() && self.sent.elapsed() >= FETCH_TIMEOUT {
+            return std::mem::take(&mut self.waiting);
+        }
+        HashSet::new()
+    }
+
+    /// Encode the converted frame of the high tier for the viewers of the low tier, skipped
+    /// while they have not fetched the previous one.
+    pub fn send(
+        &mut self,
+        sp: &GenericService,
+        display: usize,
+        yuv: &[u8],
+        yuvfmt: &EncodeYuvFormat,
+        ms: i64,
+    ) -> ResultType<()> {
+        if self.conn_ids.is_empty() || !self.waiting.is_empty() {
+            return Ok(());
+        }
+        scrap::convert::scale_yuv(yuv, yuvfmt, &mut self.yuv, &self.encoder.yuvfmt())?;
+        let encode_start = Instant::now();
+        match self
+            .encoder
+            .encode_to_message(EncodeInput::YUV(&self.yuv), ms)
+        {
+            Ok(mut vf) => {
+                let encode_time = encode_start.elapsed();
+                vf.display = display as _;
+                let mut msg = Message::new();
+                msg.set_video_frame(vf);
+                let bytes = msg.compute_size() as usize;
+                let conn_ids = &self.conn_ids;
+                let sent = sp.send_video_frame_filter(Arc::new(msg), |id| conn_ids.contains(&id));
+                VIDEO_QOS
+                    .lock()
+                    .unwrap()
+                    .on_frame_sent(&sent, bytes, encode_time);
+                self.waiting = sent;
+                self.sent = Instant::now();
+            }
+            Err(e) => {
+                VIDEO_QOS.lock().unwrap().on_encode_failed(&self.conn_ids);
+                log::error!("encode low tier fail: {e:?}");
+                if e.to_string() == scrap::codec::ENCODE_NEED_SWITCH {
+                    bail!("SWITCH");
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simulcast_low_tier_size() {
+        assert_eq!(low_tier_size(1920, 1080), (960, 540));
+        assert_eq!(low_tier_size(1366, 768), (682, 384));
+        assert_eq!(low_tier_size(3, 3), (2, 2));
+    }
+}
@@ -1,7 +1,8 @@
-use super::*;
+use super::{simulcast::Tier, *};
 use scrap::codec::{Quality, BR_BALANCED, BR_BEST, BR_SPEED};
+use serde_derive::Serialize;
 use std::{
-    collections::VecDeque,
+    collections::{HashSet, VecDeque},
     time::{Duration, Instant},
 };
 
@@ -25,6 +26,18 @@ adjust between FPS and ratio:
 
 delay:
     use delay minus RTT as the actual network delay
+
+resolution adjust (OPTION_ADAPTIVE_RESOLUTION):
+    When the ratio is at its minimum and network delay stays >= DELAY_THRESHOLD_300MS for DOWNSCALE_AFTER
+    adjustments, encode at the next lower RESOLUTION_SCALES;
+    When network delay stays < 100ms for UPSCALE_AFTER adjustments, go back to the next higher scale;
+    Only applied when all users asked for the scaled frames, see scaled_frames.rs;
+
+simulcast tiers (OPTION_SIMULCAST, see simulcast.rs):
+    Mobile peers are always in the low tier, the others move to it after DOWNSCALE_AFTER congested
+    adjustments and back after UPSCALE_AFTER good ones;
+    Only the users which asked for the scaled frames go to the low tier, see scaled_frames.rs;
+    The fps and ratio are adjusted to the users of the high tier only;
 */
 
 // Constants
@@ -43,6 +56,16 @@ const HISTORY_DELAY_LEN: usize = 2;
 const ADJUST_RATIO_INTERVAL: usize = 3; // Adjust quality ratio every 3 seconds
 const DYNAMIC_SCREEN_THRESHOLD: usize = 2; // Allow increase quality ratio if encode more than 2 times in one second
 const DELAY_THRESHOLD_150MS: u32 = 150; // 150ms is the threshold for good network condition
+const STATS_WINDOW: Duration = Duration::from_secs(1); // fps and bitrate are measured every second
+const DELAY_THRESHOLD_300MS: u32 = 300; // Congestion that lowering the quality did not solve
+const RESOLUTION_SCALES: [f32; 3] = [1.0, 0.75, 0.5];
+const DOWNSCALE_AFTER: usize = 3; // 9 seconds of congestion
+const UPSCALE_AFTER: usize = 10; // 30 seconds of good network, slower to avoid flapping
+const PROBE_HEADROOM: f32 = 0.7; // Share of the probed bandwidth used by the video
+
+/// Host option, "Y" to lower the encoded resolution under sustained congestion.
+/// Only applied to the controllers which scale the frames back to the display size.
+pub const OPTION_ADAPTIVE_RESOLUTION: &str = "allow-adaptive-resolution";
 
 #[derive(Default, Debug, Clone)]
 struct UserDelay {
@@ -85,6 +108,87 @@ impl UserDelay {
     }
 }
 
+// Encode and network statistics of one connection
+#[derive(Debug, Clone)]
+struct UserStats {
+    frames: u64,
+    dropped: u64,
+    capture_would_block: u64,
+    encode_failed: u64,
+    encode_ms: f32, // exponential moving average
+    window_start: Instant,
+    window_frames: u32,
+    window_bytes: usize,
+    fps: u32,
+    kbps: u32,
+}
+
+impl Default for UserStats {
+    fn default() -> Self {
+        UserStats {
+            frames: 0,
+            dropped: 0,
+            capture_would_block: 0,
+            encode_failed: 0,
+            encode_ms: 0.,
+            window_start: Instant::now(),
+            window_frames: 0,
+            window_bytes: 0,
+            fps: 0,
+            kbps: 0,
+        }
+    }
+}
+
+impl UserStats {
+    fn add_frame(&mut self, bytes: usize, encode_time: Duration) {
+        let encode_ms = encode_time.as_secs_f32() * 1000.;
+        self.encode_ms = if self.frames == 0 {
+            encode_ms
+        } else {
+            self.encode_ms * 0.9 + encode_ms * 0.1
+        };
+        self.frames += 1;
+        self.window_frames += 1;
+        self.window_bytes += bytes;
+        self.check_window();
+    }
+
+    fn check_window(&mut self) {
+        let elapsed = self.window_start.elapsed();
+        if elapsed < STATS_WINDOW {
+            return;
+        }
+        let ms = elapsed.as_millis().max(1) as usize;
+        self.fps = (self.window_frames as usize * 1000 / ms) as _;
+        self.kbps = (self.window_bytes * 8 / ms) as _;
+        self.window_start = Instant::now();
+        self.window_frames = 0;
+        self.window_bytes = 0;
+    }
+}
+
+/// Snapshot of the statistics of one connection, returned by the `SessionStats` ipc query.
+///
+/// The lost frames are counted by stage, so that lag reports can be triaged.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionStats {
+    pub conn_id: i32,
+    pub fps: u32,
+    pub target_fps: u32,
+    pub encode_ms: f32,
+    pub frames: u64,
+    /// Sent frames not fetched by the connection in time, the send queue or the network is too slow.
+    pub dropped: u64,
+    /// Captures without a new frame, normal on a static screen, a capturer issue otherwise.
+    pub capture_would_block: u64,
+    /// Captured frames the encoder failed to encode.
+    pub encode_failed: u64,
+    pub kbps: u32,
+    pub rtt: Option<u32>,
+    pub delay: u32,
+}
+
 // User session data structure
 #[derive(Default, Debug, Clone)]
 struct UserData {
@@ -93,6 +197,13 @@ struct UserData {
     quality: Option<(i64, Quality)>, // (time, quality)
     delay: UserDelay,
     record: bool,
+    stats: UserStats,
+    weak_device: bool,
+    tier: Tier,
+    tier_count: usize, // Consecutive ratio adjustments in favor of the other tier
+    probe_kbps: Option<u32>, // Bandwidth probed by the client, not applied yet
+    hidden: bool,      // The window of the client is not seen
+    scaled_frames: bool, // The client shows the frames smaller than the display
 }
 
 #[derive(Default, Debug, Clone)]
@@ -111,6 +222,11 @@ pub struct VideoQoS {
     adjust_ratio_instant: Instant,
     abr_config: bool,
     new_user_instant: Instant,
+    adaptive_resolution: bool,
+    scale_index: usize,     // Index in RESOLUTION_SCALES
+    congested_count: usize, // Consecutive ratio adjustments with congestion
+    clear_count: usize,     // Consecutive ratio adjustments with good network
+    simulcast: bool,
 }
 
 impl Default for VideoQoS {
@@ -124,6 +240,11 @@ impl Default for VideoQoS {
             adjust_ratio_instant: Instant::now(),
             abr_config: true,
             new_user_instant: Instant::now(),
+            adaptive_resolution: false,
+            scale_index: 0,
+            congested_count: 0,
+            clear_count: 0,
+            simulcast: false,
         }
     }
 }
@@ -163,6 +284,15 @@ impl VideoQoS {
         self.ratio
     }
 
+    // Scale of the encoded resolution, 1.0 for the native resolution or if a user can not show
+    // the scaled frames
+    pub fn resolution_scale(&self) -> f32 {
+        if self.users.values().any(|u| !u.scaled_frames) {
+            return 1.0;
+        }
+        RESOLUTION_SCALES[self.scale_index]
+    }
+
     // Check if any user is in recording mode
     pub fn record(&self) -> bool {
         self.users.iter().any(|u| u.1.record)
@@ -174,6 +304,32 @@ impl VideoQoS {
         }
     }
 
+    // Users of the low simulcast tier, empty if all users are in it, the only stream adapts to them then
+    pub fn low_tier_ids(&self) -> HashSet<i32> {
+        if !self.simulcast || self.users.values().all(|u| u.tier == Tier::Low) {
+            return HashSet::new();
+        }
+        self.users
+            .iter()
+            .filter(|(_, u)| u.tier == Tier::Low)
+            .map(|(id, _)| *id)
+            .collect()
+    }
+
+    // Users the fps and ratio are adjusted to, the hidden ones only if all of them are hidden
+    fn high_tier_users(&self) -> impl Iterator<Item = &UserData> {
+        let low_tier_ids = self.low_tier_ids();
+        let all_hidden = self
+            .users
+            .iter()
+            .filter(|(id, _)| !low_tier_ids.contains(id))
+            .all(|(_, u)| u.hidden);
+        self.users
+            .iter()
+            .filter(move |(id, u)| !low_tier_ids.contains(id) && (all_hidden || !u.hidden))
+            .map(|(_, u)| u)
+    }
+
     // Check if variable bitrate encoding is supported and enabled
     pub fn in_vbr_state(&self) -> bool {
         self.abr_config && self.displays.iter().all(|e| e.1.support_changing_quality)
@@ -186,6 +342,11 @@ impl VideoQoS {
     pub fn on_connection_open(&mut self, id: i32) {
         self.users.insert(id, UserData::default());
         self.abr_config = Config::get_option("enable-abr") != "N";
+        self.adaptive_resolution = Config::get_option(OPTION_ADAPTIVE_RESOLUTION) == "Y";
+        if !self.adaptive_resolution {
+            self.scale_index = 0;
+        }
+        self.simulcast = Config::get_option(super::simulcast::OPTION_SIMULCAST) == "Y";
         self.new_user_instant = Instant::now();
     }
 
@@ -206,6 +367,12 @@ impl VideoQoS {
         }
     }
 
+    pub fn user_hidden(&mut self, id: i32, hidden: bool) {
+        if let Some(user) = self.users.get_mut(&id) {
+            user.hidden = hidden;
+        }
+    }
+
     pub fn user_auto_adjust_fps(&mut self, id: i32, fps: u32) {
         if fps < MIN_FPS || fps > MAX_FPS {
             return;
@@ -237,6 +404,28 @@ impl VideoQoS {
         }
     }
 
+    // Mobile peers, always in the low tier of simulcast if they can show its frames
+    pub fn user_weak_device(&mut self, id: i32, v: bool) {
+        if let Some(user) = self.users.get_mut(&id) {
+            user.weak_device = v;
+            if v && self.simulcast && user.scaled_frames {
+                user.tier = Tier::Low;
+            }
+        }
+    }
+
+    // Only these users are put into the low tier of simulcast, its frames are scaled
+    pub fn user_scaled_frames(&mut self, id: i32, v: bool) {
+        if let Some(user) = self.users.get_mut(&id) {
+            user.scaled_frames = v;
+            if !v {
+                user.tier = Tier::High;
+            } else if user.weak_device && self.simulcast {
+                user.tier = Tier::Low;
+            }
+        }
+    }
+
     pub fn user_record(&mut self, id: i32, v: bool) {
         if let Some(user) = self.users.get_mut(&id) {
             user.record = v;
@@ -332,6 +521,18 @@ impl VideoQoS {
         }
     }
 
+    // Seed the delay and the ratio with the bandwidth probe of the client, see `bandwidth_probe`
+    pub fn user_bandwidth_probe(&mut self, id: i32, kbps: u32, rtt: u32) {
+        if !self.users.contains_key(&id) {
+            return;
+        }
+        self.user_network_delay(id, rtt);
+        if let Some(user) = self.users.get_mut(&id) {
+            user.probe_kbps = (kbps > 0).then_some(kbps);
+        }
+        self.apply_probes();
+    }
+
     pub fn user_delay_response_elapsed(&mut self, id: i32, elapsed: u128) {
         if let Some(user) = self.users.get_mut(&id) {
             user.delay.response_delayed = elapsed > 2000;
@@ -343,6 +544,72 @@ impl VideoQoS {
     }
 }
 
+// Statistics
+impl VideoQoS {
+    // A frame of `bytes` has been encoded and sent to `conn_ids`
+    pub fn on_frame_sent(&mut self, conn_ids: &HashSet<i32>, bytes: usize, encode_time: Duration) {
+        for id in conn_ids {
+            if let Some(user) = self.users.get_mut(id) {
+                user.stats.add_frame(bytes, encode_time);
+            }
+        }
+    }
+
+    // `conn_ids` did not fetch the frame before timeout
+    pub fn on_frame_dropped(&mut self, conn_ids: &HashSet<i32>) {
+        for id in conn_ids {
+            if let Some(user) = self.users.get_mut(id) {
+                user.stats.dropped += 1;
+            }
+        }
+    }
+
+    // The capturer returned no frame for `conn_ids`
+    pub fn on_capture_would_block(&mut self, conn_ids: &HashSet<i32>) {
+        for id in conn_ids {
+            if let Some(user) = self.users.get_mut(id) {
+                user.stats.capture_would_block += 1;
+            }
+        }
+    }
+
+    // The frame for `conn_ids` failed to encode
+    pub fn on_encode_failed(&mut self, conn_ids: &HashSet<i32>) {
+        for id in conn_ids {
+            if let Some(user) = self.users.get_mut(id) {
+                user.stats.encode_failed += 1;
+            }
+        }
+    }
+
+    pub fn session_stats(&mut self) -> Vec<SessionStats> {
+        let fps = self.fps;
+        let mut stats: Vec<SessionStats> = self
+            .users
+            .iter_mut()
+            .map(|(id, user)| {
+                // No frame is sent on static screens, update the window here.
+                user.stats.check_window();
+                SessionStats {
+                    conn_id: *id,
+                    fps: user.stats.fps,
+                    target_fps: fps,
+                    encode_ms: user.stats.encode_ms,
+                    frames: user.stats.frames,
+                    dropped: user.stats.dropped,
+                    capture_would_block: user.stats.capture_would_block,
+                    encode_failed: user.stats.encode_failed,
+                    kbps: user.stats.kbps,
+                    rtt: user.delay.rtt_calculator.get_rtt(),
+                    delay: user.delay.avg_delay(),
+                }
+            })
+            .collect();
+        stats.sort_by_key(|s| s.conn_id);
+        stats
+    }
+}
+
 // Common adjust functions
 impl VideoQoS {
     pub fn new_display(&mut self, video_service_name: String) {
@@ -358,6 +625,7 @@ impl VideoQoS {
         if let Some(display) = self.displays.get_mut(video_service_name) {
             display.send_counter += send_counter;
         }
+        self.apply_probes();
         self.adjust_fps();
         let abr_enabled = self.in_vbr_state();
         if abr_enabled {
@@ -379,6 +647,9 @@ impl VideoQoS {
     #[inline]
     fn highest_fps(&self) -> u32 {
         let user_fps = |u: &UserData| {
+            if u.hidden {
+                return MIN_FPS;
+            }
             let mut fps = u.custom_fps.unwrap_or(FPS);
             if let Some(auto_adjust_fps) = u.auto_adjust_fps {
                 if fps == 0 || auto_adjust_fps < fps {
@@ -389,9 +660,8 @@ impl VideoQoS {
         };
 
         let fps = self
-            .users
-            .iter()
-            .map(|(_, u)| user_fps(u))
+            .high_tier_users()
+            .map(|u| user_fps(u))
             .filter(|u| *u >= MIN_FPS)
             .min()
             .unwrap_or(FPS);
@@ -411,13 +681,38 @@ impl VideoQoS {
             .1
     }
 
+    // The ratio of the probed bandwidth, once the bitrate of the encoder is known. It only lowers
+    // the ratio set for the other users.
+    fn apply_probes(&mut self) {
+        let bitrate = self.bitrate();
+        if bitrate == 0 || !self.in_vbr_state() {
+            return;
+        }
+        let first = self.users.len() == 1;
+        let Some(kbps) = self
+            .users
+            .values_mut()
+            .filter_map(|u| u.probe_kbps.take())
+            .min()
+        else {
+            return;
+        };
+        let max = self.latest_quality().ratio() * MAX_BR_MULTIPLE;
+        let ratio = (self.ratio * kbps as f32 * PROBE_HEADROOM / bitrate as f32)
+            .clamp(BR_MIN_HIGH_RESOLUTION, max);
+        self.ratio = if first { ratio } else { ratio.min(self.ratio) };
+        self.adjust_ratio_instant = Instant::now();
+        log::info!("bandwidth probe of {} kbps, ratio {}", kbps, self.ratio);
+    }
+
     // Adjust quality ratio based on network delay and screen changes
     fn adjust_ratio(&mut self, dynamic_screen: bool) {
         if !self.in_vbr_state() {
             return;
         }
-        // Get maximum delay from all users
-        let max_delay = self.users.iter().map(|u| u.1.delay.avg_delay()).max();
+        self.adjust_tiers();
+        // Get maximum delay from the users of the high tier
+        let max_delay = self.high_tier_users().map(|u| u.delay.avg_delay()).max();
         let Some(max_delay) = max_delay else {
             return;
         };
@@ -504,20 +799,86 @@ impl VideoQoS {
 
         self.ratio = v.clamp(min, max);
         self.adjust_ratio_instant = Instant::now();
+        self.adjust_resolution(max_delay, self.ratio <= min);
+    }
+
+    // Lower the resolution only when the quality can not be lowered any more
+    fn adjust_resolution(&mut self, max_delay: u32, min_ratio: bool) {
+        if !self.adaptive_resolution {
+            return;
+        }
+        if max_delay >= DELAY_THRESHOLD_300MS && min_ratio {
+            self.congested_count += 1;
+            self.clear_count = 0;
+        } else if max_delay < 100 {
+            self.clear_count += 1;
+            self.congested_count = 0;
+        } else {
+            self.congested_count = 0;
+            self.clear_count = 0;
+        }
+        let index = self.scale_index;
+        if self.congested_count >= DOWNSCALE_AFTER && index + 1 < RESOLUTION_SCALES.len() {
+            self.scale_index += 1;
+        } else if self.clear_count >= UPSCALE_AFTER && index > 0 {
+            self.scale_index -= 1;
+        }
+        if self.scale_index != index {
+            log::info!(
+                "resolution scale: {} -> {}, delay: {}ms",
+                RESOLUTION_SCALES[index],
+                RESOLUTION_SCALES[self.scale_index],
+                max_delay
+            );
+            self.congested_count = 0;
+            self.clear_count = 0;
+        }
+    }
+
+    // Move the users between the simulcast tiers, slowly to avoid switching the streams too often
+    fn adjust_tiers(&mut self) {
+        if !self.simulcast {
+            return;
+        }
+        for (id, user) in self.users.iter_mut() {
+            if !user.scaled_frames {
+                user.tier = Tier::High;
+                user.tier_count = 0;
+                continue;
+            }
+            if user.weak_device {
+                user.tier = Tier::Low;
+                continue;
+            }
+            let delay = user.delay.avg_delay();
+            let (other, move_to_other, after) = match user.tier {
+                Tier::High => (Tier::Low, delay >= DELAY_THRESHOLD_300MS, DOWNSCALE_AFTER),
+                Tier::Low => (Tier::High, delay < 100, UPSCALE_AFTER),
+            };
+            if move_to_other {
+                user.tier_count += 1;
+            } else {
+                user.tier_count = 0;
+            }
+            if user.tier_count >= after {
+                log::info!("simulcast tier of {}: {:?} -> {:?}", id, user.tier, other);
+                user.tier = other;
+                user.tier_count = 0;
+            }
+        }
     }
 
     // Adjust fps based on network delay and user response time
     fn adjust_fps(&mut self) {
         let highest_fps = self.highest_fps();
-        // Get minimum fps from all users
+        // Get minimum fps from the users of the high tier
         let mut fps = self
-            .users
-            .iter()
-            .map(|u| u.1.delay.fps.unwrap_or(INIT_FPS))
+            .high_tier_users()
+            .map(|u| u.delay.fps.unwrap_or(INIT_FPS))
             .min()
             .unwrap_or(INIT_FPS);
 
-        if self.users.iter().any(|u| u.1.delay.response_delayed) {
+        if self.high_tier_users().any(|u| u.delay.response_delayed) {
             if fps > MIN_FPS + 1 {
                 fps = MIN_FPS + 1;
             }
@@ -18,7 +18,13 @@
 // to-do:
 // https://slhck.info/video/2017/03/01/rate-control.html
 
-use super::{display_service::check_display_changed, service::ServiceTmpl, video_qos::VideoQoS, *};
+use super::{
+    display_service::check_display_changed,
+    service::ServiceTmpl,
+    simulcast::{self, LowTier},
+    video_qos::VideoQoS,
+    *,
+};
 use crate::privacy_mode::{get_privacy_mode_conn_id, INVALID_PRIVACY_MODE_CONN_ID};
 use hbb_common::{
     config,
@@ -33,7 +39,7 @@ use scrap::hwcodec::{HwRamEncoder, HwRamEncoderConfig};
 use scrap::vram::{VRamEncoder, VRamEncoderConfig};
 use scrap::Capturer;
 use scrap::{
-    aom::AomEncoderConfig,
+    aom::{AomEncoderConfig, AomTuning},
     codec::{Encoder, EncoderCfg},
     record::{Recorder, RecorderContext},
     vpxcodec::{VpxEncoderConfig, VpxVideoCodecId},
@@ -47,6 +53,17 @@ use std::{
 };
 
 pub const OPTION_REFRESH: &'static str = "refresh";
+/// Encode the next frame as a key frame, e.g. after the client failed to decode.
+pub const OPTION_KEY_FRAME: &'static str = "key-frame";
+/// Container of incoming session recordings: "" (native webm/mp4), "mp4" or "mkv".
+pub const OPTION_RECORD_CONTAINER: &'static str = "record-container";
+/// AV1 screen content tools: "Y" also enables intra block copy, "N" tunes for camera content.
+pub const OPTION_AV1_SCREEN_CONTENT: &'static str = "av1-screen-content";
+/// AV1 speed preset (cpu-used), 6 - 10, lower is slower with better quality.
+pub const OPTION_AV1_CPU_USED: &'static str = "av1-cpu-used";
+/// AV1 tiles, log2 of the tile columns and rows.
+pub const OPTION_AV1_TILE_COLUMNS: &'static str = "av1-tile-columns";
+pub const OPTION_AV1_TILE_ROWS: &'static str = "av1-tile-rows";
 
 type FrameFetchedNotifierSender = UnboundedSender<(i32, Option<Instant>)>;
 type FrameFetchedNotifierReceiver = Arc<TokioMutex<UnboundedReceiver<(i32, Option<Instant>)>>>;
@@ -130,6 +147,18 @@ impl VideoFrameController {
         }
     }
 
+    // The viewers of the low simulcast tier, they are not waited for.
+    fn add_low_tier(&self, conn_ids: &HashSet<i32>) {
+        if !conn_ids.is_empty() {
+            DISPLAY_CONN_IDS
+                .lock()
+                .unwrap()
+                .entry(self.display_idx)
+                .or_default()
+                .extend(conn_ids);
+        }
+    }
+
     #[tokio::main(flavor = "current_thread")]
     async fn try_wait_next(&mut self, fetched_conn_ids: &mut HashSet<i32>, timeout_millis: u64) {
         if self.send_conn_ids.is_empty() {
@@ -180,6 +209,8 @@ impl VideoFrameController {
 pub enum VideoSource {
     Monitor,
     Camera,
+    /// A single application window, see `shared_window`.
+    Window,
 }
 
 impl VideoSource {
@@ -187,6 +218,7 @@ impl VideoSource {
         match self {
             VideoSource::Monitor => "monitor",
             VideoSource::Camera => "camera",
+            VideoSource::Window => "window",
         }
     }
 
@@ -197,6 +229,10 @@ impl VideoSource {
     pub fn is_camera(&self) -> bool {
         matches!(self, VideoSource::Camera)
     }
+
+    pub fn is_window(&self) -> bool {
+        matches!(self, VideoSource::Window)
+    }
 }
 
 #[derive(Clone)]
@@ -330,7 +366,11 @@ fn get_capturer_monitor(current: usize) -> ResultType<CapturerInfo> {
 }
 
 fn get_capturer_camera(current: usize) -> ResultType<CapturerInfo> {
-    let cameras = camera::Cameras::get_sync_cameras();
+    let mut cameras = camera::Cameras::get_sync_cameras();
+    if cameras.len() <= current {
+        // Plugged after the login.
+        cameras = camera::Cameras::all_info()?;
+    }
     let ncamera = cameras.len();
     if ncamera <= current {
         bail!("Failed to get camera {}, cameras len: {}", current, ncamera,);
@@ -369,10 +409,53 @@ fn get_capturer_camera(current: usize) -> ResultType<CapturerInfo> {
         capturer,
     });
 }
+
+#[cfg(not(target_os = "macos"))]
+fn get_capturer_window(_current: usize) -> ResultType<CapturerInfo> {
+    bail!("Sharing a window is not supported");
+}
+
+#[cfg(target_os = "macos")]
+fn get_capturer_window(current: usize) -> ResultType<CapturerInfo> {
+    if current != super::shared_window::WINDOW_IDX {
+        bail!(
+            "Failed to get window {}, only one window is shared",
+            current
+        );
+    }
+    let Some(window) = super::shared_window::get() else {
+        bail!("No shared window");
+    };
+    let capturer =
+        scrap::WindowCapturer::new(window.id).with_context(|| "Failed to capture window")?;
+    let (width, height) = (capturer.width(), capturer.height());
+    let origin = (window.x.round() as i32, window.y.round() as i32);
+    let privacy_mode_id = get_privacy_mode_conn_id().unwrap_or(INVALID_PRIVACY_MODE_CONN_ID);
+    log::debug!(
+        "window={}, origin: {:?}, width={}, height={}, owner:{}",
+        window.id,
+        &origin,
+        width,
+        height,
+        &window.owner,
+    );
+    Ok(CapturerInfo {
+        origin,
+        width,
+        height,
+        ndisplay: 1,
+        current,
+        privacy_mode_id,
+        _capturer_privacy_mode_id: privacy_mode_id,
+        capturer: Box::new(capturer),
+    })
+}
+
 fn get_capturer(source: VideoSource, current: usize) -> ResultType<CapturerInfo> {
     match source {
         VideoSource::Monitor => get_capturer_monitor(current),
         VideoSource::Camera => get_capturer_camera(current),
+        VideoSource::Window => get_capturer_window(current),
     }
 }
 
@@ -389,9 +472,19 @@ fn run(vs: VideoService) -> ResultType<()> {
         &Config::get_option("allow-auto-record-incoming"),
     );
     let client_record = video_qos.record();
+    let resolution_scale = video_qos.resolution_scale();
+    let simulcast = !video_qos.low_tier_ids().is_empty();
     drop(video_qos);
+    // Cameras are small, only displays are downscaled.
+    let (encode_width, encode_height) = if resolution_scale < 1.0 && vs.source.is_monitor() {
+        let scale = |x: usize| ((x as f32 * resolution_scale) as usize / 2 * 2).max(2);
+        (scale(c.width), scale(c.height))
+    } else {
+        (c.width, c.height)
+    };
     let (mut encoder, encoder_cfg, codec_format, use_i444, recorder) = match setup_encoder(
         &c,
+        (encode_width, encode_height),
         sp.name(),
         quality,
         client_record,
@@ -403,14 +496,15 @@ fn run(vs: VideoService) -> ResultType<()> {
         Err(err) => {
             log::error!("Failed to create encoder: {err:?}, fallback to VP9");
             Encoder::set_fallback(&EncoderCfg::VPX(VpxEncoderConfig {
-                width: c.width as _,
-                height: c.height as _,
+                width: encode_width as _,
+                height: encode_height as _,
                 quality,
                 codec: VpxVideoCodecId::VP9,
                 keyframe_interval: None,
             }));
             setup_encoder(
                 &c,
+                (encode_width, encode_height),
                 sp.name(),
                 quality,
                 client_record,
@@ -428,10 +522,42 @@ fn run(vs: VideoService) -> ResultType<()> {
         .unwrap()
         .set_support_changing_quality(&sp.name(), encoder.support_changing_quality());
     log::info!("initial quality: {quality:?}");
+    // The low tier is scaled from the converted frame, not available for textures.
+    let mut low_tier = if simulcast && !encoder.input_texture() {
+        let size = simulcast::low_tier_size(encode_width, encode_height);
+        log::info!("simulcast low tier {}x{}", size.0, size.1);
+        let cfg = get_encoder_config(
+            &c,
+            size,
+            sp.name(),
+            simulcast::LOW_TIER_RATIO,
+            false,
+            vs.source,
+        );
+        Some(LowTier::new(Encoder::new(cfg, use_i444)?))
+    } else {
+        None
+    };
+    // The frames are processed at the captured size, then scaled to the encoded size.
+    let capture_yuvfmt = ((encode_width, encode_height) != (c.width, c.height)).then(|| {
+        log::info!(
+            "downscale {}x{} to {}x{}",
+            c.width,
+            c.height,
+            encode_width,
+            encode_height
+        );
+        scrap::convert::yuvfmt_with_size(&encoder.yuvfmt(), c.width, c.height)
+    });
+    let mut capture_yuv = Vec::new();
 
     if sp.is_option_true(OPTION_REFRESH) {
         sp.set_option_bool(OPTION_REFRESH, false);
     }
+    // The first frame of a new encoder is a key frame.
+    if sp.is_option_true(OPTION_KEY_FRAME) {
+        sp.set_option_bool(OPTION_KEY_FRAME, false);
+    }
 
     let mut frame_controller = VideoFrameController::new(display_idx);
 
@@ -443,9 +569,17 @@ fn run(vs: VideoService) -> ResultType<()> {
     let repeat_encode_max = 10;
     let mut encode_fail_counter = 0;
     let mut first_frame = true;
-    let capture_width = c.width;
-    let capture_height = c.height;
+    let capture_width = encode_width;
+    let capture_height = encode_height;
     let (mut second_instant, mut send_counter) = (Instant::now(), 0);
+    let mut watermark = super::watermark::Watermark::new();
+    let mut last_pts = -1;
+    let mut idle_detector = super::idle_detector::IdleDetector::new();
+    let mut region_filter = super::region_filter::RegionFilter::new(display_idx);
+    let mut display_power = vs
+        .source
+        .is_monitor()
+        .then(|| crate::display_power::Watcher::new(display_idx));
 
     while sp.ok() {
         check_qos(
@@ -453,6 +587,8 @@ fn run(vs: VideoService) -> ResultType<()> {
             &mut quality,
             &mut spf,
             client_record,
+            resolution_scale,
+            simulcast,
             &mut send_counter,
             &mut second_instant,
             &sp.name(),
@@ -460,10 +596,45 @@ fn run(vs: VideoService) -> ResultType<()> {
         if sp.is_option_true(OPTION_REFRESH) {
             if vs.source.is_monitor() {
                 let _ = try_broadcast_display_changed(&sp, display_idx, &c, true);
+            } else if vs.source.is_camera() {
+                // The camera resolution may be changed.
+                if let Some(msg_out) =
+                    make_display_changed_msg(display_idx, None, VideoSource::Camera)
+                {
+                    sp.send_shared(Arc::new(msg_out));
+                }
             }
             log::info!("switch to refresh");
             bail!("SWITCH");
         }
+        let mut key_frame = false;
+        if sp.is_option_true(OPTION_KEY_FRAME) {
+            sp.set_option_bool(OPTION_KEY_FRAME, false);
+            key_frame = true;
+        }
+        if let Some(low_tier) = low_tier.as_mut() {
+            let subscriber_ids = sp.subscriber_ids();
+            let mut conn_ids: HashSet<i32> = VIDEO_QOS
+                .lock()
+                .unwrap()
+                .low_tier_ids()
+                .intersection(&subscriber_ids)
+                .cloned()
+                .collect();
+            if conn_ids.len() == subscriber_ids.len() {
+                // Only weak viewers on this display, the high tier adapts to them.
+                conn_ids.clear();
+            }
+            key_frame |= low_tier.set_conn_ids(conn_ids)?;
+        }
+        if key_frame && !encoder.request_key_frame() {
+            log::info!("switch to get a key frame");
+            bail!("SWITCH");
+        }
+        let low_tier_ids = low_tier
+            .as_ref()
+            .map(|t| t.conn_ids().clone())
+            .unwrap_or_default();
         if codec_format != Encoder::negotiated_codec() {
             log::info!(
                 "switch due to codec changed, {:?} -> {:?}",
@@ -486,15 +657,27 @@ fn run(vs: VideoService) -> ResultType<()> {
             // The previous check in `sp.is_option_true(OPTION_REFRESH)` block may be enough.
             try_broadcast_display_changed(&sp, display_idx, &c, false)?;
         }
+        if let Some(watcher) = display_power.as_mut() {
+            if let Some(msg_out) = watcher.check(&sp.subscriber_ids()) {
+                sp.send_shared(Arc::new(msg_out));
+            }
+        }
 
         frame_controller.reset();
 
-        let time = now - start;
-        let ms = (time.as_secs() * 1000 + time.subsec_millis() as u64) as i64;
         let res = match c.frame(spf) {
             Ok(frame) => {
                 repeat_encode_counter = 0;
                 if frame.valid() {
+                    match &frame {
+                        scrap::Frame::PixelBuffer(f) => match f.stride().first() {
+                            Some(stride) => idle_detector.on_frame(f.data(), *stride, f.height()),
+                            None => idle_detector.on_unknown(),
+                        },
+                        _ => idle_detector.on_unknown(),
+                    }
+                    // The capture time is the presentation timestamp, clients pace the playback with it.
+                    let ms = next_pts(start, &mut last_pts);
                     let screenshot = SCREENSHOTS.lock().unwrap().remove(&display_idx);
                     if let Some(mut screenshot) = screenshot {
                         let restore_vram = screenshot.restore_vram;
@@ -536,7 +719,39 @@ fn run(vs: VideoService) -> ResultType<()> {
                         }
                     }
 
-                    let frame = frame.to(encoder.yuvfmt(), &mut yuv, &mut mid_data)?;
+                    let filter_regions = !region_filter.is_empty();
+                    let frame = match (&mut watermark, &frame) {
+                        (watermark, scrap::Frame::PixelBuffer(_))
+                            if watermark.is_some()
+                                || filter_regions
+                                || capture_yuvfmt.is_some() =>
+                        {
+                            let yuvfmt = capture_yuvfmt.clone().unwrap_or_else(|| encoder.yuvfmt());
+                            let buf = if capture_yuvfmt.is_some() {
+                                &mut capture_yuv
+                            } else {
+                                &mut yuv
+                            };
+                            frame.to(yuvfmt.clone(), buf, &mut mid_data)?;
+                            // Filter first, the watermark must stay visible over hidden regions.
+                            if filter_regions {
+                                region_filter.apply(buf, &yuvfmt);
+                            }
+                            if let Some(watermark) = watermark {
+                                watermark.draw(buf, &yuvfmt);
+                            }
+                            if capture_yuvfmt.is_some() {
+                                scrap::convert::scale_yuv(
+                                    &capture_yuv,
+                                    &yuvfmt,
+                                    &mut yuv,
+                                    &encoder.yuvfmt(),
+                                )?;
+                            }
+                            EncodeInput::YUV(&yuv)
+                        }
+                        _ => frame.to(encoder.yuvfmt(), &mut yuv, &mut mid_data)?,
+                    };
                     let send_conn_ids = handle_one_frame(
                         display_idx,
                         &sp,
@@ -548,8 +763,15 @@ fn run(vs: VideoService) -> ResultType<()> {
                         &mut first_frame,
                         capture_width,
                         capture_height,
+                        &low_tier_ids,
                     )?;
                     frame_controller.set_send(now, send_conn_ids);
+                    if let Some(low_tier) = low_tier.as_mut() {
+                        if !yuv.is_empty() {
+                            low_tier.send(&sp, display_idx, &yuv, &encoder.yuvfmt(), ms)?;
+                        }
+                        frame_controller.add_low_tier(low_tier.waiting());
+                    }
                     send_counter += 1;
                 }
                 Ok(())
@@ -559,6 +781,11 @@ fn run(vs: VideoService) -> ResultType<()> {
 
         match res {
             Err(ref e) if e.kind() == WouldBlock => {
+                idle_detector.on_no_change();
+                VIDEO_QOS
+                    .lock()
+                    .unwrap()
+                    .on_capture_would_block(&sp.subscriber_ids());
                 if !encoder.latency_free() && yuv.len() > 0 {
                     // yun.len() > 0 means the frame is not texture.
                     if repeat_encode_counter < repeat_encode_max {
@@ -567,13 +794,14 @@ fn run(vs: VideoService) -> ResultType<()> {
                             display_idx,
                             &sp,
                             EncodeInput::YUV(&yuv),
-                            ms,
+                            next_pts(start, &mut last_pts),
                             &mut encoder,
                             recorder.clone(),
                             &mut encode_fail_counter,
                             &mut first_frame,
                             capture_width,
                             capture_height,
+                            &low_tier_ids,
                         )?;
                         frame_controller.set_send(now, send_conn_ids);
                         send_counter += 1;
@@ -585,6 +813,13 @@ fn run(vs: VideoService) -> ResultType<()> {
                 // The previous check in `sp.is_option_true(OPTION_REFRESH)` block may be enough.
                 if vs.source.is_monitor() {
                     try_broadcast_display_changed(&sp, display_idx, &c, true)?;
+                } else if vs.source.is_window() {
+                    // The window may be resized, the capturer is recreated with the new size.
+                    if let Some(msg_out) =
+                        make_display_changed_msg(display_idx, None, VideoSource::Window)
+                    {
+                        sp.send_shared(Arc::new(msg_out));
+                    }
                 }
                 return Err(err.into());
             }
@@ -600,23 +835,53 @@ fn run(vs: VideoService) -> ResultType<()> {
             }
             frame_controller.try_wait_next(&mut fetched_conn_ids, 300);
             // break if all connections have received current frame
-            if fetched_conn_ids.len() >= frame_controller.send_conn_ids.len() {
+            if frame_controller.send_conn_ids.is_subset(&fetched_conn_ids) {
                 break;
             }
         }
-        DISPLAY_CONN_IDS.lock().unwrap().remove(&display_idx);
+        let mut dropped_conn_ids: HashSet<i32> = frame_controller
+            .send_conn_ids
+            .difference(&fetched_conn_ids)
+            .cloned()
+            .collect();
+        if let Some(low_tier) = low_tier.as_mut() {
+            dropped_conn_ids.extend(low_tier.on_fetched(&fetched_conn_ids));
+        }
+        if !dropped_conn_ids.is_empty() {
+            VIDEO_QOS
+                .lock()
+                .unwrap()
+                .on_frame_dropped(&dropped_conn_ids);
+        }
+        match low_tier.as_ref().filter(|t| !t.waiting().is_empty()) {
+            Some(low_tier) => {
+                DISPLAY_CONN_IDS
+                    .lock()
+                    .unwrap()
+                    .insert(display_idx, low_tier.waiting().clone());
+            }
+            None => {
+                DISPLAY_CONN_IDS.lock().unwrap().remove(&display_idx);
+            }
+        }
 
         let elapsed = now.elapsed();
         // may need to enable frame(timeout)
         log::trace!("{:?} {:?}", time::Instant::now(), elapsed);
-        if elapsed < spf {
-            std::thread::sleep(spf - elapsed);
-        }
+        idle_detector.wait(spf, elapsed);
     }
 
     Ok(())
 }
 
+/// Milliseconds since `start`, strictly increasing as required by the encoders.
+#[inline]
+fn next_pts(start: Instant, last_pts: &mut i64) -> i64 {
+    let pts = (start.elapsed().as_millis() as i64).max(*last_pts + 1);
+    *last_pts = pts;
+    pts
+}
+
 struct Raii {
     display_idx: usize,
     name: String,
@@ -651,6 +916,7 @@ impl Drop for Raii {
 
 fn setup_encoder(
     c: &CapturerInfo,
+    size: (usize, usize),
     name: String,
     quality: f32,
     client_record: bool,
@@ -666,6 +932,7 @@ fn setup_encoder(
 )> {
     let encoder_cfg = get_encoder_config(
         &c,
+        size,
         name.to_string(),
         quality,
         client_record || record_incoming,
@@ -681,6 +948,7 @@ fn setup_encoder(
 
 fn get_encoder_config(
     c: &CapturerInfo,
+    (width, height): (usize, usize),
     _name: String,
     quality: f32,
     record: bool,
@@ -693,39 +961,46 @@ fn get_encoder_config(
     let negotiated_codec = Encoder::negotiated_codec();
     match negotiated_codec {
         CodecFormat::H264 | CodecFormat::H265 => {
+            // The watermark, the region filter and the downscaling need the converted frame,
+            // not available for textures.
             #[cfg(feature = "vram")]
-            if let Some(feature) = VRamEncoder::try_get(&c.device(), negotiated_codec) {
-                return EncoderCfg::VRAM(VRamEncoderConfig {
-                    device: c.device(),
-                    width: c.width,
-                    height: c.height,
-                    quality,
-                    feature,
-                    keyframe_interval,
-                });
+            if !super::watermark::is_enabled()
+                && !super::region_filter::is_enabled()
+                && (width, height) == (c.width, c.height)
+            {
+                if let Some(feature) = VRamEncoder::try_get(&c.device(), negotiated_codec) {
+                    return EncoderCfg::VRAM(VRamEncoderConfig {
+                        device: c.device(),
+                        width,
+                        height,
+                        quality,
+                        feature,
+                        keyframe_interval,
+                    });
+                }
             }
             #[cfg(feature = "hwcodec")]
             if let Some(hw) = HwRamEncoder::try_get(negotiated_codec) {
                 return EncoderCfg::HWRAM(HwRamEncoderConfig {
                     name: hw.name,
                     mc_name: hw.mc_name,
-                    width: c.width,
-                    height: c.height,
+                    width,
+                    height,
                     quality,
                     keyframe_interval,
                 });
             }
             EncoderCfg::VPX(VpxEncoderConfig {
-                width: c.width as _,
-                height: c.height as _,
+                width: width as _,
+                height: height as _,
                 quality,
                 codec: VpxVideoCodecId::VP9,
                 keyframe_interval,
             })
         }
         format @ (CodecFormat::VP8 | CodecFormat::VP9) => EncoderCfg::VPX(VpxEncoderConfig {
-            width: c.width as _,
-            height: c.height as _,
+            width: width as _,
+            height: height as _,
             quality,
             codec: if format == CodecFormat::VP8 {
                 VpxVideoCodecId::VP8
@@ -735,14 +1010,15 @@ fn get_encoder_config(
             keyframe_interval,
         }),
         CodecFormat::AV1 => EncoderCfg::AOM(AomEncoderConfig {
-            width: c.width as _,
-            height: c.height as _,
+            width: width as _,
+            height: height as _,
             quality,
             keyframe_interval,
+            tuning: get_aom_tuning(),
         }),
         _ => EncoderCfg::VPX(VpxEncoderConfig {
-            width: c.width as _,
-            height: c.height as _,
+            width: width as _,
+            height: height as _,
             quality,
             codec: VpxVideoCodecId::VP9,
             keyframe_interval,
@@ -750,6 +1026,20 @@ fn get_encoder_config(
     }
 }
 
+fn get_aom_tuning() -> AomTuning {
+    let number = |key: &str| Config::get_option(key).trim().parse::<u32>().ok();
+    AomTuning {
+        screen_content: match Config::get_option(OPTION_AV1_SCREEN_CONTENT).as_str() {
+            "Y" => Some(true),
+            "N" => Some(false),
+            _ => None,
+        },
+        cpu_used: number(OPTION_AV1_CPU_USED),
+        tile_columns: number(OPTION_AV1_TILE_COLUMNS),
+        tile_rows: number(OPTION_AV1_TILE_ROWS),
+    }
+}
+
 fn get_recorder(
     record_incoming: bool,
     display_idx: usize,
@@ -766,12 +1056,15 @@ fn get_recorder(
         } else {
             None
         };
+        super::record_retention::start(root);
         Recorder::new(RecorderContext {
             server: true,
             id: Config::get_id(),
             dir: crate::ui_interface::video_save_directory(root),
             display_idx,
             camera,
+            container: Config::get_option(OPTION_RECORD_CONTAINER).as_str().into(),
+            rotate: super::record_retention::rotate_duration(),
             tx,
         })
         .map_or(Default::default(), |r| Arc::new(Mutex::new(Some(r))))
@@ -815,6 +1108,7 @@ fn handle_one_frame(
     first_frame: &mut bool,
     width: usize,
     height: usize,
+    low_tier_ids: &HashSet<i32>,
 ) -> ResultType<HashSet<i32>> {
     sp.snapshot(|sps| {
         // so that new sub and old sub share the same encoder after switch
@@ -828,8 +1122,10 @@ fn handle_one_frame(
     let mut send_conn_ids: HashSet<i32> = Default::default();
     let first = *first_frame;
     *first_frame = false;
+    let encode_start = Instant::now();
     match encoder.encode_to_message(frame, ms) {
         Ok(mut vf) => {
+            let encode_time = encode_start.elapsed();
             *encode_fail_counter = 0;
             vf.display = display as _;
             let mut msg = Message::new();
@@ -839,9 +1135,21 @@ fn handle_one_frame(
                 .unwrap()
                 .as_mut()
                 .map(|r| r.write_message(&msg, width, height));
-            send_conn_ids = sp.send_video_frame(msg);
+            let bytes = msg.compute_size() as usize;
+            send_conn_ids =
+                sp.send_video_frame_filter(Arc::new(msg), |id| !low_tier_ids.contains(&id));
+            VIDEO_QOS
+                .lock()
+                .unwrap()
+                .on_frame_sent(&send_conn_ids, bytes, encode_time);
         }
         Err(e) => {
+            let conn_ids = sp
+                .subscriber_ids()
+                .difference(low_tier_ids)
+                .cloned()
+                .collect();
+            VIDEO_QOS.lock().unwrap().on_encode_failed(&conn_ids);
             *encode_fail_counter += 1;
             log::error!("encode fail: {e:?}, times: {}", *encode_fail_counter,);
             let max_fail_times = 3;
@@ -918,6 +1226,7 @@ pub fn make_display_changed_msg(
             VideoSource::Camera => camera::Cameras::get_sync_cameras()
                 .get(display_idx)?
                 .clone(),
+            VideoSource::Window => super::shared_window::display_info()?,
         },
     };
     let mut misc = Misc::new();
@@ -929,7 +1238,7 @@ pub fn make_display_changed_msg(
         height: display.height,
         cursor_embedded: match source {
             VideoSource::Monitor => display_service::capture_cursor_embedded(),
-            VideoSource::Camera => false,
+            VideoSource::Camera | VideoSource::Window => false,
         },
         resolutions: Some(SupportedResolutions {
             resolutions: match source {
@@ -940,10 +1249,11 @@ pub fn make_display_changed_msg(
                         crate::platform::resolutions(&display.name)
                     }
                 }
-                VideoSource::Camera => camera::Cameras::get_camera_resolution(display_idx)
-                    .ok()
-                    .into_iter()
-                    .collect(),
+                VideoSource::Camera => {
+                    camera::Cameras::get_camera_resolutions(display_idx).unwrap_or_default()
+                }
+                // The window size is not changed by the controller.
+                VideoSource::Window => vec![],
             },
             ..SupportedResolutions::default()
         })
@@ -961,6 +1271,8 @@ fn check_qos(
     ratio: &mut f32,
     spf: &mut Duration,
     client_record: bool,
+    resolution_scale: f32,
+    simulcast: bool,
     send_counter: &mut usize,
     second_instant: &mut Instant,
     name: &str,
@@ -984,6 +1296,14 @@ fn check_qos(
         log::info!("switch due to record changed");
         bail!("SWITCH");
     }
+    if resolution_scale != video_qos.resolution_scale() {
+        log::info!("switch due to resolution scale changed");
+        bail!("SWITCH");
+    }
+    if simulcast != !video_qos.low_tier_ids().is_empty() {
+        log::info!("switch due to simulcast changed");
+        bail!("SWITCH");
+    }
     if second_instant.elapsed() > Duration::from_secs(1) {
         *second_instant = Instant::now();
         video_qos.update_display_data(&name, *send_counter);
@@ -0,0 +1,318 @@
+//! Watermark burned into captured frames before they are encoded.
+//!
+//! When `OPTION_WATERMARK` is enabled, the text from `OPTION_WATERMARK_TEXT` is tiled
+//! semi-transparently over the luma plane of every frame, so that both the stream
+//! and any recording of it carry the provenance of the session. Supported placeholders:
+//!
+//! - `{id}`: the ID of this device
+//! - `{peer_id}`, `{peer_name}`: the controlling peers, comma separated
+//! - `{time}`: the local time, refreshed every second
+//!
+//! The built-in settings take precedence over the local ones.
+
+use hbb_common::config::Config;
+use scrap::{EncodeYuvFormat, Pixfmt};
+use std::time::{Duration, Instant};
+
+pub const OPTION_WATERMARK: &str = "watermark";
+pub const OPTION_WATERMARK_TEXT: &str = "watermark-text";
+
+const DEFAULT_TEXT: &str = "{id} {peer_id} {peer_name} {time}";
+const REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+// One column spacing between glyphs.
+const GLYPH_ADVANCE: usize = GLYPH_WIDTH + 1;
+const TEXT_LUMA: u16 = 235;
+
+fn get_option(key: &str) -> String {
+    let v = crate::get_builtin_option(key);
+    if v.is_empty() {
+        Config::get_option(key)
+    } else {
+        v
+    }
+}
+
+pub fn is_enabled() -> bool {
+    get_option(OPTION_WATERMARK) == "Y"
+}
+
+/// Rendered text mask, rebuilt when the text changes.
+pub struct Watermark {
+    template: String,
+    text: String,
+    mask: Vec<bool>,
+    mask_w: usize,
+    mask_h: usize,
+    scale: usize,
+    last_refresh: Option<Instant>,
+}
+
+impl Watermark {
+    /// Returns `None` if the watermark is disabled.
+    pub fn new() -> Option<Self> {
+        if !is_enabled() {
+            return None;
+        }
+        let mut template = get_option(OPTION_WATERMARK_TEXT);
+        if template.trim().is_empty() {
+            template = DEFAULT_TEXT.to_owned();
+        }
+        Some(Self {
+            template,
+            text: "".to_owned(),
+            mask: vec![],
+            mask_w: 0,
+            mask_h: 0,
+            scale: 0,
+            last_refresh: None,
+        })
+    }
+
+    fn format_text(&self) -> String {
+        let peers = super::connection::remote_peers();
+        let peer_ids: Vec<&str> = peers.iter().map(|(id, _)| id.as_str()).collect();
+        let peer_names: Vec<&str> = peers.iter().map(|(_, name)| name.as_str()).collect();
+        self.template
+            .replace("{id}", &Config::get_id())
+            .replace("{peer_id}", &peer_ids.join(","))
+            .replace("{peer_name}", &peer_names.join(","))
+            .replace(
+                "{time}",
+                &chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            )
+    }
+
+    fn refresh(&mut self, height: usize) {
+        if self
+            .last_refresh
+            .map_or(false, |t| t.elapsed() < REFRESH_INTERVAL)
+        {
+            return;
+        }
+        self.last_refresh = Some(Instant::now());
+        let text = self.format_text();
+        // About 1/60 of the frame height, at least 2 pixels per glyph dot.
+        let scale = (height / (GLYPH_HEIGHT * 60)).max(2);
+        if text == self.text && scale == self.scale {
+            return;
+        }
+        let (mask, mask_w, mask_h) = render(&text, scale);
+        self.text = text;
+        self.scale = scale;
+        self.mask = mask;
+        self.mask_w = mask_w;
+        self.mask_h = mask_h;
+    }
+
+    /// Draw the watermark on the luma plane of a converted frame.
+    pub fn draw(&mut self, yuv: &mut [u8], fmt: &EncodeYuvFormat) {
+        if !matches!(fmt.pixfmt, Pixfmt::I420 | Pixfmt::NV12 | Pixfmt::I444) {
+            return;
+        }
+        let (w, h) = (fmt.w, fmt.h);
+        let Some(&stride) = fmt.stride.first() else {
+            return;
+        };
+        if w == 0 || h == 0 || stride < w || yuv.len() < stride * h {
+            return;
+        }
+        self.refresh(h);
+        if self.mask_w == 0 || self.mask_h == 0 {
+            return;
+        }
+        // Staggered grid, so cropping the recording does not remove the watermark.
+        let step_x = self.mask_w + self.mask_h * 4;
+        let step_y = self.mask_h * 6;
+        let mut row = 0;
+        let mut y0 = self.mask_h;
+        while y0 < h {
+            let mut x0 = if row % 2 == 0 { 0 } else { step_x / 2 };
+            while x0 < w {
+                self.blend(yuv, stride, w, h, x0, y0);
+                x0 += step_x;
+            }
+            row += 1;
+            y0 += step_y;
+        }
+    }
+
+    fn blend(&self, yuv: &mut [u8], stride: usize, w: usize, h: usize, x0: usize, y0: usize) {
+        for my in 0..self.mask_h {
+            let y = y0 + my;
+            if y >= h {
+                break;
+            }
+            let line = &mut yuv[y * stride..y * stride + w];
+            for mx in 0..self.mask_w {
+                let x = x0 + mx;
+                if x >= w {
+                    break;
+                }
+                if self.mask[my * self.mask_w + mx] {
+                    line[x] = ((line[x] as u16 + TEXT_LUMA) / 2) as u8;
+                } else if my > 0 && mx > 0 && self.mask[(my - 1) * self.mask_w + mx - 1] {
+                    // Shadow, keeps the text readable on bright backgrounds.
+                    line[x] /= 2;
+                }
+            }
+        }
+    }
+}
+
+/// Render `text` with the built-in 5x7 font, returns the mask and its size.
+fn render(text: &str, scale: usize) -> (Vec<bool>, usize, usize) {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return (vec![], 0, 0);
+    }
+    // One extra dot for the shadow.
+    let w = (chars.len() * GLYPH_ADVANCE + 1) * scale;
+    let h = (GLYPH_HEIGHT + 1) * scale;
+    let mut mask = vec![false; w * h];
+    for (i, c) in chars.iter().enumerate() {
+        let glyph = glyph(*c);
+        for (col, bits) in glyph.iter().enumerate() {
+            for row in 0..GLYPH_HEIGHT {
+                if bits & (1 << row) == 0 {
+                    continue;
+                }
+                let x = (i * GLYPH_ADVANCE + col) * scale;
+                let y = row * scale;
+                for dy in 0..scale {
+                    let start = (y + dy) * w + x;
+                    mask[start..start + scale].fill(true);
+                }
+            }
+        }
+    }
+    (mask, w, h)
+}
+
+/// Column-major glyph, bit 0 is the top row. Characters out of the printable ASCII range are shown as `?`.
+fn glyph(c: char) -> &'static [u8; GLYPH_WIDTH] {
+    let c = if (' '..='~').contains(&c) { c } else { '?' };
+    &FONT[c as usize - ' ' as usize]
+}
+
+#[rustfmt::skip]
+const FONT: [[u8; GLYPH_WIDTH]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x00, 0x00, 0x5F, 0x00, 0x00], // '!'
+    [0x00, 0x07, 0x00, 0x07, 0x00], // '"'
+    [0x14, 0x7F, 0x14, 0x7F, 0x14], // '#'
+    [0x24, 0x2A, 0x7F, 0x2A, 0x12], // '$'
+    [0x23, 0x13, 0x08, 0x64, 0x62], // '%'
+    [0x36, 0x49, 0x55, 0x22, 0x50], // '&'
+    [0x00, 0x05, 0x03, 0x00, 0x00], // '''
+    [0x00, 0x1C, 0x22, 0x41, 0x00], // '('
+    [0x00, 0x41, 0x22, 0x1C, 0x00], // ')'
+    [0x08, 0x2A, 0x1C, 0x2A, 0x08], // '*'
+    [0x08, 0x08, 0x3E, 0x08, 0x08], // '+'
+    [0x00, 0x50, 0x30, 0x00, 0x00], // ','
+    [0x08, 0x08, 0x08, 0x08, 0x08], // '-'
+    [0x00, 0x60, 0x60, 0x00, 0x00], // '.'
+    [0x20, 0x10, 0x08, 0x04, 0x02], // '/'
+    [0x3E, 0x51, 0x49, 0x45, 0x3E], // '0'
+    [0x00, 0x42, 0x7F, 0x40, 0x00], // '1'
+    [0x42, 0x61, 0x51, 0x49, 0x46], // '2'
+    [0x21, 0x41, 0x45, 0x4B, 0x31], // '3'
+    [0x18, 0x14, 0x12, 0x7F, 0x10], // '4'
+    [0x27, 0x45, 0x45, 0x45, 0x39], // '5'
+    [0x3C, 0x4A, 0x49, 0x49, 0x30], // '6'
+    [0x01, 0x71, 0x09, 0x05, 0x03], // '7'
+    [0x36, 0x49, 0x49, 0x49, 0x36], // '8'
+    [0x06, 0x49, 0x49, 0x29, 0x1E], // '9'
+    [0x00, 0x36, 0x36, 0x00, 0x00], // ':'
+    [0x00, 0x56, 0x36, 0x00, 0x00], // ';'
+    [0x08, 0x14, 0x22, 0x41, 0x00], // '<'
+    [0x14, 0x14, 0x14, 0x14, 0x14], // '='
+    [0x00, 0x41, 0x22, 0x14, 0x08], // '>'
+    [0x02, 0x01, 0x51, 0x09, 0x06], // '?'
+    [0x32, 0x49, 0x79, 0x41, 0x3E], // '@'
+    [0x7E, 0x11, 0x11, 0x11, 0x7E], // 'A'
+    [0x7F, 0x49, 0x49, 0x49, 0x36], // 'B'
+    [0x3E, 0x41, 0x41, 0x41, 0x22], // 'C'
+    [0x7F, 0x41, 0x41, 0x22, 0x1C], // 'D'
+    [0x7F, 0x49, 0x49, 0x49, 0x41], // 'E'
+    [0x7F, 0x09, 0x09, 0x01, 0x01], // 'F'
+    [0x3E, 0x41, 0x41, 0x51, 0x32], // 'G'
+    [0x7F, 0x08, 0x08, 0x08, 0x7F], // 'H'
+    [0x00, 0x41, 0x7F, 0x41, 0x00], // 'I'
+    [0x20, 0x40, 0x41, 0x3F, 0x01], // 'J'
+    [0x7F, 0x08, 0x14, 0x22, 0x41], // 'K'
+    [0x7F, 0x40, 0x40, 0x40, 0x40], // 'L'
+    [0x7F, 0x02, 0x04, 0x02, 0x7F], // 'M'
+    [0x7F, 0x04, 0x08, 0x10, 0x7F], // 'N'
+    [0x3E, 0x41, 0x41, 0x41, 0x3E], // 'O'
+    [0x7F, 0x09, 0x09, 0x09, 0x06], // 'P'
+    [0x3E, 0x41, 0x51, 0x21, 0x5E], // 'Q'
+    [0x7F, 0x09, 0x19, 0x29, 0x46], // 'R'
+    [0x46, 0x49, 0x49, 0x49, 0x31], // 'S'
+    [0x01, 0x01, 0x7F, 0x01, 0x01], // 'T'
+    [0x3F, 0x40, 0x40, 0x40, 0x3F], // 'U'
+    [0x1F, 0x20, 0x40, 0x20, 0x1F], // 'V'
+    [0x7F, 0x20, 0x18, 0x20, 0x7F], // 'W'
+    [0x63, 0x14, 0x08, 0x14, 0x63], // 'X'
+    [0x03, 0x04, 0x78, 0x04, 0x03], // 'Y'
+    [0x61, 0x51, 0x49, 0x45, 0x43], // 'Z'
+    [0x00, 0x7F, 0x41, 0x41, 0x00], // '['
+    [0x02, 0x04, 0x08, 0x10, 0x20], // '\'
+    [0x00, 0x41, 0x41, 0x7F, 0x00], // ']'
+    [0x04, 0x02, 0x01, 0x02, 0x04], // '^'
+    [0x40, 0x40, 0x40, 0x40, 0x40], // '_'
+    [0x00, 0x01, 0x02, 0x04, 0x00], // '`'
+    [0x20, 0x54, 0x54, 0x54, 0x78], // 'a'
+    [0x7F, 0x48, 0x44, 0x44, 0x38], // 'b'
+    [0x38, 0x44, 0x44, 0x44, 0x20], // 'c'
+    [0x38, 0x44, 0x44, 0x48, 0x7F], // 'd'
+    [0x38, 0x54, 0x54, 0x54, 0x18], // 'e'
+    [0x08, 0x7E, 0x09, 0x01, 0x02], // 'f'
+    [0x0C, 0x52, 0x52, 0x52, 0x3E], // 'g'
+    [0x7F, 0x08, 0x04, 0x04, 0x78], // 'h'
+    [0x00, 0x44, 0x7D, 0x40, 0x00], // 'i'
+    [0x20, 0x40, 0x44, 0x3D, 0x00], // 'j'
+    [0x7F, 0x10, 0x28, 0x44, 0x00], // 'k'
+    [0x00, 0x41, 0x7F, 0x40, 0x00], // 'l'
+    [0x7C, 0x04, 0x18, 0x04, 0x78], // 'm'
+    [0x7C, 0x08, 0x04, 0x04, 0x78], // 'n'
+    [0x38, 0x44, 0x44, 0x44, 0x38], // 'o'
+    [0x7C, 0x14, 0x14, 0x14, 0x08], // 'p'
+    [0x08, 0x14, 0x14, 0x18, 0x7C], // 'q'
+    [0x7C, 0x08, 0x04, 0x04, 0x08], // 'r'
+    [0x48, 0x54, 0x54, 0x54, 0x20], // 's'
+    [0x04, 0x3F, 0x44, 0x40, 0x20], // 't'
+    [0x3C, 0x40, 0x40, 0x20, 0x7C], // 'u'
+    [0x1C, 0x20, 0x40, 0x20, 0x1C], // 'v'
+    [0x3C, 0x40, 0x30, 0x40, 0x3C], // 'w'
+    [0x44, 0x28, 0x10, 0x28, 0x44], // 'x'
+    [0x0C, 0x50, 0x50, 0x50, 0x3C], // 'y'
+    [0x44, 0x64, 0x54, 0x4C, 0x44], // 'z'
+    [0x00, 0x08, 0x36, 0x41, 0x00], // '{'
+    [0x00, 0x00, 0x7F, 0x00, 0x00], // '|'
+    [0x00, 0x41, 0x36, 0x08, 0x00], // '}'
+    [0x08, 0x04, 0x08, 0x10, 0x08], // '~'
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn watermark_render() {
+        let (mask, w, h) = render("1:", 2);
+        assert_eq!(
+            (w, h),
+            ((2 * GLYPH_ADVANCE + 1) * 2, (GLYPH_HEIGHT + 1) * 2)
+        );
+        assert_eq!(mask.len(), w * h);
+        // The vertical bar of '1' in the third column, top row.
+        assert!(mask[2 * 2]);
+        assert!(mask[w + 2 * 2 + 1]);
+        // Nothing in the spacing column.
+        assert!(!mask.iter().enumerate().any(|(i, v)| *v && i % w / 2 == 5));
+        assert_eq!(glyph('é'), glyph('?'));
+        assert_eq!(render("", 2).0.len(), 0);
+    }
+}
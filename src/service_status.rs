@@ -0,0 +1,106 @@
+//! The state of the server, asked over the IPC by the processes of the user, e.g. the tray.
+//!
+//! The tray, of the `--server` process or of a `--tray` one, shows what the server sends on the
+//! connection which asked for `ipc::Data::ServiceStatus`: the state when it is asked, and again
+//! whenever it changes, so nothing is polled over the IPC. It shows no state while the server does
+//! not answer, it connects again then. It does not matter whether the server runs in the same
+//! process or not, and the tray keeps working, hidden or not, across the restarts of the server.
+
+use crate::ipc::{self, Data};
+#[cfg(feature = "ui")]
+use hbb_common::{bail, log, ResultType};
+use hbb_common::{
+    config::{self, Config},
+    tokio,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+// How often the server checks its state for a change.
+const CHECK_INTERVAL: Duration = Duration::from_secs(1);
+// The state is sent again by then anyway, so a closed connection is noticed.
+const RESEND_INTERVAL: Duration = Duration::from_secs(30);
+// How long the tray waits to connect again.
+#[cfg(feature = "ui")]
+const RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceStatus {
+    pub id: String,
+    /// Whether the rendezvous server is connected.
+    pub online: bool,
+    /// Whether the incoming connections are stopped with the "stop-service" option.
+    pub stopped: bool,
+    /// The names of the peers of the authorized remote control connections.
+    #[serde(default)]
+    pub sessions: Vec<String>,
+}
+
+/// The state of the server, on the server.
+pub fn current() -> ServiceStatus {
+    ServiceStatus {
+        id: Config::get_id(),
+        online: config::get_online_state() > 0,
+        stopped: Config::get_option("stop-service") == "Y",
+        sessions: crate::server::remote_peers()
+            .into_iter()
+            .map(|(id, name)| if name.is_empty() { id } else { name })
+            .collect(),
+    }
+}
+
+/// Send the state of the server on the connection, again whenever it changes, until it is closed.
+pub async fn serve(stream: &mut ipc::Connection) {
+    let mut sent: Option<(ServiceStatus, Instant)> = None;
+    loop {
+        let status = current();
+        if !matches!(&sent, Some((s, time)) if *s == status && time.elapsed() < RESEND_INTERVAL) {
+            let json = serde_json::to_string(&status).unwrap_or_default();
+            if stream.send(&Data::ServiceStatus(Some(json))).await.is_err() {
+                return;
+            }
+            sent = Some((status, Instant::now()));
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+#[cfg(feature = "ui")]
+async fn watch_(callback: &mut impl FnMut(Option<ServiceStatus>)) -> ResultType<()> {
+    let mut c = ipc::connect(1000, "").await?;
+    c.send(&Data::ServiceStatus(None)).await?;
+    let ms_timeout = 2 * RESEND_INTERVAL.as_millis() as u64;
+    loop {
+        match c.next_timeout(ms_timeout).await? {
+            Some(Data::ServiceStatus(Some(status))) => callback(serde_json::from_str(&status).ok()),
+            Some(_) => {}
+            None => bail!("Closed"),
+        }
+    }
+}
+
+/// Pass the state of the server to `callback` as it changes, `None` while it does not answer.
+#[cfg(feature = "ui")]
+#[tokio::main(flavor = "current_thread")]
+pub async fn watch(mut callback: impl FnMut(Option<ServiceStatus>)) {
+    loop {
+        if let Err(e) = watch_(&mut callback).await {
+            log::debug!("The state of the server is not known: {}", e);
+        }
+        callback(None);
+        tokio::time::sleep(RETRY_INTERVAL).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn service_status() {
+        let status: ServiceStatus =
+            serde_json::from_str(r#"{"id":"123","online":true,"stopped":false}"#).unwrap();
+        assert!(status.online);
+        assert!(status.sessions.is_empty());
+    }
+}
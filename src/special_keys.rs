@@ -0,0 +1,83 @@
+//! Keys of the host without a `ControlKey`: the media keys and the functions of the system.
+//!
+//! The volume, the brightness or Mission Control have keys on the keyboards of the host but no key
+//! code the client could send, so a viewer had no way to press them. The client sends a `Request`
+//! with the key to a host with "support_special_keys" in its platform additions, and the host posts
+//! its press and its release, as the input of the peer: limited and checked with the input profile,
+//! by the name of the key, e.g. "volume_up". The client shows them as a keypad in the keyboard menu.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "special-key";
+pub const PLATFORM_ADDITION_KEY: &str = "support_special_keys";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    VolumeUp,
+    VolumeDown,
+    Mute,
+    BrightnessUp,
+    BrightnessDown,
+    PlayPause,
+    NextTrack,
+    PreviousTrack,
+    MissionControl,
+    Launchpad,
+}
+
+impl Key {
+    pub fn name(self) -> String {
+        serde_json::to_value(self)
+            .ok()
+            .and_then(|v| v.as_str().map(|s| s.to_owned()))
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub key: Key,
+}
+
+/// The request to press the key, none if it is not known, e.g. "volume_up".
+pub fn make_request(key: &str) -> Option<Message> {
+    let key: Key = serde_json::from_value(serde_json::Value::String(key.to_owned())).ok()?;
+    Some(plugin_request::make(ID, &Request { key }))
+}
+
+/// Press and release the key of the request of a connection, if `allow` lets it.
+pub fn handle_request(conn_id: i32, content: &[u8], allow: impl FnOnce(Key) -> bool) {
+    match serde_json::from_slice::<Request>(content) {
+        Ok(req) => {
+            if !allow(req.key) {
+                return;
+            }
+            log::info!("Connection {} presses {:?}", conn_id, req.key);
+            if let Err(e) = crate::platform::post_special_key(req.key) {
+                log::error!("Failed to press {:?}: {}", req.key, e);
+            }
+        }
+        Err(e) => log::warn!("Invalid special key request: {}", e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn special_key_request() {
+        let msg = make_request("mission_control").unwrap();
+        let content = match &msg.misc().union {
+            Some(hbb_common::message_proto::misc::Union::PluginRequest(p)) => p.content.clone(),
+            _ => panic!("not a request"),
+        };
+        let req: Request = serde_json::from_slice(&content).unwrap();
+        assert_eq!(req.key, Key::MissionControl);
+        assert_eq!(req.key.name(), "mission_control");
+        assert!(make_request("eject").is_none());
+    }
+}
@@ -0,0 +1,86 @@
+//! Pen and stylus input, with the pressure, the tilt and the eraser.
+//!
+//! `PointerDeviceEvent` only has touch events, so the stylus events are sent as a plugin request,
+//! see `crate::plugin_request`. The position is in the same coordinates as the mouse events.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "stylus";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// Moving above the tablet.
+    #[default]
+    Hover,
+    Down,
+    /// Moving on the tablet.
+    Move,
+    Up,
+    /// Out of the range of the tablet.
+    Leave,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct StylusEvent {
+    pub phase: Phase,
+    pub x: i32,
+    pub y: i32,
+    /// In [0, 1].
+    #[serde(default)]
+    pub pressure: f64,
+    /// In [-1, 1], positive to the right and to the bottom.
+    #[serde(default)]
+    pub tilt_x: f64,
+    #[serde(default)]
+    pub tilt_y: f64,
+    #[serde(default)]
+    pub eraser: bool,
+}
+
+impl StylusEvent {
+    /// Clamp the values from the peer into their ranges.
+    pub fn clamped(mut self) -> Self {
+        let f = |v: f64, min: f64| if v.is_finite() { v.clamp(min, 1.) } else { 0. };
+        self.pressure = f(self.pressure, 0.);
+        self.tilt_x = f(self.tilt_x, -1.);
+        self.tilt_y = f(self.tilt_y, -1.);
+        if self.phase == Phase::Up || self.phase == Phase::Hover || self.phase == Phase::Leave {
+            self.pressure = 0.;
+        }
+        self
+    }
+}
+
+pub fn make_message(evt: &StylusEvent) -> Message {
+    plugin_request::make(ID, evt)
+}
+
+pub fn parse(content: &[u8]) -> Option<StylusEvent> {
+    serde_json::from_slice::<StylusEvent>(content)
+        .ok()
+        .map(|e| e.clamped())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stylus_parse() {
+        let evt =
+            parse(br#"{"phase":"move","x":10,"y":-20,"pressure":1.5,"tilt_x":-3,"eraser":true}"#)
+                .unwrap();
+        assert_eq!(evt.phase, Phase::Move);
+        assert_eq!((evt.x, evt.y), (10, -20));
+        assert_eq!(evt.pressure, 1.);
+        assert_eq!(evt.tilt_x, -1.);
+        assert_eq!(evt.tilt_y, 0.);
+        assert!(evt.eraser);
+        let evt = parse(br#"{"phase":"up","x":0,"y":0,"pressure":0.5}"#).unwrap();
+        assert_eq!(evt.pressure, 0.);
+        assert!(parse(br#"{"phase":"tap","x":0,"y":0}"#).is_none());
+    }
+}
@@ -0,0 +1,265 @@
+//! The controlling sessions shown as tiles of a "wall" of many machines.
+//!
+//! `list` has the desktop sessions with their peers, displays, the size of their tiles and a
+//! small thumbnail of the last frame. The frames are scaled down by a `SessionHook`, added with the
+//! session, at most every `THUMBNAIL_INTERVAL` and only while the wall is listed, and a thumbnail is
+//! encoded when `list` asks for a newer one. The UI reports the size of each tile with `set_tile`. A hidden tile, or one much smaller than the
+//! display of the peer, asks the peer for fewer frames with the custom fps of the session, which
+//! is not saved, and the fps is restored once the tile is large and visible again.
+
+use crate::{
+    flutter::{sessions, SessionHook},
+    flutter_ffi::SessionID,
+};
+use hbb_common::{log, rendezvous_proto::ConnType};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+const HOOK_KEY_PREFIX: &str = "tiling-thumbnail:";
+const THUMBNAIL_WIDTH: usize = 240;
+const THUMBNAIL_INTERVAL: Duration = Duration::from_secs(2);
+// No thumbnail is taken when the wall was not listed for this long.
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+const HIDDEN_FPS: i32 = 1;
+const TINY_FPS: i32 = 5;
+const SMALL_FPS: i32 = 15;
+
+#[derive(Debug, Default)]
+struct Tile {
+    width: u32,
+    height: u32,
+    visible: bool,
+    // The fps of the session before it was throttled.
+    restore_fps: Option<i32>,
+}
+
+#[derive(Default)]
+struct Thumbnail {
+    time: Option<Instant>,
+    // The frame scaled down, not encoded yet.
+    image: Option<image::RgbaImage>,
+    // The png, in base64.
+    png: String,
+}
+
+lazy_static::lazy_static! {
+    static ref TILES: Mutex<HashMap<SessionID, Tile>> = Default::default();
+    // peer id -> thumbnail
+    static ref THUMBNAILS: Mutex<HashMap<String, Thumbnail>> = Default::default();
+    static ref LISTED: Mutex<Option<Instant>> = Default::default();
+}
+
+// The fps for the tile, none for the fps of the session.
+fn throttled_fps(tile: (u32, u32), display: (i32, i32), visible: bool) -> Option<i32> {
+    if !visible {
+        return Some(HIDDEN_FPS);
+    }
+    let display_area = display.0.max(1) as u64 * display.1.max(1) as u64;
+    let tile_area = tile.0 as u64 * tile.1 as u64;
+    if tile_area * 16 <= display_area {
+        Some(TINY_FPS)
+    } else if tile_area * 4 <= display_area {
+        Some(SMALL_FPS)
+    } else {
+        None
+    }
+}
+
+// The nearest pixels of the frame, in rgba.
+fn scale_down(rgba: &scrap::ImageRgb) -> Option<image::RgbaImage> {
+    if rgba.w == 0 || rgba.h == 0 || rgba.align == 0 {
+        return None;
+    }
+    let stride = (rgba.w * 4 + rgba.align - 1) & !(rgba.align - 1);
+    if rgba.raw.len() < stride * rgba.h {
+        return None;
+    }
+    let width = THUMBNAIL_WIDTH.min(rgba.w);
+    let height = (rgba.h * width / rgba.w).max(1);
+    let bgra = matches!(rgba.fmt, scrap::ImageFormat::ARGB);
+    Some(image::RgbaImage::from_fn(
+        width as _,
+        height as _,
+        |x, y| {
+            let i = (y as usize * rgba.h / height) * stride + (x as usize * rgba.w / width) * 4;
+            let p = &rgba.raw[i..i + 4];
+            if bgra {
+                image::Rgba([p[2], p[1], p[0], 255])
+            } else {
+                image::Rgba([p[0], p[1], p[2], 255])
+            }
+        },
+    ))
+}
+
+fn on_rgba(key: String, rgba: &mut scrap::ImageRgb) {
+    let Some(peer_id) = key.strip_prefix(HOOK_KEY_PREFIX) else {
+        return;
+    };
+    if !matches!(*LISTED.lock().unwrap(), Some(t) if t.elapsed() < LIST_TIMEOUT) {
+        return;
+    }
+    let mut thumbnails = THUMBNAILS.lock().unwrap();
+    let thumbnail = thumbnails.entry(peer_id.to_owned()).or_default();
+    if matches!(thumbnail.time, Some(t) if t.elapsed() < THUMBNAIL_INTERVAL) {
+        return;
+    }
+    thumbnail.time = Some(Instant::now());
+    if let Some(image) = scale_down(rgba) {
+        thumbnail.image = Some(image);
+    }
+}
+
+// The png of the last frame scaled down, encoded once.
+fn encode(peer_id: &str, thumbnail: &mut Thumbnail) -> String {
+    if let Some(image) = thumbnail.image.take() {
+        let mut png = vec![];
+        match image.write_to(&mut Cursor::new(&mut png), image::ImageOutputFormat::Png) {
+            Ok(()) => thumbnail.png = crate::encode64(&png),
+            Err(e) => log::debug!("Failed to encode the thumbnail of {}: {}", peer_id, e),
+        }
+    }
+    thumbnail.png.clone()
+}
+
+/// Take the thumbnails of a new desktop session.
+pub fn add(session_id: &SessionID) {
+    if let Some(session) = sessions::get_session_by_session_id(session_id) {
+        session.ui_handler.add_session_hook(
+            format!("{}{}", HOOK_KEY_PREFIX, session.get_id()),
+            SessionHook::OnSessionRgba(on_rgba),
+        );
+    }
+}
+
+/// Json list of the desktop sessions, with their peers, displays, tiles and thumbnails.
+pub fn list() -> String {
+    *LISTED.lock().unwrap() = Some(Instant::now());
+    let tiles = TILES.lock().unwrap();
+    let mut thumbnails = THUMBNAILS.lock().unwrap();
+    let mut peers = vec![];
+    let mut list = vec![];
+    for session in sessions::get_sessions() {
+        let peer_id = session.get_id();
+        let lc = session.lc.read().unwrap();
+        if lc.conn_type != ConnType::DEFAULT_CONN {
+            continue;
+        }
+        let (hostname, displays, current_display) = match lc.peer_info.as_ref() {
+            Some(pi) => (
+                pi.hostname.clone(),
+                pi.displays
+                    .iter()
+                    .map(|d| json!({"x": d.x, "y": d.y, "width": d.width, "height": d.height}))
+                    .collect(),
+                pi.current_display,
+            ),
+            None => (String::new(), vec![], 0),
+        };
+        let fps = *lc.custom_fps.lock().unwrap();
+        let thumbnail = thumbnails
+            .get_mut(&peer_id)
+            .map(|t| encode(&peer_id, t))
+            .unwrap_or_default();
+        for session_id in session.ui_handler.session_ids() {
+            let tile = tiles.get(&session_id).map(|t| {
+                json!({
+                    "width": t.width,
+                    "height": t.height,
+                    "visible": t.visible,
+                    "throttled": t.restore_fps.is_some(),
+                })
+            });
+            list.push(json!({
+                "session_id": session_id.to_string(),
+                "peer_id": peer_id,
+                "hostname": hostname,
+                "displays": displays,
+                "current_display": current_display,
+                "fps": fps,
+                "tile": tile,
+                "thumbnail": thumbnail,
+            }));
+        }
+        peers.push(peer_id);
+    }
+    thumbnails.retain(|id, _| peers.contains(id));
+    serde_json::to_string(&list).unwrap_or_default()
+}
+
+/// The size of the tile of the session in the wall view, and whether it is visible.
+pub fn set_tile(session_id: &SessionID, width: u32, height: u32, visible: bool) {
+    let Some(session) = sessions::get_session_by_session_id(session_id) else {
+        return;
+    };
+    let (display, fps) = {
+        let lc = session.lc.read().unwrap();
+        let display = lc
+            .peer_info
+            .as_ref()
+            .and_then(|pi| pi.displays.get(pi.current_display as usize))
+            .map(|d| (d.width, d.height))
+            .unwrap_or_default();
+        let fps = lc.custom_fps.lock().unwrap().map(|fps| fps as i32);
+        (display, fps)
+    };
+    let mut tiles = TILES.lock().unwrap();
+    let tile = tiles.entry(*session_id).or_default();
+    tile.width = width;
+    tile.height = height;
+    tile.visible = visible;
+    match throttled_fps((width, height), display, visible) {
+        Some(target) => {
+            let restore = *tile.restore_fps.get_or_insert(fps.unwrap_or(30));
+            let target = target.min(restore);
+            if fps != Some(target) {
+                session.throttle_fps(target);
+            }
+        }
+        None => {
+            if let Some(restore) = tile.restore_fps.take() {
+                session.throttle_fps(restore);
+            }
+        }
+    }
+}
+
+pub fn remove(session_id: &SessionID) {
+    TILES.lock().unwrap().remove(session_id);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiling_fps() {
+        assert_eq!(
+            throttled_fps((1920, 1080), (1920, 1080), false),
+            Some(HIDDEN_FPS)
+        );
+        assert_eq!(throttled_fps((1920, 1080), (1920, 1080), true), None);
+        assert_eq!(
+            throttled_fps((960, 540), (1920, 1080), true),
+            Some(SMALL_FPS)
+        );
+        assert_eq!(
+            throttled_fps((480, 270), (1920, 1080), true),
+            Some(TINY_FPS)
+        );
+        assert_eq!(throttled_fps((100, 100), (0, 0), true), None);
+
+        let mut rgba = scrap::ImageRgb::new(scrap::ImageFormat::ARGB, 64);
+        rgba.w = 480;
+        rgba.h = 270;
+        rgba.raw = [0u8, 0, 255, 255].repeat(480 * 270);
+        let image = scale_down(&rgba).unwrap();
+        assert_eq!(image.dimensions(), (240, 135));
+        assert_eq!(image.get_pixel(0, 0), &image::Rgba([255, 0, 0, 255]));
+    }
+}
@@ -0,0 +1,171 @@
+//! Custom toolbar actions of a peer: send a key chord, run a saved macro or paste a snippet.
+//!
+//! The actions are saved as json in the `PeerConfig` options under `PEER_OPTION`, and run
+//! through the same legacy key events as the keyboard menu of the toolbar.
+
+use crate::client::KEY_MAP;
+use hbb_common::{bail, ResultType};
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const PEER_OPTION: &str = "toolbar-actions";
+// A macro waits at most this long between two steps.
+pub const MAX_DELAY_MS: u64 = 10_000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolbarAction {
+    pub id: String,
+    pub label: String,
+    #[serde(flatten)]
+    pub action: Action,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Action {
+    /// e.g. "ctrl+alt+delete", "cmd+shift+4", "f5".
+    Chord {
+        keys: String,
+    },
+    Macro {
+        steps: Vec<Step>,
+    },
+    Paste {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    Chord { keys: String },
+    Text { text: String },
+    Delay { ms: u64 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Chord {
+    /// A single character, or a name of `KEY_MAP`.
+    pub key: String,
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub command: bool,
+}
+
+pub fn parse_chord(keys: &str) -> Option<Chord> {
+    let mut chord = Chord::default();
+    let mut key = None;
+    for part in keys.split('+').map(|p| p.trim().to_lowercase()) {
+        match part.as_str() {
+            "ctrl" | "control" => chord.ctrl = true,
+            "alt" | "option" => chord.alt = true,
+            "shift" => chord.shift = true,
+            "cmd" | "command" | "meta" | "win" | "super" => chord.command = true,
+            _ if key.is_none() && !part.is_empty() => key = Some(part),
+            _ => return None,
+        }
+    }
+    let key = key?;
+    chord.key = if key.chars().count() == 1 {
+        key
+    } else {
+        let name = match key.as_str() {
+            "esc" => "escape",
+            "del" => "delete",
+            "enter" => "return",
+            "ins" => "insert",
+            "pgup" | "pageup" => "prior",
+            "pgdn" | "pagedown" => "next",
+            "backspace" => "back",
+            _ => key.as_str(),
+        };
+        let name = format!("VK_{}", name.to_uppercase());
+        if !KEY_MAP.contains_key(name.as_str()) {
+            return None;
+        }
+        name
+    };
+    Some(chord)
+}
+
+/// Parse and check the actions, the chords must be valid and the ids unique.
+pub fn parse(json: &str) -> ResultType<Vec<ToolbarAction>> {
+    if json.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    let actions: Vec<ToolbarAction> = serde_json::from_str(json)?;
+    let mut ids = HashSet::new();
+    for a in actions.iter() {
+        if a.id.is_empty() || !ids.insert(a.id.as_str()) {
+            bail!("invalid toolbar action id: {:?}", a.id);
+        }
+        let chords: Vec<&String> = match &a.action {
+            Action::Chord { keys } => vec![keys],
+            Action::Macro { steps } => steps
+                .iter()
+                .filter_map(|s| match s {
+                    Step::Chord { keys } => Some(keys),
+                    _ => None,
+                })
+                .collect(),
+            Action::Paste { .. } => vec![],
+        };
+        for keys in chords {
+            if parse_chord(keys).is_none() {
+                bail!("invalid key chord: {keys}");
+            }
+        }
+    }
+    Ok(actions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn toolbar_actions_parse() {
+        assert_eq!(
+            parse_chord("Ctrl+Alt+Del"),
+            Some(Chord {
+                key: "VK_DELETE".to_owned(),
+                alt: true,
+                ctrl: true,
+                ..Default::default()
+            })
+        );
+        assert_eq!(parse_chord("cmd+shift+4").unwrap().key, "4");
+        assert_eq!(parse_chord("f5").unwrap().key, "VK_F5");
+        assert!(parse_chord("ctrl+").is_none());
+        assert!(parse_chord("ctrl+a+b").is_none());
+        assert!(parse_chord("hyper+x").is_none());
+
+        let actions = parse(
+            r#"[
+                {"id":"1","label":"Task manager","type":"chord","keys":"ctrl+shift+esc"},
+                {"id":"2","label":"Login","type":"macro","steps":[
+                    {"type":"text","text":"admin"},
+                    {"type":"chord","keys":"tab"},
+                    {"type":"delay","ms":200}
+                ]},
+                {"id":"3","label":"Host","type":"paste","text":"hostname"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(actions.len(), 3);
+        assert_eq!(
+            actions[0].action,
+            Action::Chord {
+                keys: "ctrl+shift+esc".to_owned()
+            }
+        );
+        assert!(parse("").unwrap().is_empty());
+        assert!(parse(r#"[{"id":"1","label":"x","type":"chord","keys":"ctrl+nope"}]"#).is_err());
+        assert!(parse(
+            r#"[{"id":"1","label":"a","type":"paste","text":""},
+                {"id":"1","label":"b","type":"paste","text":""}]"#
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Multi-touch gestures of the client, injected as the native gestures of the host.
+//!
+//! The pinch comes as `TouchScaleUpdate` and the multi-finger scroll as the `TouchPan*` events of
+//! `PointerDeviceEvent`. The protocol has no rotation, so it is sent as a plugin request to the
+//! hosts which advertise "support_touch_gesture". The scale and the rotation are deltas in
+//! thousandths, of the scale and of a degree clockwise, and 0 ends the gesture. The events carry no
+//! phase, so a `Tracker` per connection adds it.
+
+use crate::plugin_request;
+use hbb_common::message_proto::{touch_event, Message};
+use serde_derive::{Deserialize, Serialize};
+
+pub const ID: &str = "touch-rotate";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RotateEvent {
+    #[serde(default)]
+    pub rotation: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Began,
+    Changed,
+    Ended,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// The change of the scale, 0.1 is 10% larger.
+    Magnify(f64),
+    /// The change of the angle in degrees, clockwise.
+    Rotate(f64),
+    /// The scroll in pixels, as the trackpad of the mouse events.
+    Scroll(i32, i32),
+}
+
+/// The gestures in progress of a connection.
+#[derive(Debug, Default)]
+pub struct Tracker {
+    magnify: bool,
+    rotate: bool,
+    scroll: bool,
+}
+
+fn phase(active: &mut bool, end: bool) -> Option<Phase> {
+    match (*active, end) {
+        (false, true) => None,
+        (true, true) => {
+            *active = false;
+            Some(Phase::Ended)
+        }
+        (true, false) => Some(Phase::Changed),
+        (false, false) => {
+            *active = true;
+            Some(Phase::Began)
+        }
+    }
+}
+
+impl Tracker {
+    pub fn on_touch(&mut self, evt: &touch_event::Union) -> Option<(Gesture, Phase)> {
+        match evt {
+            touch_event::Union::ScaleUpdate(e) => {
+                let p = phase(&mut self.magnify, e.scale == 0)?;
+                Some((Gesture::Magnify(e.scale as f64 / 1000.), p))
+            }
+            touch_event::Union::PanStart(_) => {
+                // A pan start while scrolling has lost the end of the last scroll.
+                self.scroll = false;
+                let p = phase(&mut self.scroll, false)?;
+                Some((Gesture::Scroll(0, 0), p))
+            }
+            touch_event::Union::PanUpdate(e) => {
+                let p = phase(&mut self.scroll, false)?;
+                Some((Gesture::Scroll(e.x, e.y), p))
+            }
+            touch_event::Union::PanEnd(_) => {
+                let p = phase(&mut self.scroll, true)?;
+                Some((Gesture::Scroll(0, 0), p))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn on_rotate(&mut self, evt: &RotateEvent) -> Option<(Gesture, Phase)> {
+        let p = phase(&mut self.rotate, evt.rotation == 0)?;
+        Some((Gesture::Rotate(evt.rotation as f64 / 1000.), p))
+    }
+
+    /// End the gestures in progress, when the connection is closed.
+    pub fn end_all(&mut self) -> Vec<(Gesture, Phase)> {
+        let mut v = vec![];
+        if phase(&mut self.magnify, true).is_some() {
+            v.push((Gesture::Magnify(0.), Phase::Ended));
+        }
+        if phase(&mut self.rotate, true).is_some() {
+            v.push((Gesture::Rotate(0.), Phase::Ended));
+        }
+        if phase(&mut self.scroll, true).is_some() {
+            v.push((Gesture::Scroll(0, 0), Phase::Ended));
+        }
+        v
+    }
+}
+
+pub fn make_message(evt: &RotateEvent) -> Message {
+    plugin_request::make(ID, evt)
+}
+
+pub fn parse(content: &[u8]) -> Option<RotateEvent> {
+    serde_json::from_slice(content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::message_proto::{TouchPanEnd, TouchPanStart, TouchPanUpdate, TouchScaleUpdate};
+
+    fn scale(scale: i32) -> touch_event::Union {
+        touch_event::Union::ScaleUpdate(TouchScaleUpdate {
+            scale,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn touch_gesture_phases() {
+        let mut tracker = Tracker::default();
+        assert_eq!(tracker.on_touch(&scale(0)), None);
+        assert_eq!(
+            tracker.on_touch(&scale(100)),
+            Some((Gesture::Magnify(0.1), Phase::Began))
+        );
+        assert_eq!(
+            tracker.on_touch(&scale(-50)),
+            Some((Gesture::Magnify(-0.05), Phase::Changed))
+        );
+        assert_eq!(
+            tracker.on_touch(&scale(0)),
+            Some((Gesture::Magnify(0.), Phase::Ended))
+        );
+
+        let start = touch_event::Union::PanStart(TouchPanStart::default());
+        let update = touch_event::Union::PanUpdate(TouchPanUpdate {
+            x: 3,
+            y: -4,
+            ..Default::default()
+        });
+        let end = touch_event::Union::PanEnd(TouchPanEnd::default());
+        assert_eq!(tracker.on_touch(&start).unwrap().1, Phase::Began);
+        assert_eq!(
+            tracker.on_touch(&update),
+            Some((Gesture::Scroll(3, -4), Phase::Changed))
+        );
+        assert_eq!(tracker.on_touch(&end).unwrap().1, Phase::Ended);
+        assert_eq!(tracker.on_touch(&end), None);
+        // An update without a start begins the scroll.
+        assert_eq!(tracker.on_touch(&update).unwrap().1, Phase::Began);
+
+        let rotate = parse(br#"{"rotation":-15000}"#).unwrap();
+        assert_eq!(
+            tracker.on_rotate(&rotate),
+            Some((Gesture::Rotate(-15.), Phase::Began))
+        );
+        assert_eq!(
+            tracker.end_all(),
+            vec![
+                (Gesture::Rotate(0.), Phase::Ended),
+                (Gesture::Scroll(0, 0), Phase::Ended)
+            ]
+        );
+        assert!(tracker.end_all().is_empty());
+    }
+}
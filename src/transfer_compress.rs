@@ -0,0 +1,219 @@
+//! The zstd compression of the blocks of the file transfers, chosen per file by its type.
+//!
+//! `FileTransferBlock::compressed` is part of the protocol, so every peer decompresses a block
+//! flagged with it when it writes the block. The jobs are still compressed only when negotiated:
+//! a host with "support_transfer_compress" in its platform additions compresses the downloads the
+//! client asked for with a `Request` of the reserved `ID`, sent before the job, and the client
+//! compresses its uploads to such a host. The files whose format is compressed already, archives,
+//! images, audio and video, are sent as they are, the others, e.g. logs and source trees, are
+//! compressed and sent compressed only when it makes the block smaller.
+
+use crate::plugin_request;
+use hbb_common::{
+    compress::compress,
+    fs::{self, TransferJob},
+    message_proto::{FileTransferBlock, Message},
+    ResultType, Stream,
+};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+
+pub const ID: &str = "transfer-compress";
+pub const PLATFORM_ADDITION_KEY: &str = "support_transfer_compress";
+
+// The blocks smaller than this are sent as they are, e.g. the last block of a file.
+const MIN_SIZE: usize = 512;
+
+const COMPRESSED_EXTENSIONS: &[&str] = &[
+    "7z", "aac", "apk", "avi", "br", "bz2", "dmg", "docx", "epub", "flac", "gif", "gz", "heic",
+    "jar", "jpeg", "jpg", "lz4", "m4a", "m4v", "mkv", "mov", "mp3", "mp4", "odt", "ogg", "opus",
+    "pkg", "png", "pptx", "rar", "tgz", "webm", "webp", "xlsx", "xz", "zip", "zst",
+];
+
+/// Whether the file may shrink with compression, by its extension.
+pub fn is_compressible(name: &str) -> bool {
+    match Path::new(name).extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => !COMPRESSED_EXTENSIONS.contains(&ext.to_lowercase().as_str()),
+        None => true,
+    }
+}
+
+/// Compress the data of a block of the file `name` if it is not compressed yet and it gets smaller.
+fn compress_block(block: &mut FileTransferBlock, name: &str) {
+    if block.compressed || block.data.len() < MIN_SIZE || !is_compressible(name) {
+        return;
+    }
+    let data = compress(&block.data);
+    if data.len() < block.data.len() {
+        block.data = data.into();
+        block.compressed = true;
+    }
+}
+
+/// The request of the client to compress the blocks of the job `id`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: i32,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The jobs of a session to compress, with the names of their files when they are read by the cm.
+#[derive(Debug, Default)]
+pub struct Jobs(HashMap<i32, Vec<String>>);
+
+impl Jobs {
+    pub fn insert(&mut self, id: i32) {
+        self.0.insert(id, vec![]);
+    }
+
+    pub fn handle_request(&mut self, content: &[u8]) {
+        if let Some(req) = Request::parse(content) {
+            self.insert(req.id);
+        }
+    }
+
+    pub fn contains(&self, id: i32) -> bool {
+        self.0.contains_key(&id)
+    }
+
+    pub fn remove(&mut self, id: i32) {
+        self.0.remove(&id);
+    }
+
+    /// The files of a job read by the cm, only known by the connection from its directory.
+    pub fn set_files(&mut self, id: i32, files: Vec<String>) {
+        if let Some(v) = self.0.get_mut(&id) {
+            *v = files;
+        }
+    }
+
+    /// Compress a block read by the cm if its job is compressed.
+    pub fn compress_cm_block(&self, block: &mut FileTransferBlock) {
+        let file = self
+            .0
+            .get(&block.id)
+            .and_then(|files| files.get(block.file_num as usize));
+        if let Some(name) = file {
+            compress_block(block, name);
+        }
+    }
+}
+
+/// `fs::handle_read_jobs`, with the blocks of the jobs in `compressed` compressed.
+pub async fn handle_read_jobs(
+    jobs: &mut Vec<TransferJob>,
+    stream: &mut Stream,
+    compressed: &mut Jobs,
+) -> ResultType<String> {
+    let mut job_log = Default::default();
+    let mut finished = Vec::new();
+    for job in jobs.iter_mut() {
+        if job.is_last_job {
+            continue;
+        }
+        if let Err(err) = job.init_data_stream(stream).await {
+            stream
+                .send(&fs::new_error(job.id(), err, job.file_num()))
+                .await?;
+            finished.push(job.id());
+            continue;
+        }
+        match job.read().await {
+            Err(err) => {
+                stream
+                    .send(&fs::new_error(job.id(), err, job.file_num()))
+                    .await?;
+            }
+            Ok(Some(mut block)) => {
+                if compressed.contains(job.id()) {
+                    if let Some(file) = job.files().get(block.file_num as usize) {
+                        compress_block(&mut block, &file.name);
+                    }
+                }
+                stream.send(&fs::new_block(block)).await?;
+            }
+            Ok(None) => {
+                if job.job_completed() {
+                    job_log = fs::serialize_transfer_job(job, true, false, "");
+                    finished.push(job.id());
+                    match job.job_error() {
+                        Some(err) => {
+                            job_log = fs::serialize_transfer_job(job, false, false, &err);
+                            stream
+                                .send(&fs::new_error(job.id(), err, job.file_num()))
+                                .await?
+                        }
+                        None => stream.send(&fs::new_done(job.id(), job.file_num())).await?,
+                    }
+                }
+                // else: waiting for confirmation from peer
+            }
+        }
+        // Break to handle jobs one by one.
+        break;
+    }
+    for id in finished {
+        compressed.remove(id);
+        let _ = fs::remove_job(id, jobs);
+    }
+    Ok(job_log)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use hbb_common::compress::decompress;
+
+    #[test]
+    fn transfer_compress() {
+        assert!(is_compressible("logs/server.log"));
+        assert!(is_compressible("Makefile"));
+        assert!(!is_compressible("photos/IMG_0001.JPG"));
+        assert!(!is_compressible("backup.tar.gz"));
+
+        let text = "2026-10-17 INFO connection accepted\n".repeat(100);
+        let mut block = FileTransferBlock {
+            data: text.clone().into_bytes().into(),
+            ..Default::default()
+        };
+        compress_block(&mut block, "server.log");
+        assert!(block.compressed);
+        assert_eq!(decompress(&block.data), text.as_bytes());
+
+        let mut block = FileTransferBlock {
+            data: text.clone().into_bytes().into(),
+            ..Default::default()
+        };
+        compress_block(&mut block, "archive.zip");
+        assert!(!block.compressed);
+
+        let mut jobs = Jobs::default();
+        let msg = Request { id: 1 }.to_message();
+        jobs.handle_request(&msg.misc().plugin_request().content);
+        assert!(jobs.contains(1) && !jobs.contains(2));
+        jobs.set_files(1, vec!["server.log".to_owned()]);
+        let mut block = FileTransferBlock {
+            id: 1,
+            data: text.clone().into_bytes().into(),
+            ..Default::default()
+        };
+        jobs.compress_cm_block(&mut block);
+        assert!(block.compressed);
+        let mut block = FileTransferBlock {
+            id: 2,
+            data: text.into_bytes().into(),
+            ..Default::default()
+        };
+        jobs.compress_cm_block(&mut block);
+        assert!(!block.compressed);
+    }
+}
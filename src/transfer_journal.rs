@@ -0,0 +1,383 @@
+//! The file jobs of the host kept on disk, to resume the ones a restart of the service interrupted.
+//!
+//! The jobs of a file transfer session, read by its connection or written by the cm, each have a
+//! json `Entry` in `DIR`, saved as they go on and removed when they end or when the session is
+//! closed. The entries of a process which no longer runs, or released by a cm which lost its
+//! connection, are the interrupted jobs. The id of a peer is only claimed by the client, so the
+//! entries are kept for the id with the digest of a random key of the client, sent in a `Claim` as
+//! its file transfer session starts, and only the client with this key gets them. When it connects
+//! again, the cm resumes or discards them, see `ui_cm_interface::resume_transfer_jobs`, and the
+//! client gets a `Notice` of it. It resumes its saved jobs with the same paths, continued from the partial files
+//! as any resumed job, or drops them, and the partial files of the uploads are removed on the host.
+
+use crate::{plugin_request, transfer_verify::partial_path};
+use hbb_common::{
+    config::Config,
+    fs::{self, DataSource, TransferJob},
+    get_time, log,
+    message_proto::Message,
+    sysinfo::{Pid, System},
+    tokio,
+};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+pub const ID: &str = "transfer-journal";
+pub const PLATFORM_ADDITION_KEY: &str = "support_transfer_journal";
+/// The key of the client for the host, in the config of the peer.
+pub const PEER_OPTION: &str = "transfer-journal-key";
+const DIR: &str = "transfer_journal";
+const EXT: &str = ".json";
+// How often the progress of a job is saved.
+const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+// The interrupted jobs not resumed by then are forgotten, in ms.
+const MAX_AGE: i64 = 7 * 24 * 3600 * 1000;
+
+/// A file job of the host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Job {
+    /// The host writes the files, an upload of the client.
+    pub is_write: bool,
+    /// The path on the host.
+    pub path: String,
+    /// The path on the client.
+    pub remote: String,
+    pub file_num: i32,
+    pub show_hidden: bool,
+    pub transferred: u64,
+    pub total_size: u64,
+    /// The file written, the only partial one of an upload.
+    #[serde(default)]
+    pub file: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+struct Entry {
+    /// The id of the peer with the digest of its key, see `peer`.
+    peer_id: String,
+    /// The process of the job, 0 once it is released.
+    pid: u32,
+    /// In ms.
+    updated: i64,
+    #[serde(flatten)]
+    job: Job,
+}
+
+/// The key of the client, the first message of its file transfer session.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Claim {
+    pub key: String,
+}
+
+impl Claim {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The interrupted jobs resumed or discarded by the host.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Notice {
+    pub resume: bool,
+    pub jobs: Vec<Job>,
+}
+
+impl Notice {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The entries of the jobs of a session, the ones read or the ones written.
+pub struct Journal {
+    peer_id: String,
+    conn_id: i32,
+    is_write: bool,
+    // The jobs saved, when and their progress then.
+    saved: HashMap<i32, (Instant, u64)>,
+}
+
+impl Journal {
+    /// `peer_id` is the one of `peer`.
+    pub fn new(peer_id: &str, conn_id: i32, is_write: bool) -> Self {
+        Self {
+            peer_id: peer_id.to_owned(),
+            conn_id,
+            is_write,
+            saved: Default::default(),
+        }
+    }
+
+    pub fn peer_id(&self) -> &str {
+        &self.peer_id
+    }
+
+    fn path(&self, id: i32) -> PathBuf {
+        let kind = if self.is_write { "w" } else { "r" };
+        Config::path(DIR).join(format!(
+            "{}-{}-{}{}{}",
+            std::process::id(),
+            self.conn_id,
+            kind,
+            id,
+            EXT
+        ))
+    }
+
+    fn job(&self, job: &TransferJob) -> Option<Job> {
+        let DataSource::FilePath(p) = &job.data_source else {
+            return None;
+        };
+        let file = if self.is_write {
+            job.files()
+                .get(job.file_num as usize)
+                .map(|f| fs::get_string(&TransferJob::join(p, &f.name)))
+                .unwrap_or_default()
+        } else {
+            String::new()
+        };
+        Some(Job {
+            is_write: self.is_write,
+            path: fs::get_string(p),
+            remote: job.remote.clone(),
+            file_num: job.file_num,
+            show_hidden: job.show_hidden,
+            transferred: job.transferred(),
+            total_size: job.total_size(),
+            file,
+        })
+    }
+
+    fn save(&self, id: i32, entry: &Entry) {
+        let res = std::fs::create_dir_all(Config::path(DIR)).and_then(|_| {
+            std::fs::write(self.path(id), serde_json::to_vec(entry).unwrap_or_default())
+        });
+        if let Err(e) = res {
+            log::warn!("Failed to save the transfer job {}: {}", id, e);
+        }
+    }
+
+    /// Save the progress of the jobs, at most every `SAVE_INTERVAL` for each, and remove the
+    /// entries of the jobs which ended.
+    pub fn sync(&mut self, jobs: &[TransferJob]) {
+        let ended: Vec<i32> = self
+            .saved
+            .keys()
+            .filter(|id| !jobs.iter().any(|j| j.id() == **id))
+            .cloned()
+            .collect();
+        for id in ended {
+            self.saved.remove(&id);
+            std::fs::remove_file(self.path(id)).ok();
+        }
+        for job in jobs {
+            let transferred = job.transferred();
+            if let Some((time, saved)) = self.saved.get(&job.id()) {
+                if *saved == transferred || time.elapsed() < SAVE_INTERVAL {
+                    continue;
+                }
+            }
+            let Some(j) = self.job(job) else {
+                continue;
+            };
+            let entry = Entry {
+                peer_id: self.peer_id.clone(),
+                pid: std::process::id(),
+                updated: get_time(),
+                job: j,
+            };
+            self.save(job.id(), &entry);
+            self.saved.insert(job.id(), (Instant::now(), transferred));
+        }
+    }
+
+    /// Leave the entries as interrupted jobs, for a cm which runs on without the connection of
+    /// the session.
+    pub fn release(&mut self) {
+        let ids: Vec<i32> = self.saved.drain().map(|(id, _)| id).collect();
+        for id in ids {
+            let entry = std::fs::read(self.path(id))
+                .ok()
+                .and_then(|data| serde_json::from_slice::<Entry>(&data).ok());
+            if let Some(entry) = entry {
+                self.save(id, &Entry { pid: 0, ..entry });
+            }
+        }
+    }
+
+    /// Remove the entries, the jobs of a session closed are not resumed.
+    pub fn clear(&mut self) {
+        let ids: Vec<i32> = self.saved.drain().map(|(id, _)| id).collect();
+        for id in ids {
+            std::fs::remove_file(self.path(id)).ok();
+        }
+    }
+}
+
+/// The id of the entries of a peer, its id with the digest of the key it claims.
+pub fn peer(id: &str, key: &str) -> String {
+    format!("{}-{}", id, hex::encode(Sha256::digest(key.as_bytes())))
+}
+
+fn entries() -> Vec<(PathBuf, Entry)> {
+    let Ok(dir) = std::fs::read_dir(Config::path(DIR)) else {
+        return vec![];
+    };
+    dir.filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.to_string_lossy().ends_with(EXT))
+        .filter_map(|p| {
+            let entry = serde_json::from_slice(&std::fs::read(&p).ok()?).ok()?;
+            Some((p, entry))
+        })
+        .collect()
+}
+
+// The entries of the jobs of the peer no process runs any more.
+fn select(
+    entries: Vec<(PathBuf, Entry)>,
+    peer_id: &str,
+    is_alive: impl Fn(u32) -> bool,
+) -> Vec<(PathBuf, Entry)> {
+    entries
+        .into_iter()
+        .filter(|(_, e)| e.peer_id == peer_id && (e.pid == 0 || !is_alive(e.pid)))
+        .collect()
+}
+
+// The newest entry of each job.
+fn unique(mut entries: Vec<(PathBuf, Entry)>) -> Vec<Job> {
+    entries.sort_by_key(|(_, e)| Reverse(e.updated));
+    let mut jobs: Vec<Job> = vec![];
+    for (_, e) in entries {
+        if !jobs.iter().any(|j| {
+            j.is_write == e.job.is_write && j.path == e.job.path && j.remote == e.job.remote
+        }) {
+            jobs.push(e.job);
+        }
+    }
+    jobs
+}
+
+fn interrupted_entries(peer_id: &str) -> Vec<(PathBuf, Entry)> {
+    let entries = entries();
+    if entries.is_empty() {
+        return vec![];
+    }
+    let mut sys = System::new();
+    sys.refresh_processes();
+    let is_alive = |pid: u32| sys.process(Pid::from(pid as usize)).is_some();
+    let now = get_time();
+    let (expired, entries): (Vec<_>, Vec<_>) = entries
+        .into_iter()
+        .partition(|(_, e)| now - e.updated > MAX_AGE && (e.pid == 0 || !is_alive(e.pid)));
+    for (path, _) in expired {
+        std::fs::remove_file(path).ok();
+    }
+    select(entries, peer_id, is_alive)
+}
+
+/// The jobs of the peer a restart interrupted, to resume or to discard. The processes are listed
+/// and the entries read aside, not to hold the connection.
+pub async fn interrupted(peer_id: &str) -> Vec<Job> {
+    let peer_id = peer_id.to_owned();
+    tokio::task::spawn_blocking(move || unique(interrupted_entries(&peer_id)))
+        .await
+        .unwrap_or_default()
+}
+
+/// Forget the interrupted jobs of the peer, the ones to notice it of. The partial files of the
+/// uploads are removed unless they are resumed.
+pub async fn take(peer_id: &str, resume: bool) -> Vec<Job> {
+    let peer_id = peer_id.to_owned();
+    tokio::task::spawn_blocking(move || take_entries(&peer_id, resume))
+        .await
+        .unwrap_or_default()
+}
+
+fn take_entries(peer_id: &str, resume: bool) -> Vec<Job> {
+    let entries = interrupted_entries(peer_id);
+    for (path, e) in &entries {
+        std::fs::remove_file(path).ok();
+        if !resume && e.job.is_write && !e.job.file.is_empty() {
+            std::fs::remove_file(partial_path(&e.job.file)).ok();
+        }
+    }
+    log::info!(
+        "{} {} interrupted file jobs of {}",
+        if resume { "Resume" } else { "Discard" },
+        entries.len(),
+        peer_id
+    );
+    unique(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_journal() {
+        let entry = |peer_id: &str, pid: u32, updated: i64, path: &str| {
+            (
+                PathBuf::from(format!("{}-{}{}", pid, updated, EXT)),
+                Entry {
+                    peer_id: peer_id.to_owned(),
+                    pid,
+                    updated,
+                    job: Job {
+                        is_write: true,
+                        path: path.to_owned(),
+                        remote: "/client/a".to_owned(),
+                        transferred: updated as _,
+                        ..Default::default()
+                    },
+                },
+            )
+        };
+        let entries = vec![
+            entry("1", 10, 1, "/host/a"),
+            entry("1", 11, 2, "/host/a"),
+            entry("1", 0, 3, "/host/b"),
+            entry("1", 12, 4, "/host/c"),
+            entry("2", 10, 5, "/host/a"),
+        ];
+        // 12 still runs its job.
+        let selected = select(entries, "1", |pid| pid == 12);
+        assert_eq!(selected.len(), 3);
+        let jobs = unique(selected);
+        assert_eq!(jobs.len(), 2);
+        assert_eq!((jobs[0].path.as_str(), jobs[0].transferred), ("/host/b", 3));
+        assert_eq!((jobs[1].path.as_str(), jobs[1].transferred), ("/host/a", 2));
+
+        assert_eq!(peer("1", "key"), peer("1", "key"));
+        assert_ne!(peer("1", "key"), peer("1", "other"));
+        assert_ne!(peer("1", "key"), peer("2", "key"));
+        let claim = Claim {
+            key: "key".to_owned(),
+        };
+        let msg = claim.to_message();
+        let content = &msg.misc().plugin_request().content;
+        assert_eq!(Claim::parse(content), Some(claim));
+        assert_eq!(Notice::parse(content), None);
+
+        let notice = Notice { resume: true, jobs };
+        let msg = notice.to_message();
+        let content = &msg.misc().plugin_request().content;
+        assert_eq!(Notice::parse(content), Some(notice));
+    }
+}
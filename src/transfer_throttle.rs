@@ -0,0 +1,142 @@
+//! The speed of the file transfers of a connection, so they do not starve the video stream.
+//!
+//! The client saves the `Settings` per peer in the `PeerConfig` options under `PEER_OPTION` and
+//! sends them to a host with "support_transfer_throttle" in its platform additions. The host reads
+//! the files of the connection no faster than `kbps`, and with `idle_only`, only once no connection
+//! received input or sent a video frame for `IDLE_MS`, the other connections to the host included.
+
+use crate::plugin_request;
+use hbb_common::{get_time, log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{
+    sync::atomic::{AtomicI64, Ordering},
+    time::{Duration, Instant},
+};
+
+pub const ID: &str = "transfer-throttle";
+pub const PEER_OPTION: &str = "transfer-throttle";
+pub const PLATFORM_ADDITION_KEY: &str = "support_transfer_throttle";
+const IDLE_MS: i64 = 3_000;
+// How often a transfer waiting for the idle state checks it again.
+const IDLE_CHECK: Duration = Duration::from_millis(200);
+// The data sent at once after a pause, in seconds of the limit.
+const BURST: f64 = 0.25;
+
+static LAST_ACTIVITY: AtomicI64 = AtomicI64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    /// KB/s, 0 for no limit.
+    #[serde(default)]
+    pub kbps: u32,
+    #[serde(default)]
+    pub idle_only: bool,
+}
+
+impl Settings {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// Input received or a video frame sent, the transfers with `idle_only` wait.
+pub fn on_activity() {
+    LAST_ACTIVITY.store(get_time(), Ordering::Relaxed);
+}
+
+fn is_idle() -> bool {
+    get_time() - LAST_ACTIVITY.load(Ordering::Relaxed) >= IDLE_MS
+}
+
+#[derive(Debug, Default)]
+pub struct Throttle {
+    settings: Settings,
+    // The bytes which may be read now, negative after a block larger than it.
+    allowance: f64,
+    last: Option<Instant>,
+}
+
+impl Throttle {
+    /// Set the settings of the connection from its request.
+    pub fn handle_request(&mut self, conn: i32, content: &[u8]) {
+        match serde_json::from_slice::<Settings>(content) {
+            Ok(settings) => {
+                log::info!("Transfer throttle of connection {}: {:?}", conn, settings);
+                *self = Self {
+                    settings,
+                    ..Default::default()
+                };
+            }
+            Err(e) => log::warn!("Invalid transfer throttle: {}", e),
+        }
+    }
+
+    /// How long to wait before the files are read again, `None` to read them now.
+    pub fn wait(&mut self) -> Option<Duration> {
+        self.wait_at(Instant::now(), is_idle())
+    }
+
+    fn wait_at(&mut self, now: Instant, idle: bool) -> Option<Duration> {
+        if self.settings.idle_only && !idle {
+            self.last = None;
+            return Some(IDLE_CHECK);
+        }
+        if self.settings.kbps == 0 {
+            return None;
+        }
+        let rate = self.settings.kbps as f64 * 1024.;
+        if let Some(last) = self.last {
+            let elapsed = now.saturating_duration_since(last).as_secs_f64();
+            self.allowance = (self.allowance + rate * elapsed).min(rate * BURST);
+        }
+        self.last = Some(now);
+        if self.allowance >= 0. {
+            None
+        } else {
+            Some(Duration::from_secs_f64(-self.allowance / rate))
+        }
+    }
+
+    /// The bytes of the files just read.
+    pub fn consume(&mut self, bytes: u64) {
+        if self.settings.kbps > 0 {
+            self.allowance -= bytes as f64;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_throttle() {
+        let now = Instant::now();
+        let mut throttle = Throttle::default();
+        assert_eq!(throttle.wait_at(now, false), None);
+
+        throttle.handle_request(1, br#"{"kbps":100}"#);
+        assert_eq!(throttle.wait_at(now, false), None);
+        throttle.consume(50 * 1024);
+        assert_eq!(
+            throttle.wait_at(now, false),
+            Some(Duration::from_millis(500))
+        );
+        assert_eq!(
+            throttle.wait_at(now + Duration::from_millis(500), false),
+            None
+        );
+        // A long pause gives no more than the burst.
+        let later = now + Duration::from_secs(10);
+        assert_eq!(throttle.wait_at(later, false), None);
+        throttle.consume(50 * 1024);
+        assert_eq!(
+            throttle.wait_at(later, false),
+            Some(Duration::from_millis(250))
+        );
+
+        throttle.handle_request(1, br#"{"idle_only":true}"#);
+        assert_eq!(throttle.wait_at(now, false), Some(IDLE_CHECK));
+        assert_eq!(throttle.wait_at(now, true), None);
+    }
+}
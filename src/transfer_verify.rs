@@ -0,0 +1,159 @@
+//! The checksums of the data already received, before a file transfer is resumed.
+//!
+//! A transfer resumes after a reconnect from the size of the partial file of the receiver, with
+//! the jobs saved in the config of the peer. Before a download is resumed, the client sends the
+//! sha256 of each `CHUNK` of the partial file, and the host compares them with its file and
+//! answers the offset of the end of the first chunks which match. The partial file is cut there
+//! and the transfer continues from it, so a partial file changed or written wrong is not
+//! completed with the rest of the file. The offsets are the `transferred_size` of the digests.
+//!
+//! A host with "support_transfer_verify" in its platform additions answers the `Request` with a
+//! `Response`.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::File,
+    io::{self, Read},
+};
+
+pub const ID: &str = "transfer-verify";
+pub const PLATFORM_ADDITION_KEY: &str = "support_transfer_verify";
+const CHUNK: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Request {
+    pub id: i32,
+    pub file_num: i32,
+    /// The size of the partial file.
+    pub offset: u64,
+    /// The sha256 of each chunk of the partial file, in hex.
+    pub hashes: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Response {
+    pub id: i32,
+    pub file_num: i32,
+    /// Where to continue from.
+    pub offset: u64,
+}
+
+impl Request {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+impl Response {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The partial file written by the receiver of `path`.
+pub fn partial_path(path: &str) -> String {
+    format!("{}.download", path)
+}
+
+/// The sha256 of each chunk of the first `len` bytes of the file, the last chunk may be shorter.
+pub fn chunk_hashes(path: &str, len: u64) -> io::Result<Vec<String>> {
+    let mut file = File::open(path)?.take(len);
+    let mut hashes = vec![];
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let mut hasher = Sha256::new();
+        let mut read = 0;
+        while read < CHUNK {
+            let max = buf.len().min((CHUNK - read) as usize);
+            let n = file.read(&mut buf[..max])?;
+            if n == 0 {
+                break;
+            }
+            hasher.update(&buf[..n]);
+            read += n as u64;
+        }
+        if read == 0 {
+            break;
+        }
+        hashes.push(hex::encode(hasher.finalize()));
+        if read < CHUNK {
+            break;
+        }
+    }
+    Ok(hashes)
+}
+
+/// The end of the first chunks of the request which match the file of the sender.
+pub fn verified_offset(path: &str, req: &Request) -> u64 {
+    let Ok(hashes) = chunk_hashes(path, req.offset) else {
+        return 0;
+    };
+    let matched = hashes
+        .iter()
+        .zip(&req.hashes)
+        .take_while(|(a, b)| a == b)
+        .count() as u64;
+    (matched * CHUNK).min(req.offset)
+}
+
+/// Cut the partial file at the verified offset, the data after it is received again.
+pub fn truncate(path: &str, offset: u64) -> io::Result<()> {
+    std::fs::OpenOptions::new()
+        .write(true)
+        .open(partial_path(path))?
+        .set_len(offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transfer_verify() {
+        let dir = std::env::temp_dir().join(format!("transfer-verify-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let source = dir.join("source").to_string_lossy().to_string();
+        let data: Vec<u8> = (0..CHUNK * 2 + 10).map(|i| i as u8).collect();
+        std::fs::write(&source, &data).unwrap();
+        let partial = partial_path(&source);
+
+        // The whole partial file matches, even the last chunk shorter than the others.
+        let len = CHUNK + 5;
+        std::fs::write(&partial, &data[..len as usize]).unwrap();
+        let req = Request {
+            id: 1,
+            file_num: 0,
+            offset: len,
+            hashes: chunk_hashes(&partial, len).unwrap(),
+        };
+        assert_eq!(req.hashes.len(), 2);
+        assert_eq!(verified_offset(&source, &req), len);
+
+        // A byte changed in the second chunk, the transfer continues after the first one.
+        let mut changed = data[..len as usize].to_vec();
+        changed[CHUNK as usize + 1] ^= 0xff;
+        std::fs::write(&partial, &changed).unwrap();
+        let req = Request {
+            hashes: chunk_hashes(&partial, len).unwrap(),
+            ..req
+        };
+        assert_eq!(verified_offset(&source, &req), CHUNK);
+        truncate(&source, CHUNK).unwrap();
+        assert_eq!(std::fs::metadata(&partial).unwrap().len(), CHUNK);
+
+        let res = Response::parse(br#"{"id":1,"file_num":2,"offset":3}"#).unwrap();
+        assert_eq!(res.offset, 3);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -1,7 +1,10 @@
-use crate::client::translate;
+use crate::{client::translate, service_status::ServiceStatus};
 use hbb_common::{allow_err, log};
 use std::sync::{Arc, Mutex};
 
+// The sessions listed in the tooltip, the others are only counted.
+const MAX_LISTED_SESSIONS: usize = 5;
+
 pub fn start_tray() {
     if crate::ui_interface::get_builtin_option(hbb_common::config::keys::OPTION_HIDE_TRAY) == "Y" {
         // On macOS, we still need the tray event loop even if hidden
@@ -34,36 +37,27 @@ fn make_tray() -> hbb_common::ResultType<()> {
     let mut event_loop = EventLoopBuilder::new().build();
 
     let tray_menu = Menu::new();
-    let quit_i = MenuItem::new(translate("Stop service".to_owned()), true, None);
+    let quit_i = MenuItem::new(translate("Stop service".to_owned()), false, None);
     let open_i = MenuItem::new(translate("Open".to_owned()), true, None);
     tray_menu.append_items(&[&open_i, &quit_i]).ok();
-    let tooltip = |count: usize| {
-        if count == 0 {
-            format!(
-                "{} {}",
-                crate::get_app_name(),
-                translate("Service is running".to_owned()),
-            )
-        } else {
-            format!(
-                "{} - {}\n{}",
-                crate::get_app_name(),
-                translate("Ready".to_owned()),
-                translate("{".to_string() + &format!("{count}") + "} sessions"),
-            )
-        }
-    };
     let mut _tray_icon: Arc<Mutex<Option<TrayIcon>>> = Default::default();
 
+    // The tray only shows the state of the server, which may run in another process.
+    let polled_status: Arc<Mutex<Option<Option<ServiceStatus>>>> = Default::default();
+    let polled = polled_status.clone();
+    std::thread::spawn(move || {
+        crate::service_status::watch(move |status| *polled.lock().unwrap() = Some(status))
+    });
+    let mut status: Option<ServiceStatus> = None;
+
     let menu_channel = MenuEvent::receiver();
     let tray_channel = TrayEvent::receiver();
 
     let open_func = move || {
-        if cfg!(not(feature = "flutter")) {
-            crate::run_me::<&str>(vec![]).ok();
-            return;
-        }
+        #[cfg(all(target_os = "macos", feature = "flutter"))]
         crate::platform::macos::handle_application_should_open_untitled_file();
+        #[cfg(not(all(target_os = "macos", feature = "flutter")))]
+        crate::run_me::<&str>(vec![]).ok();
     };
 
     #[cfg(target_os = "macos")]
@@ -86,7 +80,7 @@ fn make_tray() -> hbb_common::ResultType<()> {
             // to prevent issues like https://github.com/tauri-apps/tray-icon/issues/90
             let tray = TrayIconBuilder::new()
                 .with_menu(Box::new(tray_menu.clone()))
-                .with_tooltip(tooltip(0))
+                .with_tooltip(tooltip(&status))
                 .with_icon(icon.clone())
                 .with_icon_as_template(true) // mac only
                 .build();
@@ -108,6 +102,25 @@ fn make_tray() -> hbb_common::ResultType<()> {
             }
         }
 
+        if let Some(polled) = polled_status.lock().unwrap().take() {
+            if polled != status {
+                status = polled;
+                if let Some(tray) = _tray_icon.lock().unwrap().as_ref() {
+                    tray.set_tooltip(Some(tooltip(&status))).ok();
+                }
+                let stopped = status.as_ref().map(|s| s.stopped).unwrap_or(false);
+                quit_i.set_text(translate(
+                    if stopped {
+                        "Start service"
+                    } else {
+                        "Stop service"
+                    }
+                    .to_owned(),
+                ));
+                quit_i.set_enabled(status.is_some());
+            }
+        }
+
         if let Ok(event) = menu_channel.try_recv() {
             if event.id == quit_i.id() {
                 /* failed in windows, seems no permission to check system process
@@ -116,8 +129,14 @@ fn make_tray() -> hbb_common::ResultType<()> {
                     return;
                 }
                 */
-                if !crate::platform::uninstall_service(false, false) {
-                    *control_flow = ControlFlow::Exit;
+                if status.as_ref().map(|s| s.stopped).unwrap_or(false) {
+                    std::thread::spawn(|| crate::ipc::set_option("stop-service", ""));
+                } else if cfg!(target_os = "macos") {
+                    if !crate::platform::uninstall_service(false, false) {
+                        *control_flow = ControlFlow::Exit;
+                    }
+                } else {
+                    std::thread::spawn(|| crate::ipc::set_option("stop-service", "Y"));
                 }
             } else if event.id == open_i.id() {
                 open_func();
@@ -131,6 +150,34 @@ fn make_tray() -> hbb_common::ResultType<()> {
     });
 }
 
+fn tooltip(status: &Option<ServiceStatus>) -> String {
+    let app_name = crate::get_app_name();
+    let Some(status) = status.as_ref().filter(|s| !s.stopped) else {
+        return format!(
+            "{} - {}",
+            app_name,
+            translate("Service is not running".to_owned())
+        );
+    };
+    if !status.online {
+        return format!("{} - {}", app_name, translate("Connecting...".to_owned()));
+    }
+    if status.sessions.is_empty() {
+        return format!(
+            "{} {}",
+            app_name,
+            translate("Service is running".to_owned())
+        );
+    }
+    let count = status.sessions.len();
+    let mut lines = vec![
+        format!("{} - {}", app_name, translate("Ready".to_owned())),
+        translate("{".to_string() + &format!("{count}") + "} sessions"),
+    ];
+    lines.extend(status.sessions.iter().take(MAX_LISTED_SESSIONS).cloned());
+    lines.join("\n")
+}
+
 fn load_icon_from_asset() -> Option<image::DynamicImage> {
     let Some(path) = std::env::current_exe().map_or(None, |x| x.parent().map(|x| x.to_path_buf()))
     else {
@@ -68,7 +68,33 @@ impl InvokeUiCM for SciterHandler {
         );
     }
 
+    fn update_client_record_status(&self, _client: &crate::ui_cm_interface::Client) {}
+
+    fn update_client_tunnels(&self, _client: &crate::ui_cm_interface::Client) {}
+
+    fn permission_request(&self, _id: i32, _name: String) {}
+
+    // No dialog to ask, the jobs are discarded.
+    fn interrupted_transfer_jobs(&self, id: i32, jobs: &[crate::transfer_journal::Job]) {
+        log::info!(
+            "{} interrupted file jobs of connection {} discarded",
+            jobs.len(),
+            id
+        );
+        crate::ui_cm_interface::resume_transfer_jobs(id, false);
+    }
+
     fn file_transfer_log(&self, _action: &str, _log: &str) {}
+
+    fn file_paste_progress(
+        &self,
+        _id: i32,
+        _transferred: u64,
+        _total: u64,
+        _canceled: bool,
+        _failed: bool,
+    ) {
+    }
 }
 
 impl SciterHandler {
@@ -487,6 +487,7 @@ impl sciter::EventHandler for SciterSession {
         fn get_default_pi();
         fn get_option(String);
         fn t(String);
+        fn format_bytes(f64);
         fn set_option(String, String);
         fn input_os_password(String, bool);
         fn save_close_state(String, String);
@@ -618,6 +619,10 @@ impl SciterSession {
         crate::client::translate(name)
     }
 
+    pub fn format_bytes(&self, size: f64) -> String {
+        crate::locale_format::bytes(size)
+    }
+
     pub fn get_icon(&self) -> String {
         super::get_icon()
     }
@@ -125,6 +125,12 @@ pub struct Client {
     pub from_switch: bool,
     pub in_voice_call: bool,
     pub incoming_voice_call: bool,
+    // The peer is recording the session.
+    pub in_recording: bool,
+    // The input, the clipboard and the changes of the files are refused, see `set_view_only`.
+    pub view_only: bool,
+    // The channels of the tunnels open and their targets, see `mux`.
+    pub tunnels: Vec<(u32, String)>,
     #[serde(skip)]
     tx: UnboundedSender<Data>,
 }
@@ -139,6 +145,8 @@ struct IpcTaskRunner<T: InvokeUiCM> {
     conn_id: i32,
     /// Read jobs for CM-side file reading (server to client transfers)
     read_jobs: Vec<fs::TransferJob>,
+    /// The write jobs of a file transfer session, see `transfer_journal`.
+    transfer_journal: Option<crate::transfer_journal::Journal>,
 }
 
 lazy_static::lazy_static! {
@@ -167,7 +175,25 @@ pub trait InvokeUiCM: Send + Clone + 'static + Sized {
 
     fn update_voice_call_state(&self, client: &Client);
 
+    fn update_client_record_status(&self, client: &Client);
+
+    fn update_client_tunnels(&self, client: &Client);
+
+    fn permission_request(&self, id: i32, name: String);
+
+    // Answered with `resume_transfer_jobs`.
+    fn interrupted_transfer_jobs(&self, id: i32, jobs: &[crate::transfer_journal::Job]);
+
     fn file_transfer_log(&self, action: &str, log: &str);
+
+    fn file_paste_progress(
+        &self,
+        id: i32,
+        transferred: u64,
+        total: u64,
+        canceled: bool,
+        failed: bool,
+    );
 }
 
 impl<T: InvokeUiCM> Deref for ConnectionManager<T> {
@@ -226,6 +252,9 @@ impl<T: InvokeUiCM> ConnectionManager<T> {
             tx,
             in_voice_call: false,
             incoming_voice_call: false,
+            in_recording: false,
+            tunnels: Default::default(),
+            view_only: false,
         };
         CLIENTS
             .write()
@@ -261,6 +290,23 @@ impl<T: InvokeUiCM> ConnectionManager<T> {
         }
     }
 
+    fn client_record_status(&self, id: i32, start: bool) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            client.in_recording = start;
+            self.ui_handler.update_client_record_status(client);
+        }
+    }
+
+    fn mux_tunnel(&self, id: i32, channel: u32, target: String, open: bool) {
+        if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+            client.tunnels.retain(|(c, _)| *c != channel);
+            if open {
+                client.tunnels.push((channel, target));
+            }
+            self.ui_handler.update_client_tunnels(client);
+        }
+    }
+
     fn voice_call_incoming(&self, id: i32) {
         if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
             client.incoming_voice_call = true;
@@ -326,6 +372,48 @@ pub fn switch_permission(id: i32, name: String, enabled: bool) {
     };
 }
 
+// A grant switches the permission first, so it is on once the peer is told.
+#[inline]
+pub fn answer_permission_request(id: i32, name: String, granted: bool) {
+    if let Some(client) = CLIENTS.read().unwrap().get(&id) {
+        if granted {
+            allow_err!(client.tx.send(Data::SwitchPermission {
+                name: name.clone(),
+                enabled: true
+            }));
+        }
+        allow_err!(client.tx.send(Data::PermissionRequest {
+            name,
+            granted: Some(granted)
+        }));
+    };
+}
+
+// Makes the session view only at once, or restores it, without changing its permissions.
+#[inline]
+pub fn set_view_only(id: i32, on: bool) {
+    if let Some(client) = CLIENTS.write().unwrap().get_mut(&id) {
+        client.view_only = on;
+        allow_err!(client.tx.send(Data::ViewOnly(on)));
+    };
+}
+
+// Resumes the file jobs of the peer a restart interrupted, or discards them.
+#[inline]
+pub fn resume_transfer_jobs(id: i32, resume: bool) {
+    if let Some(client) = CLIENTS.read().unwrap().get(&id) {
+        allow_err!(client.tx.send(Data::ResumeTransferJobs(resume)));
+    };
+}
+
+// Cancels the paste of the files of the peer on the host, or the one of the host on the peer.
+#[inline]
+pub fn cancel_file_paste(id: i32) {
+    if let Some(client) = CLIENTS.read().unwrap().get(&id) {
+        allow_err!(client.tx.send(Data::CancelFilePaste));
+    };
+}
+
 #[inline]
 pub fn get_clients_state() -> String {
     let clients = CLIENTS.read().unwrap();
@@ -373,6 +461,10 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                     match res {
                         Err(err) => {
                             log::info!("cm ipc connection closed: {}", err);
+                            // The server may be restarting, its jobs are resumed later.
+                            if let Some(journal) = self.transfer_journal.as_mut() {
+                                journal.release();
+                            }
                             break;
                         }
                         Ok(Some(data)) => {
@@ -386,11 +478,17 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                 }
                                 Data::Close => {
                                     log::info!("cm ipc connection closed from connection request");
+                                    if let Some(journal) = self.transfer_journal.as_mut() {
+                                        journal.clear();
+                                    }
                                     break;
                                 }
                                 Data::Disconnected => {
                                     self.close = false;
                                     log::info!("cm ipc connection disconnect");
+                                    if let Some(journal) = self.transfer_journal.as_mut() {
+                                        journal.clear();
+                                    }
                                     break;
                                 }
                                 Data::PrivacyModeState((_id, _, _)) => {
@@ -401,6 +499,15 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                 Data::ChatMessage { text } => {
                                     self.cm.new_message(self.conn_id, text);
                                 }
+                                Data::PermissionRequest { name, granted: None } => {
+                                    self.cm.permission_request(self.conn_id, name);
+                                }
+                                Data::TransferJournal(peer_id, jobs) => {
+                                    self.transfer_journal = Some(crate::transfer_journal::Journal::new(&peer_id, self.conn_id, true));
+                                    if !jobs.is_empty() {
+                                        self.cm.interrupted_transfer_jobs(self.conn_id, &jobs);
+                                    }
+                                }
                                 Data::FS(mut fs) => {
                                     if let ipc::FS::WriteBlock { id, file_num, data: _, compressed } = fs {
                                         if let Ok(bytes) = self.stream.next_raw().await {
@@ -416,12 +523,18 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                     if !self.read_jobs.is_empty() {
                                         file_timer = crate::rustdesk_interval(time::interval(MILLI5));
                                     }
+                                    if let Some(journal) = self.transfer_journal.as_mut() {
+                                        journal.sync(&write_jobs);
+                                    }
                                     let log = fs::serialize_transfer_jobs(&write_jobs);
                                     self.cm.ui_handler.file_transfer_log("transfer", &log);
                                 }
                                 Data::FileTransferLog((action, log)) => {
                                     self.cm.ui_handler.file_transfer_log(&action, &log);
                                 }
+                                Data::FilePasteProgress { transferred, total, canceled, failed } => {
+                                    self.cm.file_paste_progress(self.conn_id, transferred, total, canceled, failed);
+                                }
                                 Data::ClipboardFileEnabled(_enabled) => {
                                 }
                                 Data::Theme(dark) => {
@@ -443,6 +556,12 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
                                 Data::CloseVoiceCall(reason) => {
                                     self.cm.voice_call_closed(self.conn_id, reason.as_str());
                                 }
+                                Data::ClientRecordStatus(start) => {
+                                    self.cm.client_record_status(self.conn_id, start);
+                                }
+                                Data::MuxTunnel { channel, target, open } => {
+                                    self.cm.mux_tunnel(self.conn_id, channel, target, open);
+                                }
                                 _ => {
 
                                 }
@@ -525,12 +644,14 @@ impl<T: InvokeUiCM> IpcTaskRunner<T> {
             running: true,
             conn_id: 0,
             read_jobs: Vec::new(),
+            transfer_journal: None,
         };
 
         while task_runner.running {
             task_runner.run().await;
         }
         if task_runner.conn_id > 0 {
+            crate::file_trash::remove(task_runner.conn_id);
             task_runner
                 .cm
                 .remove_connection(task_runner.conn_id, task_runner.close);
@@ -571,7 +692,7 @@ async fn handle_fs(
     read_jobs: &mut Vec<fs::TransferJob>,
     tx: &UnboundedSender<Data>,
     tx_log: Option<&UnboundedSender<String>>,
-    _conn_id: i32,
+    conn_id: i32,
 ) {
     match fs {
         ipc::FS::ReadEmptyDirs {
@@ -590,11 +711,28 @@ async fn handle_fs(
             path,
             id,
             recursive,
+            trash,
         } => {
-            remove_dir(path, id, recursive, tx).await;
+            if trash {
+                trash_dir(conn_id, path, id, recursive, tx).await;
+            } else {
+                remove_dir(path, id, recursive, tx).await;
+            }
         }
-        ipc::FS::RemoveFile { path, id, file_num } => {
-            remove_file(path, id, file_num, tx).await;
+        ipc::FS::RemoveFile {
+            path,
+            id,
+            file_num,
+            trash,
+        } => {
+            if trash {
+                trash_file(conn_id, path, id, file_num, tx).await;
+            } else {
+                remove_file(path, id, file_num, tx).await;
+            }
+        }
+        ipc::FS::RestoreTrashed { id, items } => {
+            restore_trashed(conn_id, id, items, tx).await;
         }
         ipc::FS::CreateDir { path, id } => {
             create_dir(path, id, tx).await;
@@ -1231,6 +1369,61 @@ async fn remove_file(path: String, id: i32, file_num: i32, tx: &UnboundedSender<
     .await;
 }
 
+async fn trash_file(
+    conn_id: i32,
+    path: String,
+    id: i32,
+    file_num: i32,
+    tx: &UnboundedSender<Data>,
+) {
+    let res = spawn_blocking(move || crate::file_trash::trash(conn_id, &path)).await;
+    let res = match res {
+        Ok(Ok(item)) => {
+            send_raw(
+                crate::file_trash::Response::Trashed { id, item }.to_message(),
+                tx,
+            );
+            Ok(Ok(()))
+        }
+        Ok(Err(err)) => Ok(Err(err)),
+        Err(err) => Err(err),
+    };
+    handle_result(res, id, file_num, tx).await;
+}
+
+// The folder is trashed when the removal of its files left it empty, as `remove_dir` would
+// remove it, otherwise only its empty folders are removed.
+async fn trash_dir(
+    conn_id: i32,
+    path: String,
+    id: i32,
+    recursive: bool,
+    tx: &UnboundedSender<Data>,
+) {
+    let dir = fs::get_path(&path);
+    if !recursive || !crate::file_trash::is_empty_tree(&dir) {
+        return remove_dir(path, id, recursive, tx).await;
+    }
+    trash_file(conn_id, path, id, 0, tx).await;
+}
+
+async fn restore_trashed(
+    conn_id: i32,
+    id: i32,
+    items: Vec<crate::file_trash::Trashed>,
+    tx: &UnboundedSender<Data>,
+) {
+    let error = match spawn_blocking(move || crate::file_trash::restore(conn_id, &items)).await {
+        Ok(Ok(())) => String::new(),
+        Ok(Err(err)) => err.to_string(),
+        Err(err) => err.to_string(),
+    };
+    send_raw(
+        crate::file_trash::Response::Restored { id, error }.to_message(),
+        tx,
+    );
+}
+
 async fn create_dir(path: String, id: i32, tx: &UnboundedSender<Data>) {
     handle_result(
         spawn_blocking(move || fs::create_dir(&path)).await,
@@ -170,6 +170,10 @@ pub fn get_builtin_option(key: &str) -> String {
 
 #[inline]
 pub fn set_local_option(key: String, value: String) {
+    if let Err(e) = crate::hotkey_passthrough::validate(&key, &value) {
+        log::error!("Failed to set {}: {}", key, e);
+        return;
+    }
     LocalConfig::set_option(key.clone(), value.clone());
 }
 
@@ -297,6 +301,16 @@ pub fn get_sound_inputs() -> Vec<String> {
     a
 }
 
+/// Json of the input and the output audio devices that the viewers can pick, the output
+/// devices are captured by loopback.
+pub fn get_audio_devices() -> String {
+    // Not in the UI thread, see `get_sound_inputs`.
+    let (inputs, outputs) = std::thread::spawn(crate::audio_service::get_devices)
+        .join()
+        .unwrap_or_default();
+    serde_json::json!({ "inputs": inputs, "outputs": outputs }).to_string()
+}
+
 #[inline]
 pub fn set_options(m: HashMap<String, String>) {
     *OPTIONS.lock().unwrap() = m.clone();
@@ -305,6 +319,10 @@ pub fn set_options(m: HashMap<String, String>) {
 
 #[inline]
 pub fn set_option(key: String, value: String) {
+    if let Err(e) = crate::port_range::validate(&key, &value) {
+        log::error!("Failed to set {}: {}", key, e);
+        return;
+    }
     if &key == "stop-service" {
         #[cfg(target_os = "macos")]
         {
@@ -431,6 +449,19 @@ pub fn get_peer(id: String) -> PeerConfig {
     PeerConfig::load(&id)
 }
 
+/// Json of the first-run setup, see `onboarding::Status`.
+pub fn get_onboarding() -> String {
+    serde_json::to_string(&crate::onboarding::status()).unwrap_or_default()
+}
+
+/// Run an action of the first-run setup, returns the error, empty on success.
+pub fn onboarding_action(action: String, value: String) -> String {
+    match crate::onboarding::Action::parse(&action, &value).and_then(crate::onboarding::apply) {
+        Ok(()) => "".to_owned(),
+        Err(e) => e.to_string(),
+    }
+}
+
 #[inline]
 pub fn get_fav() -> Vec<String> {
     LocalConfig::get_fav()
@@ -533,6 +564,20 @@ pub fn peer_to_map(id: String, p: PeerConfig) -> HashMap<&'static str, String> {
             "alias",
             p.options.get("alias").unwrap_or(&"".to_owned()).to_owned(),
         ),
+        (
+            "display_name",
+            p.options
+                .get(crate::peer_identity::PEER_OPTION_DISPLAY_NAME)
+                .cloned()
+                .unwrap_or_default(),
+        ),
+        (
+            "avatar",
+            p.options
+                .get(crate::peer_identity::PEER_OPTION_AVATAR)
+                .cloned()
+                .unwrap_or_default(),
+        ),
         (
             "hash",
             base64::encode(p.password, base64::Variant::Original),
@@ -738,6 +783,183 @@ pub fn get_api_server() -> String {
     )
 }
 
+/// Returns the error, or empty if the message is accepted by the api server.
+pub fn send_peer_message(id: String, text: String) -> String {
+    match crate::hbbs_http::peer_message::send(&id, &text) {
+        Ok(_) => "".to_owned(),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[inline]
+pub fn get_peer_messages() -> String {
+    serde_json::to_string(&crate::hbbs_http::peer_message::get_inbox()).unwrap_or_default()
+}
+
+/// Returns the error, or empty if the api server will tell us when the host is free.
+pub fn add_host_wait(id: String) -> String {
+    match crate::hbbs_http::peer_message::register_host_wait(&id) {
+        Ok(_) => "".to_owned(),
+        Err(e) => e.to_string(),
+    }
+}
+
+#[inline]
+pub fn take_host_free_peers() -> String {
+    serde_json::to_string(&crate::hbbs_http::peer_message::take_host_free()).unwrap_or_default()
+}
+
+/// Returns the error, or empty if the status is saved and will be published with the next heartbeat.
+pub fn set_availability(status: String, note: String) -> String {
+    use crate::hbbs_http::availability::*;
+    match check(&status, &note) {
+        Ok((status, note)) => {
+            set_option(OPTION_AVAILABILITY_STATUS.to_owned(), status);
+            set_option(OPTION_AVAILABILITY_NOTE.to_owned(), note);
+            "".to_owned()
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Set the screen regions hidden from the controllers, see `region_filter`. Empty to clear.
+pub fn set_privacy_regions(regions: String) -> String {
+    use crate::server::region_filter::*;
+    match parse(&regions) {
+        Ok(_) => {
+            set_option(OPTION_PRIVACY_REGIONS.to_owned(), regions.trim().to_owned());
+            "".to_owned()
+        }
+        Err(e) => e.to_string(),
+    }
+}
+
+/// Json list of the windows that can be shared, see `shared_window`.
+pub fn get_windows() -> String {
+    crate::server::shared_window::list()
+}
+
+/// Share only the window with this id with the controllers. Empty to share the screen.
+pub fn set_shared_window(id: String) -> String {
+    use crate::server::shared_window::*;
+    let id = id.trim();
+    if !id.is_empty() {
+        match parse_id(id) {
+            Some(id) if scrap::quartz::window::window(id).is_some() => {}
+            _ => return "window not found".to_owned(),
+        }
+    }
+    set_option(OPTION_SHARED_WINDOW.to_owned(), id.to_owned());
+    "".to_owned()
+}
+
+/// Json list of the encode and network statistics of the incoming connections.
+#[tokio::main(flavor = "current_thread")]
+pub async fn get_session_stats() -> String {
+    ipc::get_session_stats(1000).await.unwrap_or_default()
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn get_connectivity() -> (String, i32, Option<String>) {
+    let (server, _) = ipc::get_rendezvous_server(1000).await;
+    let nat_type = ipc::get_nat_type(1000).await;
+    let port_mapping = ipc::get_port_mapping(1000).await.ok();
+    (server, nat_type, port_mapping)
+}
+
+/// The connectivity of this host, for `--doctor`.
+pub fn doctor() -> String {
+    use crate::{port_mapping, port_range};
+    use hbb_common::protobuf::Enum;
+    let id = ipc::get_id();
+    let options = ipc::get_options();
+    let get = |k: &str| options.get(k).cloned().unwrap_or_default();
+    let (server, nat_type, mapping) = get_connectivity();
+    let nat_type = match NatType::from_i32(nat_type) {
+        Some(NatType::ASYMMETRIC) => "asymmetric",
+        Some(NatType::SYMMETRIC) => "symmetric",
+        _ => "unknown",
+    };
+    let direct = if config::option2bool(OPTION_DIRECT_SERVER, &get(OPTION_DIRECT_SERVER)) {
+        let ports = match port_range::PortRange::parse(&get(port_range::OPTION_DIRECT_PORT_RANGE)) {
+            Ok(Some(range)) => range.to_string(),
+            _ => match get("direct-access-port") {
+                port if port.is_empty() => (RENDEZVOUS_PORT + 2).to_string(),
+                port => port,
+            },
+        };
+        format!("on, port {}", ports)
+    } else {
+        "off".to_owned()
+    };
+    let punch = match get(port_range::OPTION_PUNCH_PORT_RANGE) {
+        range if range.is_empty() => "any".to_owned(),
+        range => range,
+    };
+    let status = mapping.and_then(|s| serde_json::from_str::<port_mapping::Status>(&s).ok());
+    let mapping = match status {
+        None => "unknown, the service is not running".to_owned(),
+        Some(s) if !s.enabled => "off".to_owned(),
+        Some(port_mapping::Status {
+            protocol: Some(protocol),
+            internal_port,
+            external_port,
+            external_ip,
+            expires_in,
+            ..
+        }) => format!(
+            "on, TCP {} to {}:{} with {:?}, expires in {}s",
+            internal_port, external_ip, external_port, protocol, expires_in
+        ),
+        Some(s) if !s.error.is_empty() => format!("on, failed: {}", s.error),
+        Some(_) => "on, not mapped yet".to_owned(),
+    };
+    [
+        format!("Version: {}", crate::VERSION),
+        format!("ID: {}", id),
+        format!("Rendezvous server: {}", server),
+        format!("NAT type: {}", nat_type),
+        format!("Direct IP access: {}", direct),
+        format!("Punch hole ports: {}", punch),
+        format!("Port mapping: {}", mapping),
+    ]
+    .join("\n")
+}
+
+/// Json list of the registered options matching the query, with the translated description
+/// as "label". See `server::option_registry`.
+pub fn list_options(query: String) -> String {
+    let options: Vec<serde_json::Value> = crate::server::option_registry::search(&query)
+        .into_iter()
+        .filter_map(|o| {
+            let mut v = serde_json::to_value(o).ok()?;
+            v["label"] = serde_json::json!(crate::client::translate(o.description.to_owned()));
+            Some(v)
+        })
+        .collect();
+    serde_json::to_string(&options).unwrap_or_default()
+}
+
+/// Json object of the local chords with their target, see `hotkey_passthrough`.
+pub fn get_hotkey_passthrough() -> String {
+    serde_json::to_string(&crate::hotkey_passthrough::list()).unwrap_or_default()
+}
+
+/// Send the chord to the remote or keep it on this computer, an empty target to remove it.
+pub fn set_hotkey_passthrough(chord: String, target: String) -> String {
+    use crate::hotkey_passthrough::*;
+    let target = match target.as_str() {
+        "" => None,
+        "remote" => Some(Target::Remote),
+        "local" => Some(Target::Local),
+        _ => return format!("Invalid target: {}", target),
+    };
+    match set(&chord, target) {
+        Ok(()) => "".to_owned(),
+        Err(e) => e,
+    }
+}
+
 #[inline]
 pub fn has_hwcodec() -> bool {
     // Has real hardware codec using gpu
@@ -1251,3 +1473,59 @@ pub fn is_remote_modify_enabled_by_control_permissions() -> Option<bool> {
         .lock()
         .unwrap()
 }
+
+pub const A11Y_EVENT_CONNECTED: &str = "connected";
+pub const A11Y_EVENT_PERMISSION_DENIED: &str = "permission-denied";
+pub const A11Y_EVENT_FILE_JOB_FINISHED: &str = "file-job-finished";
+
+/// Semantic session event for screen readers, so that UIs do not have to parse log text.
+#[derive(Debug, Clone, Serialize)]
+pub struct A11yEvent {
+    /// One of `A11Y_EVENT_*`.
+    pub kind: &'static str,
+    pub peer_id: String,
+    /// Localized text, ready to be announced.
+    pub text: String,
+    /// Structured details of the event, e.g. `errors` of a file job.
+    pub details: serde_json::Value,
+    pub time: i64,
+}
+
+/// Returns false if the receiver is gone and the sink should be removed.
+pub type A11ySink = Box<dyn Fn(&str) -> bool + Send + Sync>;
+
+lazy_static::lazy_static! {
+    static ref A11Y_SINKS: Mutex<HashMap<String, A11ySink>> = Default::default();
+}
+
+pub fn start_a11y_event_stream(name: String, sink: A11ySink) {
+    A11Y_SINKS.lock().unwrap().insert(name, sink);
+}
+
+pub fn stop_a11y_event_stream(name: &str) {
+    A11Y_SINKS.lock().unwrap().remove(name);
+}
+
+/// `text` is translated, it may contain a `{}` placeholder as in `translate`.
+pub fn push_a11y_event(
+    kind: &'static str,
+    peer_id: &str,
+    text: String,
+    details: serde_json::Value,
+) {
+    let mut sinks = A11Y_SINKS.lock().unwrap();
+    if sinks.is_empty() {
+        return;
+    }
+    let event = A11yEvent {
+        kind,
+        peer_id: peer_id.to_owned(),
+        text: crate::client::translate(text),
+        details,
+        time: hbb_common::get_time(),
+    };
+    let Ok(event) = serde_json::to_string(&event) else {
+        return;
+    };
+    sinks.retain(|_, sink| sink(&event));
+}
@@ -2,8 +2,9 @@ use crate::{
     common::{get_supported_keyboard_modes, is_keyboard_mode_supported},
     input::{
         MOUSE_BUTTON_LEFT, MOUSE_BUTTON_RIGHT, MOUSE_TYPE_DOWN, MOUSE_TYPE_MASK,
-        MOUSE_TYPE_TRACKPAD, MOUSE_TYPE_UP, MOUSE_TYPE_WHEEL,
+        MOUSE_TYPE_TRACKPAD, MOUSE_TYPE_UP, MOUSE_TYPE_WHEEL, MOUSE_TYPE_WHEEL_PRECISE,
     },
+    toolbar_actions,
     ui_interface::use_texture_render,
 };
 use async_trait::async_trait;
@@ -34,7 +35,7 @@ use std::{
         atomic::{AtomicUsize, Ordering},
         Arc, Mutex, RwLock,
     },
-    time::SystemTime,
+    time::{Duration, SystemTime},
 };
 use uuid::Uuid;
 
@@ -425,18 +426,42 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(LoginConfigHandler::refresh()));
     }
 
+    /// Ask the peer for a key frame of the display, returns false if the peer does not support it.
+    pub fn request_key_frame(&self, display: i32) -> bool {
+        if !self.lc.read().unwrap().support_key_frame_request {
+            return false;
+        }
+        log::debug!("request a key frame, display {}", display);
+        let msg = crate::key_frame::Request { display }.to_message();
+        self.send(Data::Message(msg));
+        true
+    }
+
     pub fn record_screen(&self, start: bool) {
         self.send(Data::RecordScreen(start));
     }
 
     pub fn is_screenshot_supported(&self) -> bool {
-        crate::common::is_support_screenshot_num(self.lc.read().unwrap().version)
+        let lc = self.lc.read().unwrap();
+        crate::common::is_support_screenshot_num(lc.version) && !lc.do_not_record
     }
 
     pub fn take_screenshot(&self, display: i32, sid: String) {
+        if self.lc.read().unwrap().do_not_record {
+            self.handle_screenshot_resp(sid, "screenshot-not-allowed-tip".to_owned());
+            return;
+        }
         self.send(Data::TakeScreenshot((display, sid)));
     }
 
+    pub fn talk_back(&self, on: bool) {
+        self.send(Data::TalkBack(on));
+    }
+
+    pub fn is_talking_back(&self) -> bool {
+        self.lc.read().unwrap().talk_back
+    }
+
     pub fn is_recording(&self) -> bool {
         self.lc.read().unwrap().record_state
     }
@@ -469,6 +494,12 @@ impl<T: InvokeUiSession> Session<T> {
         self.lc.write().unwrap().save_trackpad_speed(trackpad_speed);
     }
 
+    /// Ask the peer for fewer or more frames, without saving it, for a tile of a wall view.
+    pub fn throttle_fps(&self, fps: i32) {
+        let msg = self.lc.write().unwrap().set_custom_fps(fps, false);
+        self.send(Data::Message(msg));
+    }
+
     pub fn set_custom_fps(&self, custom_fps: i32) {
         let msg = self.lc.write().unwrap().set_custom_fps(custom_fps, true);
         self.send(Data::Message(msg));
@@ -599,6 +630,19 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::AddPortForward(pf));
     }
 
+    /// Forward the local port to the peer over the channels of this connection, instead of a
+    /// port forward connection. Not saved, the forward ends with the session.
+    pub fn add_mux_forward(&self, port: i32, remote_host: String, remote_port: i32) {
+        if port <= 0 || remote_port <= 0 {
+            return;
+        }
+        self.send(Data::AddMuxForward((port, remote_host, remote_port)));
+    }
+
+    pub fn remove_mux_forward(&self, port: i32) {
+        self.send(Data::RemoveMuxForward(port));
+    }
+
     pub fn get_option(&self, k: String) -> String {
         if k.eq("remote_dir") {
             return self.lc.read().unwrap().get_remote_dir();
@@ -656,9 +700,9 @@ impl<T: InvokeUiSession> Session<T> {
 
     #[cfg(not(feature = "flutter"))]
     pub fn get_chatbox(&self) -> String {
-        #[cfg(feature = "inline")]
+        #[cfg(all(feature = "inline", feature = "ui"))]
         return crate::ui::inline::get_chatbox();
-        #[cfg(not(feature = "inline"))]
+        #[cfg(not(all(feature = "inline", feature = "ui")))]
         return "".to_owned();
     }
 
@@ -747,6 +791,11 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(msg_out));
     }
 
+    // Cancels the paste of the files of the peer here, or the one of these files on the peer.
+    pub fn cancel_file_paste(&self) {
+        self.send(Data::CancelFilePaste);
+    }
+
     pub fn send_chat(&self, text: String) {
         let mut misc = Misc::new();
         misc.set_chat_message(ChatMessage {
@@ -809,6 +858,16 @@ impl<T: InvokeUiSession> Session<T> {
     }
 
     pub fn capture_displays(&self, add: Vec<i32>, sub: Vec<i32>, set: Vec<i32>) {
+        let ids = self.lc.read().unwrap().display_ids.clone();
+        if let (Some(add), Some(sub), Some(set)) = (
+            crate::display_id::ids_of(&ids, &add),
+            crate::display_id::ids_of(&ids, &sub),
+            crate::display_id::ids_of(&ids, &set),
+        ) {
+            let req = crate::display_id::Request::Capture { add, sub, set };
+            self.send(Data::Message(req.to_message()));
+            return;
+        }
         let mut misc = Misc::new();
         misc.set_capture_displays(CaptureDisplays {
             add,
@@ -821,22 +880,306 @@ impl<T: InvokeUiSession> Session<T> {
         self.send(Data::Message(msg_out));
     }
 
+    pub fn get_camera_controls(&self, display: usize) {
+        let msg = crate::camera_controls::make_request(display, None);
+        self.send(Data::Message(msg));
+    }
+
+    /// `control` is the snake case name, e.g. "white_balance", `value` the manual one to set.
+    pub fn set_camera_control(
+        &self,
+        display: usize,
+        control: &str,
+        auto: bool,
+        value: Option<f64>,
+    ) {
+        let Ok(control) = serde_json::from_value(serde_json::Value::String(control.to_owned()))
+        else {
+            log::error!("unknown camera control: {control}");
+            return;
+        };
+        let set = crate::camera_controls::SetControl {
+            control,
+            auto,
+            value: value.map(|v| v as f32),
+        };
+        let msg = crate::camera_controls::make_request(display, Some(set));
+        self.send(Data::Message(msg));
+    }
+
+    /// Forward a pen event, see `stylus` for the json.
+    pub fn send_stylus(&self, evt: &str) {
+        match serde_json::from_str::<crate::stylus::StylusEvent>(evt) {
+            Ok(evt) => self.send(Data::Message(crate::stylus::make_message(&evt))),
+            Err(e) => log::error!("invalid stylus event: {e}"),
+        }
+    }
+
+    pub fn get_audio_devices(&self) {
+        let msg = crate::audio_devices::make_request(None);
+        self.send(Data::Message(msg));
+    }
+
+    /// Capture `device` of the peer, empty for the device of its settings. The pick is saved for
+    /// the next connections.
+    pub fn select_audio_device(&self, device: String) {
+        self.lc
+            .write()
+            .unwrap()
+            .set_option(crate::audio_devices::PEER_OPTION.to_owned(), device.clone());
+        let msg = crate::audio_devices::make_request(Some(device));
+        self.send(Data::Message(msg));
+    }
+
+    pub fn get_keyboard_layouts(&self) {
+        let msg = crate::keyboard_layout::make_request(None);
+        self.send(Data::Message(msg));
+    }
+
+    /// Switch the keyboard layout of the peer for the session, `keyboard_layout::SYNC` for the
+    /// layout of this side. The pick is saved for the next connections.
+    pub fn switch_keyboard_layout(&self, layout: String) {
+        self.lc.write().unwrap().set_option(
+            crate::keyboard_layout::PEER_OPTION.to_owned(),
+            layout.clone(),
+        );
+        let translate_mode = self.get_keyboard_mode() == "translate";
+        if let Some(layout) = crate::keyboard_layout::resolve(&layout, translate_mode) {
+            let msg = crate::keyboard_layout::make_request(Some(layout));
+            self.send(Data::Message(msg));
+        }
+    }
+
+    /// Ask the peer for the modes of its virtual display, or set them with a list as
+    /// "3440x1440@100,1920x1080".
+    pub fn monitor_modes(&self, modes: Option<String>) {
+        self.send(Data::Message(crate::monitor_modes::make_request(modes)));
+    }
+
+    /// Send a json `config_edit::Request` to the peer, to list, read or write its config files.
+    pub fn config_edit(&self, request: String) {
+        match serde_json::from_str::<crate::config_edit::Request>(&request) {
+            Ok(req) => self.send(Data::Message(crate::config_edit::make_request(&req))),
+            Err(e) => log::error!("Invalid config edit request: {}", e),
+        }
+    }
+
+    /// Ask the peer to wake its displays, the display sends frames again once it is awake.
+    pub fn wake_display(&self, display: usize) {
+        let msg = crate::display_power::make_wake_request(display);
+        self.send(Data::Message(msg));
+    }
+
+    /// Draw on the screen of the peer, `annotation` is a json `whiteboard::Annotation`.
+    pub fn send_annotation(&self, annotation: &str) {
+        match serde_json::from_str::<crate::whiteboard::Annotation>(annotation) {
+            Ok(annotation) => self.send(Data::Message(annotation.to_message())),
+            Err(e) => log::warn!("Invalid annotation: {}", e),
+        }
+    }
+
+    /// Save the speed of the mouse for the peer and send it, `curve` is a json `mouse_curve::Curve`.
+    pub fn set_mouse_curve(&self, curve: &str) {
+        match serde_json::from_str::<crate::mouse_curve::Curve>(curve) {
+            Ok(c) => {
+                self.lc
+                    .write()
+                    .unwrap()
+                    .set_option(crate::mouse_curve::PEER_OPTION.to_owned(), curve.to_owned());
+                self.send(Data::Message(c.to_message()));
+            }
+            Err(e) => log::warn!("Invalid mouse curve: {}", e),
+        }
+    }
+
+    /// Save the scrolling for the peer and send it, `transform` is a json
+    /// `scroll_transform::Transform`.
+    pub fn set_scroll_transform(&self, transform: &str) {
+        match serde_json::from_str::<crate::scroll_transform::Transform>(transform) {
+            Ok(t) => {
+                self.lc.write().unwrap().set_option(
+                    crate::scroll_transform::PEER_OPTION.to_owned(),
+                    transform.to_owned(),
+                );
+                self.send(Data::Message(t.to_message()));
+            }
+            Err(e) => log::warn!("Invalid scroll transform: {}", e),
+        }
+    }
+
+    /// Save what the peer keeps awake during the session and send it, `name` is the one of a
+    /// `wake_policy::Policy`, e.g. "system".
+    pub fn set_wake_policy(&self, name: &str) {
+        match crate::wake_policy::Policy::from_name(name) {
+            Some(policy) => {
+                self.lc
+                    .write()
+                    .unwrap()
+                    .set_option(crate::wake_policy::PEER_OPTION.to_owned(), name.to_owned());
+                let msg = crate::wake_policy::Request { policy }.to_message();
+                self.send(Data::Message(msg));
+            }
+            None => log::warn!("Invalid wake policy: {}", name),
+        }
+    }
+
+    /// Tell the peer whether the window of the session is seen, it lowers the frame rate for a
+    /// hidden one.
+    pub fn set_window_visible(&self, visible: bool) {
+        let msg = crate::window_visibility::Visibility { visible }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Json list of the `peer_features::Unavailable` features of the peer.
+    pub fn get_unavailable_features(&self) -> String {
+        let pi = self.lc.read().unwrap().peer_info.clone();
+        let v = pi
+            .map(|pi| crate::peer_features::all_unavailable(&pi))
+            .unwrap_or_default();
+        serde_json::to_string(&v).unwrap_or_default()
+    }
+
+    /// Tell the user why the feature is unavailable with the peer, false if it is available.
+    pub fn notify_unavailable_feature(&self, name: &str) -> bool {
+        let pi = self.lc.read().unwrap().peer_info.clone();
+        match pi.and_then(|pi| crate::peer_features::unavailable(name, &pi)) {
+            Some(u) => {
+                self.msgbox("custom-nocancel", &u.label, &u.tip(), "");
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Choose the displays the privacy mode of the peer blanks, all of them if empty, answered
+    /// with `privacy_displays`.
+    pub fn set_privacy_displays(&self, displays: Vec<usize>) {
+        let msg = crate::privacy_displays::Request { displays }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Ask the peer where to send the files dropped on the window, answered with `file_drop`.
+    pub fn request_file_drop(&self, id: i32) {
+        let msg = crate::file_drop::Request { id }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Whether the following removals of remote files skip the trash of the peer.
+    pub fn set_file_trash_permanent(&self, permanent: bool) {
+        let msg = crate::file_trash::Request::Mode { permanent }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Move back the files the removal job `id` moved to the trash of the peer, `items` is a json
+    /// list of `file_trash::Trashed`, answered with `file_trash`.
+    pub fn restore_trashed(&self, id: i32, items: &str) {
+        let Ok(items) = serde_json::from_str(items) else {
+            return;
+        };
+        let msg = crate::file_trash::Request::Restore { id, items }.to_message();
+        self.send(Data::Message(msg));
+    }
+
+    /// Save the speed of the file transfers for the peer and send it, `settings` is a json
+    /// `transfer_throttle::Settings`.
+    pub fn set_transfer_throttle(&self, settings: &str) {
+        match serde_json::from_str::<crate::transfer_throttle::Settings>(settings) {
+            Ok(s) => {
+                self.lc.write().unwrap().set_option(
+                    crate::transfer_throttle::PEER_OPTION.to_owned(),
+                    settings.to_owned(),
+                );
+                self.send(Data::Message(s.to_message()));
+            }
+            Err(e) => log::warn!("Invalid transfer throttle: {}", e),
+        }
+    }
+
+    /// The clipboard history of this computer, a json list of `clipboard::history::Entry`.
+    pub fn get_clipboard_history(&self) -> String {
+        serde_json::to_string(&crate::clipboard::history::list()).unwrap_or_default()
+    }
+
+    /// Send an item of the clipboard history to the peer, as if it was copied again.
+    pub fn send_clipboard_history(&self, id: u64) {
+        if !self.is_text_clipboard_required() {
+            return;
+        }
+        let Some(clipboards) = crate::clipboard::history::get(id) else {
+            log::warn!("No clipboard history item {}", id);
+            return;
+        };
+        let mut msg = Message::new();
+        msg.set_multi_clipboards(clipboards);
+        if self.get_toggle_option(crate::clipboard::rich_text::OPTION_PLAIN_TEXT_ONLY.to_owned()) {
+            msg = crate::clipboard::rich_text::plain_text_msg(&msg);
+        }
+        let pi = self.lc.read().unwrap().peer_info.clone();
+        if let (Some(message::Union::MultiClipboards(multi_clipboards)), Some(pi)) =
+            (&msg.union, pi)
+        {
+            if let Some(msg_out) = crate::clipboard::get_msg_if_not_support_multi_clip(
+                &pi.version,
+                &pi.platform,
+                multi_clipboards,
+            ) {
+                msg = msg_out;
+            }
+        }
+        self.send(Data::Message(msg));
+    }
+
+    /// Ask the user of the peer for a permission of this session, e.g. "file".
+    pub fn request_permission(&self, permission: &str) {
+        let content = crate::permission_request::Content::Request {
+            permission: permission.to_owned(),
+        };
+        self.send(Data::Message(content.to_message()));
+    }
+
+    /// Answer the challenge of the authentication provider of the peer, `answer` is a json
+    /// `auth_provider::Answer`.
+    pub fn send_auth_provider_answer(&self, answer: &str) {
+        match serde_json::from_str::<crate::auth_provider::Answer>(answer) {
+            Ok(a) => self.send(Data::Message(a.to_message())),
+            Err(e) => log::warn!("Invalid answer to the authentication provider: {}", e),
+        }
+    }
+
+    /// Press a media key or a function key of the peer, e.g. "volume_up".
+    pub fn send_special_key(&self, key: &str) {
+        match crate::special_keys::make_request(key) {
+            Some(msg) => self.send(Data::Message(msg)),
+            None => log::warn!("Unknown special key: {}", key),
+        }
+    }
+
     pub fn switch_display(&self, display: i32) {
         let (w, h) = match self.lc.read().unwrap().get_custom_resolution(display) {
             Some((w, h)) => (w, h),
             None => (0, 0),
         };
 
-        let mut misc = Misc::new();
-        misc.set_switch_display(SwitchDisplay {
-            display,
-            width: w,
-            height: h,
-            ..Default::default()
-        });
-        let mut msg_out = Message::new();
-        msg_out.set_misc(misc);
-        self.send(Data::Message(msg_out));
+        let ids = self.lc.read().unwrap().display_ids.clone();
+        if let Some(mut id) = crate::display_id::ids_of(&ids, &[display]) {
+            let req = crate::display_id::Request::Switch {
+                id: id.remove(0),
+                width: w,
+                height: h,
+            };
+            self.send(Data::Message(req.to_message()));
+        } else {
+            let mut misc = Misc::new();
+            misc.set_switch_display(SwitchDisplay {
+                display,
+                width: w,
+                height: h,
+                ..Default::default()
+            });
+            let mut msg_out = Message::new();
+            msg_out.set_misc(misc);
+            self.send(Data::Message(msg_out));
+        }
 
         if !use_texture_render() {
             self.capture_displays(vec![], vec![], vec![display]);
@@ -1118,6 +1461,25 @@ impl<T: InvokeUiSession> Session<T> {
         send_pointer_device_event(evt, alt, ctrl, shift, command, self);
     }
 
+    /// Record, stop, replay, delete or list the input macros on the host, see `input_macro`.
+    pub fn input_macro(&self, action: &str, name: String) {
+        use crate::input_macro::Request;
+        let req = match action {
+            "record" => Request::Record { name },
+            "stop" => Request::Stop,
+            "replay" => Request::Replay { name },
+            "delete" => Request::Delete { name },
+            _ => Request::List,
+        };
+        self.send(Data::Message(crate::input_macro::make_request(&req)));
+    }
+
+    /// The rotation delta of a touch gesture in thousandths of a degree clockwise, 0 ends it.
+    pub fn send_touch_rotate(&self, rotation: i32) {
+        let evt = crate::touch_gesture::RotateEvent { rotation };
+        self.send(Data::Message(crate::touch_gesture::make_message(&evt)));
+    }
+
     #[inline]
     fn is_scroll_reverse_mode(&self) -> bool {
         self.lc.read().unwrap().reverse_mouse_wheel.eq("Y")
@@ -1145,7 +1507,10 @@ impl<T: InvokeUiSession> Session<T> {
 
         // Compute event type once using MOUSE_TYPE_MASK for reuse
         let event_type = mask & MOUSE_TYPE_MASK;
-        let (x, y) = if event_type == MOUSE_TYPE_WHEEL || event_type == MOUSE_TYPE_TRACKPAD {
+        let (x, y) = if event_type == MOUSE_TYPE_WHEEL
+            || event_type == MOUSE_TYPE_TRACKPAD
+            || event_type == MOUSE_TYPE_WHEEL_PRECISE
+        {
             self.get_scroll_xy((x, y))
         } else {
             (x, y)
@@ -1621,6 +1986,26 @@ pub trait InvokeUiSession: Send + Sync + Clone + 'static + Sized + Default {
     fn is_multi_ui_session(&self) -> bool;
     fn update_record_status(&self, start: bool);
     fn update_empty_dirs(&self, _res: ReadEmptyDirsResponse) {}
+    fn update_camera_controls(&self, _res: crate::camera_controls::Response) {}
+    fn update_audio_devices(&self, _res: crate::audio_devices::Response) {}
+    fn update_keyboard_layouts(&self, _res: crate::keyboard_layout::Response) {}
+    fn update_config_edit(&self, _res: crate::config_edit::Response) {}
+    fn update_monitor_modes(&self, _res: crate::monitor_modes::Response) {}
+    fn file_drop(&self, _res: crate::file_drop::Response) {}
+    fn privacy_displays(&self, _res: crate::privacy_displays::Response) {}
+    fn file_trash(&self, _res: crate::file_trash::Response) {}
+    fn transfer_journal(&self, _notice: crate::transfer_journal::Notice) {}
+    fn update_display_power(&self, _notice: crate::display_power::Notice) {}
+    // The percent of a large clipboard of the peer received.
+    fn update_clipboard_progress(&self, _percent: u8) {}
+    fn update_file_paste_progress(
+        &self,
+        _transferred: u64,
+        _total: u64,
+        _canceled: bool,
+        _failed: bool,
+    ) {
+    }
     fn printer_request(&self, id: i32, path: String);
     fn handle_screenshot_resp(&self, sid: String, msg: String);
     fn handle_terminal_response(&self, response: TerminalResponse);
@@ -1673,6 +2058,12 @@ impl<T: InvokeUiSession> Interface for Session<T> {
     fn handle_peer_info(&self, mut pi: PeerInfo) {
         log::debug!("handle_peer_info :{:?}", pi);
         self.lc.write().unwrap().peer_info = Some(pi.clone());
+        self.lc.write().unwrap().display_sizes = pi
+            .displays
+            .iter()
+            .enumerate()
+            .map(|(i, d)| (i, (d.width as usize, d.height as usize)))
+            .collect();
         if pi.current_display as usize >= pi.displays.len() {
             pi.current_display = 0;
         }
@@ -1725,7 +2116,14 @@ impl<T: InvokeUiSession> Interface for Session<T> {
                 "",
             );
         }
-        self.on_connected(self.lc.read().unwrap().conn_type);
+        let conn_type = self.lc.read().unwrap().conn_type;
+        self.on_connected(conn_type);
+        crate::ui_interface::push_a11y_event(
+            crate::ui_interface::A11Y_EVENT_CONNECTED,
+            &self.get_id(),
+            format!("a11y-connected-{{{}}}-tip", self.get_id()),
+            serde_json::json!({ "conn_type": format!("{:?}", conn_type) }),
+        );
         if !pi.windows_sessions.sessions.is_empty() {
             let selected = self
                 .lc
@@ -1804,6 +2202,58 @@ impl<T: InvokeUiSession> Session<T> {
     pub fn ctrl_alt_del(&self) {
         self.send_key_event(&crate::keyboard::client::event_ctrl_alt_del());
     }
+
+    pub fn get_toolbar_actions(&self) -> String {
+        self.lc
+            .read()
+            .unwrap()
+            .get_option(toolbar_actions::PEER_OPTION)
+    }
+
+    /// Returns the error, empty if the actions are saved.
+    pub fn set_toolbar_actions(&self, actions: String) -> String {
+        if let Err(e) = toolbar_actions::parse(&actions) {
+            return e.to_string();
+        }
+        self.lc
+            .write()
+            .unwrap()
+            .set_option(toolbar_actions::PEER_OPTION.to_owned(), actions);
+        "".to_owned()
+    }
+
+    pub fn run_toolbar_action(&self, id: &str) {
+        let actions = toolbar_actions::parse(&self.get_toolbar_actions()).unwrap_or_default();
+        let Some(action) = actions.into_iter().find(|a| a.id == id) else {
+            log::warn!("toolbar action not found: {id}");
+            return;
+        };
+        match action.action {
+            toolbar_actions::Action::Chord { keys } => self.send_chord(&keys),
+            toolbar_actions::Action::Paste { text } => self.input_string(&text),
+            toolbar_actions::Action::Macro { steps } => {
+                // The delays must not block the caller.
+                let session = self.clone();
+                std::thread::spawn(move || {
+                    for step in steps {
+                        match step {
+                            toolbar_actions::Step::Chord { keys } => session.send_chord(&keys),
+                            toolbar_actions::Step::Text { text } => session.input_string(&text),
+                            toolbar_actions::Step::Delay { ms } => std::thread::sleep(
+                                Duration::from_millis(ms.min(toolbar_actions::MAX_DELAY_MS)),
+                            ),
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    fn send_chord(&self, keys: &str) {
+        if let Some(c) = toolbar_actions::parse_chord(keys) {
+            self.input_key(&c.key, false, true, c.alt, c.ctrl, c.shift, c.command);
+        }
+    }
 }
 
 #[tokio::main(flavor = "current_thread")]
@@ -0,0 +1,206 @@
+//! Updates from a self-hosted manifest instead of the public releases, for the air-gapped and the
+//! enterprise deployments.
+//!
+//! With `OPTION_UPDATE_MANIFEST_URL` set, e.g. by the `update` field of a custom server, the check
+//! of the software update reads the json `Manifest` there and offers the newest `Release` for this
+//! platform and architecture, if it is newer than this version. The manifest and the files are
+//! read over https only. A file is checked against its sha256 and its signature of the sha256
+//! digest, by the base64 ed25519 public key of `OPTION_UPDATE_MANIFEST_KEY`, before it is
+//! installed: the sha256 alone would only tell the file is the one of the manifest, which anyone
+//! able to change the manifest could also change, so nothing is installed without the key. The
+//! rollout is the one of the manifest: a release is offered once it is listed.
+//!
+//! ```json
+//! {
+//!   "releases": [{
+//!     "version": "1.4.3", "platform": "macos", "arch": "aarch64",
+//!     "url": "https://updates.example.com/rustdesk-1.4.3-aarch64.dmg",
+//!     "sha256": "<hex>", "signature": "<base64>"
+//!   }]
+//! }
+//! ```
+
+use hbb_common::{
+    bail, config::Config, get_version_number, log, sodiumoxide::crypto::sign, ResultType,
+};
+use serde_derive::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{path::Path, sync::Mutex};
+
+pub const OPTION_UPDATE_MANIFEST_URL: &str = "update-manifest-url";
+pub const OPTION_UPDATE_MANIFEST_KEY: &str = "update-manifest-key";
+
+const PLATFORM: &str = "macos";
+
+lazy_static::lazy_static! {
+    // The release offered by the last check.
+    static ref PENDING: Mutex<Option<Release>> = Default::default();
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub platform: String,
+    /// As `std::env::consts::ARCH`, e.g. "x86_64" or "aarch64", "universal" for all of them.
+    pub arch: String,
+    pub url: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default)]
+    pub releases: Vec<Release>,
+}
+
+impl Manifest {
+    /// The newest release for the platform and the architecture newer than `current`.
+    fn select(&self, platform: &str, arch: &str, current: &str) -> Option<Release> {
+        let current = get_version_number(current);
+        self.releases
+            .iter()
+            .filter(|r| r.platform.eq_ignore_ascii_case(platform))
+            .filter(|r| r.arch == arch || r.arch == "universal")
+            .filter(|r| get_version_number(&r.version) > current && is_https(&r.url))
+            .max_by_key(|r| get_version_number(&r.version))
+            .cloned()
+    }
+}
+
+fn is_https(url: &str) -> bool {
+    url.get(..8)
+        .map(|s| s.eq_ignore_ascii_case("https://"))
+        .unwrap_or_default()
+}
+
+pub fn url() -> String {
+    Config::get_option(OPTION_UPDATE_MANIFEST_URL)
+        .trim()
+        .to_owned()
+}
+
+pub fn is_configured() -> bool {
+    !url().is_empty()
+}
+
+/// The release the last check offered, none without a manifest or a newer version.
+pub fn pending() -> Option<Release> {
+    PENDING.lock().unwrap().clone()
+}
+
+/// Read the manifest, the url of the release to download, empty if there is none.
+pub async fn check() -> ResultType<String> {
+    let url = url();
+    if !is_https(&url) {
+        bail!("The update manifest is not read over https: {}", url);
+    }
+    let client = crate::hbbs_http::create_http_client_async_with_url(&url).await;
+    let response = client.get(&url).send().await?;
+    if !response.status().is_success() {
+        bail!("Failed to get the update manifest: {}", response.status());
+    }
+    let manifest: Manifest = serde_json::from_slice(&response.bytes().await?)?;
+    let release = manifest.select(PLATFORM, std::env::consts::ARCH, crate::VERSION);
+    match &release {
+        Some(r) => log::info!("Update {} in the manifest: {}", r.version, r.url),
+        None => log::debug!("No update in the manifest"),
+    }
+    let download_url = release.as_ref().map(|r| r.url.clone()).unwrap_or_default();
+    *PENDING.lock().unwrap() = release;
+    Ok(download_url)
+}
+
+fn verify_data(release: &Release, data: &[u8], key: &str) -> ResultType<()> {
+    let digest = Sha256::digest(data);
+    if !hex::encode(digest).eq_ignore_ascii_case(release.sha256.trim()) {
+        bail!("The sha256 of the update does not match the manifest");
+    }
+    if key.is_empty() {
+        bail!("No key of the update manifest to check the update with");
+    }
+    let Some(pk) = crate::decode64(key)
+        .ok()
+        .and_then(|k| sign::PublicKey::from_slice(&k))
+    else {
+        bail!("Invalid key of the update manifest");
+    };
+    let Some(signature) = crate::decode64(release.signature.trim())
+        .ok()
+        .and_then(|s| sign::Signature::try_from(s.as_slice()).ok())
+    else {
+        bail!("The update has no valid signature");
+    };
+    if !sign::verify_detached(&signature, &digest, &pk) {
+        bail!("The signature of the update does not match");
+    }
+    Ok(())
+}
+
+/// Check the downloaded file against the release of the manifest, nothing to check without a
+/// manifest.
+pub fn verify_file(path: &Path) -> ResultType<()> {
+    let Some(release) = pending() else {
+        if is_configured() {
+            bail!("No update of the manifest to check the file with");
+        }
+        return Ok(());
+    };
+    verify_data(
+        &release,
+        &std::fs::read(path)?,
+        Config::get_option(OPTION_UPDATE_MANIFEST_KEY).trim(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn update_manifest() {
+        let release = |version: &str, arch: &str| Release {
+            version: version.to_owned(),
+            platform: "macOS".to_owned(),
+            arch: arch.to_owned(),
+            url: format!("https://updates.example.com/{}-{}.dmg", version, arch),
+            ..Default::default()
+        };
+        let manifest = Manifest {
+            releases: vec![
+                release("1.4.1", "aarch64"),
+                release("1.4.3", "x86_64"),
+                release("1.4.2", "universal"),
+                release("1.3.9", "aarch64"),
+            ],
+        };
+        let selected = manifest.select("macos", "aarch64", "1.4.0").unwrap();
+        assert_eq!(selected.version, "1.4.2");
+        assert_eq!(
+            manifest.select("macos", "x86_64", "1.4.0").unwrap().version,
+            "1.4.3"
+        );
+        assert_eq!(manifest.select("macos", "aarch64", "1.4.2"), None);
+        let mut insecure = release("1.4.4", "aarch64");
+        insecure.url = insecure.url.replace("https", "http");
+        let manifest = Manifest {
+            releases: vec![insecure],
+        };
+        assert_eq!(manifest.select("macos", "aarch64", "1.4.0"), None);
+        assert!(is_https("HTTPS://updates.example.com/manifest.json"));
+        assert!(!is_https("http://updates.example.com/manifest.json"));
+
+        let data = b"update";
+        let (pk, sk) = sign::gen_keypair();
+        let digest = Sha256::digest(data);
+        let mut release = release("1.4.2", "aarch64");
+        release.sha256 = hex::encode(digest).to_uppercase();
+        assert!(verify_data(&release, data, "").is_err());
+        let key = crate::encode64(pk.0);
+        assert!(verify_data(&release, data, &key).is_err());
+        release.signature = crate::encode64(sign::sign_detached(&digest, &sk).to_bytes());
+        assert!(verify_data(&release, data, &key).is_ok());
+        assert!(verify_data(&release, b"other", &key).is_err());
+    }
+}
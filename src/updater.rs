@@ -111,7 +111,11 @@ fn check_update(manually: bool) -> ResultType<()> {
     if update_url.is_empty() {
         log::debug!("No update available.");
     } else {
-        let download_url = update_url.replace("tag", "download");
+        // The url of a manifest is the one of the file.
+        let download_url = match crate::update_manifest::pending() {
+            Some(release) => release.url,
+            None => update_url.replace("tag", "download"),
+        };
         let version = download_url.split('/').last().unwrap_or_default();
         log::debug!("New version available: {}", &version);
         let client = create_http_client_with_url(&download_url);
@@ -153,6 +157,10 @@ fn check_update(manually: bool) -> ResultType<()> {
             let mut file = std::fs::File::create(&file_path)?;
             file.write_all(&file_data)?;
         }
+        if let Err(e) = crate::update_manifest::verify_file(&file_path) {
+            std::fs::remove_file(&file_path).ok();
+            return Err(e);
+        }
     }
     Ok(())
 }
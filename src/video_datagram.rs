@@ -0,0 +1,441 @@
+//! Unreliable delivery of video frames over the UDP socket of a KCP connection.
+//!
+//! KCP delivers in order and retransmits every lost packet, so after a loss all the frames
+//! behind it wait for the retransmission. In the datagram mode, the host sends the video
+//! frames as sealed datagrams on the same socket, next to the KCP packets, and never
+//! retransmits them. The client gives up frames that are overtaken by a newer complete
+//! frame and asks for a key frame instead, the frames of the display are dropped until one
+//! arrives, as they can not be decoded. Control and input stay on the reliable stream.
+//!
+//! The mode is negotiated per connection. The host offers it in the peer info of KCP
+//! connections with a fresh key, which is only sent over the encrypted stream. A client
+//! that allows it answers with a sealed hello datagram, then the host switches the video.
+
+use hbb_common::{
+    bytes::Bytes,
+    log,
+    sodiumoxide::crypto::secretbox,
+    tokio::{self, sync::mpsc},
+};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+
+/// "Y" to receive the video as datagrams when the host offers it.
+pub const OPTION_VIDEO_DATAGRAM: &str = "allow-video-datagram";
+/// The key of the offer in the platform additions of the peer info.
+pub const PLATFORM_ADDITION_KEY: &str = "video_datagram";
+
+const MAGIC: &[u8; 4] = b"RDVD";
+const KIND_HELLO: u8 = 0;
+const KIND_FRAGMENT: u8 = 1;
+// The fragment is of a key frame.
+const FLAG_KEY: u8 = 1;
+// The nonce of each direction has its own prefix, the key is shared.
+const DIR_HOST: u8 = 0;
+const DIR_CLIENT: u8 = 1;
+// kind, flags, display, seq, index, count
+const HEADER_LEN: usize = 1 + 1 + 2 + 4 + 2 + 2;
+// Fragment payload, the datagram stays below the usual path mtu with the udp, magic,
+// nonce and mac overhead.
+const MAX_PAYLOAD: usize = 1100;
+// Larger frames are sent over the reliable stream.
+const MAX_FRAGMENTS: usize = 1024;
+const MAX_PENDING_FRAMES: usize = 16;
+// The datagrams received and not handled yet, the newer ones are dropped beyond.
+const MAX_RECEIVED_DATAGRAMS: usize = 4096;
+const HELLO_COUNT: usize = 3;
+const KEY_FRAME_REQUEST_INTERVAL: Duration = Duration::from_millis(300);
+
+/// Raw datagrams to and from the UDP socket, shared with the KCP packets.
+pub struct DatagramChannel {
+    pub tx: mpsc::UnboundedSender<Bytes>,
+    pub rx: mpsc::Receiver<Bytes>,
+}
+
+/// The sender of the datagrams received, and the receiver for the `DatagramChannel`.
+pub fn received_channel() -> (mpsc::Sender<Bytes>, mpsc::Receiver<Bytes>) {
+    mpsc::channel(MAX_RECEIVED_DATAGRAMS)
+}
+
+#[inline]
+pub fn is_datagram(packet: &[u8]) -> bool {
+    packet.starts_with(MAGIC)
+}
+
+struct Sealer {
+    key: secretbox::Key,
+    dir: u8,
+    counter: u64,
+}
+
+impl Sealer {
+    fn nonce(dir: u8, counter: u64) -> secretbox::Nonce {
+        let mut nonce = [0u8; secretbox::NONCEBYTES];
+        nonce[0] = dir;
+        nonce[secretbox::NONCEBYTES - 8..].copy_from_slice(&counter.to_le_bytes());
+        secretbox::Nonce(nonce)
+    }
+
+    fn seal(&mut self, plain: &[u8]) -> Bytes {
+        self.counter += 1;
+        let sealed = secretbox::seal(plain, &Self::nonce(self.dir, self.counter), &self.key);
+        let mut packet = Vec::with_capacity(MAGIC.len() + 8 + sealed.len());
+        packet.extend_from_slice(MAGIC);
+        packet.extend_from_slice(&self.counter.to_le_bytes());
+        packet.extend_from_slice(&sealed);
+        packet.into()
+    }
+
+    fn open(key: &secretbox::Key, dir: u8, packet: &[u8]) -> Option<Vec<u8>> {
+        if !is_datagram(packet) || packet.len() < MAGIC.len() + 8 {
+            return None;
+        }
+        let (counter, sealed) = packet[MAGIC.len()..].split_at(8);
+        let counter = u64::from_le_bytes(counter.try_into().ok()?);
+        secretbox::open(sealed, &Self::nonce(dir, counter), key).ok()
+    }
+}
+
+struct Fragment<'a> {
+    kind: u8,
+    key: bool,
+    display: u16,
+    seq: u32,
+    index: u16,
+    count: u16,
+    payload: &'a [u8],
+}
+
+impl<'a> Fragment<'a> {
+    fn write(&self) -> Vec<u8> {
+        let mut v = Vec::with_capacity(HEADER_LEN + self.payload.len());
+        v.push(self.kind);
+        v.push(if self.key { FLAG_KEY } else { 0 });
+        v.extend_from_slice(&self.display.to_le_bytes());
+        v.extend_from_slice(&self.seq.to_le_bytes());
+        v.extend_from_slice(&self.index.to_le_bytes());
+        v.extend_from_slice(&self.count.to_le_bytes());
+        v.extend_from_slice(self.payload);
+        v
+    }
+
+    fn parse(v: &'a [u8]) -> Option<Self> {
+        if v.len() < HEADER_LEN {
+            return None;
+        }
+        let u16_at = |i: usize| u16::from_le_bytes([v[i], v[i + 1]]);
+        Some(Self {
+            kind: v[0],
+            key: v[1] & FLAG_KEY != 0,
+            display: u16_at(2),
+            seq: u32::from_le_bytes([v[4], v[5], v[6], v[7]]),
+            index: u16_at(8),
+            count: u16_at(10),
+            payload: &v[HEADER_LEN..],
+        })
+    }
+}
+
+/// The host end, offered with the peer info and active after the hello of the client.
+pub struct HostDatagram {
+    tx: mpsc::UnboundedSender<Bytes>,
+    sealer: Sealer,
+    active: Arc<AtomicBool>,
+    seqs: HashMap<u16, u32>,
+}
+
+impl HostDatagram {
+    pub fn new(channel: DatagramChannel) -> Self {
+        let key = secretbox::gen_key();
+        let active = Arc::new(AtomicBool::new(false));
+        let (key_cloned, active_cloned) = (key.clone(), active.clone());
+        let mut rx = channel.rx;
+        tokio::spawn(async move {
+            while let Some(packet) = rx.recv().await {
+                let hello = Sealer::open(&key_cloned, DIR_CLIENT, &packet)
+                    .map(|v| v.first() == Some(&KIND_HELLO))
+                    .unwrap_or(false);
+                if hello && !active_cloned.swap(true, Ordering::Relaxed) {
+                    log::info!("video datagram mode accepted by the peer");
+                }
+            }
+        });
+        Self {
+            tx: channel.tx,
+            sealer: Sealer {
+                key,
+                dir: DIR_HOST,
+                counter: 0,
+            },
+            active,
+            seqs: HashMap::new(),
+        }
+    }
+
+    /// The key offered to the client.
+    pub fn offer(&self) -> String {
+        crate::encode64(&self.sealer.key.0)
+    }
+
+    #[inline]
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Send an encoded video frame message, returns false if it must go over the stream.
+    pub fn send_frame(&mut self, display: usize, key: bool, msg: &[u8]) -> bool {
+        let count = (msg.len() + MAX_PAYLOAD - 1) / MAX_PAYLOAD;
+        if !self.is_active() || count == 0 || count > MAX_FRAGMENTS {
+            return false;
+        }
+        let display = display as u16;
+        let seq = self.seqs.entry(display).or_default();
+        *seq = seq.wrapping_add(1);
+        let seq = *seq;
+        for (index, payload) in msg.chunks(MAX_PAYLOAD).enumerate() {
+            let fragment = Fragment {
+                kind: KIND_FRAGMENT,
+                key,
+                display,
+                seq,
+                index: index as _,
+                count: count as _,
+                payload,
+            };
+            let packet = self.sealer.seal(&fragment.write());
+            if self.tx.send(packet).is_err() {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+struct PendingFrame {
+    key: bool,
+    fragments: Vec<Option<Vec<u8>>>,
+    received: usize,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ClientEvent {
+    /// A complete video frame message.
+    Frame(Vec<u8>),
+    /// Frames of the display were lost, a key frame is needed.
+    Lost(usize),
+}
+
+/// Reassembles the fragments, frames are delivered in order and never late, from a key frame on.
+#[derive(Default)]
+struct Reassembler {
+    pending: BTreeMap<(u16, u32), PendingFrame>,
+    last_seqs: HashMap<u16, u32>,
+    // The displays with a key frame delivered and no frame lost since.
+    decodable: HashSet<u16>,
+    last_key_frame_requests: HashMap<u16, Instant>,
+}
+
+impl Reassembler {
+    fn push(&mut self, f: Fragment) -> Vec<ClientEvent> {
+        let mut events = vec![];
+        let last = self.last_seqs.get(&f.display).copied().unwrap_or_default();
+        let count = f.count as usize;
+        if f.kind != KIND_FRAGMENT
+            || f.seq.wrapping_sub(last) as i32 <= 0
+            || count == 0
+            || count > MAX_FRAGMENTS
+            || f.index as usize >= count
+        {
+            return events;
+        }
+        let frame = self
+            .pending
+            .entry((f.display, f.seq))
+            .or_insert_with(|| PendingFrame {
+                key: f.key,
+                fragments: vec![None; count],
+                received: 0,
+            });
+        let Some(slot) = frame.fragments.get_mut(f.index as usize) else {
+            return events;
+        };
+        if slot.is_none() {
+            *slot = Some(f.payload.to_vec());
+            frame.received += 1;
+        }
+        let complete = frame.received == frame.fragments.len();
+        if complete {
+            let Some(frame) = self.pending.remove(&(f.display, f.seq)) else {
+                return events;
+            };
+            // The older frames of the display are overtaken.
+            self.pending
+                .retain(|(display, seq), _| *display != f.display || *seq > f.seq);
+            if f.seq != last.wrapping_add(1) || !self.last_seqs.contains_key(&f.display) {
+                self.decodable.remove(&f.display);
+            }
+            self.last_seqs.insert(f.display, f.seq);
+            if frame.key {
+                self.decodable.insert(f.display);
+            }
+            if self.decodable.contains(&f.display) {
+                events.push(ClientEvent::Frame(
+                    frame.fragments.into_iter().flatten().flatten().collect(),
+                ));
+            } else {
+                self.request_key_frame(f.display, &mut events);
+            }
+        } else if self.pending.len() > MAX_PENDING_FRAMES {
+            if let Some(((display, _), _)) = self.pending.pop_first() {
+                self.decodable.remove(&display);
+                self.request_key_frame(display, &mut events);
+            }
+        }
+        events
+    }
+
+    fn request_key_frame(&mut self, display: u16, events: &mut Vec<ClientEvent>) {
+        let now = Instant::now();
+        if let Some(last) = self.last_key_frame_requests.get(&display) {
+            if now.duration_since(*last) < KEY_FRAME_REQUEST_INTERVAL {
+                return;
+            }
+        }
+        self.last_key_frame_requests.insert(display, now);
+        events.push(ClientEvent::Lost(display as _));
+    }
+}
+
+/// The client end, created from the offer of the host.
+pub struct ClientDatagram {
+    rx: mpsc::UnboundedReceiver<Bytes>,
+    key: secretbox::Key,
+    reassembler: Reassembler,
+    events: Vec<ClientEvent>,
+}
+
+impl ClientDatagram {
+    /// Accept the offer, `None` if it is invalid.
+    pub fn accept(channel: DatagramChannel, offer: &str) -> Option<Self> {
+        let key = secretbox::Key::from_slice(&crate::decode64(offer).ok()?)?;
+        let mut sealer = Sealer {
+            key: key.clone(),
+            dir: DIR_CLIENT,
+            counter: 0,
+        };
+        // Datagrams can be lost, the host only needs one.
+        for _ in 0..HELLO_COUNT {
+            let hello = Fragment {
+                kind: KIND_HELLO,
+                key: false,
+                display: 0,
+                seq: 0,
+                index: 0,
+                count: 0,
+                payload: &[],
+            };
+            channel.tx.send(sealer.seal(&hello.write())).ok()?;
+        }
+        Some(Self {
+            rx: channel.rx,
+            key,
+            reassembler: Default::default(),
+            events: vec![],
+        })
+    }
+
+    /// The next event, pending forever once the socket is closed.
+    pub async fn recv(&mut self) -> ClientEvent {
+        loop {
+            if !self.events.is_empty() {
+                return self.events.remove(0);
+            }
+            let Some(packet) = self.rx.recv().await else {
+                return std::future::pending().await;
+            };
+            let Some(plain) = Sealer::open(&self.key, DIR_HOST, &packet) else {
+                continue;
+            };
+            if let Some(f) = Fragment::parse(&plain) {
+                self.events = self.reassembler.push(f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fragment(display: u16, seq: u32, index: u16, count: u16, payload: &[u8]) -> Fragment {
+        Fragment {
+            kind: KIND_FRAGMENT,
+            key: false,
+            display,
+            seq,
+            index,
+            count,
+            payload,
+        }
+    }
+
+    #[test]
+    fn video_datagram() {
+        let key = secretbox::gen_key();
+        let mut sealer = Sealer {
+            key: key.clone(),
+            dir: DIR_HOST,
+            counter: 0,
+        };
+        let packet = sealer.seal(&fragment(1, 2, 3, 4, b"abc").write());
+        assert!(is_datagram(&packet));
+        // The direction is part of the nonce.
+        assert!(Sealer::open(&key, DIR_CLIENT, &packet).is_none());
+        let plain = Sealer::open(&key, DIR_HOST, &packet).unwrap();
+        let f = Fragment::parse(&plain).unwrap();
+        assert_eq!((f.display, f.seq, f.index, f.count), (1, 2, 3, 4));
+        assert_eq!(f.payload, b"abc");
+
+        let key = |f: Fragment<'static>| Fragment { key: true, ..f };
+        let mut r = Reassembler::default();
+        // The frames are dropped until a key frame arrives.
+        assert_eq!(
+            r.push(fragment(0, 1, 0, 1, b"a")),
+            vec![ClientEvent::Lost(0)]
+        );
+        assert!(r.push(key(fragment(0, 2, 1, 2, b"cd"))).is_empty());
+        assert_eq!(
+            r.push(key(fragment(0, 2, 0, 2, b"ab"))),
+            vec![ClientEvent::Frame(b"abcd".to_vec())]
+        );
+        // Frame 3 is incomplete when frame 4 is complete.
+        assert!(r.push(fragment(0, 3, 0, 2, b"x")).is_empty());
+        r.last_key_frame_requests.clear();
+        assert_eq!(
+            r.push(fragment(0, 4, 0, 1, b"y")),
+            vec![ClientEvent::Lost(0)]
+        );
+        // Late fragments are ignored.
+        assert!(r.push(fragment(0, 3, 1, 2, b"z")).is_empty());
+        assert!(r.pending.is_empty());
+        assert_eq!(
+            r.push(key(fragment(0, 5, 0, 1, b"k"))),
+            vec![ClientEvent::Frame(b"k".to_vec())]
+        );
+        assert_eq!(
+            r.push(fragment(0, 6, 0, 1, b"p")),
+            vec![ClientEvent::Frame(b"p".to_vec())]
+        );
+        // Other displays are independent.
+        assert_eq!(
+            r.push(key(fragment(1, 1, 0, 1, b"w"))),
+            vec![ClientEvent::Frame(b"w".to_vec())]
+        );
+    }
+}
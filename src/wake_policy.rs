@@ -0,0 +1,153 @@
+//! What a connection keeps awake on the host: the system, the display, or nothing.
+//!
+//! Without a policy, the host keeps the system awake during the incoming sessions and the display
+//! on during the remote control ones, which also stops its screensaver. The client saves a `Policy`
+//! per peer in the `PeerConfig` options under `PEER_OPTION` and sends it to a host with
+//! "support_wake_policy" in its platform additions. The host keeps awake what any of its
+//! connections asks for, and nothing if "keep-awake-during-incoming-sessions" is off.
+
+use crate::plugin_request;
+use hbb_common::{log, message_proto::Message};
+use serde_derive::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+
+pub const ID: &str = "wake-policy";
+pub const PEER_OPTION: &str = "wake-policy";
+pub const PLATFORM_ADDITION_KEY: &str = "support_wake_policy";
+
+lazy_static::lazy_static! {
+    static ref POLICIES: Mutex<HashMap<i32, Policy>> = Default::default();
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Policy {
+    /// As the host decides.
+    #[default]
+    Host,
+    /// The system awake, the display may sleep and the screensaver run, e.g. to monitor.
+    System,
+    /// The system awake and the display on, no screensaver.
+    Display,
+    /// Nothing, the host may sleep.
+    None,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Request {
+    pub policy: Policy,
+}
+
+impl Request {
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// What the connections keep awake.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Demand {
+    pub system: bool,
+    pub display: bool,
+}
+
+impl Policy {
+    /// The policy of its name, as saved in the peer option, e.g. "system".
+    pub fn from_name(name: &str) -> Option<Self> {
+        serde_json::from_value(serde_json::Value::String(name.to_owned())).ok()
+    }
+
+    fn demand(self, remote: bool) -> Demand {
+        match self {
+            Policy::Host => Demand {
+                system: true,
+                display: remote,
+            },
+            Policy::System => Demand {
+                system: true,
+                display: false,
+            },
+            Policy::Display => Demand {
+                system: true,
+                display: true,
+            },
+            Policy::None => Demand::default(),
+        }
+    }
+}
+
+/// Set the policy of a connection from its request, true if it changed.
+pub fn handle_request(conn: i32, content: &[u8]) -> bool {
+    let policy = match serde_json::from_slice::<Request>(content) {
+        Ok(req) => req.policy,
+        Err(e) => {
+            log::warn!("Invalid wake policy: {}", e);
+            return false;
+        }
+    };
+    log::info!("Wake policy of connection {}: {:?}", conn, policy);
+    let mut policies = POLICIES.lock().unwrap();
+    let old = if policy == Policy::Host {
+        policies.remove(&conn)
+    } else {
+        policies.insert(conn, policy)
+    };
+    old.unwrap_or_default() != policy
+}
+
+pub fn remove(conn: i32) {
+    POLICIES.lock().unwrap().remove(&conn);
+}
+
+/// What the connections keep awake together, `conns` are their ids and whether they are remote
+/// control ones.
+pub fn demand(conns: impl Iterator<Item = (i32, bool)>) -> Demand {
+    let policies = POLICIES.lock().unwrap();
+    conns.fold(Demand::default(), |acc, (conn, remote)| {
+        let d = policies
+            .get(&conn)
+            .copied()
+            .unwrap_or_default()
+            .demand(remote);
+        Demand {
+            system: acc.system || d.system,
+            display: acc.display || d.display,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wake_policy() {
+        // Ids no other test uses, the policies are global.
+        let (a, b) = (-101, -102);
+        assert_eq!(
+            demand([(a, true), (b, false)].into_iter()),
+            Demand {
+                system: true,
+                display: true
+            }
+        );
+        assert!(handle_request(a, br#"{"policy":"system"}"#));
+        assert!(!handle_request(a, br#"{"policy":"system"}"#));
+        assert_eq!(
+            demand([(a, true), (b, false)].into_iter()),
+            Demand {
+                system: true,
+                display: false
+            }
+        );
+        assert!(handle_request(b, br#"{"policy":"none"}"#));
+        assert!(demand([(a, true), (b, false)].into_iter()).system);
+        remove(a);
+        assert_eq!(demand([(b, false)].into_iter()), Demand::default());
+        assert!(!handle_request(b, b"none"));
+        assert_eq!(Policy::from_name("display"), Some(Policy::Display));
+        assert_eq!(Policy::from_name(""), None);
+        assert!(handle_request(b, br#"{"policy":"host"}"#));
+        assert_eq!(POLICIES.lock().unwrap().get(&b), None);
+    }
+}
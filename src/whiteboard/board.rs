@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+// The colors given to the viewers in turn, for the shapes sent without a color.
+const PEER_COLORS: [u32; 8] = [
+    0xffe53935, 0xff1e88e5, 0xff43a047, 0xfffb8c00, 0xff8e24aa, 0xff00acc1, 0xfffdd835, 0xff6d4c41,
+];
+
+/// The shapes on the screen, owned by the key of the connection which drew them, so that a viewer
+/// undoes and clears its own shapes only.
+pub(super) struct Board<T> {
+    shapes: HashMap<String, Vec<(u32, T)>>,
+    colors: HashMap<String, u32>,
+    max_shapes: usize,
+}
+
+impl<T> Board<T> {
+    pub fn new(max_shapes: usize) -> Self {
+        Self {
+            shapes: HashMap::new(),
+            colors: HashMap::new(),
+            max_shapes,
+        }
+    }
+
+    /// The color of the owner, the same for all its shapes.
+    pub fn color_of(&mut self, owner: &str) -> u32 {
+        if let Some(color) = self.colors.get(owner) {
+            return *color;
+        }
+        let color = PEER_COLORS[self.colors.len() % PEER_COLORS.len()];
+        self.colors.insert(owner.to_owned(), color);
+        color
+    }
+
+    /// Add a shape, or replace the one of the owner with the same id.
+    pub fn put(&mut self, owner: String, id: u32, shape: T) {
+        let shapes = self.shapes.entry(owner).or_default();
+        shapes.retain(|(i, _)| *i != id);
+        if shapes.len() >= self.max_shapes {
+            shapes.remove(0);
+        }
+        shapes.push((id, shape));
+    }
+
+    pub fn remove(&mut self, owner: &str, id: u32) {
+        if let Some(shapes) = self.shapes.get_mut(owner) {
+            shapes.retain(|(i, _)| *i != id);
+        }
+    }
+
+    /// Remove the last shape of the owner.
+    pub fn undo(&mut self, owner: &str) {
+        if let Some(shapes) = self.shapes.get_mut(owner) {
+            shapes.pop();
+        }
+    }
+
+    pub fn clear(&mut self, owner: &str) {
+        self.shapes.remove(owner);
+    }
+
+    pub fn clear_all(&mut self) {
+        self.shapes.clear();
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.shapes.values().flatten().map(|(_, shape)| shape)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whiteboard_board() {
+        let mut board = Board::new(2);
+        board.put("a".to_owned(), 1, "a1");
+        board.put("a".to_owned(), 2, "a2");
+        board.put("a".to_owned(), 2, "a2'");
+        board.put("b".to_owned(), 1, "b1");
+        let sorted = |board: &Board<&'static str>| {
+            let mut v: Vec<_> = board.iter().copied().collect();
+            v.sort();
+            v
+        };
+        assert_eq!(sorted(&board), vec!["a1", "a2'", "b1"]);
+        // The oldest is dropped.
+        board.put("a".to_owned(), 3, "a3");
+        assert_eq!(sorted(&board), vec!["a2'", "a3", "b1"]);
+        board.undo("a");
+        assert_eq!(sorted(&board), vec!["a2'", "b1"]);
+        board.clear("b");
+        assert_eq!(sorted(&board), vec!["a2'"]);
+        board.clear_all();
+        assert_eq!(sorted(&board), Vec::<&str>::new());
+
+        let a = board.color_of("a");
+        assert_ne!(a, board.color_of("b"));
+        assert_eq!(a, board.color_of("a"));
+    }
+}
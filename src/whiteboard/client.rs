@@ -17,6 +17,9 @@ use hbb_common::{
 use lazy_static::lazy_static;
 use std::{collections::HashMap, sync::RwLock, time::Instant};
 
+// The snapshot is waited for 3 seconds.
+const SNAPSHOT_WAIT_TRIES: usize = 30;
+
 lazy_static! {
     static ref TX_WHITEBOARD: RwLock<Option<UnboundedSender<(String, CustomEvent)>>> =
         RwLock::new(None);
@@ -39,7 +42,15 @@ pub fn get_key_cursor(conn_id: i32) -> String {
     format!("{}-cursor", conn_id)
 }
 
+#[inline]
+pub fn get_key_annotation(conn_id: i32) -> String {
+    format!("{}-annotation", conn_id)
+}
+
 pub fn register_whiteboard(k: String) {
+    if !cfg!(feature = "ui") {
+        return;
+    }
     std::thread::spawn(|| {
         allow_err!(start_whiteboard_());
     });
@@ -120,6 +131,18 @@ pub fn update_whiteboard(k: String, e: CustomEvent) {
     }
 }
 
+/// Save the shapes of all the viewers to a PNG at `path`, and read it once it is written.
+pub async fn snapshot(k: String, path: String) -> ResultType<Vec<u8>> {
+    update_whiteboard(k, CustomEvent::Snapshot(path.clone()));
+    for _ in 0..SNAPSHOT_WAIT_TRIES {
+        sleep(0.1).await;
+        if let Ok(data) = std::fs::read(&path) {
+            return Ok(data);
+        }
+    }
+    bail!("Failed to save the whiteboard snapshot");
+}
+
 #[inline]
 fn tx_send_event(conn: &mut Conn, k: String, event: CustomEvent) {
     if let CustomEvent::Cursor(cursor) = &event {
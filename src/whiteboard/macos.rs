@@ -1,10 +1,14 @@
-use super::{server::EVENT_PROXY, Cursor, CustomEvent, Ripple};
-use core_graphics::context::CGContextRef;
+use super::{board::Board, server::EVENT_PROXY, Cursor, CustomEvent, Ripple, Shape, ShapeKind};
+use core_graphics::{
+    base::kCGImageAlphaPremultipliedLast,
+    color_space::CGColorSpace,
+    context::{CGContext, CGContextRef},
+};
 use foreign_types::ForeignTypeRef;
-use hbb_common::{bail, log, ResultType};
+use hbb_common::{anyhow::anyhow, bail, log, ResultType};
 use objc::{class, msg_send, runtime::Object, sel, sel_impl};
 use piet::{
-    kurbo::{BezPath, Point},
+    kurbo::{BezPath, Line, Point, Rect},
     FontFamily, RenderContext, Text, TextLayout, TextLayoutBuilder,
 };
 use piet_coregraphics::{CoreGraphicsContext, CoreGraphicsTextLayout};
@@ -21,6 +25,11 @@ use tao::{
 const MAXIMUM_WINDOW_LEVEL: i64 = 2147483647;
 const CURSOR_TEXT_FONT_SIZE: f64 = 14.0;
 const CURSOR_TEXT_OFFSET: f64 = 20.0;
+const SHAPE_TEXT_FONT_SIZE: f64 = 18.0;
+const ARROW_HEAD_LENGTH: f64 = 12.0;
+// The shapes kept per viewer, the oldest are dropped.
+const MAX_SHAPES: usize = 256;
+const LASER_WIDTH: f64 = 6.0;
 
 struct WindowState {
     window: Arc<Window>,
@@ -36,6 +45,17 @@ struct CursorInfo {
     cursor: Cursor,
 }
 
+struct ShapeInfo {
+    window_id: WindowId,
+    shape: Shape,
+}
+
+struct LaserTrail {
+    window_id: WindowId,
+    argb: u32,
+    points: Vec<Ripple>,
+}
+
 fn set_window_properties(window: &Arc<Window>) -> ResultType<()> {
     let handle = window.window_handle()?;
     if let RawWindowHandle::AppKit(appkit_handle) = handle.as_raw() {
@@ -109,11 +129,144 @@ fn create_windows(event_loop: &EventLoop<(String, CustomEvent)>) -> ResultType<V
     Ok(windows)
 }
 
+fn window_at(windows: &Vec<WindowState>, (x, y): (f32, f32)) -> Option<&WindowState> {
+    let (x, y) = (x as f64, y as f64);
+    windows.iter().find(|window| {
+        let (l, t) = window.display_origin;
+        x >= l
+            && x <= l + window.logical_size.width
+            && y >= t
+            && y <= t + window.logical_size.height
+    })
+}
+
+fn draw_shape(context: &mut CoreGraphicsContext, shape: &Shape) {
+    let rgba = super::argb_to_rgba(shape.argb);
+    let color = piet::Color::rgba8(rgba.0, rgba.1, rgba.2, rgba.3);
+    let width = shape.width as f64;
+    let point = |p: &(f32, f32)| Point::new(p.0 as f64, p.1 as f64);
+    match &shape.kind {
+        ShapeKind::Pen(points) => {
+            let mut pb = BezPath::new();
+            for (i, p) in points.iter().enumerate() {
+                if i == 0 {
+                    pb.move_to(point(p));
+                } else {
+                    pb.line_to(point(p));
+                }
+            }
+            context.stroke(pb, &color, width);
+        }
+        ShapeKind::Rect(a, b) => {
+            context.stroke(Rect::from_points(point(a), point(b)), &color, width);
+        }
+        ShapeKind::Arrow(a, b) => {
+            let (from, to) = (point(a), point(b));
+            context.stroke(Line::new(from, to), &color, width);
+            let angle = (to - from).atan2();
+            let head = ARROW_HEAD_LENGTH + width * 2.0;
+            for side in [-1.0, 1.0] {
+                let wing = angle + std::f64::consts::PI + side * std::f64::consts::FRAC_PI_6;
+                let end = to + head * piet::kurbo::Vec2::from_angle(wing);
+                context.stroke(Line::new(to, end), &color, width);
+            }
+        }
+        ShapeKind::Text(p, text) => {
+            if let Ok(layout) = context
+                .text()
+                .new_text_layout(text.clone())
+                .font(FontFamily::SYSTEM_UI, SHAPE_TEXT_FONT_SIZE)
+                .text_color(color)
+                .build()
+            {
+                context.draw_text(&layout, point(p));
+            }
+        }
+    }
+}
+
+// Draw the shapes on all the screens, as they are placed, and save them to a PNG.
+fn save_snapshot(
+    windows: &Vec<WindowState>,
+    board: &Board<ShapeInfo>,
+    path: &str,
+) -> ResultType<()> {
+    if windows.is_empty() {
+        bail!("No screen");
+    }
+    let (mut l, mut t, mut r, mut b) = (f64::MAX, f64::MAX, f64::MIN, f64::MIN);
+    for window in windows.iter() {
+        let (x, y) = window.display_origin;
+        l = l.min(x);
+        t = t.min(y);
+        r = r.max(x + window.logical_size.width);
+        b = b.max(y + window.logical_size.height);
+    }
+    let (width, height) = ((r - l).ceil() as usize, (b - t).ceil() as usize);
+    let mut cg_context = CGContext::create_bitmap_context(
+        None,
+        width,
+        height,
+        8,
+        width * 4,
+        &CGColorSpace::create_device_rgb(),
+        kCGImageAlphaPremultipliedLast,
+    );
+    {
+        let mut context = CoreGraphicsContext::new_y_up(&mut cg_context, height as f64, None);
+        context.clear(None, piet::Color::TRANSPARENT);
+        for window in windows.iter() {
+            let window_id = window.window.id();
+            for info in board.iter().filter(|info| info.window_id == window_id) {
+                let mut shape = info.shape.clone();
+                shape.offset(
+                    (window.display_origin.0 - l) as f32,
+                    (window.display_origin.1 - t) as f32,
+                );
+                draw_shape(&mut context, &shape);
+            }
+        }
+        context.finish().map_err(|e| anyhow!("{}", e))?;
+    }
+    let mut rgba = cg_context.data().to_vec();
+    // PNG has no premultiplied alpha.
+    for pixel in rgba.chunks_exact_mut(4) {
+        let a = pixel[3] as u32;
+        if a > 0 && a < 255 {
+            for c in pixel[..3].iter_mut() {
+                *c = ((*c as u32 * 255 + a / 2) / a).min(255) as u8;
+            }
+        }
+    }
+    // Written aside first, the connection reads the file once it exists.
+    let tmp = format!("{}.tmp", path);
+    repng::encode(std::fs::File::create(&tmp)?, width as _, height as _, &rgba)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn draw_laser_trail(context: &mut CoreGraphicsContext, trail: &mut LaserTrail) {
+    Ripple::retain_active(&mut trail.points);
+    let rgba = super::argb_to_rgba(trail.argb);
+    let color = |alpha: f64| piet::Color::rgba8(rgba.0, rgba.1, rgba.2, (alpha * 255.0) as u8);
+    for pair in trail.points.windows(2) {
+        let alpha = pair[1].get_alpha();
+        let line = Line::new((pair[0].x, pair[0].y), (pair[1].x, pair[1].y));
+        context.stroke(line, &color(alpha * 0.8), LASER_WIDTH * alpha.max(0.3));
+    }
+    if let Some(head) = trail.points.last() {
+        let dot = piet::kurbo::Circle::new((head.x, head.y), LASER_WIDTH / 2.0);
+        context.fill(dot, &color(head.get_alpha()));
+    }
+}
+
 fn draw_cursors(
     windows: &Vec<WindowState>,
     window_id: WindowId,
     window_ripples: &mut HashMap<WindowId, Vec<Ripple>>,
     last_cursors: &HashMap<String, CursorInfo>,
+    board: &Board<ShapeInfo>,
+    laser_trails: &mut HashMap<String, LaserTrail>,
     map_cursor_text: &mut HashMap<(String, u32), CoreGraphicsTextLayout>,
 ) {
     for window in windows.iter() {
@@ -140,6 +293,18 @@ fn draw_cursors(
                             );
                             context.clear(None, piet::Color::TRANSPARENT);
 
+                            for info in board.iter() {
+                                if info.window_id == window_id {
+                                    draw_shape(&mut context, &info.shape);
+                                }
+                            }
+
+                            for trail in laser_trails.values_mut() {
+                                if trail.window_id == window_id {
+                                    draw_laser_trail(&mut context, trail);
+                                }
+                            }
+
                             if let Some(ripples) = window_ripples.get_mut(&window_id) {
                                 Ripple::retain_active(ripples);
                                 for ripple in ripples.iter() {
@@ -233,6 +398,8 @@ pub(super) fn create_event_loop() -> ResultType<()> {
 
     let mut window_ripples: HashMap<WindowId, Vec<Ripple>> = HashMap::new();
     let mut last_cursors: HashMap<String, CursorInfo> = HashMap::new();
+    let mut board: Board<ShapeInfo> = Board::new(MAX_SHAPES);
+    let mut laser_trails: HashMap<String, LaserTrail> = HashMap::new();
     let mut map_cursor_text: HashMap<(String, u32), CoreGraphicsTextLayout> = HashMap::new();
 
     event_loop.run(move |event, _, control_flow| {
@@ -258,6 +425,8 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                     window_id,
                     &mut window_ripples,
                     &last_cursors,
+                    &board,
+                    &mut laser_trails,
                     &mut map_cursor_text,
                 );
             }
@@ -312,10 +481,67 @@ pub(super) fn create_event_loop() -> ResultType<()> {
                         break;
                     }
                 }
+                CustomEvent::Shape(mut shape) => {
+                    let Some(window) = shape.origin().and_then(|p| window_at(&windows, p)) else {
+                        return;
+                    };
+                    shape.offset(
+                        -window.display_origin.0 as f32,
+                        -window.display_origin.1 as f32,
+                    );
+                    if shape.argb == 0 {
+                        shape.argb = board.color_of(&k);
+                    }
+                    let window_id = window.window.id();
+                    window.window.request_redraw();
+                    board.put(k, shape.id, ShapeInfo { window_id, shape });
+                }
+                CustomEvent::RemoveShape(id) => {
+                    board.remove(&k, id);
+                }
+                CustomEvent::Undo => {
+                    board.undo(&k);
+                }
+                CustomEvent::Clear => {
+                    last_cursors.remove(&k);
+                    laser_trails.remove(&k);
+                    board.clear(&k);
+                }
+                CustomEvent::ClearAll => {
+                    board.clear_all();
+                }
+                CustomEvent::Laser(x, y) => {
+                    let Some(window) = window_at(&windows, (x, y)) else {
+                        return;
+                    };
+                    let window_id = window.window.id();
+                    let point = Ripple {
+                        x: x as f64 - window.display_origin.0,
+                        y: y as f64 - window.display_origin.1,
+                        start_time: Instant::now(),
+                    };
+                    match laser_trails.get_mut(&k) {
+                        Some(trail) if trail.window_id == window_id => trail.points.push(point),
+                        _ => {
+                            let argb = board.color_of(&k);
+                            laser_trails.insert(
+                                k,
+                                LaserTrail {
+                                    window_id,
+                                    argb,
+                                    points: vec![point],
+                                },
+                            );
+                        }
+                    }
+                }
+                CustomEvent::Snapshot(path) => match save_snapshot(&windows, &board, &path) {
+                    Ok(()) => log::info!("Whiteboard snapshot saved to {}", path),
+                    Err(e) => log::error!("Failed to save the whiteboard snapshot: {}", e),
+                },
                 CustomEvent::Exit => {
                     *control_flow = ControlFlow::Exit;
                 }
-                _ => {}
             },
             _ => (),
         }
@@ -1,21 +1,45 @@
 use serde_derive::{Deserialize, Serialize};
 
+// The window drawing the whiteboard is built with the feature "ui" only.
+#[cfg(feature = "ui")]
+mod board;
 mod client;
-mod server;
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", feature = "ui"))]
 mod macos;
+#[cfg(feature = "ui")]
+mod server;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "macos", feature = "ui"))]
 use macos::create_event_loop;
 
 pub use client::*;
+#[cfg(feature = "ui")]
 pub use server::*;
 
+// The annotations of a viewer are sent as `Misc::PluginRequest` with this id, to the hosts with
+// `ANNOTATION_PLATFORM_ADDITION_KEY` in their platform additions.
+pub const ANNOTATION_ID: &str = "whiteboard-annotation";
+pub const ANNOTATION_PLATFORM_ADDITION_KEY: &str = "support_annotation";
+const MAX_SHAPE_POINTS: usize = 4096;
+const MAX_SHAPE_TEXT: usize = 256;
+const MAX_SHAPE_WIDTH: f32 = 32.0;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(tag = "t", content = "c")]
 pub enum CustomEvent {
     Cursor(Cursor),
+    // Add a shape, or replace the one with the same id, e.g. a stroke being drawn.
+    Shape(Shape),
+    RemoveShape(u32),
+    // Remove the last shape of the viewer.
+    Undo,
     Clear,
+    // Remove the shapes of all the viewers.
+    ClearAll,
+    // A point of the laser pointer of the viewer, its trail fades like the ripples.
+    Laser(f32, f32),
+    // Save the shapes of all the viewers to a PNG at the path, in the logical size of the screens.
+    Snapshot(String),
     Exit,
 }
 
@@ -28,3 +52,136 @@ pub struct Cursor {
     pub btns: i32,
     pub text: String,
 }
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "t", content = "c")]
+pub enum ShapeKind {
+    Pen(Vec<(f32, f32)>),
+    Rect((f32, f32), (f32, f32)),
+    Arrow((f32, f32), (f32, f32)),
+    Text((f32, f32), String),
+}
+
+/// A shape drawn by a viewer, in the logical coordinates of the screen, like `Cursor`. An `argb`
+/// of 0 draws it in the color the host gives to the viewer.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Shape {
+    pub id: u32,
+    pub argb: u32,
+    pub width: f32,
+    pub kind: ShapeKind,
+}
+
+impl Shape {
+    /// The point which decides the display of the shape.
+    pub fn origin(&self) -> Option<(f32, f32)> {
+        match &self.kind {
+            ShapeKind::Pen(points) => points.first().copied(),
+            ShapeKind::Rect(p, _) | ShapeKind::Arrow(p, _) | ShapeKind::Text(p, _) => Some(*p),
+        }
+    }
+
+    pub fn offset(&mut self, dx: f32, dy: f32) {
+        let mut move_point = |p: &mut (f32, f32)| {
+            p.0 += dx;
+            p.1 += dy;
+        };
+        match &mut self.kind {
+            ShapeKind::Pen(points) => points.iter_mut().for_each(move_point),
+            ShapeKind::Rect(a, b) | ShapeKind::Arrow(a, b) => {
+                move_point(a);
+                move_point(b);
+            }
+            ShapeKind::Text(p, _) => move_point(p),
+        }
+    }
+
+    fn is_valid(&self) -> bool {
+        let size_ok = match &self.kind {
+            ShapeKind::Pen(points) => !points.is_empty() && points.len() <= MAX_SHAPE_POINTS,
+            ShapeKind::Text(_, text) => !text.is_empty() && text.chars().count() <= MAX_SHAPE_TEXT,
+            _ => true,
+        };
+        size_ok && self.width > 0.0 && self.width <= MAX_SHAPE_WIDTH
+    }
+}
+
+/// What a viewer sends to annotate the screen of the host.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(tag = "t", content = "c")]
+pub enum Annotation {
+    Shape(Shape),
+    Remove(u32),
+    Undo,
+    Clear,
+    ClearAll,
+    // Save the shapes on the host, and send the PNG back if the session id is not empty, as the
+    // `ScreenshotResponse` of the session.
+    Snapshot(String),
+    // Draw a trail following the pointer of the viewer, or stop.
+    Laser(bool),
+}
+
+impl Annotation {
+    pub fn to_message(&self) -> hbb_common::message_proto::Message {
+        crate::plugin_request::make(ANNOTATION_ID, self)
+    }
+
+    /// The event of the annotation, none if it is not valid.
+    pub fn parse(content: &[u8]) -> Option<CustomEvent> {
+        match serde_json::from_slice(content).ok()? {
+            Annotation::Shape(shape) if shape.is_valid() => Some(CustomEvent::Shape(shape)),
+            Annotation::Shape(_) => None,
+            Annotation::Remove(id) => Some(CustomEvent::RemoveShape(id)),
+            Annotation::Undo => Some(CustomEvent::Undo),
+            Annotation::Clear => Some(CustomEvent::Clear),
+            Annotation::ClearAll => Some(CustomEvent::ClearAll),
+            // The connection handles them, it picks the file and follows the pointer.
+            Annotation::Snapshot(_) | Annotation::Laser(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn whiteboard_annotation() {
+        let mut shape = Shape {
+            id: 1,
+            argb: 0xffff0000,
+            width: 3.0,
+            kind: ShapeKind::Arrow((10.0, 20.0), (30.0, 40.0)),
+        };
+        let content = serde_json::to_vec(&Annotation::Shape(shape.clone())).unwrap();
+        assert!(matches!(Annotation::parse(&content), Some(CustomEvent::Shape(s)) if s == shape));
+        shape.offset(-10.0, -20.0);
+        assert_eq!(shape.kind, ShapeKind::Arrow((0.0, 0.0), (20.0, 20.0)));
+        assert_eq!(shape.origin(), Some((0.0, 0.0)));
+
+        let parse = |a: Annotation| Annotation::parse(&serde_json::to_vec(&a).unwrap());
+        let empty_pen = Shape {
+            kind: ShapeKind::Pen(vec![]),
+            ..shape.clone()
+        };
+        assert!(parse(Annotation::Shape(empty_pen)).is_none());
+        let too_wide = Shape {
+            width: 100.0,
+            ..shape.clone()
+        };
+        assert!(parse(Annotation::Shape(too_wide)).is_none());
+        assert!(matches!(
+            parse(Annotation::Remove(1)),
+            Some(CustomEvent::RemoveShape(1))
+        ));
+        assert!(matches!(parse(Annotation::Undo), Some(CustomEvent::Undo)));
+        assert!(matches!(
+            parse(Annotation::ClearAll),
+            Some(CustomEvent::ClearAll)
+        ));
+        assert!(parse(Annotation::Snapshot("".to_owned())).is_none());
+        assert!(parse(Annotation::Laser(true)).is_none());
+        assert!(Annotation::parse(br#"{"t":"Exit"}"#).is_none());
+    }
+}
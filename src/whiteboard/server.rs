@@ -121,11 +121,20 @@ impl Ripple {
         ripples.retain(|r| r.start_time.elapsed() < RIPPLE_DURATION);
     }
 
-    pub fn get_radius_alpha(&self) -> (RippleFloat, RippleFloat) {
+    fn progress(&self) -> RippleFloat {
         let elapsed = self.start_time.elapsed();
-        let progress = (elapsed.as_secs_f64() / RIPPLE_DURATION.as_secs_f64()).min(1.0);
+        (elapsed.as_secs_f64() / RIPPLE_DURATION.as_secs_f64()).min(1.0)
+    }
+
+    pub fn get_radius_alpha(&self) -> (RippleFloat, RippleFloat) {
+        let progress = self.progress();
         let radius = 25.0 * progress;
         let alpha = 1.0 - progress;
         (radius, alpha)
     }
+
+    /// The alpha of a point of a laser trail, which fades like a ripple.
+    pub fn get_alpha(&self) -> RippleFloat {
+        1.0 - self.progress()
+    }
 }
@@ -0,0 +1,85 @@
+//! Whether the window of a session is seen, so the host does not stream to a hidden one.
+//!
+//! The client sends a `Visibility` when its window is minimized or restored, or the app goes to the
+//! background and back, to a host with "support_window_visibility" in its platform additions. The
+//! video of the host is shared by its viewers, so a hidden connection no longer counts for the
+//! frame rate and the quality, which drop to `video_qos::MIN_FPS` once all the connections are
+//! hidden. The `Throttle` of the connection also skips the frames sent to it while it is hidden,
+//! whatever the rate of the others, and a key frame is requested when it is seen again.
+
+use crate::plugin_request;
+use hbb_common::message_proto::Message;
+use serde_derive::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+pub const ID: &str = "window-visibility";
+pub const PLATFORM_ADDITION_KEY: &str = "support_window_visibility";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Visibility {
+    pub visible: bool,
+}
+
+impl Visibility {
+    pub fn parse(content: &[u8]) -> Option<Self> {
+        serde_json::from_slice(content).ok()
+    }
+
+    pub fn to_message(&self) -> Message {
+        plugin_request::make(ID, self)
+    }
+}
+
+/// The video frames of a connection, skipped while its window is hidden.
+#[derive(Debug, Default)]
+pub struct Throttle {
+    hidden: bool,
+    // The displays of the frames skipped, a key frame is needed to decode them again.
+    skipped: HashSet<i32>,
+}
+
+impl Throttle {
+    /// The displays to request a key frame of.
+    pub fn set_visible(&mut self, visible: bool) -> Vec<i32> {
+        self.hidden = !visible;
+        if visible {
+            self.skipped.drain().collect()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Whether to skip a frame of the display.
+    pub fn skip(&mut self, display: i32) -> bool {
+        if self.hidden {
+            self.skipped.insert(display);
+        }
+        self.hidden
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_visibility() {
+        let msg = Visibility { visible: false }.to_message();
+        let content = &msg.misc().plugin_request().content;
+        assert_eq!(
+            Visibility::parse(content),
+            Some(Visibility { visible: false })
+        );
+        assert_eq!(Visibility::parse(b"{}"), None);
+
+        let mut throttle = Throttle::default();
+        assert!(!throttle.skip(0));
+        assert!(throttle.set_visible(false).is_empty());
+        assert!(throttle.skip(0) && throttle.skip(1) && throttle.skip(1));
+        let mut displays = throttle.set_visible(true);
+        displays.sort();
+        assert_eq!(displays, vec![0, 1]);
+        assert!(!throttle.skip(0));
+        assert!(throttle.set_visible(true).is_empty());
+    }
+}
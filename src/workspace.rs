@@ -0,0 +1,128 @@
+//! A workspace: the desktop, the file transfer and the terminal sessions of a peer, opened and
+//! closed together.
+//!
+//! A workspace is created from an authenticated desktop session. The other sessions connect
+//! with the connection token of that session, so the password and the 2FA code are asked once,
+//! and join the workspace on `session_add`. Closing the workspace asks each ui session to close
+//! with the "close_workspace" event.
+//!
+//! It shares the login only, each session still has its own connection to the peer: the host
+//! serves the file transfer and the terminal on connections of their type, with their own
+//! permissions and audit. The channels of `mux` carry the forwarded ports over the connection of
+//! the desktop session, not the other sessions of the workspace.
+
+use crate::{flutter::sessions, flutter_ffi::SessionID};
+use hbb_common::{bail, message_proto::ConnType, ResultType};
+use serde_json::json;
+use std::sync::Mutex;
+
+struct Workspace {
+    id: String,
+    peer_id: String,
+    conn_token: String,
+    sessions: Vec<SessionID>,
+}
+
+lazy_static::lazy_static! {
+    static ref WORKSPACES: Mutex<Vec<Workspace>> = Default::default();
+}
+
+/// Create a workspace with the desktop session, or get the one it is in.
+pub fn create(session_id: &SessionID) -> ResultType<String> {
+    let Some(session) = sessions::get_session_by_session_id(session_id) else {
+        bail!("session not found");
+    };
+    let Some(conn_token) = session.get_conn_token() else {
+        bail!("not logged in yet");
+    };
+    let mut workspaces = WORKSPACES.lock().unwrap();
+    if let Some(w) = workspaces.iter().find(|w| w.sessions.contains(session_id)) {
+        return Ok(w.id.clone());
+    }
+    let id = uuid::Uuid::new_v4().to_string();
+    workspaces.push(Workspace {
+        id: id.clone(),
+        peer_id: session.get_id(),
+        conn_token,
+        sessions: vec![*session_id],
+    });
+    Ok(id)
+}
+
+/// Join the workspace that the connection token comes from, if any.
+pub fn join(session_id: &SessionID, peer_id: &str, conn_token: &str) {
+    if let Some(w) = WORKSPACES
+        .lock()
+        .unwrap()
+        .iter_mut()
+        .find(|w| w.peer_id == peer_id && w.conn_token == conn_token)
+    {
+        if !w.sessions.contains(session_id) {
+            w.sessions.push(*session_id);
+        }
+    }
+}
+
+pub fn leave(session_id: &SessionID) {
+    let mut workspaces = WORKSPACES.lock().unwrap();
+    for w in workspaces.iter_mut() {
+        w.sessions.retain(|s| s != session_id);
+    }
+    workspaces.retain(|w| !w.sessions.is_empty());
+}
+
+fn conn_type_name(conn_type: ConnType) -> &'static str {
+    match conn_type {
+        ConnType::DEFAULT_CONN => "desktop",
+        ConnType::FILE_TRANSFER => "file_transfer",
+        ConnType::TERMINAL => "terminal",
+        ConnType::VIEW_CAMERA => "view_camera",
+        _ => "other",
+    }
+}
+
+/// Json list of the workspaces with their sessions.
+pub fn list() -> String {
+    let workspaces: Vec<serde_json::Value> = WORKSPACES
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|w| {
+            let sessions: Vec<serde_json::Value> = w
+                .sessions
+                .iter()
+                .filter_map(|id| {
+                    let s = sessions::get_session_by_session_id(id)?;
+                    let conn_type = s.lc.read().unwrap().conn_type;
+                    Some(json!({
+                        "session_id": id.to_string(),
+                        "type": conn_type_name(conn_type),
+                    }))
+                })
+                .collect();
+            json!({
+                "id": w.id,
+                "peer_id": w.peer_id,
+                "sessions": sessions,
+            })
+        })
+        .collect();
+    serde_json::to_string(&workspaces).unwrap_or_default()
+}
+
+/// Ask every session of the workspace to close, they leave it on `session_close`.
+pub fn close(id: &str) {
+    let members = WORKSPACES
+        .lock()
+        .unwrap()
+        .iter()
+        .find(|w| w.id == id)
+        .map(|w| w.sessions.clone())
+        .unwrap_or_default();
+    for session_id in members {
+        if let Some(s) = sessions::get_session_by_session_id(&session_id) {
+            s.ui_handler
+                .push_event_to("close_workspace", &[("id", id)], &[&session_id]);
+        }
+    }
+}